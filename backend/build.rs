@@ -12,6 +12,10 @@ fn compile_proto(
         .compile_well_known_types(true)
         .build_server(true)
         .build_client(true)
+        // Messages are also JSON for the REST gateway, with proto field names; fields a request omits
+        // take their proto defaults
+        .message_attribute(".", "#[derive(::serde::Serialize, ::serde::Deserialize)] #[serde(default)]")
+        .enum_attribute(".", "#[derive(::serde::Serialize, ::serde::Deserialize)]")
         .out_dir("../proto/rust/gen")
         .compile(
             &proto.iter().map(|p| p.as_path()).collect::<Vec<_>>(), 
@@ -88,6 +92,15 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // More specific rerun conditions to avoid unnecessary rebuilds
     println!("cargo:rerun-if-changed=../proto/greeter.proto");
     println!("cargo:rerun-if-changed=../proto/auth.proto");
+    println!("cargo:rerun-if-changed=../proto/accounts.proto");
+    println!("cargo:rerun-if-changed=../proto/sync.proto");
+    println!("cargo:rerun-if-changed=../proto/alerts.proto");
+    println!("cargo:rerun-if-changed=../proto/sharing.proto");
+    println!("cargo:rerun-if-changed=../proto/transfers.proto");
+    println!("cargo:rerun-if-changed=../proto/assistant.proto");
+    println!("cargo:rerun-if-changed=../proto/chat.proto");
+    println!("cargo:rerun-if-changed=../proto/admin.proto");
+    println!("cargo:rerun-if-changed=../proto/webhooks.proto");
     println!("cargo:rerun-if-changed=build.rs");
     
     let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR")?);
@@ -97,6 +110,15 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let proto_definitions = vec![
         vec![proto_dir.join("greeter.proto")],
         vec![proto_dir.join("auth.proto")],
+        vec![proto_dir.join("accounts.proto")],
+        vec![proto_dir.join("sync.proto")],
+        vec![proto_dir.join("alerts.proto")],
+        vec![proto_dir.join("sharing.proto")],
+        vec![proto_dir.join("transfers.proto")],
+        vec![proto_dir.join("assistant.proto")],
+        vec![proto_dir.join("chat.proto")],
+        vec![proto_dir.join("admin.proto")],
+        vec![proto_dir.join("webhooks.proto")],
     ];
 
     let mut all_proto_definitions = Vec::new();
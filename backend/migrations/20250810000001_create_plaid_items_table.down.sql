@@ -0,0 +1,4 @@
+-- Drop Plaid items table and related objects
+DROP INDEX IF EXISTS idx_plaid_items_status;
+DROP INDEX IF EXISTS idx_plaid_items_user_id;
+DROP TABLE IF EXISTS plaid_items;
@@ -0,0 +1,21 @@
+-- Plaid items (linked institutions) with envelope-encrypted access tokens
+CREATE TABLE plaid_items (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    item_id VARCHAR(255) NOT NULL UNIQUE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    access_token_ciphertext BYTEA NOT NULL,
+    access_token_nonce BYTEA NOT NULL,
+    encrypted_data_key BYTEA NOT NULL,
+    encryption_key_id VARCHAR(255) NOT NULL,
+    institution_id VARCHAR(255),
+    institution_name VARCHAR(255),
+    status VARCHAR(50) NOT NULL DEFAULT 'active',
+    error_code VARCHAR(255),
+    sync_cursor TEXT,
+    last_synced_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_plaid_items_user_id ON plaid_items(user_id);
+CREATE INDEX idx_plaid_items_status ON plaid_items(status);
@@ -0,0 +1,4 @@
+-- Drop bank accounts table and related objects
+DROP INDEX IF EXISTS idx_bank_accounts_item_id;
+DROP INDEX IF EXISTS idx_bank_accounts_user_id;
+DROP TABLE IF EXISTS bank_accounts;
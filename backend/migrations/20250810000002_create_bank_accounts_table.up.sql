@@ -0,0 +1,25 @@
+-- Bank accounts returned by Plaid, upserted on every item sync
+CREATE TABLE bank_accounts (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    account_id VARCHAR(255) NOT NULL UNIQUE,
+    item_id VARCHAR(255) NOT NULL REFERENCES plaid_items(item_id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    official_name VARCHAR(255),
+    mask VARCHAR(16),
+    account_type VARCHAR(50) NOT NULL,
+    account_subtype VARCHAR(50),
+    available_balance DOUBLE PRECISION,
+    current_balance DOUBLE PRECISION,
+    credit_limit DOUBLE PRECISION,
+    iso_currency_code VARCHAR(3),
+    unofficial_currency_code VARCHAR(16),
+    institution_id VARCHAR(255),
+    institution_name VARCHAR(255),
+    balances_updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_bank_accounts_user_id ON bank_accounts(user_id);
+CREATE INDEX idx_bank_accounts_item_id ON bank_accounts(item_id);
@@ -0,0 +1,6 @@
+-- Drop transactions table and related objects
+DROP INDEX IF EXISTS idx_transactions_updated_at;
+DROP INDEX IF EXISTS idx_transactions_item_id;
+DROP INDEX IF EXISTS idx_transactions_account_id;
+DROP INDEX IF EXISTS idx_transactions_user_date;
+DROP TABLE IF EXISTS transactions;
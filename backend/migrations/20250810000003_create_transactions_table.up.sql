@@ -0,0 +1,36 @@
+-- Transactions synced from Plaid /transactions/sync; removed transactions are soft-deleted
+CREATE TABLE transactions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    transaction_id VARCHAR(255) NOT NULL UNIQUE,
+    account_id VARCHAR(255) NOT NULL,
+    item_id VARCHAR(255) NOT NULL REFERENCES plaid_items(item_id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    amount DOUBLE PRECISION NOT NULL,
+    iso_currency_code VARCHAR(3),
+    unofficial_currency_code VARCHAR(16),
+    date DATE NOT NULL,
+    datetime TIMESTAMP WITH TIME ZONE,
+    authorized_date DATE,
+    authorized_datetime TIMESTAMP WITH TIME ZONE,
+    name TEXT NOT NULL,
+    merchant_name VARCHAR(255),
+    original_description TEXT,
+    category TEXT[] NOT NULL DEFAULT '{}',
+    category_id VARCHAR(50),
+    check_number VARCHAR(50),
+    location JSONB,
+    payment_meta JSONB,
+    pending BOOLEAN NOT NULL DEFAULT FALSE,
+    pending_transaction_id VARCHAR(255),
+    account_owner VARCHAR(255),
+    transaction_type VARCHAR(50) NOT NULL,
+    transaction_code VARCHAR(50),
+    removed_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_transactions_user_date ON transactions(user_id, date DESC) WHERE removed_at IS NULL;
+CREATE INDEX idx_transactions_account_id ON transactions(account_id);
+CREATE INDEX idx_transactions_item_id ON transactions(item_id);
+CREATE INDEX idx_transactions_updated_at ON transactions(user_id, updated_at);
@@ -0,0 +1,7 @@
+-- Remove per-item sync status columns
+ALTER TABLE plaid_items
+    DROP COLUMN IF EXISTS consecutive_sync_failures,
+    DROP COLUMN IF EXISTS last_sync_duration_ms,
+    DROP COLUMN IF EXISTS last_sync_error,
+    DROP COLUMN IF EXISTS last_sync_status,
+    DROP COLUMN IF EXISTS last_sync_attempt_at;
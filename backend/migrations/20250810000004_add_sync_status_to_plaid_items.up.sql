@@ -0,0 +1,7 @@
+-- Per-item outcome of the most recent transaction sync attempt
+ALTER TABLE plaid_items
+    ADD COLUMN last_sync_attempt_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN last_sync_status VARCHAR(20),
+    ADD COLUMN last_sync_error TEXT,
+    ADD COLUMN last_sync_duration_ms INTEGER,
+    ADD COLUMN consecutive_sync_failures INTEGER NOT NULL DEFAULT 0;
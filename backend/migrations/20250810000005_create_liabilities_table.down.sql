@@ -0,0 +1,5 @@
+-- Drop liabilities table and related objects
+DROP INDEX IF EXISTS idx_liabilities_user_due_date;
+DROP INDEX IF EXISTS idx_liabilities_item_id;
+DROP INDEX IF EXISTS idx_liabilities_user_id;
+DROP TABLE IF EXISTS liabilities;
@@ -0,0 +1,27 @@
+-- Debt details from Plaid Liabilities, one row per credit card, mortgage or student loan account
+CREATE TABLE liabilities (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    account_id VARCHAR(255) NOT NULL UNIQUE REFERENCES bank_accounts(account_id) ON DELETE CASCADE,
+    item_id VARCHAR(255) NOT NULL REFERENCES plaid_items(item_id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    liability_type VARCHAR(16) NOT NULL,
+    aprs JSONB NOT NULL DEFAULT '[]',
+    interest_rate_percentage DOUBLE PRECISION,
+    is_overdue BOOLEAN,
+    last_payment_amount DOUBLE PRECISION,
+    last_payment_date DATE,
+    next_payment_due_date DATE,
+    minimum_payment_amount DOUBLE PRECISION,
+    last_statement_balance DOUBLE PRECISION,
+    last_statement_issue_date DATE,
+    origination_date DATE,
+    origination_principal_amount DOUBLE PRECISION,
+    maturity_date DATE,
+    details JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_liabilities_user_id ON liabilities(user_id);
+CREATE INDEX idx_liabilities_item_id ON liabilities(item_id);
+CREATE INDEX idx_liabilities_user_due_date ON liabilities(user_id, next_payment_due_date);
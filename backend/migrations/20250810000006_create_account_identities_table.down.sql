@@ -0,0 +1,4 @@
+-- Drop account identities table and related objects
+DROP INDEX IF EXISTS idx_account_identities_item_id;
+DROP INDEX IF EXISTS idx_account_identities_user_id;
+DROP TABLE IF EXISTS account_identities;
@@ -0,0 +1,17 @@
+-- Account-owner identity from Plaid Identity; owners are envelope-encrypted JSON because they hold PII
+CREATE TABLE account_identities (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    account_id VARCHAR(255) NOT NULL UNIQUE REFERENCES bank_accounts(account_id) ON DELETE CASCADE,
+    item_id VARCHAR(255) NOT NULL REFERENCES plaid_items(item_id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    owners_ciphertext BYTEA NOT NULL,
+    owners_nonce BYTEA NOT NULL,
+    encrypted_data_key BYTEA NOT NULL,
+    encryption_key_id VARCHAR(255) NOT NULL,
+    fetched_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_account_identities_user_id ON account_identities(user_id);
+CREATE INDEX idx_account_identities_item_id ON account_identities(item_id);
@@ -0,0 +1,3 @@
+-- Drop transaction_categories table and related objects
+DROP INDEX IF EXISTS idx_transaction_categories_user_category;
+DROP TABLE IF EXISTS transaction_categories;
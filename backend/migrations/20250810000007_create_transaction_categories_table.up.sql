@@ -0,0 +1,15 @@
+-- Category assigned to a transaction by the AI categorizer or overridden by the user
+CREATE TABLE transaction_categories (
+    transaction_id VARCHAR(255) PRIMARY KEY REFERENCES transactions(transaction_id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    category VARCHAR(64) NOT NULL,
+    confidence DOUBLE PRECISION NOT NULL,
+    source VARCHAR(16) NOT NULL,
+    model VARCHAR(100),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    CONSTRAINT transaction_categories_source_check CHECK (source IN ('ai', 'manual')),
+    CONSTRAINT transaction_categories_confidence_check CHECK (confidence >= 0 AND confidence <= 1)
+);
+
+CREATE INDEX idx_transaction_categories_user_category ON transaction_categories(user_id, category);
@@ -0,0 +1,3 @@
+-- Drop net_worth_snapshots table and related objects
+DROP INDEX IF EXISTS idx_net_worth_snapshots_user_date;
+DROP TABLE IF EXISTS net_worth_snapshots;
@@ -0,0 +1,16 @@
+-- Daily net worth per user and currency: asset balances minus credit and loan balances
+CREATE TABLE net_worth_snapshots (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    snapshot_date DATE NOT NULL,
+    currency_code VARCHAR(16) NOT NULL,
+    assets DOUBLE PRECISION NOT NULL,
+    liabilities DOUBLE PRECISION NOT NULL,
+    net_worth DOUBLE PRECISION NOT NULL,
+    account_count INTEGER NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    CONSTRAINT net_worth_snapshots_user_date_currency_key UNIQUE (user_id, snapshot_date, currency_code)
+);
+
+CREATE INDEX idx_net_worth_snapshots_user_date ON net_worth_snapshots(user_id, snapshot_date DESC);
@@ -0,0 +1,2 @@
+-- Remove display currency preference from users
+ALTER TABLE users DROP COLUMN IF EXISTS display_currency;
@@ -0,0 +1,2 @@
+-- Currency the user wants aggregates converted to
+ALTER TABLE users ADD COLUMN display_currency VARCHAR(3) NOT NULL DEFAULT 'USD';
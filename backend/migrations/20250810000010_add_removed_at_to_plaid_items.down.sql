@@ -0,0 +1,3 @@
+-- Remove item removal timestamp
+DROP INDEX IF EXISTS idx_plaid_items_removed_at;
+ALTER TABLE plaid_items DROP COLUMN IF EXISTS removed_at;
@@ -0,0 +1,4 @@
+-- When a user unlinked an item; its data is purged once the retention period has passed
+ALTER TABLE plaid_items ADD COLUMN removed_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX idx_plaid_items_removed_at ON plaid_items(removed_at) WHERE removed_at IS NOT NULL;
@@ -0,0 +1,3 @@
+-- Drop audit_log table and related objects
+DROP INDEX IF EXISTS idx_audit_log_user_created;
+DROP TABLE IF EXISTS audit_log;
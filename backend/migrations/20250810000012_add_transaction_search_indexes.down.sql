@@ -0,0 +1,5 @@
+-- Restore transaction indexes from before search
+CREATE INDEX IF NOT EXISTS idx_transactions_user_date ON transactions(user_id, date DESC) WHERE removed_at IS NULL;
+DROP INDEX IF EXISTS idx_transactions_user_date_id;
+DROP INDEX IF EXISTS idx_transactions_merchant_name_trgm;
+DROP INDEX IF EXISTS idx_transactions_name_trgm;
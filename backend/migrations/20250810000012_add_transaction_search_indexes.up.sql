@@ -0,0 +1,9 @@
+-- Indexes for SearchTransactions: trigram text search and newest-first keyset pagination
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX idx_transactions_name_trgm ON transactions USING GIN (name gin_trgm_ops) WHERE removed_at IS NULL;
+CREATE INDEX idx_transactions_merchant_name_trgm ON transactions USING GIN (merchant_name gin_trgm_ops) WHERE removed_at IS NULL;
+
+-- Extends idx_transactions_user_date with the keyset tie-breaker
+CREATE INDEX idx_transactions_user_date_id ON transactions(user_id, date DESC, transaction_id DESC) WHERE removed_at IS NULL;
+DROP INDEX IF EXISTS idx_transactions_user_date;
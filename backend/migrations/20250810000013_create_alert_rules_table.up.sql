@@ -0,0 +1,14 @@
+-- Per-user alert thresholds evaluated after each transaction sync
+CREATE TABLE alert_rules (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    kind VARCHAR(32) NOT NULL CHECK (kind IN ('large_transaction', 'low_balance')),
+    account_id VARCHAR(255) REFERENCES bank_accounts(account_id) ON DELETE CASCADE,
+    category VARCHAR(64),
+    threshold DOUBLE PRECISION NOT NULL CHECK (threshold > 0),
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_alert_rules_user_id ON alert_rules(user_id) WHERE enabled;
@@ -0,0 +1,3 @@
+-- Drop notifications table and related objects
+DROP INDEX IF EXISTS idx_notifications_user_created;
+DROP TABLE IF EXISTS notifications;
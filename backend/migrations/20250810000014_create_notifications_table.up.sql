@@ -0,0 +1,18 @@
+-- Notifications sent to users; dedup_key makes each alert fire once
+CREATE TABLE notifications (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    rule_id UUID REFERENCES alert_rules(id) ON DELETE SET NULL,
+    kind VARCHAR(32) NOT NULL,
+    dedup_key VARCHAR(512) NOT NULL,
+    channel VARCHAR(16) NOT NULL DEFAULT 'email',
+    subject TEXT NOT NULL,
+    body TEXT NOT NULL,
+    status VARCHAR(16) NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'sent', 'failed')),
+    error TEXT,
+    sent_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    CONSTRAINT notifications_user_dedup_key UNIQUE (user_id, dedup_key)
+);
+
+CREATE INDEX idx_notifications_user_created ON notifications(user_id, created_at DESC);
@@ -0,0 +1,3 @@
+-- Drop bills table and related objects
+DROP INDEX IF EXISTS idx_bills_next_due_date;
+DROP TABLE IF EXISTS bills;
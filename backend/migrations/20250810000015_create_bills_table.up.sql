@@ -0,0 +1,19 @@
+-- Recurring bills detected from transaction history, with their next expected due date
+CREATE TABLE bills (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    account_id VARCHAR(255) NOT NULL REFERENCES bank_accounts(account_id) ON DELETE CASCADE,
+    merchant_key VARCHAR(255) NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    cadence VARCHAR(16) NOT NULL CHECK (cadence IN ('weekly', 'biweekly', 'monthly', 'quarterly', 'annual')),
+    typical_amount DOUBLE PRECISION NOT NULL,
+    iso_currency_code VARCHAR(3),
+    occurrences INTEGER NOT NULL,
+    last_paid_date DATE NOT NULL,
+    next_due_date DATE NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    CONSTRAINT bills_user_account_merchant UNIQUE (user_id, account_id, merchant_key)
+);
+
+CREATE INDEX idx_bills_next_due_date ON bills(next_due_date);
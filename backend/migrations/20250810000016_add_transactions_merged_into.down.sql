@@ -0,0 +1,2 @@
+-- Remove duplicate merge target
+ALTER TABLE transactions DROP COLUMN IF EXISTS merged_into;
@@ -0,0 +1,2 @@
+-- Transaction a duplicate was merged into; merged rows stay soft-deleted when the source resends them
+ALTER TABLE transactions ADD COLUMN merged_into VARCHAR(255);
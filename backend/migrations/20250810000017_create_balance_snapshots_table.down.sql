@@ -0,0 +1,3 @@
+-- Drop balance_snapshots table and related objects
+DROP INDEX IF EXISTS idx_balance_snapshots_account_recorded;
+DROP TABLE IF EXISTS balance_snapshots;
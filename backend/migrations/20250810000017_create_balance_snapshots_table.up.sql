@@ -0,0 +1,13 @@
+-- Account balances as recorded each time they were fetched from Plaid
+CREATE TABLE balance_snapshots (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    account_id VARCHAR(255) NOT NULL REFERENCES bank_accounts(account_id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    available_balance DOUBLE PRECISION,
+    current_balance DOUBLE PRECISION,
+    iso_currency_code VARCHAR(3),
+    unofficial_currency_code VARCHAR(16),
+    recorded_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_balance_snapshots_account_recorded ON balance_snapshots(account_id, recorded_at DESC);
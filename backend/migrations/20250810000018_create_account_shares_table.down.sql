@@ -0,0 +1,5 @@
+-- Drop account_shares table and related objects
+DROP INDEX IF EXISTS idx_account_shares_owner;
+DROP INDEX IF EXISTS idx_account_shares_grantee;
+DROP INDEX IF EXISTS idx_account_shares_open;
+DROP TABLE IF EXISTS account_shares;
@@ -0,0 +1,21 @@
+-- Bank accounts shared with another user; invitations are addressed by email and accepted by that user
+CREATE TABLE account_shares (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    account_id VARCHAR(255) NOT NULL REFERENCES bank_accounts(account_id) ON DELETE CASCADE,
+    owner_user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    invitee_email VARCHAR(255) NOT NULL,
+    grantee_user_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    access VARCHAR(16) NOT NULL CHECK (access IN ('read', 'full')),
+    status VARCHAR(16) NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'accepted', 'declined', 'revoked')),
+    responded_at TIMESTAMP WITH TIME ZONE,
+    revoked_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- At most one open invitation or share per account and invitee
+CREATE UNIQUE INDEX idx_account_shares_open
+    ON account_shares(account_id, LOWER(invitee_email))
+    WHERE status IN ('pending', 'accepted');
+CREATE INDEX idx_account_shares_grantee ON account_shares(grantee_user_id) WHERE status = 'accepted';
+CREATE INDEX idx_account_shares_owner ON account_shares(owner_user_id);
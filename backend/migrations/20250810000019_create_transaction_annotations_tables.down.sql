@@ -0,0 +1,4 @@
+-- Drop transaction annotation and split tables and related objects
+DROP TABLE IF EXISTS transaction_splits;
+DROP INDEX IF EXISTS idx_transaction_annotations_tags;
+DROP TABLE IF EXISTS transaction_annotations;
@@ -0,0 +1,24 @@
+-- User-entered notes and tags on a transaction
+CREATE TABLE transaction_annotations (
+    transaction_id VARCHAR(255) PRIMARY KEY REFERENCES transactions(transaction_id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    notes TEXT,
+    tags TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_transaction_annotations_tags ON transaction_annotations USING GIN (tags);
+
+-- Parts a transaction is split into, each with its own category; the parts add up to the transaction amount
+CREATE TABLE transaction_splits (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    transaction_id VARCHAR(255) NOT NULL REFERENCES transactions(transaction_id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    amount DOUBLE PRECISION NOT NULL,
+    category VARCHAR(64) NOT NULL,
+    description TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    CONSTRAINT transaction_splits_position_unique UNIQUE (transaction_id, position)
+);
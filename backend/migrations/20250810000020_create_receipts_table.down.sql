@@ -0,0 +1,3 @@
+-- Drop receipts table and related objects
+DROP INDEX IF EXISTS idx_receipts_transaction;
+DROP TABLE IF EXISTS receipts;
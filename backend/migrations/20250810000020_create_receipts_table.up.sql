@@ -0,0 +1,18 @@
+-- Receipt files stored in S3 and attached to transactions
+CREATE TABLE receipts (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    transaction_id VARCHAR(255) NOT NULL REFERENCES transactions(transaction_id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    uploaded_by UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    object_key VARCHAR(1024) NOT NULL UNIQUE,
+    content_type VARCHAR(100) NOT NULL,
+    size_bytes BIGINT NOT NULL,
+    file_name VARCHAR(255),
+    status VARCHAR(16) NOT NULL DEFAULT 'pending',
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    attached_at TIMESTAMP WITH TIME ZONE,
+    CONSTRAINT receipts_status_check CHECK (status IN ('pending', 'attached')),
+    CONSTRAINT receipts_size_check CHECK (size_bytes > 0)
+);
+
+CREATE INDEX idx_receipts_transaction ON receipts(transaction_id, created_at);
@@ -0,0 +1,5 @@
+-- Drop receipt extraction columns
+ALTER TABLE receipts
+    DROP COLUMN IF EXISTS scanned_at,
+    DROP COLUMN IF EXISTS scan_model,
+    DROP COLUMN IF EXISTS extraction;
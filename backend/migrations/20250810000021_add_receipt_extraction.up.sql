@@ -0,0 +1,5 @@
+-- Structured data read from a receipt by the vision model
+ALTER TABLE receipts
+    ADD COLUMN extraction JSONB,
+    ADD COLUMN scan_model VARCHAR(100),
+    ADD COLUMN scanned_at TIMESTAMP WITH TIME ZONE;
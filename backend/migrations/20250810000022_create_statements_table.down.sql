@@ -0,0 +1,4 @@
+-- Drop statements table and related objects
+DROP INDEX IF EXISTS idx_statements_item;
+DROP INDEX IF EXISTS idx_statements_account;
+DROP TABLE IF EXISTS statements;
@@ -0,0 +1,17 @@
+-- Monthly bank statement PDFs fetched from Plaid Statements and stored in S3
+CREATE TABLE statements (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    statement_id VARCHAR(255) NOT NULL UNIQUE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    item_id VARCHAR(255) NOT NULL REFERENCES plaid_items(item_id) ON DELETE CASCADE,
+    account_id VARCHAR(255) NOT NULL REFERENCES bank_accounts(account_id) ON DELETE CASCADE,
+    year INTEGER NOT NULL,
+    month INTEGER NOT NULL,
+    object_key VARCHAR(1024) NOT NULL UNIQUE,
+    size_bytes BIGINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    CONSTRAINT statements_month_check CHECK (month BETWEEN 1 AND 12)
+);
+
+CREATE INDEX idx_statements_account ON statements(account_id, year DESC, month DESC);
+CREATE INDEX idx_statements_item ON statements(item_id);
@@ -0,0 +1,3 @@
+-- Drop weekly digest preference
+DROP INDEX IF EXISTS idx_users_weekly_digest;
+ALTER TABLE users DROP COLUMN IF EXISTS weekly_digest_enabled;
@@ -0,0 +1,4 @@
+-- Opt-in weekly summary email
+ALTER TABLE users ADD COLUMN weekly_digest_enabled BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX idx_users_weekly_digest ON users(id) WHERE weekly_digest_enabled;
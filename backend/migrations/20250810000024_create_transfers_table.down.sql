@@ -0,0 +1,4 @@
+-- Drop transfers tables and related objects
+DROP TABLE IF EXISTS transfer_event_cursor;
+DROP INDEX IF EXISTS idx_transfers_user;
+DROP TABLE IF EXISTS transfers;
@@ -0,0 +1,33 @@
+-- Money moved between two of a user's linked accounts with Plaid Transfer: an ACH debit from the
+-- source account, followed by an ACH credit to the destination once the debit settles.
+-- Account IDs are not foreign keys so the history survives unlinking an item.
+CREATE TABLE transfers (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    from_account_id VARCHAR(255) NOT NULL,
+    to_account_id VARCHAR(255) NOT NULL,
+    amount DOUBLE PRECISION NOT NULL CHECK (amount > 0),
+    iso_currency_code VARCHAR(3) NOT NULL,
+    description VARCHAR(15) NOT NULL,
+    status VARCHAR(32) NOT NULL CHECK (status IN (
+        'authorized', 'declined', 'debit_pending', 'credit_pending', 'completed', 'failed', 'cancelled'
+    )),
+    debit_authorization_id VARCHAR(255),
+    debit_transfer_id VARCHAR(255) UNIQUE,
+    credit_transfer_id VARCHAR(255) UNIQUE,
+    failure_reason TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    CONSTRAINT transfers_distinct_accounts CHECK (from_account_id <> to_account_id)
+);
+
+CREATE INDEX idx_transfers_user ON transfers(user_id, created_at DESC);
+
+-- Last Plaid transfer event applied; /transfer/event/sync resumes after it
+CREATE TABLE transfer_event_cursor (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    last_event_id BIGINT NOT NULL DEFAULT 0,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO transfer_event_cursor (id, last_event_id) VALUES (TRUE, 0);
@@ -0,0 +1,2 @@
+-- Drop the item provider column
+ALTER TABLE plaid_items DROP COLUMN IF EXISTS provider;
@@ -0,0 +1,2 @@
+-- Bank data provider each item was linked through; existing items all came from Plaid
+ALTER TABLE plaid_items ADD COLUMN provider VARCHAR(32) NOT NULL DEFAULT 'plaid';
@@ -0,0 +1,72 @@
+-- Drop monthly partitioning and restore the single transactions table with its foreign keys
+ALTER TABLE transactions RENAME TO transactions_partitioned;
+
+CREATE TABLE transactions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    transaction_id VARCHAR(255) NOT NULL UNIQUE,
+    account_id VARCHAR(255) NOT NULL,
+    item_id VARCHAR(255) NOT NULL REFERENCES plaid_items(item_id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    amount DOUBLE PRECISION NOT NULL,
+    iso_currency_code VARCHAR(3),
+    unofficial_currency_code VARCHAR(16),
+    date DATE NOT NULL,
+    datetime TIMESTAMP WITH TIME ZONE,
+    authorized_date DATE,
+    authorized_datetime TIMESTAMP WITH TIME ZONE,
+    name TEXT NOT NULL,
+    merchant_name VARCHAR(255),
+    original_description TEXT,
+    category TEXT[] NOT NULL DEFAULT '{}',
+    category_id VARCHAR(50),
+    check_number VARCHAR(50),
+    location JSONB,
+    payment_meta JSONB,
+    pending BOOLEAN NOT NULL DEFAULT FALSE,
+    pending_transaction_id VARCHAR(255),
+    account_owner VARCHAR(255),
+    transaction_type VARCHAR(50) NOT NULL,
+    transaction_code VARCHAR(50),
+    removed_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    merged_into VARCHAR(255)
+);
+
+INSERT INTO transactions (
+    id, transaction_id, account_id, item_id, user_id, amount, iso_currency_code, unofficial_currency_code,
+    date, datetime, authorized_date, authorized_datetime, name, merchant_name, original_description,
+    category, category_id, check_number, location, payment_meta, pending, pending_transaction_id,
+    account_owner, transaction_type, transaction_code, removed_at, created_at, updated_at, merged_into
+)
+SELECT
+    id, transaction_id, account_id, item_id, user_id, amount, iso_currency_code, unofficial_currency_code,
+    date, datetime, authorized_date, authorized_datetime, name, merchant_name, original_description,
+    category, category_id, check_number, location, payment_meta, pending, pending_transaction_id,
+    account_owner, transaction_type, transaction_code, removed_at, created_at, updated_at, merged_into
+FROM transactions_partitioned
+ON CONFLICT (transaction_id) DO NOTHING;
+
+DROP TABLE transactions_partitioned;
+DROP FUNCTION IF EXISTS create_transactions_partition(DATE);
+
+CREATE INDEX idx_transactions_user_date_id ON transactions(user_id, date DESC, transaction_id DESC) WHERE removed_at IS NULL;
+CREATE INDEX idx_transactions_account_id ON transactions(account_id);
+CREATE INDEX idx_transactions_item_id ON transactions(item_id);
+CREATE INDEX idx_transactions_updated_at ON transactions(user_id, updated_at);
+CREATE INDEX idx_transactions_name_trgm ON transactions USING GIN (name gin_trgm_ops) WHERE removed_at IS NULL;
+CREATE INDEX idx_transactions_merchant_name_trgm ON transactions USING GIN (merchant_name gin_trgm_ops) WHERE removed_at IS NULL;
+
+DELETE FROM transaction_categories c WHERE NOT EXISTS (SELECT 1 FROM transactions t WHERE t.transaction_id = c.transaction_id);
+DELETE FROM transaction_annotations a WHERE NOT EXISTS (SELECT 1 FROM transactions t WHERE t.transaction_id = a.transaction_id);
+DELETE FROM transaction_splits s WHERE NOT EXISTS (SELECT 1 FROM transactions t WHERE t.transaction_id = s.transaction_id);
+DELETE FROM receipts r WHERE NOT EXISTS (SELECT 1 FROM transactions t WHERE t.transaction_id = r.transaction_id);
+
+ALTER TABLE transaction_categories ADD CONSTRAINT transaction_categories_transaction_id_fkey
+    FOREIGN KEY (transaction_id) REFERENCES transactions(transaction_id) ON DELETE CASCADE;
+ALTER TABLE transaction_annotations ADD CONSTRAINT transaction_annotations_transaction_id_fkey
+    FOREIGN KEY (transaction_id) REFERENCES transactions(transaction_id) ON DELETE CASCADE;
+ALTER TABLE transaction_splits ADD CONSTRAINT transaction_splits_transaction_id_fkey
+    FOREIGN KEY (transaction_id) REFERENCES transactions(transaction_id) ON DELETE CASCADE;
+ALTER TABLE receipts ADD CONSTRAINT receipts_transaction_id_fkey
+    FOREIGN KEY (transaction_id) REFERENCES transactions(transaction_id) ON DELETE CASCADE;
@@ -0,0 +1,111 @@
+-- Transactions partitioned by month of `date`, so sync upserts and date-range reads touch a few small partitions.
+-- A partitioned table's unique keys must include the partition key, so transaction_id is unique per
+-- (transaction_id, date) and the repository upserts by transaction_id itself; child tables lose their
+-- foreign keys to transactions and are cleaned up with their item or user instead.
+ALTER TABLE transaction_categories DROP CONSTRAINT IF EXISTS transaction_categories_transaction_id_fkey;
+ALTER TABLE transaction_annotations DROP CONSTRAINT IF EXISTS transaction_annotations_transaction_id_fkey;
+ALTER TABLE transaction_splits DROP CONSTRAINT IF EXISTS transaction_splits_transaction_id_fkey;
+ALTER TABLE receipts DROP CONSTRAINT IF EXISTS receipts_transaction_id_fkey;
+
+ALTER TABLE transactions RENAME TO transactions_unpartitioned;
+
+CREATE TABLE transactions (
+    id UUID NOT NULL DEFAULT uuid_generate_v4(),
+    transaction_id VARCHAR(255) NOT NULL,
+    account_id VARCHAR(255) NOT NULL,
+    item_id VARCHAR(255) NOT NULL REFERENCES plaid_items(item_id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    amount DOUBLE PRECISION NOT NULL,
+    iso_currency_code VARCHAR(3),
+    unofficial_currency_code VARCHAR(16),
+    date DATE NOT NULL,
+    datetime TIMESTAMP WITH TIME ZONE,
+    authorized_date DATE,
+    authorized_datetime TIMESTAMP WITH TIME ZONE,
+    name TEXT NOT NULL,
+    merchant_name VARCHAR(255),
+    original_description TEXT,
+    category TEXT[] NOT NULL DEFAULT '{}',
+    category_id VARCHAR(50),
+    check_number VARCHAR(50),
+    location JSONB,
+    payment_meta JSONB,
+    pending BOOLEAN NOT NULL DEFAULT FALSE,
+    pending_transaction_id VARCHAR(255),
+    account_owner VARCHAR(255),
+    transaction_type VARCHAR(50) NOT NULL,
+    transaction_code VARCHAR(50),
+    removed_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    merged_into VARCHAR(255),
+    PRIMARY KEY (transaction_id, date)
+) PARTITION BY RANGE (date);
+
+-- Dates without a monthly partition (old backfills, far-future typos) land here until their month is created
+CREATE TABLE transactions_default PARTITION OF transactions DEFAULT;
+
+-- Create the partition for the month containing `month`, moving any of its rows out of the default
+-- partition first; returns false when the partition already exists
+CREATE OR REPLACE FUNCTION create_transactions_partition(month DATE) RETURNS BOOLEAN AS $$
+DECLARE
+    start_date DATE := date_trunc('month', month)::DATE;
+    end_date DATE := (date_trunc('month', month) + INTERVAL '1 month')::DATE;
+    partition_name TEXT := 'transactions_' || to_char(month, 'YYYY_MM');
+BEGIN
+    IF to_regclass(partition_name) IS NOT NULL THEN
+        RETURN FALSE;
+    END IF;
+
+    EXECUTE format('CREATE TABLE %I (LIKE transactions INCLUDING DEFAULTS INCLUDING CONSTRAINTS)', partition_name);
+    EXECUTE format(
+        'WITH moved AS (DELETE FROM transactions_default WHERE date >= %L AND date < %L RETURNING *) '
+        'INSERT INTO %I SELECT * FROM moved',
+        start_date, end_date, partition_name
+    );
+    EXECUTE format(
+        'ALTER TABLE transactions ATTACH PARTITION %I FOR VALUES FROM (%L) TO (%L)',
+        partition_name, start_date, end_date
+    );
+    RETURN TRUE;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Monthly partitions from the oldest stored transaction through three months ahead
+DO $$
+DECLARE
+    month DATE := date_trunc('month', LEAST(
+        (SELECT MIN(date) FROM transactions_unpartitioned),
+        CURRENT_DATE
+    ))::DATE;
+BEGIN
+    WHILE month <= date_trunc('month', CURRENT_DATE + INTERVAL '3 months') LOOP
+        PERFORM create_transactions_partition(month);
+        month := (month + INTERVAL '1 month')::DATE;
+    END LOOP;
+END;
+$$;
+
+INSERT INTO transactions (
+    id, transaction_id, account_id, item_id, user_id, amount, iso_currency_code, unofficial_currency_code,
+    date, datetime, authorized_date, authorized_datetime, name, merchant_name, original_description,
+    category, category_id, check_number, location, payment_meta, pending, pending_transaction_id,
+    account_owner, transaction_type, transaction_code, removed_at, created_at, updated_at, merged_into
+)
+SELECT
+    id, transaction_id, account_id, item_id, user_id, amount, iso_currency_code, unofficial_currency_code,
+    date, datetime, authorized_date, authorized_datetime, name, merchant_name, original_description,
+    category, category_id, check_number, location, payment_meta, pending, pending_transaction_id,
+    account_owner, transaction_type, transaction_code, removed_at, created_at, updated_at, merged_into
+FROM transactions_unpartitioned;
+
+DROP TABLE transactions_unpartitioned;
+
+-- Indexes are created on the parent after the copy and cascade to every partition
+CREATE INDEX idx_transactions_transaction_id ON transactions(transaction_id);
+CREATE INDEX idx_transactions_user_date_id ON transactions(user_id, date DESC, transaction_id DESC) WHERE removed_at IS NULL;
+CREATE INDEX idx_transactions_account_id ON transactions(account_id);
+CREATE INDEX idx_transactions_item_id ON transactions(item_id);
+CREATE INDEX idx_transactions_updated_at ON transactions(user_id, updated_at);
+CREATE INDEX idx_transactions_name_trgm ON transactions USING GIN (name gin_trgm_ops) WHERE removed_at IS NULL;
+CREATE INDEX idx_transactions_merchant_name_trgm ON transactions USING GIN (merchant_name gin_trgm_ops) WHERE removed_at IS NULL;
@@ -0,0 +1,9 @@
+-- Drop the spending aggregates and the triggers maintaining them
+DROP TRIGGER IF EXISTS transaction_splits_spending_refresh ON transaction_splits;
+DROP TRIGGER IF EXISTS transaction_categories_spending_refresh ON transaction_categories;
+DROP TRIGGER IF EXISTS transactions_spending_refresh_update ON transactions;
+DROP TRIGGER IF EXISTS transactions_spending_refresh_insert_delete ON transactions;
+DROP FUNCTION IF EXISTS queue_spending_refresh_for_transaction();
+DROP FUNCTION IF EXISTS queue_spending_refresh();
+DROP TABLE IF EXISTS spending_refresh_queue;
+DROP TABLE IF EXISTS spending_monthly_totals;
@@ -0,0 +1,85 @@
+-- Pre-aggregated spending per user, month, effective category and currency, so spending summaries
+-- read whole months without scanning their transactions. Rows with category '' total every category
+-- of the month, counting split transactions once.
+CREATE TABLE spending_monthly_totals (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    month DATE NOT NULL,
+    category VARCHAR(255) NOT NULL,
+    currency_code VARCHAR(16) NOT NULL,
+    amount DOUBLE PRECISION NOT NULL,
+    transaction_count BIGINT NOT NULL,
+    refreshed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, month, category, currency_code)
+);
+
+-- Months whose totals are out of date; readers scan their transactions until the refresh job recomputes them
+CREATE TABLE spending_refresh_queue (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    month DATE NOT NULL,
+    queued_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, month)
+);
+
+CREATE INDEX idx_spending_refresh_queue_queued_at ON spending_refresh_queue(queued_at);
+
+CREATE OR REPLACE FUNCTION queue_spending_refresh() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP IN ('UPDATE', 'DELETE') THEN
+        INSERT INTO spending_refresh_queue (user_id, month)
+        VALUES (OLD.user_id, date_trunc('month', OLD.date)::DATE)
+        ON CONFLICT DO NOTHING;
+    END IF;
+    IF TG_OP IN ('INSERT', 'UPDATE') THEN
+        INSERT INTO spending_refresh_queue (user_id, month)
+        VALUES (NEW.user_id, date_trunc('month', NEW.date)::DATE)
+        ON CONFLICT DO NOTHING;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Categories and splits carry no date, so the month comes from their transaction
+CREATE OR REPLACE FUNCTION queue_spending_refresh_for_transaction() RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO spending_refresh_queue (user_id, month)
+    SELECT t.user_id, date_trunc('month', t.date)::DATE
+    FROM transactions t
+    WHERE t.transaction_id IN (
+        CASE WHEN TG_OP = 'INSERT' THEN NULL ELSE OLD.transaction_id END,
+        CASE WHEN TG_OP = 'DELETE' THEN NULL ELSE NEW.transaction_id END
+    )
+    ON CONFLICT DO NOTHING;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER transactions_spending_refresh_insert_delete
+    AFTER INSERT OR DELETE ON transactions
+    FOR EACH ROW EXECUTE FUNCTION queue_spending_refresh();
+
+-- Sync re-upserts unchanged transactions, so only changes that affect spending queue a refresh
+CREATE TRIGGER transactions_spending_refresh_update
+    AFTER UPDATE ON transactions
+    FOR EACH ROW
+    WHEN (
+        OLD.amount IS DISTINCT FROM NEW.amount
+        OR OLD.date IS DISTINCT FROM NEW.date
+        OR OLD.pending IS DISTINCT FROM NEW.pending
+        OR OLD.removed_at IS DISTINCT FROM NEW.removed_at
+        OR OLD.iso_currency_code IS DISTINCT FROM NEW.iso_currency_code
+        OR OLD.unofficial_currency_code IS DISTINCT FROM NEW.unofficial_currency_code
+    )
+    EXECUTE FUNCTION queue_spending_refresh();
+
+CREATE TRIGGER transaction_categories_spending_refresh
+    AFTER INSERT OR UPDATE OR DELETE ON transaction_categories
+    FOR EACH ROW EXECUTE FUNCTION queue_spending_refresh_for_transaction();
+
+CREATE TRIGGER transaction_splits_spending_refresh
+    AFTER INSERT OR UPDATE OR DELETE ON transaction_splits
+    FOR EACH ROW EXECUTE FUNCTION queue_spending_refresh_for_transaction();
+
+-- Every existing month starts out queued; the refresh job fills the totals in the background
+INSERT INTO spending_refresh_queue (user_id, month)
+SELECT DISTINCT user_id, date_trunc('month', date)::DATE FROM transactions
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,4 @@
+-- Drop chat tables
+DROP TABLE IF EXISTS chat_messages;
+DROP INDEX IF EXISTS idx_chat_conversations_user;
+DROP TABLE IF EXISTS chat_conversations;
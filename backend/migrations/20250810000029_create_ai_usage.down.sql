@@ -0,0 +1,3 @@
+-- Drop AI usage table
+DROP INDEX IF EXISTS idx_ai_usage_user_created;
+DROP TABLE IF EXISTS ai_usage;
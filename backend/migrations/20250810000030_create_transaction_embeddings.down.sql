@@ -0,0 +1,5 @@
+-- Drop transaction embeddings; the vector extension is left installed
+DROP INDEX IF EXISTS idx_transaction_embeddings_embedding;
+DROP INDEX IF EXISTS idx_transaction_embeddings_item;
+DROP INDEX IF EXISTS idx_transaction_embeddings_user;
+DROP TABLE IF EXISTS transaction_embeddings;
@@ -0,0 +1,19 @@
+-- Embeddings of each transaction's merchant, name and category for semantic search, filled in after syncs.
+-- Rows embedded with a different model than the configured one are re-embedded; like other child tables
+-- of the partitioned transactions table, rows are cleaned up with their item or user.
+CREATE EXTENSION IF NOT EXISTS vector;
+
+CREATE TABLE transaction_embeddings (
+    transaction_id VARCHAR(255) PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    item_id VARCHAR(255) NOT NULL REFERENCES plaid_items(item_id) ON DELETE CASCADE,
+    model VARCHAR(255) NOT NULL,
+    embedding vector(1536) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_transaction_embeddings_user ON transaction_embeddings(user_id);
+CREATE INDEX idx_transaction_embeddings_item ON transaction_embeddings(item_id);
+CREATE INDEX idx_transaction_embeddings_embedding ON transaction_embeddings
+    USING hnsw (embedding vector_cosine_ops);
@@ -0,0 +1,4 @@
+-- Remove prompt cache token columns
+ALTER TABLE ai_usage
+    DROP COLUMN IF EXISTS cache_write_tokens,
+    DROP COLUMN IF EXISTS cache_read_tokens;
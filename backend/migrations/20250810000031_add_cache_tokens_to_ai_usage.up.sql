@@ -0,0 +1,5 @@
+-- Prompt cache tokens reported by the provider, kept apart from input_tokens because they are
+-- billed at different rates
+ALTER TABLE ai_usage
+    ADD COLUMN cache_read_tokens INTEGER NOT NULL DEFAULT 0 CHECK (cache_read_tokens >= 0),
+    ADD COLUMN cache_write_tokens INTEGER NOT NULL DEFAULT 0 CHECK (cache_write_tokens >= 0);
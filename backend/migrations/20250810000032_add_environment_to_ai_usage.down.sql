@@ -0,0 +1,3 @@
+-- Remove AI usage environment
+DROP INDEX IF EXISTS idx_ai_usage_environment_feature_created;
+ALTER TABLE ai_usage DROP COLUMN IF EXISTS environment;
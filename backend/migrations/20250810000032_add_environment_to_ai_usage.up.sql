@@ -0,0 +1,4 @@
+-- Deployment each AI request was made from, so monthly feature budgets are summed per environment
+ALTER TABLE ai_usage ADD COLUMN environment VARCHAR(32) NOT NULL DEFAULT 'dev';
+
+CREATE INDEX idx_ai_usage_environment_feature_created ON ai_usage(environment, feature, created_at);
@@ -0,0 +1,6 @@
+-- Drop AI batch tables
+DROP INDEX IF EXISTS idx_ai_batch_tasks_pending;
+DROP INDEX IF EXISTS idx_ai_batch_tasks_batch;
+DROP TABLE IF EXISTS ai_batch_tasks;
+DROP INDEX IF EXISTS idx_ai_batches_user_created;
+DROP TABLE IF EXISTS ai_batches;
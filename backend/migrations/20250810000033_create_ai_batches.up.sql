@@ -0,0 +1,33 @@
+-- Batches of small AI tasks submitted together and processed in the background. Counts are
+-- refreshed as tasks finish; a batch is done once no task is queued or running.
+CREATE TABLE ai_batches (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    kind VARCHAR(32) NOT NULL,
+    status VARCHAR(16) NOT NULL DEFAULT 'queued' CHECK (status IN ('queued', 'running', 'completed')),
+    total_tasks INTEGER NOT NULL,
+    succeeded_tasks INTEGER NOT NULL DEFAULT 0,
+    failed_tasks INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX idx_ai_batches_user_created ON ai_batches(user_id, created_at DESC);
+
+-- One task per input. Running tasks whose claim is older than the processor's lease are
+-- claimed again, so a crashed worker doesn't strand them.
+CREATE TABLE ai_batch_tasks (
+    id BIGSERIAL PRIMARY KEY,
+    batch_id UUID NOT NULL REFERENCES ai_batches(id) ON DELETE CASCADE,
+    input TEXT NOT NULL,
+    status VARCHAR(16) NOT NULL DEFAULT 'queued' CHECK (status IN ('queued', 'running', 'succeeded', 'failed')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    output JSONB,
+    error TEXT,
+    claimed_at TIMESTAMP WITH TIME ZONE,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_ai_batch_tasks_batch ON ai_batch_tasks(batch_id, id);
+CREATE INDEX idx_ai_batch_tasks_pending ON ai_batch_tasks(status, id) WHERE status IN ('queued', 'running');
@@ -0,0 +1,4 @@
+-- Drop AI email drafts
+DROP INDEX IF EXISTS idx_ai_email_drafts_pending;
+DROP INDEX IF EXISTS idx_ai_email_drafts_user_created;
+DROP TABLE IF EXISTS ai_email_drafts;
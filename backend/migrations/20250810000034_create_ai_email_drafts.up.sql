@@ -0,0 +1,21 @@
+-- Email copy written by an LLM, kept for review whether or not it was sent. Drafts with safety
+-- issues are never used in an email.
+CREATE TABLE ai_email_drafts (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    kind VARCHAR(32) NOT NULL,
+    provider VARCHAR(32) NOT NULL,
+    model VARCHAR(100) NOT NULL,
+    prompt_version BIGINT,
+    facts TEXT NOT NULL,
+    content TEXT NOT NULL,
+    issues TEXT[] NOT NULL DEFAULT '{}',
+    used BOOLEAN NOT NULL DEFAULT FALSE,
+    review_status VARCHAR(16) NOT NULL DEFAULT 'pending' CHECK (review_status IN ('pending', 'approved', 'rejected')),
+    reviewed_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    reviewed_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_ai_email_drafts_user_created ON ai_email_drafts(user_id, created_at DESC);
+CREATE INDEX idx_ai_email_drafts_pending ON ai_email_drafts(created_at) WHERE review_status = 'pending';
@@ -0,0 +1,3 @@
+-- Drop email templates
+DROP INDEX IF EXISTS idx_email_templates_active;
+DROP TABLE IF EXISTS email_templates;
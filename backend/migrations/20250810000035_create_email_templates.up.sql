@@ -0,0 +1,17 @@
+-- Edited versions of the emails the app sends. At most one version per template is active; without one
+-- the template compiled into the server is used.
+CREATE TABLE email_templates (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    name VARCHAR(64) NOT NULL,
+    version INTEGER NOT NULL,
+    subject TEXT NOT NULL,
+    html TEXT NOT NULL,
+    text TEXT NOT NULL,
+    active BOOLEAN NOT NULL DEFAULT FALSE,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    activated_at TIMESTAMP WITH TIME ZONE,
+    UNIQUE (name, version)
+);
+
+CREATE UNIQUE INDEX idx_email_templates_active ON email_templates(name) WHERE active;
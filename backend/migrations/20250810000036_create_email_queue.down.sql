@@ -0,0 +1,4 @@
+-- Drop email queue table
+DROP INDEX IF EXISTS idx_email_queue_dead;
+DROP INDEX IF EXISTS idx_email_queue_due;
+DROP TABLE IF EXISTS email_queue;
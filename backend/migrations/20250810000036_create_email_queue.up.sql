@@ -0,0 +1,22 @@
+-- Outbox of emails sent by a background worker, so request paths don't wait on SES. Failed sends
+-- are retried with exponential backoff; emails out of attempts or past expires_at are dead.
+CREATE TABLE email_queue (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    template VARCHAR(64) NOT NULL,
+    recipient TEXT NOT NULL,
+    -- Placeholder values; cleared once the email is sent, as they may hold one-time codes
+    template_data JSONB NOT NULL DEFAULT '{}',
+    status VARCHAR(16) NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'sending', 'sent', 'dead')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP WITH TIME ZONE,
+    claimed_at TIMESTAMP WITH TIME ZONE,
+    last_error TEXT,
+    message_id TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    sent_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX idx_email_queue_due ON email_queue(next_attempt_at) WHERE status IN ('pending', 'sending');
+CREATE INDEX idx_email_queue_dead ON email_queue(updated_at DESC) WHERE status = 'dead';
@@ -0,0 +1,3 @@
+-- Drop user and queued email locales
+ALTER TABLE email_queue DROP COLUMN IF EXISTS locale;
+ALTER TABLE users DROP COLUMN IF EXISTS locale;
@@ -0,0 +1,5 @@
+-- Language of the emails sent to a user, as a BCP 47 tag such as "es-MX"
+ALTER TABLE users ADD COLUMN locale VARCHAR(35) NOT NULL DEFAULT 'en';
+
+-- Language a queued email is rendered in
+ALTER TABLE email_queue ADD COLUMN locale VARCHAR(35) NOT NULL DEFAULT 'en';
@@ -0,0 +1,3 @@
+-- Drop email scheduling
+ALTER TABLE email_queue DROP COLUMN IF EXISTS send_at;
+ALTER TABLE users DROP COLUMN IF EXISTS timezone;
@@ -0,0 +1,5 @@
+-- IANA time zone used to send emails at local times
+ALTER TABLE users ADD COLUMN timezone VARCHAR(64) NOT NULL DEFAULT 'UTC';
+
+-- Earliest time a queued email may be sent; its first attempt is due then
+ALTER TABLE email_queue ADD COLUMN send_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW();
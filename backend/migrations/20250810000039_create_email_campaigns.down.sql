@@ -0,0 +1,5 @@
+-- Drop email campaign tables
+DROP INDEX IF EXISTS idx_email_campaign_recipients_pending;
+DROP TABLE IF EXISTS email_campaign_recipients;
+DROP INDEX IF EXISTS idx_email_campaigns_created;
+DROP TABLE IF EXISTS email_campaigns;
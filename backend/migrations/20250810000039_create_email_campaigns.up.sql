@@ -0,0 +1,42 @@
+-- Announcement emails sent to an audience by a background job. Counts are refreshed as recipients
+-- are sent; a campaign is done once no recipient is pending or sending.
+CREATE TABLE email_campaigns (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    name VARCHAR(200) NOT NULL,
+    subject TEXT NOT NULL,
+    html TEXT NOT NULL,
+    text TEXT NOT NULL,
+    audience VARCHAR(32) NOT NULL CHECK (audience IN ('all_users', 'weekly_digest', 'recipients')),
+    status VARCHAR(16) NOT NULL DEFAULT 'queued' CHECK (status IN ('queued', 'sending', 'completed', 'cancelled')),
+    total_recipients INTEGER NOT NULL DEFAULT 0,
+    sent_count INTEGER NOT NULL DEFAULT 0,
+    failed_count INTEGER NOT NULL DEFAULT 0,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    send_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    started_at TIMESTAMP WITH TIME ZONE,
+    completed_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX idx_email_campaigns_created ON email_campaigns(created_at DESC);
+
+-- One row per address, with the merge variables filled into the campaign's placeholders.
+-- Sending recipients whose claim is older than the sender's lease are claimed again.
+CREATE TABLE email_campaign_recipients (
+    id BIGSERIAL PRIMARY KEY,
+    campaign_id UUID NOT NULL REFERENCES email_campaigns(id) ON DELETE CASCADE,
+    email TEXT NOT NULL,
+    merge_data JSONB NOT NULL DEFAULT '{}',
+    status VARCHAR(16) NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'sending', 'sent', 'failed', 'cancelled')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    claimed_at TIMESTAMP WITH TIME ZONE,
+    message_id TEXT,
+    error TEXT,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    UNIQUE (campaign_id, email)
+);
+
+CREATE INDEX idx_email_campaign_recipients_pending
+    ON email_campaign_recipients(status, id) WHERE status IN ('pending', 'sending');
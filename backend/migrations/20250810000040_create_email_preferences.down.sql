@@ -0,0 +1,2 @@
+-- Drop email preferences
+DROP TABLE IF EXISTS email_preferences;
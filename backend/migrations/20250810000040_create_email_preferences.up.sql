@@ -0,0 +1,10 @@
+-- Kinds of email an address receives, keyed by the lowercased address so people who aren't users
+-- can unsubscribe from campaigns too. Addresses without a row receive every kind. For users, digest
+-- is kept in step with users.weekly_digest_enabled.
+CREATE TABLE email_preferences (
+    email TEXT PRIMARY KEY,
+    transactional BOOLEAN NOT NULL DEFAULT TRUE,
+    digest BOOLEAN NOT NULL DEFAULT TRUE,
+    marketing BOOLEAN NOT NULL DEFAULT TRUE,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
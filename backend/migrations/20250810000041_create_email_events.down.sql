@@ -0,0 +1,2 @@
+-- Drop email events
+DROP TABLE IF EXISTS email_events;
@@ -0,0 +1,18 @@
+-- Delivery, open, click, bounce and complaint events SES publishes for sent emails through a
+-- configuration set, keyed by the SES message ID. event_id is the SNS notification ID so a
+-- redelivered notification is stored once.
+CREATE TABLE email_events (
+    id BIGSERIAL PRIMARY KEY,
+    event_id TEXT NOT NULL UNIQUE,
+    message_id TEXT NOT NULL,
+    event_type VARCHAR(32) NOT NULL CHECK (event_type IN (
+        'send', 'delivery', 'delivery_delay', 'open', 'click', 'bounce', 'complaint', 'reject',
+        'rendering_failure', 'subscription'
+    )),
+    recipients TEXT[] NOT NULL DEFAULT '{}',
+    detail JSONB NOT NULL DEFAULT '{}',
+    occurred_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_email_events_message ON email_events(message_id, occurred_at);
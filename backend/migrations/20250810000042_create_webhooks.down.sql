@@ -0,0 +1,6 @@
+-- Drop webhook tables
+DROP INDEX IF EXISTS idx_webhook_deliveries_endpoint;
+DROP INDEX IF EXISTS idx_webhook_deliveries_due;
+DROP TABLE IF EXISTS webhook_deliveries;
+DROP INDEX IF EXISTS idx_webhook_endpoints_user;
+DROP TABLE IF EXISTS webhook_endpoints;
@@ -0,0 +1,6 @@
+-- Indexes for admin support lookups: users by part of their email or name, and the emails queued for an address
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX idx_users_email_trgm ON users USING GIN (email gin_trgm_ops);
+CREATE INDEX idx_users_name_trgm ON users USING GIN (name gin_trgm_ops);
+CREATE INDEX idx_email_queue_recipient ON email_queue(lower(recipient), created_at DESC);
@@ -0,0 +1,6 @@
+ALTER TABLE otp_codes ADD COLUMN code VARCHAR(10) NOT NULL DEFAULT '';
+ALTER TABLE otp_codes
+    DROP COLUMN encryption_key_id,
+    DROP COLUMN encrypted_data_key,
+    DROP COLUMN code_nonce,
+    DROP COLUMN code_ciphertext;
@@ -0,0 +1,14 @@
+-- Sign-in codes are kept encrypted, and only until they are used or expire, for the email worker to
+-- render; emails queue the code's id rather than the code
+ALTER TABLE otp_codes
+    ADD COLUMN code_ciphertext BYTEA,
+    ADD COLUMN code_nonce BYTEA,
+    ADD COLUMN encrypted_data_key BYTEA,
+    ADD COLUMN encryption_key_id VARCHAR(255);
+ALTER TABLE otp_codes DROP COLUMN code;
+
+-- Codes already queued in plaintext can't be sent any more
+UPDATE email_queue
+SET status = 'dead', template_data = '{}', last_error = 'Queued before codes were encrypted', updated_at = NOW()
+WHERE template_data ? 'otp_code';
+UPDATE email_queue SET template_data = '{}' WHERE status = 'dead';
@@ -0,0 +1 @@
+ALTER TABLE plaid_items DROP COLUMN IF EXISTS sync_claimed_until;
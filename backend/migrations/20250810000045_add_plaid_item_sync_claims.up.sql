@@ -0,0 +1,2 @@
+-- Lease on an item's transaction sync, so only one instance syncs an item at a time
+ALTER TABLE plaid_items ADD COLUMN sync_claimed_until TIMESTAMP WITH TIME ZONE;
@@ -0,0 +1,16 @@
+-- Sessions of refresh tokens, the store sessions move to from Redis (migration phase flags `session-store-*`).
+-- Revoked sessions keep their row until it expires, so a write racing the logout can't bring them back.
+CREATE TABLE auth_sessions (
+    refresh_token_jti VARCHAR(64) PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    google_id VARCHAR(255) NOT NULL,
+    email VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    last_activity TIMESTAMP WITH TIME ZONE NOT NULL,
+    last_refresh_at TIMESTAMP WITH TIME ZONE,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    revoked_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX idx_auth_sessions_user ON auth_sessions(user_id) WHERE revoked_at IS NULL;
+CREATE INDEX idx_auth_sessions_expires_at ON auth_sessions(expires_at);
@@ -0,0 +1,2 @@
+-- Admin role granted with `create-admin`, checked on every admin RPC
+ALTER TABLE users ADD COLUMN is_admin BOOLEAN NOT NULL DEFAULT FALSE;
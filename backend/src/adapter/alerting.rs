@@ -0,0 +1,144 @@
+use crate::adapter::email_templates::EmailLocale;
+use crate::adapter::mailer::Mailer;
+use crate::adapter::ses::EmailPriority;
+use anyhow::Result;
+use serde::Serialize;
+use std::sync::Arc;
+use tracing::{error, info, instrument, warn};
+
+/// How urgently an operator must react to an alert
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum AlertSeverity {
+    /// Needs immediate attention
+    Page,
+    /// Needs attention within a working day
+    Ticket,
+}
+
+impl AlertSeverity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertSeverity::Page => "page",
+            AlertSeverity::Ticket => "ticket",
+        }
+    }
+}
+
+/// Operator alert raised by the service itself
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    /// Stable identifier; a resolved alert carries the key of the alert it resolves
+    pub key: String,
+    pub severity: AlertSeverity,
+    pub summary: String,
+    pub details: String,
+    pub resolved: bool,
+}
+
+/// Destination for operator alerts
+#[async_trait::async_trait]
+pub trait AlertSink: Send + Sync {
+    async fn send(&self, alert: &Alert) -> Result<()>;
+}
+
+/// Writes alerts to the structured log, for log-based alert routing
+#[derive(Debug, Clone, Default)]
+pub struct LogAlertSink;
+
+#[async_trait::async_trait]
+impl AlertSink for LogAlertSink {
+    async fn send(&self, alert: &Alert) -> Result<()> {
+        if alert.resolved {
+            info!(
+                alert_key = %alert.key,
+                severity = %alert.severity.as_str(),
+                summary = %alert.summary,
+                "Alert resolved"
+            );
+        } else {
+            error!(
+                alert_key = %alert.key,
+                severity = %alert.severity.as_str(),
+                summary = %alert.summary,
+                details = %alert.details,
+                "Alert firing"
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Emails alerts to operators
+pub struct EmailAlertSink {
+    mailer: Arc<Mailer>,
+    recipients: Vec<String>,
+}
+
+impl EmailAlertSink {
+    pub fn new(mailer: Arc<Mailer>, recipients: Vec<String>) -> Self {
+        Self { mailer, recipients }
+    }
+
+    /// Parse a comma-separated recipient list, e.g. from `ALERT_EMAIL_RECIPIENTS`
+    pub fn parse_recipients(value: &str) -> Vec<String> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|r| !r.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl AlertSink for EmailAlertSink {
+    #[instrument(skip(self, alert), fields(alert_key = %alert.key))]
+    async fn send(&self, alert: &Alert) -> Result<()> {
+        let subject = if alert.resolved {
+            format!("[RESOLVED] {}", alert.summary)
+        } else {
+            format!("[{}] {}", alert.severity.as_str().to_uppercase(), alert.summary)
+        };
+        let priority = match (alert.resolved, alert.severity) {
+            (false, AlertSeverity::Page) => EmailPriority::High,
+            _ => EmailPriority::Normal,
+        };
+
+        let mut failures = 0;
+        for recipient in &self.recipients {
+            if let Err(e) = self
+                .mailer
+                .send_notification_email(
+                    recipient.as_str(),
+                    subject.as_str(),
+                    alert.details.as_str(),
+                    priority,
+                    EmailLocale::English,
+                )
+                .await
+            {
+                warn!(recipient = %recipient, error = ?e, "Failed to email alert");
+                failures += 1;
+            }
+        }
+
+        if failures > 0 && failures == self.recipients.len() {
+            anyhow::bail!("Alert email failed for every recipient: {}", alert.summary);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_recipients() {
+        assert_eq!(
+            EmailAlertSink::parse_recipients(" oncall@example.com, ,ops@example.com "),
+            vec!["oncall@example.com".to_string(), "ops@example.com".to_string()]
+        );
+        assert!(EmailAlertSink::parse_recipients("").is_empty());
+    }
+}
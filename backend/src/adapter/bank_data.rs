@@ -0,0 +1,284 @@
+// Bank aggregation behind a provider-neutral trait, so items can be linked through Plaid or another aggregator
+use crate::adapter::plaid::{
+    AccountIdentity, BankAccount, BankTransaction, Institution, Liability, LinkTokenRequest, LinkTokenResponse,
+    PlaidClient, PublicTokenExchangeRequest, PublicTokenExchangeResponse, Statement, TransactionSyncRequest,
+    TransactionSyncResponse,
+};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Name stored on items linked through Plaid
+pub const PLAID_PROVIDER: &str = "plaid";
+
+/// Operations handlers, jobs and repositories need from a bank aggregator.
+///
+/// `access_token` is the provider's long-lived credential for one linked item (a Plaid access
+/// token, an MX member GUID, a Finicity customer/institution-login pair, ...).
+#[async_trait]
+pub trait BankDataProvider: Send + Sync {
+    /// Identifier stored on the items linked through this provider
+    fn name(&self) -> &'static str;
+
+    /// Start a link session for the provider's connect widget
+    async fn create_link_token(&self, request: LinkTokenRequest) -> Result<LinkTokenResponse>;
+
+    /// Trade the widget's one-time token for a long-lived item credential
+    async fn exchange_public_token(&self, request: PublicTokenExchangeRequest) -> Result<PublicTokenExchangeResponse>;
+
+    /// Accounts with balances that may be cached by the provider
+    async fn get_accounts(&self, access_token: &str) -> Result<Vec<BankAccount>>;
+
+    /// Accounts with balances fetched live from the institution
+    async fn get_balances(&self, access_token: &str) -> Result<Vec<BankAccount>>;
+
+    /// One page of transaction changes after `request.cursor`
+    async fn sync_transactions(&self, request: TransactionSyncRequest) -> Result<TransactionSyncResponse>;
+
+    /// Every transaction dated within `start_date..=end_date` (`YYYY-MM-DD`)
+    async fn get_all_transactions(
+        &self,
+        access_token: &str,
+        start_date: &str,
+        end_date: &str,
+    ) -> Result<Vec<BankTransaction>>;
+
+    async fn get_liabilities(&self, access_token: &str) -> Result<Vec<Liability>>;
+
+    async fn get_identity(&self, access_token: &str) -> Result<Vec<AccountIdentity>>;
+
+    async fn get_institution(&self, institution_id: &str, country_codes: Vec<&str>) -> Result<Institution>;
+
+    /// Revoke the item credential at the provider
+    async fn remove_item(&self, access_token: &str) -> Result<()>;
+
+    /// Monthly statements of the item's accounts; providers without statements list none
+    async fn list_statements(&self, _access_token: &str) -> Result<Vec<Statement>> {
+        Ok(Vec::new())
+    }
+
+    /// Statement PDF bytes
+    async fn download_statement(&self, _access_token: &str, statement_id: &str) -> Result<Vec<u8>> {
+        Err(anyhow!("{} does not provide statement {}", self.name(), statement_id))
+    }
+}
+
+#[async_trait]
+impl BankDataProvider for PlaidClient {
+    fn name(&self) -> &'static str {
+        PLAID_PROVIDER
+    }
+
+    async fn create_link_token(&self, mut request: LinkTokenRequest) -> Result<LinkTokenResponse> {
+        if request.webhook.is_none() {
+            request.webhook = self.config().webhook_url.clone();
+        }
+        PlaidClient::create_link_token(self, request).await
+    }
+
+    async fn exchange_public_token(&self, request: PublicTokenExchangeRequest) -> Result<PublicTokenExchangeResponse> {
+        PlaidClient::exchange_public_token(self, request).await
+    }
+
+    async fn get_accounts(&self, access_token: &str) -> Result<Vec<BankAccount>> {
+        PlaidClient::get_accounts(self, access_token).await
+    }
+
+    async fn get_balances(&self, access_token: &str) -> Result<Vec<BankAccount>> {
+        PlaidClient::get_balances(self, access_token).await
+    }
+
+    async fn sync_transactions(&self, request: TransactionSyncRequest) -> Result<TransactionSyncResponse> {
+        PlaidClient::sync_transactions(self, request).await
+    }
+
+    async fn get_all_transactions(
+        &self,
+        access_token: &str,
+        start_date: &str,
+        end_date: &str,
+    ) -> Result<Vec<BankTransaction>> {
+        PlaidClient::get_all_transactions(self, access_token, start_date, end_date).await
+    }
+
+    async fn get_liabilities(&self, access_token: &str) -> Result<Vec<Liability>> {
+        PlaidClient::get_liabilities(self, access_token).await
+    }
+
+    async fn get_identity(&self, access_token: &str) -> Result<Vec<AccountIdentity>> {
+        PlaidClient::get_identity(self, access_token).await
+    }
+
+    async fn get_institution(&self, institution_id: &str, country_codes: Vec<&str>) -> Result<Institution> {
+        PlaidClient::get_institution(self, institution_id, country_codes).await
+    }
+
+    async fn remove_item(&self, access_token: &str) -> Result<()> {
+        PlaidClient::remove_item(self, access_token).await
+    }
+
+    async fn list_statements(&self, access_token: &str) -> Result<Vec<Statement>> {
+        PlaidClient::list_statements(self, access_token).await
+    }
+
+    async fn download_statement(&self, access_token: &str, statement_id: &str) -> Result<Vec<u8>> {
+        PlaidClient::download_statement(self, access_token, statement_id).await
+    }
+}
+
+/// Configured providers: existing items use the provider they were linked with, new links use
+/// the provider routed for the user's country, falling back to the default provider
+#[derive(Clone)]
+pub struct BankDataProviders {
+    providers: HashMap<&'static str, Arc<dyn BankDataProvider>>,
+    default: &'static str,
+    /// ISO country code -> provider name
+    regions: HashMap<String, &'static str>,
+}
+
+impl BankDataProviders {
+    pub fn new(default: Arc<dyn BankDataProvider>) -> Self {
+        let name = default.name();
+        Self {
+            providers: HashMap::from([(name, default)]),
+            default: name,
+            regions: HashMap::new(),
+        }
+    }
+
+    /// Register another provider
+    pub fn with_provider(mut self, provider: Arc<dyn BankDataProvider>) -> Self {
+        self.providers.insert(provider.name(), provider);
+        self
+    }
+
+    /// Route new links from a country to a registered provider
+    pub fn with_region(mut self, country_code: &str, provider: &str) -> Result<Self> {
+        let (name, _) = self
+            .providers
+            .get_key_value(provider)
+            .ok_or_else(|| anyhow!("Unknown bank data provider '{}' for region {}", provider, country_code))?;
+        self.regions.insert(country_code.trim().to_ascii_uppercase(), *name);
+        Ok(self)
+    }
+
+    /// Apply `country=provider` routes separated by commas, e.g. `CA=mx,GB=plaid`
+    pub fn with_regions(mut self, routes: &str) -> Result<Self> {
+        for route in routes.split(',').map(str::trim).filter(|route| !route.is_empty()) {
+            let (country_code, provider) = route
+                .split_once('=')
+                .with_context(|| format!("Invalid bank data provider route '{}'", route))?;
+            self = self.with_region(country_code, provider.trim())?;
+        }
+        Ok(self)
+    }
+
+    pub fn default_provider(&self) -> Arc<dyn BankDataProvider> {
+        self.providers[self.default].clone()
+    }
+
+    /// Provider registered under `name`, e.g. the one an item was linked with
+    pub fn get(&self, name: &str) -> Result<Arc<dyn BankDataProvider>> {
+        self.providers
+            .get(name)
+            .cloned()
+            .ok_or_else(|| anyhow!("Bank data provider '{}' is not configured", name))
+    }
+
+    /// Provider for a new link from `country_code`
+    pub fn for_country(&self, country_code: Option<&str>) -> Arc<dyn BankDataProvider> {
+        country_code
+            .and_then(|code| self.regions.get(&code.trim().to_ascii_uppercase()))
+            .map(|name| self.providers[name].clone())
+            .unwrap_or_else(|| self.default_provider())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct StubProvider(&'static str);
+
+    #[async_trait]
+    impl BankDataProvider for StubProvider {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        async fn create_link_token(&self, _request: LinkTokenRequest) -> Result<LinkTokenResponse> {
+            unimplemented!()
+        }
+
+        async fn exchange_public_token(&self, _request: PublicTokenExchangeRequest) -> Result<PublicTokenExchangeResponse> {
+            unimplemented!()
+        }
+
+        async fn get_accounts(&self, _access_token: &str) -> Result<Vec<BankAccount>> {
+            unimplemented!()
+        }
+
+        async fn get_balances(&self, _access_token: &str) -> Result<Vec<BankAccount>> {
+            unimplemented!()
+        }
+
+        async fn sync_transactions(&self, _request: TransactionSyncRequest) -> Result<TransactionSyncResponse> {
+            unimplemented!()
+        }
+
+        async fn get_all_transactions(
+            &self,
+            _access_token: &str,
+            _start_date: &str,
+            _end_date: &str,
+        ) -> Result<Vec<BankTransaction>> {
+            unimplemented!()
+        }
+
+        async fn get_liabilities(&self, _access_token: &str) -> Result<Vec<Liability>> {
+            unimplemented!()
+        }
+
+        async fn get_identity(&self, _access_token: &str) -> Result<Vec<AccountIdentity>> {
+            unimplemented!()
+        }
+
+        async fn get_institution(&self, _institution_id: &str, _country_codes: Vec<&str>) -> Result<Institution> {
+            unimplemented!()
+        }
+
+        async fn remove_item(&self, _access_token: &str) -> Result<()> {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    fn test_provider_routing() {
+        let providers = BankDataProviders::new(Arc::new(StubProvider("plaid")))
+            .with_provider(Arc::new(StubProvider("mx")))
+            .with_regions(" ca=mx , GB=plaid")
+            .unwrap();
+
+        assert_eq!(providers.for_country(Some("CA")).name(), "mx");
+        assert_eq!(providers.for_country(Some("gb")).name(), "plaid");
+        assert_eq!(providers.for_country(Some("FR")).name(), "plaid");
+        assert_eq!(providers.for_country(None).name(), "plaid");
+        assert_eq!(providers.get("mx").unwrap().name(), "mx");
+        assert!(providers.get("finicity").is_err());
+    }
+
+    #[test]
+    fn test_unknown_region_provider_rejected() {
+        let providers = BankDataProviders::new(Arc::new(StubProvider("plaid")));
+        assert!(providers.clone().with_regions("CA=finicity").is_err());
+        assert!(providers.with_regions("CA").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_statements_default_to_none() {
+        let provider = StubProvider("mx");
+        assert!(provider.list_statements("token").await.unwrap().is_empty());
+        assert!(provider.download_statement("token", "st_1").await.is_err());
+    }
+}
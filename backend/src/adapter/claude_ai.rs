@@ -1,7 +1,13 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::VecDeque;
 use reqwest::Client;
-use anyhow::{Result, Context};
+use anyhow::{bail, Result, Context};
+use base64::Engine;
+use crate::adapter::model_fallback::{
+    backoff_delay, model_chain, parse_fallback_models, parse_retry_after, retry_decision, ModelRoute, RetryDecision,
+};
+use crate::adapter::sse::SseParser;
+use crate::settings::{SettingValues, Settings};
 
 /// Configuration for Claude AI API client
 #[derive(Debug, Clone)]
@@ -14,8 +20,12 @@ pub struct ClaudeAIConfig {
     pub default_model: String,
     /// Request timeout in seconds
     pub timeout_seconds: u64,
-    /// Maximum number of retries for failed requests
+    /// Timeout in seconds for a streamed response, which stays open while tokens are generated
+    pub stream_timeout_seconds: u64,
+    /// Maximum number of attempts per model for failed requests
     pub max_retries: u32,
+    /// Models tried in order when the requested one is rate limited or failing
+    pub fallback_models: Vec<ModelRoute>,
 }
 
 impl Default for ClaudeAIConfig {
@@ -25,11 +35,36 @@ impl Default for ClaudeAIConfig {
             base_url: "https://api.anthropic.com".to_string(),
             default_model: "claude-3-sonnet-20240229".to_string(),
             timeout_seconds: 60,
+            stream_timeout_seconds: 300,
             max_retries: 3,
+            fallback_models: Vec::new(),
         }
     }
 }
 
+impl ClaudeAIConfig {
+    /// Read from `CLAUDE_API_KEY`, `CLAUDE_BASE_URL`, `CLAUDE_DEFAULT_MODEL`, `CLAUDE_TIMEOUT_SECONDS`,
+    /// `CLAUDE_STREAM_TIMEOUT_SECONDS`, `CLAUDE_MAX_RETRIES` and `CLAUDE_FALLBACK_MODELS`; `None` without an
+    /// API key
+    pub fn from_values(values: &mut SettingValues) -> Option<Self> {
+        let api_key = values.optional("claude-api-key")?;
+        let defaults = Self::default();
+        let fallback_models = parse_fallback_models(&values.or("claude-fallback-models", "")).unwrap_or_else(|e| {
+            values.error(format!("claude-fallback-models is invalid: {}", e));
+            Vec::new()
+        });
+        Some(Self {
+            api_key,
+            base_url: values.or("claude-base-url", &defaults.base_url),
+            default_model: values.or("claude-default-model", &defaults.default_model),
+            timeout_seconds: values.parsed("claude-timeout-seconds", defaults.timeout_seconds),
+            stream_timeout_seconds: values.parsed("claude-stream-timeout-seconds", defaults.stream_timeout_seconds),
+            max_retries: values.parsed("claude-max-retries", defaults.max_retries),
+            fallback_models,
+        })
+    }
+}
+
 /// Request payload for Claude AI messages API
 #[derive(Debug, Serialize)]
 pub struct ClaudeRequest {
@@ -37,11 +72,84 @@ pub struct ClaudeRequest {
     pub max_tokens: u32,
     pub temperature: Option<f32>,
     pub messages: Vec<ClaudeMessage>,
-    pub system: Option<String>,
+    pub system: Option<ClaudeSystem>,
     pub stop_sequences: Option<Vec<String>>,
     pub stream: Option<bool>,
 }
 
+impl ClaudeRequest {
+    /// Mark the system prompt for server-side caching, so repeated requests sharing it are billed
+    /// at the cache read price. Prompts shorter than the model's minimum are not cached.
+    pub fn with_cached_system(mut self) -> Self {
+        self.system = self.system.map(|system| ClaudeSystem::cached(system.text()));
+        self
+    }
+}
+
+/// System prompt, as plain text or as blocks that can carry cache breakpoints
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum ClaudeSystem {
+    Text(String),
+    Blocks(Vec<ClaudeSystemBlock>),
+}
+
+impl ClaudeSystem {
+    /// A single text block cached up to its end
+    pub fn cached(text: String) -> Self {
+        ClaudeSystem::Blocks(vec![ClaudeSystemBlock {
+            r#type: "text".to_string(),
+            text,
+            cache_control: Some(ClaudeCacheControl::ephemeral()),
+        }])
+    }
+
+    /// The prompt's text, with blocks joined
+    pub fn text(&self) -> String {
+        match self {
+            ClaudeSystem::Text(text) => text.clone(),
+            ClaudeSystem::Blocks(blocks) => blocks.iter().map(|b| b.text.as_str()).collect::<Vec<_>>().join("\n\n"),
+        }
+    }
+}
+
+impl From<String> for ClaudeSystem {
+    fn from(text: String) -> Self {
+        ClaudeSystem::Text(text)
+    }
+}
+
+impl From<&str> for ClaudeSystem {
+    fn from(text: &str) -> Self {
+        ClaudeSystem::Text(text.to_string())
+    }
+}
+
+/// Text block of a system prompt
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClaudeSystemBlock {
+    pub r#type: String,
+    pub text: String,
+    /// Caches the prompt prefix ending with this block
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<ClaudeCacheControl>,
+}
+
+/// Prompt caching breakpoint
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClaudeCacheControl {
+    pub r#type: String,
+}
+
+impl ClaudeCacheControl {
+    /// The default cache, kept for five minutes after its last use
+    pub fn ephemeral() -> Self {
+        Self {
+            r#type: "ephemeral".to_string(),
+        }
+    }
+}
+
 /// Individual message in a Claude conversation
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ClaudeMessage {
@@ -49,8 +157,113 @@ pub struct ClaudeMessage {
     pub content: String,
 }
 
-/// Response from Claude AI API
+/// Image types the vision API accepts
+const VISION_IMAGE_TYPES: &[&str] = &["image/jpeg", "image/png", "image/gif", "image/webp"];
+
+/// Largest file sent inline; the API rejects bigger inline images, so larger files are sent by URL
+pub const MAX_INLINE_MEDIA_BYTES: usize = 5 * 1024 * 1024;
+
+/// Where the API reads an image or document from
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClaudeMediaSource {
+    /// File sent inline with the message
+    Base64 { media_type: String, data: String },
+    /// File the API downloads, such as a presigned S3 URL; it must stay valid until the request completes
+    Url { url: String },
+}
+
+/// Content block of a message that mixes text with images or documents
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClaudeContentBlock {
+    Text { text: String },
+    Image { source: ClaudeMediaSource },
+    Document { source: ClaudeMediaSource },
+}
+
+impl ClaudeContentBlock {
+    pub fn text(text: &str) -> Self {
+        ClaudeContentBlock::Text { text: text.to_string() }
+    }
+
+    /// Image or PDF block for a file, or `None` when Claude cannot read the media type
+    pub fn media(media_type: &str, data: &[u8]) -> Option<Self> {
+        Self::with_source(
+            media_type,
+            ClaudeMediaSource::Base64 {
+                media_type: media_type.to_string(),
+                data: base64::engine::general_purpose::STANDARD.encode(data),
+            },
+        )
+    }
+
+    /// Image or PDF block read from `url`, or `None` when Claude cannot read the media type
+    pub fn media_url(media_type: &str, url: &str) -> Option<Self> {
+        Self::with_source(media_type, ClaudeMediaSource::Url { url: url.to_string() })
+    }
+
+    fn with_source(media_type: &str, source: ClaudeMediaSource) -> Option<Self> {
+        if VISION_IMAGE_TYPES.contains(&media_type) {
+            Some(ClaudeContentBlock::Image { source })
+        } else if media_type == "application/pdf" {
+            Some(ClaudeContentBlock::Document { source })
+        } else {
+            None
+        }
+    }
+}
+
+/// Message in a conversation whose turns carry content blocks
+#[derive(Debug, Serialize, Clone)]
+pub struct ClaudeContentMessage {
+    pub role: String, // "user" or "assistant"
+    pub content: Vec<ClaudeContentBlock>,
+}
+
+impl ClaudeContentMessage {
+    pub fn user(content: Vec<ClaudeContentBlock>) -> Self {
+        Self {
+            role: "user".to_string(),
+            content,
+        }
+    }
+
+    pub fn assistant(content: Vec<ClaudeContentBlock>) -> Self {
+        Self {
+            role: "assistant".to_string(),
+            content,
+        }
+    }
+}
+
+/// Request payload for conversations with content blocks
+#[derive(Debug, Serialize)]
+struct ClaudeContentRequest {
+    model: String,
+    max_tokens: u32,
+    temperature: Option<f32>,
+    messages: Vec<ClaudeContentMessage>,
+    system: Option<String>,
+    stream: Option<bool>,
+}
+
+/// Request payload for the token counting API
+#[derive(Debug, Serialize)]
+struct ClaudeCountTokensRequest<'a> {
+    model: &'a str,
+    messages: &'a [ClaudeMessage],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<&'a str>,
+}
+
 #[derive(Debug, Deserialize)]
+struct ClaudeCountTokensResponse {
+    input_tokens: u32,
+}
+
+/// Response from Claude AI API
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ClaudeResponse {
     pub id: String,
     pub r#type: String,
@@ -63,17 +276,34 @@ pub struct ClaudeResponse {
 }
 
 /// Content block in Claude response
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ClaudeContent {
     pub r#type: String,
     pub text: String,
 }
 
 /// Usage statistics from Claude API
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct ClaudeUsage {
+    /// Input tokens read outside the prompt cache
     pub input_tokens: u32,
     pub output_tokens: u32,
+    /// Input tokens written to the prompt cache
+    #[serde(default)]
+    pub cache_creation_input_tokens: u32,
+    /// Input tokens read from the prompt cache
+    #[serde(default)]
+    pub cache_read_input_tokens: u32,
+}
+
+impl ClaudeUsage {
+    /// Add the usage of another request, for operations spanning several
+    pub fn accumulate(&mut self, other: &ClaudeUsage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_input_tokens += other.cache_creation_input_tokens;
+        self.cache_read_input_tokens += other.cache_read_input_tokens;
+    }
 }
 
 /// Error response from Claude AI API
@@ -83,6 +313,177 @@ pub struct ClaudeError {
     pub message: String,
 }
 
+/// Error status returned by the messages API, after retries and fallbacks were used up
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClaudeStatusError {
+    pub status: reqwest::StatusCode,
+    pub body: String,
+}
+
+impl ClaudeStatusError {
+    /// Whether the request was refused by rate limits, including "overloaded" responses
+    pub fn is_rate_limited(&self) -> bool {
+        self.status == reqwest::StatusCode::TOO_MANY_REQUESTS || self.status.as_u16() == 529
+    }
+
+    /// The status error behind `error`, if any, looking through added context
+    pub fn find(error: &anyhow::Error) -> Option<&ClaudeStatusError> {
+        error.chain().find_map(|cause| cause.downcast_ref::<ClaudeStatusError>())
+    }
+}
+
+impl std::fmt::Display for ClaudeStatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Claude AI API error: {} - {}", self.status, self.body)
+    }
+}
+
+impl std::error::Error for ClaudeStatusError {}
+
+/// Incremental output of a streamed message
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClaudeStreamEvent {
+    /// Text appended to the assistant's reply
+    TextDelta(String),
+    /// Last event of the message, with why generation stopped and the final token usage
+    Stop {
+        stop_reason: Option<String>,
+        usage: ClaudeUsage,
+    },
+}
+
+/// Payload of a messages API stream event
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamPayload {
+    MessageStart { message: StreamMessageStart },
+    ContentBlockDelta { delta: StreamDelta },
+    MessageDelta {
+        delta: StreamMessageDelta,
+        #[serde(default)]
+        usage: Option<StreamDeltaUsage>,
+    },
+    MessageStop,
+    Error { error: ClaudeError },
+    /// `ping`, `content_block_start`, `content_block_stop` and event types added later
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamMessageStart {
+    model: String,
+    usage: ClaudeUsage,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamDelta {
+    TextDelta { text: String },
+    /// Tool input and other non-text deltas
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamMessageDelta {
+    stop_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamDeltaUsage {
+    output_tokens: u32,
+}
+
+/// Turns the messages API event stream into text deltas and a final stop event
+#[derive(Debug, Default)]
+struct StreamDecoder {
+    parser: SseParser,
+    model: String,
+    usage: ClaudeUsage,
+    stop_reason: Option<String>,
+    finished: bool,
+}
+
+impl StreamDecoder {
+    /// Events completed by `chunk`; events after `message_stop` are ignored
+    fn push(&mut self, chunk: &[u8]) -> Result<Vec<ClaudeStreamEvent>> {
+        let mut events = Vec::new();
+
+        for event in self.parser.push(chunk) {
+            if self.finished {
+                break;
+            }
+            let payload: StreamPayload = serde_json::from_str(&event.data)
+                .with_context(|| format!("Failed to parse Claude AI stream event '{}'", event.event))?;
+            match payload {
+                StreamPayload::MessageStart { message } => {
+                    self.model = message.model;
+                    self.usage = message.usage;
+                }
+                StreamPayload::ContentBlockDelta {
+                    delta: StreamDelta::TextDelta { text },
+                } => events.push(ClaudeStreamEvent::TextDelta(text)),
+                StreamPayload::MessageDelta { delta, usage } => {
+                    self.stop_reason = delta.stop_reason;
+                    if let Some(usage) = usage {
+                        self.usage.output_tokens = usage.output_tokens;
+                    }
+                }
+                StreamPayload::MessageStop => {
+                    self.finished = true;
+                    events.push(ClaudeStreamEvent::Stop {
+                        stop_reason: self.stop_reason.take(),
+                        usage: self.usage.clone(),
+                    });
+                }
+                StreamPayload::Error { error } => {
+                    bail!("Claude AI stream error: {} - {}", error.r#type, error.message);
+                }
+                StreamPayload::ContentBlockDelta { .. } | StreamPayload::Other => {}
+            }
+        }
+        Ok(events)
+    }
+}
+
+/// Streamed response from the messages API, read with [`ClaudeMessageStream::next_event`]
+#[derive(Debug)]
+pub struct ClaudeMessageStream {
+    response: reqwest::Response,
+    decoder: StreamDecoder,
+    pending: VecDeque<ClaudeStreamEvent>,
+}
+
+impl ClaudeMessageStream {
+    /// Next event, or `None` after the stop event has been returned
+    pub async fn next_event(&mut self) -> Result<Option<ClaudeStreamEvent>> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                if let ClaudeStreamEvent::Stop { usage, .. } = &event {
+                    tracing::info!(
+                        input_tokens = usage.input_tokens,
+                        output_tokens = usage.output_tokens,
+                        cache_read_input_tokens = usage.cache_read_input_tokens,
+                        cache_creation_input_tokens = usage.cache_creation_input_tokens,
+                        model = %self.decoder.model,
+                        "Finished streaming response from Claude AI"
+                    );
+                }
+                return Ok(Some(event));
+            }
+            if self.decoder.finished {
+                return Ok(None);
+            }
+
+            match self.response.chunk().await.context("Failed to read Claude AI stream")? {
+                Some(chunk) => self.pending.extend(self.decoder.push(&chunk)?),
+                None => bail!("Claude AI stream ended before the message stopped"),
+            }
+        }
+    }
+}
+
 /// Client for interacting with Claude AI API
 #[derive(Debug)]
 pub struct ClaudeAIClient {
@@ -101,118 +502,157 @@ impl ClaudeAIClient {
         Ok(Self { config, client })
     }
 
-    /// Create a new Claude AI client from environment variables
-    pub fn from_env() -> Result<Self> {
-        let api_key = std::env::var("CLAUDE_API_KEY")
-            .context("CLAUDE_API_KEY environment variable not set")?;
-        
-        let config = ClaudeAIConfig {
-            api_key,
-            base_url: std::env::var("CLAUDE_BASE_URL")
-                .unwrap_or_else(|_| "https://api.anthropic.com".to_string()),
-            default_model: std::env::var("CLAUDE_DEFAULT_MODEL")
-                .unwrap_or_else(|_| "claude-3-sonnet-20240229".to_string()),
-            timeout_seconds: std::env::var("CLAUDE_TIMEOUT_SECONDS")
-                .unwrap_or_else(|_| "60".to_string())
-                .parse()
-                .unwrap_or(60),
-            max_retries: std::env::var("CLAUDE_MAX_RETRIES")
-                .unwrap_or_else(|_| "3".to_string())
-                .parse()
-                .unwrap_or(3),
-        };
-
+    /// Create a new Claude AI client from settings
+    pub fn from_settings(settings: &Settings) -> Result<Self> {
+        let config = settings
+            .claude
+            .clone()
+            .context("Claude API key not configured (claude-api-key)")?;
         Self::new(config)
     }
 
     /// Send a message to Claude AI and get a response
     #[tracing::instrument(skip(self), fields(model = %request.model))]
     pub async fn send_message(&self, request: ClaudeRequest) -> Result<ClaudeResponse> {
-        let url = format!("{}/v1/messages", self.config.base_url);
-        
-        let mut headers = HashMap::new();
-        headers.insert("x-api-key", self.config.api_key.as_str());
-        headers.insert("anthropic-version", "2023-06-01");
-        headers.insert("content-type", "application/json");
+        self.post_messages(&request).await
+    }
+
+    /// Send a message and stream the reply as it is generated.
+    ///
+    /// Connection failures and error statuses are retried like `send_message`; once the
+    /// stream has started, errors are returned from [`ClaudeMessageStream::next_event`].
+    #[tracing::instrument(skip(self), fields(model = %request.model))]
+    pub async fn send_message_stream(&self, mut request: ClaudeRequest) -> Result<ClaudeMessageStream> {
+        request.stream = Some(true);
+        let timeout = std::time::Duration::from_secs(self.config.stream_timeout_seconds);
+        let response = self.send_with_retries(&request, timeout).await?;
+
+        Ok(ClaudeMessageStream {
+            response,
+            decoder: StreamDecoder::default(),
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// POST a request body to the messages API, retrying failures with backoff
+    async fn post_messages<T: Serialize>(&self, request: &T) -> Result<ClaudeResponse> {
+        let timeout = std::time::Duration::from_secs(self.config.timeout_seconds);
+        let resp = self.send_with_retries(request, timeout).await?;
+        let claude_response: ClaudeResponse = resp
+            .json()
+            .await
+            .context("Failed to parse Claude AI response")?;
+
+        tracing::info!(
+            input_tokens = claude_response.usage.input_tokens,
+            output_tokens = claude_response.usage.output_tokens,
+            cache_read_input_tokens = claude_response.usage.cache_read_input_tokens,
+            cache_creation_input_tokens = claude_response.usage.cache_creation_input_tokens,
+            model = %claude_response.model,
+            "Successfully received response from Claude AI"
+        );
+
+        Ok(claude_response)
+    }
 
-        let mut attempt = 0;
+    /// POST to the messages API, moving down the model fallback chain until a success status is returned.
+    ///
+    /// Each model gets `max_retries` attempts with backoff; rate limits skip to the next model straight
+    /// away. Fallback models use their own timeout for non-streamed requests and cap `max_tokens`.
+    async fn send_with_retries<T: Serialize>(
+        &self,
+        request: &T,
+        timeout: std::time::Duration,
+    ) -> Result<reqwest::Response> {
+        let url = format!("{}/v1/messages", self.config.base_url);
+        let mut body = serde_json::to_value(request).context("Failed to serialize Claude AI request")?;
+        let requested_model = body["model"].as_str().unwrap_or(self.config.default_model.as_str()).to_string();
+        let requested_max_tokens = body["max_tokens"].as_u64().map(|t| t as u32);
+        let streaming = body["stream"].as_bool().unwrap_or(false);
+        let chain = model_chain(&requested_model, &self.config.fallback_models);
+        let max_attempts = self.config.max_retries.max(1);
         let mut last_error = None;
 
-        while attempt < self.config.max_retries {
-            tracing::debug!(
-                attempt = attempt + 1,
-                max_retries = self.config.max_retries,
-                "Sending request to Claude AI"
-            );
-
-            let response = self
-                .client
-                .post(&url)
-                .header("x-api-key", &self.config.api_key)
-                .header("anthropic-version", "2023-06-01")
-                .header("content-type", "application/json")
-                .json(&request)
-                .send()
-                .await;
-
-            match response {
-                Ok(resp) if resp.status().is_success() => {
-                    let claude_response: ClaudeResponse = resp
-                        .json()
-                        .await
-                        .context("Failed to parse Claude AI response")?;
-                    
-                    tracing::info!(
-                        input_tokens = claude_response.usage.input_tokens,
-                        output_tokens = claude_response.usage.output_tokens,
-                        model = %claude_response.model,
-                        "Successfully received response from Claude AI"
-                    );
-                    
-                    return Ok(claude_response);
-                }
-                Ok(resp) => {
-                    let status = resp.status();
-                    let error_text = resp
-                        .text()
-                        .await
-                        .unwrap_or_else(|_| "Unknown error".to_string());
-                    
-                    let error = anyhow::anyhow!(
-                        "Claude AI API error: {} - {}",
-                        status,
-                        error_text
-                    );
-                    
-                    tracing::error!(
-                        status = %status,
-                        error = %error_text,
-                        attempt = attempt + 1,
-                        "Claude AI API returned error"
-                    );
-                    
-                    last_error = Some(error);
-                }
-                Err(e) => {
-                    let error = anyhow::anyhow!("Request failed: {}", e);
-                    tracing::error!(
-                        error = %e,
-                        attempt = attempt + 1,
-                        "Failed to send request to Claude AI"
-                    );
-                    last_error = Some(error);
-                }
+        for (position, route) in chain.iter().enumerate() {
+            let has_next_model = position + 1 < chain.len();
+            if position > 0 {
+                tracing::warn!(model = %route.model, "Falling back to another Claude model");
+            }
+            body["model"] = serde_json::Value::from(route.model.as_str());
+            if let Some(max_tokens) = requested_max_tokens {
+                body["max_tokens"] = serde_json::Value::from(route.capped_max_tokens(max_tokens));
             }
+            let timeout = match route.timeout {
+                Some(route_timeout) if !streaming => route_timeout,
+                _ => timeout,
+            };
 
-            attempt += 1;
-            
-            if attempt < self.config.max_retries {
-                let delay = std::time::Duration::from_millis(1000 * 2_u64.pow(attempt - 1));
+            for attempt in 1..=max_attempts {
                 tracing::debug!(
-                    delay_ms = delay.as_millis(),
-                    "Retrying request after delay"
+                    model = %route.model,
+                    attempt,
+                    max_retries = max_attempts,
+                    "Sending request to Claude AI"
                 );
-                tokio::time::sleep(delay).await;
+
+                let response = self
+                    .client
+                    .post(&url)
+                    .header("x-api-key", &self.config.api_key)
+                    .header("anthropic-version", "2023-06-01")
+                    .header("content-type", "application/json")
+                    .timeout(timeout)
+                    .json(&body)
+                    .send()
+                    .await;
+
+                let (status, retry_after) = match response {
+                    Ok(resp) if resp.status().is_success() => return Ok(resp),
+                    Ok(resp) => {
+                        let status = resp.status();
+                        let retry_after = parse_retry_after(
+                            resp.headers().get(reqwest::header::RETRY_AFTER).and_then(|v| v.to_str().ok()),
+                        );
+                        let error_text = resp
+                            .text()
+                            .await
+                            .unwrap_or_else(|_| "Unknown error".to_string());
+                        tracing::error!(
+                            model = %route.model,
+                            status = %status,
+                            error = %error_text,
+                            attempt,
+                            "Claude AI API returned error"
+                        );
+                        last_error = Some(anyhow::Error::new(ClaudeStatusError {
+                            status,
+                            body: error_text,
+                        }));
+                        (Some(status), retry_after)
+                    }
+                    Err(e) => {
+                        tracing::error!(
+                            model = %route.model,
+                            error = %e,
+                            attempt,
+                            "Failed to send request to Claude AI"
+                        );
+                        last_error = Some(anyhow::anyhow!("Request failed: {}", e));
+                        (None, None)
+                    }
+                };
+
+                match retry_decision(status, attempt, max_attempts, has_next_model) {
+                    RetryDecision::Retry => {
+                        let delay = backoff_delay(attempt, retry_after);
+                        tracing::debug!(delay_ms = delay.as_millis(), "Retrying request after delay");
+                        tokio::time::sleep(delay).await;
+                    }
+                    RetryDecision::NextModel => break,
+                    RetryDecision::Fail => {
+                        return Err(last_error.unwrap_or_else(|| anyhow::anyhow!("Claude AI request failed")));
+                    }
+                }
             }
         }
 
@@ -234,7 +674,7 @@ impl ClaudeAIClient {
                 role: "user".to_string(),
                 content: message.to_string(),
             }],
-            system: system_prompt.map(|s| s.to_string()),
+            system: system_prompt.map(ClaudeSystem::from),
             stop_sequences: None,
             stream: Some(false),
         };
@@ -258,17 +698,84 @@ impl ClaudeAIClient {
         max_tokens: Option<u32>,
         temperature: Option<f32>,
     ) -> Result<ClaudeResponse> {
-        let request = ClaudeRequest {
+        let request = self.conversation_request(messages, system_prompt, max_tokens, temperature);
+        self.send_message(request).await
+    }
+
+    /// Request `send_conversation` would send, for callers that send it another way, such as through a cache
+    pub fn conversation_request(
+        &self,
+        messages: Vec<ClaudeMessage>,
+        system_prompt: Option<&str>,
+        max_tokens: Option<u32>,
+        temperature: Option<f32>,
+    ) -> ClaudeRequest {
+        ClaudeRequest {
             model: self.config.default_model.clone(),
             max_tokens: max_tokens.unwrap_or(4096),
             temperature,
             messages,
-            system: system_prompt.map(|s| s.to_string()),
+            system: system_prompt.map(ClaudeSystem::from),
             stop_sequences: None,
             stream: Some(false),
+        }
+    }
+
+    /// Send a conversation whose messages carry content blocks, such as images or PDFs
+    #[tracing::instrument(skip(self, messages), fields(message_count = messages.len()))]
+    pub async fn send_content_conversation(
+        &self,
+        messages: Vec<ClaudeContentMessage>,
+        system_prompt: Option<&str>,
+        max_tokens: Option<u32>,
+        temperature: Option<f32>,
+    ) -> Result<ClaudeResponse> {
+        let request = ClaudeContentRequest {
+            model: self.config.default_model.clone(),
+            max_tokens: max_tokens.unwrap_or(4096),
+            temperature,
+            messages,
+            system: system_prompt.map(|s| s.to_string()),
+            stream: Some(false),
         };
 
-        self.send_message(request).await
+        self.post_messages(&request).await
+    }
+
+    /// Input tokens a conversation would use with `model`, without generating a reply
+    #[tracing::instrument(skip(self, messages, system_prompt), fields(message_count = messages.len()))]
+    pub async fn count_tokens(
+        &self,
+        model: &str,
+        messages: &[ClaudeMessage],
+        system_prompt: Option<&str>,
+    ) -> Result<u32> {
+        let request = ClaudeCountTokensRequest {
+            model,
+            messages,
+            system: system_prompt,
+        };
+        let response = self
+            .client
+            .post(format!("{}/v1/messages/count_tokens", self.config.base_url))
+            .header("x-api-key", &self.config.api_key)
+            .header("anthropic-version", "2023-06-01")
+            .header("content-type", "application/json")
+            .json(&request)
+            .send()
+            .await
+            .context("Failed to send Claude AI token count request")?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            bail!("Claude AI API error: {} - {}", status, error_text);
+        }
+        let counted: ClaudeCountTokensResponse = response
+            .json()
+            .await
+            .context("Failed to parse Claude AI token count")?;
+        Ok(counted.input_tokens)
     }
 
     /// Get the current configuration
@@ -305,7 +812,9 @@ mod tests {
         assert_eq!(config.base_url, "https://api.anthropic.com");
         assert_eq!(config.default_model, "claude-3-sonnet-20240229");
         assert_eq!(config.timeout_seconds, 60);
+        assert_eq!(config.stream_timeout_seconds, 300);
         assert_eq!(config.max_retries, 3);
+        assert!(config.fallback_models.is_empty());
     }
 
     #[test]
@@ -319,6 +828,109 @@ mod tests {
         assert_eq!(assistant_msg.content, "Hello! How can I help?");
     }
 
+    #[test]
+    fn test_media_blocks() {
+        let image = ClaudeContentBlock::media("image/png", b"png").unwrap();
+        let json = serde_json::to_value(&image).unwrap();
+        assert_eq!(json["type"], "image");
+        assert_eq!(json["source"]["type"], "base64");
+        assert_eq!(json["source"]["media_type"], "image/png");
+        assert_eq!(json["source"]["data"], "cG5n");
+
+        let pdf = ClaudeContentBlock::media("application/pdf", b"%PDF").unwrap();
+        assert_eq!(serde_json::to_value(&pdf).unwrap()["type"], "document");
+        assert!(ClaudeContentBlock::media("image/heic", b"heic").is_none());
+    }
+
+    #[test]
+    fn test_media_url_blocks() {
+        let url = "https://bucket.s3.amazonaws.com/receipts/r.jpg?X-Amz-Signature=abc";
+        let image = ClaudeContentBlock::media_url("image/jpeg", url).unwrap();
+        let json = serde_json::to_value(&image).unwrap();
+        assert_eq!(json["type"], "image");
+        assert_eq!(json["source"], serde_json::json!({"type": "url", "url": url}));
+
+        let pdf = ClaudeContentBlock::media_url("application/pdf", url).unwrap();
+        assert_eq!(serde_json::to_value(&pdf).unwrap()["type"], "document");
+        assert!(ClaudeContentBlock::media_url("text/csv", url).is_none());
+
+        let message = ClaudeContentMessage::user(vec![image, ClaudeContentBlock::text("Read this receipt.")]);
+        let json = serde_json::to_value(&message).unwrap();
+        assert_eq!(json["role"], "user");
+        assert_eq!(json["content"][1], serde_json::json!({"type": "text", "text": "Read this receipt."}));
+    }
+
+    #[test]
+    fn test_cached_system_prompt_serializes_as_blocks() {
+        let client = ClaudeAIClient::new(ClaudeAIConfig::default()).unwrap();
+        let messages = vec![ClaudeAIClient::user_message("Hi")];
+        let request = client.conversation_request(messages, Some("Taxonomy"), None, None);
+        assert_eq!(serde_json::to_value(&request).unwrap()["system"], "Taxonomy");
+
+        let cached = serde_json::to_value(request.with_cached_system()).unwrap();
+        assert_eq!(
+            cached["system"],
+            serde_json::json!([{"type": "text", "text": "Taxonomy", "cache_control": {"type": "ephemeral"}}])
+        );
+    }
+
+    #[test]
+    fn test_stream_decoder_emits_text_and_stop() {
+        let stream = concat!(
+            "event: message_start\n",
+            "data: {\"type\":\"message_start\",\"message\":{\"id\":\"msg_1\",\"type\":\"message\",\"role\":\"assistant\",\"content\":[],\"model\":\"claude\",\"stop_reason\":null,\"usage\":{\"input_tokens\":12,\"output_tokens\":1,\"cache_read_input_tokens\":900}}}\n\n",
+            "event: content_block_start\n",
+            "data: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"text\",\"text\":\"\"}}\n\n",
+            "event: ping\n",
+            "data: {\"type\":\"ping\"}\n\n",
+            "event: content_block_delta\n",
+            "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hello\"}}\n\n",
+            "event: content_block_delta\n",
+            "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\" there\"}}\n\n",
+            "event: content_block_stop\n",
+            "data: {\"type\":\"content_block_stop\",\"index\":0}\n\n",
+            "event: message_delta\n",
+            "data: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"end_turn\",\"stop_sequence\":null},\"usage\":{\"output_tokens\":5}}\n\n",
+            "event: message_stop\n",
+            "data: {\"type\":\"message_stop\"}\n\n",
+        );
+
+        // Feed a few bytes at a time, as the network might deliver them
+        let mut decoder = StreamDecoder::default();
+        let mut events = Vec::new();
+        for chunk in stream.as_bytes().chunks(7) {
+            events.extend(decoder.push(chunk).unwrap());
+        }
+
+        assert_eq!(
+            events,
+            vec![
+                ClaudeStreamEvent::TextDelta("Hello".to_string()),
+                ClaudeStreamEvent::TextDelta(" there".to_string()),
+                ClaudeStreamEvent::Stop {
+                    stop_reason: Some("end_turn".to_string()),
+                    usage: ClaudeUsage {
+                        input_tokens: 12,
+                        output_tokens: 5,
+                        cache_creation_input_tokens: 0,
+                        cache_read_input_tokens: 900,
+                    },
+                },
+            ]
+        );
+        assert!(decoder.finished);
+        assert_eq!(decoder.model, "claude");
+    }
+
+    #[test]
+    fn test_stream_decoder_surfaces_errors() {
+        let mut decoder = StreamDecoder::default();
+        let error = decoder
+            .push(b"event: error\ndata: {\"type\":\"error\",\"error\":{\"type\":\"overloaded_error\",\"message\":\"Overloaded\"}}\n\n")
+            .unwrap_err();
+        assert!(error.to_string().contains("overloaded_error"));
+    }
+
     #[tokio::test]
     async fn test_client_creation() {
         let config = ClaudeAIConfig {
@@ -0,0 +1,163 @@
+// Structured JSON replies from Claude, parsed into caller types and repaired when malformed
+use crate::adapter::claude_ai::{ClaudeAIClient, ClaudeMessage, ClaudeUsage};
+use anyhow::{anyhow, Result};
+use serde::de::DeserializeOwned;
+use tracing::{debug, instrument, warn};
+
+/// Prefilled start of the assistant turn, forcing the reply to continue a JSON object
+const JSON_PREFILL: &str = "{";
+
+/// Follow-up requests asking the model to fix an unusable reply
+pub const DEFAULT_JSON_REPAIRS: u32 = 2;
+
+/// Appended to the caller's system prompt
+const JSON_INSTRUCTION: &str = "Reply with a single JSON object only, without any text before or after it.";
+
+/// Parsed reply with what it took to get it
+#[derive(Debug, Clone)]
+pub struct JsonReply<T> {
+    pub value: T,
+    /// Model that generated the accepted reply
+    pub model: String,
+    /// Tokens used by all attempts, including rejected ones
+    pub usage: ClaudeUsage,
+    /// Requests sent, 1 when the first reply was accepted
+    pub attempts: u32,
+}
+
+/// Parse a reply continuing the prefill and check it with `validate`.
+///
+/// Text after the JSON object is ignored. The error is phrased for the model, as it is sent back
+/// in the repair prompt.
+fn parse_json_reply<T, F>(reply: &str, validate: &F) -> std::result::Result<T, String>
+where
+    T: DeserializeOwned,
+    F: Fn(&T) -> std::result::Result<(), String>,
+{
+    let json = format!("{}{}", JSON_PREFILL, reply.trim_start());
+    let value = serde_json::Deserializer::from_str(&json)
+        .into_iter::<T>()
+        .next()
+        .ok_or_else(|| "The reply was empty".to_string())?
+        .map_err(|e| format!("The reply is not valid JSON of the expected shape: {}", e))?;
+    validate(&value)?;
+    Ok(value)
+}
+
+/// Conversation asking for a corrected reply: the rejected reply, the problem with it, and a fresh prefill
+fn repair_messages(mut messages: Vec<ClaudeMessage>, rejected: &str, problem: &str) -> Vec<ClaudeMessage> {
+    messages.pop();
+    messages.push(ClaudeAIClient::assistant_message(&format!("{}{}", JSON_PREFILL, rejected.trim_start())));
+    messages.push(ClaudeAIClient::user_message(&format!(
+        "{} Reply again with the corrected JSON object only.",
+        problem
+    )));
+    messages.push(ClaudeAIClient::assistant_message(JSON_PREFILL));
+    messages
+}
+
+impl ClaudeAIClient {
+    /// Send a conversation whose reply is a JSON object, deserialized into `T` and checked with `validate`.
+    ///
+    /// `messages` must end with a user turn. A reply that doesn't parse or fails validation is sent back
+    /// with the problem, up to `max_repairs` times, before giving up with the last problem as the error.
+    #[instrument(skip(self, messages, system_prompt, validate), fields(message_count = messages.len()))]
+    pub async fn send_json<T, F>(
+        &self,
+        mut messages: Vec<ClaudeMessage>,
+        system_prompt: Option<&str>,
+        max_tokens: Option<u32>,
+        max_repairs: u32,
+        validate: F,
+    ) -> Result<JsonReply<T>>
+    where
+        T: DeserializeOwned + Send,
+        F: Fn(&T) -> std::result::Result<(), String> + Sync,
+    {
+        let system = match system_prompt {
+            Some(prompt) => format!("{}\n\n{}", prompt, JSON_INSTRUCTION),
+            None => JSON_INSTRUCTION.to_string(),
+        };
+        messages.push(ClaudeAIClient::assistant_message(JSON_PREFILL));
+
+        let mut usage = ClaudeUsage::default();
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let response = self
+                .send_conversation(messages.clone(), Some(&system), max_tokens, Some(0.0))
+                .await?;
+            usage.accumulate(&response.usage);
+
+            let reply: String = response.content.iter().map(|block| block.text.as_str()).collect();
+            match parse_json_reply(&reply, &validate) {
+                Ok(value) => {
+                    debug!(attempts, "Structured reply accepted");
+                    return Ok(JsonReply {
+                        value,
+                        model: response.model,
+                        usage,
+                        attempts,
+                    });
+                }
+                Err(problem) if attempts <= max_repairs => {
+                    warn!(attempts, problem = %problem, "Structured reply rejected; asking for a repair");
+                    messages = repair_messages(messages, &reply, &problem);
+                }
+                Err(problem) => {
+                    return Err(anyhow!("No usable JSON reply after {} attempts: {}", attempts, problem));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Score {
+        score: i32,
+    }
+
+    fn in_range(score: &Score) -> std::result::Result<(), String> {
+        if (0..=10).contains(&score.score) {
+            Ok(())
+        } else {
+            Err(format!("score must be between 0 and 10, got {}", score.score))
+        }
+    }
+
+    #[test]
+    fn test_parse_json_reply() {
+        assert_eq!(parse_json_reply(r#""score": 7}"#, &in_range), Ok(Score { score: 7 }));
+        assert_eq!(
+            parse_json_reply(" \"score\": 3}\nHope this helps!", &in_range),
+            Ok(Score { score: 3 })
+        );
+        assert!(parse_json_reply::<Score, _>(r#""score": "high"}"#, &in_range)
+            .unwrap_err()
+            .contains("not valid JSON"));
+        assert_eq!(
+            parse_json_reply::<Score, _>(r#""score": 12}"#, &in_range),
+            Err("score must be between 0 and 10, got 12".to_string())
+        );
+    }
+
+    #[test]
+    fn test_repair_messages_replace_prefill() {
+        let messages = vec![
+            ClaudeAIClient::user_message("Rate this"),
+            ClaudeAIClient::assistant_message(JSON_PREFILL),
+        ];
+        let repaired = repair_messages(messages, r#""score": 12}"#, "score must be between 0 and 10.");
+
+        let roles: Vec<&str> = repaired.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, ["user", "assistant", "user", "assistant"]);
+        assert_eq!(repaired[1].content, r#"{"score": 12}"#);
+        assert!(repaired[2].content.starts_with("score must be between 0 and 10."));
+        assert_eq!(repaired[3].content, JSON_PREFILL);
+    }
+}
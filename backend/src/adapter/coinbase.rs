@@ -0,0 +1,615 @@
+// Coinbase exchange accounts linked with a read-only API key, plus public spot prices for valuing crypto holdings
+use crate::adapter::bank_data::BankDataProvider;
+use crate::adapter::plaid::{
+    AccountBalances, AccountIdentity, BankAccount, BankTransaction, Institution, Liability, LinkTokenRequest,
+    LinkTokenResponse, PublicTokenExchangeRequest, PublicTokenExchangeResponse, TransactionSyncRequest,
+    TransactionSyncResponse,
+};
+use crate::settings::SettingValues;
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::{Client, Method};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{debug, info, instrument, warn};
+
+/// Name stored on items linked through Coinbase
+pub const COINBASE_PROVIDER: &str = "coinbase";
+
+/// Institution ID reported for Coinbase accounts
+const COINBASE_INSTITUTION_ID: &str = "coinbase";
+
+/// Largest page the v2 API returns
+const PAGE_LIMIT: u32 = 100;
+
+/// Transactions this far before the sync cursor are fetched again, so pending sends and
+/// trades that complete later are updated
+const SYNC_OVERLAP_DAYS: i64 = 7;
+
+/// Configuration for the Coinbase client
+#[derive(Debug, Clone)]
+pub struct CoinbaseConfig {
+    /// Coinbase v2 API base URL
+    pub base_url: String,
+    /// Currency spot prices are quoted in
+    pub quote_currency: String,
+    /// How long a fetched spot price is served before refetching
+    pub price_cache_ttl_seconds: u64,
+    /// Request timeout in seconds
+    pub timeout_seconds: u64,
+}
+
+impl Default for CoinbaseConfig {
+    fn default() -> Self {
+        Self {
+            base_url: "https://api.coinbase.com".to_string(),
+            quote_currency: "USD".to_string(),
+            price_cache_ttl_seconds: 60,
+            timeout_seconds: 10,
+        }
+    }
+}
+
+impl CoinbaseConfig {
+    /// Read from settings
+    /// - COINBASE_API_URL: API base URL (default: https://api.coinbase.com)
+    /// - COINBASE_PRICE_CACHE_TTL_SECONDS: how long a spot price is served from cache (default: 60)
+    pub fn from_values(values: &mut SettingValues) -> Self {
+        let defaults = Self::default();
+        Self {
+            base_url: values.or("coinbase-api-url", &defaults.base_url),
+            price_cache_ttl_seconds: values
+                .parsed("coinbase-price-cache-ttl-seconds", defaults.price_cache_ttl_seconds),
+            ..defaults
+        }
+    }
+}
+
+/// API key pair a user creates in Coinbase with the `wallet:accounts:read`,
+/// `wallet:transactions:read` and `wallet:user:read` permissions.
+///
+/// Stored as the item's encrypted access token in `key:secret` form.
+#[derive(Clone, PartialEq, Eq)]
+pub struct CoinbaseCredentials {
+    pub api_key: String,
+    pub api_secret: String,
+}
+
+impl std::fmt::Debug for CoinbaseCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CoinbaseCredentials")
+            .field("api_key", &self.api_key)
+            .field("api_secret", &"<redacted>")
+            .finish()
+    }
+}
+
+impl CoinbaseCredentials {
+    pub fn encode(&self) -> String {
+        format!("{}:{}", self.api_key, self.api_secret)
+    }
+
+    pub fn decode(token: &str) -> Result<Self> {
+        let (api_key, api_secret) = token
+            .split_once(':')
+            .filter(|(key, secret)| !key.is_empty() && !secret.is_empty())
+            .ok_or_else(|| anyhow!("Coinbase credentials must be an API key and secret"))?;
+        Ok(Self {
+            api_key: api_key.to_string(),
+            api_secret: api_secret.to_string(),
+        })
+    }
+
+    /// `CB-ACCESS-SIGN`: hex HMAC-SHA256 of timestamp, method, request path and body
+    fn sign(&self, timestamp: i64, method: &Method, path: &str, body: &str) -> Result<String> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.api_secret.as_bytes())
+            .map_err(|e| anyhow!("Invalid Coinbase API secret: {}", e))?;
+        mac.update(format!("{}{}{}{}", timestamp, method.as_str(), path, body).as_bytes());
+        Ok(format!("{:x}", mac.finalize().into_bytes()))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Envelope<T> {
+    data: T,
+    #[serde(default)]
+    pagination: Option<Pagination>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Pagination {
+    next_uri: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CoinbaseUser {
+    id: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Money {
+    amount: String,
+    currency: String,
+}
+
+impl Money {
+    fn value(&self) -> Result<f64> {
+        self.amount
+            .parse()
+            .with_context(|| format!("Invalid Coinbase amount '{}'", self.amount))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CoinbaseCurrency {
+    code: String,
+    #[serde(rename = "type", default)]
+    currency_type: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CoinbaseAccount {
+    id: String,
+    name: String,
+    #[serde(rename = "type")]
+    account_type: String,
+    currency: CoinbaseCurrency,
+    balance: Money,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct CoinbaseTransactionDetails {
+    title: Option<String>,
+    subtitle: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CoinbaseTransaction {
+    id: String,
+    #[serde(rename = "type")]
+    transaction_type: String,
+    status: String,
+    amount: Money,
+    #[serde(default)]
+    description: Option<String>,
+    created_at: DateTime<Utc>,
+    #[serde(default)]
+    details: CoinbaseTransactionDetails,
+}
+
+#[derive(Debug, Deserialize)]
+struct SpotPrice {
+    amount: String,
+}
+
+/// Fiat wallets carry an ISO code; crypto assets go in the unofficial code like Plaid's crypto accounts
+fn account_from_coinbase(account: &CoinbaseAccount, item_id: &str) -> Result<BankAccount> {
+    let is_fiat = account.currency.currency_type.as_deref() == Some("fiat") || account.account_type == "fiat";
+    let code = account.currency.code.to_ascii_uppercase();
+    Ok(BankAccount {
+        account_id: account.id.clone(),
+        item_id: item_id.to_string(),
+        mask: None,
+        name: account.name.clone(),
+        official_name: None,
+        account_type: if is_fiat { "depository" } else { "investment" }.to_string(),
+        account_subtype: Some(if is_fiat { "cash management" } else { "crypto exchange" }.to_string()),
+        balances: AccountBalances {
+            available: Some(account.balance.value()?),
+            current: Some(account.balance.value()?),
+            limit: None,
+            iso_currency_code: is_fiat.then(|| code.clone()),
+            unofficial_currency_code: (!is_fiat).then_some(code),
+        },
+        institution_id: Some(COINBASE_INSTITUTION_ID.to_string()),
+        institution_name: Some("Coinbase".to_string()),
+    })
+}
+
+/// Coinbase amounts are positive when funds arrive; stored transactions follow Plaid's
+/// convention of positive amounts leaving the account
+fn transaction_from_coinbase(
+    transaction: &CoinbaseTransaction,
+    account_id: &str,
+    fiat: bool,
+) -> Result<BankTransaction> {
+    let code = transaction.amount.currency.to_ascii_uppercase();
+    let name = transaction
+        .details
+        .title
+        .clone()
+        .or_else(|| transaction.description.clone())
+        .unwrap_or_else(|| transaction.transaction_type.replace('_', " "));
+    let category = match transaction.transaction_type.as_str() {
+        "buy" | "sell" | "trade" | "advanced_trade_fill" => "Crypto Trade",
+        "send" | "transfer" | "request" => "Transfer",
+        "staking_reward" | "interest" | "inflation_reward" => "Interest",
+        "fiat_deposit" | "fiat_withdrawal" | "exchange_deposit" | "exchange_withdrawal" => "Transfer",
+        _ => "Crypto",
+    };
+
+    Ok(BankTransaction {
+        transaction_id: transaction.id.clone(),
+        account_id: account_id.to_string(),
+        amount: -transaction.amount.value()?,
+        iso_currency_code: fiat.then(|| code.clone()),
+        unofficial_currency_code: (!fiat).then_some(code),
+        category: vec![category.to_string()],
+        category_id: None,
+        check_number: None,
+        date: transaction.created_at.format("%Y-%m-%d").to_string(),
+        datetime: Some(transaction.created_at),
+        authorized_date: None,
+        authorized_datetime: None,
+        location: None,
+        name,
+        merchant_name: None,
+        original_description: transaction.details.subtitle.clone(),
+        payment_meta: None,
+        pending: transaction.status != "completed",
+        pending_transaction_id: None,
+        account_owner: None,
+        transaction_type: "special".to_string(),
+        transaction_code: None,
+    })
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CachedPrice {
+    price: f64,
+    fetched_at: DateTime<Utc>,
+}
+
+/// Coinbase v2 API client: signed account and transaction reads per linked item, and
+/// unauthenticated spot prices shared across users
+#[derive(Debug, Clone)]
+pub struct CoinbaseClient {
+    config: CoinbaseConfig,
+    client: Client,
+    /// Spot prices by asset symbol, quoted in `config.quote_currency`
+    prices: Arc<RwLock<HashMap<String, CachedPrice>>>,
+}
+
+impl CoinbaseClient {
+    pub fn new(config: CoinbaseConfig) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(config.timeout_seconds))
+            .build()
+            .context("Failed to create HTTP client")?;
+
+        Ok(Self {
+            config,
+            client,
+            prices: Arc::new(RwLock::new(HashMap::new())),
+        })
+    }
+
+    /// Currency `spot_price` quotes in
+    pub fn quote_currency(&self) -> &str {
+        &self.config.quote_currency
+    }
+
+    /// Current spot price of one unit of `symbol` in the quote currency, cached briefly
+    #[instrument(skip(self))]
+    pub async fn spot_price(&self, symbol: &str) -> Result<f64> {
+        let symbol = symbol.to_ascii_uppercase();
+        let ttl = chrono::Duration::seconds(self.config.price_cache_ttl_seconds as i64);
+        if let Some(cached) = self.prices.read().await.get(&symbol) {
+            if Utc::now() - cached.fetched_at < ttl {
+                return Ok(cached.price);
+            }
+        }
+
+        let path = format!("/v2/prices/{}-{}/spot", symbol, self.config.quote_currency);
+        let response = self
+            .client
+            .get(format!("{}{}", self.config.base_url, path))
+            .send()
+            .await
+            .context("Failed to request Coinbase spot price")?;
+        if !response.status().is_success() {
+            bail!("Coinbase has no {} price for {} (HTTP {})", self.config.quote_currency, symbol, response.status());
+        }
+        let envelope: Envelope<SpotPrice> = response.json().await.context("Failed to parse Coinbase spot price")?;
+        let price: f64 = envelope
+            .data
+            .amount
+            .parse()
+            .with_context(|| format!("Invalid Coinbase price '{}'", envelope.data.amount))?;
+        if price <= 0.0 {
+            bail!("Coinbase returned a non-positive price for {}", symbol);
+        }
+
+        self.prices.write().await.insert(
+            symbol,
+            CachedPrice {
+                price,
+                fetched_at: Utc::now(),
+            },
+        );
+        Ok(price)
+    }
+
+    /// Signed GET of a v2 path (with query string) under `credentials`
+    async fn get<T: DeserializeOwned>(&self, credentials: &CoinbaseCredentials, path: &str) -> Result<Envelope<T>> {
+        let timestamp = Utc::now().timestamp();
+        let signature = credentials.sign(timestamp, &Method::GET, path, "")?;
+        debug!(path = %path, "Calling Coinbase API");
+
+        let response = self
+            .client
+            .get(format!("{}{}", self.config.base_url, path))
+            .header("CB-ACCESS-KEY", &credentials.api_key)
+            .header("CB-ACCESS-SIGN", signature)
+            .header("CB-ACCESS-TIMESTAMP", timestamp.to_string())
+            .header("CB-VERSION", "2024-01-01")
+            .send()
+            .await
+            .context("Failed to call Coinbase API")?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+            bail!("Coinbase rejected the API credentials (INVALID_ACCESS_TOKEN, HTTP {})", status);
+        }
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            bail!("Coinbase API rate limit exceeded (RATE_LIMIT_EXCEEDED)");
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            bail!("Coinbase API returned HTTP {}: {}", status, body);
+        }
+        response.json().await.context("Failed to parse Coinbase response")
+    }
+
+    /// Every page of a paginated v2 listing
+    async fn get_all<T: DeserializeOwned>(&self, credentials: &CoinbaseCredentials, path: &str) -> Result<Vec<T>> {
+        let mut items = Vec::new();
+        let mut next = Some(path.to_string());
+        while let Some(path) = next {
+            let page: Envelope<Vec<T>> = self.get(credentials, &path).await?;
+            items.extend(page.data);
+            next = page.pagination.and_then(|p| p.next_uri).filter(|uri| !uri.is_empty());
+        }
+        Ok(items)
+    }
+
+    async fn accounts(&self, credentials: &CoinbaseCredentials) -> Result<Vec<CoinbaseAccount>> {
+        self.get_all(credentials, &format!("/v2/accounts?limit={}", PAGE_LIMIT)).await
+    }
+
+    /// An account's transactions created at or after `since`, newest first
+    async fn account_transactions(
+        &self,
+        credentials: &CoinbaseCredentials,
+        account_id: &str,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<Vec<CoinbaseTransaction>> {
+        let mut transactions = Vec::new();
+        let mut next = Some(format!("/v2/accounts/{}/transactions?limit={}&order=desc", account_id, PAGE_LIMIT));
+        while let Some(path) = next {
+            let page: Envelope<Vec<CoinbaseTransaction>> = self.get(credentials, &path).await?;
+            let reached_since = since.is_some_and(|since| page.data.iter().any(|t| t.created_at < since));
+            transactions.extend(
+                page.data
+                    .into_iter()
+                    .filter(|t| since.map_or(true, |since| t.created_at >= since)),
+            );
+            next = page
+                .pagination
+                .and_then(|p| p.next_uri)
+                .filter(|uri| !uri.is_empty() && !reached_since);
+        }
+        Ok(transactions)
+    }
+
+    /// Transactions of every account created at or after `since`
+    async fn transactions_since(
+        &self,
+        credentials: &CoinbaseCredentials,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<Vec<BankTransaction>> {
+        let mut transactions = Vec::new();
+        for account in self.accounts(credentials).await? {
+            let fiat = account.currency.currency_type.as_deref() == Some("fiat") || account.account_type == "fiat";
+            for transaction in self.account_transactions(credentials, &account.id, since).await? {
+                transactions.push(transaction_from_coinbase(&transaction, &account.id, fiat)?);
+            }
+        }
+        Ok(transactions)
+    }
+}
+
+#[async_trait]
+impl BankDataProvider for CoinbaseClient {
+    fn name(&self) -> &'static str {
+        COINBASE_PROVIDER
+    }
+
+    async fn create_link_token(&self, _request: LinkTokenRequest) -> Result<LinkTokenResponse> {
+        bail!("Coinbase accounts are linked with an API key, not a link token")
+    }
+
+    /// `request.public_token` carries the API key pair; it is verified and becomes the item credential
+    #[instrument(skip(self, request))]
+    async fn exchange_public_token(&self, request: PublicTokenExchangeRequest) -> Result<PublicTokenExchangeResponse> {
+        let credentials = CoinbaseCredentials::decode(&request.public_token)?;
+        let user: Envelope<CoinbaseUser> = self.get(&credentials, "/v2/user").await?;
+
+        info!("Coinbase API key verified");
+        Ok(PublicTokenExchangeResponse {
+            access_token: credentials.encode(),
+            item_id: format!("{}-{}", COINBASE_PROVIDER, user.data.id),
+            request_id: String::new(),
+        })
+    }
+
+    async fn get_accounts(&self, access_token: &str) -> Result<Vec<BankAccount>> {
+        let credentials = CoinbaseCredentials::decode(access_token)?;
+        let user: Envelope<CoinbaseUser> = self.get(&credentials, "/v2/user").await?;
+        let item_id = format!("{}-{}", COINBASE_PROVIDER, user.data.id);
+
+        self.accounts(&credentials)
+            .await?
+            .iter()
+            .map(|account| account_from_coinbase(account, &item_id))
+            .collect()
+    }
+
+    /// Coinbase balances are always live
+    async fn get_balances(&self, access_token: &str) -> Result<Vec<BankAccount>> {
+        self.get_accounts(access_token).await
+    }
+
+    /// Coinbase has no change feed, so the cursor is the newest transaction time seen and each
+    /// sync re-reads a short overlap before it; re-applied transactions are upserts
+    #[instrument(skip(self, request))]
+    async fn sync_transactions(&self, request: TransactionSyncRequest) -> Result<TransactionSyncResponse> {
+        let credentials = CoinbaseCredentials::decode(&request.access_token)?;
+        let cursor = request
+            .cursor
+            .as_deref()
+            .filter(|c| !c.is_empty())
+            .map(|c| DateTime::parse_from_rfc3339(c).map(|t| t.with_timezone(&Utc)))
+            .transpose()
+            .context("Invalid Coinbase sync cursor")?;
+        let since = cursor.map(|c| c - chrono::Duration::days(SYNC_OVERLAP_DAYS));
+
+        let added = self.transactions_since(&credentials, since).await?;
+        let newest = added.iter().filter_map(|t| t.datetime).chain(cursor).max();
+
+        Ok(TransactionSyncResponse {
+            added,
+            modified: Vec::new(),
+            removed: Vec::new(),
+            next_cursor: newest.map(|t| t.to_rfc3339()).unwrap_or_default(),
+            has_more: false,
+            request_id: String::new(),
+        })
+    }
+
+    async fn get_all_transactions(
+        &self,
+        access_token: &str,
+        start_date: &str,
+        end_date: &str,
+    ) -> Result<Vec<BankTransaction>> {
+        let credentials = CoinbaseCredentials::decode(access_token)?;
+        let start = chrono::NaiveDate::parse_from_str(start_date, "%Y-%m-%d")
+            .with_context(|| format!("Invalid start date '{}'", start_date))?;
+        let since = start.and_hms_opt(0, 0, 0).map(|t| t.and_utc());
+
+        let mut transactions = self.transactions_since(&credentials, since).await?;
+        transactions.retain(|t| t.date.as_str() <= end_date);
+        Ok(transactions)
+    }
+
+    async fn get_liabilities(&self, _access_token: &str) -> Result<Vec<Liability>> {
+        Ok(Vec::new())
+    }
+
+    async fn get_identity(&self, _access_token: &str) -> Result<Vec<AccountIdentity>> {
+        bail!("Coinbase does not provide account owner identity")
+    }
+
+    async fn get_institution(&self, institution_id: &str, _country_codes: Vec<&str>) -> Result<Institution> {
+        if institution_id != COINBASE_INSTITUTION_ID {
+            bail!("Unknown Coinbase institution '{}' (INVALID_INSTITUTION)", institution_id);
+        }
+        Ok(Institution {
+            institution_id: COINBASE_INSTITUTION_ID.to_string(),
+            name: "Coinbase".to_string(),
+            logo: None,
+            primary_color: Some("#0052ff".to_string()),
+            url: Some("https://www.coinbase.com".to_string()),
+            products: vec!["balance".to_string(), "transactions".to_string()],
+            country_codes: Vec::new(),
+            oauth: false,
+        })
+    }
+
+    /// API keys can only be revoked by their owner in Coinbase; the stored copy is dropped with the item
+    async fn remove_item(&self, _access_token: &str) -> Result<()> {
+        warn!("Coinbase API keys cannot be revoked remotely; the user should delete the key in Coinbase");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_credentials_round_trip() {
+        let credentials = CoinbaseCredentials::decode("key123:secret456").unwrap();
+        assert_eq!(credentials.api_key, "key123");
+        assert_eq!(credentials.encode(), "key123:secret456");
+        assert!(!format!("{:?}", credentials).contains("secret456"));
+        assert!(CoinbaseCredentials::decode("key123").is_err());
+        assert!(CoinbaseCredentials::decode(":secret").is_err());
+    }
+
+    #[test]
+    fn test_signature_is_hex_hmac() {
+        let credentials = CoinbaseCredentials::decode("key:secret").unwrap();
+        let signature = credentials.sign(1_700_000_000, &Method::GET, "/v2/accounts", "").unwrap();
+        assert_eq!(signature.len(), 64);
+        assert!(signature.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(signature, credentials.sign(1_700_000_001, &Method::GET, "/v2/accounts", "").unwrap());
+    }
+
+    #[test]
+    fn test_account_mapping() {
+        let accounts: Envelope<Vec<CoinbaseAccount>> = serde_json::from_str(
+            r#"{
+                "pagination": {"next_uri": null},
+                "data": [
+                    {"id": "a1", "name": "BTC Wallet", "type": "wallet",
+                     "currency": {"code": "BTC", "type": "crypto"},
+                     "balance": {"amount": "0.50000000", "currency": "BTC"}},
+                    {"id": "a2", "name": "Cash (USD)", "type": "fiat",
+                     "currency": {"code": "USD", "type": "fiat"},
+                     "balance": {"amount": "12.34", "currency": "USD"}}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let btc = account_from_coinbase(&accounts.data[0], "coinbase-u1").unwrap();
+        assert_eq!(btc.account_type, "investment");
+        assert_eq!(btc.balances.current, Some(0.5));
+        assert_eq!(btc.balances.unofficial_currency_code.as_deref(), Some("BTC"));
+        assert_eq!(btc.balances.iso_currency_code, None);
+
+        let cash = account_from_coinbase(&accounts.data[1], "coinbase-u1").unwrap();
+        assert_eq!(cash.account_type, "depository");
+        assert_eq!(cash.balances.iso_currency_code.as_deref(), Some("USD"));
+    }
+
+    #[test]
+    fn test_transaction_mapping() {
+        let transaction: CoinbaseTransaction = serde_json::from_str(
+            r#"{
+                "id": "t1", "type": "buy", "status": "completed",
+                "amount": {"amount": "0.01000000", "currency": "BTC"},
+                "created_at": "2024-03-01T12:00:00Z",
+                "details": {"title": "Bought Bitcoin", "subtitle": "Using USD Wallet"}
+            }"#,
+        )
+        .unwrap();
+
+        let mapped = transaction_from_coinbase(&transaction, "a1", false).unwrap();
+        assert_eq!(mapped.amount, -0.01);
+        assert_eq!(mapped.name, "Bought Bitcoin");
+        assert_eq!(mapped.date, "2024-03-01");
+        assert_eq!(mapped.category, vec!["Crypto Trade"]);
+        assert_eq!(mapped.unofficial_currency_code.as_deref(), Some("BTC"));
+        assert!(!mapped.pending);
+    }
+}
@@ -0,0 +1,40 @@
+// Idempotency keys that keep a logical email, such as one queued email or one sign-in code, from
+// being delivered twice, and the store they are claimed in
+use anyhow::Result;
+use async_trait::async_trait;
+
+/// What claiming an idempotency key found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdempotencyClaim {
+    /// Not sent before; the caller sends it, then completes or releases the key
+    Claimed,
+    /// Already sent, as this message ID
+    Sent(String),
+    /// Another send holds the key and hasn't finished
+    InProgress,
+}
+
+/// Another request is sending the same logical email, so this one wasn't sent
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("An email with the same idempotency key is being sent")]
+pub struct EmailInProgress;
+
+impl EmailInProgress {
+    /// Whether `error` is, or was caused by, a send held back by another in progress
+    pub fn is(error: &anyhow::Error) -> bool {
+        error.chain().any(|cause| cause.is::<EmailInProgress>())
+    }
+}
+
+/// Where idempotency keys are claimed and sent emails remembered
+#[async_trait]
+pub trait EmailIdempotency: Send + Sync {
+    /// Claim `key` for a send, unless it was sent or is being sent
+    async fn claim(&self, key: &str) -> Result<IdempotencyClaim>;
+
+    /// Remember that the email claimed by `key` was sent as `message_id`
+    async fn complete(&self, key: &str, message_id: &str) -> Result<()>;
+
+    /// Give up a claim after a failed send so a retry can send it
+    async fn release(&self, key: &str) -> Result<()>;
+}
@@ -0,0 +1,196 @@
+// Kinds of email people can opt out of, the signed one-click unsubscribe links put in them, and a
+// source of who opted out
+use crate::settings::SettingValues;
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// Kind of email, deciding whether a recipient's preferences apply
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EmailCategory {
+    /// Sign-in codes and verification; always sent
+    Critical,
+    /// Alerts and reminders about the recipient's accounts
+    Transactional,
+    /// The weekly summary
+    Digest,
+    /// Announcements and campaigns
+    Marketing,
+}
+
+impl EmailCategory {
+    /// Categories a recipient can opt out of
+    pub const OPTIONAL: [EmailCategory; 3] = [
+        EmailCategory::Transactional,
+        EmailCategory::Digest,
+        EmailCategory::Marketing,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmailCategory::Critical => "critical",
+            EmailCategory::Transactional => "transactional",
+            EmailCategory::Digest => "digest",
+            EmailCategory::Marketing => "marketing",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "critical" => Some(EmailCategory::Critical),
+            "transactional" => Some(EmailCategory::Transactional),
+            "digest" => Some(EmailCategory::Digest),
+            "marketing" => Some(EmailCategory::Marketing),
+            _ => None,
+        }
+    }
+
+    /// Whether recipients can opt out, and the email carries an unsubscribe link
+    pub fn is_optional(&self) -> bool {
+        *self != EmailCategory::Critical
+    }
+}
+
+/// The recipient opted out of the email's category, so it wasn't sent
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Recipient unsubscribed from {} emails", .category.as_str())]
+pub struct EmailSuppressed {
+    pub email: String,
+    pub category: EmailCategory,
+}
+
+impl EmailSuppressed {
+    /// Whether `error` is, or was caused by, a suppressed send
+    pub fn is(error: &anyhow::Error) -> bool {
+        error.chain().any(|cause| cause.is::<EmailSuppressed>())
+    }
+}
+
+/// Where recipients' opt-outs are read from
+#[async_trait]
+pub trait EmailPreferenceSource: Send + Sync {
+    /// Whether `email` receives emails of `category`
+    async fn allows(&self, email: &str, category: EmailCategory) -> Result<bool>;
+}
+
+/// Signs and checks one-click unsubscribe links. Links don't expire, so an old email can still be
+/// used to unsubscribe.
+#[derive(Clone)]
+pub struct UnsubscribeLinks {
+    secret: Vec<u8>,
+    base_url: String,
+}
+
+impl UnsubscribeLinks {
+    pub fn new(secret: impl Into<Vec<u8>>, base_url: impl Into<String>) -> Self {
+        Self {
+            secret: secret.into(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Links from settings; `None` when either is unset
+    /// - EMAIL_UNSUBSCRIBE_URL: Public URL of the unsubscribe endpoint, e.g. `https://api.example.com/api/email/unsubscribe`
+    /// - EMAIL_UNSUBSCRIBE_SECRET: Key the links are signed with
+    pub fn from_values(values: &SettingValues) -> Option<Self> {
+        let base_url = values.optional("email-unsubscribe-url")?;
+        let secret = values.optional("email-unsubscribe-secret")?;
+        Some(Self::new(secret, base_url))
+    }
+
+    /// Link that unsubscribes `email` from `category`
+    pub fn link(&self, email: &str, category: EmailCategory) -> Result<String> {
+        Ok(format!("{}/{}", self.base_url, self.token(email, category)?))
+    }
+
+    /// `address.category.signature`, with the address and signature base64url-encoded
+    pub fn token(&self, email: &str, category: EmailCategory) -> Result<String> {
+        let email = email.trim().to_lowercase();
+        let signature = self.mac(&email, category)?.finalize().into_bytes();
+        Ok(format!(
+            "{}.{}.{}",
+            URL_SAFE_NO_PAD.encode(email.as_bytes()),
+            category.as_str(),
+            URL_SAFE_NO_PAD.encode(signature)
+        ))
+    }
+
+    /// Address and category of a token signed with this key
+    pub fn verify(&self, token: &str) -> Result<(String, EmailCategory)> {
+        let mut parts = token.splitn(3, '.');
+        let (Some(email), Some(category), Some(signature)) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(anyhow!("Malformed unsubscribe token"));
+        };
+        let email = String::from_utf8(URL_SAFE_NO_PAD.decode(email).context("Malformed unsubscribe token")?)
+            .context("Malformed unsubscribe token")?;
+        let category = EmailCategory::parse(category)
+            .filter(EmailCategory::is_optional)
+            .ok_or_else(|| anyhow!("Unknown email category '{}'", category))?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).context("Malformed unsubscribe token")?;
+        self.mac(&email, category)?
+            .verify_slice(&signature)
+            .map_err(|_| anyhow!("Invalid unsubscribe token signature"))?;
+        Ok((email, category))
+    }
+
+    fn mac(&self, email: &str, category: EmailCategory) -> Result<Hmac<Sha256>> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.secret).map_err(|e| anyhow!("Invalid unsubscribe secret: {}", e))?;
+        mac.update(format!("unsubscribe:{}:{}", category.as_str(), email).as_bytes());
+        Ok(mac)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_category_round_trip() {
+        for category in [
+            EmailCategory::Critical,
+            EmailCategory::Transactional,
+            EmailCategory::Digest,
+            EmailCategory::Marketing,
+        ] {
+            assert_eq!(EmailCategory::parse(category.as_str()), Some(category));
+        }
+        assert!(!EmailCategory::Critical.is_optional());
+        assert!(EmailCategory::OPTIONAL.iter().all(EmailCategory::is_optional));
+    }
+
+    #[test]
+    fn test_unsubscribe_token_round_trip() {
+        let links = UnsubscribeLinks::new("secret", "https://api.example.com/api/email/unsubscribe/");
+        let link = links.link(" Ana@Example.com", EmailCategory::Digest).unwrap();
+        let token = link
+            .strip_prefix("https://api.example.com/api/email/unsubscribe/")
+            .unwrap();
+        assert_eq!(
+            links.verify(token).unwrap(),
+            ("ana@example.com".to_string(), EmailCategory::Digest)
+        );
+
+        // Another key, or a token moved to another category, doesn't verify
+        assert!(UnsubscribeLinks::new("other", "https://x").verify(token).is_err());
+        let moved = token.replacen(".digest.", ".marketing.", 1);
+        assert!(links.verify(&moved).is_err());
+        let critical = links.token("ana@example.com", EmailCategory::Critical).unwrap();
+        assert!(links.verify(&critical).is_err());
+        assert!(links.verify("not-a-token").is_err());
+    }
+
+    #[test]
+    fn test_suppressed_is_found_through_context() {
+        let error = anyhow::Error::new(EmailSuppressed {
+            email: "ana@example.com".to_string(),
+            category: EmailCategory::Marketing,
+        })
+        .context("Failed to send campaign email");
+        assert!(EmailSuppressed::is(&error));
+        assert!(!EmailSuppressed::is(&anyhow!("SES unavailable")));
+    }
+}
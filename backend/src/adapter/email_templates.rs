@@ -0,0 +1,595 @@
+// Templates of the emails Mailer sends, with compiled defaults, their translations, a source for
+// edited versions and the branded layout they are sent in
+use crate::adapter::email_preferences::EmailCategory;
+use crate::adapter::ses::TemplateData;
+use crate::settings::SettingValues;
+use anyhow::Result;
+use async_trait::async_trait;
+
+/// Document every HTML body is placed in; `{{content}}` is the body
+const LAYOUT_HTML: &str = include_str!("../../templates/email/layout/base.html");
+/// Plain text counterpart of `LAYOUT_HTML`
+const LAYOUT_TEXT: &str = include_str!("../../templates/email/layout/base.txt");
+
+/// Language an email is written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum EmailLocale {
+    #[default]
+    English,
+    Spanish,
+    French,
+}
+
+impl EmailLocale {
+    pub const ALL: [EmailLocale; 3] = [EmailLocale::English, EmailLocale::Spanish, EmailLocale::French];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmailLocale::English => "en",
+            EmailLocale::Spanish => "es",
+            EmailLocale::French => "fr",
+        }
+    }
+
+    /// Locale of a language tag such as `es-MX` or `fr_CA`; English for anything unsupported
+    pub fn parse(tag: &str) -> Self {
+        let language = tag.split(['-', '_']).next().unwrap_or_default().to_ascii_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|locale| locale.as_str() == language)
+            .unwrap_or_default()
+    }
+}
+
+/// Email sent from a template
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EmailTemplateName {
+    OtpLogin,
+    Verification,
+    Notification,
+    WeeklyDigest,
+}
+
+impl EmailTemplateName {
+    pub const ALL: [EmailTemplateName; 4] = [
+        EmailTemplateName::OtpLogin,
+        EmailTemplateName::Verification,
+        EmailTemplateName::Notification,
+        EmailTemplateName::WeeklyDigest,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmailTemplateName::OtpLogin => "otp_login",
+            EmailTemplateName::Verification => "verification",
+            EmailTemplateName::Notification => "notification",
+            EmailTemplateName::WeeklyDigest => "weekly_digest",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|name| name.as_str() == value)
+    }
+
+    /// Kind of email, deciding whether recipients can opt out of it
+    pub fn category(&self) -> EmailCategory {
+        match self {
+            EmailTemplateName::OtpLogin | EmailTemplateName::Verification => EmailCategory::Critical,
+            EmailTemplateName::Notification => EmailCategory::Transactional,
+            EmailTemplateName::WeeklyDigest => EmailCategory::Digest,
+        }
+    }
+
+    /// Placeholders filled in when the email is sent
+    pub fn keys(&self) -> &'static [&'static str] {
+        match self {
+            EmailTemplateName::OtpLogin => &["user_name", "otp_code", "expires_minutes"],
+            EmailTemplateName::Verification => &["user_name", "verification_code"],
+            EmailTemplateName::Notification => &["subject", "message"],
+            EmailTemplateName::WeeklyDigest => &[
+                "user_name",
+                "week_start",
+                "week_end",
+                "transaction_count",
+                "money_in",
+                "money_out",
+                "insight_html",
+                "insight_text",
+                "spending_html",
+                "spending_text",
+                "transactions_html",
+                "transactions_text",
+                "bills_html",
+                "bills_text",
+            ],
+        }
+    }
+
+    /// Placeholders a version must use somewhere, as the email is pointless without them
+    pub fn required_keys(&self) -> &'static [&'static str] {
+        match self {
+            EmailTemplateName::OtpLogin => &["otp_code"],
+            EmailTemplateName::Verification => &["verification_code"],
+            EmailTemplateName::Notification => &["message"],
+            EmailTemplateName::WeeklyDigest => &["spending_html", "spending_text", "bills_html", "bills_text"],
+        }
+    }
+
+    /// Values shown when previewing a template
+    pub fn sample_data(&self) -> TemplateData {
+        let mut data = TemplateData::new();
+        for key in self.keys() {
+            let value = match *key {
+                "user_name" => "Ana",
+                "otp_code" => "482913",
+                "expires_minutes" => "5",
+                "verification_code" => "731046",
+                "subject" => "Large transaction on Checking",
+                "message" => "A transaction of 1250.00 USD at Corner Market posted to Checking.",
+                "week_start" => "Mar 04",
+                "week_end" => "Mar 10",
+                "transaction_count" => "12",
+                "money_in" => "2000.00 USD",
+                "money_out" => "684.75 USD",
+                "insight_html" => "<p>You spent a little more on groceries than the week before.</p>",
+                "insight_text" => "You spent a little more on groceries than the week before.",
+                "spending_html" => "<ul><li>Spent 684.75 USD, up 12% from the week before</li></ul>",
+                "spending_text" => "- Spent 684.75 USD, up 12% from the week before",
+                "transactions_html" => "<ul><li>Mar 08 Corner Market: 84.75 USD</li></ul>",
+                "transactions_text" => "- Mar 08 Corner Market: 84.75 USD",
+                "bills_html" => "<ul><li>Rent due Mar 15: about 1200.00 USD</li></ul>",
+                "bills_text" => "- Rent due Mar 15: about 1200.00 USD",
+                _ => "",
+            };
+            data.insert(*key, value);
+        }
+        data
+    }
+
+    /// Translation of the compiled template into `locale`, if there is one. Only the account and
+    /// notification emails are translated; edited versions are always English.
+    pub fn translated_template(&self, locale: EmailLocale) -> Option<EmailTemplateContent> {
+        let (subject, html, text) = match (self, locale) {
+            (EmailTemplateName::OtpLogin, EmailLocale::Spanish) => (
+                "🔐 Tu código de acceso - {{otp_code}}",
+                include_str!("../../templates/email/es/otp_login.html"),
+                include_str!("../../templates/email/es/otp_login.txt"),
+            ),
+            (EmailTemplateName::OtpLogin, EmailLocale::French) => (
+                "🔐 Votre code de connexion - {{otp_code}}",
+                include_str!("../../templates/email/fr/otp_login.html"),
+                include_str!("../../templates/email/fr/otp_login.txt"),
+            ),
+            (EmailTemplateName::Verification, EmailLocale::Spanish) => (
+                "Verifica tu correo electrónico",
+                include_str!("../../templates/email/es/verification.html"),
+                include_str!("../../templates/email/es/verification.txt"),
+            ),
+            (EmailTemplateName::Verification, EmailLocale::French) => (
+                "Vérifiez votre adresse e-mail",
+                include_str!("../../templates/email/fr/verification.html"),
+                include_str!("../../templates/email/fr/verification.txt"),
+            ),
+            (EmailTemplateName::Notification, EmailLocale::Spanish) => (
+                "{{subject}}",
+                include_str!("../../templates/email/es/notification.html"),
+                include_str!("../../templates/email/es/notification.txt"),
+            ),
+            (EmailTemplateName::Notification, EmailLocale::French) => (
+                "{{subject}}",
+                include_str!("../../templates/email/fr/notification.html"),
+                include_str!("../../templates/email/fr/notification.txt"),
+            ),
+            _ => return None,
+        };
+        Some(EmailTemplateContent {
+            subject: subject.to_string(),
+            html: html.to_string(),
+            text: text.to_string(),
+            version: None,
+        })
+    }
+
+    /// Template compiled into the binary, used until a version is activated
+    pub fn default_template(&self) -> EmailTemplateContent {
+        let (subject, html, text) = match self {
+            EmailTemplateName::OtpLogin => (
+                "🔐 Your Login Code - {{otp_code}}",
+                include_str!("../../templates/email/otp_login.html"),
+                include_str!("../../templates/email/otp_login.txt"),
+            ),
+            EmailTemplateName::Verification => (
+                "Email Verification Required",
+                include_str!("../../templates/email/verification.html"),
+                include_str!("../../templates/email/verification.txt"),
+            ),
+            EmailTemplateName::Notification => (
+                "{{subject}}",
+                include_str!("../../templates/email/notification.html"),
+                include_str!("../../templates/email/notification.txt"),
+            ),
+            EmailTemplateName::WeeklyDigest => (
+                "Your week in review: {{week_start}} to {{week_end}}",
+                include_str!("../../templates/email/weekly_digest.html"),
+                include_str!("../../templates/email/weekly_digest.txt"),
+            ),
+        };
+        EmailTemplateContent {
+            subject: subject.to_string(),
+            html: html.to_string(),
+            text: text.to_string(),
+            version: None,
+        }
+    }
+}
+
+/// Subject and bodies of an email, with `{{key}}` placeholders
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmailTemplateContent {
+    pub subject: String,
+    pub html: String,
+    pub text: String,
+    /// Stored version, or `None` for the compiled default
+    pub version: Option<i32>,
+}
+
+impl EmailTemplateContent {
+    /// Problems that keep this content from being used for `name`
+    pub fn validate(&self, name: EmailTemplateName) -> Result<(), String> {
+        if self.subject.trim().is_empty() || self.html.trim().is_empty() || self.text.trim().is_empty() {
+            return Err("subject, html and text must not be empty".to_string());
+        }
+        let used: Vec<&str> = [&self.subject, &self.html, &self.text]
+            .into_iter()
+            .flat_map(|part| placeholders(part))
+            .collect();
+        if let Some(unknown) = used.iter().find(|key| !name.keys().contains(key)) {
+            return Err(format!(
+                "Unknown placeholder {{{{{}}}}}; {} templates can use {}",
+                unknown,
+                name.as_str(),
+                name.keys().join(", ")
+            ));
+        }
+        let missing: Vec<&str> = name
+            .required_keys()
+            .iter()
+            .copied()
+            .filter(|key| !used.contains(key))
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!("Missing required placeholders: {}", missing.join(", ")));
+        }
+        Ok(())
+    }
+
+    /// Subject and bodies with the placeholders filled in
+    pub fn render(&self, data: &TemplateData) -> EmailTemplateContent {
+        EmailTemplateContent {
+            subject: data.render_template(&self.subject),
+            html: data.render_template(&self.html),
+            text: data.render_template(&self.text),
+            version: self.version,
+        }
+    }
+
+    /// Whether the HTML is a whole document rather than a body for the layout, as versions saved
+    /// before the shared layout are
+    pub fn is_document(&self) -> bool {
+        self.html.to_ascii_lowercase().contains("<html")
+    }
+
+    /// Bodies placed in the layout drawn with `brand`, with its footer in `locale`. Whole documents
+    /// are left as they are.
+    pub fn with_layout(&self, brand: &EmailBrand, locale: EmailLocale) -> EmailTemplateContent {
+        if self.is_document() {
+            return self.clone();
+        }
+        let [signoff, notice, support] = footer_lines(brand, locale);
+        let mut layout = TemplateData::new();
+        layout.insert("lang", locale.as_str());
+        layout.insert("title", self.subject.clone());
+        layout.insert("product_name", brand.product_name.clone());
+        layout.insert("brand_color", brand.primary_color.clone());
+        layout.insert("brand_accent_color", brand.accent_color.clone());
+        layout.insert("footer_signoff", signoff);
+        layout.insert("footer_notice", notice);
+        layout.insert("footer_support", support);
+        // The bodies go in last so the layout values can't fill placeholders of theirs
+        let wrap = |template: &str, body: &str| {
+            layout
+                .render_template(&expand_partials(template))
+                .replace("{{content}}", body.trim_end())
+        };
+        EmailTemplateContent {
+            subject: self.subject.clone(),
+            html: wrap(LAYOUT_HTML, &self.html),
+            text: wrap(LAYOUT_TEXT, &self.text),
+            version: self.version,
+        }
+    }
+}
+
+/// Look and sender details of the email layout
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmailBrand {
+    pub product_name: String,
+    /// Color of the header and codes, as `#rrggbb`
+    pub primary_color: String,
+    /// Color the header fades to, as `#rrggbb`
+    pub accent_color: String,
+    /// Address the footer tells recipients to write to for help (optional)
+    pub support_email: Option<String>,
+}
+
+impl Default for EmailBrand {
+    fn default() -> Self {
+        Self {
+            product_name: "Origin".to_string(),
+            primary_color: "#667eea".to_string(),
+            accent_color: "#764ba2".to_string(),
+            support_email: None,
+        }
+    }
+}
+
+impl EmailBrand {
+    /// Read from settings
+    /// - EMAIL_PRODUCT_NAME: Name in the header and sign-off (default: Origin)
+    /// - EMAIL_PRIMARY_COLOR: Header and code color as `#rrggbb` (default: #667eea)
+    /// - EMAIL_ACCENT_COLOR: Header gradient end color as `#rrggbb` (default: #764ba2)
+    /// - EMAIL_SUPPORT_ADDRESS: Help address shown in the footer (optional)
+    pub fn from_values(values: &mut SettingValues) -> Self {
+        let defaults = Self::default();
+        let mut color = |key: &str, default: String| match values.optional(key) {
+            Some(color) if is_hex_color(&color) => color,
+            Some(color) => {
+                values.error(format!("{} must be a #rrggbb color, not '{}'", key, color));
+                default
+            }
+            None => default,
+        };
+        Self {
+            primary_color: color("email-primary-color", defaults.primary_color),
+            accent_color: color("email-accent-color", defaults.accent_color),
+            product_name: values.or("email-product-name", &defaults.product_name),
+            support_email: values.optional("email-support-address"),
+        }
+    }
+}
+
+/// Whether `color` is a `#rrggbb` color, the only form put into the layout's styles
+fn is_hex_color(color: &str) -> bool {
+    color.len() == 7 && color.starts_with('#') && color[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Sign-off, automated message notice and help line of the layout footer in `locale`
+fn footer_lines(brand: &EmailBrand, locale: EmailLocale) -> [String; 3] {
+    let product = &brand.product_name;
+    let support = brand.support_email.as_deref();
+    match locale {
+        EmailLocale::English => [
+            format!("The {} Team", product),
+            "This is an automated message. Please do not reply to this email.".to_string(),
+            support.map_or_else(
+                || "Need help? Contact our support team.".to_string(),
+                |email| format!("Need help? Write to us at {}.", email),
+            ),
+        ],
+        EmailLocale::Spanish => [
+            format!("El equipo de {}", product),
+            "Este es un mensaje automático. No respondas a este correo.".to_string(),
+            support.map_or_else(
+                || "¿Necesitas ayuda? Contacta con nuestro equipo de soporte.".to_string(),
+                |email| format!("¿Necesitas ayuda? Escríbenos a {}.", email),
+            ),
+        ],
+        EmailLocale::French => [
+            format!("L'équipe {}", product),
+            "Ceci est un message automatique. Merci de ne pas y répondre.".to_string(),
+            support.map_or_else(
+                || "Besoin d'aide ? Contactez notre équipe d'assistance.".to_string(),
+                |email| format!("Besoin d'aide ? Écrivez-nous à {}.", email),
+            ),
+        ],
+    }
+}
+
+/// Layout partial included with `{{> name}}`
+fn partial(name: &str) -> Option<&'static str> {
+    match name {
+        "styles" => Some(include_str!("../../templates/email/layout/styles.html")),
+        "header" => Some(include_str!("../../templates/email/layout/header.html")),
+        "footer" => Some(include_str!("../../templates/email/layout/footer.html")),
+        "footer_text" => Some(include_str!("../../templates/email/layout/footer.txt")),
+        _ => None,
+    }
+}
+
+/// `template` with its `{{> name}}` includes replaced by the partials, which may include others.
+/// Unknown partials are left in place.
+fn expand_partials(template: &str) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{>") {
+        let Some(end) = rest[start..].find("}}").map(|end| start + end + 2) else {
+            break;
+        };
+        result.push_str(&rest[..start]);
+        match partial(rest[start + 3..end - 2].trim()) {
+            Some(body) => result.push_str(expand_partials(body).trim_end()),
+            None => result.push_str(&rest[start..end]),
+        }
+        rest = &rest[end..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Names of the `{{key}}` placeholders in `template`, in order of appearance
+pub fn placeholders(template: &str) -> Vec<&str> {
+    let mut keys = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        rest = &rest[start + 2..];
+        let Some(end) = rest.find("}}") else {
+            break;
+        };
+        let key = &rest[..end];
+        if !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            keys.push(key);
+        }
+        rest = &rest[end + 2..];
+    }
+    keys
+}
+
+/// Where edited templates are read from; `None` means the compiled default is in use
+#[async_trait]
+pub trait EmailTemplateSource: Send + Sync {
+    async fn active_template(&self, name: EmailTemplateName) -> Result<Option<EmailTemplateContent>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_placeholders() {
+        assert_eq!(
+            placeholders("Hi {{user_name}}, code {{otp_code}}"),
+            vec!["user_name", "otp_code"]
+        );
+        assert_eq!(
+            placeholders(".a { color: red; } {{ bad key }} {{open"),
+            Vec::<&str>::new()
+        );
+    }
+
+    #[test]
+    fn test_defaults_are_valid() {
+        for name in EmailTemplateName::ALL {
+            assert_eq!(EmailTemplateName::parse(name.as_str()), Some(name));
+            assert_eq!(name.default_template().validate(name), Ok(()), "{}", name.as_str());
+        }
+    }
+
+    #[test]
+    fn test_translations_are_valid() {
+        for locale in EmailLocale::ALL {
+            assert_eq!(EmailLocale::parse(locale.as_str()), locale);
+            for name in EmailTemplateName::ALL {
+                let Some(template) = name.translated_template(locale) else {
+                    continue;
+                };
+                assert_eq!(template.validate(name), Ok(()), "{} {}", locale.as_str(), name.as_str());
+                // Same placeholders as the English version
+                let keys = |template: &EmailTemplateContent| {
+                    let mut keys = placeholders(&template.text);
+                    keys.sort();
+                    keys.dedup();
+                    keys.into_iter().map(str::to_string).collect::<Vec<_>>()
+                };
+                assert_eq!(keys(&template), keys(&name.default_template()));
+            }
+        }
+        assert!(EmailTemplateName::WeeklyDigest.translated_template(EmailLocale::Spanish).is_none());
+        assert!(EmailTemplateName::OtpLogin.translated_template(EmailLocale::English).is_none());
+    }
+
+    #[test]
+    fn test_parse_locale() {
+        assert_eq!(EmailLocale::parse("es-MX"), EmailLocale::Spanish);
+        assert_eq!(EmailLocale::parse("fr_CA"), EmailLocale::French);
+        assert_eq!(EmailLocale::parse("FR"), EmailLocale::French);
+        assert_eq!(EmailLocale::parse("de"), EmailLocale::English);
+        assert_eq!(EmailLocale::parse(""), EmailLocale::English);
+    }
+
+    #[test]
+    fn test_validate_rejects_unknown_and_missing_keys() {
+        let content = EmailTemplateContent {
+            subject: "Your code".to_string(),
+            html: "<p>{{otp_cod}}</p>".to_string(),
+            text: "{{otp_code}}".to_string(),
+            version: Some(2),
+        };
+        assert!(content
+            .validate(EmailTemplateName::OtpLogin)
+            .unwrap_err()
+            .contains("{{otp_cod}}"));
+
+        let content = EmailTemplateContent {
+            html: "<p>Hello {{user_name}}</p>".to_string(),
+            text: "Hello".to_string(),
+            ..content
+        };
+        assert_eq!(
+            content.validate(EmailTemplateName::OtpLogin),
+            Err("Missing required placeholders: otp_code".to_string())
+        );
+    }
+
+    #[test]
+    fn test_render_sample_data() {
+        let name = EmailTemplateName::Notification;
+        let rendered = name.default_template().render(&name.sample_data());
+        assert_eq!(rendered.subject, "Large transaction on Checking");
+        assert!(rendered.text.contains("Corner Market"));
+        assert!(placeholders(&rendered.html).is_empty());
+    }
+
+    #[test]
+    fn test_layout_fills_in_every_template() {
+        let brand = EmailBrand::default();
+        for locale in EmailLocale::ALL {
+            for name in EmailTemplateName::ALL {
+                let template = name
+                    .translated_template(locale)
+                    .unwrap_or_else(|| name.default_template());
+                assert!(!template.is_document(), "{} {}", locale.as_str(), name.as_str());
+                let sent = template.with_layout(&brand, locale).render(&name.sample_data());
+                for body in [&sent.html, &sent.text] {
+                    assert!(placeholders(body).is_empty(), "{} {}", locale.as_str(), name.as_str());
+                    assert!(!body.contains("{{"), "{} {}", locale.as_str(), name.as_str());
+                }
+                assert!(sent.html.contains(&format!("<html lang=\"{}\">", locale.as_str())));
+                assert!(sent.html.trim_end().ends_with("</html>"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_layout_brand() {
+        let brand = EmailBrand {
+            product_name: "Acme".to_string(),
+            primary_color: "#112233".to_string(),
+            support_email: Some("help@acme.test".to_string()),
+            ..EmailBrand::default()
+        };
+        let name = EmailTemplateName::Verification;
+        let sent = name
+            .default_template()
+            .with_layout(&brand, EmailLocale::English)
+            .render(&name.sample_data());
+        assert!(sent.html.contains("<p class=\"product\">Acme</p>"));
+        assert!(sent.html.contains("color: #112233;"));
+        assert!(sent.html.contains("<title>Email Verification Required</title>"));
+        assert!(sent.text.starts_with("Email Verification Required"));
+        assert!(sent.text.contains("The Acme Team\n"));
+        assert!(sent.text.contains("Write to us at help@acme.test."));
+
+        // Versions saved as whole documents are sent as they are
+        let document = EmailTemplateContent {
+            subject: "Your code".to_string(),
+            html: "<!DOCTYPE html><HTML><body>{{otp_code}}</body></HTML>".to_string(),
+            text: "{{otp_code}}".to_string(),
+            version: Some(3),
+        };
+        assert_eq!(document.with_layout(&brand, EmailLocale::French), document);
+
+        assert!(is_hex_color("#A1b2C3"));
+        assert!(!is_hex_color("red"));
+        assert!(!is_hex_color("#123456; }"));
+    }
+}
@@ -0,0 +1,460 @@
+// Ways of delivering a built email: SES, SMTP or Postmark in deployed environments, a file/log
+// transport for local dev, and failover between two of them
+pub use crate::adapter::ses::{EmailRequest, EmailResponse};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, instrument, warn};
+use uuid::Uuid;
+
+/// Delivers emails whose subject and bodies are final apart from their template data. Requests
+/// are provider-neutral; SES-only options such as stored templates are refused by other providers.
+#[async_trait]
+pub trait EmailTransport: Send + Sync {
+    /// Short name for logs, e.g. "ses"
+    fn name(&self) -> &'static str;
+
+    async fn send_email(&self, request: EmailRequest) -> Result<EmailResponse>;
+}
+
+/// Transport for local development: logs each email and, with a directory, writes it there as an
+/// `.eml` file that mail clients can open. Nothing leaves the machine.
+pub struct DevEmailTransport {
+    sender: String,
+    dir: Option<PathBuf>,
+}
+
+impl DevEmailTransport {
+    pub fn new<S: Into<String>>(sender: S, dir: Option<PathBuf>) -> Self {
+        Self {
+            sender: sender.into(),
+            dir,
+        }
+    }
+}
+
+#[async_trait]
+impl EmailTransport for DevEmailTransport {
+    fn name(&self) -> &'static str {
+        "dev"
+    }
+
+    #[instrument(skip(self, request), fields(to_count = request.to.len(), subject = %request.subject))]
+    async fn send_email(&self, request: EmailRequest) -> Result<EmailResponse> {
+        let start_time = std::time::Instant::now();
+        let request = request.rendered();
+        if request.to.is_empty() {
+            return Err(anyhow::anyhow!("At least one recipient is required"));
+        }
+
+        let message_id = format!("dev-{}", Uuid::new_v4());
+        let sender = request.sender.clone().unwrap_or_else(|| self.sender.clone());
+        let path = match &self.dir {
+            Some(dir) => {
+                let path = dir.join(format!("{}-{}.eml", Utc::now().format("%Y%m%dT%H%M%S"), message_id));
+                let contents = match &request.raw_message {
+                    Some(raw) => raw.clone(),
+                    None => to_mime(&request, &sender, &message_id).into_bytes(),
+                };
+                std::fs::write(&path, contents).with_context(|| format!("Failed to write {}", path.display()))?;
+                Some(path)
+            }
+            None => None,
+        };
+
+        info!(
+            message_id = %message_id,
+            to = ?request.to,
+            subject = %request.subject,
+            path = ?path,
+            body = %request.text_body.as_deref().unwrap_or_default(),
+            "Email captured by dev transport"
+        );
+
+        Ok(EmailResponse {
+            message_id,
+            accepted: true,
+            processing_time_ms: start_time.elapsed().as_millis() as u64,
+        })
+    }
+}
+
+/// Counters of a failover transport, readable while it runs
+#[derive(Debug, Default)]
+pub struct EmailTransportMetrics {
+    primary_sends: AtomicU64,
+    primary_failures: AtomicU64,
+    fallback_sends: AtomicU64,
+    fallback_failures: AtomicU64,
+    failovers: AtomicU64,
+}
+
+/// Point-in-time copy of [`EmailTransportMetrics`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EmailTransportSnapshot {
+    pub primary_sends: u64,
+    pub primary_failures: u64,
+    pub fallback_sends: u64,
+    pub fallback_failures: u64,
+    /// Times the primary was taken out of rotation after sustained errors
+    pub failovers: u64,
+}
+
+impl EmailTransportMetrics {
+    pub fn snapshot(&self) -> EmailTransportSnapshot {
+        EmailTransportSnapshot {
+            primary_sends: self.primary_sends.load(Ordering::Relaxed),
+            primary_failures: self.primary_failures.load(Ordering::Relaxed),
+            fallback_sends: self.fallback_sends.load(Ordering::Relaxed),
+            fallback_failures: self.fallback_failures.load(Ordering::Relaxed),
+            failovers: self.failovers.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct FailoverState {
+    consecutive_failures: u32,
+    /// While set and in the future, sends skip the primary
+    failed_over_until: Option<Instant>,
+}
+
+/// Sends through a primary transport, retrying each failed email through a fallback. After
+/// `failure_threshold` primary failures in a row the primary is skipped for `cooldown`; the first
+/// email after that probes it again.
+pub struct FailoverTransport {
+    primary: Arc<dyn EmailTransport>,
+    fallback: Arc<dyn EmailTransport>,
+    failure_threshold: u32,
+    cooldown: Duration,
+    state: Mutex<FailoverState>,
+    metrics: EmailTransportMetrics,
+}
+
+impl FailoverTransport {
+    pub fn new(
+        primary: Arc<dyn EmailTransport>,
+        fallback: Arc<dyn EmailTransport>,
+        failure_threshold: u32,
+        cooldown: Duration,
+    ) -> Self {
+        Self {
+            primary,
+            fallback,
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            state: Mutex::new(FailoverState::default()),
+            metrics: EmailTransportMetrics::default(),
+        }
+    }
+
+    pub fn metrics(&self) -> EmailTransportSnapshot {
+        self.metrics.snapshot()
+    }
+
+    /// Whether the primary is currently out of rotation
+    pub fn is_failed_over(&self) -> bool {
+        self.skip_primary(Instant::now())
+    }
+
+    fn skip_primary(&self, now: Instant) -> bool {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.failed_over_until.is_some_and(|until| now < until)
+    }
+
+    fn record_primary(&self, ok: bool) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if ok {
+            self.metrics.primary_sends.fetch_add(1, Ordering::Relaxed);
+            if state.failed_over_until.take().is_some() {
+                info!(primary = self.primary.name(), "Primary email transport recovered");
+            }
+            state.consecutive_failures = 0;
+            return;
+        }
+
+        self.metrics.primary_failures.fetch_add(1, Ordering::Relaxed);
+        state.consecutive_failures += 1;
+        // A failed probe after the cooldown reopens the circuit straight away
+        let probing = state.failed_over_until.is_some();
+        if probing || state.consecutive_failures >= self.failure_threshold {
+            if !probing {
+                self.metrics.failovers.fetch_add(1, Ordering::Relaxed);
+                warn!(
+                    primary = self.primary.name(),
+                    fallback = self.fallback.name(),
+                    consecutive_failures = state.consecutive_failures,
+                    cooldown_secs = self.cooldown.as_secs(),
+                    "Email transport failing over"
+                );
+            }
+            state.failed_over_until = Some(Instant::now() + self.cooldown);
+        }
+    }
+}
+
+#[async_trait]
+impl EmailTransport for FailoverTransport {
+    fn name(&self) -> &'static str {
+        "failover"
+    }
+
+    async fn send_email(&self, request: EmailRequest) -> Result<EmailResponse> {
+        let primary_error = if self.skip_primary(Instant::now()) {
+            None
+        } else {
+            match self.primary.send_email(request.clone()).await {
+                Ok(response) => {
+                    self.record_primary(true);
+                    return Ok(response);
+                }
+                Err(e) => {
+                    self.record_primary(false);
+                    warn!(
+                        primary = self.primary.name(),
+                        fallback = self.fallback.name(),
+                        error = ?e,
+                        "Primary email transport failed; trying the fallback"
+                    );
+                    Some(e)
+                }
+            }
+        };
+
+        match self.fallback.send_email(request).await {
+            Ok(response) => {
+                self.metrics.fallback_sends.fetch_add(1, Ordering::Relaxed);
+                Ok(response)
+            }
+            Err(e) => {
+                self.metrics.fallback_failures.fetch_add(1, Ordering::Relaxed);
+                Err(match primary_error {
+                    Some(primary) => anyhow!(
+                        "{} failed: {:#}; {} failed: {:#}",
+                        self.primary.name(),
+                        primary,
+                        self.fallback.name(),
+                        e
+                    ),
+                    None => e.context(format!(
+                        "{} failed over to {}",
+                        self.primary.name(),
+                        self.fallback.name()
+                    )),
+                })
+            }
+        }
+    }
+}
+
+/// The request as a multipart MIME message. Stored SES templates can't be rendered locally, so
+/// their name and data are shown as the text body.
+fn to_mime(request: &EmailRequest, sender: &str, message_id: &str) -> String {
+    let boundary = format!("boundary-{}", message_id);
+    let mut headers = vec![
+        format!("Message-ID: <{}@localhost>", message_id),
+        format!("Date: {}", Utc::now().to_rfc2822()),
+        format!("From: {}", sender),
+        format!("To: {}", request.to.join(", ")),
+    ];
+    if let Some(cc) = &request.cc {
+        headers.push(format!("Cc: {}", cc.join(", ")));
+    }
+    if let Some(reply_to) = &request.reply_to {
+        headers.push(format!("Reply-To: {}", reply_to));
+    }
+    headers.push(format!("Subject: {}", request.subject));
+    for (name, value) in &request.headers {
+        headers.push(format!("{}: {}", name, value));
+    }
+    let mut tags: Vec<String> = request.tags.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+    tags.sort();
+    if !tags.is_empty() {
+        headers.push(format!("X-Tags: {}", tags.join("; ")));
+    }
+    headers.push("MIME-Version: 1.0".to_string());
+    headers.push(format!(
+        "Content-Type: multipart/alternative; boundary=\"{}\"",
+        boundary
+    ));
+
+    let text = match &request.ses_template {
+        Some(template) => {
+            let data = request
+                .template_data
+                .as_ref()
+                .and_then(|data| data.to_json().ok())
+                .unwrap_or_default();
+            Some(format!("SES template {} with data {}", template, data))
+        }
+        None => request.text_body.clone(),
+    };
+    let mut parts = Vec::new();
+    if let Some(text) = text {
+        parts.push(("text/plain", text));
+    }
+    if let Some(html) = &request.html_body {
+        parts.push(("text/html", html.clone()));
+    }
+
+    let mut message = headers.join("\r\n");
+    message.push_str("\r\n\r\n");
+    for (content_type, body) in parts {
+        message.push_str(&format!(
+            "--{}\r\nContent-Type: {}; charset=UTF-8\r\n\r\n{}\r\n",
+            boundary, content_type, body
+        ));
+    }
+    message.push_str(&format!("--{}--\r\n", boundary));
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapter::ses::TemplateData;
+
+    #[test]
+    fn test_to_mime() {
+        let mut data = TemplateData::new();
+        data.insert("code", "482913");
+        let request = EmailRequest::new(vec!["ana@example.com"], "Code {{code}}")
+            .with_text_body("Your code is {{code}}")
+            .with_html_body("<p>{{code}}</p>")
+            .with_template_data(data)
+            .with_tag("email_type", "otp_login")
+            .rendered();
+
+        let mime = to_mime(&request, "noreply@localhost", "dev-1");
+        assert!(mime.contains("To: ana@example.com\r\n"));
+        assert!(mime.contains("Subject: Code 482913\r\n"));
+        assert!(mime.contains("X-Tags: email_type=otp_login\r\n"));
+        assert!(mime.contains("Content-Type: text/plain; charset=UTF-8\r\n\r\nYour code is 482913\r\n"));
+        assert!(mime.contains("<p>482913</p>"));
+        assert!(mime.ends_with("--boundary-dev-1--\r\n"));
+    }
+
+    /// Fails while `failing` is set
+    struct FlakyTransport {
+        name: &'static str,
+        failing: std::sync::atomic::AtomicBool,
+        calls: AtomicU64,
+    }
+
+    impl FlakyTransport {
+        fn new(name: &'static str, failing: bool) -> Arc<Self> {
+            Arc::new(Self {
+                name,
+                failing: std::sync::atomic::AtomicBool::new(failing),
+                calls: AtomicU64::new(0),
+            })
+        }
+
+        fn calls(&self) -> u64 {
+            self.calls.load(Ordering::Relaxed)
+        }
+    }
+
+    #[async_trait]
+    impl EmailTransport for FlakyTransport {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        async fn send_email(&self, _request: EmailRequest) -> Result<EmailResponse> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            if self.failing.load(Ordering::Relaxed) {
+                anyhow::bail!("{} unavailable", self.name);
+            }
+            Ok(EmailResponse {
+                message_id: self.name.to_string(),
+                accepted: true,
+                processing_time_ms: 0,
+            })
+        }
+    }
+
+    fn request() -> EmailRequest {
+        EmailRequest::new(vec!["ana@example.com"], "Hello").with_text_body("Hi")
+    }
+
+    #[tokio::test]
+    async fn test_failover_after_sustained_errors() {
+        let primary = FlakyTransport::new("ses", true);
+        let fallback = FlakyTransport::new("smtp", false);
+        let transport = FailoverTransport::new(primary.clone(), fallback.clone(), 2, Duration::from_secs(60));
+
+        // Each failed email still goes out through the fallback
+        for _ in 0..2 {
+            assert_eq!(transport.send_email(request()).await.unwrap().message_id, "smtp");
+        }
+        assert!(transport.is_failed_over());
+
+        // The primary is skipped during the cooldown
+        transport.send_email(request()).await.unwrap();
+        assert_eq!(primary.calls(), 2);
+        assert_eq!(fallback.calls(), 3);
+        assert_eq!(
+            transport.metrics(),
+            EmailTransportSnapshot {
+                primary_sends: 0,
+                primary_failures: 2,
+                fallback_sends: 3,
+                fallback_failures: 0,
+                failovers: 1,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failover_recovers_after_cooldown() {
+        let primary = FlakyTransport::new("ses", true);
+        let fallback = FlakyTransport::new("smtp", false);
+        let transport = FailoverTransport::new(primary.clone(), fallback.clone(), 1, Duration::ZERO);
+
+        transport.send_email(request()).await.unwrap();
+        assert_eq!(transport.metrics().failovers, 1);
+
+        // With the cooldown over the next email probes the primary
+        primary.failing.store(false, Ordering::Relaxed);
+        assert_eq!(transport.send_email(request()).await.unwrap().message_id, "ses");
+        assert!(!transport.is_failed_over());
+        assert_eq!(transport.metrics().primary_sends, 1);
+    }
+
+    #[tokio::test]
+    async fn test_failover_reports_both_errors() {
+        let transport = FailoverTransport::new(
+            FlakyTransport::new("ses", true),
+            FlakyTransport::new("smtp", true),
+            3,
+            Duration::from_secs(60),
+        );
+        let error = format!("{:#}", transport.send_email(request()).await.unwrap_err());
+        assert!(error.contains("ses unavailable"));
+        assert!(error.contains("smtp unavailable"));
+        assert_eq!(transport.metrics().fallback_failures, 1);
+    }
+
+    #[tokio::test]
+    async fn test_dev_transport_writes_eml() {
+        let dir = std::env::temp_dir().join(format!("dev-emails-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let transport = DevEmailTransport::new("noreply@localhost", Some(dir.clone()));
+
+        let request = EmailRequest::new(vec!["ana@example.com"], "Hello").with_text_body("Hi");
+        let response = transport.send_email(request).await.unwrap();
+        assert!(response.message_id.starts_with("dev-"));
+
+        let files: Vec<_> = std::fs::read_dir(&dir).unwrap().collect();
+        assert_eq!(files.len(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let request = EmailRequest::new(Vec::<String>::new(), "Nobody").with_text_body("Hi");
+        assert!(transport.send_email(request).await.is_err());
+    }
+}
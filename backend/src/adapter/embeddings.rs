@@ -0,0 +1,216 @@
+// Text embeddings from an OpenAI-compatible embeddings API, for semantic search
+use crate::settings::SettingValues;
+use anyhow::{bail, Context, Result};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::{debug, error, instrument};
+
+/// Size of stored embeddings; the `vector` column is declared with this many dimensions
+pub const EMBEDDING_DIMENSIONS: usize = 1536;
+
+/// Most inputs sent in one request
+const MAX_INPUTS_PER_REQUEST: usize = 256;
+
+/// Configuration for the embeddings client
+#[derive(Debug, Clone)]
+pub struct EmbeddingsConfig {
+    pub api_key: String,
+    /// API root including the version, e.g. https://api.openai.com/v1
+    pub base_url: String,
+    /// Model producing `EMBEDDING_DIMENSIONS`-dimensional vectors
+    pub model: String,
+    /// Request timeout in seconds
+    pub timeout_seconds: u64,
+    /// Maximum number of attempts for failed requests
+    pub max_retries: u32,
+}
+
+impl Default for EmbeddingsConfig {
+    fn default() -> Self {
+        Self {
+            api_key: String::new(),
+            base_url: "https://api.openai.com/v1".to_string(),
+            model: "text-embedding-3-small".to_string(),
+            timeout_seconds: 30,
+            max_retries: 3,
+        }
+    }
+}
+
+impl EmbeddingsConfig {
+    /// Read from `EMBEDDINGS_API_KEY` (else `OPENAI_API_KEY`), `EMBEDDINGS_BASE_URL` and `EMBEDDINGS_MODEL`;
+    /// `None` without an API key
+    pub fn from_values(values: &mut SettingValues) -> Option<Self> {
+        let api_key = values
+            .optional("embeddings-api-key")
+            .or_else(|| values.optional("openai-api-key"))?;
+        let defaults = Self::default();
+        Some(Self {
+            api_key,
+            base_url: values.or("embeddings-base-url", &defaults.base_url),
+            model: values.or("embeddings-model", &defaults.model),
+            ..defaults
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct EmbeddingsRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+    dimensions: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingsResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+/// Vectors in input order, checking every input got one of the expected size
+fn ordered_embeddings(mut data: Vec<EmbeddingData>, input_count: usize) -> Result<Vec<Vec<f32>>> {
+    if data.len() != input_count {
+        bail!("Embeddings API returned {} vectors for {} inputs", data.len(), input_count);
+    }
+    data.sort_by_key(|d| d.index);
+    data.into_iter()
+        .enumerate()
+        .map(|(position, d)| {
+            if d.index != position {
+                bail!("Embeddings API returned no vector for input {}", position);
+            }
+            if d.embedding.len() != EMBEDDING_DIMENSIONS {
+                bail!(
+                    "Embeddings API returned {} dimensions, expected {}",
+                    d.embedding.len(),
+                    EMBEDDING_DIMENSIONS
+                );
+            }
+            Ok(d.embedding)
+        })
+        .collect()
+}
+
+/// Client for OpenAI-compatible embeddings APIs
+#[derive(Debug)]
+pub struct EmbeddingsClient {
+    config: EmbeddingsConfig,
+    client: Client,
+}
+
+impl EmbeddingsClient {
+    pub fn new(config: EmbeddingsConfig) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(config.timeout_seconds))
+            .build()
+            .context("Failed to create HTTP client")?;
+
+        Ok(Self { config, client })
+    }
+
+    /// Model stored alongside embeddings, so a model change can be detected and re-embedded
+    pub fn model(&self) -> &str {
+        &self.config.model
+    }
+
+    /// One vector per input, in input order
+    #[instrument(skip(self, inputs), fields(input_count = inputs.len()))]
+    pub async fn embed(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>> {
+        let mut embeddings = Vec::with_capacity(inputs.len());
+        for chunk in inputs.chunks(MAX_INPUTS_PER_REQUEST) {
+            let body = EmbeddingsRequest {
+                model: &self.config.model,
+                input: chunk,
+                dimensions: EMBEDDING_DIMENSIONS,
+            };
+            let response: EmbeddingsResponse = self
+                .send_with_retries(&body)
+                .await?
+                .json()
+                .await
+                .context("Failed to parse embeddings response")?;
+            embeddings.extend(ordered_embeddings(response.data, chunk.len())?);
+        }
+        Ok(embeddings)
+    }
+
+    /// POST to the embeddings API until a success status is returned, retrying with backoff
+    async fn send_with_retries(&self, body: &EmbeddingsRequest<'_>) -> Result<reqwest::Response> {
+        let url = format!("{}/embeddings", self.config.base_url.trim_end_matches('/'));
+        let mut last_error = None;
+
+        for attempt in 1..=self.config.max_retries.max(1) {
+            debug!(attempt, "Sending embeddings request");
+            let response = self
+                .client
+                .post(&url)
+                .bearer_auth(&self.config.api_key)
+                .json(body)
+                .send()
+                .await;
+
+            match response {
+                Ok(resp) if resp.status().is_success() => return Ok(resp),
+                Ok(resp) => {
+                    let status = resp.status();
+                    let error_text = resp.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+                    error!(status = %status, error = %error_text, attempt, "Embeddings API returned error");
+                    last_error = Some(anyhow::anyhow!("Embeddings API error: {} - {}", status, error_text));
+                    // Other client errors will fail the same way again
+                    if status.is_client_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    error!(error = %e, attempt, "Failed to send embeddings request");
+                    last_error = Some(anyhow::anyhow!("Request failed: {}", e));
+                }
+            }
+
+            if attempt < self.config.max_retries {
+                tokio::time::sleep(Duration::from_millis(1000 * 2_u64.pow(attempt - 1))).await;
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("All retry attempts failed")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(index: usize, value: f32) -> EmbeddingData {
+        EmbeddingData {
+            index,
+            embedding: vec![value; EMBEDDING_DIMENSIONS],
+        }
+    }
+
+    #[test]
+    fn test_embeddings_are_returned_in_input_order() {
+        let embeddings = ordered_embeddings(vec![data(1, 0.2), data(0, 0.1)], 2).unwrap();
+        assert_eq!(embeddings[0][0], 0.1);
+        assert_eq!(embeddings[1][0], 0.2);
+    }
+
+    #[test]
+    fn test_incomplete_or_wrong_sized_embeddings_fail() {
+        assert!(ordered_embeddings(vec![data(0, 0.1)], 2).is_err());
+        assert!(ordered_embeddings(vec![data(0, 0.1), data(0, 0.2)], 2).is_err());
+        assert!(ordered_embeddings(
+            vec![EmbeddingData {
+                index: 0,
+                embedding: vec![0.1; 8]
+            }],
+            1
+        )
+        .is_err());
+    }
+}
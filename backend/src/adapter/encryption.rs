@@ -0,0 +1,207 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::{debug, instrument};
+
+const NONCE_LEN: usize = 12;
+const KEY_LEN: usize = 32;
+
+/// Secret encrypted with a per-record data key that is itself wrapped by a master key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncryptedSecret {
+    /// AES-256-GCM ciphertext of the secret under the data key
+    pub ciphertext: Vec<u8>,
+    /// Nonce used to encrypt the secret
+    pub nonce: Vec<u8>,
+    /// Data key wrapped by the master key (`nonce || ciphertext`)
+    pub encrypted_data_key: Vec<u8>,
+    /// Identifier of the master key that wrapped the data key
+    pub key_id: String,
+}
+
+/// AES-256-GCM envelope encryption for secrets stored at rest (e.g. Plaid access tokens).
+///
+/// Every secret gets a fresh random data key; only the wrapped data key and the master
+/// key id are persisted. Previous master keys can be registered for decryption so keys
+/// can be rotated without re-encrypting existing rows up front.
+#[derive(Clone)]
+pub struct EnvelopeCipher {
+    active_key_id: String,
+    master_keys: HashMap<String, [u8; KEY_LEN]>,
+}
+
+impl std::fmt::Debug for EnvelopeCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EnvelopeCipher")
+            .field("active_key_id", &self.active_key_id)
+            .field("key_count", &self.master_keys.len())
+            .finish()
+    }
+}
+
+fn decode_master_key(encoded: &str) -> Result<[u8; KEY_LEN]> {
+    let bytes = STANDARD
+        .decode(encoded.trim())
+        .context("Master key must be base64 encoded")?;
+    bytes
+        .try_into()
+        .map_err(|_| anyhow!("Master key must be exactly {} bytes", KEY_LEN))
+}
+
+/// Whether a base64 master key is the all-zero key developer machines default to
+pub fn is_zero_key(master_key_b64: &str) -> bool {
+    decode_master_key(master_key_b64).is_ok_and(|key| key.iter().all(|byte| *byte == 0))
+}
+
+impl EnvelopeCipher {
+    /// Create a cipher from a base64-encoded 32-byte master key
+    pub fn new<K: Into<String>>(key_id: K, master_key_b64: &str) -> Result<Self> {
+        let key_id = key_id.into();
+        let mut master_keys = HashMap::new();
+        master_keys.insert(key_id.clone(), decode_master_key(master_key_b64)?);
+
+        Ok(Self {
+            active_key_id: key_id,
+            master_keys,
+        })
+    }
+
+    /// Register a retired master key that can still decrypt existing secrets
+    pub fn with_previous_key<K: Into<String>>(mut self, key_id: K, master_key_b64: &str) -> Result<Self> {
+        self.master_keys.insert(key_id.into(), decode_master_key(master_key_b64)?);
+        Ok(self)
+    }
+
+    pub fn active_key_id(&self) -> &str {
+        &self.active_key_id
+    }
+
+    /// Encrypt a secret; `context` is bound as associated data (e.g. the Plaid item ID)
+    #[instrument(skip(self, plaintext, context))]
+    pub fn encrypt(&self, plaintext: &str, context: &str) -> Result<EncryptedSecret> {
+        let master_key = self
+            .master_keys
+            .get(&self.active_key_id)
+            .ok_or_else(|| anyhow!("Active master key is not loaded"))?;
+
+        // Encrypt the secret with a fresh data key
+        let data_key = Aes256Gcm::generate_key(&mut OsRng);
+        let data_cipher = Aes256Gcm::new(&data_key);
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = data_cipher
+            .encrypt(&nonce, Payload { msg: plaintext.as_bytes(), aad: context.as_bytes() })
+            .map_err(|_| anyhow!("Failed to encrypt secret"))?;
+
+        // Wrap the data key with the master key
+        let master_cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(master_key));
+        let key_nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let wrapped_key = master_cipher
+            .encrypt(&key_nonce, Payload { msg: data_key.as_slice(), aad: self.active_key_id.as_bytes() })
+            .map_err(|_| anyhow!("Failed to wrap data key"))?;
+
+        let mut encrypted_data_key = key_nonce.to_vec();
+        encrypted_data_key.extend_from_slice(&wrapped_key);
+
+        debug!(key_id = %self.active_key_id, "Encrypted secret with envelope encryption");
+
+        Ok(EncryptedSecret {
+            ciphertext,
+            nonce: nonce.to_vec(),
+            encrypted_data_key,
+            key_id: self.active_key_id.clone(),
+        })
+    }
+
+    /// Decrypt a secret produced by `encrypt` with the same `context`
+    #[instrument(skip(self, secret, context), fields(key_id = %secret.key_id))]
+    pub fn decrypt(&self, secret: &EncryptedSecret, context: &str) -> Result<String> {
+        let master_key = self
+            .master_keys
+            .get(&secret.key_id)
+            .ok_or_else(|| anyhow!("Unknown master key id '{}'", secret.key_id))?;
+
+        if secret.encrypted_data_key.len() <= NONCE_LEN || secret.nonce.len() != NONCE_LEN {
+            return Err(anyhow!("Malformed encrypted secret"));
+        }
+
+        let (key_nonce, wrapped_key) = secret.encrypted_data_key.split_at(NONCE_LEN);
+        let master_cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(master_key));
+        let data_key = master_cipher
+            .decrypt(Nonce::from_slice(key_nonce), Payload { msg: wrapped_key, aad: secret.key_id.as_bytes() })
+            .map_err(|_| anyhow!("Failed to unwrap data key"))?;
+
+        if data_key.len() != KEY_LEN {
+            return Err(anyhow!("Unwrapped data key has invalid length"));
+        }
+
+        let data_cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&data_key));
+        let plaintext = data_cipher
+            .decrypt(Nonce::from_slice(&secret.nonce), Payload { msg: &secret.ciphertext, aad: context.as_bytes() })
+            .map_err(|_| anyhow!("Failed to decrypt secret"))?;
+
+        String::from_utf8(plaintext).context("Decrypted secret is not valid UTF-8")
+    }
+
+    /// Whether a secret was wrapped by a retired key and should be re-encrypted
+    pub fn needs_rotation(&self, secret: &EncryptedSecret) -> bool {
+        secret.key_id != self.active_key_id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_key(byte: u8) -> String {
+        STANDARD.encode([byte; KEY_LEN])
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_round_trip() {
+        let cipher = EnvelopeCipher::new("test-v1", &test_key(7)).unwrap();
+        let secret = cipher.encrypt("access-sandbox-123", "item_1").unwrap();
+
+        assert_ne!(secret.ciphertext, b"access-sandbox-123".to_vec());
+        assert_eq!(secret.key_id, "test-v1");
+        assert_eq!(cipher.decrypt(&secret, "item_1").unwrap(), "access-sandbox-123");
+    }
+
+    #[test]
+    fn test_decrypt_fails_with_wrong_context() {
+        let cipher = EnvelopeCipher::new("test-v1", &test_key(7)).unwrap();
+        let secret = cipher.encrypt("access-sandbox-123", "item_1").unwrap();
+        assert!(cipher.decrypt(&secret, "item_2").is_err());
+    }
+
+    #[test]
+    fn test_key_rotation() {
+        let old_cipher = EnvelopeCipher::new("v1", &test_key(1)).unwrap();
+        let secret = old_cipher.encrypt("access-sandbox-123", "item_1").unwrap();
+
+        let new_cipher = EnvelopeCipher::new("v2", &test_key(2))
+            .unwrap()
+            .with_previous_key("v1", &test_key(1))
+            .unwrap();
+        assert!(new_cipher.needs_rotation(&secret));
+        assert_eq!(new_cipher.decrypt(&secret, "item_1").unwrap(), "access-sandbox-123");
+
+        let unaware_cipher = EnvelopeCipher::new("v2", &test_key(2)).unwrap();
+        assert!(unaware_cipher.decrypt(&secret, "item_1").is_err());
+    }
+
+    #[test]
+    fn test_rejects_invalid_master_key() {
+        assert!(EnvelopeCipher::new("bad", "not-base64!").is_err());
+        assert!(EnvelopeCipher::new("short", &STANDARD.encode([0u8; 16])).is_err());
+    }
+
+    #[test]
+    fn test_is_zero_key() {
+        assert!(is_zero_key(&test_key(0)));
+        assert!(!is_zero_key(&test_key(1)));
+        assert!(!is_zero_key("not-base64!"));
+    }
+}
@@ -0,0 +1,261 @@
+use crate::settings::SettingValues;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use reqwest::Client;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{debug, info, instrument, warn};
+
+/// Configuration for the exchange-rate client
+#[derive(Debug, Clone)]
+pub struct FxConfig {
+    /// ECB euro foreign exchange reference rates feed
+    pub rates_url: String,
+    /// How long fetched rates are served before refetching
+    pub cache_ttl_seconds: u64,
+    /// Request timeout in seconds
+    pub timeout_seconds: u64,
+}
+
+impl Default for FxConfig {
+    fn default() -> Self {
+        Self {
+            rates_url: "https://www.ecb.europa.eu/stats/eurofxref/eurofxref-daily.xml".to_string(),
+            cache_ttl_seconds: 6 * 60 * 60,
+            timeout_seconds: 10,
+        }
+    }
+}
+
+impl FxConfig {
+    /// Read from `FX_RATES_URL` and `FX_RATES_CACHE_TTL_SECONDS`
+    pub fn from_values(values: &mut SettingValues) -> Self {
+        let defaults = Self::default();
+        Self {
+            rates_url: values.or("fx-rates-url", &defaults.rates_url),
+            cache_ttl_seconds: values.parsed("fx-rates-cache-ttl-seconds", defaults.cache_ttl_seconds),
+            timeout_seconds: defaults.timeout_seconds,
+        }
+    }
+}
+
+/// Daily reference rates, quoted as units of each currency per euro
+#[derive(Debug, Clone, PartialEq)]
+pub struct FxRates {
+    pub date: NaiveDate,
+    rates: HashMap<String, f64>,
+}
+
+impl FxRates {
+    pub fn new(date: NaiveDate, mut rates: HashMap<String, f64>) -> Self {
+        rates.insert("EUR".to_string(), 1.0);
+        Self { date, rates }
+    }
+
+    pub fn supports(&self, currency: &str) -> bool {
+        self.rates.contains_key(&currency.to_ascii_uppercase())
+    }
+
+    /// Currencies with a rate, sorted
+    pub fn currencies(&self) -> Vec<String> {
+        let mut currencies: Vec<String> = self.rates.keys().cloned().collect();
+        currencies.sort();
+        currencies
+    }
+
+    /// Convert `amount` between currencies through the euro cross rate; `None` if either is unknown
+    pub fn convert(&self, amount: f64, from: &str, to: &str) -> Option<f64> {
+        let from = self.rates.get(&from.to_ascii_uppercase())?;
+        let to = self.rates.get(&to.to_ascii_uppercase())?;
+        Some(amount / from * to)
+    }
+
+    /// Add an asset without a reference rate, such as a crypto currency, from its price in a
+    /// supported currency; `false` when the quote currency is unknown or the price is not positive
+    pub fn add_asset_price(&mut self, asset: &str, price: f64, quote: &str) -> bool {
+        let Some(quote_rate) = self.rates.get(&quote.to_ascii_uppercase()).copied() else {
+            return false;
+        };
+        if price <= 0.0 {
+            return false;
+        }
+        self.rates.insert(asset.to_ascii_uppercase(), quote_rate / price);
+        true
+    }
+}
+
+/// Parse the ECB `eurofxref-daily.xml` feed
+pub fn parse_ecb_rates(xml: &str) -> Result<FxRates> {
+    let mut date = None;
+    let mut rates = HashMap::new();
+
+    for element in xml.split('<').filter(|e| e.starts_with("Cube ")) {
+        if let Some(time) = attribute(element, "time") {
+            date = Some(
+                NaiveDate::parse_from_str(time, "%Y-%m-%d")
+                    .with_context(|| format!("Invalid ECB rate date '{}'", time))?,
+            );
+        }
+        if let (Some(currency), Some(rate)) = (attribute(element, "currency"), attribute(element, "rate")) {
+            let rate: f64 = rate
+                .parse()
+                .with_context(|| format!("Invalid ECB rate '{}' for {}", rate, currency))?;
+            if rate > 0.0 {
+                rates.insert(currency.to_ascii_uppercase(), rate);
+            }
+        }
+    }
+
+    let Some(date) = date else {
+        bail!("ECB rates feed has no date");
+    };
+    if rates.is_empty() {
+        bail!("ECB rates feed has no rates");
+    }
+    Ok(FxRates::new(date, rates))
+}
+
+/// Value of `name='...'` or `name="..."` in an XML start tag
+fn attribute<'a>(element: &'a str, name: &str) -> Option<&'a str> {
+    for quote in ['\'', '"'] {
+        let prefix = format!("{}={}", name, quote);
+        if let Some(start) = element.find(&prefix) {
+            let value = &element[start + prefix.len()..];
+            return value.find(quote).map(|end| &value[..end]);
+        }
+    }
+    None
+}
+
+#[derive(Debug)]
+struct CachedRates {
+    rates: Arc<FxRates>,
+    fetched_at: DateTime<Utc>,
+}
+
+/// Client for daily exchange rates with an in-process cache
+#[derive(Debug, Clone)]
+pub struct FxClient {
+    config: FxConfig,
+    client: Client,
+    cache: Arc<RwLock<Option<CachedRates>>>,
+}
+
+impl FxClient {
+    pub fn new(config: FxConfig) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(config.timeout_seconds))
+            .build()
+            .context("Failed to create HTTP client")?;
+
+        Ok(Self {
+            config,
+            client,
+            cache: Arc::new(RwLock::new(None)),
+        })
+    }
+
+    /// Latest rates, refetched once the cache is older than the TTL.
+    ///
+    /// When a refetch fails the previous rates keep being served, so a feed outage only
+    /// makes conversions stale rather than unavailable.
+    #[instrument(skip(self))]
+    pub async fn rates(&self) -> Result<Arc<FxRates>> {
+        let ttl = chrono::Duration::seconds(self.config.cache_ttl_seconds as i64);
+        if let Some(cached) = self.cache.read().await.as_ref() {
+            if Utc::now() - cached.fetched_at < ttl {
+                return Ok(cached.rates.clone());
+            }
+        }
+
+        let mut cache = self.cache.write().await;
+        // Another caller may have refreshed while this one waited for the lock
+        if let Some(cached) = cache.as_ref() {
+            if Utc::now() - cached.fetched_at < ttl {
+                return Ok(cached.rates.clone());
+            }
+        }
+
+        match self.fetch().await {
+            Ok(rates) => {
+                let rates = Arc::new(rates);
+                *cache = Some(CachedRates {
+                    rates: rates.clone(),
+                    fetched_at: Utc::now(),
+                });
+                Ok(rates)
+            }
+            Err(e) => match cache.as_ref() {
+                Some(cached) => {
+                    warn!(error = ?e, rate_date = %cached.rates.date, "Failed to refresh exchange rates; serving cached rates");
+                    Ok(cached.rates.clone())
+                }
+                None => Err(e),
+            },
+        }
+    }
+
+    async fn fetch(&self) -> Result<FxRates> {
+        debug!(url = %self.config.rates_url, "Fetching exchange rates");
+        let response = self
+            .client
+            .get(&self.config.rates_url)
+            .send()
+            .await
+            .context("Failed to request exchange rates")?
+            .error_for_status()
+            .context("Exchange rate feed returned an error")?;
+        let body = response.text().await.context("Failed to read exchange rates")?;
+
+        let rates = parse_ecb_rates(&body)?;
+        info!(rate_date = %rates.date, currency_count = rates.rates.len(), "Fetched exchange rates");
+        Ok(rates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ECB_FEED: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gesmes:Envelope xmlns:gesmes="http://www.gesmes.org/xml/2002-08-01" xmlns="http://www.ecb.int/vocabulary/2002-08-01/eurofxref">
+	<gesmes:subject>Reference rates</gesmes:subject>
+	<Cube>
+		<Cube time='2024-03-01'>
+			<Cube currency='USD' rate='1.0820'/>
+			<Cube currency='JPY' rate='162.50'/>
+			<Cube currency='GBP' rate='0.85550'/>
+		</Cube>
+	</Cube>
+</gesmes:Envelope>"#;
+
+    #[test]
+    fn test_parse_ecb_rates() {
+        let rates = parse_ecb_rates(ECB_FEED).unwrap();
+        assert_eq!(rates.date, NaiveDate::from_ymd_opt(2024, 3, 1).unwrap());
+        assert_eq!(rates.currencies(), vec!["EUR", "GBP", "JPY", "USD"]);
+        assert!(parse_ecb_rates("<Cube><Cube currency='USD' rate='1.08'/></Cube>").is_err());
+    }
+
+    #[test]
+    fn test_convert_through_euro() {
+        let rates = parse_ecb_rates(ECB_FEED).unwrap();
+        assert!((rates.convert(108.2, "USD", "EUR").unwrap() - 100.0).abs() < 1e-9);
+        assert!((rates.convert(100.0, "eur", "jpy").unwrap() - 16250.0).abs() < 1e-9);
+        assert!((rates.convert(1.082, "USD", "GBP").unwrap() - 0.8555).abs() < 1e-9);
+        assert_eq!(rates.convert(1.0, "USD", "BTC"), None);
+    }
+
+    #[test]
+    fn test_asset_price_converts_through_quote() {
+        let mut rates = parse_ecb_rates(ECB_FEED).unwrap();
+        assert!(rates.add_asset_price("btc", 50_000.0, "USD"));
+        assert!((rates.convert(0.5, "BTC", "USD").unwrap() - 25_000.0).abs() < 1e-6);
+        assert!((rates.convert(1.0, "BTC", "EUR").unwrap() - 50_000.0 / 1.082).abs() < 1e-6);
+        assert!(!rates.add_asset_price("ETH", 3_000.0, "XYZ"));
+        assert!(!rates.add_asset_price("ETH", 0.0, "USD"));
+        assert!(!rates.supports("ETH"));
+    }
+}
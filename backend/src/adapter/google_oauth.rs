@@ -1,3 +1,4 @@
+use crate::settings::SettingValues;
 use anyhow::{Context, Result};
 use oauth2::{
     basic::BasicClient, reqwest::async_http_client, AuthUrl, AuthorizationCode, ClientId,
@@ -43,6 +44,29 @@ impl Default for GoogleOAuthConfig {
     }
 }
 
+impl GoogleOAuthConfig {
+    /// Read from settings
+    /// - GOOGLE_OAUTH_CLIENT_ID / GOOGLE_OAUTH_CLIENT_SECRET: OAuth client credentials (required)
+    /// - GOOGLE_OAUTH_REDIRECT_URI: Callback URL (default: http://localhost:3000/auth/callback)
+    /// - GOOGLE_OAUTH_SCOPES: Comma-separated scopes (default: openid,email,profile)
+    /// - GOOGLE_OAUTH_TIMEOUT_SECONDS: Request timeout (default: 30)
+    /// - GOOGLE_OAUTH_MAX_RETRIES: Retries of failed requests (default: 3)
+    pub fn from_values(values: &mut SettingValues) -> Self {
+        let defaults = Self::default();
+        Self {
+            client_id: values.required("google-oauth-client-id"),
+            client_secret: values.required("google-oauth-client-secret"),
+            redirect_uri: values.or("google-oauth-redirect-uri", &defaults.redirect_uri),
+            scopes: match values.optional("google-oauth-scopes") {
+                Some(scopes) => scopes.split(',').map(|s| s.trim().to_string()).collect(),
+                None => defaults.scopes,
+            },
+            timeout_seconds: values.parsed("google-oauth-timeout-seconds", defaults.timeout_seconds),
+            max_retries: values.parsed("google-oauth-max-retries", defaults.max_retries),
+        }
+    }
+}
+
 /// Google user profile information from OAuth
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GoogleUser {
@@ -111,36 +135,6 @@ impl GoogleOAuthClient {
         })
     }
 
-    /// Create a new Google OAuth client from environment variables
-    pub fn from_env() -> Result<Self> {
-        let client_id = std::env::var("GOOGLE_OAUTH_CLIENT_ID")
-            .context("GOOGLE_OAUTH_CLIENT_ID environment variable not set")?;
-        let client_secret = std::env::var("GOOGLE_OAUTH_CLIENT_SECRET")
-            .context("GOOGLE_OAUTH_CLIENT_SECRET environment variable not set")?;
-
-        let config = GoogleOAuthConfig {
-            client_id,
-            client_secret,
-            redirect_uri: std::env::var("GOOGLE_OAUTH_REDIRECT_URI")
-                .unwrap_or_else(|_| "http://localhost:3000/auth/callback".to_string()),
-            scopes: std::env::var("GOOGLE_OAUTH_SCOPES")
-                .unwrap_or_else(|_| "openid,email,profile".to_string())
-                .split(',')
-                .map(|s| s.trim().to_string())
-                .collect(),
-            timeout_seconds: std::env::var("GOOGLE_OAUTH_TIMEOUT_SECONDS")
-                .unwrap_or_else(|_| "30".to_string())
-                .parse()
-                .unwrap_or(30),
-            max_retries: std::env::var("GOOGLE_OAUTH_MAX_RETRIES")
-                .unwrap_or_else(|_| "3".to_string())
-                .parse()
-                .unwrap_or(3),
-        };
-
-        Self::new(config)
-    }
-
     /// Generate authorization URL for OAuth flow
     #[instrument(skip(self))]
     pub fn get_authorization_url(&self, _use_pkce: bool) -> AuthorizationUrl {
@@ -1,3 +1,4 @@
+use crate::settings::Settings;
 use anyhow::{anyhow, Context, Result};
 use chrono::{DateTime, Duration, Utc};
 use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
@@ -19,44 +20,16 @@ pub struct JwtConfig {
 }
 
 impl JwtConfig {
-    /// Create JWT configuration from environment variables
-    pub fn from_env() -> Result<Self> {
-        let secret = std::env::var("JWT_SECRET")
-            .context("JWT_SECRET environment variable is required")?;
-        
-        if secret.len() < 32 {
-            return Err(anyhow!("JWT_SECRET must be at least 32 characters long"));
+    /// Configuration with the signing secret, issuer, audience and lifetimes from settings
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self {
+            secret: settings.jwt.secret_key.clone(),
+            algorithm: Algorithm::HS256,
+            access_token_expiry: Duration::minutes(settings.jwt.access_token_expires_minutes),
+            refresh_token_expiry: Duration::days(settings.jwt.refresh_token_expires_days),
+            issuer: settings.jwt.issuer.clone(),
+            audience: settings.jwt.audience.clone(),
         }
-
-        let algorithm = std::env::var("JWT_ALGORITHM")
-            .unwrap_or_else(|_| "HS256".to_string())
-            .parse::<Algorithm>()
-            .context("Invalid JWT_ALGORITHM value")?;
-
-        let access_token_hours = std::env::var("JWT_ACCESS_TOKEN_EXPIRY_HOURS")
-            .unwrap_or_else(|_| "1".to_string())
-            .parse::<i64>()
-            .context("Invalid JWT_ACCESS_TOKEN_EXPIRY_HOURS value")?;
-
-        let refresh_token_days = std::env::var("JWT_REFRESH_TOKEN_EXPIRY_DAYS")
-            .unwrap_or_else(|_| "30".to_string())
-            .parse::<i64>()
-            .context("Invalid JWT_REFRESH_TOKEN_EXPIRY_DAYS value")?;
-
-        let issuer = std::env::var("JWT_ISSUER")
-            .unwrap_or_else(|_| "origin-backend".to_string());
-
-        let audience = std::env::var("JWT_AUDIENCE")
-            .unwrap_or_else(|_| "origin-frontend".to_string());
-
-        Ok(Self {
-            secret,
-            algorithm,
-            access_token_expiry: Duration::hours(access_token_hours),
-            refresh_token_expiry: Duration::days(refresh_token_days),
-            issuer,
-            audience,
-        })
     }
 }
 
@@ -129,10 +102,9 @@ impl JwtService {
         })
     }
 
-    /// Create JWT service from environment variables
-    pub fn from_env() -> Result<Self> {
-        let config = JwtConfig::from_env()?;
-        Self::new(config)
+    /// Create JWT service from settings
+    pub fn from_settings(settings: &Settings) -> Result<Self> {
+        Self::new(JwtConfig::from_settings(settings))
     }
 
     /// Generate a token pair (access + refresh) for a user
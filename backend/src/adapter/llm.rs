@@ -0,0 +1,321 @@
+// Text generation behind a vendor-neutral trait, so assistant features can run on Claude or an OpenAI-compatible API
+use crate::adapter::claude_ai::{
+    ClaudeAIClient, ClaudeMessage, ClaudeRequest, ClaudeStreamEvent, ClaudeSystem, ClaudeUsage,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Name of the Anthropic Claude provider
+pub const CLAUDE_PROVIDER: &str = "claude";
+
+/// Author of a conversation turn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LlmRole {
+    User,
+    Assistant,
+}
+
+impl LlmRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LlmRole::User => "user",
+            LlmRole::Assistant => "assistant",
+        }
+    }
+}
+
+/// One turn of a conversation
+#[derive(Debug, Clone, PartialEq)]
+pub struct LlmMessage {
+    pub role: LlmRole,
+    pub content: String,
+}
+
+impl LlmMessage {
+    pub fn user(content: &str) -> Self {
+        Self {
+            role: LlmRole::User,
+            content: content.to_string(),
+        }
+    }
+
+    pub fn assistant(content: &str) -> Self {
+        Self {
+            role: LlmRole::Assistant,
+            content: content.to_string(),
+        }
+    }
+}
+
+/// Generation request; turns alternate between user and assistant, starting with the user
+#[derive(Debug, Clone, Default)]
+pub struct LlmRequest {
+    /// Model to use; the provider's default when unset
+    pub model: Option<String>,
+    pub system: Option<String>,
+    /// Ask the provider to cache the system prompt; providers that cache automatically ignore it
+    pub cache_system: bool,
+    pub messages: Vec<LlmMessage>,
+    pub max_tokens: u32,
+    pub temperature: Option<f32>,
+    pub stop_sequences: Vec<String>,
+}
+
+/// Tokens read and generated by one request
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LlmUsage {
+    /// Input tokens read outside the provider's prompt cache
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    /// Input tokens read from the prompt cache
+    pub cache_read_tokens: u32,
+    /// Input tokens written to the prompt cache
+    pub cache_write_tokens: u32,
+}
+
+/// Complete reply to a request
+#[derive(Debug, Clone)]
+pub struct LlmResponse {
+    pub text: String,
+    /// Model that generated the reply
+    pub model: String,
+    /// `end_turn`, `max_tokens`, `stop_sequence` or a provider-specific reason
+    pub stop_reason: Option<String>,
+    pub usage: LlmUsage,
+}
+
+/// Incremental output of a streamed reply
+#[derive(Debug, Clone, PartialEq)]
+pub enum LlmStreamEvent {
+    /// Text appended to the reply
+    TextDelta(String),
+    /// Last event of the reply
+    Stop { stop_reason: Option<String>, usage: LlmUsage },
+}
+
+/// Streamed reply; ends after the stop event or the first error
+pub type LlmStream = BoxStream<'static, Result<LlmStreamEvent>>;
+
+/// Operations assistant features need from a text generation API
+#[async_trait]
+pub trait LlmProvider: Send + Sync {
+    /// Name clients use to select the provider
+    fn name(&self) -> &'static str;
+
+    /// Model used for requests that don't name one
+    fn default_model(&self) -> &str;
+
+    /// Generate a complete reply
+    async fn send(&self, request: LlmRequest) -> Result<LlmResponse>;
+
+    /// Generate a reply, streaming text as it is produced
+    async fn stream(&self, request: LlmRequest) -> Result<LlmStream>;
+
+    /// Input tokens the request's system prompt and messages would use
+    async fn count_tokens(&self, request: &LlmRequest) -> Result<u32>;
+}
+
+fn claude_messages(messages: &[LlmMessage]) -> Vec<ClaudeMessage> {
+    messages
+        .iter()
+        .map(|m| ClaudeAIClient::create_message(m.role.as_str(), &m.content))
+        .collect()
+}
+
+fn claude_usage(usage: &ClaudeUsage) -> LlmUsage {
+    LlmUsage {
+        input_tokens: usage.input_tokens,
+        output_tokens: usage.output_tokens,
+        cache_read_tokens: usage.cache_read_input_tokens,
+        cache_write_tokens: usage.cache_creation_input_tokens,
+    }
+}
+
+impl ClaudeAIClient {
+    fn llm_request(&self, request: LlmRequest, stream: bool) -> ClaudeRequest {
+        ClaudeRequest {
+            model: request.model.unwrap_or_else(|| self.config().default_model.clone()),
+            max_tokens: request.max_tokens,
+            temperature: request.temperature,
+            messages: claude_messages(&request.messages),
+            system: request.system.map(|system| {
+                if request.cache_system {
+                    ClaudeSystem::cached(system)
+                } else {
+                    ClaudeSystem::Text(system)
+                }
+            }),
+            stop_sequences: Some(request.stop_sequences).filter(|s| !s.is_empty()),
+            stream: Some(stream),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for ClaudeAIClient {
+    fn name(&self) -> &'static str {
+        CLAUDE_PROVIDER
+    }
+
+    fn default_model(&self) -> &str {
+        &self.config().default_model
+    }
+
+    async fn send(&self, request: LlmRequest) -> Result<LlmResponse> {
+        let response = self.send_message(self.llm_request(request, false)).await?;
+        Ok(LlmResponse {
+            text: response.content.iter().map(|c| c.text.as_str()).collect(),
+            model: response.model,
+            stop_reason: response.stop_reason,
+            usage: claude_usage(&response.usage),
+        })
+    }
+
+    async fn stream(&self, request: LlmRequest) -> Result<LlmStream> {
+        let reply = self.send_message_stream(self.llm_request(request, true)).await?;
+
+        let events = futures::stream::unfold(Some(reply), |reply| async move {
+            let mut reply = reply?;
+            match reply.next_event().await {
+                Ok(Some(ClaudeStreamEvent::TextDelta(text))) => Some((Ok(LlmStreamEvent::TextDelta(text)), Some(reply))),
+                Ok(Some(ClaudeStreamEvent::Stop { stop_reason, usage })) => Some((
+                    Ok(LlmStreamEvent::Stop {
+                        stop_reason,
+                        usage: claude_usage(&usage),
+                    }),
+                    Some(reply),
+                )),
+                Ok(None) => None,
+                Err(e) => Some((Err(e), None)),
+            }
+        });
+        Ok(Box::pin(events))
+    }
+
+    async fn count_tokens(&self, request: &LlmRequest) -> Result<u32> {
+        let model = request.model.as_deref().unwrap_or(&self.config().default_model);
+        ClaudeAIClient::count_tokens(self, model, &claude_messages(&request.messages), request.system.as_deref()).await
+    }
+}
+
+/// Configured LLM providers with a default for requests that don't pick one
+#[derive(Clone, Default)]
+pub struct LlmProviders {
+    providers: HashMap<&'static str, Arc<dyn LlmProvider>>,
+    default: Option<&'static str>,
+}
+
+impl LlmProviders {
+    /// Register a provider; the first one registered is the default
+    pub fn with_provider(mut self, provider: Arc<dyn LlmProvider>) -> Self {
+        let name = provider.name();
+        self.default.get_or_insert(name);
+        self.providers.insert(name, provider);
+        self
+    }
+
+    /// Make a registered provider the default
+    pub fn with_default(mut self, provider: &str) -> Result<Self> {
+        let (name, _) = self
+            .providers
+            .get_key_value(provider)
+            .ok_or_else(|| anyhow!("Unknown LLM provider '{}'", provider))?;
+        self.default = Some(*name);
+        Ok(self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.providers.is_empty()
+    }
+
+    /// Provider named by a request, or the default when it names none
+    pub fn resolve(&self, provider: Option<&str>) -> Result<Arc<dyn LlmProvider>> {
+        let name = match provider.map(str::trim).filter(|p| !p.is_empty()) {
+            Some(name) => name,
+            None => self.default.ok_or_else(|| anyhow!("No LLM provider is configured"))?,
+        };
+        self.providers
+            .get(name)
+            .cloned()
+            .ok_or_else(|| anyhow!("LLM provider '{}' is not configured", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct StubProvider(&'static str);
+
+    #[async_trait]
+    impl LlmProvider for StubProvider {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn default_model(&self) -> &str {
+            "stub"
+        }
+
+        async fn send(&self, _request: LlmRequest) -> Result<LlmResponse> {
+            unimplemented!()
+        }
+
+        async fn stream(&self, _request: LlmRequest) -> Result<LlmStream> {
+            unimplemented!()
+        }
+
+        async fn count_tokens(&self, _request: &LlmRequest) -> Result<u32> {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    fn test_provider_selection() {
+        let providers = LlmProviders::default()
+            .with_provider(Arc::new(StubProvider("claude")))
+            .with_provider(Arc::new(StubProvider("openai")));
+
+        assert_eq!(providers.resolve(None).unwrap().name(), "claude");
+        assert_eq!(providers.resolve(Some(" ")).unwrap().name(), "claude");
+        assert_eq!(providers.resolve(Some("openai")).unwrap().name(), "openai");
+        assert!(providers.resolve(Some("gemini")).is_err());
+
+        let providers = providers.with_default("openai").unwrap();
+        assert_eq!(providers.resolve(None).unwrap().name(), "openai");
+        assert!(providers.with_default("gemini").is_err());
+    }
+
+    #[test]
+    fn test_no_providers_configured() {
+        let providers = LlmProviders::default();
+        assert!(providers.is_empty());
+        assert!(providers.resolve(None).is_err());
+    }
+
+    #[test]
+    fn test_claude_request_mapping() {
+        let client = ClaudeAIClient::new(crate::adapter::claude_ai::ClaudeAIConfig {
+            api_key: "test-key".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        let request = client.llm_request(
+            LlmRequest {
+                system: Some("Be brief".to_string()),
+                messages: vec![LlmMessage::user("Hi"), LlmMessage::assistant("Hello")],
+                max_tokens: 100,
+                ..Default::default()
+            },
+            true,
+        );
+
+        assert_eq!(request.model, client.config().default_model);
+        assert_eq!(request.messages[1].role, "assistant");
+        assert_eq!(request.stop_sequences, None);
+        assert_eq!(request.stream, Some(true));
+    }
+}
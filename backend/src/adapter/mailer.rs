@@ -0,0 +1,751 @@
+// Builds the app's emails from their templates and hands them to an email transport
+use crate::adapter::email_idempotency::{EmailIdempotency, EmailInProgress, IdempotencyClaim};
+use crate::adapter::email_preferences::{EmailCategory, EmailPreferenceSource, EmailSuppressed, UnsubscribeLinks};
+use crate::adapter::email_templates::{
+    EmailBrand, EmailLocale, EmailTemplateContent, EmailTemplateName, EmailTemplateSource,
+};
+use crate::adapter::email_transport::{DevEmailTransport, EmailTransport, FailoverTransport};
+use crate::adapter::postmark::{PostmarkClient, PostmarkConfig};
+use crate::adapter::ses::{EmailPriority, EmailRequest, EmailResponse, SESClient, SESConfig, TemplateData};
+use crate::adapter::smtp::{SmtpClient, SmtpConfig};
+use crate::settings::SettingValues;
+use anyhow::{bail, Context, Result};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, instrument, warn};
+
+/// How emails are delivered and drawn, a section of `Settings`. Not `Debug`, as it holds secrets.
+#[derive(Clone)]
+pub struct EmailSettings {
+    /// ses, smtp, postmark or dev
+    pub transport: String,
+    /// Transport to fail over to when the first keeps failing
+    pub fallback_transport: Option<String>,
+    /// Failures in a row before failing over
+    pub failover_threshold: u32,
+    /// How long before the first transport is tried again
+    pub failover_cooldown: Duration,
+    /// `None` when no SES sender is configured
+    pub ses: Option<SESConfig>,
+    /// `None` when no SMTP server is configured
+    pub smtp: Option<SmtpConfig>,
+    /// `None` when no Postmark token is configured
+    pub postmark: Option<PostmarkConfig>,
+    /// From address of the dev transport
+    pub dev_sender: String,
+    /// Directory the dev transport writes `.eml` files to
+    pub dev_dir: Option<PathBuf>,
+    pub brand: EmailBrand,
+    /// `None` leaves optional emails without an unsubscribe link
+    pub unsubscribe_links: Option<UnsubscribeLinks>,
+}
+
+impl EmailSettings {
+    /// Read from settings
+    /// - EMAIL_TRANSPORT: ses, smtp, postmark or dev (default: dev when `ENVIRONMENT=local`, ses otherwise)
+    /// - EMAIL_FALLBACK_TRANSPORT: Transport to fail over to when the first keeps failing (default:
+    ///   smtp with ses when SMTP_HOST is set)
+    /// - EMAIL_FAILOVER_THRESHOLD: Failures in a row before failing over (default: 5)
+    /// - EMAIL_FAILOVER_COOLDOWN_SECS: Seconds before the first transport is tried again (default: 300)
+    /// - EMAIL_DEV_DIR: Directory for the dev transport's `.eml` files (optional; log only when unset)
+    /// - the settings of each transport (`SESConfig`, `SmtpConfig`, `PostmarkConfig`), the brand
+    ///   (`EmailBrand`) and the unsubscribe links (`UnsubscribeLinks`)
+    pub fn from_values(values: &mut SettingValues) -> Self {
+        let transport = values.or("email-transport", if values.is_local() { "dev" } else { "ses" });
+        let fallback_transport = match values.optional("email-fallback-transport") {
+            Some(fallback) => Some(fallback),
+            None if transport == "ses" && values.optional("smtp-host").is_some() => Some("smtp".to_string()),
+            None => None,
+        };
+        Self {
+            transport,
+            fallback_transport,
+            failover_threshold: values.parsed("email-failover-threshold", 5),
+            failover_cooldown: Duration::from_secs(values.parsed("email-failover-cooldown-secs", 300)),
+            ses: SESConfig::from_values(values),
+            smtp: SmtpConfig::from_values(values),
+            postmark: PostmarkConfig::from_values(values),
+            dev_sender: values.or("aws-ses-default-sender", "noreply@localhost"),
+            dev_dir: values.optional("email-dev-dir").map(PathBuf::from),
+            brand: EmailBrand::from_values(values),
+            unsubscribe_links: UnsubscribeLinks::from_values(values),
+        }
+    }
+}
+
+/// Sends the app's templated emails through a transport
+pub struct Mailer {
+    transport: Arc<dyn EmailTransport>,
+    templates: Option<Arc<dyn EmailTemplateSource>>,
+    preferences: Option<Arc<dyn EmailPreferenceSource>>,
+    unsubscribe_links: Option<UnsubscribeLinks>,
+    brand: EmailBrand,
+    idempotency: Option<Arc<dyn EmailIdempotency>>,
+}
+
+impl Mailer {
+    pub fn new(transport: Arc<dyn EmailTransport>) -> Self {
+        Self {
+            transport,
+            templates: None,
+            preferences: None,
+            unsubscribe_links: None,
+            brand: EmailBrand::default(),
+            idempotency: None,
+        }
+    }
+
+    /// Mailer sending through the transports in `settings`, failing over from the first to the fallback
+    /// when the first keeps failing
+    pub async fn from_settings(settings: &EmailSettings) -> Result<Self> {
+        let primary = build_transport(&settings.transport, settings).await?;
+        let transport: Arc<dyn EmailTransport> = match &settings.fallback_transport {
+            Some(fallback) if *fallback == settings.transport => {
+                bail!("EMAIL_FALLBACK_TRANSPORT must differ from EMAIL_TRANSPORT")
+            }
+            Some(fallback) => Arc::new(FailoverTransport::new(
+                primary,
+                build_transport(fallback, settings).await?,
+                settings.failover_threshold,
+                settings.failover_cooldown,
+            )),
+            None => primary,
+        };
+        info!(transport = transport.name(), "Email transport selected");
+        Ok(Self::new(transport).with_brand(settings.brand.clone()))
+    }
+
+    /// Send the active version of each template from `templates` instead of the compiled default
+    pub fn with_templates(mut self, templates: Arc<dyn EmailTemplateSource>) -> Self {
+        self.templates = Some(templates);
+        self
+    }
+
+    /// Skip optional emails to recipients who opted out of their category, and with `links`, add a
+    /// one-click unsubscribe link to those emails
+    pub fn with_preferences(
+        mut self,
+        preferences: Arc<dyn EmailPreferenceSource>,
+        links: Option<UnsubscribeLinks>,
+    ) -> Self {
+        self.preferences = Some(preferences);
+        self.unsubscribe_links = links;
+        self
+    }
+
+    /// Draw the email layout with `brand`
+    pub fn with_brand(mut self, brand: EmailBrand) -> Self {
+        self.brand = brand;
+        self
+    }
+
+    /// Send requests with an idempotency key at most once, remembering them in `idempotency`
+    pub fn with_idempotency(mut self, idempotency: Arc<dyn EmailIdempotency>) -> Self {
+        self.idempotency = Some(idempotency);
+        self
+    }
+
+    pub fn brand(&self) -> &EmailBrand {
+        &self.brand
+    }
+
+    pub fn transport_name(&self) -> &'static str {
+        self.transport.name()
+    }
+
+    /// Template to send in `locale`, in the branded layout: its translation when there is one,
+    /// otherwise the English active version
+    async fn template(&self, name: EmailTemplateName, locale: EmailLocale) -> EmailTemplateContent {
+        match name.translated_template(locale) {
+            Some(translated) => translated.with_layout(&self.brand, locale),
+            None => self
+                .active_template(name)
+                .await
+                .with_layout(&self.brand, EmailLocale::English),
+        }
+    }
+
+    /// Active version of the template, or the compiled default when none is active or it can't be
+    /// loaded
+    async fn active_template(&self, name: EmailTemplateName) -> EmailTemplateContent {
+        let Some(templates) = &self.templates else {
+            return name.default_template();
+        };
+        match templates.active_template(name).await {
+            Ok(Some(template)) => template,
+            Ok(None) => name.default_template(),
+            Err(e) => {
+                warn!(template = name.as_str(), error = ?e, "Failed to load email template; using the default");
+                name.default_template()
+            }
+        }
+    }
+
+    /// Send an email. One whose idempotency key was sent already isn't sent again; the first
+    /// send's response is returned. One whose key another send holds fails with
+    /// [`EmailInProgress`].
+    pub async fn send_email(&self, request: EmailRequest) -> Result<EmailResponse> {
+        let (Some(idempotency), Some(key)) = (&self.idempotency, request.idempotency_key.clone()) else {
+            return self.transport.send_email(request).await;
+        };
+        match idempotency.claim(&key).await {
+            Ok(IdempotencyClaim::Claimed) => {}
+            Ok(IdempotencyClaim::Sent(message_id)) => {
+                info!(message_id = %message_id, "Email already sent; not sending it again");
+                return Ok(EmailResponse {
+                    message_id,
+                    accepted: true,
+                    processing_time_ms: 0,
+                });
+            }
+            Ok(IdempotencyClaim::InProgress) => return Err(EmailInProgress.into()),
+            // Sign-in codes must still go out while Redis is down, at the risk of a duplicate
+            Err(e) => {
+                warn!(error = ?e, "Failed to claim email idempotency key; sending without it");
+                return self.transport.send_email(request).await;
+            }
+        }
+
+        let result = self.transport.send_email(request).await;
+        let recorded = match &result {
+            Ok(response) => idempotency.complete(&key, &response.message_id).await,
+            Err(_) => idempotency.release(&key).await,
+        };
+        if let Err(e) = recorded {
+            warn!(error = ?e, sent = result.is_ok(), "Failed to update email idempotency key");
+        }
+        result
+    }
+
+    /// Send an email of `category`, unless it is optional and a recipient opted out of it, which
+    /// fails with [`EmailSuppressed`]. Optional emails to one recipient get an unsubscribe link, in
+    /// `locale`, in their bodies and List-Unsubscribe headers.
+    pub async fn send_categorized_email(
+        &self,
+        mut request: EmailRequest,
+        category: EmailCategory,
+        locale: EmailLocale,
+    ) -> Result<EmailResponse> {
+        if !category.is_optional() {
+            return self.send_email(request).await;
+        }
+        if let Some(preferences) = &self.preferences {
+            for recipient in &request.to {
+                if !preferences.allows(recipient, category).await? {
+                    return Err(EmailSuppressed {
+                        email: recipient.clone(),
+                        category,
+                    }
+                    .into());
+                }
+            }
+        }
+        if let (Some(links), [recipient]) = (&self.unsubscribe_links, request.to.as_slice()) {
+            let link = links.link(recipient, category)?;
+            request = with_unsubscribe_link(request.rendered(), &link, locale);
+        }
+        self.send_email(request).await
+    }
+
+    /// Send an OTP login email with one-time password
+    #[instrument(skip(self))]
+    pub async fn send_otp_login_email<T, C>(
+        &self,
+        to_email: T,
+        otp_code: C,
+        user_name: Option<String>,
+        expires_minutes: Option<u32>,
+        locale: EmailLocale,
+        idempotency_key: Option<String>,
+    ) -> Result<EmailResponse>
+    where
+        T: Into<String> + std::fmt::Debug,
+        C: Into<String> + std::fmt::Display + std::fmt::Debug,
+    {
+        let mut template_data = TemplateData::new();
+        template_data.insert("otp_code", otp_code.to_string());
+        template_data.insert("user_name", user_name.unwrap_or_else(|| "User".to_string()));
+        template_data.insert("expires_minutes", expires_minutes.unwrap_or(5).to_string());
+
+        let template = self.template(EmailTemplateName::OtpLogin, locale).await;
+        let mut request = EmailRequest::from_template(vec![to_email], template)
+            .with_template_data(template_data)
+            .with_priority(EmailPriority::High)
+            .with_tag("email_type", "otp_login")
+            .with_tag("locale", locale.as_str())
+            .with_tag("template", "otp_verification")
+            .with_tag("security_level", "high");
+        request.idempotency_key = idempotency_key;
+
+        self.send_email(request).await
+    }
+
+    /// Send a verification email with a verification code
+    #[instrument(skip(self))]
+    pub async fn send_verification_email<T, C>(
+        &self,
+        to_email: T,
+        verification_code: C,
+        user_name: Option<String>,
+        locale: EmailLocale,
+    ) -> Result<EmailResponse>
+    where
+        T: Into<String> + std::fmt::Debug,
+        C: Into<String> + std::fmt::Display + std::fmt::Debug,
+    {
+        let mut template_data = TemplateData::new();
+        template_data.insert("verification_code", verification_code.to_string());
+        template_data.insert("user_name", user_name.unwrap_or_else(|| "User".to_string()));
+
+        let template = self.template(EmailTemplateName::Verification, locale).await;
+        let request = EmailRequest::from_template(vec![to_email], template)
+            .with_template_data(template_data)
+            .with_priority(EmailPriority::High)
+            .with_tag("email_type", "verification")
+            .with_tag("locale", locale.as_str())
+            .with_tag("template", "verification_code");
+
+        self.send_email(request).await
+    }
+
+    /// Send a notification email. The subject and message are sent as given; `locale` picks the
+    /// language of the text around them.
+    #[instrument(skip(self, message))]
+    pub async fn send_notification_email<T, S, M>(
+        &self,
+        to_email: T,
+        subject: S,
+        message: M,
+        priority: EmailPriority,
+        locale: EmailLocale,
+    ) -> Result<EmailResponse>
+    where
+        T: Into<String> + std::fmt::Debug,
+        S: Into<String> + std::fmt::Display + std::fmt::Debug,
+        M: Into<String>,
+    {
+        let mut template_data = TemplateData::new();
+        template_data.insert("subject", subject.to_string());
+        template_data.insert("message", message.into());
+
+        let template = self.template(EmailTemplateName::Notification, locale).await;
+        let request = EmailRequest::from_template(vec![to_email], template)
+            .with_template_data(template_data)
+            .with_priority(priority)
+            .with_tag("email_type", "notification")
+            .with_tag("locale", locale.as_str());
+
+        self.send_categorized_email(request, EmailCategory::Transactional, locale)
+            .await
+    }
+
+    /// Send the weekly summary email.
+    ///
+    /// Expects `user_name`, `week_start`, `week_end`, `transaction_count`, `money_in`, `money_out`,
+    /// `spending_html`/`spending_text`, `transactions_html`/`transactions_text` and
+    /// `bills_html`/`bills_text` and `insight_html`/`insight_text`, which may be empty; the `_html`
+    /// values must already be escaped.
+    #[instrument(skip(self, template_data))]
+    pub async fn send_weekly_digest_email<T>(&self, to_email: T, template_data: TemplateData) -> Result<EmailResponse>
+    where
+        T: Into<String> + std::fmt::Debug,
+    {
+        let template = self.template(EmailTemplateName::WeeklyDigest, EmailLocale::English).await;
+        let request = EmailRequest::from_template(vec![to_email], template)
+            .with_template_data(template_data)
+            .with_priority(EmailPriority::Low)
+            .with_tag("email_type", "weekly_digest")
+            .with_tag("template", "weekly_digest");
+
+        self.send_categorized_email(request, EmailCategory::Digest, EmailLocale::English)
+            .await
+    }
+
+    /// Send the template `name` filled from `template_data`, as the email queue worker does, at
+    /// most once per `idempotency_key`
+    #[instrument(skip(self, template_data, idempotency_key), fields(template = name.as_str()))]
+    pub async fn send_template_email<T>(
+        &self,
+        to_email: T,
+        name: EmailTemplateName,
+        template_data: TemplateData,
+        locale: EmailLocale,
+        idempotency_key: Option<String>,
+    ) -> Result<EmailResponse>
+    where
+        T: Into<String> + std::fmt::Debug,
+    {
+        let mut request = self
+            .template_request(vec![to_email.into()], name, template_data, locale)
+            .await;
+        request.idempotency_key = idempotency_key;
+        self.send_categorized_email(request, name.category(), locale).await
+    }
+
+    /// The email `send_template_email` would send to `to_email`, rendered, without checking the
+    /// recipient's preferences or sending it. The unsubscribe link is only added for a recipient.
+    pub async fn preview_template_email(
+        &self,
+        to_email: Option<&str>,
+        name: EmailTemplateName,
+        template_data: TemplateData,
+        locale: EmailLocale,
+    ) -> Result<EmailRequest> {
+        let to: Vec<String> = to_email.map(str::to_string).into_iter().collect();
+        let request = self.template_request(to, name, template_data, locale).await.rendered();
+        let category = name.category();
+        match (&self.unsubscribe_links, to_email) {
+            (Some(links), Some(to_email)) if category.is_optional() => {
+                let link = links.link(to_email, category)?;
+                Ok(with_unsubscribe_link(request, &link, locale))
+            }
+            _ => Ok(request),
+        }
+    }
+
+    async fn template_request(
+        &self,
+        to: Vec<String>,
+        name: EmailTemplateName,
+        template_data: TemplateData,
+        locale: EmailLocale,
+    ) -> EmailRequest {
+        let priority = match name {
+            EmailTemplateName::OtpLogin | EmailTemplateName::Verification => EmailPriority::High,
+            EmailTemplateName::Notification => EmailPriority::Normal,
+            EmailTemplateName::WeeklyDigest => EmailPriority::Low,
+        };
+        let template = self.template(name, locale).await;
+        EmailRequest::from_template(to, template)
+            .with_template_data(template_data)
+            .with_priority(priority)
+            .with_tag("email_type", name.as_str())
+            .with_tag("locale", locale.as_str())
+    }
+}
+
+/// Transport named `kind`, configured from `settings`
+async fn build_transport(kind: &str, settings: &EmailSettings) -> Result<Arc<dyn EmailTransport>> {
+    Ok(match kind {
+        "dev" => {
+            if let Some(dir) = &settings.dev_dir {
+                std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+            }
+            Arc::new(DevEmailTransport::new(
+                settings.dev_sender.clone(),
+                settings.dev_dir.clone(),
+            ))
+        }
+        "smtp" => match &settings.smtp {
+            Some(config) => Arc::new(SmtpClient::new(config.clone())?),
+            None => bail!("The smtp transport needs SMTP_HOST and SMTP_DEFAULT_SENDER"),
+        },
+        "postmark" => match &settings.postmark {
+            Some(config) => Arc::new(PostmarkClient::new(config.clone())?),
+            None => bail!("The postmark transport needs POSTMARK_SERVER_TOKEN and POSTMARK_DEFAULT_SENDER"),
+        },
+        "ses" => match &settings.ses {
+            Some(config) => Arc::new(SESClient::new(config.clone()).await?),
+            None => bail!("The ses transport needs AWS_SES_DEFAULT_SENDER"),
+        },
+        other => bail!("Unknown email transport '{}'", other),
+    })
+}
+
+/// Text of the unsubscribe link in `locale`
+fn unsubscribe_label(locale: EmailLocale) -> &'static str {
+    match locale {
+        EmailLocale::English => "Unsubscribe from these emails",
+        EmailLocale::Spanish => "Darse de baja de estos correos",
+        EmailLocale::French => "Se désabonner de ces e-mails",
+    }
+}
+
+/// A rendered request with `link` at the end of its bodies and in the RFC 8058 one-click headers
+fn with_unsubscribe_link(mut request: EmailRequest, link: &str, locale: EmailLocale) -> EmailRequest {
+    let label = unsubscribe_label(locale);
+    // Already filled in; filling in again would expand placeholders inside the values
+    if request.ses_template.is_none() {
+        request.template_data = None;
+    }
+    if let Some(html) = request.html_body.take() {
+        let footer = format!(
+            "<p style=\"font-size:12px;color:#6b7280;text-align:center\"><a href=\"{}\">{}</a></p>",
+            link, label
+        );
+        request.html_body = Some(match html.rfind("</body>") {
+            Some(at) => format!("{}{}{}", &html[..at], footer, &html[at..]),
+            None => format!("{}{}", html, footer),
+        });
+    }
+    if let Some(text) = request.text_body.take() {
+        request.text_body = Some(format!("{}\n\n{}: {}\n", text.trim_end(), label, link));
+    }
+    request
+        .with_header("List-Unsubscribe", format!("<{}>", link))
+        .with_header("List-Unsubscribe-Post", "List-Unsubscribe=One-Click")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Keeps what it is asked to send
+    #[derive(Default)]
+    struct RecordingTransport {
+        sent: Mutex<Vec<EmailRequest>>,
+    }
+
+    #[async_trait::async_trait]
+    impl EmailTransport for RecordingTransport {
+        fn name(&self) -> &'static str {
+            "recording"
+        }
+
+        async fn send_email(&self, request: EmailRequest) -> Result<EmailResponse> {
+            self.sent.lock().unwrap().push(request.rendered());
+            Ok(EmailResponse {
+                message_id: "recorded".to_string(),
+                accepted: true,
+                processing_time_ms: 0,
+            })
+        }
+    }
+
+    struct FailingSource;
+
+    #[async_trait::async_trait]
+    impl EmailTemplateSource for FailingSource {
+        async fn active_template(&self, _name: EmailTemplateName) -> Result<Option<EmailTemplateContent>> {
+            anyhow::bail!("database unavailable")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_notification_uses_default_template_when_source_fails() {
+        let transport = Arc::new(RecordingTransport::default());
+        let mailer = Mailer::new(transport.clone()).with_templates(Arc::new(FailingSource));
+
+        mailer
+            .send_notification_email(
+                "ana@example.com",
+                "Large transaction",
+                "1250.00 USD",
+                EmailPriority::High,
+                EmailLocale::English,
+            )
+            .await
+            .unwrap();
+
+        let sent = transport.sent.lock().unwrap();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].subject, "Large transaction");
+        assert!(sent[0].text_body.as_deref().unwrap().contains("1250.00 USD"));
+        assert_eq!(
+            sent[0].tags.get("template_version").map(String::as_str),
+            Some("default")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_otp_email_in_user_locale() {
+        let transport = Arc::new(RecordingTransport::default());
+        let mailer = Mailer::new(transport.clone()).with_templates(Arc::new(FailingSource));
+
+        mailer
+            .send_otp_login_email("ana@example.com", "482913", None, None, EmailLocale::Spanish, None)
+            .await
+            .unwrap();
+        // Untranslated templates fall back to English
+        let data = EmailTemplateName::WeeklyDigest.sample_data();
+        mailer
+            .send_template_email(
+                "ana@example.com",
+                EmailTemplateName::WeeklyDigest,
+                data,
+                EmailLocale::French,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let sent = transport.sent.lock().unwrap();
+        assert_eq!(sent[0].subject, "🔐 Tu código de acceso - 482913");
+        assert!(sent[0].text_body.as_deref().unwrap().contains("Caduca en 5 minutos"));
+        assert_eq!(sent[0].tags.get("locale").map(String::as_str), Some("es"));
+        assert!(sent[1].subject.starts_with("Your week in review"));
+        // In the shared layout, with its footer in the language of the body
+        let html = sent[0].html_body.as_deref().unwrap();
+        assert!(html.contains("<strong>El equipo de Origin</strong>"));
+        assert!(sent[1].text_body.as_deref().unwrap().contains("The Origin Team"));
+    }
+
+    /// Opted out of one category
+    struct OptedOut(EmailCategory);
+
+    #[async_trait::async_trait]
+    impl EmailPreferenceSource for OptedOut {
+        async fn allows(&self, _email: &str, category: EmailCategory) -> Result<bool> {
+            Ok(category != self.0)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_preferences_and_unsubscribe_link() {
+        let transport = Arc::new(RecordingTransport::default());
+        let links = UnsubscribeLinks::new("secret", "https://api.example.com/unsubscribe");
+        let mailer =
+            Mailer::new(transport.clone()).with_preferences(Arc::new(OptedOut(EmailCategory::Digest)), Some(links));
+
+        let error = mailer
+            .send_weekly_digest_email("ana@example.com", EmailTemplateName::WeeklyDigest.sample_data())
+            .await
+            .unwrap_err();
+        assert!(EmailSuppressed::is(&error));
+        mailer
+            .send_notification_email(
+                "ana@example.com",
+                "Bill due",
+                "Rent is due on Mar 15",
+                EmailPriority::Normal,
+                EmailLocale::Spanish,
+            )
+            .await
+            .unwrap();
+        mailer
+            .send_otp_login_email("ana@example.com", "482913", None, None, EmailLocale::English, None)
+            .await
+            .unwrap();
+
+        let sent = transport.sent.lock().unwrap();
+        assert_eq!(sent.len(), 2);
+        let unsubscribe = sent[0]
+            .headers
+            .iter()
+            .find(|(name, _)| name == "List-Unsubscribe")
+            .map(|(_, value)| value.as_str())
+            .unwrap();
+        assert!(unsubscribe.starts_with("<https://api.example.com/unsubscribe/"));
+        assert!(sent[0]
+            .text_body
+            .as_deref()
+            .unwrap()
+            .contains("Darse de baja de estos correos: https://api.example.com/unsubscribe/"));
+        assert!(sent[0].html_body.as_deref().unwrap().contains("Darse de baja de estos correos</a>"));
+        // Sign-in codes can't be unsubscribed from
+        assert!(sent[1].headers.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_preview_is_not_sent() {
+        let transport = Arc::new(RecordingTransport::default());
+        let links = UnsubscribeLinks::new("secret", "https://api.example.com/unsubscribe");
+        let mailer =
+            Mailer::new(transport.clone()).with_preferences(Arc::new(OptedOut(EmailCategory::Digest)), Some(links));
+
+        let mut data = TemplateData::new();
+        data.insert("subject", "Bill due");
+        let preview = mailer
+            .preview_template_email(
+                Some("ana@example.com"),
+                EmailTemplateName::Notification,
+                data.clone(),
+                EmailLocale::French,
+            )
+            .await
+            .unwrap();
+        assert_eq!(preview.subject, "Bill due");
+        assert!(preview.text_body.as_deref().unwrap().contains("{{message}}"));
+        assert!(preview.text_body.as_deref().unwrap().contains("Se désabonner de ces e-mails"));
+
+        // Opted out of digests, but a preview doesn't check; without a recipient there is no link
+        let preview = mailer
+            .preview_template_email(None, EmailTemplateName::WeeklyDigest, data, EmailLocale::English)
+            .await
+            .unwrap();
+        assert!(preview.to.is_empty());
+        assert!(preview.headers.is_empty());
+        assert!(transport.sent.lock().unwrap().is_empty());
+    }
+
+    /// Keeps claims in memory
+    #[derive(Default)]
+    struct MemoryIdempotency {
+        keys: Mutex<std::collections::HashMap<String, Option<String>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl EmailIdempotency for MemoryIdempotency {
+        async fn claim(&self, key: &str) -> Result<IdempotencyClaim> {
+            let mut keys = self.keys.lock().unwrap();
+            Ok(match keys.get(key) {
+                Some(Some(message_id)) => IdempotencyClaim::Sent(message_id.clone()),
+                Some(None) => IdempotencyClaim::InProgress,
+                None => {
+                    keys.insert(key.to_string(), None);
+                    IdempotencyClaim::Claimed
+                }
+            })
+        }
+
+        async fn complete(&self, key: &str, message_id: &str) -> Result<()> {
+            self.keys
+                .lock()
+                .unwrap()
+                .insert(key.to_string(), Some(message_id.to_string()));
+            Ok(())
+        }
+
+        async fn release(&self, key: &str) -> Result<()> {
+            self.keys.lock().unwrap().remove(key);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_key_sends_once() {
+        let transport = Arc::new(RecordingTransport::default());
+        let idempotency = Arc::new(MemoryIdempotency::default());
+        let mailer = Mailer::new(transport.clone()).with_idempotency(idempotency.clone());
+
+        for _ in 0..2 {
+            let response = mailer
+                .send_otp_login_email(
+                    "ana@example.com",
+                    "482913",
+                    None,
+                    None,
+                    EmailLocale::English,
+                    Some("otp:ana@example.com:1".to_string()),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.message_id, "recorded");
+        }
+        // Without a key every request is sent
+        for _ in 0..2 {
+            mailer
+                .send_email(EmailRequest::new(vec!["ana@example.com"], "Hi").with_text_body("Hi"))
+                .await
+                .unwrap();
+        }
+        assert_eq!(transport.sent.lock().unwrap().len(), 3);
+
+        idempotency
+            .keys
+            .lock()
+            .unwrap()
+            .insert("email_queue:2".to_string(), None);
+        let error = mailer
+            .send_email(
+                EmailRequest::new(vec!["ana@example.com"], "Hi")
+                    .with_text_body("Hi")
+                    .with_idempotency_key("email_queue:2"),
+            )
+            .await
+            .unwrap_err();
+        assert!(EmailInProgress::is(&error));
+    }
+}
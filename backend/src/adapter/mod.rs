@@ -1,24 +1,75 @@
+pub mod alerting;
+pub mod bank_data;
 pub mod claude_ai;
+pub mod claude_json;
+pub mod coinbase;
+pub mod email_idempotency;
+pub mod email_preferences;
+pub mod email_templates;
+pub mod email_transport;
+pub mod embeddings;
+pub mod encryption;
+pub mod fx;
 pub mod google_oauth;
 pub mod jwt_service;
+pub mod llm;
+pub mod mailer;
+pub mod model_fallback;
+pub mod openai;
 pub mod otp;
 pub mod otp_service;
 pub mod parameter_store;
 pub mod plaid;
+pub mod plaid_webhook;
+pub mod postmark;
+pub mod s3;
+pub mod secrets;
+pub mod secrets_manager;
 pub mod ses;
+pub mod smtp;
+pub mod sqs;
+pub mod sse;
+pub mod vault;
+pub mod webhooks;
 
+pub use alerting::{Alert, AlertSeverity, AlertSink, EmailAlertSink, LogAlertSink};
+pub use bank_data::{BankDataProvider, BankDataProviders, PLAID_PROVIDER};
 pub use claude_ai::ClaudeAIClient;
+pub use claude_json::{JsonReply, DEFAULT_JSON_REPAIRS};
+pub use coinbase::{CoinbaseClient, CoinbaseConfig, CoinbaseCredentials, COINBASE_PROVIDER};
+pub use embeddings::{EmbeddingsClient, EmbeddingsConfig, EMBEDDING_DIMENSIONS};
+pub use email_idempotency::{EmailIdempotency, EmailInProgress, IdempotencyClaim};
+pub use email_preferences::{EmailCategory, EmailPreferenceSource, EmailSuppressed, UnsubscribeLinks};
+pub use email_templates::{EmailBrand, EmailLocale, EmailTemplateContent, EmailTemplateName, EmailTemplateSource};
+pub use email_transport::{DevEmailTransport, EmailTransport, EmailTransportSnapshot, FailoverTransport};
+pub use encryption::{EnvelopeCipher, EncryptedSecret};
+pub use fx::{FxClient, FxConfig, FxRates};
+pub use llm::{LlmMessage, LlmProvider, LlmProviders, LlmRequest, LlmResponse, LlmRole, LlmStream, LlmStreamEvent, LlmUsage, CLAUDE_PROVIDER};
+pub use mailer::{EmailSettings, Mailer};
+pub use model_fallback::ModelRoute;
+pub use openai::{OpenAIClient, OpenAIConfig, OPENAI_PROVIDER};
 pub use google_oauth::{GoogleOAuthClient, GoogleOAuthConfig, AuthorizationUrl, TokenResponse, GoogleUser};
 pub use otp::{OtpManager, OtpConfig, OtpEntry, OtpStatus};
 pub use otp_service::OtpService;
-pub use parameter_store::{ParameterStore, AppConfig};
+pub use parameter_store::ParameterStore;
 pub use plaid::{
     PlaidClient, PlaidConfig, PlaidEnvironment, 
     BankAccount, BankTransaction, AccountBalances,
     LinkTokenRequest, LinkTokenResponse,
     PublicTokenExchangeRequest, PublicTokenExchangeResponse,
-    TransactionSyncRequest, TransactionSyncResponse,
+    TransactionSyncRequest, TransactionSyncResponse, TransactionsPage,
     TransactionLocation, TransactionPaymentMeta, RemovedTransaction,
-    PlaidError
+    Liability, LiabilityApr, LiabilityKind,
+    AccountIdentity, IdentityOwner, IdentityContact, IdentityAddress,
+    Statement, TransferDirection, TransferAuthorizationRequest, TransferAuthorization,
+    CreatedTransfer, TransferEvent, TransferEventsPage, PlaidError
 };
-pub use ses::{SESClient, SESConfig, EmailRequest, EmailResponse, TemplateData, EmailPriority};
\ No newline at end of file
+pub use postmark::{PostmarkClient, PostmarkConfig};
+pub use s3::{S3Client, S3Config, PresignedUrl, ObjectMetadata};
+pub use secrets::SecretsProvider;
+pub use secrets_manager::SecretsManager;
+pub use ses::{SESClient, SESConfig, EmailRequest, EmailResponse, TemplateData, EmailPriority};
+pub use smtp::{SmtpClient, SmtpConfig, SmtpTls};
+pub use sqs::{QueueMessage, SqsConfig, SqsQueue};
+pub use vault::{VaultClient, VaultConfig};
+pub use webhooks::{WebhookClient, WebhookClientConfig, WebhookResponse};
\ No newline at end of file
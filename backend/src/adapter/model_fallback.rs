@@ -0,0 +1,212 @@
+// Ordered model fallback for Claude requests, with per-model timeouts and output caps
+use anyhow::{bail, Context, Result};
+use reqwest::StatusCode;
+use std::time::Duration;
+
+/// Longest wait between attempts, including waits asked for by `retry-after`
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// A model tried after the requested one fails
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelRoute {
+    pub model: String,
+    /// Timeout of non-streamed requests to this model, instead of the client's
+    pub timeout: Option<Duration>,
+    /// Cap on `max_tokens` for this model, keeping a fallback to a pricier model within budget
+    pub max_tokens: Option<u32>,
+}
+
+impl ModelRoute {
+    pub fn new(model: &str) -> Self {
+        Self {
+            model: model.to_string(),
+            timeout: None,
+            max_tokens: None,
+        }
+    }
+
+    /// Parse `model[:timeout_seconds[:max_tokens]]`; empty fields leave the limit unset
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut fields = spec.trim().split(':').map(str::trim);
+        let model = fields
+            .next()
+            .filter(|m| !m.is_empty())
+            .context("Fallback model name is empty")?;
+        let timeout = match fields.next().filter(|f| !f.is_empty()) {
+            Some(seconds) => {
+                Some(Duration::from_secs(seconds.parse().with_context(|| {
+                    format!("Invalid timeout '{}' for fallback model {}", seconds, model)
+                })?))
+            }
+            None => None,
+        };
+        let max_tokens = match fields.next().filter(|f| !f.is_empty()) {
+            Some(tokens) => Some(
+                tokens
+                    .parse()
+                    .with_context(|| format!("Invalid max tokens '{}' for fallback model {}", tokens, model))?,
+            ),
+            None => None,
+        };
+        if fields.next().is_some() {
+            bail!("Fallback model '{}' has too many fields", spec.trim());
+        }
+        Ok(Self {
+            model: model.to_string(),
+            timeout,
+            max_tokens,
+        })
+    }
+
+    /// `max_tokens` of a request to this model
+    pub fn capped_max_tokens(&self, requested: u32) -> u32 {
+        self.max_tokens.map_or(requested, |cap| requested.min(cap))
+    }
+}
+
+/// Parse a comma-separated list of `ModelRoute` specs, e.g. `claude-3-5-haiku-latest:20:1024,claude-3-haiku-20240307`
+pub fn parse_fallback_models(specs: &str) -> Result<Vec<ModelRoute>> {
+    specs
+        .split(',')
+        .filter(|spec| !spec.trim().is_empty())
+        .map(ModelRoute::parse)
+        .collect()
+}
+
+/// Models to try for a request to `requested`, in order.
+///
+/// The requested model comes first, using a matching fallback entry's limits if there is one; the
+/// other fallbacks follow once each.
+pub fn model_chain(requested: &str, fallbacks: &[ModelRoute]) -> Vec<ModelRoute> {
+    let primary = fallbacks
+        .iter()
+        .find(|route| route.model == requested)
+        .cloned()
+        .unwrap_or_else(|| ModelRoute::new(requested));
+    std::iter::once(primary)
+        .chain(fallbacks.iter().filter(|route| route.model != requested).cloned())
+        .collect()
+}
+
+/// What to do after a failed attempt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryDecision {
+    /// Try the same model again after a delay
+    Retry,
+    /// Move on to the next model in the chain
+    NextModel,
+    /// Give up; the request will fail the same way on any model
+    Fail,
+}
+
+/// Decide how to continue after an error status, or a connection failure or timeout when `status` is None.
+///
+/// Rate limits apply per model, so they move straight on to the next model; the last model in the
+/// chain waits and retries instead. Server errors and connection failures are retried until the
+/// model's attempts run out.
+pub fn retry_decision(
+    status: Option<StatusCode>,
+    attempt: u32,
+    max_attempts: u32,
+    has_next_model: bool,
+) -> RetryDecision {
+    let out_of_attempts = attempt >= max_attempts;
+    match status {
+        Some(StatusCode::TOO_MANY_REQUESTS) if has_next_model => RetryDecision::NextModel,
+        // Other client errors, such as an invalid request, fail the same way on every model
+        Some(status) if status != StatusCode::TOO_MANY_REQUESTS && !status.is_server_error() => RetryDecision::Fail,
+        _ if !out_of_attempts => RetryDecision::Retry,
+        _ if has_next_model => RetryDecision::NextModel,
+        _ => RetryDecision::Fail,
+    }
+}
+
+/// Delay before retry `attempt` (1-based): the server's `retry-after` when given, else exponential backoff
+pub fn backoff_delay(attempt: u32, retry_after: Option<Duration>) -> Duration {
+    retry_after
+        .unwrap_or_else(|| Duration::from_millis(1000 * 2_u64.pow(attempt.saturating_sub(1).min(5))))
+        .min(MAX_BACKOFF)
+}
+
+/// Seconds in a `retry-after` header; HTTP dates are ignored
+pub fn parse_retry_after(value: Option<&str>) -> Option<Duration> {
+    value.and_then(|v| v.trim().parse().ok()).map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fallback_models() {
+        let routes =
+            parse_fallback_models("claude-3-5-haiku-latest:20:1024, claude-3-haiku-20240307,claude-3-opus::512")
+                .unwrap();
+        assert_eq!(
+            routes[0],
+            ModelRoute {
+                model: "claude-3-5-haiku-latest".to_string(),
+                timeout: Some(Duration::from_secs(20)),
+                max_tokens: Some(1024),
+            }
+        );
+        assert_eq!(routes[1], ModelRoute::new("claude-3-haiku-20240307"));
+        assert_eq!(routes[2].timeout, None);
+        assert_eq!(routes[2].max_tokens, Some(512));
+        assert!(parse_fallback_models("").unwrap().is_empty());
+        assert!(parse_fallback_models("claude:soon").is_err());
+        assert!(parse_fallback_models("claude:1:2:3").is_err());
+    }
+
+    #[test]
+    fn test_model_chain_starts_with_requested_model() {
+        let fallbacks = parse_fallback_models("haiku:10,sonnet:30:2048").unwrap();
+        let chain: Vec<String> = model_chain("sonnet", &fallbacks).into_iter().map(|r| r.model).collect();
+        assert_eq!(chain, ["sonnet", "haiku"]);
+        assert_eq!(model_chain("sonnet", &fallbacks)[0].max_tokens, Some(2048));
+        assert_eq!(model_chain("opus", &fallbacks)[0], ModelRoute::new("opus"));
+        assert_eq!(model_chain("opus", &[]).len(), 1);
+    }
+
+    #[test]
+    fn test_capped_max_tokens() {
+        let route = ModelRoute::parse("opus::1000").unwrap();
+        assert_eq!(route.capped_max_tokens(4096), 1000);
+        assert_eq!(route.capped_max_tokens(500), 500);
+        assert_eq!(ModelRoute::new("haiku").capped_max_tokens(4096), 4096);
+    }
+
+    #[test]
+    fn test_retry_decision() {
+        // Anthropic's "overloaded" status
+        let overloaded = StatusCode::from_u16(529).ok();
+        assert_eq!(
+            retry_decision(Some(StatusCode::TOO_MANY_REQUESTS), 1, 3, true),
+            RetryDecision::NextModel
+        );
+        assert_eq!(
+            retry_decision(Some(StatusCode::TOO_MANY_REQUESTS), 1, 3, false),
+            RetryDecision::Retry
+        );
+        assert_eq!(retry_decision(overloaded, 1, 3, true), RetryDecision::Retry);
+        assert_eq!(retry_decision(overloaded, 3, 3, true), RetryDecision::NextModel);
+        assert_eq!(retry_decision(None, 3, 3, false), RetryDecision::Fail);
+        assert_eq!(
+            retry_decision(Some(StatusCode::BAD_REQUEST), 1, 3, true),
+            RetryDecision::Fail
+        );
+        assert_eq!(
+            retry_decision(Some(StatusCode::UNAUTHORIZED), 1, 3, true),
+            RetryDecision::Fail
+        );
+    }
+
+    #[test]
+    fn test_backoff_delay() {
+        assert_eq!(backoff_delay(1, None), Duration::from_secs(1));
+        assert_eq!(backoff_delay(3, None), Duration::from_secs(4));
+        assert_eq!(backoff_delay(20, None), MAX_BACKOFF);
+        assert_eq!(backoff_delay(1, parse_retry_after(Some("7"))), Duration::from_secs(7));
+        assert_eq!(parse_retry_after(Some("Wed, 21 Oct 2026 07:28:00 GMT")), None);
+    }
+}
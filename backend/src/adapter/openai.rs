@@ -0,0 +1,448 @@
+// OpenAI-compatible chat completions client (OpenAI, Azure OpenAI, vLLM, Ollama, ...)
+use crate::adapter::llm::{LlmMessage, LlmProvider, LlmRequest, LlmResponse, LlmStream, LlmStreamEvent, LlmUsage};
+use crate::adapter::sse::SseParser;
+use crate::settings::SettingValues;
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::Duration;
+use tracing::{debug, error, info, instrument};
+
+/// Name of the OpenAI-compatible provider
+pub const OPENAI_PROVIDER: &str = "openai";
+
+/// Tokens added to each message's estimate for role and formatting overhead
+const MESSAGE_OVERHEAD_TOKENS: u32 = 4;
+
+/// Configuration for the OpenAI-compatible client
+#[derive(Debug, Clone)]
+pub struct OpenAIConfig {
+    pub api_key: String,
+    /// API root including the version, e.g. https://api.openai.com/v1
+    pub base_url: String,
+    pub default_model: String,
+    /// Request timeout in seconds
+    pub timeout_seconds: u64,
+    /// Timeout in seconds for a streamed response, which stays open while tokens are generated
+    pub stream_timeout_seconds: u64,
+    /// Maximum number of attempts for failed requests
+    pub max_retries: u32,
+}
+
+impl Default for OpenAIConfig {
+    fn default() -> Self {
+        Self {
+            api_key: String::new(),
+            base_url: "https://api.openai.com/v1".to_string(),
+            default_model: "gpt-4o-mini".to_string(),
+            timeout_seconds: 60,
+            stream_timeout_seconds: 300,
+            max_retries: 3,
+        }
+    }
+}
+
+impl OpenAIConfig {
+    /// Read from `OPENAI_API_KEY`, `OPENAI_BASE_URL` and `OPENAI_DEFAULT_MODEL`; `None` without an API key
+    pub fn from_values(values: &mut SettingValues) -> Option<Self> {
+        let api_key = values.optional("openai-api-key")?;
+        let defaults = Self::default();
+        Some(Self {
+            api_key,
+            base_url: values.or("openai-base-url", &defaults.base_url),
+            default_model: values.or("openai-default-model", &defaults.default_model),
+            ..defaults
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ChatMessage<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct StreamOptions {
+    include_usage: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct ChatCompletionRequest<'a> {
+    model: &'a str,
+    messages: Vec<ChatMessage<'a>>,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<&'a [String]>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatCompletionResponse {
+    model: String,
+    choices: Vec<ChatChoice>,
+    #[serde(default)]
+    usage: Option<ChatUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatChoice {
+    message: ChatChoiceMessage,
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatChoiceMessage {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+struct ChatUsage {
+    prompt_tokens: u32,
+    completion_tokens: u32,
+    #[serde(default)]
+    prompt_tokens_details: Option<PromptTokensDetails>,
+}
+
+/// Breakdown of prompt tokens; prompts are cached automatically
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+struct PromptTokensDetails {
+    #[serde(default)]
+    cached_tokens: u32,
+}
+
+impl From<ChatUsage> for LlmUsage {
+    fn from(usage: ChatUsage) -> Self {
+        // Cached tokens are part of prompt_tokens here, but counted separately by Claude
+        let cached = usage.prompt_tokens_details.map_or(0, |d| d.cached_tokens);
+        LlmUsage {
+            input_tokens: usage.prompt_tokens.saturating_sub(cached),
+            output_tokens: usage.completion_tokens,
+            cache_read_tokens: cached,
+            cache_write_tokens: 0,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatCompletionChunk {
+    #[serde(default)]
+    choices: Vec<ChunkChoice>,
+    #[serde(default)]
+    usage: Option<ChatUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChunkChoice {
+    #[serde(default)]
+    delta: ChunkDelta,
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ChunkDelta {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+/// Map OpenAI finish reasons onto the provider-neutral stop reasons
+fn stop_reason(finish_reason: Option<String>) -> Option<String> {
+    finish_reason.map(|reason| match reason.as_str() {
+        "stop" => "end_turn".to_string(),
+        "length" => "max_tokens".to_string(),
+        _ => reason,
+    })
+}
+
+/// Turns the chat completions event stream into text deltas and a final stop event.
+///
+/// The finish reason and the usage arrive in separate chunks, so the stop event is
+/// emitted on the closing `[DONE]` marker.
+#[derive(Debug, Default)]
+struct StreamDecoder {
+    parser: SseParser,
+    finish_reason: Option<String>,
+    usage: ChatUsage,
+    finished: bool,
+}
+
+impl StreamDecoder {
+    fn push(&mut self, chunk: &[u8]) -> Result<Vec<LlmStreamEvent>> {
+        let mut events = Vec::new();
+
+        for event in self.parser.push(chunk) {
+            if self.finished {
+                break;
+            }
+            if event.data.trim() == "[DONE]" {
+                self.finished = true;
+                events.push(LlmStreamEvent::Stop {
+                    stop_reason: stop_reason(self.finish_reason.take()),
+                    usage: self.usage.into(),
+                });
+                continue;
+            }
+
+            let chunk: ChatCompletionChunk =
+                serde_json::from_str(&event.data).context("Failed to parse OpenAI stream chunk")?;
+            if let Some(usage) = chunk.usage {
+                self.usage = usage;
+            }
+            for choice in chunk.choices {
+                if let Some(text) = choice.delta.content.filter(|t| !t.is_empty()) {
+                    events.push(LlmStreamEvent::TextDelta(text));
+                }
+                if choice.finish_reason.is_some() {
+                    self.finish_reason = choice.finish_reason;
+                }
+            }
+        }
+        Ok(events)
+    }
+}
+
+/// Client for OpenAI-compatible chat completion APIs
+#[derive(Debug)]
+pub struct OpenAIClient {
+    config: OpenAIConfig,
+    client: Client,
+}
+
+impl OpenAIClient {
+    pub fn new(config: OpenAIConfig) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(config.timeout_seconds))
+            .build()
+            .context("Failed to create HTTP client")?;
+
+        Ok(Self { config, client })
+    }
+
+    fn completion_request<'a>(&'a self, request: &'a LlmRequest, stream: bool) -> ChatCompletionRequest<'a> {
+        let mut messages = Vec::with_capacity(request.messages.len() + 1);
+        if let Some(system) = &request.system {
+            messages.push(ChatMessage {
+                role: "system",
+                content: system,
+            });
+        }
+        messages.extend(request.messages.iter().map(|m: &LlmMessage| ChatMessage {
+            role: m.role.as_str(),
+            content: &m.content,
+        }));
+
+        ChatCompletionRequest {
+            model: request.model.as_deref().unwrap_or(&self.config.default_model),
+            messages,
+            max_tokens: request.max_tokens,
+            temperature: request.temperature,
+            stop: Some(request.stop_sequences.as_slice()).filter(|s| !s.is_empty()),
+            stream,
+            stream_options: stream.then_some(StreamOptions { include_usage: true }),
+        }
+    }
+
+    /// POST to chat completions until a success status is returned, retrying with backoff
+    async fn send_with_retries(&self, body: &ChatCompletionRequest<'_>, timeout: Duration) -> Result<reqwest::Response> {
+        let url = format!("{}/chat/completions", self.config.base_url.trim_end_matches('/'));
+        let mut last_error = None;
+
+        for attempt in 1..=self.config.max_retries.max(1) {
+            debug!(attempt, "Sending request to OpenAI");
+            let response = self
+                .client
+                .post(&url)
+                .bearer_auth(&self.config.api_key)
+                .timeout(timeout)
+                .json(body)
+                .send()
+                .await;
+
+            match response {
+                Ok(resp) if resp.status().is_success() => return Ok(resp),
+                Ok(resp) => {
+                    let status = resp.status();
+                    let error_text = resp.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+                    error!(status = %status, error = %error_text, attempt, "OpenAI API returned error");
+                    last_error = Some(anyhow::anyhow!("OpenAI API error: {} - {}", status, error_text));
+                    // Other client errors will fail the same way again
+                    if status.is_client_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    error!(error = %e, attempt, "Failed to send request to OpenAI");
+                    last_error = Some(anyhow::anyhow!("Request failed: {}", e));
+                }
+            }
+
+            if attempt < self.config.max_retries {
+                tokio::time::sleep(Duration::from_millis(1000 * 2_u64.pow(attempt - 1))).await;
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("All retry attempts failed")))
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OpenAIClient {
+    fn name(&self) -> &'static str {
+        OPENAI_PROVIDER
+    }
+
+    fn default_model(&self) -> &str {
+        &self.config.default_model
+    }
+
+    #[instrument(skip(self, request), fields(message_count = request.messages.len()))]
+    async fn send(&self, request: LlmRequest) -> Result<LlmResponse> {
+        let body = self.completion_request(&request, false);
+        let response: ChatCompletionResponse = self
+            .send_with_retries(&body, Duration::from_secs(self.config.timeout_seconds))
+            .await?
+            .json()
+            .await
+            .context("Failed to parse OpenAI response")?;
+
+        let Some(choice) = response.choices.into_iter().next() else {
+            bail!("No choices in OpenAI response");
+        };
+        let usage: LlmUsage = response.usage.unwrap_or_default().into();
+        info!(
+            input_tokens = usage.input_tokens,
+            output_tokens = usage.output_tokens,
+            model = %response.model,
+            "Successfully received response from OpenAI"
+        );
+
+        Ok(LlmResponse {
+            text: choice.message.content.unwrap_or_default(),
+            model: response.model,
+            stop_reason: stop_reason(choice.finish_reason),
+            usage,
+        })
+    }
+
+    #[instrument(skip(self, request), fields(message_count = request.messages.len()))]
+    async fn stream(&self, request: LlmRequest) -> Result<LlmStream> {
+        let body = self.completion_request(&request, true);
+        let response = self
+            .send_with_retries(&body, Duration::from_secs(self.config.stream_timeout_seconds))
+            .await?;
+
+        let state = (response, StreamDecoder::default(), VecDeque::new());
+        let events = futures::stream::unfold(Some(state), |state| async move {
+            let (mut response, mut decoder, mut pending) = state?;
+            loop {
+                if let Some(event) = pending.pop_front() {
+                    return Some((Ok(event), Some((response, decoder, pending))));
+                }
+                if decoder.finished {
+                    return None;
+                }
+                match response.chunk().await {
+                    Ok(Some(chunk)) => match decoder.push(&chunk) {
+                        Ok(events) => pending.extend(events),
+                        Err(e) => return Some((Err(e), None)),
+                    },
+                    Ok(None) => return Some((Err(anyhow::anyhow!("OpenAI stream ended before [DONE]")), None)),
+                    Err(e) => return Some((Err(anyhow::Error::new(e).context("Failed to read OpenAI stream")), None)),
+                }
+            }
+        });
+        Ok(Box::pin(events))
+    }
+
+    /// Estimated locally at about four characters per token, as the API has no counting endpoint
+    async fn count_tokens(&self, request: &LlmRequest) -> Result<u32> {
+        let texts = request.system.iter().chain(request.messages.iter().map(|m| &m.content));
+        Ok(texts
+            .map(|text| (text.chars().count() as u32).div_ceil(4) + MESSAGE_OVERHEAD_TOKENS)
+            .sum())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client() -> OpenAIClient {
+        OpenAIClient::new(OpenAIConfig {
+            api_key: "test-key".to_string(),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_completion_request_puts_system_first() {
+        let client = client();
+        let request = LlmRequest {
+            system: Some("Be brief".to_string()),
+            messages: vec![LlmMessage::user("Hi")],
+            max_tokens: 50,
+            ..Default::default()
+        };
+
+        let json = serde_json::to_value(client.completion_request(&request, true)).unwrap();
+        assert_eq!(json["model"], "gpt-4o-mini");
+        assert_eq!(json["messages"][0]["role"], "system");
+        assert_eq!(json["messages"][1]["content"], "Hi");
+        assert_eq!(json["stream_options"]["include_usage"], true);
+        assert!(json.get("stop").is_none());
+        assert!(json.get("temperature").is_none());
+    }
+
+    #[test]
+    fn test_stream_decoder_emits_text_and_stop() {
+        let stream = concat!(
+            "data: {\"id\":\"c1\",\"choices\":[{\"index\":0,\"delta\":{\"role\":\"assistant\",\"content\":\"\"},\"finish_reason\":null}]}\n\n",
+            "data: {\"id\":\"c1\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hello\"},\"finish_reason\":null}]}\n\n",
+            "data: {\"id\":\"c1\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\" there\"},\"finish_reason\":null}]}\n\n",
+            "data: {\"id\":\"c1\",\"choices\":[{\"index\":0,\"delta\":{},\"finish_reason\":\"length\"}]}\n\n",
+            "data: {\"id\":\"c1\",\"choices\":[],\"usage\":{\"prompt_tokens\":9,\"completion_tokens\":2,\"total_tokens\":11}}\n\n",
+            "data: [DONE]\n\n",
+        );
+
+        let mut decoder = StreamDecoder::default();
+        let mut events = Vec::new();
+        for chunk in stream.as_bytes().chunks(11) {
+            events.extend(decoder.push(chunk).unwrap());
+        }
+
+        assert_eq!(
+            events,
+            vec![
+                LlmStreamEvent::TextDelta("Hello".to_string()),
+                LlmStreamEvent::TextDelta(" there".to_string()),
+                LlmStreamEvent::Stop {
+                    stop_reason: Some("max_tokens".to_string()),
+                    usage: LlmUsage {
+                        input_tokens: 9,
+                        output_tokens: 2,
+                        ..Default::default()
+                    },
+                },
+            ]
+        );
+        assert!(decoder.finished);
+    }
+
+    #[tokio::test]
+    async fn test_count_tokens_estimate() {
+        let request = LlmRequest {
+            system: Some("a".repeat(8)),
+            messages: vec![LlmMessage::user(&"b".repeat(10))],
+            ..Default::default()
+        };
+        assert_eq!(client().count_tokens(&request).await.unwrap(), (2 + 4) + (3 + 4));
+    }
+}
@@ -1,20 +1,23 @@
 use super::otp::{OtpManager, OtpConfig};
+use super::email_templates::EmailLocale;
+use super::mailer::Mailer;
 use super::ses::SESClient;
+use std::sync::Arc;
 use anyhow::Result;
 use tracing::{info, instrument};
 
 /// High-level OTP service that combines OTP generation with email sending
 pub struct OtpService {
     otp_manager: OtpManager,
-    ses_client: SESClient,
+    mailer: Mailer,
 }
 
 impl OtpService {
     /// Create a new OTP service
-    pub fn new(otp_manager: OtpManager, ses_client: SESClient) -> Self {
+    pub fn new(otp_manager: OtpManager, mailer: Mailer) -> Self {
         Self {
             otp_manager,
-            ses_client,
+            mailer,
         }
     }
 
@@ -32,12 +35,14 @@ impl OtpService {
             .map_err(|e| anyhow::anyhow!("Failed to generate OTP: {}", e))?;
 
         // Send email
-        let email_response = self.ses_client
+        let email_response = self.mailer
             .send_otp_login_email(
                 email,
                 &otp_entry.code,
                 user_name,
                 Some(self.otp_manager.config().expires_minutes),
+                EmailLocale::default(),
+                Some(format!("otp:{}:{}", email, otp_entry.created_at)),
             )
             .await?;
 
@@ -85,6 +90,7 @@ pub async fn example_otp_usage() -> Result<()> {
         default_sender_name: Some("Origin Security Team".to_string()),
         reply_to: Some("support@yourdomain.com".to_string()),
         configuration_set: Some("origin-email-tracking".to_string()),
+        max_send_rate: None,
     };
     let ses_client = SESClient::new(ses_config).await?;
 
@@ -97,7 +103,7 @@ pub async fn example_otp_usage() -> Result<()> {
     let otp_manager = OtpManager::with_config(otp_config);
 
     // Create OTP service
-    let otp_service = OtpService::new(otp_manager, ses_client);
+    let otp_service = OtpService::new(otp_manager, Mailer::new(Arc::new(ses_client)));
 
     // Example: Send OTP login email
     let message_id = otp_service
@@ -155,11 +161,12 @@ mod tests {
             default_sender_name: Some("Test Sender".to_string()),
             reply_to: None,
             configuration_set: None,
+            max_send_rate: None,
         };
 
         let ses_client = SESClient::new(ses_config).await.unwrap();
         let otp_manager = OtpManager::new();
-        let otp_service = OtpService::new(otp_manager, ses_client);
+        let otp_service = OtpService::new(otp_manager, Mailer::new(Arc::new(ses_client)));
 
         // This would send an actual email if AWS is configured
         // let _message_id = otp_service
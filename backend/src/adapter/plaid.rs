@@ -1,9 +1,10 @@
-use anyhow::{Result, Context};
-use chrono::{DateTime, Utc};
+use anyhow::{anyhow, Result, Context};
+use chrono::{DateTime, NaiveDate, Utc};
 use plaid::PlaidClient as PlaidSDKClient;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use tracing::{info, debug, instrument};
-use crate::adapter::AppConfig;
+use crate::settings::{Settings, SettingsSources};
 
 #[derive(Debug, Clone)]
 pub struct PlaidConfig {
@@ -28,6 +29,15 @@ impl PlaidEnvironment {
             PlaidEnvironment::Production => "production",
         }
     }
+
+    /// API host for the environment
+    pub fn base_url(&self) -> &'static str {
+        match self {
+            PlaidEnvironment::Sandbox => "https://sandbox.plaid.com",
+            PlaidEnvironment::Development => "https://development.plaid.com",
+            PlaidEnvironment::Production => "https://production.plaid.com",
+        }
+    }
 }
 
 impl Default for PlaidConfig {
@@ -175,6 +185,153 @@ pub struct TransactionSyncResponse {
     pub request_id: String,
 }
 
+/// One page of `/transactions/get` results
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionsPage {
+    pub transactions: Vec<BankTransaction>,
+    /// Total transactions in the requested range across all pages
+    pub total_transactions: i64,
+    pub offset: i32,
+}
+
+/// Largest page Plaid returns from `/transactions/get`
+pub const MAX_TRANSACTIONS_GET_COUNT: i32 = 500;
+const DEFAULT_TRANSACTIONS_GET_COUNT: i32 = 100;
+
+/// Validated `/transactions/get` date range and pagination
+#[derive(Debug, Clone, PartialEq)]
+struct TransactionsGetRange {
+    start_date: chrono::NaiveDate,
+    end_date: chrono::NaiveDate,
+    count: i32,
+    offset: i32,
+}
+
+impl TransactionsGetRange {
+    fn validate(start_date: &str, end_date: &str, count: Option<i32>, offset: Option<i32>) -> Result<Self> {
+        let start = chrono::NaiveDate::parse_from_str(start_date, "%Y-%m-%d")
+            .map_err(|_| anyhow::anyhow!("Invalid start_date format, expected YYYY-MM-DD"))?;
+        let end = chrono::NaiveDate::parse_from_str(end_date, "%Y-%m-%d")
+            .map_err(|_| anyhow::anyhow!("Invalid end_date format, expected YYYY-MM-DD"))?;
+
+        if start > end {
+            return Err(anyhow::anyhow!("start_date must not be after end_date"));
+        }
+        if end > Utc::now().date_naive() {
+            return Err(anyhow::anyhow!("end_date must not be in the future"));
+        }
+
+        let count = count.unwrap_or(DEFAULT_TRANSACTIONS_GET_COUNT);
+        if !(1..=MAX_TRANSACTIONS_GET_COUNT).contains(&count) {
+            return Err(anyhow::anyhow!("count must be between 1 and {}", MAX_TRANSACTIONS_GET_COUNT));
+        }
+        let offset = offset.unwrap_or(0);
+        if offset < 0 {
+            return Err(anyhow::anyhow!("offset must not be negative"));
+        }
+
+        Ok(Self { start_date: start, end_date: end, count, offset })
+    }
+}
+
+/// Kind of debt reported by Plaid Liabilities
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LiabilityKind {
+    Credit,
+    Mortgage,
+    Student,
+}
+
+impl LiabilityKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LiabilityKind::Credit => "credit",
+            LiabilityKind::Mortgage => "mortgage",
+            LiabilityKind::Student => "student",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "credit" => Some(LiabilityKind::Credit),
+            "mortgage" => Some(LiabilityKind::Mortgage),
+            "student" => Some(LiabilityKind::Student),
+            _ => None,
+        }
+    }
+}
+
+/// One APR on a credit card (purchase, cash advance, balance transfer, ...)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LiabilityApr {
+    pub apr_percentage: f64,
+    pub apr_type: String,
+    pub balance_subject_to_apr: Option<f64>,
+    pub interest_charge_amount: Option<f64>,
+}
+
+/// Debt details of one account, normalized across credit cards, mortgages and student loans
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Liability {
+    pub account_id: String,
+    pub kind: LiabilityKind,
+    pub aprs: Vec<LiabilityApr>,
+    /// Mortgage/student loan rate, or the purchase APR of a credit card
+    pub interest_rate_percentage: Option<f64>,
+    pub is_overdue: Option<bool>,
+    pub last_payment_amount: Option<f64>,
+    pub last_payment_date: Option<NaiveDate>,
+    pub next_payment_due_date: Option<NaiveDate>,
+    pub minimum_payment_amount: Option<f64>,
+    pub last_statement_balance: Option<f64>,
+    pub last_statement_issue_date: Option<NaiveDate>,
+    pub origination_date: Option<NaiveDate>,
+    pub origination_principal_amount: Option<f64>,
+    /// Mortgage maturity or expected student loan payoff
+    pub maturity_date: Option<NaiveDate>,
+    /// Remaining type-specific fields, without account or reference numbers
+    pub details: serde_json::Value,
+}
+
+/// Account-holder email or phone number from Plaid Identity
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdentityContact {
+    pub data: String,
+    #[serde(default)]
+    pub primary: bool,
+    /// e.g. primary, secondary, home, work, mobile
+    #[serde(rename = "type", default)]
+    pub contact_type: String,
+}
+
+/// Account-holder address from Plaid Identity
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdentityAddress {
+    pub street: Option<String>,
+    pub city: Option<String>,
+    pub region: Option<String>,
+    pub postal_code: Option<String>,
+    pub country: Option<String>,
+    pub primary: bool,
+}
+
+/// One owner of an account as reported by the institution
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IdentityOwner {
+    pub names: Vec<String>,
+    pub emails: Vec<IdentityContact>,
+    pub phone_numbers: Vec<IdentityContact>,
+    pub addresses: Vec<IdentityAddress>,
+}
+
+/// Owners of one account
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountIdentity {
+    pub account_id: String,
+    pub owners: Vec<IdentityOwner>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RemovedTransaction {
     pub transaction_id: String,
@@ -189,8 +346,539 @@ pub struct PlaidError {
     pub request_id: Option<String>,
 }
 
+/// Transaction as serialized by the Plaid API; the SDK models are converted through this
+/// shape so the mapping only depends on Plaid's documented JSON field names.
+#[derive(Debug, Deserialize)]
+struct PlaidTransactionPayload {
+    transaction_id: String,
+    account_id: String,
+    amount: f64,
+    iso_currency_code: Option<String>,
+    unofficial_currency_code: Option<String>,
+    #[serde(default)]
+    category: Option<Vec<String>>,
+    category_id: Option<String>,
+    check_number: Option<String>,
+    date: String,
+    datetime: Option<DateTime<Utc>>,
+    authorized_date: Option<String>,
+    authorized_datetime: Option<DateTime<Utc>>,
+    location: Option<TransactionLocation>,
+    name: Option<String>,
+    merchant_name: Option<String>,
+    original_description: Option<String>,
+    payment_meta: Option<TransactionPaymentMeta>,
+    #[serde(default)]
+    pending: bool,
+    pending_transaction_id: Option<String>,
+    account_owner: Option<String>,
+    payment_channel: Option<String>,
+    transaction_type: Option<String>,
+    transaction_code: Option<String>,
+}
+
+fn non_empty_location(location: TransactionLocation) -> Option<TransactionLocation> {
+    let has_value = [
+        &location.address,
+        &location.city,
+        &location.region,
+        &location.postal_code,
+        &location.country,
+        &location.store_number,
+    ]
+    .iter()
+    .any(|v| v.is_some())
+        || location.lat.is_some()
+        || location.lon.is_some();
+    has_value.then_some(location)
+}
+
+fn non_empty_payment_meta(meta: TransactionPaymentMeta) -> Option<TransactionPaymentMeta> {
+    let has_value = [
+        &meta.by_order_of,
+        &meta.payee,
+        &meta.payer,
+        &meta.payment_method,
+        &meta.payment_processor,
+        &meta.ppd_id,
+        &meta.reason,
+        &meta.reference_number,
+    ]
+    .iter()
+    .any(|v| v.is_some());
+    has_value.then_some(meta)
+}
+
+impl From<PlaidTransactionPayload> for BankTransaction {
+    fn from(payload: PlaidTransactionPayload) -> Self {
+        let name = payload
+            .name
+            .or_else(|| payload.merchant_name.clone())
+            .unwrap_or_default();
+
+        BankTransaction {
+            transaction_id: payload.transaction_id,
+            account_id: payload.account_id,
+            amount: payload.amount,
+            iso_currency_code: payload.iso_currency_code,
+            unofficial_currency_code: payload.unofficial_currency_code,
+            category: payload.category.unwrap_or_default(),
+            category_id: payload.category_id,
+            check_number: payload.check_number,
+            date: payload.date,
+            datetime: payload.datetime,
+            authorized_date: payload.authorized_date,
+            authorized_datetime: payload.authorized_datetime,
+            // Plaid signals "no data" with objects whose fields are all null
+            location: payload.location.and_then(non_empty_location),
+            name,
+            merchant_name: payload.merchant_name,
+            original_description: payload.original_description,
+            payment_meta: payload.payment_meta.and_then(non_empty_payment_meta),
+            pending: payload.pending,
+            pending_transaction_id: payload.pending_transaction_id,
+            account_owner: payload.account_owner,
+            transaction_type: payload
+                .payment_channel
+                .or(payload.transaction_type)
+                .unwrap_or_else(|| "other".to_string()),
+            transaction_code: payload.transaction_code,
+        }
+    }
+}
+
+/// Convert the accounts of one Plaid item into `BankAccount`s
+fn bank_accounts_from(accounts: Vec<plaid::model::AccountBase>, item: &plaid::model::Item) -> Vec<BankAccount> {
+    accounts
+        .into_iter()
+        .map(|account| BankAccount {
+            account_id: account.account_id,
+            item_id: item.item_id.clone(),
+            mask: account.mask,
+            name: account.name,
+            official_name: account.official_name,
+            account_type: format!("{:?}", account.type_),
+            account_subtype: account.subtype.map(|s| format!("{:?}", s)),
+            balances: AccountBalances {
+                available: account.balances.available,
+                current: account.balances.current,
+                limit: account.balances.limit,
+                iso_currency_code: account.balances.iso_currency_code,
+                unofficial_currency_code: account.balances.unofficial_currency_code,
+            },
+            institution_id: item.institution_id.clone(),
+            institution_name: None, // Will be populated separately if needed
+        })
+        .collect()
+}
+
+/// Convert a Plaid transaction JSON object into a `BankTransaction`
+pub fn transaction_from_json(value: serde_json::Value) -> Result<BankTransaction> {
+    let payload: PlaidTransactionPayload =
+        serde_json::from_value(value).context("Unexpected Plaid transaction shape")?;
+    Ok(payload.into())
+}
+
+fn convert_transactions<T: Serialize>(transactions: &[T]) -> Result<Vec<BankTransaction>> {
+    transactions
+        .iter()
+        .map(|t| transaction_from_json(serde_json::to_value(t)?))
+        .collect()
+}
+
+/// Liabilities object as serialized by the Plaid API, one JSON array per liability kind
+#[derive(Debug, Default, Deserialize)]
+struct PlaidLiabilitiesPayload {
+    #[serde(default)]
+    credit: Option<Vec<serde_json::Value>>,
+    #[serde(default)]
+    mortgage: Option<Vec<serde_json::Value>>,
+    #[serde(default)]
+    student: Option<Vec<serde_json::Value>>,
+}
+
+/// Fields shared by Plaid credit, mortgage and student liability objects
+#[derive(Debug, Deserialize)]
+struct PlaidLiabilityPayload {
+    account_id: Option<String>,
+    #[serde(default)]
+    aprs: Vec<LiabilityApr>,
+    interest_rate_percentage: Option<f64>,
+    interest_rate: Option<PlaidInterestRate>,
+    is_overdue: Option<bool>,
+    last_payment_amount: Option<f64>,
+    last_payment_date: Option<NaiveDate>,
+    next_payment_due_date: Option<NaiveDate>,
+    minimum_payment_amount: Option<f64>,
+    next_monthly_payment: Option<f64>,
+    last_statement_balance: Option<f64>,
+    last_statement_issue_date: Option<NaiveDate>,
+    origination_date: Option<NaiveDate>,
+    origination_principal_amount: Option<f64>,
+    maturity_date: Option<NaiveDate>,
+    expected_payoff_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PlaidInterestRate {
+    percentage: Option<f64>,
+}
+
+/// Fields never stored with a liability
+const REDACTED_LIABILITY_FIELDS: &[&str] = &["account_number", "payment_reference_number"];
+
+/// Fields promoted to `Liability` columns, dropped from `details`
+const PROMOTED_LIABILITY_FIELDS: &[&str] = &[
+    "account_id", "aprs", "interest_rate_percentage", "is_overdue", "last_payment_amount",
+    "last_payment_date", "next_payment_due_date", "minimum_payment_amount", "last_statement_balance",
+    "last_statement_issue_date", "origination_date", "origination_principal_amount", "maturity_date",
+    "expected_payoff_date",
+];
+
+fn liability_from_json(kind: LiabilityKind, mut value: serde_json::Value) -> Result<Option<Liability>> {
+    let payload: PlaidLiabilityPayload =
+        serde_json::from_value(value.clone()).context("Unexpected Plaid liability shape")?;
+    // Credit entries without an account cannot be attached to anything
+    let Some(account_id) = payload.account_id else {
+        return Ok(None);
+    };
+
+    if let Some(fields) = value.as_object_mut() {
+        for field in REDACTED_LIABILITY_FIELDS.iter().chain(PROMOTED_LIABILITY_FIELDS) {
+            fields.remove(*field);
+        }
+    }
+
+    let interest_rate_percentage = match kind {
+        LiabilityKind::Credit => payload
+            .aprs
+            .iter()
+            .find(|apr| apr.apr_type == "purchase_apr")
+            .map(|apr| apr.apr_percentage),
+        LiabilityKind::Mortgage => payload.interest_rate.and_then(|rate| rate.percentage),
+        LiabilityKind::Student => payload.interest_rate_percentage,
+    };
+
+    Ok(Some(Liability {
+        account_id,
+        kind,
+        aprs: payload.aprs,
+        interest_rate_percentage,
+        is_overdue: payload.is_overdue,
+        last_payment_amount: payload.last_payment_amount,
+        last_payment_date: payload.last_payment_date,
+        next_payment_due_date: payload.next_payment_due_date,
+        minimum_payment_amount: payload.minimum_payment_amount.or(payload.next_monthly_payment),
+        last_statement_balance: payload.last_statement_balance,
+        last_statement_issue_date: payload.last_statement_issue_date,
+        origination_date: payload.origination_date,
+        origination_principal_amount: payload.origination_principal_amount,
+        maturity_date: payload.maturity_date.or(payload.expected_payoff_date),
+        details: value,
+    }))
+}
+
+/// Convert a Plaid `liabilities` JSON object into `Liability` values
+pub fn liabilities_from_json(value: serde_json::Value) -> Result<Vec<Liability>> {
+    let payload: PlaidLiabilitiesPayload =
+        serde_json::from_value(value).context("Unexpected Plaid liabilities shape")?;
+
+    let mut liabilities = Vec::new();
+    for (kind, entries) in [
+        (LiabilityKind::Credit, payload.credit),
+        (LiabilityKind::Mortgage, payload.mortgage),
+        (LiabilityKind::Student, payload.student),
+    ] {
+        for entry in entries.unwrap_or_default() {
+            liabilities.extend(liability_from_json(kind, entry)?);
+        }
+    }
+    Ok(liabilities)
+}
+
+/// Account with owners as serialized by `/identity/get`
+#[derive(Debug, Deserialize)]
+struct PlaidIdentityAccountPayload {
+    account_id: String,
+    #[serde(default)]
+    owners: Vec<PlaidOwnerPayload>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PlaidOwnerPayload {
+    #[serde(default)]
+    names: Vec<String>,
+    #[serde(default)]
+    emails: Vec<IdentityContact>,
+    #[serde(default)]
+    phone_numbers: Vec<IdentityContact>,
+    #[serde(default)]
+    addresses: Vec<PlaidAddressPayload>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PlaidAddressPayload {
+    data: PlaidAddressDataPayload,
+    #[serde(default)]
+    primary: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct PlaidAddressDataPayload {
+    street: Option<String>,
+    city: Option<String>,
+    region: Option<String>,
+    postal_code: Option<String>,
+    country: Option<String>,
+}
+
+impl From<PlaidOwnerPayload> for IdentityOwner {
+    fn from(payload: PlaidOwnerPayload) -> Self {
+        IdentityOwner {
+            names: payload.names,
+            emails: payload.emails,
+            phone_numbers: payload.phone_numbers,
+            addresses: payload
+                .addresses
+                .into_iter()
+                .map(|address| IdentityAddress {
+                    street: address.data.street,
+                    city: address.data.city,
+                    region: address.data.region,
+                    postal_code: address.data.postal_code,
+                    country: address.data.country,
+                    primary: address.primary,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Monthly statement available from Plaid Statements
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Statement {
+    pub statement_id: String,
+    pub account_id: String,
+    pub year: i32,
+    /// 1-12
+    pub month: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct PlaidStatementsAccountPayload {
+    account_id: String,
+    #[serde(default)]
+    statements: Vec<PlaidStatementPayload>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PlaidStatementPayload {
+    statement_id: String,
+    year: i32,
+    month: u32,
+}
+
+/// Convert Plaid `/statements/list` accounts into statements, skipping entries with an invalid month
+pub fn statements_from_json(accounts: serde_json::Value) -> Result<Vec<Statement>> {
+    let accounts: Vec<PlaidStatementsAccountPayload> =
+        serde_json::from_value(accounts).context("Unexpected Plaid statements shape")?;
+    Ok(accounts
+        .into_iter()
+        .flat_map(|account| {
+            let account_id = account.account_id;
+            account
+                .statements
+                .into_iter()
+                .filter(|statement| (1..=12).contains(&statement.month))
+                .map(move |statement| Statement {
+                    statement_id: statement.statement_id,
+                    account_id: account_id.clone(),
+                    year: statement.year,
+                    month: statement.month,
+                })
+        })
+        .collect())
+}
+
+/// Direction of a Plaid Transfer, seen from the linked account
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferDirection {
+    /// Pull funds from the linked account
+    Debit,
+    /// Push funds to the linked account
+    Credit,
+}
+
+impl TransferDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransferDirection::Debit => "debit",
+            TransferDirection::Credit => "credit",
+        }
+    }
+
+    fn to_plaid(self) -> plaid::model::TransferType {
+        match self {
+            TransferDirection::Debit => plaid::model::TransferType::Debit,
+            TransferDirection::Credit => plaid::model::TransferType::Credit,
+        }
+    }
+}
+
+/// Request for `/transfer/authorization/create` over standard ACH
+#[derive(Debug, Clone)]
+pub struct TransferAuthorizationRequest {
+    pub access_token: String,
+    pub account_id: String,
+    pub direction: TransferDirection,
+    pub amount: f64,
+    /// Account holder's legal name, required by Plaid's risk check
+    pub legal_name: String,
+    /// Makes retries return the original authorization
+    pub idempotency_key: String,
+}
+
+/// Plaid's risk decision on a proposed transfer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferAuthorization {
+    pub authorization_id: String,
+    /// `approved`, `declined` or `user_action_required`
+    pub decision: String,
+    pub decision_rationale: Option<String>,
+}
+
+impl TransferAuthorization {
+    pub fn is_approved(&self) -> bool {
+        self.decision == "approved"
+    }
+}
+
+/// Transfer created from an approved authorization
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreatedTransfer {
+    pub transfer_id: String,
+    pub status: String,
+}
+
+/// Lifecycle event of a transfer from `/transfer/event/sync`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferEvent {
+    pub event_id: i64,
+    pub transfer_id: String,
+    /// `pending`, `posted`, `settled`, `funds_available`, `cancelled`, `failed`, `returned`, ...
+    pub event_type: String,
+    pub failure_reason: Option<String>,
+}
+
+/// One page of transfer events, oldest first
+#[derive(Debug, Clone)]
+pub struct TransferEventsPage {
+    pub events: Vec<TransferEvent>,
+    pub has_more: bool,
+}
+
+/// Public key from `/webhook_verification_key/get` that signs a webhook's `Plaid-Verification` JWT
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct WebhookVerificationKey {
+    pub kid: String,
+    /// Always `ES256`
+    pub alg: String,
+    /// Base64url coordinates of the P-256 public key
+    pub x: String,
+    pub y: String,
+    /// Set once Plaid rotated the key out (Unix timestamp)
+    pub expired_at: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct WebhookVerificationKeyResponse {
+    key: WebhookVerificationKey,
+}
+
+/// Plaid expects transfer amounts as decimal strings with two fraction digits
+pub fn transfer_amount(amount: f64) -> String {
+    format!("{:.2}", amount)
+}
+
+/// Plaid's wire name of a serialized enum value, e.g. `funds_available`
+fn plaid_enum_name<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn transfer_failure_description(failure: Option<&plaid::model::TransferFailure>) -> Option<String> {
+    let failure = failure?;
+    match (&failure.description, &failure.ach_return_code) {
+        (Some(description), Some(code)) => Some(format!("{} ({})", description, code)),
+        (Some(description), None) => Some(description.clone()),
+        (None, Some(code)) => Some(format!("ACH return code {}", code)),
+        (None, None) => None,
+    }
+}
+
+/// Institution metadata used to brand linked accounts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Institution {
+    pub institution_id: String,
+    pub name: String,
+    /// Base64-encoded PNG logo, when Plaid has one
+    pub logo: Option<String>,
+    /// Hex brand color such as `#1f1f1f`
+    pub primary_color: Option<String>,
+    pub url: Option<String>,
+    /// Plaid products the institution supports, e.g. `transactions`, `liabilities`
+    pub products: Vec<String>,
+    pub country_codes: Vec<String>,
+    pub oauth: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct PlaidInstitutionPayload {
+    institution_id: String,
+    name: String,
+    logo: Option<String>,
+    primary_color: Option<String>,
+    url: Option<String>,
+    #[serde(default)]
+    products: Vec<String>,
+    #[serde(default)]
+    country_codes: Vec<String>,
+    #[serde(default)]
+    oauth: bool,
+}
+
+/// Convert a Plaid institution JSON object into an `Institution`
+pub fn institution_from_json(value: serde_json::Value) -> Result<Institution> {
+    let payload: PlaidInstitutionPayload =
+        serde_json::from_value(value).context("Unexpected Plaid institution shape")?;
+    Ok(Institution {
+        institution_id: payload.institution_id,
+        name: payload.name,
+        logo: payload.logo.filter(|l| !l.is_empty()),
+        primary_color: payload.primary_color.filter(|c| !c.is_empty()),
+        url: payload.url.filter(|u| !u.is_empty()),
+        products: payload.products,
+        country_codes: payload.country_codes,
+        oauth: payload.oauth,
+    })
+}
+
+/// Convert a Plaid account-with-owners JSON object into an `AccountIdentity`
+pub fn identity_from_json(value: serde_json::Value) -> Result<AccountIdentity> {
+    let payload: PlaidIdentityAccountPayload =
+        serde_json::from_value(value).context("Unexpected Plaid identity shape")?;
+    Ok(AccountIdentity {
+        account_id: payload.account_id,
+        owners: payload.owners.into_iter().map(IdentityOwner::from).collect(),
+    })
+}
+
 pub struct PlaidClient {
     client: PlaidSDKClient,
+    /// Plain HTTP client for endpoints the SDK cannot call, such as binary downloads
+    http: reqwest::Client,
     config: PlaidConfig,
 }
 
@@ -205,47 +893,28 @@ impl PlaidClient {
             "Initialized Plaid client"
         );
 
-        Ok(Self { client, config })
+        Ok(Self {
+            client,
+            http: reqwest::Client::new(),
+            config,
+        })
     }
 
+    /// Client configured from `Settings::load`
     #[instrument]
     pub async fn from_config() -> Result<Self> {
-        let app_config = AppConfig::load().await;
-        
-        let environment = match app_config.plaid_env.to_lowercase().as_str() {
-            "production" => PlaidEnvironment::Production,
-            "development" => PlaidEnvironment::Development,
-            _ => PlaidEnvironment::Sandbox,
-        };
-
-        let config = PlaidConfig {
-            client_id: app_config.plaid_client_id,
-            secret: app_config.plaid_secret,
-            environment,
-            webhook_url: app_config.plaid_webhook_url,
-        };
-
-        Self::new(config)
+        Self::new(Settings::load(&SettingsSources::from_env().await?).await?.plaid)
     }
 
+    /// Client configured from `Settings::from_env`
     #[instrument]
     pub fn from_env() -> Result<Self> {
-        let app_config = AppConfig::from_env();
-        
-        let environment = match app_config.plaid_env.to_lowercase().as_str() {
-            "production" => PlaidEnvironment::Production,
-            "development" => PlaidEnvironment::Development,
-            _ => PlaidEnvironment::Sandbox,
-        };
-
-        let config = PlaidConfig {
-            client_id: app_config.plaid_client_id,
-            secret: app_config.plaid_secret,
-            environment,
-            webhook_url: app_config.plaid_webhook_url,
-        };
+        Self::new(Settings::from_env()?.plaid)
+    }
 
-        Self::new(config)
+    /// Get the current configuration
+    pub fn config(&self) -> &PlaidConfig {
+        &self.config
     }
 
     #[instrument(skip(self, _request))]
@@ -288,30 +957,7 @@ impl PlaidClient {
             .await
             .context("Failed to fetch accounts from Plaid")?;
 
-        let mut bank_accounts = Vec::new();
-
-        for account in response.accounts {
-            let bank_account = BankAccount {
-                account_id: account.account_id.clone(),
-                item_id: response.item.item_id.clone(),
-                mask: account.mask.clone(),
-                name: account.name.clone(),
-                official_name: account.official_name.clone(),
-                account_type: format!("{:?}", account.type_),
-                account_subtype: account.subtype.map(|s| format!("{:?}", s)),
-                balances: AccountBalances {
-                    available: account.balances.available,
-                    current: account.balances.current,
-                    limit: account.balances.limit,
-                    iso_currency_code: account.balances.iso_currency_code,
-                    unofficial_currency_code: account.balances.unofficial_currency_code,
-                },
-                institution_id: response.item.institution_id.clone(),
-                institution_name: None, // Will be populated separately if needed
-            };
-
-            bank_accounts.push(bank_account);
-        }
+        let bank_accounts = bank_accounts_from(response.accounts, &response.item);
 
         info!(
             account_count = bank_accounts.len(),
@@ -323,6 +969,81 @@ impl PlaidClient {
         Ok(bank_accounts)
     }
 
+    /// Fetch real-time balances from `/accounts/balance/get`.
+    ///
+    /// Unlike `get_accounts`, which may return cached balances, this asks the institution
+    /// for fresh data and is billed per call.
+    #[instrument(skip(self, access_token), fields(access_token_length = access_token.len()))]
+    pub async fn get_balances(&self, access_token: &str) -> Result<Vec<BankAccount>> {
+        debug!("Fetching real-time balances from Plaid");
+
+        let response = self.client
+            .accounts_balance_get(access_token)
+            .await
+            .context("Failed to fetch balances from Plaid")?;
+
+        let bank_accounts = bank_accounts_from(response.accounts, &response.item);
+
+        info!(
+            account_count = bank_accounts.len(),
+            item_id = %response.item.item_id,
+            request_id = %response.request_id,
+            "Balances fetched successfully"
+        );
+
+        Ok(bank_accounts)
+    }
+
+    /// Fetch credit card, mortgage and student loan details from `/liabilities/get`
+    #[instrument(skip(self, access_token), fields(access_token_length = access_token.len()))]
+    pub async fn get_liabilities(&self, access_token: &str) -> Result<Vec<Liability>> {
+        debug!("Fetching liabilities from Plaid");
+
+        let response = self.client
+            .liabilities_get(access_token)
+            .await
+            .context("Failed to fetch liabilities from Plaid")?;
+
+        let liabilities = liabilities_from_json(
+            serde_json::to_value(&response.liabilities).context("Failed to serialize Plaid liabilities")?,
+        )?;
+
+        info!(
+            liability_count = liabilities.len(),
+            item_id = %response.item.item_id,
+            request_id = %response.request_id,
+            "Liabilities fetched successfully"
+        );
+
+        Ok(liabilities)
+    }
+
+    /// Fetch account-owner names, emails, phone numbers and addresses from `/identity/get`
+    #[instrument(skip(self, access_token), fields(access_token_length = access_token.len()))]
+    pub async fn get_identity(&self, access_token: &str) -> Result<Vec<AccountIdentity>> {
+        debug!("Fetching identity from Plaid");
+
+        let response = self.client
+            .identity_get(access_token)
+            .await
+            .context("Failed to fetch identity from Plaid")?;
+
+        let identities = response
+            .accounts
+            .iter()
+            .map(|account| identity_from_json(serde_json::to_value(account)?))
+            .collect::<Result<Vec<_>>>()?;
+
+        info!(
+            account_count = identities.len(),
+            item_id = %response.item.item_id,
+            request_id = %response.request_id,
+            "Identity fetched successfully"
+        );
+
+        Ok(identities)
+    }
+
     #[instrument(skip(self, request), fields(access_token_length = request.access_token.len()))]
     pub async fn sync_transactions(&self, request: TransactionSyncRequest) -> Result<TransactionSyncResponse> {
         debug!(
@@ -331,18 +1052,31 @@ impl PlaidClient {
             "Syncing transactions from Plaid"
         );
 
-        let response = self.client
-            .transactions_sync(&request.access_token)
+        let mut sync_request = self.client.transactions_sync(&request.access_token);
+        if let Some(cursor) = request.cursor.as_deref() {
+            sync_request = sync_request.cursor(cursor);
+        }
+        if let Some(count) = request.count {
+            sync_request = sync_request.count(count as i64);
+        }
+
+        let response = sync_request
             .await
             .context("Failed to sync transactions from Plaid")?;
 
-        // Note: This is simplified - in a real implementation you would properly convert
-        // the Plaid Transaction types to your BankTransaction types
-        let added = Vec::new();
-        let modified = Vec::new();
-        let removed = Vec::new();
+        let added = convert_transactions(&response.added).context("Failed to convert added transactions")?;
+        let modified = convert_transactions(&response.modified).context("Failed to convert modified transactions")?;
+        let removed = response
+            .removed
+            .iter()
+            .map(|r| serde_json::to_value(r).and_then(serde_json::from_value))
+            .collect::<Result<Vec<RemovedTransaction>, _>>()
+            .context("Failed to convert removed transactions")?;
 
         info!(
+            added = added.len(),
+            modified = modified.len(),
+            removed = removed.len(),
             has_more = response.has_more,
             request_id = %response.request_id,
             "Transactions synced successfully"
@@ -358,18 +1092,98 @@ impl PlaidClient {
         })
     }
 
-    #[instrument(skip(self, _access_token, _start_date, _end_date))]
+    /// Fetch one page of `/transactions/get`; dates are `YYYY-MM-DD` and inclusive
+    #[instrument(skip(self, access_token), fields(access_token_length = access_token.len()))]
+    pub async fn get_transactions_page(
+        &self,
+        access_token: &str,
+        start_date: &str,
+        end_date: &str,
+        count: Option<i32>,
+        offset: Option<i32>,
+    ) -> Result<TransactionsPage> {
+        let range = TransactionsGetRange::validate(start_date, end_date, count, offset)?;
+        debug!(
+            start_date = %range.start_date,
+            end_date = %range.end_date,
+            count = range.count,
+            offset = range.offset,
+            "Fetching transactions from Plaid"
+        );
+
+        let response = self.client
+            .transactions_get(access_token, range.end_date, range.start_date)
+            .options(plaid::model::TransactionsGetRequestOptions {
+                count: Some(range.count as i64),
+                offset: Some(range.offset as i64),
+                include_original_description: Some(true),
+                ..Default::default()
+            })
+            .await
+            .context("Failed to fetch transactions from Plaid")?;
+
+        let transactions = convert_transactions(&response.transactions)
+            .context("Failed to convert transactions")?;
+
+        info!(
+            transaction_count = transactions.len(),
+            total_transactions = response.total_transactions,
+            request_id = %response.request_id,
+            "Transactions fetched successfully"
+        );
+
+        Ok(TransactionsPage {
+            transactions,
+            total_transactions: response.total_transactions,
+            offset: range.offset,
+        })
+    }
+
+    /// Fetch one page of transactions in a date range
+    #[instrument(skip(self, access_token, start_date, end_date))]
     pub async fn get_transactions(
         &self,
-        _access_token: &str,
-        _start_date: &str,
-        _end_date: &str,
-        _count: Option<i32>,
-        _offset: Option<i32>,
+        access_token: &str,
+        start_date: &str,
+        end_date: &str,
+        count: Option<i32>,
+        offset: Option<i32>,
     ) -> Result<Vec<BankTransaction>> {
-        // Note: This is a placeholder implementation
-        // In a real implementation, you would need to properly construct the request
-        return Err(anyhow::anyhow!("Transactions get not yet implemented"));
+        let page = self
+            .get_transactions_page(access_token, start_date, end_date, count, offset)
+            .await?;
+        Ok(page.transactions)
+    }
+
+    /// Fetch every transaction in a date range, paging until `total_transactions` is reached
+    #[instrument(skip(self, access_token))]
+    pub async fn get_all_transactions(
+        &self,
+        access_token: &str,
+        start_date: &str,
+        end_date: &str,
+    ) -> Result<Vec<BankTransaction>> {
+        let mut transactions = Vec::new();
+
+        loop {
+            let page = self
+                .get_transactions_page(
+                    access_token,
+                    start_date,
+                    end_date,
+                    Some(MAX_TRANSACTIONS_GET_COUNT),
+                    Some(transactions.len() as i32),
+                )
+                .await?;
+
+            let fetched = page.transactions.len();
+            transactions.extend(page.transactions);
+            if fetched == 0 || transactions.len() as i64 >= page.total_transactions {
+                break;
+            }
+        }
+
+        Ok(transactions)
     }
 
     #[instrument(skip(self, access_token), fields(access_token_length = access_token.len()))]
@@ -390,10 +1204,256 @@ impl PlaidClient {
         Ok(serde_json::to_value(response.item)?)
     }
 
+    /// Fetch an institution's name, branding and supported products
     #[instrument(skip(self))]
-    pub async fn get_institution(&self, _institution_id: &str, _country_codes: Vec<&str>) -> Result<serde_json::Value> {
-        // Note: This is a placeholder implementation
-        return Err(anyhow::anyhow!("Institution get not yet implemented"));
+    pub async fn get_institution(&self, institution_id: &str, country_codes: Vec<&str>) -> Result<Institution> {
+        debug!("Fetching institution from Plaid");
+
+        let country_codes: Vec<plaid::model::CountryCode> = serde_json::from_value(serde_json::json!(country_codes))
+            .context("Invalid country codes")?;
+        let response = self.client
+            .institutions_get_by_id(institution_id, &country_codes)
+            .options(plaid::model::InstitutionsGetByIdRequestOptions {
+                include_optional_metadata: Some(true),
+                ..Default::default()
+            })
+            .await
+            .context("Failed to fetch institution from Plaid")?;
+
+        let institution = institution_from_json(serde_json::to_value(&response.institution)?)?;
+
+        info!(
+            institution_name = %institution.name,
+            request_id = %response.request_id,
+            "Institution fetched successfully"
+        );
+
+        Ok(institution)
+    }
+
+    /// List the monthly statements of an item's accounts from `/statements/list`
+    #[instrument(skip(self, access_token), fields(access_token_length = access_token.len()))]
+    pub async fn list_statements(&self, access_token: &str) -> Result<Vec<Statement>> {
+        debug!("Listing statements from Plaid");
+
+        let response = self.client
+            .statements_list(access_token)
+            .await
+            .context("Failed to list statements from Plaid")?;
+
+        let statements = statements_from_json(
+            serde_json::to_value(&response.accounts).context("Failed to serialize Plaid statements")?,
+        )?;
+
+        info!(
+            statement_count = statements.len(),
+            item_id = %response.item_id,
+            request_id = %response.request_id,
+            "Statements listed successfully"
+        );
+
+        Ok(statements)
+    }
+
+    /// Download a statement PDF from `/statements/download`, verifying Plaid's content hash.
+    /// Called directly because the SDK decodes the binary response as JSON.
+    #[instrument(skip(self, access_token), fields(access_token_length = access_token.len()))]
+    pub async fn download_statement(&self, access_token: &str, statement_id: &str) -> Result<Vec<u8>> {
+        debug!("Downloading statement from Plaid");
+
+        let response = self.http
+            .post(format!("{}/statements/download", self.config.environment.base_url()))
+            .header("PLAID-CLIENT-ID", &self.config.client_id)
+            .header("PLAID-SECRET", &self.config.secret)
+            .json(&serde_json::json!({
+                "access_token": access_token,
+                "statement_id": statement_id,
+            }))
+            .send()
+            .await
+            .context("Failed to download statement from Plaid")?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            let error: Option<PlaidError> = serde_json::from_str(&body).ok();
+            return Err(match error {
+                Some(error) => anyhow!("Plaid statement download failed: {} ({})", error.error_code, error.error_message),
+                None => anyhow!("Plaid statement download failed with status {}", status),
+            });
+        }
+
+        let expected_hash = response
+            .headers()
+            .get("Plaid-Content-Hash")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_ascii_lowercase);
+        let body = response.bytes().await.context("Failed to read statement from Plaid")?.to_vec();
+        if let Some(expected_hash) = expected_hash {
+            let actual_hash = format!("{:x}", Sha256::digest(&body));
+            if actual_hash != expected_hash {
+                return Err(anyhow!("Plaid statement {} failed its content hash check", statement_id));
+            }
+        }
+
+        info!(size = body.len(), "Statement downloaded successfully");
+
+        Ok(body)
+    }
+
+    /// Ask Plaid to risk-check a proposed ACH transfer with `/transfer/authorization/create`
+    #[instrument(skip(self, request), fields(account_id = %request.account_id, direction = request.direction.as_str()))]
+    pub async fn authorize_transfer(&self, request: TransferAuthorizationRequest) -> Result<TransferAuthorization> {
+        debug!("Authorizing transfer with Plaid");
+
+        let amount = transfer_amount(request.amount);
+        let response = self.client
+            .transfer_authorization_create(plaid::request::transfer_authorization_create::TransferAuthorizationCreateRequired {
+                access_token: &request.access_token,
+                account_id: &request.account_id,
+                amount: &amount,
+                network: plaid::model::TransferNetwork::Ach,
+                type_: request.direction.to_plaid(),
+                user: plaid::model::TransferAuthorizationUserInRequest {
+                    legal_name: request.legal_name.clone(),
+                    ..Default::default()
+                },
+            })
+            .ach_class(plaid::model::AchClass::Web)
+            .idempotency_key(&request.idempotency_key)
+            .await
+            .context("Failed to authorize transfer with Plaid")?;
+
+        let authorization = TransferAuthorization {
+            authorization_id: response.authorization.id.clone(),
+            decision: plaid_enum_name(&response.authorization.decision),
+            decision_rationale: response.authorization.decision_rationale.as_ref().map(|r| r.description.clone()),
+        };
+
+        info!(
+            authorization_id = %authorization.authorization_id,
+            decision = %authorization.decision,
+            request_id = %response.request_id,
+            "Transfer authorization created"
+        );
+
+        Ok(authorization)
+    }
+
+    /// Initiate a transfer from an approved authorization with `/transfer/create`
+    #[instrument(skip(self, access_token), fields(access_token_length = access_token.len()))]
+    pub async fn create_transfer(
+        &self,
+        access_token: &str,
+        account_id: &str,
+        authorization_id: &str,
+        description: &str,
+    ) -> Result<CreatedTransfer> {
+        debug!("Creating transfer with Plaid");
+
+        let response = self.client
+            .transfer_create(plaid::request::transfer_create::TransferCreateRequired {
+                access_token,
+                account_id,
+                authorization_id,
+                description,
+            })
+            .await
+            .context("Failed to create transfer with Plaid")?;
+
+        let transfer = CreatedTransfer {
+            transfer_id: response.transfer.id.clone(),
+            status: plaid_enum_name(&response.transfer.status),
+        };
+
+        info!(
+            transfer_id = %transfer.transfer_id,
+            status = %transfer.status,
+            request_id = %response.request_id,
+            "Transfer created"
+        );
+
+        Ok(transfer)
+    }
+
+    /// Cancel a transfer that has not been sent to the network yet
+    #[instrument(skip(self))]
+    pub async fn cancel_transfer(&self, transfer_id: &str) -> Result<()> {
+        debug!("Cancelling transfer with Plaid");
+
+        let response = self.client
+            .transfer_cancel(transfer_id)
+            .await
+            .context("Failed to cancel transfer with Plaid")?;
+
+        info!(request_id = %response.request_id, "Transfer cancelled");
+
+        Ok(())
+    }
+
+    /// Transfer events after `after_id` from `/transfer/event/sync`
+    #[instrument(skip(self))]
+    pub async fn sync_transfer_events(&self, after_id: i64) -> Result<TransferEventsPage> {
+        debug!("Syncing transfer events from Plaid");
+
+        let response = self.client
+            .transfer_event_sync(after_id)
+            .count(25)
+            .await
+            .context("Failed to sync transfer events from Plaid")?;
+
+        let events: Vec<TransferEvent> = response
+            .transfer_events
+            .iter()
+            .map(|event| TransferEvent {
+                event_id: event.event_id,
+                transfer_id: event.transfer_id.clone(),
+                event_type: plaid_enum_name(&event.event_type),
+                failure_reason: transfer_failure_description(event.failure_reason.as_ref()),
+            })
+            .collect();
+
+        info!(
+            event_count = events.len(),
+            has_more = response.has_more,
+            request_id = %response.request_id,
+            "Transfer events synced"
+        );
+
+        Ok(TransferEventsPage {
+            events,
+            has_more: response.has_more,
+        })
+    }
+
+    /// Key `key_id` that signs webhooks, from `/webhook_verification_key/get`.
+    /// Called directly because the SDK has no webhook verification endpoint.
+    #[instrument(skip(self))]
+    pub async fn webhook_verification_key(&self, key_id: &str) -> Result<WebhookVerificationKey> {
+        debug!("Fetching webhook verification key from Plaid");
+
+        let response = self.http
+            .post(format!("{}/webhook_verification_key/get", self.config.environment.base_url()))
+            .header("PLAID-CLIENT-ID", &self.config.client_id)
+            .header("PLAID-SECRET", &self.config.secret)
+            .json(&serde_json::json!({ "key_id": key_id }))
+            .send()
+            .await
+            .context("Failed to fetch webhook verification key from Plaid")?;
+
+        let status = response.status();
+        let body = response.text().await.context("Failed to read webhook verification key from Plaid")?;
+        if !status.is_success() {
+            let error: Option<PlaidError> = serde_json::from_str(&body).ok();
+            return Err(match error {
+                Some(error) => anyhow!("Plaid webhook key lookup failed: {} ({})", error.error_code, error.error_message),
+                None => anyhow!("Plaid webhook key lookup failed with status {}", status),
+            });
+        }
+
+        let response: WebhookVerificationKeyResponse =
+            serde_json::from_str(&body).context("Unexpected Plaid webhook verification key shape")?;
+        Ok(response.key)
     }
 
     #[instrument(skip(self, access_token), fields(access_token_length = access_token.len()))]
@@ -436,10 +1496,244 @@ mod tests {
         assert_eq!(request.language, "en");
     }
 
+    #[test]
+    fn test_statements_from_json() {
+        let statements = statements_from_json(serde_json::json!([
+            {
+                "account_id": "acc_1",
+                "account_mask": "0000",
+                "account_name": "Checking",
+                "statements": [
+                    {"statement_id": "st_1", "year": 2024, "month": 1},
+                    {"statement_id": "st_bad", "year": 2024, "month": 13}
+                ]
+            },
+            {"account_id": "acc_2", "statements": [{"statement_id": "st_2", "year": 2023, "month": 12}]}
+        ]))
+        .unwrap();
+
+        assert_eq!(statements.len(), 2);
+        assert_eq!(
+            statements[0],
+            Statement {
+                statement_id: "st_1".to_string(),
+                account_id: "acc_1".to_string(),
+                year: 2024,
+                month: 1,
+            }
+        );
+        assert_eq!(statements[1].account_id, "acc_2");
+    }
+
+    #[test]
+    fn test_transfer_amount_and_failure() {
+        assert_eq!(transfer_amount(12.5), "12.50");
+        assert_eq!(transfer_amount(0.004 + 10.0), "10.00");
+
+        let failure = plaid::model::TransferFailure {
+            ach_return_code: Some("R01".to_string()),
+            description: Some("Insufficient funds".to_string()),
+        };
+        assert_eq!(
+            transfer_failure_description(Some(&failure)),
+            Some("Insufficient funds (R01)".to_string())
+        );
+        assert_eq!(transfer_failure_description(None), None);
+        assert_eq!(plaid_enum_name(&plaid::model::TransferEventType::FundsAvailable), "funds_available");
+    }
+
+    #[test]
+    fn test_institution_from_json() {
+        let institution = institution_from_json(serde_json::json!({
+            "institution_id": "ins_109508",
+            "name": "First Platypus Bank",
+            "products": ["assets", "auth", "transactions"],
+            "country_codes": ["US"],
+            "url": "https://www.platypus.example",
+            "primary_color": "#1f1f1f",
+            "logo": "",
+            "routing_numbers": ["011000138"],
+            "oauth": false
+        }))
+        .unwrap();
+
+        assert_eq!(institution.name, "First Platypus Bank");
+        assert_eq!(institution.products, vec!["assets", "auth", "transactions"]);
+        assert_eq!(institution.primary_color.as_deref(), Some("#1f1f1f"));
+        assert_eq!(institution.logo, None);
+        assert!(institution_from_json(serde_json::json!({ "name": "No ID" })).is_err());
+    }
+
+    fn sync_fixture() -> serde_json::Value {
+        serde_json::from_str(include_str!("../../tests/fixtures/plaid/transactions_sync.json")).unwrap()
+    }
+
+    #[test]
+    fn test_transaction_from_json_maps_all_fields() {
+        let fixture = sync_fixture();
+        let transaction = transaction_from_json(fixture["added"][0].clone()).unwrap();
+
+        assert_eq!(transaction.transaction_id, "lPNjeW1nR6CDn5okmGQ6hEpMo4lLNoSrzqDje");
+        assert_eq!(transaction.amount, 72.1);
+        assert_eq!(transaction.iso_currency_code.as_deref(), Some("USD"));
+        assert_eq!(transaction.category, vec!["Shops", "Supermarkets and Groceries"]);
+        assert_eq!(transaction.date, "2023-09-24");
+        assert_eq!(transaction.authorized_date.as_deref(), Some("2023-09-22"));
+        assert_eq!(
+            transaction.datetime.unwrap().to_rfc3339(),
+            "2023-09-24T11:01:01+00:00"
+        );
+        assert_eq!(transaction.merchant_name.as_deref(), Some("Walmart"));
+        assert_eq!(transaction.transaction_type, "in store");
+        assert!(!transaction.pending);
+        assert_eq!(
+            transaction.pending_transaction_id.as_deref(),
+            Some("no86Eox18VHMvaOVL7gPUM9ap3aR1LsAVZ5nc")
+        );
+
+        let location = transaction.location.unwrap();
+        assert_eq!(location.city.as_deref(), Some("Washington"));
+        assert_eq!(location.lat, Some(38.898));
+        // Payment meta with only null fields is dropped
+        assert!(transaction.payment_meta.is_none());
+    }
+
+    #[test]
+    fn test_transaction_from_json_handles_pending_credit() {
+        let fixture = sync_fixture();
+        let transaction = transaction_from_json(fixture["added"][1].clone()).unwrap();
+
+        assert_eq!(transaction.amount, -1500.0);
+        assert!(transaction.pending);
+        assert!(transaction.category.is_empty());
+        assert!(transaction.location.is_none());
+        assert_eq!(transaction.transaction_code.as_deref(), Some("direct debit"));
+
+        let meta = transaction.payment_meta.unwrap();
+        assert_eq!(meta.payer.as_deref(), Some("Gusto"));
+        assert_eq!(meta.payment_method.as_deref(), Some("ACH"));
+    }
+
+    #[test]
+    fn test_transaction_from_json_tolerates_missing_optional_fields() {
+        let fixture = sync_fixture();
+        let transaction = transaction_from_json(fixture["modified"][0].clone()).unwrap();
+
+        assert_eq!(transaction.name, "DOORDASH");
+        assert!(transaction.location.is_none());
+        assert!(transaction.account_owner.is_none());
+        assert_eq!(transaction.transaction_type, "online");
+    }
+
+    #[test]
+    fn test_removed_transactions_from_fixture() {
+        let fixture = sync_fixture();
+        let removed: Vec<RemovedTransaction> = serde_json::from_value(fixture["removed"].clone()).unwrap();
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].transaction_id, "CmdQTNgems8BT1B7ibkoUXVPyAeehT3Tmzk0l");
+    }
+
+    #[test]
+    fn test_transaction_from_json_rejects_missing_ids() {
+        assert!(transaction_from_json(serde_json::json!({ "amount": 1.0, "date": "2023-01-01" })).is_err());
+    }
+
+    #[test]
+    fn test_transactions_get_range_validation() {
+        let range = TransactionsGetRange::validate("2024-01-01", "2024-01-31", None, None).unwrap();
+        assert_eq!(range.count, DEFAULT_TRANSACTIONS_GET_COUNT);
+        assert_eq!(range.offset, 0);
+
+        assert!(TransactionsGetRange::validate("2024-13-01", "2024-01-31", None, None).is_err());
+        assert!(TransactionsGetRange::validate("2024-02-01", "2024-01-31", None, None).is_err());
+        assert!(TransactionsGetRange::validate("2024-01-01", "2999-01-01", None, None).is_err());
+        assert!(TransactionsGetRange::validate("2024-01-01", "2024-01-31", Some(0), None).is_err());
+        assert!(TransactionsGetRange::validate("2024-01-01", "2024-01-31", Some(501), None).is_err());
+        assert!(TransactionsGetRange::validate("2024-01-01", "2024-01-31", Some(10), Some(-1)).is_err());
+    }
+
     #[test]
     fn test_plaid_environment_as_str() {
         assert_eq!(PlaidEnvironment::Sandbox.as_str(), "sandbox");
         assert_eq!(PlaidEnvironment::Development.as_str(), "development");
         assert_eq!(PlaidEnvironment::Production.as_str(), "production");
     }
+
+    fn liabilities_fixture() -> serde_json::Value {
+        serde_json::from_str(include_str!("../../tests/fixtures/plaid/liabilities_get.json")).unwrap()
+    }
+
+    #[test]
+    fn test_liabilities_from_json_normalizes_each_kind() {
+        let liabilities = liabilities_from_json(liabilities_fixture()["liabilities"].clone()).unwrap();
+        assert_eq!(liabilities.len(), 3);
+
+        let credit = &liabilities[0];
+        assert_eq!(credit.kind, LiabilityKind::Credit);
+        assert_eq!(credit.aprs.len(), 2);
+        assert_eq!(credit.interest_rate_percentage, Some(12.5));
+        assert_eq!(credit.next_payment_due_date, NaiveDate::from_ymd_opt(2020, 5, 28));
+
+        let mortgage = &liabilities[1];
+        assert_eq!(mortgage.kind, LiabilityKind::Mortgage);
+        assert_eq!(mortgage.interest_rate_percentage, Some(3.99));
+        assert_eq!(mortgage.minimum_payment_amount, Some(3141.54));
+        assert_eq!(mortgage.maturity_date, NaiveDate::from_ymd_opt(2045, 7, 31));
+        assert_eq!(mortgage.details["has_pmi"], true);
+        assert!(mortgage.details.get("account_number").is_none());
+
+        let student = &liabilities[2];
+        assert_eq!(student.kind, LiabilityKind::Student);
+        assert_eq!(student.interest_rate_percentage, Some(5.25));
+        assert_eq!(student.maturity_date, NaiveDate::from_ymd_opt(2032, 7, 28));
+        assert_eq!(student.details["loan_name"], "Consolidation");
+        assert!(student.details.get("payment_reference_number").is_none());
+    }
+
+    #[test]
+    fn test_liabilities_skip_credit_without_account() {
+        let value = serde_json::json!({
+            "credit": [{ "account_id": null, "aprs": [] }],
+            "mortgage": null,
+            "student": null
+        });
+        assert!(liabilities_from_json(value).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_identity_from_json_flattens_addresses() {
+        let value = serde_json::json!({
+            "account_id": "BxBXxLj1m4HMXBm9WZZmCWVbPjX16EHwv99vp",
+            "balances": { "available": 100, "current": 110 },
+            "owners": [{
+                "names": ["Alberta Bobbeth Charleson"],
+                "emails": [
+                    { "data": "accountholder0@example.com", "primary": true, "type": "primary" }
+                ],
+                "phone_numbers": [
+                    { "data": "1112223333", "primary": false, "type": "home" }
+                ],
+                "addresses": [{
+                    "data": {
+                        "city": "Malakoff",
+                        "country": "US",
+                        "postal_code": "14236",
+                        "region": "NY",
+                        "street": "2992 Cameron Road"
+                    },
+                    "primary": true
+                }]
+            }]
+        });
+
+        let identity = identity_from_json(value).unwrap();
+        assert_eq!(identity.account_id, "BxBXxLj1m4HMXBm9WZZmCWVbPjX16EHwv99vp");
+        let owner = &identity.owners[0];
+        assert_eq!(owner.names, vec!["Alberta Bobbeth Charleson"]);
+        assert_eq!(owner.emails[0].contact_type, "primary");
+        assert!(owner.emails[0].primary);
+        assert_eq!(owner.phone_numbers[0].contact_type, "home");
+        assert_eq!(owner.addresses[0].city.as_deref(), Some("Malakoff"));
+        assert!(owner.addresses[0].primary);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,166 @@
+use crate::adapter::plaid::{PlaidClient, WebhookVerificationKey};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{debug, instrument};
+
+/// Header carrying the JWT Plaid signs each webhook with
+pub const VERIFICATION_HEADER: &str = "plaid-verification";
+/// Oldest webhook accepted, in seconds since Plaid signed it, so a captured webhook can't be replayed later
+const MAX_WEBHOOK_AGE_SECONDS: i64 = 5 * 60;
+/// Seconds a key is cached before it is fetched again, to notice when Plaid expires it
+const KEY_CACHE_TTL_SECONDS: i64 = 60 * 60;
+
+#[derive(Debug, Deserialize)]
+struct WebhookClaims {
+    iat: i64,
+    request_body_sha256: String,
+}
+
+struct CachedKey {
+    key: WebhookVerificationKey,
+    fetched_at: DateTime<Utc>,
+}
+
+/// Verifies the `Plaid-Verification` JWT of incoming webhooks: signed with one of Plaid's ES256 keys,
+/// issued within the last five minutes and over the SHA-256 of the body as received
+pub struct PlaidWebhookVerifier {
+    plaid: Arc<PlaidClient>,
+    /// Keys by key ID
+    keys: RwLock<HashMap<String, CachedKey>>,
+}
+
+impl PlaidWebhookVerifier {
+    pub fn new(plaid: Arc<PlaidClient>) -> Self {
+        Self {
+            plaid,
+            keys: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Check `token` from the `Plaid-Verification` header against the raw webhook body
+    #[instrument(skip_all)]
+    pub async fn verify(&self, token: &str, body: &[u8]) -> Result<()> {
+        let header = decode_header(token).context("Malformed Plaid-Verification token")?;
+        if header.alg != Algorithm::ES256 {
+            bail!("Plaid-Verification token is signed with {:?}, not ES256", header.alg);
+        }
+        let key_id = header.kid.context("Plaid-Verification token names no key")?;
+        let key = self.key(&key_id).await?;
+        verify_token(token, &key, body, Utc::now().timestamp())
+    }
+
+    async fn key(&self, key_id: &str) -> Result<WebhookVerificationKey> {
+        if let Some(cached) = self.keys.read().await.get(key_id) {
+            if (Utc::now() - cached.fetched_at).num_seconds() < KEY_CACHE_TTL_SECONDS {
+                return Ok(cached.key.clone());
+            }
+        }
+
+        let key = self.plaid.webhook_verification_key(key_id).await?;
+        debug!(key_id, "Fetched Plaid webhook verification key");
+        self.keys.write().await.insert(
+            key_id.to_string(),
+            CachedKey {
+                key: key.clone(),
+                fetched_at: Utc::now(),
+            },
+        );
+        Ok(key)
+    }
+}
+
+fn verify_token(token: &str, key: &WebhookVerificationKey, body: &[u8], now: i64) -> Result<()> {
+    if key.expired_at.is_some_and(|expired_at| expired_at <= now) {
+        bail!("Plaid webhook key {} has expired", key.kid);
+    }
+    let decoding_key = DecodingKey::from_ec_components(&key.x, &key.y).context("Invalid Plaid webhook key")?;
+
+    let mut validation = Validation::new(Algorithm::ES256);
+    // Plaid's tokens carry no expiry; their age is checked against `iat` instead
+    validation.validate_exp = false;
+    validation.required_spec_claims = HashSet::new();
+    let claims = decode::<WebhookClaims>(token, &decoding_key, &validation)
+        .context("Plaid-Verification signature is invalid")?
+        .claims;
+
+    if now - claims.iat > MAX_WEBHOOK_AGE_SECONDS {
+        bail!("Plaid-Verification token was issued {} seconds ago", now - claims.iat);
+    }
+    if format!("{:x}", Sha256::digest(body)) != claims.request_body_sha256 {
+        bail!("Webhook body does not match its Plaid-Verification hash");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::{engine::general_purpose::STANDARD, Engine as _};
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::json;
+
+    // Test-only P-256 key pair; the private key is PKCS#8 DER
+    const PRIVATE_KEY: &str =
+        "MIGHAgEAMBMGByqGSM49AgEGCCqGSM49AwEHBG0wawIBAQQghJo784MHZIc5Y6Q2xVpV+ajbNcmxQ7q3AsTzLatakmihRANCAAQB\
+         Wzdz+XMP3so5fIrpPrGKxGitP5z/SplPRKW29wVtGFq1dX6YBye8wDyW29R8SG/iFxpVSzuxMYPrF4XrY+4r";
+    const BODY: &[u8] =
+        br#"{"webhook_type":"TRANSFER","webhook_code":"TRANSFER_EVENTS_UPDATE","environment":"sandbox"}"#;
+    const NOW: i64 = 1_760_000_000;
+
+    fn key() -> WebhookVerificationKey {
+        WebhookVerificationKey {
+            kid: "key-1".to_string(),
+            alg: "ES256".to_string(),
+            x: "AVs3c_lzD97KOXyK6T6xisRorT-c_0qZT0SltvcFbRg".to_string(),
+            y: "WrV1fpgHJ7zAPJbb1HxIb-IXGlVLO7Exg-sXhetj7is".to_string(),
+            expired_at: None,
+        }
+    }
+
+    fn sign(body: &[u8], iat: i64) -> String {
+        let header = Header {
+            kid: Some("key-1".to_string()),
+            ..Header::new(Algorithm::ES256)
+        };
+        let claims = json!({
+            "iat": iat,
+            "request_body_sha256": format!("{:x}", Sha256::digest(body)),
+        });
+        let der = STANDARD.decode(PRIVATE_KEY).unwrap();
+        encode(&header, &claims, &EncodingKey::from_ec_der(&der)).unwrap()
+    }
+
+    #[test]
+    fn test_verify_token() {
+        let token = sign(BODY, NOW - 10);
+        assert!(verify_token(&token, &key(), BODY, NOW).is_ok());
+
+        // Another body, even one differing only in whitespace, doesn't match the signed hash
+        let tampered =
+            br#"{"webhook_type":"TRANSFER", "webhook_code":"TRANSFER_EVENTS_UPDATE","environment":"sandbox"}"#;
+        assert!(verify_token(&token, &key(), tampered, NOW).is_err());
+
+        // Nor does a token Plaid signed more than five minutes ago
+        let stale = sign(BODY, NOW - MAX_WEBHOOK_AGE_SECONDS - 1);
+        assert!(verify_token(&stale, &key(), BODY, NOW).is_err());
+
+        // Nor one signed with an expired key
+        let expired = WebhookVerificationKey {
+            expired_at: Some(NOW - 60),
+            ..key()
+        };
+        assert!(verify_token(&token, &expired, BODY, NOW).is_err());
+
+        // Nor a token whose signature was altered
+        let (signed, signature) = token.rsplit_once('.').unwrap();
+        let replacement = if signature.starts_with('A') { "B" } else { "A" };
+        let forged = format!("{}.{}{}", signed, replacement, &signature[1..]);
+        assert!(verify_token(&forged, &key(), BODY, NOW).is_err());
+    }
+}
@@ -0,0 +1,296 @@
+// Postmark email transport, for deployments outside AWS
+use crate::adapter::email_transport::{EmailRequest, EmailResponse, EmailTransport};
+use crate::settings::SettingValues;
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::{debug, info, instrument};
+
+/// Most recipients Postmark accepts across To, Cc and Bcc
+const MAX_RECIPIENTS: usize = 50;
+
+/// Configuration for the Postmark client
+#[derive(Debug, Clone)]
+pub struct PostmarkConfig {
+    /// Server API token
+    pub server_token: String,
+    pub api_url: String,
+    /// Message stream emails are sent on, e.g. "outbound" for transactional email
+    pub message_stream: String,
+    /// Default sender email address; must be a confirmed sender signature or domain
+    pub default_sender: String,
+    /// Default sender name (optional)
+    pub default_sender_name: Option<String>,
+    /// Reply-to email address (optional)
+    pub reply_to: Option<String>,
+    /// Request timeout in seconds
+    pub timeout_seconds: u64,
+}
+
+impl Default for PostmarkConfig {
+    fn default() -> Self {
+        Self {
+            server_token: String::new(),
+            api_url: "https://api.postmarkapp.com".to_string(),
+            message_stream: "outbound".to_string(),
+            default_sender: String::new(),
+            default_sender_name: None,
+            reply_to: None,
+            timeout_seconds: 10,
+        }
+    }
+}
+
+impl PostmarkConfig {
+    /// Read from settings; `None` when no server token or sender is set
+    /// - POSTMARK_SERVER_TOKEN: Server API token
+    /// - POSTMARK_API_URL: API base URL (default: https://api.postmarkapp.com)
+    /// - POSTMARK_MESSAGE_STREAM: Message stream (default: outbound)
+    /// - POSTMARK_DEFAULT_SENDER: Default sender email (default: AWS_SES_DEFAULT_SENDER)
+    /// - POSTMARK_DEFAULT_SENDER_NAME: Default sender name (default: AWS_SES_DEFAULT_SENDER_NAME)
+    /// - POSTMARK_REPLY_TO: Default reply-to address (default: AWS_SES_REPLY_TO)
+    pub fn from_values(values: &SettingValues) -> Option<Self> {
+        let or_ses = |key: &str, fallback: &str| values.optional(key).or_else(|| values.optional(fallback));
+        let defaults = Self::default();
+        Some(Self {
+            server_token: values.optional("postmark-server-token")?,
+            api_url: values.or("postmark-api-url", &defaults.api_url),
+            message_stream: values.or("postmark-message-stream", &defaults.message_stream),
+            default_sender: or_ses("postmark-default-sender", "aws-ses-default-sender")?,
+            default_sender_name: or_ses("postmark-default-sender-name", "aws-ses-default-sender-name"),
+            reply_to: or_ses("postmark-reply-to", "aws-ses-reply-to"),
+            timeout_seconds: defaults.timeout_seconds,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct PostmarkHeader {
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(rename = "Value")]
+    value: String,
+}
+
+/// Body of `POST /email`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct PostmarkEmail {
+    from: String,
+    to: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cc: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bcc: Option<String>,
+    subject: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    html_body: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text_body: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reply_to: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tag: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    headers: Vec<PostmarkHeader>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    metadata: HashMap<String, String>,
+    message_stream: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PostmarkResponse {
+    #[serde(rename = "ErrorCode", default)]
+    error_code: i64,
+    #[serde(rename = "Message", default)]
+    message: String,
+    #[serde(rename = "MessageID")]
+    message_id: Option<String>,
+}
+
+/// Sends email through the Postmark API
+pub struct PostmarkClient {
+    client: Client,
+    config: PostmarkConfig,
+}
+
+impl PostmarkClient {
+    #[instrument(skip(config), fields(message_stream = %config.message_stream))]
+    pub fn new(config: PostmarkConfig) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(config.timeout_seconds))
+            .build()
+            .context("Failed to create HTTP client")?;
+
+        info!(
+            message_stream = %config.message_stream,
+            default_sender = %config.default_sender,
+            "Initialized Postmark client"
+        );
+        Ok(Self { client, config })
+    }
+
+    fn sender(&self, request: &EmailRequest) -> String {
+        let email = request.sender.as_deref().unwrap_or(&self.config.default_sender);
+        match request
+            .sender_name
+            .as_ref()
+            .or(self.config.default_sender_name.as_ref())
+        {
+            Some(name) => format!("{} <{}>", name, email),
+            None => email.to_string(),
+        }
+    }
+
+    /// The rendered request as a Postmark email. Message tags become metadata, with the email type
+    /// also used as the Postmark tag.
+    fn build_email(&self, request: &EmailRequest) -> Result<PostmarkEmail> {
+        if request.to.is_empty() {
+            bail!("At least one recipient is required");
+        }
+        if let Some(template) = &request.ses_template {
+            bail!("SES template '{}' can't be sent through Postmark", template);
+        }
+        if request.raw_message.is_some() {
+            bail!("Raw MIME messages can't be sent through Postmark");
+        }
+        if request.text_body.is_none() && request.html_body.is_none() {
+            bail!("Either text_body or html_body must be provided");
+        }
+        let recipients =
+            request.to.len() + request.cc.as_ref().map_or(0, Vec::len) + request.bcc.as_ref().map_or(0, Vec::len);
+        if recipients > MAX_RECIPIENTS {
+            bail!(
+                "Postmark accepts at most {} recipients, got {}",
+                MAX_RECIPIENTS,
+                recipients
+            );
+        }
+
+        let join = |addresses: &Option<Vec<String>>| addresses.as_ref().filter(|a| !a.is_empty()).map(|a| a.join(","));
+        Ok(PostmarkEmail {
+            from: self.sender(request),
+            to: request.to.join(","),
+            cc: join(&request.cc),
+            bcc: join(&request.bcc),
+            subject: request.subject.clone(),
+            html_body: request.html_body.clone(),
+            text_body: request.text_body.clone(),
+            reply_to: request.reply_to.clone().or_else(|| self.config.reply_to.clone()),
+            tag: request.tags.get("email_type").cloned(),
+            headers: request
+                .headers
+                .iter()
+                .map(|(name, value)| PostmarkHeader {
+                    name: name.clone(),
+                    value: value.clone(),
+                })
+                .collect(),
+            metadata: request.tags.clone(),
+            message_stream: self.config.message_stream.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl EmailTransport for PostmarkClient {
+    fn name(&self) -> &'static str {
+        "postmark"
+    }
+
+    #[instrument(skip(self, request), fields(to_count = request.to.len(), subject = %request.subject))]
+    async fn send_email(&self, request: EmailRequest) -> Result<EmailResponse> {
+        let start_time = std::time::Instant::now();
+        let request = request.rendered();
+        let email = self.build_email(&request)?;
+
+        debug!(to_addresses = ?request.to, "Sending email via Postmark");
+        let url = format!("{}/email", self.config.api_url.trim_end_matches('/'));
+        let response = self
+            .client
+            .post(&url)
+            .header("X-Postmark-Server-Token", &self.config.server_token)
+            .header("Accept", "application/json")
+            .json(&email)
+            .send()
+            .await
+            .context("Failed to send email via Postmark")?;
+
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        let parsed: Option<PostmarkResponse> = serde_json::from_str(&body).ok();
+        let message_id = match parsed {
+            Some(PostmarkResponse {
+                error_code: 0,
+                message_id: Some(message_id),
+                ..
+            }) if status.is_success() => message_id,
+            Some(PostmarkResponse {
+                error_code, message, ..
+            }) => {
+                bail!("Postmark error {} ({}): {}", error_code, status, message)
+            }
+            None => bail!("Postmark error ({}): {}", status, body),
+        };
+
+        let processing_time = start_time.elapsed().as_millis() as u64;
+        info!(
+            message_id = %message_id,
+            processing_time_ms = processing_time,
+            to_count = request.to.len(),
+            subject = %request.subject,
+            "Email sent via Postmark"
+        );
+        Ok(EmailResponse {
+            message_id,
+            accepted: true,
+            processing_time_ms: processing_time,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client() -> PostmarkClient {
+        PostmarkClient::new(PostmarkConfig {
+            server_token: "token".to_string(),
+            default_sender: "noreply@example.com".to_string(),
+            default_sender_name: Some("Origin".to_string()),
+            ..PostmarkConfig::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_build_email() {
+        let client = client();
+        let request = EmailRequest::new(vec!["ana@example.com"], "Your code")
+            .with_text_body("Code 482913")
+            .with_html_body("<p>Code 482913</p>")
+            .with_bcc(vec!["audit@example.com".to_string()])
+            .with_tag("email_type", "otp_login")
+            .with_header("List-Unsubscribe", "<https://example.com/u>");
+
+        let email = client.build_email(&request).unwrap();
+        let json = serde_json::to_value(&email).unwrap();
+        assert_eq!(json["From"], "Origin <noreply@example.com>");
+        assert_eq!(json["To"], "ana@example.com");
+        assert_eq!(json["Bcc"], "audit@example.com");
+        assert!(json.get("Cc").is_none());
+        assert_eq!(json["Tag"], "otp_login");
+        assert_eq!(json["Metadata"]["email_type"], "otp_login");
+        assert_eq!(json["Headers"][0]["Name"], "List-Unsubscribe");
+        assert_eq!(json["MessageStream"], "outbound");
+
+        let request =
+            EmailRequest::new(vec!["ana@example.com"], "Raw").with_raw_message(b"Subject: Hi\r\n\r\nBody".to_vec());
+        assert!(client.build_email(&request).is_err());
+        let request = EmailRequest::new(vec!["ana@example.com"], "No body");
+        assert!(client.build_email(&request).is_err());
+    }
+}
@@ -0,0 +1,206 @@
+use crate::settings::SettingValues;
+use anyhow::{Context, Result};
+use aws_config::BehaviorVersion;
+use aws_sdk_s3::presigning::{PresignedRequest, PresigningConfig};
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::Client;
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+use tracing::{debug, info, instrument};
+
+/// Configuration for the Amazon S3 client
+#[derive(Debug, Clone)]
+pub struct S3Config {
+    /// AWS region of the bucket (e.g., "us-east-1")
+    pub region: String,
+    /// Bucket user files are stored in
+    pub bucket: String,
+    /// How long presigned upload and download URLs stay valid
+    pub presign_ttl: Duration,
+}
+
+impl Default for S3Config {
+    fn default() -> Self {
+        Self {
+            region: "us-east-1".to_string(),
+            bucket: String::new(),
+            presign_ttl: Duration::from_secs(15 * 60),
+        }
+    }
+}
+
+impl S3Config {
+    /// Read from settings; `None` when no bucket is set
+    /// - AWS_S3_BUCKET: Bucket for user files
+    /// - AWS_S3_REGION: AWS region (default: us-east-1)
+    /// - AWS_S3_PRESIGN_TTL_SECS: Lifetime of presigned URLs in seconds (default: 900)
+    pub fn from_values(values: &mut SettingValues) -> Option<Self> {
+        let bucket = values.optional("aws-s3-bucket")?;
+        let defaults = Self::default();
+        Some(Self {
+            region: values.or("aws-s3-region", &defaults.region),
+            bucket,
+            presign_ttl: values
+                .parsed_optional("aws-s3-presign-ttl-secs")
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.presign_ttl),
+        })
+    }
+}
+
+/// URL a client uses to transfer an object directly to or from S3
+#[derive(Debug, Clone)]
+pub struct PresignedUrl {
+    pub url: String,
+    pub method: String,
+    /// Headers the client must send with the request
+    pub headers: Vec<(String, String)>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Size and type of a stored object
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectMetadata {
+    pub content_length: i64,
+    pub content_type: Option<String>,
+}
+
+/// Amazon S3 client for user file storage
+#[derive(Debug, Clone)]
+pub struct S3Client {
+    client: Client,
+    config: S3Config,
+}
+
+impl S3Client {
+    /// Create a new S3 client with configuration
+    #[instrument(skip(config), fields(region = %config.region, bucket = %config.bucket))]
+    pub async fn new(config: S3Config) -> Result<Self> {
+        let aws_config = aws_config::defaults(BehaviorVersion::latest())
+            .region(aws_config::Region::new(config.region.clone()))
+            .load()
+            .await;
+
+        let client = Client::new(&aws_config);
+
+        info!(region = %config.region, bucket = %config.bucket, "Initialized S3 client");
+
+        Ok(Self { client, config })
+    }
+
+    pub fn bucket(&self) -> &str {
+        &self.config.bucket
+    }
+
+    fn presigning_config(&self) -> Result<PresigningConfig> {
+        PresigningConfig::expires_in(self.config.presign_ttl).context("Invalid presigned URL lifetime")
+    }
+
+    fn presigned_url(&self, request: PresignedRequest) -> PresignedUrl {
+        PresignedUrl {
+            url: request.uri().to_string(),
+            method: request.method().to_string(),
+            headers: request
+                .headers()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            expires_at: Utc::now()
+                + chrono::Duration::from_std(self.config.presign_ttl).unwrap_or_else(|_| chrono::Duration::zero()),
+        }
+    }
+
+    /// Presigned PUT for uploading an object of exactly `content_length` bytes of `content_type`
+    #[instrument(skip(self))]
+    pub async fn presigned_upload_url(&self, key: &str, content_type: &str, content_length: i64) -> Result<PresignedUrl> {
+        let request = self
+            .client
+            .put_object()
+            .bucket(&self.config.bucket)
+            .key(key)
+            .content_type(content_type)
+            .content_length(content_length)
+            .presigned(self.presigning_config()?)
+            .await
+            .context("Failed to presign S3 upload")?;
+
+        debug!(key = %key, "Presigned S3 upload");
+        Ok(self.presigned_url(request))
+    }
+
+    /// Presigned GET for downloading an object; `file_name` is suggested to the browser when set
+    #[instrument(skip(self))]
+    pub async fn presigned_download_url(&self, key: &str, file_name: Option<&str>) -> Result<PresignedUrl> {
+        let mut request = self.client.get_object().bucket(&self.config.bucket).key(key);
+        if let Some(file_name) = file_name {
+            request = request.response_content_disposition(format!("attachment; filename=\"{}\"", file_name));
+        }
+        let request = request
+            .presigned(self.presigning_config()?)
+            .await
+            .context("Failed to presign S3 download")?;
+
+        debug!(key = %key, "Presigned S3 download");
+        Ok(self.presigned_url(request))
+    }
+
+    /// Size and type of an object, or `None` when it does not exist
+    #[instrument(skip(self))]
+    pub async fn head_object(&self, key: &str) -> Result<Option<ObjectMetadata>> {
+        match self.client.head_object().bucket(&self.config.bucket).key(key).send().await {
+            Ok(output) => Ok(Some(ObjectMetadata {
+                content_length: output.content_length().unwrap_or_default(),
+                content_type: output.content_type().map(str::to_string),
+            })),
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_not_found()) => Ok(None),
+            Err(e) => Err(e).context("Failed to read S3 object metadata"),
+        }
+    }
+
+    /// Store an object
+    #[instrument(skip(self, body), fields(size = body.len()))]
+    pub async fn put_object(&self, key: &str, body: Vec<u8>, content_type: &str) -> Result<()> {
+        self.client
+            .put_object()
+            .bucket(&self.config.bucket)
+            .key(key)
+            .content_type(content_type)
+            .body(ByteStream::from(body))
+            .send()
+            .await
+            .context("Failed to store S3 object")?;
+
+        debug!(key = %key, "Stored S3 object");
+        Ok(())
+    }
+
+    /// Read a whole object into memory
+    #[instrument(skip(self))]
+    pub async fn get_object(&self, key: &str) -> Result<Vec<u8>> {
+        let output = self
+            .client
+            .get_object()
+            .bucket(&self.config.bucket)
+            .key(key)
+            .send()
+            .await
+            .context("Failed to read S3 object")?;
+        let body = output.body.collect().await.context("Failed to read S3 object body")?;
+
+        Ok(body.into_bytes().to_vec())
+    }
+
+    /// Delete an object; deleting a missing object succeeds
+    #[instrument(skip(self))]
+    pub async fn delete_object(&self, key: &str) -> Result<()> {
+        self.client
+            .delete_object()
+            .bucket(&self.config.bucket)
+            .key(key)
+            .send()
+            .await
+            .context("Failed to delete S3 object")?;
+
+        debug!(key = %key, "Deleted S3 object");
+        Ok(())
+    }
+}
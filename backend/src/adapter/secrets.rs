@@ -0,0 +1,43 @@
+// Secret stores behind a provider-neutral trait, so settings can be read from AWS or from Vault
+use crate::adapter::secrets_manager::{secret_id, SecretsManager};
+use anyhow::Result;
+use async_trait::async_trait;
+
+/// Name of the AWS Secrets Manager provider
+pub const SECRETS_MANAGER_PROVIDER: &str = "secrets-manager";
+
+/// Read access to a store of versioned secrets.
+///
+/// Secrets are addressed by a `namespace` such as `/origin/prod` and a `key` such as `jwt-secret`;
+/// each provider maps the pair onto its own naming.
+#[async_trait]
+pub trait SecretsProvider: Send + Sync {
+    /// Name used to select the provider in `SECRET_SOURCE` and `SECRET_SOURCES`
+    fn name(&self) -> &'static str;
+
+    /// The secret's current value and version, or `None` when it doesn't exist
+    async fn get_versioned_secret(&self, namespace: &str, key: &str) -> Result<Option<(String, String)>>;
+
+    /// The secret's current version; a new one means the secret was changed or rotated
+    async fn current_version(&self, namespace: &str, key: &str) -> Result<Option<String>> {
+        Ok(self
+            .get_versioned_secret(namespace, key)
+            .await?
+            .map(|(_, version)| version))
+    }
+}
+
+#[async_trait]
+impl SecretsProvider for SecretsManager {
+    fn name(&self) -> &'static str {
+        SECRETS_MANAGER_PROVIDER
+    }
+
+    async fn get_versioned_secret(&self, namespace: &str, key: &str) -> Result<Option<(String, String)>> {
+        SecretsManager::get_versioned_secret(self, &secret_id(key, Some(namespace))).await
+    }
+
+    async fn current_version(&self, namespace: &str, key: &str) -> Result<Option<String>> {
+        SecretsManager::current_version(self, &secret_id(key, Some(namespace))).await
+    }
+}
@@ -0,0 +1,100 @@
+use anyhow::{Context, Result};
+use aws_config::{BehaviorVersion, Region};
+use aws_sdk_secretsmanager::Client;
+use tracing::{error, info, instrument};
+
+/// Staging label of the version a secret currently resolves to
+const CURRENT_STAGE: &str = "AWSCURRENT";
+
+/// AWS Secrets Manager client, read like `ParameterStore`. Secrets are named `<namespace>/<name>`,
+/// e.g. `origin/prod/jwt-secret`.
+#[derive(Debug, Clone)]
+pub struct SecretsManager {
+    client: Client,
+}
+
+impl SecretsManager {
+    #[instrument(skip_all)]
+    pub async fn new() -> Self {
+        let config = aws_config::defaults(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .load()
+            .await;
+
+        let client = Client::new(&config);
+        info!("Initialized Secrets Manager client");
+
+        Self { client }
+    }
+
+    /// A secret's string value; `Some(None)` when it has no string value, `None` when it can't be read
+    #[instrument(skip(self))]
+    pub async fn get_secret(&self, name: String, namespace: Option<String>) -> Option<Option<String>> {
+        let secret_id = secret_id(&name, namespace.as_deref());
+        match self.get_versioned_secret(&secret_id).await {
+            Ok(secret) => Some(secret.map(|(value, _)| value)),
+            Err(e) => {
+                error!(secret = %secret_id, error = ?e, "Failed to get secret");
+                None
+            }
+        }
+    }
+
+    /// A secret's current string value and version ID, or `None` when it doesn't exist
+    #[instrument(skip(self))]
+    pub async fn get_versioned_secret(&self, secret_id: &str) -> Result<Option<(String, String)>> {
+        match self.client.get_secret_value().secret_id(secret_id).send().await {
+            Ok(response) => Ok(response
+                .secret_string()
+                .and_then(|value| Some((value.to_string(), response.version_id()?.to_string())))),
+            Err(e)
+                if e.as_service_error()
+                    .is_some_and(|e| e.is_resource_not_found_exception()) =>
+            {
+                Ok(None)
+            }
+            Err(e) => Err(e).with_context(|| format!("Failed to get secret {}", secret_id)),
+        }
+    }
+
+    /// Version ID the secret currently resolves to, without reading its value. Rotation moves the
+    /// `AWSCURRENT` label to a new version, so a changed ID means the secret was rotated.
+    #[instrument(skip(self))]
+    pub async fn current_version(&self, secret_id: &str) -> Result<Option<String>> {
+        match self.client.describe_secret().secret_id(secret_id).send().await {
+            Ok(response) => Ok(response.version_ids_to_stages().and_then(|versions| {
+                versions
+                    .iter()
+                    .find(|(_, stages)| stages.iter().any(|stage| stage == CURRENT_STAGE))
+                    .map(|(version, _)| version.clone())
+            })),
+            Err(e)
+                if e.as_service_error()
+                    .is_some_and(|e| e.is_resource_not_found_exception()) =>
+            {
+                Ok(None)
+            }
+            Err(e) => Err(e).with_context(|| format!("Failed to describe secret {}", secret_id)),
+        }
+    }
+}
+
+/// Full secret name; Secrets Manager names don't start with a slash
+pub fn secret_id(name: &str, namespace: Option<&str>) -> String {
+    match namespace.map(|ns| ns.trim_matches('/')) {
+        Some(ns) if !ns.is_empty() => format!("{}/{}", ns, name),
+        _ => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_id() {
+        assert_eq!(secret_id("jwt-secret", Some("origin/prod")), "origin/prod/jwt-secret");
+        assert_eq!(secret_id("jwt-secret", Some("/origin/prod/")), "origin/prod/jwt-secret");
+        assert_eq!(secret_id("shared/api-key", None), "shared/api-key");
+    }
+}
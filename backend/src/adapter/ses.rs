@@ -1,10 +1,21 @@
 use aws_config::BehaviorVersion;
-use aws_sdk_ses::Client;
-use aws_sdk_ses::types::{Body, Content, Destination, Message};
+use aws_sdk_sesv2::Client;
+use aws_sdk_sesv2::operation::get_account::GetAccountOutput;
+use aws_sdk_sesv2::primitives::Blob;
+use aws_sdk_sesv2::types::{
+    Body, Content, Destination, DkimAttributes, EmailContent, ListManagementOptions, Message, MessageHeader,
+    MessageTag, RawMessage, Template, VerificationStatus,
+};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use anyhow::{Result, Context};
-use tracing::{info, debug, instrument};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use anyhow::{bail, Context, Result};
+use tracing::{info, debug, warn, instrument};
+use crate::adapter::email_templates::EmailTemplateContent;
+use crate::adapter::email_transport::EmailTransport;
+use crate::settings::SettingValues;
 
 /// Configuration for Amazon SES client
 #[derive(Debug, Clone)]
@@ -19,6 +30,9 @@ pub struct SESConfig {
     pub reply_to: Option<String>,
     /// Configuration set name (optional, for tracking)
     pub configuration_set: Option<String>,
+    /// Upper bound on emails sent per second (optional); the account's SES maximum send rate
+    /// applies when it is lower
+    pub max_send_rate: Option<f64>,
 }
 
 impl Default for SESConfig {
@@ -29,10 +43,34 @@ impl Default for SESConfig {
             default_sender_name: None,
             reply_to: None,
             configuration_set: None,
+            max_send_rate: None,
         }
     }
 }
 
+impl SESConfig {
+    /// Read from settings; `None` when no default sender is set
+    /// - AWS_SES_REGION: AWS region (default: us-east-1)
+    /// - AWS_SES_DEFAULT_SENDER: Default sender email
+    /// - AWS_SES_DEFAULT_SENDER_NAME: Default sender name (optional)
+    /// - AWS_SES_REPLY_TO: Default reply-to address (optional)
+    /// - AWS_SES_CONFIGURATION_SET: Configuration set name (optional)
+    /// - AWS_SES_MAX_SEND_RATE: Emails per second to stay under (optional)
+    pub fn from_values(values: &mut SettingValues) -> Option<Self> {
+        let default_sender = values.optional("aws-ses-default-sender")?;
+        Some(Self {
+            region: values.or("aws-ses-region", "us-east-1"),
+            default_sender,
+            default_sender_name: values.optional("aws-ses-default-sender-name"),
+            reply_to: values.optional("aws-ses-reply-to"),
+            configuration_set: values.optional("aws-ses-configuration-set"),
+            max_send_rate: values
+                .parsed_optional("aws-ses-max-send-rate")
+                .filter(|rate: &f64| *rate > 0.0),
+        })
+    }
+}
+
 /// Email template data for dynamic content replacement
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TemplateData {
@@ -54,6 +92,10 @@ impl TemplateData {
         self.data.get(key)
     }
 
+    pub fn values(&self) -> &HashMap<String, String> {
+        &self.data
+    }
+
     /// Replace template variables in text with actual values
     /// Variables in format {{variable_name}} will be replaced
     pub fn render_template(&self, template: &str) -> String {
@@ -64,6 +106,38 @@ impl TemplateData {
         }
         result
     }
+
+    /// Values as the JSON object SES fills its stored templates from
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(&self.data).context("Failed to serialize template data")
+    }
+}
+
+/// Longest tag name or value SES accepts
+const MAX_TAG_CHARS: usize = 256;
+
+/// SES message tag; characters other than ASCII letters, digits, `_` and `-` become `_`
+fn message_tag(name: &str, value: &str) -> Result<MessageTag> {
+    let clean = |s: &str| -> String {
+        s.chars()
+            .take(MAX_TAG_CHARS)
+            .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+            .collect()
+    };
+    MessageTag::builder()
+        .name(clean(name))
+        .value(clean(value))
+        .build()
+        .context("Failed to build message tag")
+}
+
+/// Contact list a message is sent to, so SES adds unsubscribe headers and skips unsubscribed contacts
+#[derive(Debug, Clone)]
+pub struct ListManagement {
+    /// Contact list name
+    pub contact_list: String,
+    /// Topic within the list (optional)
+    pub topic: Option<String>,
 }
 
 /// Email priority levels
@@ -109,8 +183,20 @@ pub struct EmailRequest {
     pub priority: EmailPriority,
     /// Template data for dynamic content
     pub template_data: Option<TemplateData>,
-    /// Email tags for tracking (key-value pairs)
+    /// Email tags for tracking (key-value pairs), sent as SES message tags
     pub tags: HashMap<String, String>,
+    /// Extra message headers, such as List-Unsubscribe; not sent with raw messages or SES templates
+    pub headers: Vec<(String, String)>,
+    /// Complete MIME message, e.g. with attachments; sent as is instead of the subject and bodies
+    pub raw_message: Option<Vec<u8>>,
+    /// Template stored in SES, rendered by SES from the template data instead of the subject and bodies
+    pub ses_template: Option<String>,
+    /// Contact list for bulk email (optional)
+    pub list_management: Option<ListManagement>,
+    /// Configuration set (overrides default if provided)
+    pub configuration_set: Option<String>,
+    /// Names the logical email, e.g. "otp:{email}:{otp_id}"; the mailer sends a key at most once
+    pub idempotency_key: Option<String>,
 }
 
 impl EmailRequest {
@@ -128,9 +214,24 @@ impl EmailRequest {
             priority: EmailPriority::Normal,
             template_data: None,
             tags: HashMap::new(),
+            headers: Vec::new(),
+            raw_message: None,
+            ses_template: None,
+            list_management: None,
+            configuration_set: None,
+            idempotency_key: None,
         }
     }
 
+    /// Request with a template's subject and bodies; placeholders are filled from the template data
+    pub fn from_template<T: Into<String>>(to: Vec<T>, template: EmailTemplateContent) -> Self {
+        let version = template.version.map_or_else(|| "default".to_string(), |v| v.to_string());
+        Self::new(to, template.subject)
+            .with_html_body(template.html)
+            .with_text_body(template.text)
+            .with_tag("template_version", version)
+    }
+
     pub fn with_text_body<T: Into<String>>(mut self, body: T) -> Self {
         self.text_body = Some(body.into());
         self
@@ -175,6 +276,53 @@ impl EmailRequest {
         self.tags.insert(key.into(), value.into());
         self
     }
+
+    pub fn with_header<K: Into<String>, V: Into<String>>(mut self, name: K, value: V) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn with_raw_message(mut self, message: Vec<u8>) -> Self {
+        self.raw_message = Some(message);
+        self
+    }
+
+    pub fn with_ses_template<T: Into<String>>(mut self, name: T) -> Self {
+        self.ses_template = Some(name.into());
+        self
+    }
+
+    pub fn with_list_management<L: Into<String>>(mut self, contact_list: L, topic: Option<String>) -> Self {
+        self.list_management = Some(ListManagement {
+            contact_list: contact_list.into(),
+            topic,
+        });
+        self
+    }
+
+    pub fn with_idempotency_key<T: Into<String>>(mut self, key: T) -> Self {
+        self.idempotency_key = Some(key.into());
+        self
+    }
+
+    pub fn with_configuration_set<T: Into<String>>(mut self, name: T) -> Self {
+        self.configuration_set = Some(name.into());
+        self
+    }
+
+    /// Fill the template data into the subject and bodies; SES fills in its stored templates itself
+    pub fn rendered(mut self) -> Self {
+        if let (Some(template_data), None) = (&self.template_data, &self.ses_template) {
+            self.subject = template_data.render_template(&self.subject);
+            if let Some(ref body) = self.text_body {
+                self.text_body = Some(template_data.render_template(body));
+            }
+            if let Some(ref body) = self.html_body {
+                self.html_body = Some(template_data.render_template(body));
+            }
+        }
+        self
+    }
 }
 
 /// Email sending response
@@ -188,10 +336,223 @@ pub struct EmailResponse {
     pub processing_time_ms: u64,
 }
 
-/// Amazon SES client for sending emails
+/// Send rate used until the account quota is known; the SES sandbox rate
+const DEFAULT_MAX_SEND_RATE: f64 = 1.0;
+/// Fraction of the account's maximum send rate the limiter allows, leaving room for other senders
+/// sharing the account
+const SEND_RATE_HEADROOM: f64 = 0.9;
+/// Share of the daily quota used at startup above which a warning is logged
+const DAILY_QUOTA_WARNING: f64 = 0.8;
+
+/// Sending quota of the SES account
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SendQuota {
+    /// Emails the account may send per second
+    pub max_send_rate: f64,
+    /// Emails the account may send in 24 hours
+    pub max_24_hour_send: f64,
+    /// Emails sent in the last 24 hours
+    pub sent_last_24_hours: f64,
+}
+
+impl SendQuota {
+    /// Share of the daily quota used, from 0.0 to 1.0 and beyond
+    pub fn daily_usage(&self) -> f64 {
+        if self.max_24_hour_send <= 0.0 {
+            return 0.0;
+        }
+        self.sent_last_24_hours / self.max_24_hour_send
+    }
+}
+
+/// Counters of a send rate limiter, readable while it runs
+#[derive(Debug, Default)]
+struct SendRateMetrics {
+    sends: AtomicU64,
+    throttled_sends: AtomicU64,
+    throttled_ms: AtomicU64,
+}
+
+/// Point-in-time copy of a send rate limiter's counters
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SendRateSnapshot {
+    /// Emails per second currently allowed
+    pub rate: f64,
+    pub sends: u64,
+    /// Sends that had to wait for the bucket to refill
+    pub throttled_sends: u64,
+    /// Total time sends spent waiting
+    pub throttled_ms: u64,
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: f64, now: Instant) -> Self {
+        let rate = rate.max(0.1);
+        Self {
+            rate,
+            capacity: rate.max(1.0),
+            tokens: rate.max(1.0),
+            updated: now,
+        }
+    }
+
+    /// Take `count` tokens, going into debt when there aren't enough; returns how long the caller
+    /// waits for the debt to be paid back. Callers are served in the order they reserve.
+    fn reserve(&mut self, count: f64, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.updated = now;
+        self.tokens -= count;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+
+    fn set_rate(&mut self, rate: f64, now: Instant) {
+        self.reserve(0.0, now);
+        self.rate = rate.max(0.1);
+        self.capacity = self.rate.max(1.0);
+        self.tokens = self.tokens.min(self.capacity);
+    }
+}
+
+/// Token bucket spacing sends so bursts stay under the SES maximum send rate; a burst of up to one
+/// second's worth of sends goes out at once
+#[derive(Debug)]
+pub struct SendRateLimiter {
+    bucket: Mutex<TokenBucket>,
+    metrics: SendRateMetrics,
+}
+
+impl SendRateLimiter {
+    pub fn new(sends_per_second: f64) -> Self {
+        Self {
+            bucket: Mutex::new(TokenBucket::new(sends_per_second, Instant::now())),
+            metrics: SendRateMetrics::default(),
+        }
+    }
+
+    /// Wait until `recipients` more sends fit under the rate; SES counts every recipient as a send
+    pub async fn acquire(&self, recipients: usize) {
+        let wait = {
+            let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+            bucket.reserve(recipients.max(1) as f64, Instant::now())
+        };
+        self.metrics.sends.fetch_add(1, Ordering::Relaxed);
+        if !wait.is_zero() {
+            self.metrics.throttled_sends.fetch_add(1, Ordering::Relaxed);
+            self.metrics
+                .throttled_ms
+                .fetch_add(wait.as_millis() as u64, Ordering::Relaxed);
+            debug!(wait_ms = wait.as_millis() as u64, "Waiting for SES send rate");
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    pub fn set_rate(&self, sends_per_second: f64) {
+        let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+        bucket.set_rate(sends_per_second, Instant::now());
+    }
+
+    pub fn metrics(&self) -> SendRateSnapshot {
+        let rate = self.bucket.lock().unwrap_or_else(|e| e.into_inner()).rate;
+        SendRateSnapshot {
+            rate,
+            sends: self.metrics.sends.load(Ordering::Relaxed),
+            throttled_sends: self.metrics.throttled_sends.load(Ordering::Relaxed),
+            throttled_ms: self.metrics.throttled_ms.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Emails per second to allow: the configured limit, lowered to stay under the account's rate
+fn allowed_send_rate(configured: Option<f64>, quota: Option<&SendQuota>) -> f64 {
+    let account = quota
+        .map(|quota| quota.max_send_rate * SEND_RATE_HEADROOM)
+        .filter(|rate| *rate > 0.0);
+    match (configured.filter(|rate| *rate > 0.0), account) {
+        (Some(configured), Some(account)) => configured.min(account),
+        (Some(configured), None) => configured,
+        (None, Some(account)) => account,
+        (None, None) => DEFAULT_MAX_SEND_RATE,
+    }
+}
+
+/// Verification state of an SES identity, an email address or a domain
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdentityStatus {
+    pub identity: String,
+    /// Whether email can be sent from the identity
+    pub verified_for_sending: bool,
+    /// SES verification status, e.g. "PENDING", "SUCCESS" or "FAILED"
+    pub verification_status: Option<String>,
+    /// DKIM signing status, e.g. "PENDING", "SUCCESS" or "NOT_STARTED"
+    pub dkim_status: Option<String>,
+    /// Easy DKIM tokens; a domain is verified once their CNAME records are published
+    pub dkim_tokens: Vec<String>,
+}
+
+impl IdentityStatus {
+    fn new(
+        identity: &str,
+        verified_for_sending: bool,
+        verification_status: Option<&VerificationStatus>,
+        dkim: Option<&DkimAttributes>,
+    ) -> Self {
+        Self {
+            identity: identity.to_string(),
+            verified_for_sending,
+            verification_status: verification_status.map(|status| status.as_str().to_string()),
+            dkim_status: dkim
+                .and_then(|dkim| dkim.status())
+                .map(|status| status.as_str().to_string()),
+            dkim_tokens: dkim.map(|dkim| dkim.tokens().to_vec()).unwrap_or_default(),
+        }
+    }
+
+    /// Whether SES signs the identity's email with DKIM
+    pub fn dkim_verified(&self) -> bool {
+        self.dkim_status.as_deref() == Some("SUCCESS")
+    }
+
+    /// CNAME records, as (name, value), to publish in the domain's DNS for Easy DKIM
+    pub fn dkim_records(&self) -> Vec<(String, String)> {
+        let domain = sender_domain(&self.identity).unwrap_or(&self.identity);
+        self.dkim_tokens
+            .iter()
+            .map(|token| {
+                (
+                    format!("{}._domainkey.{}", token, domain),
+                    format!("{}.dkim.amazonses.com", token),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Domain of an email address
+fn sender_domain(address: &str) -> Option<&str> {
+    address
+        .rsplit_once('@')
+        .map(|(_, domain)| domain.trim_end_matches('>'))
+        .filter(|domain| !domain.is_empty())
+}
+
+/// Amazon SES (v2 API) client for sending emails
 pub struct SESClient {
     client: Client,
     config: SESConfig,
+    limiter: SendRateLimiter,
 }
 
 impl SESClient {
@@ -211,29 +572,79 @@ impl SESClient {
             "Initialized SES client"
         );
 
-        Ok(Self { client, config })
+        let ses = Self {
+            limiter: SendRateLimiter::new(allowed_send_rate(config.max_send_rate, None)),
+            client,
+            config,
+        };
+        ses.refresh_quota().await;
+        ses.check_default_sender().await;
+        Ok(ses)
     }
 
-    /// Create SES client from environment variables
-    /// Expected environment variables:
-    /// - AWS_SES_REGION: AWS region (default: us-east-1)
-    /// - AWS_SES_DEFAULT_SENDER: Default sender email
-    /// - AWS_SES_DEFAULT_SENDER_NAME: Default sender name (optional)
-    /// - AWS_SES_REPLY_TO: Default reply-to address (optional)
-    /// - AWS_SES_CONFIGURATION_SET: Configuration set name (optional)
-    #[instrument]
-    pub async fn from_env() -> Result<Self> {
-        let config = SESConfig {
-            region: std::env::var("AWS_SES_REGION")
-                .unwrap_or_else(|_| "us-east-1".to_string()),
-            default_sender: std::env::var("AWS_SES_DEFAULT_SENDER")
-                .context("AWS_SES_DEFAULT_SENDER environment variable is required")?,
-            default_sender_name: std::env::var("AWS_SES_DEFAULT_SENDER_NAME").ok(),
-            reply_to: std::env::var("AWS_SES_REPLY_TO").ok(),
-            configuration_set: std::env::var("AWS_SES_CONFIGURATION_SET").ok(),
+    /// Warn when the default sender can't be sent from in this region, as every email without its
+    /// own sender would be rejected
+    #[instrument(skip(self))]
+    pub async fn check_default_sender(&self) {
+        let sender = &self.config.default_sender;
+        match self.can_send_from(sender).await {
+            Ok(true) => debug!(default_sender = %sender, "SES default sender is verified"),
+            Ok(false) => warn!(
+                default_sender = %sender,
+                region = %self.config.region,
+                "SES default sender is not verified in this region; emails from it will be rejected"
+            ),
+            Err(e) => warn!(error = ?e, "Failed to check the SES default sender"),
+        }
+    }
+
+    /// Read the account's sending quota and adjust the send rate to it. When the quota can't be
+    /// read the configured rate, or the sandbox rate, is kept.
+    #[instrument(skip(self))]
+    pub async fn refresh_quota(&self) -> Option<SendQuota> {
+        let quota = match self.send_quota().await {
+            Ok(quota) => quota,
+            Err(e) => {
+                warn!(error = ?e, "Failed to read SES sending quota; keeping the configured send rate");
+                return None;
+            }
         };
+        let rate = allowed_send_rate(self.config.max_send_rate, Some(&quota));
+        self.limiter.set_rate(rate);
+
+        info!(
+            max_send_rate = quota.max_send_rate,
+            max_24_hour_send = quota.max_24_hour_send,
+            sent_last_24_hours = quota.sent_last_24_hours,
+            allowed_send_rate = rate,
+            "SES sending quota loaded"
+        );
+        if quota.daily_usage() >= DAILY_QUOTA_WARNING {
+            warn!(
+                sent_last_24_hours = quota.sent_last_24_hours,
+                max_24_hour_send = quota.max_24_hour_send,
+                "SES daily sending quota nearly used"
+            );
+        }
+        Some(quota)
+    }
+
+    /// Sending quota of the account, with what it sent in the last 24 hours
+    pub async fn send_quota(&self) -> Result<SendQuota> {
+        let account = self.get_account().await?;
+        let quota = account
+            .send_quota()
+            .context("SES account has no sending quota")?;
+        Ok(SendQuota {
+            max_send_rate: quota.max_send_rate(),
+            max_24_hour_send: quota.max24_hour_send(),
+            sent_last_24_hours: quota.sent_last24_hours(),
+        })
+    }
 
-        Self::new(config).await
+    /// Counters of the send rate limiter
+    pub fn send_rate_metrics(&self) -> SendRateSnapshot {
+        self.limiter.metrics()
     }
 
     /// Send an email using the SES client
@@ -244,28 +655,18 @@ impl SESClient {
         has_html = request.html_body.is_some(),
         has_text = request.text_body.is_some()
     ))]
-    pub async fn send_email(&self, mut request: EmailRequest) -> Result<EmailResponse> {
+    pub async fn send_email(&self, request: EmailRequest) -> Result<EmailResponse> {
         let start_time = std::time::Instant::now();
         
         // Apply template data if provided
-        if let Some(template_data) = &request.template_data {
-            request.subject = template_data.render_template(&request.subject);
-            if let Some(ref body) = request.text_body {
-                request.text_body = Some(template_data.render_template(body));
-            }
-            if let Some(ref body) = request.html_body {
-                request.html_body = Some(template_data.render_template(body));
-            }
-        }
+        let request = request.rendered();
 
         // Validate request
         if request.to.is_empty() {
             return Err(anyhow::anyhow!("At least one recipient is required"));
         }
 
-        if request.text_body.is_none() && request.html_body.is_none() {
-            return Err(anyhow::anyhow!("Either text_body or html_body must be provided"));
-        }
+        let content = Self::email_content(&request)?;
 
         // Build destination
         let mut destination_builder = Destination::builder();
@@ -287,43 +688,6 @@ impl SESClient {
         
         let destination = destination_builder.build();
 
-        // Build message body
-        let mut body_builder = Body::builder();
-        
-        if let Some(text) = &request.text_body {
-            body_builder = body_builder.text(
-                Content::builder()
-                    .data(text)
-                    .charset("UTF-8")
-                    .build()
-                    .context("Failed to build text content")?
-            );
-        }
-        
-        if let Some(html) = &request.html_body {
-            body_builder = body_builder.html(
-                Content::builder()
-                    .data(html)
-                    .charset("UTF-8")
-                    .build()
-                    .context("Failed to build HTML content")?
-            );
-        }
-
-        let body = body_builder.build();
-
-        // Build message
-        let message = Message::builder()
-            .subject(
-                Content::builder()
-                    .data(&request.subject)
-                    .charset("UTF-8")
-                    .build()
-                    .context("Failed to build subject content")?
-            )
-            .body(body)
-            .build();
-
         // Determine sender
         let sender = match (&request.sender, &request.sender_name) {
             (Some(email), Some(name)) => format!("{} <{}>", name, email),
@@ -338,9 +702,9 @@ impl SESClient {
         // Build send email request
         let mut send_request = self.client
             .send_email()
-            .source(&sender)
+            .from_email_address(&sender)
             .destination(destination)
-            .message(message);
+            .content(content);
 
         // Add reply-to if specified
         let reply_to_addr = request.reply_to
@@ -352,10 +716,30 @@ impl SESClient {
         }
 
         // Add configuration set if specified
-        if let Some(config_set) = &self.config.configuration_set {
+        let config_set = request.configuration_set
+            .as_ref()
+            .or(self.config.configuration_set.as_ref());
+
+        if let Some(config_set) = config_set {
             send_request = send_request.configuration_set_name(config_set);
         }
 
+        // Add contact list for bulk email
+        if let Some(list) = &request.list_management {
+            send_request = send_request.list_management_options(
+                ListManagementOptions::builder()
+                    .contact_list_name(&list.contact_list)
+                    .set_topic_name(list.topic.clone())
+                    .build()
+                    .context("Failed to build list management options")?
+            );
+        }
+
+        // Add message tags, which reach the configuration set's event destinations
+        for (name, value) in &request.tags {
+            send_request = send_request.email_tags(message_tag(name, value)?);
+        }
+
         // Send the email
         debug!(
             sender = %sender,
@@ -363,13 +747,19 @@ impl SESClient {
             "Sending email via SES"
         );
 
+        // Wait for room under the send rate so bursts don't get throttled by SES
+        let recipients = request.to.len()
+            + request.cc.as_ref().map_or(0, Vec::len)
+            + request.bcc.as_ref().map_or(0, Vec::len);
+        self.limiter.acquire(recipients).await;
+
         let response = send_request
             .send()
             .await
             .context("Failed to send email via SES")?;
 
         let processing_time = start_time.elapsed().as_millis() as u64;
-        let message_id = response.message_id().to_string();
+        let message_id = response.message_id().unwrap_or_default().to_string();
 
         info!(
             message_id = %message_id,
@@ -387,6 +777,82 @@ impl SESClient {
         })
     }
 
+    /// Content of the request: the raw message, the SES template, or else the subject and bodies
+    fn email_content(request: &EmailRequest) -> Result<EmailContent> {
+        if let Some(raw) = &request.raw_message {
+            let raw = RawMessage::builder()
+                .data(Blob::new(raw.clone()))
+                .build()
+                .context("Failed to build raw message")?;
+            return Ok(EmailContent::builder().raw(raw).build());
+        }
+
+        if let Some(template_name) = &request.ses_template {
+            let template_data = match &request.template_data {
+                Some(data) => data.to_json()?,
+                None => "{}".to_string(),
+            };
+            let template = Template::builder()
+                .template_name(template_name)
+                .template_data(template_data)
+                .build();
+            return Ok(EmailContent::builder().template(template).build());
+        }
+
+        if request.text_body.is_none() && request.html_body.is_none() {
+            return Err(anyhow::anyhow!("Either text_body or html_body must be provided"));
+        }
+
+        // Build message body
+        let mut body_builder = Body::builder();
+        
+        if let Some(text) = &request.text_body {
+            body_builder = body_builder.text(
+                Content::builder()
+                    .data(text)
+                    .charset("UTF-8")
+                    .build()
+                    .context("Failed to build text content")?
+            );
+        }
+        
+        if let Some(html) = &request.html_body {
+            body_builder = body_builder.html(
+                Content::builder()
+                    .data(html)
+                    .charset("UTF-8")
+                    .build()
+                    .context("Failed to build HTML content")?
+            );
+        }
+
+        let body = body_builder.build();
+
+        // Build message
+        let mut message = Message::builder();
+        for (name, value) in &request.headers {
+            message = message.headers(
+                MessageHeader::builder()
+                    .name(name)
+                    .value(value)
+                    .build()
+                    .context("Failed to build message header")?
+            );
+        }
+        let message = message
+            .subject(
+                Content::builder()
+                    .data(&request.subject)
+                    .charset("UTF-8")
+                    .build()
+                    .context("Failed to build subject content")?
+            )
+            .body(body)
+            .build();
+
+        Ok(EmailContent::builder().simple(message).build())
+    }
+
     /// Send a simple text email
     #[instrument(skip(self, body))]
     pub async fn send_text_email<T, S, B>(
@@ -430,401 +896,124 @@ impl SESClient {
         self.send_email(request).await
     }
 
-    /// Send an OTP login email with one-time password
+    /// Sending quota and status of the SES account
     #[instrument(skip(self))]
-    pub async fn send_otp_login_email<T, C>(
-        &self,
-        to_email: T,
-        otp_code: C,
-        user_name: Option<String>,
-        expires_minutes: Option<u32>,
-    ) -> Result<EmailResponse>
-    where
-        T: Into<String> + std::fmt::Debug,
-        C: Into<String> + std::fmt::Display + std::fmt::Debug,
-    {
-        let mut template_data = TemplateData::new();
-        template_data.insert("otp_code", otp_code.to_string());
-        template_data.insert("user_name", user_name.unwrap_or_else(|| "User".to_string()));
-        template_data.insert("expires_minutes", expires_minutes.unwrap_or(5).to_string());
-
-        let html_body = r#"
-<!DOCTYPE html>
-<html>
-<head>
-    <meta charset="UTF-8">
-    <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>Login Verification</title>
-    <style>
-        @import url('https://fonts.googleapis.com/css2?family=Inter:wght@400;500;600&display=swap');
-        .email-container {
-            font-family: 'Inter', -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, sans-serif;
-            line-height: 1.6;
-            color: #1f2937;
-            max-width: 600px;
-            margin: 0 auto;
-            background: #ffffff;
-        }
-        .header {
-            background: linear-gradient(135deg, #667eea 0%, #764ba2 100%);
-            padding: 40px 30px;
-            text-align: center;
-            border-radius: 12px 12px 0 0;
-        }
-        .header h1 {
-            color: #ffffff;
-            margin: 0;
-            font-size: 28px;
-            font-weight: 600;
-        }
-        .content {
-            padding: 40px 30px;
-            background: #ffffff;
-        }
-        .greeting {
-            font-size: 18px;
-            margin-bottom: 20px;
-            color: #374151;
-        }
-        .otp-section {
-            background: linear-gradient(135deg, #f8fafc 0%, #f1f5f9 100%);
-            border: 2px solid #e2e8f0;
-            border-radius: 16px;
-            padding: 30px;
-            text-align: center;
-            margin: 30px 0;
-            box-shadow: 0 4px 6px -1px rgba(0, 0, 0, 0.1);
-        }
-        .otp-label {
-            font-size: 16px;
-            color: #64748b;
-            margin-bottom: 10px;
-            font-weight: 500;
-        }
-        .otp-code {
-            font-size: 42px;
-            font-weight: 600;
-            color: #1e40af;
-            letter-spacing: 8px;
-            margin: 15px 0;
-            padding: 15px;
-            background: #ffffff;
-            border-radius: 12px;
-            border: 2px solid #dbeafe;
-            display: inline-block;
-            min-width: 200px;
-        }
-        .security-notice {
-            background: #fef3c7;
-            border-left: 4px solid #f59e0b;
-            padding: 20px;
-            margin: 25px 0;
-            border-radius: 0 8px 8px 0;
-        }
-        .security-notice h3 {
-            color: #92400e;
-            margin: 0 0 10px 0;
-            font-size: 16px;
-            font-weight: 600;
-        }
-        .security-notice p {
-            color: #a16207;
-            margin: 0;
-            font-size: 14px;
-        }
-        .footer {
-            padding: 30px;
-            background: #f8fafc;
-            border-top: 1px solid #e2e8f0;
-            text-align: center;
-            border-radius: 0 0 12px 12px;
-        }
-        .footer p {
-            color: #6b7280;
-            font-size: 14px;
-            margin: 5px 0;
-        }
-        .expires {
-            color: #ef4444;
-            font-weight: 500;
-            font-size: 16px;
-        }
-        .steps {
-            background: #f0f9ff;
-            border: 1px solid #bae6fd;
-            border-radius: 8px;
-            padding: 20px;
-            margin: 20px 0;
-        }
-        .steps h3 {
-            color: #0369a1;
-            margin: 0 0 15px 0;
-            font-size: 16px;
-        }
-        .steps ol {
-            margin: 0;
-            padding-left: 20px;
-            color: #0f172a;
-        }
-        .steps li {
-            margin: 8px 0;
-            font-size: 14px;
-        }
-    </style>
-</head>
-<body>
-    <div class="email-container">
-        <div class="header">
-            <h1>🔐 Login Verification</h1>
-        </div>
-        
-        <div class="content">
-            <p class="greeting">Hello {{user_name}},</p>
-            
-            <p>We received a request to sign in to your account. To complete your login, please use the one-time password below:</p>
-            
-            <div class="otp-section">
-                <div class="otp-label">Your Login Code</div>
-                <div class="otp-code">{{otp_code}}</div>
-                <p class="expires">⏱️ Expires in {{expires_minutes}} minutes</p>
-            </div>
-            
-            <div class="steps">
-                <h3>How to use this code:</h3>
-                <ol>
-                    <li>Return to the login page where you requested this code</li>
-                    <li>Enter the 6-digit code exactly as shown above</li>
-                    <li>Click "Verify" to complete your login</li>
-                </ol>
-            </div>
-            
-            <div class="security-notice">
-                <h3>🛡️ Security Notice</h3>
-                <p>If you didn't request this login code, please ignore this email and consider changing your password. This code can only be used once and will expire automatically.</p>
-            </div>
-            
-            <p>For your security, this code will only work for the next {{expires_minutes}} minutes. If you need a new code, please request one from the login page.</p>
-        </div>
-        
-        <div class="footer">
-            <p><strong>The Origin Team</strong></p>
-            <p>This is an automated security message. Please do not reply to this email.</p>
-            <p>Need help? Contact our support team.</p>
-        </div>
-    </div>
-</body>
-</html>
-        "#;
-
-        let text_body = r#"
-🔐 LOGIN VERIFICATION
-
-Hello {{user_name}},
-
-We received a request to sign in to your account. To complete your login, please use the one-time password below:
-
-━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
-    YOUR LOGIN CODE: {{otp_code}}
-    ⏱️ Expires in {{expires_minutes}} minutes
-━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
-
-HOW TO USE THIS CODE:
-1. Return to the login page where you requested this code
-2. Enter the 6-digit code exactly as shown above
-3. Click "Verify" to complete your login
-
-🛡️ SECURITY NOTICE
-If you didn't request this login code, please ignore this email and consider changing your password. This code can only be used once and will expire automatically.
-
-For your security, this code will only work for the next {{expires_minutes}} minutes. If you need a new code, please request one from the login page.
-
-━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
-The Origin Team
-This is an automated security message. Please do not reply to this email.
-Need help? Contact our support team.
-        "#;
-
-        let request = EmailRequest::new(vec![to_email], "🔐 Your Login Code - {{otp_code}}")
-            .with_html_body(html_body)
-            .with_text_body(text_body)
-            .with_template_data(template_data)
-            .with_priority(EmailPriority::High)
-            .with_tag("email_type", "otp_login")
-            .with_tag("template", "otp_verification")
-            .with_tag("security_level", "high");
-
-        self.send_email(request).await
+    pub async fn get_account(&self) -> Result<GetAccountOutput> {
+        self.client
+            .get_account()
+            .send()
+            .await
+            .context("Failed to get SES account")
     }
 
-    /// Send a verification email with a verification code
+    /// Check if an email address is verified in SES
     #[instrument(skip(self))]
-    pub async fn send_verification_email<T, C>(
-        &self,
-        to_email: T,
-        verification_code: C,
-        user_name: Option<String>,
-    ) -> Result<EmailResponse>
-    where
-        T: Into<String> + std::fmt::Debug,
-        C: Into<String> + std::fmt::Display + std::fmt::Debug,
-    {
-        let mut template_data = TemplateData::new();
-        template_data.insert("verification_code", verification_code.to_string());
-        template_data.insert("user_name", user_name.unwrap_or_else(|| "User".to_string()));
-
-        let html_body = r#"
-<!DOCTYPE html>
-<html>
-<head>
-    <meta charset="UTF-8">
-    <title>Email Verification</title>
-</head>
-<body style="font-family: Arial, sans-serif; line-height: 1.6; color: #333;">
-    <div style="max-width: 600px; margin: 0 auto; padding: 20px;">
-        <h2 style="color: #2c3e50;">Email Verification Required</h2>
-        <p>Hello {{user_name}},</p>
-        <p>Thank you for registering with our service. To complete your registration, please verify your email address using the verification code below:</p>
-        
-        <div style="background-color: #f8f9fa; border: 2px solid #e9ecef; border-radius: 8px; padding: 20px; text-align: center; margin: 20px 0;">
-            <h3 style="margin: 0; color: #495057;">Verification Code</h3>
-            <h1 style="margin: 10px 0; color: #007bff; font-size: 32px; letter-spacing: 4px;">{{verification_code}}</h1>
-        </div>
-        
-        <p>This verification code will expire in 24 hours. If you didn't request this verification, please ignore this email.</p>
-        
-        <p>Best regards,<br>The Support Team</p>
-        
-        <hr style="border: none; border-top: 1px solid #e9ecef; margin: 30px 0;">
-        <p style="font-size: 12px; color: #6c757d;">
-            This is an automated message. Please do not reply to this email.
-        </p>
-    </div>
-</body>
-</html>
-        "#;
-
-        let text_body = r#"
-Email Verification Required
-
-Hello {{user_name}},
-
-Thank you for registering with our service. To complete your registration, please verify your email address using the verification code below:
-
-Verification Code: {{verification_code}}
-
-This verification code will expire in 24 hours. If you didn't request this verification, please ignore this email.
-
-Best regards,
-The Support Team
-
----
-This is an automated message. Please do not reply to this email.
-        "#;
+    pub async fn is_email_verified(&self, email: &str) -> Result<bool> {
+        let verified = self
+            .identity_status(email)
+            .await?
+            .is_some_and(|status| status.verified_for_sending);
 
-        let request = EmailRequest::new(vec![to_email], "Email Verification Required")
-            .with_html_body(html_body)
-            .with_text_body(text_body)
-            .with_template_data(template_data)
-            .with_priority(EmailPriority::High)
-            .with_tag("email_type", "verification")
-            .with_tag("template", "verification_code");
+        debug!(
+            email = %email,
+            verified = verified,
+            "Checked email verification status"
+        );
 
-        self.send_email(request).await
+        Ok(verified)
     }
 
-    /// Send a notification email
-    #[instrument(skip(self, message))]
-    pub async fn send_notification_email<T, S, M>(
-        &self,
-        to_email: T,
-        subject: S,
-        message: M,
-        priority: EmailPriority,
-    ) -> Result<EmailResponse>
-    where
-        T: Into<String> + std::fmt::Debug,
-        S: Into<String> + std::fmt::Display + std::fmt::Debug,
-        M: Into<String>,
-    {
-        let message_text = message.into();
-        
-        let html_body = format!(r#"
-<!DOCTYPE html>
-<html>
-<head>
-    <meta charset="UTF-8">
-    <title>Notification</title>
-</head>
-<body style="font-family: Arial, sans-serif; line-height: 1.6; color: #333;">
-    <div style="max-width: 600px; margin: 0 auto; padding: 20px;">
-        <h2 style="color: #2c3e50;">Notification</h2>
-        <div style="background-color: #f8f9fa; border-left: 4px solid #007bff; padding: 15px; margin: 20px 0;">
-            <p style="margin: 0;">{}</p>
-        </div>
-        <p>Best regards,<br>The Support Team</p>
-        <hr style="border: none; border-top: 1px solid #e9ecef; margin: 30px 0;">
-        <p style="font-size: 12px; color: #6c757d;">
-            This is an automated message. Please do not reply to this email.
-        </p>
-    </div>
-</body>
-</html>
-        "#, message_text);
-
-        let text_body = format!(r#"
-Notification
-
-{}
-
-Best regards,
-The Support Team
-
----
-This is an automated message. Please do not reply to this email.
-        "#, message_text);
-
-        let request = EmailRequest::new(vec![to_email], subject)
-            .with_html_body(html_body)
-            .with_text_body(text_body)
-            .with_priority(priority)
-            .with_tag("email_type", "notification");
+    /// Whether email can be sent from `address`, because the address or its domain is verified
+    #[instrument(skip(self))]
+    pub async fn can_send_from(&self, address: &str) -> Result<bool> {
+        if self.is_email_verified(address).await? {
+            return Ok(true);
+        }
+        match sender_domain(address) {
+            Some(domain) => Ok(self
+                .identity_status(domain)
+                .await?
+                .is_some_and(|status| status.verified_for_sending)),
+            None => Ok(false),
+        }
+    }
 
-        self.send_email(request).await
+    /// Verification and DKIM state of an email address or domain identity, `None` when it hasn't
+    /// been created in this region
+    #[instrument(skip(self))]
+    pub async fn identity_status(&self, identity: &str) -> Result<Option<IdentityStatus>> {
+        let response = match self.client.get_email_identity().email_identity(identity).send().await {
+            Ok(response) => response,
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_not_found_exception()) => return Ok(None),
+            Err(e) => return Err(e).context("Failed to get SES identity"),
+        };
+        Ok(Some(IdentityStatus::new(
+            identity,
+            response.verified_for_sending_status(),
+            response.verification_status(),
+            response.dkim_attributes(),
+        )))
     }
 
-    /// Verify SES sending statistics and quota
+    /// Create an email address identity; SES emails the address a link to verify it. An existing
+    /// identity is returned as it is.
     #[instrument(skip(self))]
-    pub async fn get_send_statistics(&self) -> Result<aws_sdk_ses::operation::get_send_statistics::GetSendStatisticsOutput> {
-        self.client
-            .get_send_statistics()
-            .send()
-            .await
-            .context("Failed to get SES send statistics")
+    pub async fn create_email_identity(&self, email: &str) -> Result<IdentityStatus> {
+        if sender_domain(email).is_none() {
+            bail!("'{}' is not an email address", email);
+        }
+        self.create_identity(email).await
     }
 
-    /// Check if an email address is verified in SES
+    /// Create a domain identity with Easy DKIM; the domain is verified once the CNAME records from
+    /// [`IdentityStatus::dkim_records`] are published. An existing identity is returned as it is.
     #[instrument(skip(self))]
-    pub async fn is_email_verified(&self, email: &str) -> Result<bool> {
-        let response = self.client
-            .get_identity_verification_attributes()
-            .identities(email)
+    pub async fn create_domain_identity(&self, domain: &str) -> Result<IdentityStatus> {
+        if domain.contains('@') || !domain.contains('.') {
+            bail!("'{}' is not a domain", domain);
+        }
+        self.create_identity(domain).await
+    }
+
+    async fn create_identity(&self, identity: &str) -> Result<IdentityStatus> {
+        let status = match self
+            .client
+            .create_email_identity()
+            .email_identity(identity)
             .send()
             .await
-            .context("Failed to check email verification status")?;
-
-        let verified = response
-            .verification_attributes()
-            .get(email)
-            .map(|attr| attr.verification_status().as_str() == "Success")
-            .unwrap_or(false);
+        {
+            Ok(response) => IdentityStatus::new(
+                identity,
+                response.verified_for_sending_status(),
+                None,
+                response.dkim_attributes(),
+            ),
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_already_exists_exception()) => self
+                .identity_status(identity)
+                .await?
+                .context("SES identity already exists but can't be read")?,
+            Err(e) => return Err(e).context("Failed to create SES identity"),
+        };
 
-        debug!(
-            email = %email,
-            verified = verified,
-            "Checked email verification status"
+        info!(
+            identity = %identity,
+            verified = status.verified_for_sending,
+            dkim_status = ?status.dkim_status,
+            "SES identity created"
         );
+        Ok(status)
+    }
+}
 
-        Ok(verified)
+#[async_trait::async_trait]
+impl EmailTransport for SESClient {
+    fn name(&self) -> &'static str {
+        "ses"
+    }
+
+    async fn send_email(&self, request: EmailRequest) -> Result<EmailResponse> {
+        SESClient::send_email(self, request).await
     }
 }
 
@@ -857,4 +1046,91 @@ mod tests {
         assert!(matches!(request.priority, EmailPriority::High));
         assert_eq!(request.tags.get("test"), Some(&"value".to_string()));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_email_content() {
+        let request = EmailRequest::new(vec!["test@example.com"], "Test Subject").with_text_body("Test body");
+        let content = SESClient::email_content(&request).unwrap();
+        let message = content.simple().unwrap();
+        assert_eq!(message.subject().map(|s| s.data()), Some("Test Subject"));
+
+        let mut data = TemplateData::new();
+        data.insert("code", "123456");
+        let request = request.with_template_data(data).with_ses_template("otp");
+        let content = SESClient::email_content(&request).unwrap();
+        let template = content.template().unwrap();
+        assert_eq!(template.template_name(), Some("otp"));
+        assert_eq!(template.template_data(), Some(r#"{"code":"123456"}"#));
+
+        let request = request.with_raw_message(b"Subject: Hi\r\n\r\nBody".to_vec());
+        assert!(SESClient::email_content(&request).unwrap().raw().is_some());
+
+        let request = EmailRequest::new(vec!["test@example.com"], "No body");
+        assert!(SESClient::email_content(&request).is_err());
+    }
+
+    #[test]
+    fn test_token_bucket_spaces_bursts() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(2.0, start);
+        assert_eq!(bucket.reserve(1.0, start), Duration::ZERO);
+        assert_eq!(bucket.reserve(1.0, start), Duration::ZERO);
+        // Out of tokens: each further send waits another half second
+        assert_eq!(bucket.reserve(1.0, start), Duration::from_millis(500));
+        assert_eq!(bucket.reserve(1.0, start), Duration::from_secs(1));
+
+        // Refills over time, but never past one second's worth
+        let later = start + Duration::from_secs(10);
+        assert_eq!(bucket.reserve(2.0, later), Duration::ZERO);
+        assert_eq!(bucket.reserve(1.0, later), Duration::from_millis(500));
+
+        bucket.set_rate(10.0, later + Duration::from_secs(1));
+        assert_eq!(bucket.reserve(1.0, later + Duration::from_secs(1)), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_allowed_send_rate() {
+        let quota = SendQuota {
+            max_send_rate: 14.0,
+            max_24_hour_send: 50_000.0,
+            sent_last_24_hours: 45_000.0,
+        };
+        assert_eq!(allowed_send_rate(None, None), DEFAULT_MAX_SEND_RATE);
+        assert_eq!(allowed_send_rate(Some(5.0), None), 5.0);
+        assert!((allowed_send_rate(None, Some(&quota)) - 12.6).abs() < 1e-9);
+        assert_eq!(allowed_send_rate(Some(5.0), Some(&quota)), 5.0);
+        assert!((allowed_send_rate(Some(50.0), Some(&quota)) - 12.6).abs() < 1e-9);
+        assert!((quota.daily_usage() - 0.9).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_message_tag_sanitized() {
+        let tag = message_tag("template_version", "weekly digest/v2").unwrap();
+        assert_eq!(tag.name(), "template_version");
+        assert_eq!(tag.value(), "weekly_digest_v2");
+    }
+
+    #[test]
+    fn test_identity_dkim_records() {
+        let status = IdentityStatus {
+            identity: "example.com".to_string(),
+            verified_for_sending: false,
+            verification_status: Some("PENDING".to_string()),
+            dkim_status: Some("PENDING".to_string()),
+            dkim_tokens: vec!["abc123".to_string(), "def456".to_string()],
+        };
+        assert!(!status.dkim_verified());
+        assert_eq!(
+            status.dkim_records()[0],
+            (
+                "abc123._domainkey.example.com".to_string(),
+                "abc123.dkim.amazonses.com".to_string()
+            )
+        );
+        assert_eq!(status.dkim_records().len(), 2);
+
+        assert_eq!(sender_domain("noreply@mail.example.com"), Some("mail.example.com"));
+        assert_eq!(sender_domain("example.com"), None);
+        assert_eq!(sender_domain("noreply@"), None);
+    }
+}
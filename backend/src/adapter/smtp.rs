@@ -0,0 +1,304 @@
+// SMTP email transport for self-hosted deployments, local MailHog, and failover from SES
+use crate::adapter::email_transport::EmailTransport;
+use crate::adapter::ses::{EmailRequest, EmailResponse};
+use crate::settings::SettingValues;
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use lettre::address::{Address, Envelope};
+use lettre::message::header::{ContentType, HeaderName, HeaderValue};
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::time::Duration;
+use tracing::{debug, info, instrument};
+use uuid::Uuid;
+
+/// How the connection to the SMTP server is secured
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtpTls {
+    /// Plain connection, e.g. to MailHog on localhost
+    None,
+    /// Upgrade with STARTTLS (usually port 587)
+    StartTls,
+    /// TLS from the start (usually port 465)
+    Tls,
+}
+
+impl SmtpTls {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SmtpTls::None => "none",
+            SmtpTls::StartTls => "starttls",
+            SmtpTls::Tls => "tls",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "none" => Some(SmtpTls::None),
+            "starttls" => Some(SmtpTls::StartTls),
+            "tls" => Some(SmtpTls::Tls),
+            _ => None,
+        }
+    }
+}
+
+/// Configuration for the SMTP client
+#[derive(Debug, Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub tls: SmtpTls,
+    /// Default sender email address
+    pub default_sender: String,
+    /// Default sender name (optional)
+    pub default_sender_name: Option<String>,
+    /// Reply-to email address (optional)
+    pub reply_to: Option<String>,
+    /// Timeout of each SMTP command
+    pub timeout: Duration,
+}
+
+impl SmtpConfig {
+    /// Read from settings; `None` when no server or sender is set
+    /// - SMTP_HOST: Server host name
+    /// - SMTP_PORT: Server port (default: 587)
+    /// - SMTP_USERNAME / SMTP_PASSWORD: Credentials (optional)
+    /// - SMTP_TLS: none, starttls or tls (default: starttls)
+    /// - SMTP_DEFAULT_SENDER: Default sender email (default: AWS_SES_DEFAULT_SENDER)
+    /// - SMTP_DEFAULT_SENDER_NAME: Default sender name (default: AWS_SES_DEFAULT_SENDER_NAME)
+    /// - SMTP_REPLY_TO: Default reply-to address (default: AWS_SES_REPLY_TO)
+    /// - SMTP_TIMEOUT_SECS: Command timeout (default: 10)
+    pub fn from_values(values: &mut SettingValues) -> Option<Self> {
+        let host = values.optional("smtp-host")?;
+        let or_ses = |values: &SettingValues, key: &str, fallback: &str| {
+            values.optional(key).or_else(|| values.optional(fallback))
+        };
+        let default_sender = or_ses(values, "smtp-default-sender", "aws-ses-default-sender")?;
+        let tls = values.or("smtp-tls", "starttls");
+        let tls = SmtpTls::parse(&tls).unwrap_or_else(|| {
+            values.error(format!("smtp-tls must be none, starttls or tls, not '{}'", tls));
+            SmtpTls::StartTls
+        });
+        Some(Self {
+            host,
+            port: values.parsed("smtp-port", 587),
+            username: values.optional("smtp-username"),
+            password: values.optional("smtp-password"),
+            tls,
+            default_sender,
+            default_sender_name: or_ses(values, "smtp-default-sender-name", "aws-ses-default-sender-name"),
+            reply_to: or_ses(values, "smtp-reply-to", "aws-ses-reply-to"),
+            timeout: Duration::from_secs(values.parsed("smtp-timeout-secs", 10)),
+        })
+    }
+}
+
+/// Sends email through an SMTP server
+pub struct SmtpClient {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    config: SmtpConfig,
+}
+
+impl SmtpClient {
+    #[instrument(skip(config), fields(host = %config.host, port = config.port))]
+    pub fn new(config: SmtpConfig) -> Result<Self> {
+        let builder = match config.tls {
+            SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host),
+            SmtpTls::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)
+                .context("Failed to set up STARTTLS")?,
+            SmtpTls::Tls => {
+                AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host).context("Failed to set up TLS")?
+            }
+        };
+        let mut builder = builder.port(config.port).timeout(Some(config.timeout));
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        info!(
+            host = %config.host,
+            port = config.port,
+            tls = config.tls.as_str(),
+            default_sender = %config.default_sender,
+            "Initialized SMTP client"
+        );
+        Ok(Self {
+            transport: builder.build(),
+            config,
+        })
+    }
+
+    fn sender(&self, request: &EmailRequest) -> String {
+        let email = request.sender.as_deref().unwrap_or(&self.config.default_sender);
+        match request
+            .sender_name
+            .as_ref()
+            .or(self.config.default_sender_name.as_ref())
+        {
+            Some(name) => format!("{} <{}>", name, email),
+            None => email.to_string(),
+        }
+    }
+
+    /// The request as a MIME message with the given Message-ID
+    fn build_message(&self, request: &EmailRequest, message_id: &str) -> Result<Message> {
+        let mailbox = |address: &str| -> Result<Mailbox> {
+            address
+                .parse()
+                .with_context(|| format!("Invalid email address '{}'", address))
+        };
+
+        let mut builder = Message::builder()
+            .message_id(Some(message_id.to_string()))
+            .from(mailbox(&self.sender(request))?)
+            .subject(request.subject.as_str());
+        for to in &request.to {
+            builder = builder.to(mailbox(to)?);
+        }
+        for cc in request.cc.iter().flatten() {
+            builder = builder.cc(mailbox(cc)?);
+        }
+        for bcc in request.bcc.iter().flatten() {
+            builder = builder.bcc(mailbox(bcc)?);
+        }
+        if let Some(reply_to) = request.reply_to.as_ref().or(self.config.reply_to.as_ref()) {
+            builder = builder.reply_to(mailbox(reply_to)?);
+        }
+        for (name, value) in &request.headers {
+            let name = HeaderName::new_from_ascii(name.clone())
+                .with_context(|| format!("Invalid email header name '{}'", name))?;
+            builder = builder.raw_header(HeaderValue::new(name, value.clone()));
+        }
+
+        let message = match (&request.text_body, &request.html_body) {
+            (Some(text), Some(html)) => {
+                builder.multipart(MultiPart::alternative_plain_html(text.clone(), html.clone()))
+            }
+            (Some(text), None) => builder.header(ContentType::TEXT_PLAIN).body(text.clone()),
+            (None, Some(html)) => builder.header(ContentType::TEXT_HTML).body(html.clone()),
+            (None, None) => bail!("Either text_body or html_body must be provided"),
+        };
+        message.context("Failed to build email message")
+    }
+
+    /// Envelope of a raw message: the sender and every recipient
+    fn envelope(&self, request: &EmailRequest) -> Result<Envelope> {
+        let address = |address: &str| -> Result<Address> {
+            let mailbox: Mailbox = address
+                .parse()
+                .with_context(|| format!("Invalid email address '{}'", address))?;
+            Ok(mailbox.email)
+        };
+        let recipients = request
+            .to
+            .iter()
+            .chain(request.cc.iter().flatten())
+            .chain(request.bcc.iter().flatten())
+            .map(|r| address(r))
+            .collect::<Result<Vec<_>>>()?;
+        Envelope::new(Some(address(&self.sender(request))?), recipients).context("Invalid email envelope")
+    }
+
+    /// Message-ID in the sender's domain
+    fn new_message_id(&self) -> String {
+        let domain = self.config.default_sender.rsplit('@').next().unwrap_or("localhost");
+        format!("<{}@{}>", Uuid::new_v4(), domain)
+    }
+}
+
+#[async_trait]
+impl EmailTransport for SmtpClient {
+    fn name(&self) -> &'static str {
+        "smtp"
+    }
+
+    #[instrument(skip(self, request), fields(to_count = request.to.len(), subject = %request.subject))]
+    async fn send_email(&self, request: EmailRequest) -> Result<EmailResponse> {
+        let start_time = std::time::Instant::now();
+        let request = request.rendered();
+        if request.to.is_empty() {
+            bail!("At least one recipient is required");
+        }
+        if let Some(template) = &request.ses_template {
+            bail!("SES template '{}' can't be sent over SMTP", template);
+        }
+
+        let message_id = self.new_message_id();
+        debug!(host = %self.config.host, to_addresses = ?request.to, "Sending email via SMTP");
+        match &request.raw_message {
+            Some(raw) => self.transport.send_raw(&self.envelope(&request)?, raw).await,
+            None => self.transport.send(self.build_message(&request, &message_id)?).await,
+        }
+        .context("Failed to send email via SMTP")?;
+
+        let processing_time = start_time.elapsed().as_millis() as u64;
+        info!(
+            message_id = %message_id,
+            processing_time_ms = processing_time,
+            to_count = request.to.len(),
+            subject = %request.subject,
+            "Email sent via SMTP"
+        );
+        Ok(EmailResponse {
+            message_id,
+            accepted: true,
+            processing_time_ms: processing_time,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client() -> SmtpClient {
+        SmtpClient::new(SmtpConfig {
+            host: "localhost".to_string(),
+            port: 1025,
+            username: None,
+            password: None,
+            tls: SmtpTls::None,
+            default_sender: "noreply@example.com".to_string(),
+            default_sender_name: Some("Origin".to_string()),
+            reply_to: None,
+            timeout: Duration::from_secs(5),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_tls_round_trip() {
+        for tls in [SmtpTls::None, SmtpTls::StartTls, SmtpTls::Tls] {
+            assert_eq!(SmtpTls::parse(tls.as_str()), Some(tls));
+        }
+        assert_eq!(SmtpTls::parse("ssl"), None);
+    }
+
+    #[test]
+    fn test_build_message() {
+        let client = client();
+        let request = EmailRequest::new(vec!["ana@example.com"], "Your code")
+            .with_text_body("Code 482913")
+            .with_html_body("<p>Code 482913</p>")
+            .with_cc(vec!["ops@example.com".to_string()]);
+
+        let message = client.build_message(&request, "<1@example.com>").unwrap();
+        let formatted = String::from_utf8(message.formatted()).unwrap();
+        assert!(formatted.contains("From: Origin <noreply@example.com>"));
+        assert!(formatted.contains("To: ana@example.com"));
+        assert!(formatted.contains("Cc: ops@example.com"));
+        assert!(formatted.contains("Message-ID: <1@example.com>"));
+        assert!(formatted.contains("multipart/alternative"));
+
+        let envelope = client.envelope(&request).unwrap();
+        assert_eq!(envelope.to().len(), 2);
+
+        let request = EmailRequest::new(vec!["not an address"], "Oops").with_text_body("Hi");
+        assert!(client.build_message(&request, "<2@example.com>").is_err());
+        let request = EmailRequest::new(vec!["ana@example.com"], "No body");
+        assert!(client.build_message(&request, "<3@example.com>").is_err());
+    }
+}
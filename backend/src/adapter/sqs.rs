@@ -0,0 +1,290 @@
+use crate::settings::SettingValues;
+use anyhow::{anyhow, Context, Result};
+use aws_config::BehaviorVersion;
+use aws_sdk_sqs::types::{MessageSystemAttributeName, QueueAttributeName, SendMessageBatchRequestEntry};
+use aws_sdk_sqs::Client;
+use std::time::Duration;
+use tracing::{debug, info, instrument, warn};
+
+/// Most messages SQS returns per receive
+pub const MAX_RECEIVE_MESSAGES: i32 = 10;
+/// Most messages SQS accepts per batch send
+pub const MAX_SEND_BATCH: usize = 10;
+/// Longest a sent message can be delayed
+pub const MAX_DELAY: Duration = Duration::from_secs(15 * 60);
+/// Longest a received message can be hidden from other consumers
+pub const MAX_VISIBILITY_TIMEOUT: Duration = Duration::from_secs(12 * 60 * 60);
+
+/// Configuration for an Amazon SQS queue consumer
+#[derive(Debug, Clone)]
+pub struct SqsConfig {
+    /// AWS region of the queue (e.g., "us-east-1")
+    pub region: String,
+    /// URL of the queue
+    pub queue_url: String,
+    /// Seconds a receive waits for messages when the queue is empty (long polling, 0-20)
+    pub wait_time_seconds: i32,
+    /// Seconds a received message is hidden from other consumers; `None` keeps the queue's setting
+    pub visibility_timeout_seconds: Option<i32>,
+    /// URL of the queue messages that keep failing are moved to
+    pub dead_letter_queue_url: Option<String>,
+}
+
+impl Default for SqsConfig {
+    fn default() -> Self {
+        Self {
+            region: "us-east-1".to_string(),
+            queue_url: String::new(),
+            wait_time_seconds: 1,
+            visibility_timeout_seconds: None,
+            dead_letter_queue_url: None,
+        }
+    }
+}
+
+impl SqsConfig {
+    /// Read the queue whose URL is in `queue_url_key`, e.g. `email-events-queue-url`; `None` when it is not set
+    /// - `queue_url_key`: URL of the queue, e.g. EMAIL_EVENTS_QUEUE_URL
+    /// - The same key ending in `-dlq-url` instead of `-queue-url`, e.g. EMAIL_EVENTS_DLQ_URL: URL of the
+    ///   dead-letter queue (default: none)
+    /// - AWS_SQS_REGION: AWS region (default: us-east-1)
+    /// - SQS_VISIBILITY_TIMEOUT_SECS: seconds a received message stays hidden (default: the queue's setting)
+    pub fn from_values(values: &mut SettingValues, queue_url_key: &str) -> Option<Self> {
+        let queue_url = values.optional(queue_url_key)?;
+        let defaults = Self::default();
+        Some(Self {
+            region: values.or("aws-sqs-region", &defaults.region),
+            queue_url,
+            wait_time_seconds: defaults.wait_time_seconds,
+            visibility_timeout_seconds: values.parsed_optional("sqs-visibility-timeout-secs"),
+            dead_letter_queue_url: values.optional(&dead_letter_queue_key(queue_url_key)),
+        })
+    }
+}
+
+/// Message received from a queue; it is redelivered unless deleted before its visibility timeout
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueueMessage {
+    pub message_id: String,
+    pub receipt_handle: String,
+    pub body: String,
+    /// Times the message has been received, this time included
+    pub receive_count: u32,
+}
+
+/// Amazon SQS client reading one queue
+#[derive(Debug, Clone)]
+pub struct SqsQueue {
+    client: Client,
+    config: SqsConfig,
+}
+
+impl SqsQueue {
+    /// Create a new SQS client with configuration
+    #[instrument(skip(config), fields(region = %config.region))]
+    pub async fn new(config: SqsConfig) -> Result<Self> {
+        let aws_config = aws_config::defaults(BehaviorVersion::latest())
+            .region(aws_config::Region::new(config.region.clone()))
+            .load()
+            .await;
+
+        let client = Client::new(&aws_config);
+
+        info!(region = %config.region, queue_url = %config.queue_url, "Initialized SQS client");
+
+        Ok(Self { client, config })
+    }
+
+    /// Client for this queue's dead-letter queue, if it has one
+    pub fn dead_letter_queue(&self) -> Option<SqsQueue> {
+        let queue_url = self.config.dead_letter_queue_url.clone()?;
+        Some(Self {
+            client: self.client.clone(),
+            config: SqsConfig {
+                queue_url,
+                dead_letter_queue_url: None,
+                ..self.config.clone()
+            },
+        })
+    }
+
+    pub fn queue_url(&self) -> &str {
+        &self.config.queue_url
+    }
+
+    /// Seconds a received message stays hidden, when this client sets it
+    pub fn visibility_timeout(&self) -> Option<Duration> {
+        self.config
+            .visibility_timeout_seconds
+            .map(|seconds| Duration::from_secs(seconds.max(0) as u64))
+    }
+
+    /// Receive up to `max_messages` messages, waiting briefly when there are none
+    #[instrument(skip(self))]
+    pub async fn receive(&self, max_messages: i32) -> Result<Vec<QueueMessage>> {
+        let output = self
+            .client
+            .receive_message()
+            .queue_url(&self.config.queue_url)
+            .max_number_of_messages(max_messages.clamp(1, MAX_RECEIVE_MESSAGES))
+            .wait_time_seconds(self.config.wait_time_seconds.clamp(0, 20))
+            .set_visibility_timeout(self.config.visibility_timeout_seconds)
+            .attribute_names(QueueAttributeName::All)
+            .send()
+            .await
+            .context("Failed to receive SQS messages")?;
+
+        let messages: Vec<QueueMessage> = output
+            .messages()
+            .iter()
+            .filter_map(|message| {
+                Some(QueueMessage {
+                    message_id: message.message_id()?.to_string(),
+                    receipt_handle: message.receipt_handle()?.to_string(),
+                    body: message.body().unwrap_or_default().to_string(),
+                    receive_count: message
+                        .attributes()
+                        .and_then(|attributes| attributes.get(&MessageSystemAttributeName::ApproximateReceiveCount))
+                        .and_then(|count| count.parse().ok())
+                        .unwrap_or(1),
+                })
+            })
+            .collect();
+
+        debug!(count = messages.len(), "Received SQS messages");
+        Ok(messages)
+    }
+
+    /// Remove a handled message so it isn't delivered again
+    #[instrument(skip(self, message), fields(message_id = %message.message_id))]
+    pub async fn delete(&self, message: &QueueMessage) -> Result<()> {
+        self.client
+            .delete_message()
+            .queue_url(&self.config.queue_url)
+            .receipt_handle(&message.receipt_handle)
+            .send()
+            .await
+            .context("Failed to delete SQS message")?;
+
+        Ok(())
+    }
+
+    /// Send one message, delivered after `delay` (at most 15 minutes); returns its message ID
+    #[instrument(skip(self, body))]
+    pub async fn send(&self, body: &str, delay: Duration) -> Result<String> {
+        let output = self
+            .client
+            .send_message()
+            .queue_url(&self.config.queue_url)
+            .message_body(body)
+            .delay_seconds(delay_seconds(delay))
+            .send()
+            .await
+            .context("Failed to send SQS message")?;
+
+        Ok(output.message_id().unwrap_or_default().to_string())
+    }
+
+    /// Send messages in batches of ten; returns how many were accepted. Messages SQS rejects are
+    /// logged and left out of the count.
+    #[instrument(skip(self, bodies), fields(count = bodies.len()))]
+    pub async fn send_batch(&self, bodies: &[String]) -> Result<usize> {
+        let mut sent = 0;
+        for chunk in bodies.chunks(MAX_SEND_BATCH) {
+            let entries = chunk
+                .iter()
+                .enumerate()
+                .map(|(index, body)| {
+                    SendMessageBatchRequestEntry::builder()
+                        .id(index.to_string())
+                        .message_body(body)
+                        .build()
+                        .map_err(|e| anyhow!("Invalid SQS batch entry: {}", e))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let output = self
+                .client
+                .send_message_batch()
+                .queue_url(&self.config.queue_url)
+                .set_entries(Some(entries))
+                .send()
+                .await
+                .context("Failed to send SQS message batch")?;
+
+            for failed in output.failed() {
+                warn!(entry = %failed.id(), code = %failed.code(), "SQS rejected a batched message");
+            }
+            sent += output.successful().len();
+        }
+
+        Ok(sent)
+    }
+
+    /// Hide a received message from other consumers for `timeout` from now, to keep working on it or
+    /// to retry it later; a zero timeout makes it visible again right away
+    #[instrument(skip(self, message), fields(message_id = %message.message_id))]
+    pub async fn change_visibility(&self, message: &QueueMessage, timeout: Duration) -> Result<()> {
+        self.client
+            .change_message_visibility()
+            .queue_url(&self.config.queue_url)
+            .receipt_handle(&message.receipt_handle)
+            .visibility_timeout(timeout.min(MAX_VISIBILITY_TIMEOUT).as_secs() as i32)
+            .send()
+            .await
+            .context("Failed to change SQS message visibility")?;
+
+        Ok(())
+    }
+
+    /// Move up to `limit` messages from `source`, usually a dead-letter queue, back onto this queue;
+    /// returns how many were moved. A message is only deleted from `source` once it has been sent here.
+    #[instrument(skip(self, source), fields(source = %source.config.queue_url))]
+    pub async fn redrive_from(&self, source: &SqsQueue, limit: usize) -> Result<usize> {
+        let mut moved = 0;
+        while moved < limit {
+            let messages = source.receive((limit - moved) as i32).await?;
+            if messages.is_empty() {
+                break;
+            }
+            for message in &messages {
+                self.send(&message.body, Duration::ZERO).await?;
+                source.delete(message).await?;
+                moved += 1;
+            }
+        }
+
+        info!(moved, "Redrove SQS messages");
+        Ok(moved)
+    }
+}
+
+/// Dead-letter queue key paired with a queue URL key: `x-queue-url` pairs with `x-dlq-url`
+fn dead_letter_queue_key(queue_url_key: &str) -> String {
+    let prefix = queue_url_key
+        .strip_suffix("-queue-url")
+        .or_else(|| queue_url_key.strip_suffix("-url"))
+        .unwrap_or(queue_url_key);
+    format!("{}-dlq-url", prefix)
+}
+
+fn delay_seconds(delay: Duration) -> i32 {
+    delay.min(MAX_DELAY).as_secs() as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dead_letter_queue_key() {
+        assert_eq!(dead_letter_queue_key("email-events-queue-url"), "email-events-dlq-url");
+        assert_eq!(dead_letter_queue_key("plaid-webhooks-url"), "plaid-webhooks-dlq-url");
+        assert_eq!(dead_letter_queue_key("jobs"), "jobs-dlq-url");
+    }
+
+    #[test]
+    fn test_delay_is_capped() {
+        assert_eq!(delay_seconds(Duration::from_millis(1500)), 1);
+        assert_eq!(delay_seconds(Duration::from_secs(3600)), 900);
+    }
+}
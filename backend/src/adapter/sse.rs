@@ -0,0 +1,93 @@
+// Server-sent event parsing for streamed HTTP responses
+
+/// One server-sent event
+#[derive(Debug, PartialEq)]
+pub struct SseEvent {
+    /// Event type; `message` when the event names none
+    pub event: String,
+    pub data: String,
+}
+
+/// Splits a server-sent event byte stream into events; chunks may end anywhere, even mid-character
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+}
+
+impl SseParser {
+    /// Events completed by `chunk`
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut events = Vec::new();
+        while let Some((end, separator_len)) = Self::event_end(&self.buffer) {
+            let block: Vec<u8> = self.buffer.drain(..end + separator_len).collect();
+            if let Some(event) = Self::parse_event(&String::from_utf8_lossy(&block[..end])) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    /// Position and length of the blank line ending the first buffered event
+    fn event_end(buffer: &[u8]) -> Option<(usize, usize)> {
+        (0..buffer.len()).find_map(|i| {
+            if buffer[i..].starts_with(b"\n\n") {
+                Some((i, 2))
+            } else if buffer[i..].starts_with(b"\r\n\r\n") {
+                Some((i, 4))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Event from its `field: value` lines; comments are skipped and events without data dropped
+    fn parse_event(block: &str) -> Option<SseEvent> {
+        let mut event = String::new();
+        let mut data = Vec::new();
+
+        for line in block.lines().filter(|line| !line.starts_with(':')) {
+            let (field, value) = match line.split_once(':') {
+                Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+                None => (line, ""),
+            };
+            match field {
+                "event" => event = value.to_string(),
+                "data" => data.push(value),
+                _ => {}
+            }
+        }
+
+        if data.is_empty() {
+            return None;
+        }
+        Some(SseEvent {
+            event: if event.is_empty() { "message".to_string() } else { event },
+            data: data.join("\n"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sse_parser_handles_split_chunks() {
+        let mut parser = SseParser::default();
+        assert!(parser.push(b"event: ping\ndata: {\"type\":").is_empty());
+        assert_eq!(
+            parser.push(b"\"ping\"}\n\n: comment\n\nevent: a\r\ndata: 1\r\ndata: 2\r\n\r\ndata: \xc3"),
+            vec![
+                SseEvent { event: "ping".to_string(), data: "{\"type\":\"ping\"}".to_string() },
+                SseEvent { event: "a".to_string(), data: "1\n2".to_string() },
+            ]
+        );
+        // The second byte of a split UTF-8 character completes the last event
+        assert_eq!(
+            parser.push(b"\xa9\n\n"),
+            vec![SseEvent { event: "message".to_string(), data: "é".to_string() }]
+        );
+    }
+}
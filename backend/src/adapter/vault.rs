@@ -0,0 +1,335 @@
+use crate::adapter::secrets::SecretsProvider;
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::{debug, info, instrument};
+
+/// Name of the Vault provider
+pub const VAULT_PROVIDER: &str = "vault";
+
+/// Field of a KV entry holding the secret's value
+pub const VALUE_FIELD: &str = "value";
+
+/// AppRole tokens are renewed this long before they expire
+const TOKEN_RENEW_MARGIN: Duration = Duration::from_secs(60);
+
+/// How the client authenticates to Vault
+#[derive(Clone)]
+pub enum VaultAuth {
+    /// A fixed token, e.g. from a Vault agent
+    Token(String),
+    /// AppRole login, repeated before the issued token expires
+    AppRole {
+        /// Mount path of the AppRole auth method
+        mount: String,
+        role_id: String,
+        secret_id: String,
+    },
+}
+
+impl std::fmt::Debug for VaultAuth {
+    // Tokens and secret IDs are credentials
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Token(_) => f.write_str("Token"),
+            Self::AppRole { mount, role_id, .. } => f
+                .debug_struct("AppRole")
+                .field("mount", mount)
+                .field("role_id", role_id)
+                .finish_non_exhaustive(),
+        }
+    }
+}
+
+/// Configuration for the Vault KV v2 client
+#[derive(Debug, Clone)]
+pub struct VaultConfig {
+    /// Server address, e.g. https://vault.internal:8200
+    pub address: String,
+    /// Mount path of the KV v2 secrets engine
+    pub kv_mount: String,
+    /// Vault Enterprise namespace, sent as `X-Vault-Namespace`
+    pub namespace: Option<String>,
+    pub auth: VaultAuth,
+    /// Request timeout in seconds
+    pub timeout_seconds: u64,
+}
+
+impl VaultConfig {
+    /// Load the configuration from environment variables
+    /// - VAULT_ADDR: server address (required)
+    /// - VAULT_TOKEN: token to authenticate with; otherwise AppRole is used
+    /// - VAULT_ROLE_ID / VAULT_SECRET_ID: AppRole credentials
+    /// - VAULT_APPROLE_MOUNT: AppRole auth mount (default: approle)
+    /// - VAULT_KV_MOUNT: KV v2 mount (default: secret)
+    /// - VAULT_NAMESPACE: Vault Enterprise namespace (default: none)
+    pub fn from_env() -> Result<Self> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        let auth = match (var("VAULT_TOKEN"), var("VAULT_ROLE_ID"), var("VAULT_SECRET_ID")) {
+            (Some(token), _, _) => VaultAuth::Token(token),
+            (None, Some(role_id), Some(secret_id)) => VaultAuth::AppRole {
+                mount: var("VAULT_APPROLE_MOUNT").unwrap_or_else(|| "approle".to_string()),
+                role_id,
+                secret_id,
+            },
+            _ => bail!("Set VAULT_TOKEN, or VAULT_ROLE_ID and VAULT_SECRET_ID"),
+        };
+        Ok(Self {
+            address: var("VAULT_ADDR").context("VAULT_ADDR environment variable not set")?,
+            kv_mount: var("VAULT_KV_MOUNT").unwrap_or_else(|| "secret".to_string()),
+            namespace: var("VAULT_NAMESPACE"),
+            auth,
+            timeout_seconds: 10,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct KvResponse {
+    data: KvData,
+}
+
+#[derive(Debug, Deserialize)]
+struct KvData {
+    /// `null` when the latest version is deleted
+    data: Option<serde_json::Map<String, Value>>,
+    metadata: KvVersion,
+}
+
+#[derive(Debug, Deserialize)]
+struct KvVersion {
+    version: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct KvMetadataResponse {
+    data: KvMetadata,
+}
+
+#[derive(Debug, Deserialize)]
+struct KvMetadata {
+    current_version: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct LoginResponse {
+    auth: LoginAuth,
+}
+
+#[derive(Debug, Deserialize)]
+struct LoginAuth {
+    client_token: String,
+    lease_duration: u64,
+}
+
+struct CachedToken {
+    token: String,
+    /// `None` for tokens that aren't renewed
+    expires_at: Option<Instant>,
+}
+
+/// HashiCorp Vault client reading KV v2 secrets.
+///
+/// The secret `key` in `namespace` is the KV entry `<namespace>/<key>` (e.g. `origin/prod/jwt-secret`) and
+/// its value is the entry's `value` field. Versions are the KV version numbers.
+#[derive(Clone)]
+pub struct VaultClient {
+    config: VaultConfig,
+    client: Client,
+    token: Arc<Mutex<Option<CachedToken>>>,
+}
+
+impl VaultClient {
+    pub fn new(config: VaultConfig) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(config.timeout_seconds))
+            .build()
+            .context("Failed to create HTTP client")?;
+        info!(address = %config.address, kv_mount = %config.kv_mount, auth = ?config.auth, "Initialized Vault client");
+
+        Ok(Self {
+            config,
+            client,
+            token: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    pub fn from_env() -> Result<Self> {
+        Self::new(VaultConfig::from_env()?)
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/v1/{}", self.config.address.trim_end_matches('/'), path)
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let request = self.client.request(method, self.url(path));
+        match &self.config.namespace {
+            Some(namespace) => request.header("X-Vault-Namespace", namespace),
+            None => request,
+        }
+    }
+
+    /// A token valid for the next request, logging in again when the cached one is about to expire
+    async fn token(&self) -> Result<String> {
+        let (mount, role_id, secret_id) = match &self.config.auth {
+            VaultAuth::Token(token) => return Ok(token.clone()),
+            VaultAuth::AppRole {
+                mount,
+                role_id,
+                secret_id,
+            } => (mount, role_id, secret_id),
+        };
+
+        let mut cached = self.token.lock().await;
+        if let Some(token) = cached.as_ref() {
+            if !token
+                .expires_at
+                .is_some_and(|at| Instant::now() + TOKEN_RENEW_MARGIN >= at)
+            {
+                return Ok(token.token.clone());
+            }
+        }
+
+        let response = self
+            .request(reqwest::Method::POST, &format!("auth/{}/login", mount))
+            .json(&serde_json::json!({ "role_id": role_id, "secret_id": secret_id }))
+            .send()
+            .await
+            .context("Failed to log in to Vault")?;
+        if !response.status().is_success() {
+            bail!("Vault AppRole login failed with status {}", response.status());
+        }
+        let login: LoginResponse = response.json().await.context("Invalid Vault login response")?;
+        debug!(
+            lease_duration = login.auth.lease_duration,
+            "Logged in to Vault with AppRole"
+        );
+
+        let token = login.auth.client_token;
+        *cached = Some(CachedToken {
+            token: token.clone(),
+            expires_at: (login.auth.lease_duration > 0)
+                .then(|| Instant::now() + Duration::from_secs(login.auth.lease_duration)),
+        });
+        Ok(token)
+    }
+
+    /// GET a Vault path; `None` on 404
+    async fn get<T: serde::de::DeserializeOwned>(&self, path: &str) -> Result<Option<T>> {
+        let response = self
+            .request(reqwest::Method::GET, path)
+            .header("X-Vault-Token", self.token().await?)
+            .send()
+            .await
+            .with_context(|| format!("Failed to read {} from Vault", path))?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => Ok(Some(
+                response
+                    .json()
+                    .await
+                    .with_context(|| format!("Invalid Vault response for {}", path))?,
+            )),
+            status => Err(anyhow!("Vault returned {} for {}", status, path)),
+        }
+    }
+
+    /// Value and version of the KV entry at `path`, or `None` when it doesn't exist or was deleted
+    #[instrument(skip(self))]
+    pub async fn read(&self, path: &str) -> Result<Option<(String, u64)>> {
+        let Some(response) = self
+            .get::<KvResponse>(&format!("{}/data/{}", self.config.kv_mount, path))
+            .await?
+        else {
+            return Ok(None);
+        };
+        let Some(data) = response.data.data else {
+            return Ok(None);
+        };
+        let value = match data.get(VALUE_FIELD) {
+            Some(Value::String(value)) => value.clone(),
+            Some(_) => bail!("Vault secret {} has a non-string {} field", path, VALUE_FIELD),
+            None => bail!("Vault secret {} has no {} field", path, VALUE_FIELD),
+        };
+        Ok(Some((value, response.data.metadata.version)))
+    }
+
+    /// Current version of the KV entry at `path`, read from its metadata
+    #[instrument(skip(self))]
+    pub async fn current_version(&self, path: &str) -> Result<Option<u64>> {
+        Ok(self
+            .get::<KvMetadataResponse>(&format!("{}/metadata/{}", self.config.kv_mount, path))
+            .await?
+            .map(|metadata| metadata.data.current_version))
+    }
+}
+
+/// KV path of a setting; KV paths don't start with a slash
+pub fn kv_path(namespace: &str, key: &str) -> String {
+    match namespace.trim_matches('/') {
+        "" => key.to_string(),
+        namespace => format!("{}/{}", namespace, key),
+    }
+}
+
+#[async_trait]
+impl SecretsProvider for VaultClient {
+    fn name(&self) -> &'static str {
+        VAULT_PROVIDER
+    }
+
+    async fn get_versioned_secret(&self, namespace: &str, key: &str) -> Result<Option<(String, String)>> {
+        Ok(self
+            .read(&kv_path(namespace, key))
+            .await?
+            .map(|(value, version)| (value, version.to_string())))
+    }
+
+    async fn current_version(&self, namespace: &str, key: &str) -> Result<Option<String>> {
+        Ok(VaultClient::current_version(self, &kv_path(namespace, key))
+            .await?
+            .map(|version| version.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kv_path() {
+        assert_eq!(kv_path("/origin/prod", "jwt-secret"), "origin/prod/jwt-secret");
+        assert_eq!(kv_path("", "jwt-secret"), "jwt-secret");
+    }
+
+    #[test]
+    fn test_kv_response() {
+        let body = r#"{"data": {"data": {"value": "s3cret"}, "metadata": {"version": 4, "deletion_time": ""}}}"#;
+        let response: KvResponse = serde_json::from_str(body).unwrap();
+        assert_eq!(response.data.metadata.version, 4);
+        assert_eq!(
+            response.data.data.unwrap().get(VALUE_FIELD),
+            Some(&Value::from("s3cret"))
+        );
+
+        let deleted = r#"{"data": {"data": null, "metadata": {"version": 5}}}"#;
+        assert!(serde_json::from_str::<KvResponse>(deleted).unwrap().data.data.is_none());
+    }
+
+    #[test]
+    fn test_auth_debug_hides_credentials() {
+        let auth = VaultAuth::AppRole {
+            mount: "approle".to_string(),
+            role_id: "role".to_string(),
+            secret_id: "hunter2".to_string(),
+        };
+        assert!(!format!("{:?}", auth).contains("hunter2"));
+        assert_eq!(format!("{:?}", VaultAuth::Token("hvs.abc".to_string())), "Token");
+    }
+}
@@ -0,0 +1,326 @@
+// Command line of the server binary: `serve` runs the servers, the other subcommands run one
+// operational task with the same settings and adapters and exit
+use crate::adapter::bank_data::BankDataProviders;
+use crate::adapter::coinbase::CoinbaseClient;
+use crate::adapter::email_templates::{EmailLocale, EmailTemplateName};
+use crate::adapter::encryption::EnvelopeCipher;
+use crate::adapter::mailer::Mailer;
+use crate::adapter::plaid::PlaidClient;
+use crate::dedup::TransactionDeduplicator;
+use crate::jobs::scheduler::Job;
+use crate::jobs::{RemovedItemPurgeJob, SyncCoordinator};
+use crate::model::audit_log::AuditLogRepository;
+use crate::model::auth_session::AuthSessionRepository;
+use crate::model::bank_account::BankAccountRepository;
+use crate::model::email_template::EmailTemplateRepository;
+use crate::model::otp::OtpRepository;
+use crate::model::plaid_item::{PlaidItemRepository, PlaidItemStatus};
+use crate::model::transaction::TransactionRepository;
+use crate::model::transaction_sync::TransactionSyncer;
+use crate::model::user::{CreateUserRequest, User, UserRepository};
+use crate::settings::{Settings, LOCAL_ENVIRONMENT};
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{Parser, Subcommand};
+use serde_json::json;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::info;
+use uuid::Uuid;
+
+/// Email of the user `seed` creates when none is given
+const DEFAULT_SEED_EMAIL: &str = "demo@example.com";
+
+/// Origin backend
+#[derive(Debug, Parser)]
+#[command(version)]
+pub struct Cli {
+    /// Task to run; the servers when none is given
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+pub enum Command {
+    /// Run the gRPC server and background jobs
+    Serve,
+    /// Apply pending database migrations
+    Migrate,
+    /// Create a demo user to sign in with locally; refused outside ENVIRONMENT=local
+    Seed {
+        /// Email the demo user signs in with
+        #[arg(long, default_value = DEFAULT_SEED_EMAIL)]
+        email: String,
+    },
+    /// Grant the admin role to the user with an email, creating the user if needed
+    CreateAdmin {
+        #[arg(long)]
+        email: String,
+        /// Display name of a new user (default: the part of the email before the @)
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// Delete expired sign-in codes and sessions, and purge removed items past REMOVED_ITEM_RETENTION_DAYS
+    CleanupExpired,
+    /// Send a template filled with sample data through the configured email transport
+    SendTestEmail {
+        #[arg(long)]
+        to: String,
+        /// Template to send: otp_login, verification, notification or weekly_digest
+        #[arg(long, default_value = "notification")]
+        template: String,
+        /// Language tag of the translation to send
+        #[arg(long, default_value = "en")]
+        locale: String,
+    },
+    /// Sync one linked item's accounts and transactions now
+    SyncPlaid {
+        /// Provider item ID
+        #[arg(long)]
+        item: String,
+    },
+}
+
+/// Run an operational task; `serve` is run by the binary itself
+pub async fn run(command: Command, settings: &Settings) -> Result<()> {
+    match command {
+        Command::Serve => bail!("serve is not an operational task"),
+        Command::Migrate => migrate(settings).await,
+        Command::Seed { email } => seed(settings, &email).await,
+        Command::CreateAdmin { email, name } => create_admin(settings, &email, name).await,
+        Command::CleanupExpired => cleanup_expired(settings).await,
+        Command::SendTestEmail { to, template, locale } => send_test_email(settings, &to, &template, &locale).await,
+        Command::SyncPlaid { item } => sync_plaid(settings, &item).await,
+    }
+}
+
+async fn connect(settings: &Settings) -> Result<PgPool> {
+    settings
+        .database_pool
+        .connect("primary", &settings.database_url, false)
+        .await
+}
+
+fn token_cipher(settings: &Settings) -> Result<Arc<EnvelopeCipher>> {
+    let cipher = EnvelopeCipher::new(settings.token_encryption.key_id.clone(), &settings.token_encryption.key)?;
+    Ok(Arc::new(cipher))
+}
+
+fn plaid_items(pool: &PgPool, settings: &Settings) -> Result<PlaidItemRepository> {
+    Ok(PlaidItemRepository::new(pool.clone(), token_cipher(settings)?))
+}
+
+async fn migrate(settings: &Settings) -> Result<()> {
+    let pool = connect(settings).await?;
+    let migrator = sqlx::migrate!("./migrations");
+    migrator.run(&pool).await.context("Failed to apply migrations")?;
+    info!(migrations = migrator.iter().count(), "Database migrations applied");
+    Ok(())
+}
+
+async fn seed(settings: &Settings, email: &str) -> Result<()> {
+    if settings.environment != LOCAL_ENVIRONMENT {
+        bail!(
+            "seed only runs with ENVIRONMENT={}, not {}",
+            LOCAL_ENVIRONMENT,
+            settings.environment
+        );
+    }
+    let pool = connect(settings).await?;
+    let (user, created) = find_or_create_user(&UserRepository::new(pool), email, None).await?;
+    info!(user_id = %user.id, created, "Demo user ready; sign in with an email code");
+    Ok(())
+}
+
+async fn create_admin(settings: &Settings, email: &str, name: Option<String>) -> Result<()> {
+    let pool = connect(settings).await?;
+    let users = UserRepository::new(pool.clone());
+    let (user, created) = find_or_create_user(&users, email, name).await?;
+    if user.is_admin {
+        info!(user_id = %user.id, created, "User is already an admin");
+        return Ok(());
+    }
+
+    users
+        .set_admin(user.id, true)
+        .await?
+        .ok_or_else(|| anyhow!("User {} was deleted", user.id))?;
+    AuditLogRepository::new(pool)
+        .record(
+            user.id,
+            "user.admin_granted",
+            "user",
+            &user.id.to_string(),
+            json!({ "source": "cli" }),
+        )
+        .await?;
+    info!(user_id = %user.id, created, "Admin role granted");
+    Ok(())
+}
+
+async fn cleanup_expired(settings: &Settings) -> Result<()> {
+    let pool = connect(settings).await?;
+    let codes = OtpRepository::new(pool.clone(), token_cipher(settings)?)
+        .cleanup_expired_codes()
+        .await?;
+    info!(deleted = codes, "Expired sign-in codes deleted");
+    let sessions = AuthSessionRepository::new(pool.clone()).delete_expired().await?;
+    info!(deleted = sessions, "Expired sessions deleted");
+
+    // The same purge the scheduled job runs
+    let purge = RemovedItemPurgeJob::new(plaid_items(&pool, settings)?, settings.jobs.removed_item_retention_days);
+    purge.run().await
+}
+
+async fn send_test_email(settings: &Settings, to: &str, template: &str, locale: &str) -> Result<()> {
+    let to = to.trim();
+    if !to.contains('@') {
+        bail!("Invalid recipient");
+    }
+    let name = EmailTemplateName::parse(template).ok_or_else(|| anyhow!("Unknown email template '{}'", template))?;
+
+    // Sent with the active template versions, as the servers send them
+    let pool = connect(settings).await?;
+    let mailer = Mailer::from_settings(&settings.email)
+        .await?
+        .with_templates(Arc::new(EmailTemplateRepository::new(pool)));
+    let response = mailer
+        .send_template_email(to, name, name.sample_data(), EmailLocale::parse(locale), None)
+        .await
+        .context("Failed to send test email")?;
+    info!(
+        transport = mailer.transport_name(),
+        template = name.as_str(),
+        message_id = %response.message_id,
+        "Test email sent"
+    );
+    Ok(())
+}
+
+async fn sync_plaid(settings: &Settings, item_id: &str) -> Result<()> {
+    let pool = connect(settings).await?;
+    let items = plaid_items(&pool, settings)?;
+    let item = items
+        .find_by_item_id(item_id)
+        .await?
+        .ok_or_else(|| anyhow!("No item '{}'", item_id))?;
+    if item.status() == PlaidItemStatus::Removed {
+        bail!("Item '{}' was removed", item_id);
+    }
+
+    let providers = BankDataProviders::new(Arc::new(PlaidClient::new(settings.plaid.clone())?))
+        .with_provider(Arc::new(CoinbaseClient::new(settings.coinbase.clone())?))
+        .with_regions(&settings.bank_data_provider_regions)?;
+    let transactions = TransactionRepository::new(pool.clone());
+    // Synced as the scheduled job syncs it, recording the sync status and merging duplicates
+    let coordinator = SyncCoordinator::new(
+        TransactionSyncer::new(
+            providers,
+            items.clone(),
+            transactions.clone(),
+            BankAccountRepository::new(pool.clone()),
+        ),
+        items,
+        1,
+    )
+    .with_dedup(TransactionDeduplicator::new(
+        transactions,
+        AuditLogRepository::new(pool.clone()),
+    ));
+
+    let outcome = coordinator.sync_item(&item).await;
+    if let Some(error) = outcome.error {
+        bail!("Sync of item '{}' failed: {}", item_id, error);
+    }
+    info!(
+        item_id,
+        added = outcome.summary.added,
+        modified = outcome.summary.modified,
+        removed = outcome.summary.removed,
+        "Item synced"
+    );
+    Ok(())
+}
+
+/// The user with `email`, created as a sign-in-code user when there is none; returns whether it
+/// was created
+async fn find_or_create_user(users: &UserRepository, email: &str, name: Option<String>) -> Result<(User, bool)> {
+    let email = email.trim().to_lowercase();
+    if !email.contains('@') {
+        bail!("Invalid email");
+    }
+    if let Some(user) = users.find_by_email(&email).await? {
+        return Ok((user, false));
+    }
+    let name = name.unwrap_or_else(|| default_name(&email));
+    let user = users
+        .create_user(CreateUserRequest {
+            // Users signing in with an email code get a placeholder Google ID, as in the auth service
+            google_id: format!("otp_{}", Uuid::new_v4()),
+            email,
+            name,
+            picture_url: None,
+            locale: None,
+        })
+        .await?;
+    Ok((user, true))
+}
+
+/// Display name for a user created from an email
+fn default_name(email: &str) -> String {
+    email.split('@').next().unwrap_or_default().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_serves_without_subcommand() {
+        let cli = Cli::try_parse_from(["template"]).unwrap();
+        assert_eq!(cli.command, None);
+    }
+
+    #[test]
+    fn test_parse_subcommands() {
+        let cli = Cli::try_parse_from(["template", "sync-plaid", "--item", "item_123"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Some(Command::SyncPlaid {
+                item: "item_123".to_string()
+            })
+        );
+
+        let cli = Cli::try_parse_from(["template", "send-test-email", "--to", "ops@example.com"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Some(Command::SendTestEmail {
+                to: "ops@example.com".to_string(),
+                template: "notification".to_string(),
+                locale: "en".to_string(),
+            })
+        );
+
+        assert!(Cli::try_parse_from(["template", "sync-plaid"]).is_err());
+        let cli = Cli::try_parse_from(["template", "create-admin", "--email", "ops@example.com"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Some(Command::CreateAdmin {
+                email: "ops@example.com".to_string(),
+                name: None,
+            })
+        );
+
+        assert!(Cli::try_parse_from(["template", "create-admin"]).is_err());
+    }
+
+    #[test]
+    fn test_default_name() {
+        assert_eq!(default_name("jane.doe@example.com"), "jane.doe");
+    }
+}
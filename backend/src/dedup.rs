@@ -0,0 +1,276 @@
+// Duplicate transaction merging after sync and import
+use crate::import::{DUPLICATE_DATE_TOLERANCE_DAYS, IMPORTED_ID_PREFIX};
+use crate::model::audit_log::AuditLogRepository;
+use crate::model::bill::normalize_payee;
+use crate::model::plaid_item::PlaidItem;
+use crate::model::transaction::{Transaction, TransactionFilter, TransactionRepository};
+use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use tracing::{info, instrument, warn};
+use uuid::Uuid;
+
+/// Most transactions examined in one pass
+const DEDUP_WINDOW_LIMIT: i64 = 10_000;
+
+/// Why two transactions were merged
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeReason {
+    /// A pending transaction whose posted version is also stored
+    PendingPosted,
+    /// The same purchase from two sources: same account, amount and merchant within a few days
+    NearDuplicate,
+}
+
+impl MergeReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MergeReason::PendingPosted => "pending_posted",
+            MergeReason::NearDuplicate => "near_duplicate",
+        }
+    }
+}
+
+/// Duplicate to fold into the transaction that is kept
+#[derive(Debug, Clone, PartialEq)]
+pub struct Merge {
+    pub kept: String,
+    pub merged: String,
+    pub reason: MergeReason,
+}
+
+fn is_imported(transaction: &Transaction) -> bool {
+    transaction.transaction_id.starts_with(IMPORTED_ID_PREFIX)
+}
+
+/// Whether two transactions name the same payee; one name may extend the other
+/// (`"starbucks"` and `"starbucks store"`), since sources describe merchants differently
+fn same_merchant(a: &Transaction, b: &Transaction) -> bool {
+    let names = |t: &Transaction| -> Vec<String> {
+        [t.merchant_name.as_deref(), Some(t.name.as_str())]
+            .into_iter()
+            .flatten()
+            .map(normalize_payee)
+            .filter(|name| !name.is_empty())
+            .collect()
+    };
+    let extends = |long: &str, short: &str| long == short || long.starts_with(&format!("{} ", short));
+
+    let b_names = names(b);
+    names(a)
+        .iter()
+        .any(|a_name| b_names.iter().any(|b_name| extends(a_name, b_name) || extends(b_name, a_name)))
+}
+
+/// Duplicates among a user's live transactions.
+///
+/// A pending transaction is merged into the posted transaction that references it. An imported
+/// transaction is merged into a Plaid one with the same account, amount and merchant within
+/// `DUPLICATE_DATE_TOLERANCE_DAYS`; each Plaid transaction absorbs at most one import so repeated
+/// identical purchases are kept. Transactions from the same source are never near-duplicates.
+pub fn find_merges(transactions: &[Transaction]) -> Vec<Merge> {
+    let live: Vec<&Transaction> = transactions.iter().filter(|t| t.removed_at.is_none()).collect();
+    let by_id: HashMap<&str, &Transaction> = live.iter().map(|t| (t.transaction_id.as_str(), *t)).collect();
+    let cents = |amount: f64| (amount * 100.0).round() as i64;
+
+    let mut merges = Vec::new();
+    let mut merged: HashSet<&str> = HashSet::new();
+
+    for posted in live.iter().filter(|t| !t.pending) {
+        let Some(pending_id) = posted.pending_transaction_id.as_deref() else { continue };
+        if by_id.get(pending_id).is_some_and(|t| t.pending) && merged.insert(pending_id) {
+            merges.push(Merge {
+                kept: posted.transaction_id.clone(),
+                merged: pending_id.to_string(),
+                reason: MergeReason::PendingPosted,
+            });
+        }
+    }
+
+    let mut absorbed: HashSet<&str> = HashSet::new();
+    for imported in live.iter().filter(|t| is_imported(t)) {
+        if merged.contains(imported.transaction_id.as_str()) {
+            continue;
+        }
+        let candidate = live
+            .iter()
+            .filter(|t| {
+                !is_imported(t)
+                    && !merged.contains(t.transaction_id.as_str())
+                    && !absorbed.contains(t.transaction_id.as_str())
+                    && t.account_id == imported.account_id
+                    && cents(t.amount) == cents(imported.amount)
+                    && (t.date - imported.date).num_days().abs() <= DUPLICATE_DATE_TOLERANCE_DAYS
+                    && same_merchant(t, imported)
+            })
+            .min_by_key(|t| ((t.date - imported.date).num_days().abs(), t.pending));
+        if let Some(kept) = candidate {
+            absorbed.insert(kept.transaction_id.as_str());
+            merged.insert(imported.transaction_id.as_str());
+            merges.push(Merge {
+                kept: kept.transaction_id.clone(),
+                merged: imported.transaction_id.clone(),
+                reason: MergeReason::NearDuplicate,
+            });
+        }
+    }
+
+    merges
+}
+
+/// Finds and merges duplicate transactions, recording each merge in the audit log
+#[derive(Clone)]
+pub struct TransactionDeduplicator {
+    transactions: TransactionRepository,
+    audit_log: AuditLogRepository,
+}
+
+impl TransactionDeduplicator {
+    pub fn new(transactions: TransactionRepository, audit_log: AuditLogRepository) -> Self {
+        Self { transactions, audit_log }
+    }
+
+    /// Merge duplicates among a user's transactions dated between two days (widened by the
+    /// duplicate tolerance); `source` names the caller in the audit log. Returns the merges applied.
+    #[instrument(skip(self))]
+    pub async fn dedup_window(
+        &self,
+        user_id: Uuid,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+        source: &str,
+    ) -> Result<usize> {
+        let tolerance = Duration::days(DUPLICATE_DATE_TOLERANCE_DAYS);
+        let filter = TransactionFilter {
+            start_date: Some(start_date - tolerance),
+            end_date: Some(end_date + tolerance),
+            limit: DEDUP_WINDOW_LIMIT,
+            ..Default::default()
+        };
+        let transactions = self.transactions.list_by_user(user_id, &filter).await?;
+        if transactions.len() as i64 == DEDUP_WINDOW_LIMIT {
+            warn!(limit = DEDUP_WINDOW_LIMIT, "Dedup window truncated");
+        }
+
+        let mut applied = 0;
+        for merge in find_merges(&transactions) {
+            if !self.transactions.merge_duplicate(user_id, &merge.kept, &merge.merged).await? {
+                continue;
+            }
+            applied += 1;
+            let metadata = json!({
+                "merged_transaction_id": merge.merged,
+                "reason": merge.reason.as_str(),
+                "source": source,
+            });
+            if let Err(e) = self
+                .audit_log
+                .record(user_id, "transaction.merged", "transaction", &merge.kept, metadata)
+                .await
+            {
+                warn!(kept = %merge.kept, merged = %merge.merged, error = %e, "Failed to audit transaction merge");
+            }
+        }
+
+        if applied > 0 {
+            info!(applied, "Merged duplicate transactions");
+        }
+        Ok(applied)
+    }
+
+    /// Merge duplicates around the transactions an item sync stored since `since`
+    #[instrument(skip(self, item), fields(item_id = %item.item_id))]
+    pub async fn dedup_item(&self, item: &PlaidItem, since: DateTime<Utc>) -> Result<usize> {
+        let added = self.transactions.list_added_since(&item.item_id, since).await?;
+        match (added.iter().map(|t| t.date).min(), added.iter().map(|t| t.date).max()) {
+            (Some(start_date), Some(end_date)) => self.dedup_window(item.user_id, start_date, end_date, "sync").await,
+            _ => Ok(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transaction(id: &str, name: &str, day: u32, amount: f64, pending: bool) -> Transaction {
+        Transaction {
+            id: Uuid::new_v4(),
+            transaction_id: id.to_string(),
+            account_id: "acc_1".to_string(),
+            item_id: "item_1".to_string(),
+            user_id: Uuid::new_v4(),
+            amount,
+            iso_currency_code: Some("USD".to_string()),
+            unofficial_currency_code: None,
+            date: NaiveDate::from_ymd_opt(2024, 3, day).unwrap(),
+            datetime: None,
+            authorized_date: None,
+            authorized_datetime: None,
+            name: name.to_string(),
+            merchant_name: None,
+            original_description: None,
+            category: vec![],
+            category_id: None,
+            check_number: None,
+            location: None,
+            payment_meta: None,
+            pending,
+            pending_transaction_id: None,
+            account_owner: None,
+            transaction_type: "place".to_string(),
+            transaction_code: None,
+            removed_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_pending_merged_into_posted() {
+        let pending = transaction("txn_pending", "Coffee", 1, 4.5, true);
+        let mut posted = transaction("txn_posted", "Coffee", 2, 4.75, false);
+        posted.pending_transaction_id = Some("txn_pending".to_string());
+
+        assert_eq!(
+            find_merges(&[pending.clone(), posted.clone()]),
+            vec![Merge {
+                kept: "txn_posted".to_string(),
+                merged: "txn_pending".to_string(),
+                reason: MergeReason::PendingPosted,
+            }]
+        );
+
+        let mut removed = pending;
+        removed.removed_at = Some(Utc::now());
+        assert!(find_merges(&[removed, posted]).is_empty());
+    }
+
+    #[test]
+    fn test_import_merged_into_plaid_transaction() {
+        let mut plaid = transaction("txn_1", "STARBUCKS STORE 1234", 3, 4.5, false);
+        plaid.merchant_name = Some("Starbucks".to_string());
+        let imported = transaction("import_a", "Starbucks Store #1234", 4, 4.5, false);
+        let repeat = transaction("import_b", "Starbucks Store #1234", 4, 4.5, false);
+        let other = transaction("import_c", "Blue Bottle", 3, 4.5, false);
+
+        let merges = find_merges(&[plaid, imported, repeat, other]);
+        assert_eq!(
+            merges,
+            vec![Merge {
+                kept: "txn_1".to_string(),
+                merged: "import_a".to_string(),
+                reason: MergeReason::NearDuplicate,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_same_source_never_near_duplicate() {
+        let first = transaction("txn_1", "Coffee", 3, 4.5, false);
+        let second = transaction("txn_2", "Coffee", 3, 4.5, false);
+        let late_import = transaction("import_a", "Coffee", 8, 4.5, false);
+        assert!(find_merges(&[first, second, late_import]).is_empty());
+    }
+}
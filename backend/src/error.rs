@@ -0,0 +1,250 @@
+use crate::request_id;
+use std::collections::HashMap;
+use std::time::Duration;
+use thiserror::Error;
+use tonic::{Code, Status};
+use tonic_types::{ErrorDetails, StatusExt};
+use tracing::error;
+
+/// Error domain attached to every `google.rpc.ErrorInfo` detail
+pub const ERROR_DOMAIN: &str = "origin.api";
+
+/// Crate-wide application error, converted into a `tonic::Status` at the RPC boundary
+#[derive(Debug, Error)]
+pub enum AppError {
+    /// The request was malformed or failed validation
+    #[error("{0}")]
+    Validation(String),
+    /// The requested resource does not exist
+    #[error("{0}")]
+    NotFound(String),
+    /// Missing or invalid credentials
+    #[error("{0}")]
+    Unauthorized(String),
+    /// Authenticated but not allowed to perform the operation
+    #[error("{0}")]
+    PermissionDenied(String),
+    /// The resource already exists or is in a conflicting state
+    #[error("{0}")]
+    Conflict(String),
+    /// The caller exceeded a rate limit or quota
+    #[error("{message}")]
+    RateLimited {
+        message: String,
+        retry_after: Option<Duration>,
+    },
+    /// An upstream provider (Google, Plaid, SES, Claude, ...) failed
+    #[error("{provider}: {message}")]
+    Upstream { provider: String, message: String },
+    /// Database failure
+    #[error("database error: {0}")]
+    Database(#[from] sqlx::Error),
+    /// Redis failure
+    #[error("redis error: {0}")]
+    Redis(#[from] redis::RedisError),
+    /// Redis connection pool failure
+    #[error("redis pool error: {0}")]
+    RedisPool(#[from] deadpool_redis::PoolError),
+    /// Internal failure with a client-safe message
+    #[error("{0}")]
+    Internal(String),
+    /// Unexpected failure bubbled up from an adapter
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+pub type AppResult<T> = std::result::Result<T, AppError>;
+
+impl AppError {
+    pub fn validation<M: Into<String>>(message: M) -> Self {
+        Self::Validation(message.into())
+    }
+
+    pub fn not_found<M: Into<String>>(message: M) -> Self {
+        Self::NotFound(message.into())
+    }
+
+    pub fn unauthorized<M: Into<String>>(message: M) -> Self {
+        Self::Unauthorized(message.into())
+    }
+
+    pub fn permission_denied<M: Into<String>>(message: M) -> Self {
+        Self::PermissionDenied(message.into())
+    }
+
+    pub fn conflict<M: Into<String>>(message: M) -> Self {
+        Self::Conflict(message.into())
+    }
+
+    pub fn rate_limited<M: Into<String>>(message: M, retry_after: Option<Duration>) -> Self {
+        Self::RateLimited {
+            message: message.into(),
+            retry_after,
+        }
+    }
+
+    pub fn upstream<P: Into<String>, M: Into<String>>(provider: P, message: M) -> Self {
+        Self::Upstream {
+            provider: provider.into(),
+            message: message.into(),
+        }
+    }
+
+    pub fn internal<M: Into<String>>(message: M) -> Self {
+        Self::Internal(message.into())
+    }
+
+    /// gRPC status code for this error
+    pub fn code(&self) -> Code {
+        match self {
+            AppError::Validation(_) => Code::InvalidArgument,
+            AppError::NotFound(_) => Code::NotFound,
+            AppError::Unauthorized(_) => Code::Unauthenticated,
+            AppError::PermissionDenied(_) => Code::PermissionDenied,
+            AppError::Conflict(_) => Code::AlreadyExists,
+            AppError::RateLimited { .. } => Code::ResourceExhausted,
+            AppError::Upstream { .. } => Code::Unavailable,
+            AppError::Database(sqlx::Error::RowNotFound) => Code::NotFound,
+            AppError::Database(_)
+            | AppError::Redis(_)
+            | AppError::RedisPool(_)
+            | AppError::Internal(_)
+            | AppError::Other(_) => Code::Internal,
+        }
+    }
+
+    /// Machine-readable reason used in `ErrorInfo.reason`
+    pub fn reason(&self) -> &'static str {
+        match self {
+            AppError::Validation(_) => "VALIDATION_FAILED",
+            AppError::NotFound(_) => "NOT_FOUND",
+            AppError::Unauthorized(_) => "UNAUTHENTICATED",
+            AppError::PermissionDenied(_) => "PERMISSION_DENIED",
+            AppError::Conflict(_) => "CONFLICT",
+            AppError::RateLimited { .. } => "RATE_LIMITED",
+            AppError::Upstream { .. } => "UPSTREAM_FAILURE",
+            AppError::Database(sqlx::Error::RowNotFound) => "NOT_FOUND",
+            AppError::Database(_) => "DATABASE_ERROR",
+            AppError::Redis(_) | AppError::RedisPool(_) => "CACHE_ERROR",
+            AppError::Internal(_) | AppError::Other(_) => "INTERNAL",
+        }
+    }
+
+    /// Message safe to return to clients (never leaks driver or adapter internals)
+    pub fn client_message(&self) -> String {
+        match self {
+            AppError::Validation(m)
+            | AppError::NotFound(m)
+            | AppError::Unauthorized(m)
+            | AppError::PermissionDenied(m)
+            | AppError::Conflict(m)
+            | AppError::Internal(m) => m.clone(),
+            AppError::RateLimited { message, .. } => message.clone(),
+            AppError::Upstream { message, .. } => message.clone(),
+            AppError::Database(sqlx::Error::RowNotFound) => "Resource not found".to_string(),
+            AppError::Database(_) => "Database operation failed".to_string(),
+            AppError::Redis(_) | AppError::RedisPool(_) => "Cache operation failed".to_string(),
+            AppError::Other(_) => "Internal server error".to_string(),
+        }
+    }
+
+    fn metadata(&self) -> HashMap<String, String> {
+        let mut metadata = HashMap::new();
+        match self {
+            AppError::Upstream { provider, .. } => {
+                metadata.insert("provider".to_string(), provider.clone());
+            }
+            AppError::RateLimited {
+                retry_after: Some(retry_after),
+                ..
+            } => {
+                metadata.insert(
+                    "retry_after_seconds".to_string(),
+                    retry_after.as_secs().to_string(),
+                );
+            }
+            _ => {}
+        }
+        metadata
+    }
+}
+
+impl From<AppError> for Status {
+    fn from(err: AppError) -> Self {
+        let code = err.code();
+        if code == Code::Internal {
+            error!(error = %err, "Internal error returned to client");
+        }
+
+        let mut details = ErrorDetails::with_error_info(err.reason(), ERROR_DOMAIN, err.metadata());
+        if let AppError::RateLimited {
+            retry_after: Some(retry_after),
+            ..
+        } = &err
+        {
+            details.set_retry_info(Some(*retry_after));
+        }
+        // Clients show this to users so support can find the call in the logs
+        if let Some(request_id) = request_id::current() {
+            details.set_request_info(request_id, "");
+        }
+
+        Status::with_error_details(code, err.client_message(), details)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_codes() {
+        assert_eq!(AppError::validation("bad").code(), Code::InvalidArgument);
+        assert_eq!(AppError::not_found("missing").code(), Code::NotFound);
+        assert_eq!(AppError::unauthorized("nope").code(), Code::Unauthenticated);
+        assert_eq!(AppError::rate_limited("slow down", None).code(), Code::ResourceExhausted);
+        assert_eq!(AppError::upstream("plaid", "down").code(), Code::Unavailable);
+        assert_eq!(AppError::from(sqlx::Error::RowNotFound).code(), Code::NotFound);
+        assert_eq!(AppError::from(anyhow::anyhow!("boom")).code(), Code::Internal);
+    }
+
+    #[test]
+    fn test_internal_errors_do_not_leak_details() {
+        let err = AppError::from(anyhow::anyhow!("connection string postgres://secret"));
+        assert_eq!(err.client_message(), "Internal server error");
+    }
+
+    #[test]
+    fn test_status_carries_error_info() {
+        let status: Status = AppError::upstream("plaid", "Bank connection failed").into();
+        assert_eq!(status.code(), Code::Unavailable);
+        assert_eq!(status.message(), "Bank connection failed");
+
+        let info = status.get_details_error_info().expect("error info detail");
+        assert_eq!(info.reason, "UPSTREAM_FAILURE");
+        assert_eq!(info.domain, ERROR_DOMAIN);
+        assert_eq!(info.metadata.get("provider"), Some(&"plaid".to_string()));
+    }
+
+    #[test]
+    fn test_rate_limited_status_carries_retry_info() {
+        let status: Status =
+            AppError::rate_limited("Too many requests", Some(Duration::from_secs(30))).into();
+        assert_eq!(status.code(), Code::ResourceExhausted);
+
+        let retry = status.get_details_retry_info().expect("retry info detail");
+        assert_eq!(retry.retry_delay, Some(Duration::from_secs(30)));
+    }
+
+    #[tokio::test]
+    async fn test_status_carries_request_id() {
+        let status = request_id::RequestId("req-9".to_string())
+            .scope(async { Status::from(AppError::internal("Failed")) })
+            .await;
+        let info = status.get_details_request_info().expect("request info detail");
+        assert_eq!(info.request_id, "req-9");
+
+        let status: Status = AppError::internal("Failed").into();
+        assert!(status.get_details_request_info().is_none());
+    }
+}
@@ -0,0 +1,216 @@
+// In-process domain events, so reactions to a change (emails, syncs) live outside the RPC that made it
+pub mod reactions;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tokio::task::JoinHandle;
+use tracing::{warn, Instrument, Span};
+use uuid::Uuid;
+
+/// Something that happened in the domain, delivered to every handler subscribed to its type
+pub trait Event: Clone + Send + Sync + 'static {
+    /// Name used in logs
+    const NAME: &'static str;
+}
+
+/// Reaction to one type of event
+#[async_trait]
+pub trait EventHandler<E: Event>: Send + Sync {
+    /// Name used in logs
+    fn name(&self) -> &'static str;
+
+    async fn handle(&self, event: E) -> Result<()>;
+}
+
+type Handlers<E> = Vec<Arc<dyn EventHandler<E>>>;
+
+/// Typed in-process event bus.
+///
+/// Each handler runs on its own task, so publishing never waits on a reaction and a failed
+/// reaction doesn't fail the call that published the event. Events aren't persisted: reactions
+/// that must survive a restart belong in a job.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    /// `Handlers<E>` of each event type, keyed by the type
+    handlers: Arc<RwLock<HashMap<TypeId, Box<dyn Any + Send + Sync>>>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `handler` for every `E` published from now on
+    pub fn subscribe<E: Event>(&self, handler: Arc<dyn EventHandler<E>>) {
+        let mut handlers = self.handlers.write().unwrap_or_else(|e| e.into_inner());
+        handlers
+            .entry(TypeId::of::<E>())
+            .or_insert_with(|| Box::new(Handlers::<E>::new()))
+            .downcast_mut::<Handlers<E>>()
+            .expect("handlers are keyed by their event type")
+            .push(handler);
+    }
+
+    /// Number of handlers subscribed to `E`
+    pub fn subscriber_count<E: Event>(&self) -> usize {
+        self.handlers_for::<E>().len()
+    }
+
+    /// Hand `event` to every handler subscribed to its type, returning how many there were
+    pub fn publish<E: Event>(&self, event: E) -> usize {
+        self.dispatch(event).len()
+    }
+
+    fn handlers_for<E: Event>(&self) -> Handlers<E> {
+        let handlers = self.handlers.read().unwrap_or_else(|e| e.into_inner());
+        handlers
+            .get(&TypeId::of::<E>())
+            .and_then(|handlers| handlers.downcast_ref::<Handlers<E>>())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn dispatch<E: Event>(&self, event: E) -> Vec<JoinHandle<()>> {
+        self.handlers_for::<E>()
+            .into_iter()
+            .map(|handler| {
+                let event = event.clone();
+                // Keep the publisher's request and trace IDs on the reaction's logs
+                tokio::spawn(
+                    async move {
+                        if let Err(e) = handler.handle(event).await {
+                            warn!(event = E::NAME, handler = handler.name(), error = %e, "Event handler failed");
+                        }
+                    }
+                    .instrument(Span::current()),
+                )
+            })
+            .collect()
+    }
+}
+
+/// A user signed up, through Google or an email code
+#[derive(Debug, Clone)]
+pub struct UserCreated {
+    pub user_id: Uuid,
+    pub email: String,
+    pub name: String,
+    /// Language tag the user's emails are written in
+    pub locale: String,
+}
+
+impl Event for UserCreated {
+    const NAME: &'static str = "user_created";
+}
+
+/// A user connected a bank item
+#[derive(Debug, Clone)]
+pub struct ItemLinked {
+    pub user_id: Uuid,
+    pub item_id: String,
+    /// Bank data provider the item belongs to
+    pub provider: String,
+}
+
+impl Event for ItemLinked {
+    const NAME: &'static str = "item_linked";
+}
+
+/// A sync of a bank item stored new transactions
+#[derive(Debug, Clone)]
+pub struct ItemSynced {
+    pub user_id: Uuid,
+    pub item_id: String,
+    /// When the sync started; transactions stored since are the ones it added
+    pub started_at: DateTime<Utc>,
+    pub added: usize,
+}
+
+impl Event for ItemSynced {
+    const NAME: &'static str = "item_synced";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recorder {
+        seen: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl EventHandler<ItemLinked> for Recorder {
+        fn name(&self) -> &'static str {
+            "recorder"
+        }
+
+        async fn handle(&self, event: ItemLinked) -> Result<()> {
+            self.seen.lock().unwrap().push(event.item_id);
+            Ok(())
+        }
+    }
+
+    struct Failing;
+
+    #[async_trait]
+    impl EventHandler<ItemLinked> for Failing {
+        fn name(&self) -> &'static str {
+            "failing"
+        }
+
+        async fn handle(&self, _event: ItemLinked) -> Result<()> {
+            Err(anyhow!("boom"))
+        }
+    }
+
+    fn item_linked(item_id: &str) -> ItemLinked {
+        ItemLinked {
+            user_id: Uuid::new_v4(),
+            item_id: item_id.to_string(),
+            provider: "plaid".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_publish_reaches_every_handler_of_the_type() {
+        let bus = EventBus::new();
+        let first = Arc::new(Recorder::default());
+        let second = Arc::new(Recorder::default());
+        bus.subscribe::<ItemLinked>(first.clone());
+        bus.subscribe::<ItemLinked>(second.clone());
+        assert_eq!(bus.subscriber_count::<ItemLinked>(), 2);
+        assert_eq!(bus.subscriber_count::<UserCreated>(), 0);
+
+        for task in bus.dispatch(item_linked("item-1")) {
+            task.await.unwrap();
+        }
+        assert_eq!(*first.seen.lock().unwrap(), vec!["item-1"]);
+        assert_eq!(*second.seen.lock().unwrap(), vec!["item-1"]);
+    }
+
+    #[tokio::test]
+    async fn test_failed_handler_does_not_affect_others() {
+        let bus = EventBus::new();
+        let recorder = Arc::new(Recorder::default());
+        bus.subscribe::<ItemLinked>(Arc::new(Failing));
+        bus.subscribe::<ItemLinked>(recorder.clone());
+
+        for task in bus.dispatch(item_linked("item-2")) {
+            task.await.unwrap();
+        }
+        assert_eq!(*recorder.seen.lock().unwrap(), vec!["item-2"]);
+    }
+
+    #[tokio::test]
+    async fn test_publish_without_subscribers() {
+        let bus = EventBus::new();
+        assert_eq!(bus.publish(item_linked("item-3")), 0);
+    }
+}
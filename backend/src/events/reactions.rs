@@ -0,0 +1,168 @@
+// Reactions subscribed to the event bus at startup
+use crate::adapter::email_templates::{EmailLocale, EmailTemplateName};
+use crate::adapter::ses::TemplateData;
+use crate::events::{EventHandler, ItemLinked, ItemSynced, UserCreated};
+use crate::jobs::SyncCoordinator;
+use crate::model::email_queue::EmailQueueRepository;
+use crate::model::plaid_item::PlaidItemRepository;
+use crate::model::transaction::{Transaction, TransactionRepository};
+use crate::model::webhook::{event_payload, WebhookEventType, WebhookRepository};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::{json, Value};
+use tracing::{debug, info};
+use uuid::Uuid;
+
+/// Queues a welcome email for each new user
+pub struct WelcomeEmail {
+    queue: EmailQueueRepository,
+}
+
+impl WelcomeEmail {
+    pub fn new(queue: EmailQueueRepository) -> Self {
+        Self { queue }
+    }
+}
+
+#[async_trait]
+impl EventHandler<UserCreated> for WelcomeEmail {
+    fn name(&self) -> &'static str {
+        "welcome_email"
+    }
+
+    async fn handle(&self, event: UserCreated) -> Result<()> {
+        let mut data = TemplateData::new();
+        data.insert("subject", "Welcome to Origin");
+        data.insert(
+            "message",
+            format!(
+                "Hi {}, your account is ready. Link a bank account to see your balances and spending in one place.",
+                event.name
+            ),
+        );
+        self.queue
+            .enqueue(
+                &event.email,
+                EmailTemplateName::Notification,
+                EmailLocale::parse(&event.locale),
+                &data,
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+/// Runs the first transaction sync of a newly linked item instead of waiting for the scheduled one
+pub struct SyncOnLink {
+    coordinator: SyncCoordinator,
+    items: PlaidItemRepository,
+}
+
+impl SyncOnLink {
+    pub fn new(coordinator: SyncCoordinator, items: PlaidItemRepository) -> Self {
+        Self { coordinator, items }
+    }
+}
+
+#[async_trait]
+impl EventHandler<ItemLinked> for SyncOnLink {
+    fn name(&self) -> &'static str {
+        "sync_on_link"
+    }
+
+    async fn handle(&self, event: ItemLinked) -> Result<()> {
+        let item = self
+            .items
+            .find_by_item_id(&event.item_id)
+            .await?
+            .ok_or_else(|| anyhow!("Linked item {} not found", event.item_id))?;
+        let outcome = self.coordinator.sync_item(&item).await;
+        if let Some(error) = outcome.error {
+            return Err(anyhow!(error));
+        }
+        info!(
+            user_id = %event.user_id,
+            item_id = %event.item_id,
+            skipped = outcome.skipped,
+            "Initial sync of linked item finished"
+        );
+        Ok(())
+    }
+}
+
+/// Queues webhook deliveries for events users' endpoints subscribe to
+pub struct WebhookFanout {
+    webhooks: WebhookRepository,
+    transactions: TransactionRepository,
+}
+
+impl WebhookFanout {
+    pub fn new(webhooks: WebhookRepository, transactions: TransactionRepository) -> Self {
+        Self { webhooks, transactions }
+    }
+}
+
+#[async_trait]
+impl EventHandler<ItemLinked> for WebhookFanout {
+    fn name(&self) -> &'static str {
+        "webhook_fanout"
+    }
+
+    async fn handle(&self, event: ItemLinked) -> Result<()> {
+        let data = json!({ "item_id": event.item_id, "provider": event.provider });
+        let event_id = Uuid::new_v4();
+        let payload = event_payload(event_id, WebhookEventType::ItemLinked, Utc::now(), data);
+        self.webhooks
+            .enqueue(event.user_id, WebhookEventType::ItemLinked, event_id, &payload)
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventHandler<ItemSynced> for WebhookFanout {
+    fn name(&self) -> &'static str {
+        "webhook_fanout"
+    }
+
+    async fn handle(&self, event: ItemSynced) -> Result<()> {
+        let event_type = WebhookEventType::TransactionCreated;
+        if !self.webhooks.has_subscribers(event.user_id, event_type).await? {
+            return Ok(());
+        }
+        let transactions = self
+            .transactions
+            .list_added_since(&event.item_id, event.started_at)
+            .await?;
+        let now = Utc::now();
+        let mut queued = 0;
+        for transaction in &transactions {
+            // Keyed by the transaction, so an overlapping sync doesn't announce it twice
+            let payload = event_payload(transaction.id, event_type, now, transaction_data(transaction));
+            queued += self
+                .webhooks
+                .enqueue(event.user_id, event_type, transaction.id, &payload)
+                .await?
+                .len();
+        }
+        debug!(user_id = %event.user_id, item_id = %event.item_id, queued, "Transaction webhooks queued");
+        Ok(())
+    }
+}
+
+/// Fields of a transaction sent in `transaction.created` events
+fn transaction_data(transaction: &Transaction) -> Value {
+    json!({
+        "transaction_id": transaction.transaction_id,
+        "account_id": transaction.account_id,
+        "item_id": transaction.item_id,
+        "amount": transaction.amount,
+        "iso_currency_code": transaction.iso_currency_code,
+        "date": transaction.date.to_string(),
+        "name": transaction.name,
+        "merchant_name": transaction.merchant_name,
+        "pending": transaction.pending,
+    })
+}
@@ -0,0 +1,164 @@
+// Transaction file export
+use crate::model::transaction::Transaction;
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+/// Supported export file formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    /// One JSON object per line
+    JsonLines,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv",
+            ExportFormat::JsonLines => "application/x-ndjson",
+        }
+    }
+
+    /// Text written before the first record, if the format has a header
+    pub fn header(&self) -> Option<String> {
+        match self {
+            ExportFormat::Csv => Some(format!("{}\n", CSV_COLUMNS.join(","))),
+            ExportFormat::JsonLines => None,
+        }
+    }
+}
+
+const CSV_COLUMNS: &[&str] = &[
+    "transaction_id",
+    "account_id",
+    "date",
+    "authorized_date",
+    "name",
+    "merchant_name",
+    "amount",
+    "currency_code",
+    "category",
+    "pending",
+];
+
+/// Exported transaction, with Plaid's sign convention (positive amounts are outflows)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExportRecord<'a> {
+    pub transaction_id: &'a str,
+    pub account_id: &'a str,
+    pub date: String,
+    pub authorized_date: Option<String>,
+    pub name: &'a str,
+    pub merchant_name: Option<&'a str>,
+    pub amount: f64,
+    pub currency_code: Option<&'a str>,
+    /// Effective category: the user's override, else the AI category
+    pub category: Option<&'a str>,
+    pub pending: bool,
+}
+
+impl<'a> ExportRecord<'a> {
+    pub fn new(transaction: &'a Transaction, category: Option<&'a str>) -> Self {
+        Self {
+            transaction_id: &transaction.transaction_id,
+            account_id: &transaction.account_id,
+            date: transaction.date.format("%Y-%m-%d").to_string(),
+            authorized_date: transaction.authorized_date.map(|d| d.format("%Y-%m-%d").to_string()),
+            name: &transaction.name,
+            merchant_name: transaction.merchant_name.as_deref(),
+            amount: transaction.amount,
+            currency_code: transaction
+                .iso_currency_code
+                .as_deref()
+                .or(transaction.unofficial_currency_code.as_deref()),
+            category,
+            pending: transaction.pending,
+        }
+    }
+
+    /// The record as one line of `format`, including the trailing newline
+    pub fn to_line(&self, format: ExportFormat) -> Result<String> {
+        match format {
+            ExportFormat::Csv => {
+                let fields = [
+                    csv_field(self.transaction_id),
+                    csv_field(self.account_id),
+                    self.date.clone(),
+                    self.authorized_date.clone().unwrap_or_default(),
+                    csv_field(self.name),
+                    self.merchant_name.map(csv_field).unwrap_or_default(),
+                    format!("{:.2}", self.amount),
+                    self.currency_code.map(csv_field).unwrap_or_default(),
+                    self.category.map(csv_field).unwrap_or_default(),
+                    self.pending.to_string(),
+                ];
+                Ok(format!("{}\n", fields.join(",")))
+            }
+            ExportFormat::JsonLines => {
+                let json = serde_json::to_string(self).context("Failed to serialize transaction")?;
+                Ok(format!("{}\n", json))
+            }
+        }
+    }
+}
+
+/// Quote a CSV field when it contains a delimiter, quote or line break.
+///
+/// Values starting with a formula character are prefixed with `'` so spreadsheets
+/// don't evaluate merchant-controlled text.
+pub(crate) fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record<'a>(name: &'a str, merchant_name: Option<&'a str>) -> ExportRecord<'a> {
+        ExportRecord {
+            transaction_id: "tx_1",
+            account_id: "acc_1",
+            date: "2024-03-01".to_string(),
+            authorized_date: None,
+            name,
+            merchant_name,
+            amount: 4.5,
+            currency_code: Some("USD"),
+            category: Some("FOOD_AND_DRINK"),
+            pending: false,
+        }
+    }
+
+    #[test]
+    fn test_csv_line() {
+        assert_eq!(
+            ExportFormat::Csv.header().unwrap(),
+            "transaction_id,account_id,date,authorized_date,name,merchant_name,amount,currency_code,category,pending\n"
+        );
+        assert_eq!(
+            record("Coffee, \"large\"", None).to_line(ExportFormat::Csv).unwrap(),
+            "tx_1,acc_1,2024-03-01,,\"Coffee, \"\"large\"\"\",,4.50,USD,FOOD_AND_DRINK,false\n"
+        );
+        assert_eq!(csv_field("=HYPERLINK(\"x\")"), "\"'=HYPERLINK(\"\"x\"\")\"");
+    }
+
+    #[test]
+    fn test_json_line() {
+        let line = record("Coffee", Some("Blue Bottle")).to_line(ExportFormat::JsonLines).unwrap();
+        assert!(line.ends_with('\n'));
+        let value: serde_json::Value = serde_json::from_str(line.trim_end()).unwrap();
+        assert_eq!(value["merchant_name"], "Blue Bottle");
+        assert_eq!(value["amount"], 4.5);
+        assert_eq!(value["authorized_date"], serde_json::Value::Null);
+        assert!(ExportFormat::JsonLines.header().is_none());
+    }
+}
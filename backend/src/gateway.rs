@@ -0,0 +1,916 @@
+//! REST/JSON gateway for clients that can't speak gRPC, enabled with the `rest-gateway` feature.
+//!
+//! Routes follow the `google.api.http` annotations in auth.proto, accounts.proto and the unsubscribe
+//! service of alerts.proto, and call the same handler instances as the gRPC server, so validation,
+//! authentication and errors behave alike.
+//! Messages are JSON with the proto field names; GET routes read request fields from the query string.
+//! Plaid's transfer webhooks are received here too, as verifying them needs the raw body.
+
+use crate::adapter::plaid_webhook::VERIFICATION_HEADER;
+use crate::gen::accounts::accounts_service_server::AccountsService;
+use crate::gen::accounts::{
+    AttachReceiptRequest, CreateLinkTokenRequest, CreateLinkTokenResponse, CreateReceiptUploadRequest,
+    CreateReceiptUploadResponse, DeleteReceiptRequest, DeleteReceiptResponse, ExchangePublicTokenRequest,
+    ExchangePublicTokenResponse, GenerateTaxReportRequest, GenerateTaxReportResponse, GetAccountIdentityRequest,
+    GetAccountIdentityResponse, GetBalanceHistoryRequest, GetBalanceHistoryResponse, GetInstitutionRequest,
+    GetNetWorthHistoryRequest, GetNetWorthHistoryResponse, GetReceiptDownloadUrlRequest, GetReceiptDownloadUrlResponse,
+    GetSpendingSummaryRequest, GetSpendingSummaryResponse, GetStatementDownloadUrlRequest,
+    GetStatementDownloadUrlResponse, Institution, LinkExchangeAccountRequest, ListBankAccountsRequest,
+    ListBankAccountsResponse, ListBillsRequest, ListBillsResponse, ListLiabilitiesRequest, ListLiabilitiesResponse,
+    ListReceiptsRequest, ListReceiptsResponse, ListStatementsRequest, ListStatementsResponse,
+    ListTransactionCategoriesRequest, ListTransactionCategoriesResponse, ListTransactionsRequest,
+    ListTransactionsResponse, Receipt, RefreshBalancesRequest, RefreshBalancesResponse, RemoveBankConnectionRequest,
+    RemoveBankConnectionResponse, ScanReceiptRequest, SearchTransactionsRequest, SearchTransactionsResponse,
+    SemanticSearchTransactionsRequest, SemanticSearchTransactionsResponse, SetDisplayCurrencyRequest,
+    SetDisplayCurrencyResponse, SetTransactionCategoryRequest, SetTransactionCategoryResponse,
+    SetTransactionNotesRequest, SetTransactionNotesResponse, SetTransactionSplitsRequest, SetTransactionSplitsResponse,
+    SetTransactionTagsRequest, SetTransactionTagsResponse, TriggerSyncRequest, TriggerSyncResponse,
+};
+use crate::gen::alerts::email_unsubscribe_service_server::EmailUnsubscribeService;
+use crate::gen::alerts::{UnsubscribeRequest, UnsubscribeResponse};
+use crate::gen::auth::auth_service_server::AuthService;
+use crate::gen::auth::{
+    CompleteOAuthRequest, CompleteOAuthResponse, CreateAccessTokenRequest, CreateAccessTokenResponse,
+    GetProfileRequest, GetProfileResponse, GetUserSessionsRequest, GetUserSessionsResponse, InitiateOAuthRequest,
+    InitiateOAuthResponse, LogoutAllRequest, LogoutAllResponse, LogoutRequest, LogoutResponse, RefreshTokenRequest,
+    RefreshTokenResponse, RevokeSessionRequest, RevokeSessionResponse, SendOtpRequest, SendOtpResponse,
+    ValidateTokenRequest, ValidateTokenResponse, VerifyOtpRequest, VerifyOtpResponse,
+};
+use crate::handler::accounts::AccountsHandler;
+use crate::handler::alerts::UnsubscribeHandler;
+use crate::handler::auth::AuthServiceImpl;
+use crate::handler::interceptor::AuthInterceptor;
+use crate::handler::transfers::TransferWebhookHandler;
+use crate::request_id::{self, RequestId, REQUEST_ID_HEADER};
+use crate::settings::SettingValues;
+use anyhow::Result;
+use axum::body::Bytes;
+use axum::extract::{Path, Query, Request as HttpRequest, State};
+use axum::http::header::{AUTHORIZATION, USER_AGENT};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post, put};
+use axum::{Json, Router};
+use serde::Serialize;
+use serde_json::json;
+use sqlx::PgPool;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tonic::metadata::{AsciiMetadataKey, AsciiMetadataValue, KeyAndValueRef, MetadataMap};
+use tonic::{Code, Request, Status};
+use tracing::{info, info_span, warn, Instrument};
+
+/// REST gateway configuration
+#[derive(Debug, Clone)]
+pub struct GatewayConfig {
+    pub addr: SocketAddr,
+}
+
+impl GatewayConfig {
+    /// Read from settings
+    /// - GATEWAY_ADDR: listen address (default: [::0]:8082)
+    pub fn from_values(values: &mut SettingValues) -> Self {
+        Self {
+            addr: values.parsed("gateway-addr", SocketAddr::from(([0u16; 8], 8082))),
+        }
+    }
+}
+
+/// Handlers shared with the gRPC server
+#[derive(Clone)]
+pub struct GatewayState {
+    auth: Arc<AuthServiceImpl>,
+    accounts: Arc<AccountsHandler>,
+    unsubscribe: Arc<UnsubscribeHandler>,
+    transfer_webhooks: Arc<TransferWebhookHandler>,
+    interceptor: AuthInterceptor,
+    pool: PgPool,
+}
+
+impl GatewayState {
+    pub fn new(
+        auth: Arc<AuthServiceImpl>,
+        accounts: Arc<AccountsHandler>,
+        unsubscribe: Arc<UnsubscribeHandler>,
+        transfer_webhooks: Arc<TransferWebhookHandler>,
+        interceptor: AuthInterceptor,
+        pool: PgPool,
+    ) -> Self {
+        Self {
+            auth,
+            accounts,
+            unsubscribe,
+            transfer_webhooks,
+            interceptor,
+            pool,
+        }
+    }
+
+    /// Request for an RPC that authenticates the caller itself, with the HTTP headers as metadata
+    fn request<T>(&self, headers: &HeaderMap, message: T) -> Request<T> {
+        let mut request = Request::new(message);
+        *request.metadata_mut() = metadata(headers);
+        if let Some(id) = request_id::current() {
+            request.extensions_mut().insert(RequestId(id));
+        }
+        request
+    }
+
+    /// Request for an RPC behind `AuthInterceptor`, authenticated the same way
+    fn authenticated<T>(&self, headers: &HeaderMap, message: T) -> Result<Request<T>, GatewayError> {
+        let header = headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| Status::unauthenticated("Missing authorization header"))?;
+        let auth = self.interceptor.authenticate_header(header).map_err(Status::from)?;
+
+        let mut request = self.request(headers, message);
+        request.extensions_mut().insert(auth);
+        Ok(request)
+    }
+}
+
+/// HTTP headers as gRPC metadata; binary and non-ASCII headers are dropped
+fn metadata(headers: &HeaderMap) -> MetadataMap {
+    let mut metadata = MetadataMap::new();
+    for (name, value) in headers {
+        if name.as_str().ends_with("-bin") {
+            continue;
+        }
+        if let (Ok(key), Ok(value)) = (
+            AsciiMetadataKey::from_bytes(name.as_str().as_bytes()),
+            AsciiMetadataValue::try_from(value.as_bytes()),
+        ) {
+            metadata.append(key, value);
+        }
+    }
+    metadata
+}
+
+/// Token of an `Authorization: Bearer` header, for auth RPCs that take the access token in the message
+fn bearer_token(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get(AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.trim().split_once(' ')?;
+    scheme.eq_ignore_ascii_case("bearer").then(|| token.trim().to_string())
+}
+
+fn user_agent(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+/// HTTP status for a gRPC status code, as mapped by grpc-gateway
+fn http_status(code: Code) -> StatusCode {
+    match code {
+        Code::Ok => StatusCode::OK,
+        Code::Cancelled => StatusCode::from_u16(499).unwrap_or(StatusCode::BAD_REQUEST),
+        Code::InvalidArgument | Code::FailedPrecondition | Code::OutOfRange => StatusCode::BAD_REQUEST,
+        Code::DeadlineExceeded => StatusCode::GATEWAY_TIMEOUT,
+        Code::NotFound => StatusCode::NOT_FOUND,
+        Code::AlreadyExists | Code::Aborted => StatusCode::CONFLICT,
+        Code::PermissionDenied => StatusCode::FORBIDDEN,
+        Code::Unauthenticated => StatusCode::UNAUTHORIZED,
+        Code::ResourceExhausted => StatusCode::TOO_MANY_REQUESTS,
+        Code::Unimplemented => StatusCode::NOT_IMPLEMENTED,
+        Code::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        Code::Unknown | Code::Internal | Code::DataLoss => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Give each call a request ID as `RequestIdLayer` does for gRPC: read from or set in `x-request-id`,
+/// current while the handler runs and echoed in the response
+async fn propagate_request_id(mut request: HttpRequest, next: Next) -> Response {
+    let id = RequestId::accept_or_generate(
+        request
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok()),
+    );
+    let header = HeaderValue::from_str(id.as_str()).ok();
+    if let Some(header) = &header {
+        request.headers_mut().insert(REQUEST_ID_HEADER, header.clone());
+    }
+
+    let span = info_span!("gateway_request", path = %request.uri().path(), request_id = %id.as_str());
+    let mut response = id.scope(next.run(request)).instrument(span).await;
+    if let Some(header) = header {
+        response.headers_mut().insert(REQUEST_ID_HEADER, header);
+    }
+    response
+}
+
+/// Failed RPC, rendered as `{"code": <gRPC code>, "message": ..., "request_id": ...}` with the matching
+/// HTTP status
+#[derive(Debug)]
+pub struct GatewayError(Status);
+
+impl From<Status> for GatewayError {
+    fn from(status: Status) -> Self {
+        Self(status)
+    }
+}
+
+impl IntoResponse for GatewayError {
+    fn into_response(self) -> Response {
+        let body = json!({
+            "code": self.0.code() as i32,
+            "message": self.0.message(),
+            "request_id": request_id::current(),
+        });
+        (http_status(self.0.code()), Json(body)).into_response()
+    }
+}
+
+/// Successful RPC, rendered as the JSON message with its response metadata as headers
+pub struct GatewayResponse<T>(tonic::Response<T>);
+
+impl<T> From<tonic::Response<T>> for GatewayResponse<T> {
+    fn from(response: tonic::Response<T>) -> Self {
+        Self(response)
+    }
+}
+
+impl<T: Serialize> IntoResponse for GatewayResponse<T> {
+    fn into_response(self) -> Response {
+        let (metadata, message, _) = self.0.into_parts();
+        let mut response = Json(message).into_response();
+        for entry in metadata.iter() {
+            if let KeyAndValueRef::Ascii(key, value) = entry {
+                if let (Ok(name), Ok(value)) = (
+                    HeaderName::from_bytes(key.as_str().as_bytes()),
+                    HeaderValue::from_bytes(value.as_bytes()),
+                ) {
+                    response.headers_mut().append(name, value);
+                }
+            }
+        }
+        response
+    }
+}
+
+type GatewayResult<T> = Result<GatewayResponse<T>, GatewayError>;
+
+/// Liveness and database reachability, for load balancer health checks
+async fn health(State(state): State<GatewayState>) -> Response {
+    match sqlx::query("SELECT 1").execute(&state.pool).await {
+        Ok(_) => Json(json!({ "status": "ok" })).into_response(),
+        Err(e) => {
+            warn!(error = %e, "Health check failed to reach the database");
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(json!({ "status": "unavailable" })),
+            )
+                .into_response()
+        }
+    }
+}
+
+async fn initiate_google_oauth(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Json(message): Json<InitiateOAuthRequest>,
+) -> GatewayResult<InitiateOAuthResponse> {
+    let request = state.request(&headers, message);
+    Ok(state.auth.initiate_google_o_auth(request).await?.into())
+}
+
+async fn complete_google_oauth(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Json(mut message): Json<CompleteOAuthRequest>,
+) -> GatewayResult<CompleteOAuthResponse> {
+    message.user_agent = message.user_agent.or_else(|| user_agent(&headers));
+    let request = state.request(&headers, message);
+    Ok(state.auth.complete_google_o_auth(request).await?.into())
+}
+
+async fn refresh_token(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Json(message): Json<RefreshTokenRequest>,
+) -> GatewayResult<RefreshTokenResponse> {
+    let request = state.request(&headers, message);
+    Ok(state.auth.refresh_token(request).await?.into())
+}
+
+async fn logout(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Json(mut message): Json<LogoutRequest>,
+) -> GatewayResult<LogoutResponse> {
+    if message.access_token.is_empty() {
+        message.access_token = bearer_token(&headers).unwrap_or_default();
+    }
+    let request = state.request(&headers, message);
+    Ok(state.auth.logout(request).await?.into())
+}
+
+async fn logout_all(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Json(mut message): Json<LogoutAllRequest>,
+) -> GatewayResult<LogoutAllResponse> {
+    if message.access_token.is_empty() {
+        message.access_token = bearer_token(&headers).unwrap_or_default();
+    }
+    let request = state.request(&headers, message);
+    Ok(state.auth.logout_all(request).await?.into())
+}
+
+async fn validate_token(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Json(mut message): Json<ValidateTokenRequest>,
+) -> GatewayResult<ValidateTokenResponse> {
+    if message.access_token.is_empty() {
+        message.access_token = bearer_token(&headers).unwrap_or_default();
+    }
+    let request = state.request(&headers, message);
+    Ok(state.auth.validate_token(request).await?.into())
+}
+
+async fn get_profile(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Query(mut message): Query<GetProfileRequest>,
+) -> GatewayResult<GetProfileResponse> {
+    if message.access_token.is_empty() {
+        message.access_token = bearer_token(&headers).unwrap_or_default();
+    }
+    let request = state.request(&headers, message);
+    Ok(state.auth.get_profile(request).await?.into())
+}
+
+async fn get_user_sessions(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Query(mut message): Query<GetUserSessionsRequest>,
+) -> GatewayResult<GetUserSessionsResponse> {
+    if message.access_token.is_empty() {
+        message.access_token = bearer_token(&headers).unwrap_or_default();
+    }
+    let request = state.request(&headers, message);
+    Ok(state.auth.get_user_sessions(request).await?.into())
+}
+
+async fn revoke_session(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Path(session_id): Path<String>,
+    Json(mut message): Json<RevokeSessionRequest>,
+) -> GatewayResult<RevokeSessionResponse> {
+    message.session_id = session_id;
+    if message.access_token.is_empty() {
+        message.access_token = bearer_token(&headers).unwrap_or_default();
+    }
+    let request = state.request(&headers, message);
+    Ok(state.auth.revoke_session(request).await?.into())
+}
+
+async fn send_otp(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Json(message): Json<SendOtpRequest>,
+) -> GatewayResult<SendOtpResponse> {
+    let request = state.request(&headers, message);
+    Ok(state.auth.send_otp(request).await?.into())
+}
+
+async fn verify_otp(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Json(mut message): Json<VerifyOtpRequest>,
+) -> GatewayResult<VerifyOtpResponse> {
+    message.user_agent = message.user_agent.or_else(|| user_agent(&headers));
+    let request = state.request(&headers, message);
+    Ok(state.auth.verify_otp(request).await?.into())
+}
+
+async fn create_access_token(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Json(mut message): Json<CreateAccessTokenRequest>,
+) -> GatewayResult<CreateAccessTokenResponse> {
+    if message.access_token.is_empty() {
+        message.access_token = bearer_token(&headers).unwrap_or_default();
+    }
+    let request = state.request(&headers, message);
+    Ok(state.auth.create_access_token(request).await?.into())
+}
+
+/// Unsubscribe link from an email, opened by the recipient (GET) or posted by their mail client
+/// (RFC 8058 POST, whose `List-Unsubscribe=One-Click` body carries nothing else)
+async fn unsubscribe(
+    State(handler): State<Arc<UnsubscribeHandler>>,
+    Path(token): Path<String>,
+) -> GatewayResult<UnsubscribeResponse> {
+    let mut request = Request::new(UnsubscribeRequest { token });
+    if let Some(id) = request_id::current() {
+        request.extensions_mut().insert(RequestId(id));
+    }
+    Ok(handler.unsubscribe(request).await?.into())
+}
+
+/// Plaid Transfer webhook, passed on as received since its signature covers the raw body
+async fn transfer_webhook(
+    State(handler): State<Arc<TransferWebhookHandler>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<StatusCode, GatewayError> {
+    let verification = headers.get(VERIFICATION_HEADER).and_then(|value| value.to_str().ok());
+    handler.receive(verification, &body).await.map_err(Status::from)?;
+    Ok(StatusCode::OK)
+}
+
+async fn create_link_token(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Json(message): Json<CreateLinkTokenRequest>,
+) -> GatewayResult<CreateLinkTokenResponse> {
+    let request = state.authenticated(&headers, message)?;
+    Ok(state.accounts.create_link_token(request).await?.into())
+}
+
+async fn exchange_public_token(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Json(message): Json<ExchangePublicTokenRequest>,
+) -> GatewayResult<ExchangePublicTokenResponse> {
+    let request = state.authenticated(&headers, message)?;
+    Ok(state.accounts.exchange_public_token(request).await?.into())
+}
+
+async fn link_exchange_account(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Json(message): Json<LinkExchangeAccountRequest>,
+) -> GatewayResult<ExchangePublicTokenResponse> {
+    let request = state.authenticated(&headers, message)?;
+    Ok(state.accounts.link_exchange_account(request).await?.into())
+}
+
+async fn list_bank_accounts(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Query(message): Query<ListBankAccountsRequest>,
+) -> GatewayResult<ListBankAccountsResponse> {
+    let request = state.authenticated(&headers, message)?;
+    Ok(state.accounts.list_bank_accounts(request).await?.into())
+}
+
+async fn get_institution(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Path(institution_id): Path<String>,
+) -> GatewayResult<Institution> {
+    let request = state.authenticated(&headers, GetInstitutionRequest { institution_id })?;
+    Ok(state.accounts.get_institution(request).await?.into())
+}
+
+async fn list_transactions(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Query(message): Query<ListTransactionsRequest>,
+) -> GatewayResult<ListTransactionsResponse> {
+    let request = state.authenticated(&headers, message)?;
+    Ok(state.accounts.list_transactions(request).await?.into())
+}
+
+async fn search_transactions(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Json(message): Json<SearchTransactionsRequest>,
+) -> GatewayResult<SearchTransactionsResponse> {
+    let request = state.authenticated(&headers, message)?;
+    Ok(state.accounts.search_transactions(request).await?.into())
+}
+
+async fn semantic_search_transactions(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Json(message): Json<SemanticSearchTransactionsRequest>,
+) -> GatewayResult<SemanticSearchTransactionsResponse> {
+    let request = state.authenticated(&headers, message)?;
+    Ok(state.accounts.semantic_search_transactions(request).await?.into())
+}
+
+async fn set_transaction_category(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Path(transaction_id): Path<String>,
+    Json(mut message): Json<SetTransactionCategoryRequest>,
+) -> GatewayResult<SetTransactionCategoryResponse> {
+    message.transaction_id = transaction_id;
+    let request = state.authenticated(&headers, message)?;
+    Ok(state.accounts.set_transaction_category(request).await?.into())
+}
+
+async fn set_transaction_notes(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Path(transaction_id): Path<String>,
+    Json(mut message): Json<SetTransactionNotesRequest>,
+) -> GatewayResult<SetTransactionNotesResponse> {
+    message.transaction_id = transaction_id;
+    let request = state.authenticated(&headers, message)?;
+    Ok(state.accounts.set_transaction_notes(request).await?.into())
+}
+
+async fn set_transaction_tags(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Path(transaction_id): Path<String>,
+    Json(mut message): Json<SetTransactionTagsRequest>,
+) -> GatewayResult<SetTransactionTagsResponse> {
+    message.transaction_id = transaction_id;
+    let request = state.authenticated(&headers, message)?;
+    Ok(state.accounts.set_transaction_tags(request).await?.into())
+}
+
+async fn set_transaction_splits(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Path(transaction_id): Path<String>,
+    Json(mut message): Json<SetTransactionSplitsRequest>,
+) -> GatewayResult<SetTransactionSplitsResponse> {
+    message.transaction_id = transaction_id;
+    let request = state.authenticated(&headers, message)?;
+    Ok(state.accounts.set_transaction_splits(request).await?.into())
+}
+
+async fn create_receipt_upload(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Path(transaction_id): Path<String>,
+    Json(mut message): Json<CreateReceiptUploadRequest>,
+) -> GatewayResult<CreateReceiptUploadResponse> {
+    message.transaction_id = transaction_id;
+    let request = state.authenticated(&headers, message)?;
+    Ok(state.accounts.create_receipt_upload(request).await?.into())
+}
+
+async fn attach_receipt(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Path(receipt_id): Path<String>,
+) -> GatewayResult<Receipt> {
+    let request = state.authenticated(&headers, AttachReceiptRequest { receipt_id })?;
+    Ok(state.accounts.attach_receipt(request).await?.into())
+}
+
+async fn list_receipts(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Path(transaction_id): Path<String>,
+) -> GatewayResult<ListReceiptsResponse> {
+    let request = state.authenticated(&headers, ListReceiptsRequest { transaction_id })?;
+    Ok(state.accounts.list_receipts(request).await?.into())
+}
+
+async fn get_receipt_download_url(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Path(receipt_id): Path<String>,
+) -> GatewayResult<GetReceiptDownloadUrlResponse> {
+    let request = state.authenticated(&headers, GetReceiptDownloadUrlRequest { receipt_id })?;
+    Ok(state.accounts.get_receipt_download_url(request).await?.into())
+}
+
+async fn delete_receipt(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Path(receipt_id): Path<String>,
+) -> GatewayResult<DeleteReceiptResponse> {
+    let request = state.authenticated(&headers, DeleteReceiptRequest { receipt_id })?;
+    Ok(state.accounts.delete_receipt(request).await?.into())
+}
+
+async fn scan_receipt(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Path(receipt_id): Path<String>,
+) -> GatewayResult<Receipt> {
+    let request = state.authenticated(&headers, ScanReceiptRequest { receipt_id })?;
+    Ok(state.accounts.scan_receipt(request).await?.into())
+}
+
+async fn list_statements(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Query(message): Query<ListStatementsRequest>,
+) -> GatewayResult<ListStatementsResponse> {
+    let request = state.authenticated(&headers, message)?;
+    Ok(state.accounts.list_statements(request).await?.into())
+}
+
+async fn get_statement_download_url(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Path(statement_id): Path<String>,
+) -> GatewayResult<GetStatementDownloadUrlResponse> {
+    let request = state.authenticated(&headers, GetStatementDownloadUrlRequest { statement_id })?;
+    Ok(state.accounts.get_statement_download_url(request).await?.into())
+}
+
+async fn generate_tax_report(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Path(year): Path<i32>,
+    Query(mut message): Query<GenerateTaxReportRequest>,
+) -> GatewayResult<GenerateTaxReportResponse> {
+    message.year = year;
+    let request = state.authenticated(&headers, message)?;
+    Ok(state.accounts.generate_tax_report(request).await?.into())
+}
+
+async fn list_transaction_categories(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Query(message): Query<ListTransactionCategoriesRequest>,
+) -> GatewayResult<ListTransactionCategoriesResponse> {
+    let request = state.authenticated(&headers, message)?;
+    Ok(state.accounts.list_transaction_categories(request).await?.into())
+}
+
+async fn get_spending_summary(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Query(message): Query<GetSpendingSummaryRequest>,
+) -> GatewayResult<GetSpendingSummaryResponse> {
+    let request = state.authenticated(&headers, message)?;
+    Ok(state.accounts.get_spending_summary(request).await?.into())
+}
+
+async fn remove_bank_connection(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Path(item_id): Path<String>,
+) -> GatewayResult<RemoveBankConnectionResponse> {
+    let request = state.authenticated(&headers, RemoveBankConnectionRequest { item_id })?;
+    Ok(state.accounts.remove_bank_connection(request).await?.into())
+}
+
+async fn trigger_sync(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Json(message): Json<TriggerSyncRequest>,
+) -> GatewayResult<TriggerSyncResponse> {
+    let request = state.authenticated(&headers, message)?;
+    Ok(state.accounts.trigger_sync(request).await?.into())
+}
+
+async fn refresh_balances(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Json(message): Json<RefreshBalancesRequest>,
+) -> GatewayResult<RefreshBalancesResponse> {
+    let request = state.authenticated(&headers, message)?;
+    Ok(state.accounts.refresh_balances(request).await?.into())
+}
+
+async fn list_liabilities(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Query(message): Query<ListLiabilitiesRequest>,
+) -> GatewayResult<ListLiabilitiesResponse> {
+    let request = state.authenticated(&headers, message)?;
+    Ok(state.accounts.list_liabilities(request).await?.into())
+}
+
+async fn list_bills(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Query(message): Query<ListBillsRequest>,
+) -> GatewayResult<ListBillsResponse> {
+    let request = state.authenticated(&headers, message)?;
+    Ok(state.accounts.list_bills(request).await?.into())
+}
+
+async fn get_account_identity(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Path(account_id): Path<String>,
+    Query(mut message): Query<GetAccountIdentityRequest>,
+) -> GatewayResult<GetAccountIdentityResponse> {
+    message.account_id = account_id;
+    let request = state.authenticated(&headers, message)?;
+    Ok(state.accounts.get_account_identity(request).await?.into())
+}
+
+async fn get_net_worth_history(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Query(message): Query<GetNetWorthHistoryRequest>,
+) -> GatewayResult<GetNetWorthHistoryResponse> {
+    let request = state.authenticated(&headers, message)?;
+    Ok(state.accounts.get_net_worth_history(request).await?.into())
+}
+
+async fn get_balance_history(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Path(account_id): Path<String>,
+    Query(mut message): Query<GetBalanceHistoryRequest>,
+) -> GatewayResult<GetBalanceHistoryResponse> {
+    message.account_id = account_id;
+    let request = state.authenticated(&headers, message)?;
+    Ok(state.accounts.get_balance_history(request).await?.into())
+}
+
+async fn set_display_currency(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Json(message): Json<SetDisplayCurrencyRequest>,
+) -> GatewayResult<SetDisplayCurrencyResponse> {
+    let request = state.authenticated(&headers, message)?;
+    Ok(state.accounts.set_display_currency(request).await?.into())
+}
+
+/// Routes for the unary auth and accounts RPCs; streaming RPCs stay gRPC-only
+pub fn router(state: GatewayState) -> Router {
+    let unsubscribe = unsubscribe_router(state.unsubscribe.clone());
+    let transfer_webhooks = Router::new()
+        .route("/api/webhooks/plaid/transfer", post(transfer_webhook))
+        .with_state(state.transfer_webhooks.clone());
+    Router::new()
+        .route("/healthz", get(health))
+        .route("/api/auth/oauth/google/initiate", post(initiate_google_oauth))
+        .route("/api/auth/oauth/google/complete", post(complete_google_oauth))
+        .route("/api/auth/refresh", post(refresh_token))
+        .route("/api/auth/logout", post(logout))
+        .route("/api/auth/logout-all", post(logout_all))
+        .route("/api/auth/validate", post(validate_token))
+        .route("/api/auth/profile", get(get_profile))
+        .route("/api/auth/sessions", get(get_user_sessions))
+        .route("/api/auth/sessions/:session_id/revoke", post(revoke_session))
+        .route("/api/auth/otp/send", post(send_otp))
+        .route("/api/auth/otp/verify", post(verify_otp))
+        .route("/api/auth/tokens", post(create_access_token))
+        .route("/api/accounts", get(list_bank_accounts))
+        .route("/api/accounts/link-token", post(create_link_token))
+        .route("/api/accounts/exchange", post(exchange_public_token))
+        .route("/api/accounts/exchange-accounts", post(link_exchange_account))
+        .route("/api/institutions/:institution_id", get(get_institution))
+        .route("/api/accounts/transactions", get(list_transactions))
+        .route("/api/accounts/transactions/search", post(search_transactions))
+        .route(
+            "/api/accounts/transactions/semantic-search",
+            post(semantic_search_transactions),
+        )
+        .route(
+            "/api/accounts/transactions/categories",
+            get(list_transaction_categories),
+        )
+        .route(
+            "/api/accounts/transactions/:transaction_id/category",
+            put(set_transaction_category),
+        )
+        .route(
+            "/api/accounts/transactions/:transaction_id/notes",
+            put(set_transaction_notes),
+        )
+        .route(
+            "/api/accounts/transactions/:transaction_id/tags",
+            put(set_transaction_tags),
+        )
+        .route(
+            "/api/accounts/transactions/:transaction_id/splits",
+            put(set_transaction_splits),
+        )
+        .route(
+            "/api/accounts/transactions/:transaction_id/receipts",
+            post(create_receipt_upload).get(list_receipts),
+        )
+        .route("/api/accounts/receipts/:receipt_id", delete(delete_receipt))
+        .route("/api/accounts/receipts/:receipt_id/attach", post(attach_receipt))
+        .route(
+            "/api/accounts/receipts/:receipt_id/download",
+            get(get_receipt_download_url),
+        )
+        .route("/api/accounts/receipts/:receipt_id/scan", post(scan_receipt))
+        .route("/api/accounts/statements", get(list_statements))
+        .route(
+            "/api/accounts/statements/:statement_id/download",
+            get(get_statement_download_url),
+        )
+        .route("/api/accounts/reports/tax/:year", get(generate_tax_report))
+        .route("/api/accounts/spending/summary", get(get_spending_summary))
+        .route("/api/accounts/items/:item_id", delete(remove_bank_connection))
+        .route("/api/accounts/sync", post(trigger_sync))
+        .route("/api/accounts/balances/refresh", post(refresh_balances))
+        .route("/api/accounts/liabilities", get(list_liabilities))
+        .route("/api/accounts/bills", get(list_bills))
+        .route("/api/accounts/:account_id/identity", get(get_account_identity))
+        .route("/api/accounts/net-worth/history", get(get_net_worth_history))
+        .route("/api/accounts/:account_id/balances/history", get(get_balance_history))
+        .route("/api/accounts/display-currency", put(set_display_currency))
+        .with_state(state)
+        .merge(unsubscribe)
+        .merge(transfer_webhooks)
+        .layer(middleware::from_fn(propagate_request_id))
+}
+
+/// Routes of the unauthenticated unsubscribe links, which only need the unsubscribe handler
+fn unsubscribe_router(handler: Arc<UnsubscribeHandler>) -> Router {
+    Router::new()
+        .route("/api/email/unsubscribe/:token", get(unsubscribe).post(unsubscribe))
+        .with_state(handler)
+}
+
+/// Serve the gateway until the listener fails
+pub async fn serve(config: GatewayConfig, state: GatewayState) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(config.addr).await?;
+    info!(addr = %config.addr, "REST gateway listening");
+    axum::serve(listener, router(state)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapter::email_preferences::{EmailCategory, UnsubscribeLinks};
+    use crate::model::email_preferences::EmailPreferencesRepository;
+    use axum::body::Body;
+    use axum::http::Method;
+    use sqlx::postgres::PgPoolOptions;
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_http_status_mapping() {
+        assert_eq!(http_status(Code::InvalidArgument), StatusCode::BAD_REQUEST);
+        assert_eq!(http_status(Code::Unauthenticated), StatusCode::UNAUTHORIZED);
+        assert_eq!(http_status(Code::PermissionDenied), StatusCode::FORBIDDEN);
+        assert_eq!(http_status(Code::NotFound), StatusCode::NOT_FOUND);
+        assert_eq!(http_status(Code::ResourceExhausted), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(http_status(Code::Unavailable), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(http_status(Code::Cancelled).as_u16(), 499);
+    }
+
+    #[test]
+    fn test_headers_become_metadata() {
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer abc"));
+        headers.insert("if-none-match", HeaderValue::from_static("\"v1\""));
+        headers.insert("trace-bin", HeaderValue::from_static("AAEC"));
+
+        let metadata = metadata(&headers);
+        assert_eq!(metadata.get("authorization").unwrap(), "Bearer abc");
+        assert_eq!(metadata.get("if-none-match").unwrap(), "\"v1\"");
+        assert!(metadata.get_bin("trace-bin").is_none());
+        assert_eq!(bearer_token(&headers).as_deref(), Some("abc"));
+    }
+
+    #[test]
+    fn test_response_metadata_becomes_headers() {
+        let mut response = tonic::Response::new(json!({ "id": "user-1" }));
+        response.metadata_mut().insert("etag", "\"v2\"".parse().unwrap());
+
+        let response = GatewayResponse::from(response).into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get("etag").unwrap(), "\"v2\"");
+
+        let response = GatewayError::from(Status::not_found("No such receipt")).into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    /// Unsubscribe routes over a database that can't be reached, so accepted links fail to save
+    fn unsubscribe_routes(links: &UnsubscribeLinks) -> Router {
+        let pool = PgPoolOptions::new()
+            .acquire_timeout(Duration::from_millis(100))
+            .connect_lazy("postgres://localhost:1/origin")
+            .unwrap();
+        let handler = UnsubscribeHandler::new(EmailPreferencesRepository::new(pool), Some(links.clone()));
+        unsubscribe_router(Arc::new(handler))
+    }
+
+    async fn request_link(router: Router, method: Method, link: &str) -> StatusCode {
+        let path = link.strip_prefix("https://api.example.com").unwrap();
+        let request = axum::http::Request::builder()
+            .method(method)
+            .uri(path)
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body(Body::from("List-Unsubscribe=One-Click"))
+            .unwrap();
+        router.oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_generated_unsubscribe_link_is_routed() {
+        let links = UnsubscribeLinks::new("secret", "https://api.example.com/api/email/unsubscribe");
+        let link = links.link("person@example.com", EmailCategory::Marketing).unwrap();
+
+        // The link reaches the handler over both methods, and fails only on saving the opt-out
+        for method in [Method::GET, Method::POST] {
+            let status = request_link(unsubscribe_routes(&links), method, &link).await;
+            assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        }
+
+        let tampered = link.replace("marketing", "digest");
+        let status = request_link(unsubscribe_routes(&links), Method::GET, &tampered).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
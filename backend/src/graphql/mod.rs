@@ -0,0 +1,240 @@
+//! Read-only GraphQL layer for the web dashboard, enabled with the `graphql` feature.
+//!
+//! Resolvers reuse the same repositories as the gRPC handlers and scope every query
+//! to the authenticated caller; there are no mutations.
+
+pub mod server;
+
+use crate::handler::interceptor::{require_scope, AuthContext};
+use crate::model::auth::Scope;
+use crate::model::bank_account::{BankAccountRepository, StoredBankAccount};
+use crate::model::plaid_item::{PlaidItem, PlaidItemRepository};
+use crate::model::transaction::{Transaction as StoredTransaction, TransactionFilter, TransactionRepository};
+use async_graphql::{Context, EmptyMutation, EmptySubscription, Error, Object, Result, Schema, SimpleObject};
+use chrono::{DateTime, NaiveDate, Utc};
+use tracing::error;
+
+pub use server::{GraphqlConfig, PersistedQueries};
+
+pub type DashboardSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// Limits applied to every GraphQL operation
+#[derive(Debug, Clone, Copy)]
+pub struct QueryLimits {
+    pub max_depth: usize,
+    pub max_complexity: usize,
+}
+
+impl Default for QueryLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: 8,
+            max_complexity: 250,
+        }
+    }
+}
+
+/// Build the dashboard schema over the shared repositories
+pub fn build_schema(
+    accounts: BankAccountRepository,
+    items: PlaidItemRepository,
+    transactions: TransactionRepository,
+    limits: QueryLimits,
+) -> DashboardSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(accounts)
+        .data(items)
+        .data(transactions)
+        .limit_depth(limits.max_depth)
+        .limit_complexity(limits.max_complexity)
+        .extension(async_graphql::extensions::Tracing)
+        .finish()
+}
+
+/// Account balances
+#[derive(SimpleObject)]
+pub struct Balances {
+    pub available: Option<f64>,
+    pub current: Option<f64>,
+    pub limit: Option<f64>,
+    pub iso_currency_code: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Persisted bank account
+#[derive(SimpleObject)]
+pub struct Account {
+    pub account_id: String,
+    pub item_id: String,
+    pub name: String,
+    pub official_name: Option<String>,
+    pub mask: Option<String>,
+    pub account_type: String,
+    pub account_subtype: Option<String>,
+    pub institution_name: Option<String>,
+    pub balances: Balances,
+}
+
+impl From<StoredBankAccount> for Account {
+    fn from(account: StoredBankAccount) -> Self {
+        Self {
+            account_id: account.account_id,
+            item_id: account.item_id,
+            name: account.name,
+            official_name: account.official_name,
+            mask: account.mask,
+            account_type: account.account_type,
+            account_subtype: account.account_subtype,
+            institution_name: account.institution_name,
+            balances: Balances {
+                available: account.available_balance,
+                current: account.current_balance,
+                limit: account.credit_limit,
+                iso_currency_code: account.iso_currency_code,
+                updated_at: account.balances_updated_at,
+            },
+        }
+    }
+}
+
+/// Linked bank connection; access tokens are never exposed
+#[derive(SimpleObject)]
+pub struct LinkedItem {
+    pub item_id: String,
+    pub institution_id: Option<String>,
+    pub institution_name: Option<String>,
+    pub status: String,
+    pub error_code: Option<String>,
+    pub last_synced_at: Option<DateTime<Utc>>,
+}
+
+impl From<PlaidItem> for LinkedItem {
+    fn from(item: PlaidItem) -> Self {
+        Self {
+            item_id: item.item_id,
+            institution_id: item.institution_id,
+            institution_name: item.institution_name,
+            status: item.status,
+            error_code: item.error_code,
+            last_synced_at: item.last_synced_at,
+        }
+    }
+}
+
+/// Synced transaction
+#[derive(SimpleObject)]
+pub struct Transaction {
+    pub transaction_id: String,
+    pub account_id: String,
+    pub amount: f64,
+    pub iso_currency_code: Option<String>,
+    pub date: NaiveDate,
+    pub name: String,
+    pub merchant_name: Option<String>,
+    pub category: Vec<String>,
+    pub pending: bool,
+}
+
+impl From<StoredTransaction> for Transaction {
+    fn from(transaction: StoredTransaction) -> Self {
+        Self {
+            transaction_id: transaction.transaction_id,
+            account_id: transaction.account_id,
+            amount: transaction.amount,
+            iso_currency_code: transaction.iso_currency_code,
+            date: transaction.date,
+            name: transaction.name,
+            merchant_name: transaction.merchant_name,
+            category: transaction.category,
+            pending: transaction.pending,
+        }
+    }
+}
+
+/// Largest page a single `transactions` query may request
+const MAX_TRANSACTIONS_PAGE: i32 = 200;
+
+fn caller(ctx: &Context<'_>, scope: Scope) -> Result<AuthContext> {
+    let auth = ctx
+        .data_opt::<AuthContext>()
+        .cloned()
+        .ok_or_else(|| Error::new("Missing authentication"))?;
+    require_scope(&auth.claims, scope).map_err(|e| Error::new(e.client_message()))?;
+    Ok(auth)
+}
+
+fn internal_error(e: anyhow::Error, message: &str) -> Error {
+    error!("{}: {:?}", message, e);
+    Error::new(message)
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// The caller's bank accounts from the last sync
+    async fn accounts(&self, ctx: &Context<'_>) -> Result<Vec<Account>> {
+        let auth = caller(ctx, Scope::AccountsRead)?;
+        let accounts = ctx
+            .data::<BankAccountRepository>()?
+            .list_by_user(auth.user_id)
+            .await
+            .map_err(|e| internal_error(e, "Failed to list accounts"))?;
+
+        Ok(accounts.into_iter().map(Account::from).collect())
+    }
+
+    /// A single account owned by the caller
+    async fn account(&self, ctx: &Context<'_>, account_id: String) -> Result<Option<Account>> {
+        let auth = caller(ctx, Scope::AccountsRead)?;
+        let account = ctx
+            .data::<BankAccountRepository>()?
+            .find_by_account_id(&account_id)
+            .await
+            .map_err(|e| internal_error(e, "Failed to load account"))?;
+
+        Ok(account
+            .filter(|account| account.user_id == auth.user_id)
+            .map(Account::from))
+    }
+
+    /// The caller's linked bank connections
+    async fn linked_items(&self, ctx: &Context<'_>) -> Result<Vec<LinkedItem>> {
+        let auth = caller(ctx, Scope::AccountsRead)?;
+        let items = ctx
+            .data::<PlaidItemRepository>()?
+            .list_by_user(auth.user_id)
+            .await
+            .map_err(|e| internal_error(e, "Failed to list linked items"))?;
+
+        Ok(items.into_iter().map(LinkedItem::from).collect())
+    }
+
+    /// The caller's transactions, newest first
+    async fn transactions(
+        &self,
+        ctx: &Context<'_>,
+        account_id: Option<String>,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+        #[graphql(default = 50)] first: i32,
+        #[graphql(default = 0)] offset: i32,
+    ) -> Result<Vec<Transaction>> {
+        let auth = caller(ctx, Scope::TransactionsRead)?;
+        let filter = TransactionFilter {
+            account_id,
+            start_date,
+            end_date,
+            limit: first.clamp(1, MAX_TRANSACTIONS_PAGE) as i64,
+            offset: offset.max(0) as i64,
+            include_details: false,
+        };
+        let transactions = ctx
+            .data::<TransactionRepository>()?
+            .list_by_user(auth.user_id, &filter)
+            .await
+            .map_err(|e| internal_error(e, "Failed to list transactions"))?;
+
+        Ok(transactions.into_iter().map(Transaction::from).collect())
+    }
+}
@@ -0,0 +1,179 @@
+use super::{DashboardSchema, QueryLimits};
+use crate::handler::interceptor::AuthInterceptor;
+use crate::settings::SettingValues;
+use anyhow::{Context, Result};
+use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
+use axum::extract::State;
+use axum::http::{header::AUTHORIZATION, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::Router;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// GraphQL endpoint configuration
+#[derive(Debug, Clone)]
+pub struct GraphqlConfig {
+    pub addr: SocketAddr,
+    pub limits: QueryLimits,
+    /// JSON file holding the array of allowed query documents
+    pub persisted_queries_path: Option<String>,
+    /// Accept documents that are not in the allowlist (local development only)
+    pub allow_arbitrary_queries: bool,
+}
+
+impl GraphqlConfig {
+    /// Read from settings
+    /// - GRAPHQL_ADDR: listen address (default: [::0]:8081)
+    /// - GRAPHQL_MAX_DEPTH / GRAPHQL_MAX_COMPLEXITY: query limits
+    /// - GRAPHQL_PERSISTED_QUERIES_PATH: JSON array of allowed query documents
+    /// - GRAPHQL_ALLOW_ARBITRARY_QUERIES: disable the allowlist (default: false)
+    pub fn from_values(values: &mut SettingValues) -> Self {
+        let defaults = QueryLimits::default();
+        Self {
+            addr: values.parsed("graphql-addr", SocketAddr::from(([0u16; 8], 8081))),
+            limits: QueryLimits {
+                max_depth: values.parsed("graphql-max-depth", defaults.max_depth),
+                max_complexity: values.parsed("graphql-max-complexity", defaults.max_complexity),
+            },
+            persisted_queries_path: values.optional("graphql-persisted-queries-path"),
+            allow_arbitrary_queries: values.flag("graphql-allow-arbitrary-queries", false),
+        }
+    }
+}
+
+/// Allowlist of query documents the dashboard is built with, keyed by SHA-256 of the document
+#[derive(Debug, Clone, Default)]
+pub struct PersistedQueries {
+    hashes: HashSet<String>,
+    allow_arbitrary: bool,
+}
+
+impl PersistedQueries {
+    /// Hash of a query document, ignoring surrounding whitespace
+    pub fn hash(query: &str) -> String {
+        format!("{:x}", Sha256::digest(query.trim().as_bytes()))
+    }
+
+    pub fn from_documents<I, S>(documents: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self {
+            hashes: documents.into_iter().map(|d| Self::hash(d.as_ref())).collect(),
+            allow_arbitrary: false,
+        }
+    }
+
+    pub fn from_file(path: &str) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read persisted queries from {}", path))?;
+        let documents: Vec<String> =
+            serde_json::from_str(&contents).context("Persisted queries must be a JSON array of strings")?;
+        Ok(Self::from_documents(documents))
+    }
+
+    /// Accept any document; used when the allowlist is disabled
+    pub fn allow_all() -> Self {
+        Self {
+            hashes: HashSet::new(),
+            allow_arbitrary: true,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    pub fn is_allowed(&self, query: &str) -> bool {
+        self.allow_arbitrary || self.hashes.contains(&Self::hash(query))
+    }
+}
+
+#[derive(Clone)]
+struct GraphqlState {
+    schema: DashboardSchema,
+    interceptor: AuthInterceptor,
+    persisted_queries: Arc<PersistedQueries>,
+}
+
+async fn graphql_handler(
+    State(state): State<GraphqlState>,
+    headers: HeaderMap,
+    request: GraphQLRequest,
+) -> Response {
+    let header = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let auth = match state.interceptor.authenticate_header(header) {
+        Ok(auth) => auth,
+        Err(e) => return (StatusCode::UNAUTHORIZED, e.client_message()).into_response(),
+    };
+
+    let request = request.into_inner();
+    if !state.persisted_queries.is_allowed(&request.query) {
+        warn!(user_id = %auth.user_id, "Rejected GraphQL query outside the persisted allowlist");
+        return (StatusCode::FORBIDDEN, "Query is not in the persisted query allowlist").into_response();
+    }
+
+    GraphQLResponse::from(state.schema.execute(request.data(auth)).await).into_response()
+}
+
+/// Serve the dashboard schema on `POST /graphql`
+pub async fn serve(config: GraphqlConfig, schema: DashboardSchema, interceptor: AuthInterceptor) -> Result<()> {
+    let persisted_queries = match (&config.persisted_queries_path, config.allow_arbitrary_queries) {
+        (_, true) => PersistedQueries::allow_all(),
+        (Some(path), false) => PersistedQueries::from_file(path)?,
+        (None, false) => PersistedQueries::default(),
+    };
+    if !config.allow_arbitrary_queries && persisted_queries.is_empty() {
+        warn!("GraphQL allowlist is empty; every query will be rejected");
+    }
+
+    let app = Router::new()
+        .route("/graphql", post(graphql_handler))
+        .with_state(GraphqlState {
+            schema,
+            interceptor,
+            persisted_queries: Arc::new(persisted_queries),
+        });
+
+    let listener = tokio::net::TcpListener::bind(config.addr).await?;
+    info!(
+        addr = %config.addr,
+        max_depth = config.limits.max_depth,
+        max_complexity = config.limits.max_complexity,
+        "GraphQL server listening"
+    );
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allowlist_matches_trimmed_documents() {
+        let queries = PersistedQueries::from_documents(["query Accounts { accounts { accountId } }"]);
+
+        assert_eq!(queries.len(), 1);
+        assert!(queries.is_allowed("  query Accounts { accounts { accountId } }\n"));
+        assert!(!queries.is_allowed("query Other { linkedItems { itemId } }"));
+    }
+
+    #[test]
+    fn test_allow_all_accepts_any_document() {
+        assert!(PersistedQueries::allow_all().is_allowed("{ accounts { name } }"));
+        assert!(!PersistedQueries::default().is_allowed("{ accounts { name } }"));
+    }
+}
@@ -0,0 +1,3179 @@
+use crate::adapter::fx::{FxClient, FxRates};
+use crate::adapter::s3::S3Client;
+use crate::adapter::bank_data::{BankDataProvider, BankDataProviders};
+use crate::adapter::coinbase::{CoinbaseClient, CoinbaseCredentials, COINBASE_PROVIDER};
+use crate::adapter::plaid::{BankAccount, BankTransaction, Institution, LinkTokenRequest, PublicTokenExchangeRequest};
+use crate::dedup::TransactionDeduplicator;
+use crate::error::AppError;
+use crate::events::{EventBus, ItemLinked};
+use crate::export::{ExportFormat, ExportRecord};
+use crate::handler::assistant::{admit_ai_request, check_ai_quota};
+use crate::handler::field_mask::{clear_unmasked, ReadMask};
+use crate::handler::interceptor::AuthContext;
+use crate::handler::pagination::{decode_cursor, encode_cursor, page_size, split_page};
+use crate::import::{self, CsvMapping, ExistingTransaction, ImportFormat, DUPLICATE_DATE_TOLERANCE_DAYS};
+use crate::jobs::{ItemSyncOutcome, SyncCoordinator};
+use crate::model::ai_usage::AiFeature;
+use crate::model::auth::Scope;
+use crate::model::audit_log::AuditLogRepository;
+use crate::model::account_share::{AccountShareRepository, ShareAccess};
+use crate::model::account_identity::{AccountIdentityRepository, StoredAccountIdentity};
+use crate::model::balance_cache::{BalanceCache, CachedBalances, RefreshPermit};
+use crate::model::balance_history::{BalanceHistoryRepository, BalanceSnapshot};
+use crate::model::bank_account::{BankAccountRepository, StoredBankAccount};
+use crate::model::bill::{Bill, BillRepository};
+use crate::model::institution_cache::InstitutionCache;
+use crate::model::liability::{LiabilityRepository, StoredLiability};
+use crate::model::net_worth::{Granularity, NetWorthRepository, NetWorthSnapshot};
+use crate::model::pubsub::{BalanceSubscription, BalanceUpdates};
+use crate::model::receipt::{
+    receipt_extension, receipt_object_key, validate_receipt, NewReceipt, Receipt, ReceiptRepository, ReceiptStatus,
+};
+use crate::receipt_scan::ReceiptScanner;
+use crate::model::tax_report::TaxReportRepository;
+use crate::report::TaxReport;
+use crate::model::statement::{statement_file_name, BankStatement, StatementRepository};
+use crate::model::spending::{SpendingGroupBy, SpendingPeriod, SpendingRepository, SpendingTotal};
+use crate::model::plaid_item::{CreatePlaidItemRequest, PlaidItem, PlaidItemRepository, PlaidItemStatus};
+use crate::model::transaction::{Transaction, TransactionFilter, TransactionRepository, TransactionSearch};
+use crate::model::transaction_annotation::{
+    normalize_tags, validate_splits, NewSplit, TransactionAnnotationRepository, TransactionSplit, MAX_NOTES_CHARS,
+};
+use crate::model::user::UserRepository;
+use crate::model::transaction_embedding::{relative_date_range, SemanticSearch, TransactionEmbedder};
+use crate::model::transaction_category::{
+    taxonomy_category, TransactionCategory, TransactionCategoryRepository, CATEGORY_TAXONOMY,
+};
+use crate::gen::accounts::{
+    accounts_service_server::AccountsService, AccountBalances as ProtoAccountBalances, AttachReceiptRequest,
+    BalanceEvent, BalancePoint, CreateReceiptUploadRequest, CreateReceiptUploadResponse, DeleteReceiptRequest,
+    DeleteReceiptResponse, GetReceiptDownloadUrlRequest, GetReceiptDownloadUrlResponse, ListReceiptsRequest,
+    ListReceiptsResponse, Receipt as ProtoReceipt, ReceiptExtraction as ProtoReceiptExtraction,
+    ReceiptLineItem as ProtoReceiptLineItem, ScanReceiptRequest, ListStatementsRequest, ListStatementsResponse,
+    Statement as ProtoStatement, GetStatementDownloadUrlRequest, GetStatementDownloadUrlResponse,
+    GenerateTaxReportRequest, GenerateTaxReportResponse, TaxBucketTotal as ProtoTaxBucketTotal,
+    DeductibleCandidate as ProtoDeductibleCandidate, TaxReportFormat as ProtoTaxReportFormat,
+    BankAccount as ProtoBankAccount, CreateLinkTokenRequest, CsvColumnMapping,
+    ExportFormat as ProtoExportFormat, ExportTransactionsChunk, ExportTransactionsRequest, GetInstitutionRequest, CreateLinkTokenResponse,
+    LinkExchangeAccountRequest,
+    Bill as ProtoBill, ExchangePublicTokenRequest, ExchangePublicTokenResponse, GetAccountIdentityRequest,
+    GetBalanceHistoryRequest, GetBalanceHistoryResponse,
+    GetAccountIdentityResponse, GetNetWorthHistoryRequest, GetNetWorthHistoryResponse,
+    GetSpendingSummaryRequest, GetSpendingSummaryResponse, IdentityAddress as ProtoIdentityAddress,
+    IdentityContact as ProtoIdentityContact, IdentityOwner as ProtoIdentityOwner, Institution as ProtoInstitution,
+    import_transactions_request::Payload as ImportPayload, ImportFormat as ProtoImportFormat,
+    ImportPreviewRow, ImportRowError, ImportTransactionsRequest, ImportTransactionsResponse, ItemSyncResult,
+    Liability as ProtoLiability, LiabilityApr as ProtoLiabilityApr, ListBankAccountsRequest,
+    ListBankAccountsResponse, ListBillsRequest, ListBillsResponse, ListLiabilitiesRequest, ListLiabilitiesResponse,
+    ListTransactionCategoriesRequest, ListTransactionCategoriesResponse, ListTransactionsRequest,
+    ListTransactionsResponse, NetWorthGranularity, NetWorthPoint, RefreshBalancesRequest,
+    RefreshBalancesResponse, RemoveBankConnectionRequest, RemoveBankConnectionResponse, SearchTransactionsRequest,
+    SearchTransactionsResponse, SemanticMatch, SemanticSearchTransactionsRequest, SemanticSearchTransactionsResponse,
+    SetDisplayCurrencyRequest, SetDisplayCurrencyResponse,
+    SetTransactionCategoryRequest, SetTransactionCategoryResponse, SetTransactionNotesRequest,
+    SetTransactionNotesResponse, SetTransactionSplitsRequest, SetTransactionSplitsResponse, SetTransactionTagsRequest,
+    SetTransactionTagsResponse, SpendingAmount,
+    SpendingGroupBy as ProtoSpendingGroupBy, StreamBalancesRequest, Transaction as ProtoTransaction,
+    TransactionCategorization as ProtoTransactionCategorization,
+    TransactionLocation as ProtoTransactionLocation,
+    TransactionPaymentMeta as ProtoTransactionPaymentMeta, TransactionSplit as ProtoTransactionSplit,
+    TriggerSyncRequest, TriggerSyncResponse,
+};
+use chrono::{Datelike, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use futures::Stream;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+use tonic::{Request, Response, Status, Streaming};
+use tracing::{debug, error, info, instrument, warn};
+use uuid::Uuid;
+
+/// Fields of `Transaction` that may appear in a `ListTransactions` read mask
+const TRANSACTION_FIELDS: &[&str] = &[
+    "transaction_id", "account_id", "amount", "iso_currency_code", "date", "authorized_date",
+    "name", "merchant_name", "category", "pending", "pending_transaction_id", "transaction_type",
+    "location", "payment_meta", "original_description", "categorization", "notes", "tags", "splits",
+];
+
+/// Fields filled in from the user's notes, tags and splits
+const TRANSACTION_ANNOTATION_FIELDS: &[&str] = &["notes", "tags", "splits"];
+
+/// Fields loaded from the detail columns, skipped in SQL unless requested
+const TRANSACTION_DETAIL_FIELDS: &[&str] = &["location", "payment_meta", "original_description"];
+
+/// Net worth and balance history window when the request gives no start date
+const DEFAULT_NET_WORTH_HISTORY_DAYS: i64 = 90;
+
+/// Longest net worth or balance history window a single request may span
+const MAX_NET_WORTH_HISTORY_DAYS: i64 = 5 * 366;
+
+/// Display currency for users without a stored preference
+const DEFAULT_DISPLAY_CURRENCY: &str = "USD";
+
+const DEFAULT_SPENDING_GROUP_LIMIT: i32 = 20;
+const MAX_SPENDING_GROUP_LIMIT: i32 = 100;
+
+/// Longest period a spending summary may cover
+const MAX_SPENDING_PERIOD_DAYS: i64 = 366;
+
+/// Countries institutions are looked up in; matches the Link token default
+const INSTITUTION_COUNTRY_CODES: &[&str] = &["US"];
+
+/// Transactions loaded and sent per export chunk
+const EXPORT_PAGE_SIZE: i64 = 500;
+
+/// Chunks queued per export stream before the producing task waits on the client
+const EXPORT_STREAM_BUFFER: usize = 4;
+
+/// Largest file ImportTransactions accepts
+const MAX_IMPORT_BYTES: usize = 10 * 1024 * 1024;
+
+/// Parsed records returned in an import response
+const IMPORT_PREVIEW_ROWS: usize = 100;
+
+/// Longest receipt file name kept for downloads
+const MAX_RECEIPT_FILE_NAME_CHARS: usize = 255;
+
+/// Keyset position of a SearchTransactions page, handed to clients as an opaque token
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct TransactionPageToken {
+    date: NaiveDate,
+    transaction_id: String,
+}
+
+const DEFAULT_TRANSACTION_PAGE_SIZE: i32 = 50;
+const MAX_TRANSACTION_PAGE_SIZE: i32 = 500;
+
+const DEFAULT_SEMANTIC_PAGE_SIZE: i32 = 20;
+const MAX_SEMANTIC_PAGE_SIZE: i32 = 100;
+/// Semantic matches further than this cosine distance from the query are left out
+const MAX_SEMANTIC_DISTANCE: f64 = 0.65;
+const MAX_SEMANTIC_QUERY_CHARS: usize = 500;
+
+/// Events queued per balance stream before the forwarding task waits on the client
+const BALANCE_STREAM_BUFFER: usize = 16;
+
+/// Idle time after which a heartbeat is sent on a balance stream
+const BALANCE_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+/// A client that has not drained its buffer within this time is disconnected
+const BALANCE_SEND_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// gRPC Accounts Service implementation backed by the configured bank data providers
+pub struct AccountsHandler {
+    providers: BankDataProviders,
+    pool: PgPool,
+    item_repository: PlaidItemRepository,
+    account_repository: BankAccountRepository,
+    transaction_repository: TransactionRepository,
+    liability_repository: LiabilityRepository,
+    identity_repository: AccountIdentityRepository,
+    category_repository: TransactionCategoryRepository,
+    net_worth_repository: NetWorthRepository,
+    spending_repository: SpendingRepository,
+    user_repository: UserRepository,
+    fx_client: FxClient,
+    /// Spot prices for valuing crypto holdings, which have no reference exchange rate
+    crypto_prices: CoinbaseClient,
+    sync_coordinator: SyncCoordinator,
+    balance_updates: BalanceUpdates,
+    balance_cache: BalanceCache,
+    institution_cache: InstitutionCache,
+    audit_log: AuditLogRepository,
+    bill_repository: BillRepository,
+    balance_history_repository: BalanceHistoryRepository,
+    share_repository: AccountShareRepository,
+    annotation_repository: TransactionAnnotationRepository,
+    receipt_repository: ReceiptRepository,
+    /// Receipt and statement file storage; receipt and statement RPCs are unavailable without it
+    file_storage: Option<Arc<S3Client>>,
+    /// Receipt OCR; needs both file storage and a Claude API key
+    receipt_scanner: Option<ReceiptScanner>,
+    statement_repository: StatementRepository,
+    tax_report_repository: TaxReportRepository,
+    /// Semantic transaction search; needs an embeddings API key
+    transaction_embedder: Option<TransactionEmbedder>,
+    events: EventBus,
+}
+
+impl AccountsHandler {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        providers: BankDataProviders,
+        pool: PgPool,
+        item_repository: PlaidItemRepository,
+        account_repository: BankAccountRepository,
+        transaction_repository: TransactionRepository,
+        liability_repository: LiabilityRepository,
+        identity_repository: AccountIdentityRepository,
+        category_repository: TransactionCategoryRepository,
+        net_worth_repository: NetWorthRepository,
+        spending_repository: SpendingRepository,
+        user_repository: UserRepository,
+        fx_client: FxClient,
+        crypto_prices: CoinbaseClient,
+        sync_coordinator: SyncCoordinator,
+        balance_updates: BalanceUpdates,
+        balance_cache: BalanceCache,
+        institution_cache: InstitutionCache,
+        audit_log: AuditLogRepository,
+        bill_repository: BillRepository,
+        balance_history_repository: BalanceHistoryRepository,
+        share_repository: AccountShareRepository,
+        annotation_repository: TransactionAnnotationRepository,
+        receipt_repository: ReceiptRepository,
+        file_storage: Option<Arc<S3Client>>,
+        receipt_scanner: Option<ReceiptScanner>,
+        statement_repository: StatementRepository,
+        tax_report_repository: TaxReportRepository,
+        transaction_embedder: Option<TransactionEmbedder>,
+        events: EventBus,
+    ) -> Self {
+        Self {
+            providers,
+            pool,
+            item_repository,
+            account_repository,
+            transaction_repository,
+            liability_repository,
+            identity_repository,
+            category_repository,
+            net_worth_repository,
+            spending_repository,
+            user_repository,
+            fx_client,
+            crypto_prices,
+            sync_coordinator,
+            balance_updates,
+            balance_cache,
+            institution_cache,
+            audit_log,
+            bill_repository,
+            balance_history_repository,
+            share_repository,
+            annotation_repository,
+            receipt_repository,
+            file_storage,
+            receipt_scanner,
+            statement_repository,
+            tax_report_repository,
+            transaction_embedder,
+            events,
+        }
+    }
+
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+
+    /// Provider an item was linked through, with the item's decrypted credential
+    async fn item_credentials(&self, item: &PlaidItem) -> anyhow::Result<(Arc<dyn BankDataProvider>, String)> {
+        let provider = self.providers.get(&item.provider)?;
+        let access_token = self.item_repository.access_token(item).await?;
+        Ok((provider, access_token))
+    }
+
+    /// Fetch real-time balances for each of the user's active items and persist them.
+    ///
+    /// Items that fail are skipped so one broken connection does not block the rest;
+    /// the call fails only when every item fails.
+    async fn refresh_item_balances(&self, user_id: Uuid) -> Result<CachedBalances, AppError> {
+        let items = self.item_repository.list_by_user(user_id).await.map_err(|e| {
+            error!("Failed to list Plaid items: {:?}", e);
+            AppError::internal("Failed to refresh balances")
+        })?;
+        let items: Vec<_> = items
+            .into_iter()
+            .filter(|item| item.status() == PlaidItemStatus::Active)
+            .collect();
+
+        let mut last_error = None;
+        let mut refreshed_items = 0;
+        for item in &items {
+            let result = match self.item_credentials(item).await {
+                Ok((provider, access_token)) => provider.get_balances(&access_token).await,
+                Err(e) => Err(e),
+            };
+
+            match result {
+                Ok(accounts) => {
+                    self.account_repository
+                        .upsert_accounts(user_id, &item.item_id, &accounts)
+                        .await
+                        .map_err(|e| {
+                            error!("Failed to store bank accounts: {:?}", e);
+                            AppError::internal("Failed to store balances")
+                        })?;
+                    self.balance_updates.publish_balances(user_id, accounts);
+                    refreshed_items += 1;
+                }
+                Err(e) => {
+                    warn!(item_id = %item.item_id, "Failed to refresh item balances: {:?}", e);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        if refreshed_items == 0 {
+            if let Some(e) = last_error {
+                return Err(map_plaid_error(e, "Failed to refresh balances"));
+            }
+        }
+
+        let stored = self.account_repository.list_by_user(user_id).await.map_err(|e| {
+            error!("Failed to list bank accounts: {:?}", e);
+            AppError::internal("Failed to refresh balances")
+        })?;
+
+        info!(user_id = %user_id, refreshed_items, item_count = items.len(), "Balances refreshed");
+        Ok(CachedBalances {
+            accounts: stored.iter().map(|account| account.to_bank_account()).collect(),
+            refreshed_at: Utc::now(),
+        })
+    }
+
+    /// Fetch liabilities for each of the user's active items and persist them.
+    ///
+    /// Items whose institution does not support Liabilities are skipped.
+    async fn refresh_liabilities(&self, user_id: Uuid) -> Result<(), AppError> {
+        let items = self.item_repository.list_by_user(user_id).await.map_err(|e| {
+            error!("Failed to list Plaid items: {:?}", e);
+            AppError::internal("Failed to refresh liabilities")
+        })?;
+
+        for item in items.iter().filter(|item| item.status() == PlaidItemStatus::Active) {
+            let result = match self.item_credentials(item).await {
+                Ok((provider, access_token)) => provider.get_liabilities(&access_token).await,
+                Err(e) => Err(e),
+            };
+
+            match result {
+                Ok(liabilities) => {
+                    self.liability_repository
+                        .upsert_liabilities(user_id, &item.item_id, &liabilities)
+                        .await
+                        .map_err(|e| {
+                            error!("Failed to store liabilities: {:?}", e);
+                            AppError::internal("Failed to store liabilities")
+                        })?;
+                }
+                Err(e) if format!("{:?}", e).contains("PRODUCTS_NOT_SUPPORTED") => {
+                    debug!(item_id = %item.item_id, "Institution does not support liabilities");
+                }
+                Err(e) => {
+                    warn!(item_id = %item.item_id, "Failed to refresh item liabilities: {:?}", e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fetch identity for every account of the item and persist it
+    async fn refresh_identity(&self, user_id: Uuid, item_id: &str) -> Result<(), AppError> {
+        let item = self
+            .item_repository
+            .find_by_item_id(item_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to load Plaid item: {:?}", e);
+                AppError::internal("Failed to refresh identity")
+            })?
+            .filter(|item| item.user_id == user_id)
+            .ok_or_else(|| AppError::not_found("Linked item not found"))?;
+
+        let (provider, access_token) = self.item_credentials(&item).await.map_err(|e| {
+            error!("Failed to load item credentials: {:?}", e);
+            AppError::internal("Failed to refresh identity")
+        })?;
+        let identities = provider.get_identity(&access_token).await.map_err(|e| {
+            error!("Failed to fetch identity: {:?}", e);
+            map_plaid_error(e, "Failed to fetch identity")
+        })?;
+
+        self.identity_repository
+            .upsert_identities(user_id, item_id, &identities)
+            .await
+            .map_err(|e| {
+                error!("Failed to store identity: {:?}", e);
+                AppError::internal("Failed to store identity")
+            })?;
+        Ok(())
+    }
+
+    async fn stored_identity(&self, user_id: Uuid, account_id: &str) -> Result<Option<StoredAccountIdentity>, AppError> {
+        self.identity_repository
+            .find_by_account(user_id, account_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to load identity: {:?}", e);
+                AppError::internal("Failed to load identity")
+            })
+    }
+
+    /// Currency to convert summaries to: the requested one, else the user's preference
+    async fn display_currency(&self, user_id: Uuid, requested: Option<&str>) -> Result<String, AppError> {
+        if let Some(currency) = requested.filter(|c| !c.is_empty()) {
+            return normalize_currency(currency);
+        }
+
+        let user = self.user_repository.find_by_id(user_id).await.map_err(|e| {
+            error!("Failed to load user: {:?}", e);
+            AppError::internal("Failed to load display currency")
+        })?;
+        Ok(user
+            .map(|user| user.display_currency)
+            .unwrap_or_else(|| DEFAULT_DISPLAY_CURRENCY.to_string()))
+    }
+
+    /// Latest exchange rates; `None` when the feed is unavailable so summaries degrade to native currencies
+    async fn fx_rates(&self, display_currency: &str) -> Result<Option<Arc<FxRates>>, AppError> {
+        match self.fx_client.rates().await {
+            Ok(rates) if rates.supports(display_currency) => Ok(Some(rates)),
+            Ok(_) => Err(AppError::validation(format!("No exchange rate for {}", display_currency))),
+            Err(e) => {
+                warn!(error = ?e, "Exchange rates unavailable; returning unconverted amounts");
+                Ok(None)
+            }
+        }
+    }
+
+    /// Add live prices for `currencies` without a reference rate, such as crypto holdings.
+    /// Assets without a price stay unconvertible and are reported as unconverted.
+    async fn with_asset_prices(&self, rates: Arc<FxRates>, currencies: &[String]) -> Arc<FxRates> {
+        let mut missing: Vec<&String> = currencies.iter().filter(|c| !rates.supports(c)).collect();
+        missing.sort();
+        missing.dedup();
+        if missing.is_empty() {
+            return rates;
+        }
+
+        let mut priced = (*rates).clone();
+        for asset in missing {
+            match self.crypto_prices.spot_price(asset).await {
+                Ok(price) => {
+                    priced.add_asset_price(asset, price, self.crypto_prices.quote_currency());
+                }
+                Err(e) => debug!(asset = %asset, error = %e, "No spot price for asset"),
+            }
+        }
+        Arc::new(priced)
+    }
+
+    /// Exchange a link credential with `provider`, then store the item and its accounts
+    async fn link_item(
+        &self,
+        user_id: Uuid,
+        provider: Arc<dyn BankDataProvider>,
+        public_token: String,
+    ) -> Result<ExchangePublicTokenResponse, AppError> {
+        let exchange = provider
+            .exchange_public_token(PublicTokenExchangeRequest { public_token })
+            .await
+            .map_err(|e| {
+                error!("Failed to exchange public token: {:?}", e);
+                map_plaid_error(e, "Failed to exchange public token")
+            })?;
+
+        let accounts = provider
+            .get_accounts(&exchange.access_token)
+            .await
+            .map_err(|e| {
+                error!("Failed to fetch accounts: {:?}", e);
+                map_plaid_error(e, "Failed to fetch accounts")
+            })?;
+
+        // Persist the item so the access token survives restarts
+        let institution = accounts.first();
+        self.item_repository
+            .upsert_item(CreatePlaidItemRequest {
+                user_id,
+                item_id: exchange.item_id.clone(),
+                access_token: exchange.access_token,
+                institution_id: institution.and_then(|a| a.institution_id.clone()),
+                institution_name: institution.and_then(|a| a.institution_name.clone()),
+                provider: provider.name().to_string(),
+            })
+            .await
+            .map_err(|e| {
+                error!("Failed to store Plaid item: {:?}", e);
+                AppError::internal("Failed to store bank connection")
+            })?;
+
+        self.account_repository
+            .upsert_accounts(user_id, &exchange.item_id, &accounts)
+            .await
+            .map_err(|e| {
+                error!("Failed to store bank accounts: {:?}", e);
+                AppError::internal("Failed to store bank accounts")
+            })?;
+        self.balance_updates.publish_balances(user_id, accounts.clone());
+        self.events.publish(ItemLinked {
+            user_id,
+            item_id: exchange.item_id.clone(),
+            provider: provider.name().to_string(),
+        });
+
+        info!(
+            user_id = %user_id,
+            item_id = %exchange.item_id,
+            provider = provider.name(),
+            account_count = accounts.len(),
+            "Bank item linked successfully"
+        );
+        Ok(ExchangePublicTokenResponse {
+            item_id: exchange.item_id,
+            accounts: accounts.iter().map(Self::account_to_proto).collect(),
+        })
+    }
+
+    fn bill_to_proto(bill: &Bill) -> ProtoBill {
+        ProtoBill {
+            bill_id: bill.id.to_string(),
+            account_id: bill.account_id.clone(),
+            name: bill.name.clone(),
+            cadence: bill.cadence.clone(),
+            typical_amount: bill.typical_amount,
+            iso_currency_code: bill.iso_currency_code.clone(),
+            occurrences: bill.occurrences,
+            last_paid_date: bill.last_paid_date.format("%Y-%m-%d").to_string(),
+            next_due_date: bill.next_due_date.format("%Y-%m-%d").to_string(),
+        }
+    }
+
+    pub(crate) fn liability_to_proto(liability: &StoredLiability) -> ProtoLiability {
+        let date = |d: Option<NaiveDate>| d.map(|d| d.format("%Y-%m-%d").to_string());
+        ProtoLiability {
+            account_id: liability.account_id.clone(),
+            item_id: liability.item_id.clone(),
+            account_name: liability.account_name.clone(),
+            liability_type: liability.liability_type.clone(),
+            current_balance: liability.current_balance,
+            iso_currency_code: liability.iso_currency_code.clone(),
+            aprs: liability
+                .aprs
+                .iter()
+                .map(|apr| ProtoLiabilityApr {
+                    apr_percentage: apr.apr_percentage,
+                    apr_type: apr.apr_type.clone(),
+                    balance_subject_to_apr: apr.balance_subject_to_apr,
+                    interest_charge_amount: apr.interest_charge_amount,
+                })
+                .collect(),
+            interest_rate_percentage: liability.interest_rate_percentage,
+            is_overdue: liability.is_overdue,
+            last_payment_amount: liability.last_payment_amount,
+            last_payment_date: date(liability.last_payment_date),
+            next_payment_due_date: date(liability.next_payment_due_date),
+            minimum_payment_amount: liability.minimum_payment_amount,
+            last_statement_balance: liability.last_statement_balance,
+            last_statement_issue_date: date(liability.last_statement_issue_date),
+            origination_date: date(liability.origination_date),
+            origination_principal_amount: liability.origination_principal_amount,
+            maturity_date: date(liability.maturity_date),
+            updated_at: liability.updated_at.timestamp(),
+        }
+    }
+
+    /// Institutions by ID, served from the cache and fetched from Plaid on a miss.
+    ///
+    /// Branding is decoration, so lookups that fail are logged and left out rather than failing the call.
+    async fn institutions(&self, institution_ids: &[String]) -> HashMap<String, Institution> {
+        let mut institutions = match self.institution_cache.get_many(institution_ids).await {
+            Ok(institutions) => institutions,
+            Err(e) => {
+                warn!("Failed to read cached institutions: {:?}", e);
+                HashMap::new()
+            }
+        };
+
+        let misses: Vec<&String> = institution_ids.iter().filter(|id| !institutions.contains_key(*id)).collect();
+        let provider = self.providers.default_provider();
+        let fetched = futures::future::join_all(misses.iter().map(|id| {
+            provider.get_institution(id, INSTITUTION_COUNTRY_CODES.to_vec())
+        }))
+        .await;
+
+        for (institution_id, result) in misses.into_iter().zip(fetched) {
+            match result {
+                Ok(institution) => {
+                    if let Err(e) = self.institution_cache.put(&institution).await {
+                        warn!(institution_id = %institution_id, "Failed to cache institution: {:?}", e);
+                    }
+                    institutions.insert(institution_id.clone(), institution);
+                }
+                Err(e) => warn!(institution_id = %institution_id, "Failed to fetch institution: {:?}", e),
+            }
+        }
+
+        institutions
+    }
+
+    fn institution_to_proto(institution: &Institution) -> ProtoInstitution {
+        ProtoInstitution {
+            institution_id: institution.institution_id.clone(),
+            name: institution.name.clone(),
+            logo: institution.logo.clone(),
+            primary_color: institution.primary_color.clone(),
+            url: institution.url.clone(),
+            products: institution.products.clone(),
+            country_codes: institution.country_codes.clone(),
+            oauth: institution.oauth,
+        }
+    }
+
+    /// Convert transactions for a response, attaching categorization, notes, tags and splits when the mask asks for them
+    async fn transactions_to_proto(
+        &self,
+        user_id: Uuid,
+        transactions: &[Transaction],
+        read_mask: &ReadMask,
+        failure: &str,
+    ) -> Result<Vec<ProtoTransaction>, AppError> {
+        let categories = if read_mask.includes("categorization") {
+            let transaction_ids: Vec<String> = transactions.iter().map(|t| t.transaction_id.clone()).collect();
+            self.category_repository
+                .find_for_transactions(user_id, &transaction_ids)
+                .await
+                .map_err(|e| {
+                    error!("Failed to load transaction categories: {:?}", e);
+                    AppError::internal(failure)
+                })?
+        } else {
+            Default::default()
+        };
+        let annotations = if TRANSACTION_ANNOTATION_FIELDS.iter().any(|f| read_mask.includes(f)) {
+            let transaction_ids: Vec<String> = transactions.iter().map(|t| t.transaction_id.clone()).collect();
+            self.annotation_repository
+                .find_for_transactions(user_id, &transaction_ids)
+                .await
+                .map_err(|e| {
+                    error!("Failed to load transaction annotations: {:?}", e);
+                    AppError::internal(failure)
+                })?
+        } else {
+            Default::default()
+        };
+
+        Ok(transactions
+            .iter()
+            .map(|t| {
+                let mut proto = Self::transaction_to_proto(t, read_mask);
+                proto.categorization = categories.get(&t.transaction_id).map(Self::categorization_to_proto);
+                if let Some(annotations) = annotations.get(&t.transaction_id) {
+                    proto.notes = annotations.notes.clone();
+                    proto.tags = annotations.tags.clone();
+                    proto.splits = annotations.splits.iter().map(Self::split_to_proto).collect();
+                    clear_unmasked!(read_mask, proto, notes, tags, splits);
+                }
+                proto
+            })
+            .collect())
+    }
+
+    /// Load an account the caller owns or was granted `needed` access to through a share; accounts
+    /// the caller cannot see are reported as not found, without revealing that they exist
+    async fn require_account_access(
+        &self,
+        user_id: Uuid,
+        account_id: &str,
+        needed: ShareAccess,
+    ) -> Result<StoredBankAccount, AppError> {
+        let account = self
+            .account_repository
+            .find_by_account_id(account_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to load bank account: {:?}", e);
+                AppError::internal("Failed to load bank account")
+            })?;
+
+        let account = match account {
+            Some(account) if account.user_id == user_id => return Ok(account),
+            Some(account) => account,
+            None => return Err(AppError::not_found("Account not found")),
+        };
+
+        let access = self.share_repository.access_for(user_id, account_id).await.map_err(|e| {
+            error!("Failed to load account share: {:?}", e);
+            AppError::internal("Failed to load bank account")
+        })?;
+        match access {
+            Some(access) if access.allows(needed) => Ok(account),
+            Some(_) => Err(AppError::permission_denied("Account is shared with read-only access")),
+            None => {
+                warn!(user_id = %user_id, account_id = %account_id, "Rejected cross-user account access");
+                Err(AppError::not_found("Account not found"))
+            }
+        }
+    }
+
+    /// Owner of the transactions a request reads: the caller when no accounts are named, otherwise
+    /// the owner of the named accounts, which must all be accessible and belong to one user
+    async fn transactions_owner(
+        &self,
+        user_id: Uuid,
+        account_ids: &[String],
+        needed: ShareAccess,
+    ) -> Result<Uuid, AppError> {
+        let mut owner = None;
+        for account_id in account_ids {
+            let account = self.require_account_access(user_id, account_id, needed).await?;
+            if owner.is_some_and(|owner| owner != account.user_id) {
+                return Err(AppError::validation("Accounts shared by different users cannot be combined"));
+            }
+            owner = Some(account.user_id);
+        }
+        Ok(owner.unwrap_or(user_id))
+    }
+
+    /// Load a transaction on an account the caller owns or was granted `needed` access to
+    async fn require_transaction_access(
+        &self,
+        user_id: Uuid,
+        transaction_id: &str,
+        needed: ShareAccess,
+    ) -> Result<Transaction, AppError> {
+        let transaction = self
+            .transaction_repository
+            .find_by_transaction_id(transaction_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to load transaction: {:?}", e);
+                AppError::internal("Failed to load transaction")
+            })?
+            .ok_or_else(|| AppError::not_found("Transaction not found"))?;
+        if transaction.user_id == user_id {
+            return Ok(transaction);
+        }
+
+        match self.require_account_access(user_id, &transaction.account_id, needed).await {
+            Ok(_) => Ok(transaction),
+            Err(AppError::NotFound(_)) => Err(AppError::not_found("Transaction not found")),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Load a receipt, and its transaction, that the caller may access with `needed`
+    async fn require_receipt_access(
+        &self,
+        user_id: Uuid,
+        receipt_id: &str,
+        needed: ShareAccess,
+    ) -> Result<(Receipt, Transaction), AppError> {
+        let receipt_id = Uuid::parse_str(receipt_id).map_err(|_| AppError::validation("receipt_id must be a UUID"))?;
+        let receipt = self
+            .receipt_repository
+            .find(receipt_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to load receipt: {:?}", e);
+                AppError::internal("Failed to load receipt")
+            })?
+            .ok_or_else(|| AppError::not_found("Receipt not found"))?;
+
+        match self.require_transaction_access(user_id, &receipt.transaction_id, needed).await {
+            Ok(transaction) => Ok((receipt, transaction)),
+            Err(AppError::NotFound(_)) => Err(AppError::not_found("Receipt not found")),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn file_storage(&self) -> Result<&S3Client, AppError> {
+        self.file_storage
+            .as_deref()
+            .ok_or_else(|| AppError::upstream("s3", "File storage is not configured"))
+    }
+
+    fn receipt_to_proto(receipt: &Receipt, transaction_amount: f64) -> ProtoReceipt {
+        let extraction = receipt.extraction.as_ref().map(|extraction| &extraction.0);
+        ProtoReceipt {
+            receipt_id: receipt.id.to_string(),
+            transaction_id: receipt.transaction_id.clone(),
+            content_type: receipt.content_type.clone(),
+            size_bytes: receipt.size_bytes,
+            file_name: receipt.file_name.clone(),
+            attached_at: receipt.attached_at.unwrap_or(receipt.created_at).timestamp(),
+            extraction: extraction.map(|extraction| ProtoReceiptExtraction {
+                merchant: extraction.merchant.clone(),
+                date: extraction.date.map(|date| date.format("%Y-%m-%d").to_string()),
+                total: extraction.total,
+                tax: extraction.tax,
+                currency: extraction.currency.clone(),
+                line_items: extraction
+                    .line_items
+                    .iter()
+                    .map(|item| ProtoReceiptLineItem {
+                        description: item.description.clone(),
+                        quantity: item.quantity,
+                        amount: item.amount,
+                    })
+                    .collect(),
+                scanned_at: receipt.scanned_at.map(|t| t.timestamp()).unwrap_or_default(),
+            }),
+            total_matches_transaction: extraction.is_some_and(|extraction| extraction.total_matches(transaction_amount)),
+        }
+    }
+
+    fn statement_to_proto(statement: &BankStatement) -> ProtoStatement {
+        ProtoStatement {
+            statement_id: statement.id.to_string(),
+            account_id: statement.account_id.clone(),
+            year: statement.year,
+            month: statement.month,
+            size_bytes: statement.size_bytes,
+            fetched_at: statement.created_at.timestamp(),
+        }
+    }
+
+    pub(crate) fn account_to_proto(account: &BankAccount) -> ProtoBankAccount {
+        ProtoBankAccount {
+            account_id: account.account_id.clone(),
+            item_id: account.item_id.clone(),
+            name: account.name.clone(),
+            official_name: account.official_name.clone(),
+            mask: account.mask.clone(),
+            account_type: account.account_type.clone(),
+            account_subtype: account.account_subtype.clone(),
+            balances: Some(ProtoAccountBalances {
+                available: account.balances.available,
+                current: account.balances.current,
+                limit: account.balances.limit,
+                iso_currency_code: account.balances.iso_currency_code.clone(),
+                unofficial_currency_code: account.balances.unofficial_currency_code.clone(),
+            }),
+            institution_id: account.institution_id.clone(),
+            institution_name: account.institution_name.clone(),
+            institution: None,
+            shared_access: None,
+        }
+    }
+
+    pub(crate) fn transaction_to_proto(transaction: &Transaction, read_mask: &ReadMask) -> ProtoTransaction {
+        let location_mask = read_mask.sub_mask("location");
+        let mut proto = ProtoTransaction {
+            transaction_id: transaction.transaction_id.clone(),
+            account_id: transaction.account_id.clone(),
+            amount: transaction.amount,
+            iso_currency_code: transaction.iso_currency_code.clone(),
+            date: transaction.date.format("%Y-%m-%d").to_string(),
+            authorized_date: transaction.authorized_date.map(|d| d.format("%Y-%m-%d").to_string()),
+            name: transaction.name.clone(),
+            merchant_name: transaction.merchant_name.clone(),
+            category: transaction.category.clone(),
+            pending: transaction.pending,
+            pending_transaction_id: transaction.pending_transaction_id.clone(),
+            transaction_type: transaction.transaction_type.clone(),
+            location: transaction.location.as_ref().map(|location| {
+                let mut proto = ProtoTransactionLocation {
+                    address: location.address.clone(),
+                    city: location.city.clone(),
+                    region: location.region.clone(),
+                    postal_code: location.postal_code.clone(),
+                    country: location.country.clone(),
+                    lat: location.lat,
+                    lon: location.lon,
+                    store_number: location.store_number.clone(),
+                };
+                clear_unmasked!(
+                    location_mask, proto,
+                    address, city, region, postal_code, country, lat, lon, store_number,
+                );
+                proto
+            }),
+            payment_meta: transaction.payment_meta.as_ref().map(|meta| ProtoTransactionPaymentMeta {
+                by_order_of: meta.by_order_of.clone(),
+                payee: meta.payee.clone(),
+                payer: meta.payer.clone(),
+                payment_method: meta.payment_method.clone(),
+                payment_processor: meta.payment_processor.clone(),
+                ppd_id: meta.ppd_id.clone(),
+                reason: meta.reason.clone(),
+                reference_number: meta.reference_number.clone(),
+            }),
+            original_description: transaction.original_description.clone(),
+            // Stored separately; filled in by callers that load categories and annotations
+            categorization: None,
+            notes: None,
+            tags: Vec::new(),
+            splits: Vec::new(),
+        };
+
+        clear_unmasked!(
+            read_mask, proto,
+            transaction_id, account_id, amount, iso_currency_code, date, authorized_date, name,
+            merchant_name, category, pending, pending_transaction_id, transaction_type, location,
+            payment_meta, original_description,
+        );
+        proto
+    }
+
+    fn split_to_proto(split: &TransactionSplit) -> ProtoTransactionSplit {
+        ProtoTransactionSplit {
+            amount: split.amount,
+            category: split.category.clone(),
+            description: split.description.clone(),
+        }
+    }
+
+    pub(crate) fn categorization_to_proto(category: &TransactionCategory) -> ProtoTransactionCategorization {
+        ProtoTransactionCategorization {
+            category: category.category.clone(),
+            confidence: category.confidence,
+            source: category.source.clone(),
+            updated_at: category.updated_at.timestamp(),
+        }
+    }
+}
+
+fn parse_date(value: Option<&str>, field: &str) -> Result<Option<NaiveDate>, AppError> {
+    value
+        .filter(|v| !v.is_empty())
+        .map(|v| {
+            NaiveDate::parse_from_str(v, "%Y-%m-%d")
+                .map_err(|_| AppError::validation(format!("{} must be formatted as YYYY-MM-DD", field)))
+        })
+        .transpose()
+}
+
+/// Map a Plaid adapter failure to an `AppError`, surfacing client-caused token errors as validation failures
+pub(crate) fn map_plaid_error(e: anyhow::Error, message: &str) -> AppError {
+    let detail = format!("{:?}", e);
+    if detail.contains("INVALID_PUBLIC_TOKEN") || detail.contains("INVALID_ACCESS_TOKEN") {
+        AppError::validation(message)
+    } else if detail.contains("ITEM_LOGIN_REQUIRED") {
+        AppError::permission_denied("Bank connection requires re-authentication")
+    } else if detail.contains("INVALID_INSTITUTION") {
+        AppError::not_found("Institution not found")
+    } else if detail.contains("RATE_LIMIT_EXCEEDED") {
+        AppError::rate_limited(message, None)
+    } else {
+        AppError::upstream("plaid", message)
+    }
+}
+
+/// Every persisted account of the user, sent when a stream opens or falls behind
+async fn balance_snapshot(
+    account_repository: &BankAccountRepository,
+    user_id: Uuid,
+) -> Result<BalanceEvent, Status> {
+    let accounts = account_repository.list_by_user(user_id).await.map_err(|e| {
+        error!("Failed to load balances: {:?}", e);
+        AppError::internal("Failed to load balances")
+    })?;
+
+    Ok(BalanceEvent {
+        accounts: accounts
+            .iter()
+            .map(|account| AccountsHandler::account_to_proto(&account.to_bank_account()))
+            .collect(),
+        snapshot: true,
+        heartbeat: false,
+        sent_at: Utc::now().timestamp(),
+    })
+}
+
+/// Forward a user's balance updates to one stream until the client disconnects.
+///
+/// Each stream has its own bounded buffer, so a slow client only stalls its own task.
+/// If the task falls behind the user's topic it skips the missed updates and sends a
+/// fresh snapshot instead.
+#[instrument(skip(updates, account_repository, initial, tx))]
+async fn forward_balance_updates(
+    user_id: Uuid,
+    mut updates: BalanceSubscription,
+    account_repository: BankAccountRepository,
+    initial: BalanceEvent,
+    tx: mpsc::Sender<Result<BalanceEvent, Status>>,
+) {
+    if tx.send(Ok(initial)).await.is_err() {
+        return;
+    }
+
+    let mut heartbeat = tokio::time::interval(BALANCE_HEARTBEAT_INTERVAL);
+    heartbeat.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    heartbeat.reset();
+
+    loop {
+        let event = tokio::select! {
+            _ = tx.closed() => break,
+            _ = heartbeat.tick() => BalanceEvent {
+                heartbeat: true,
+                sent_at: Utc::now().timestamp(),
+                ..Default::default()
+            },
+            update = updates.recv() => match update {
+                Ok(update) => BalanceEvent {
+                    accounts: update.accounts.iter().map(AccountsHandler::account_to_proto).collect(),
+                    snapshot: false,
+                    heartbeat: false,
+                    sent_at: Utc::now().timestamp(),
+                },
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!(skipped, "Balance stream fell behind, resending snapshot");
+                    match balance_snapshot(&account_repository, user_id).await {
+                        Ok(snapshot) => snapshot,
+                        Err(status) => {
+                            let _ = tx.send(Err(status)).await;
+                            break;
+                        }
+                    }
+                }
+                Err(RecvError::Closed) => break,
+            },
+        };
+
+        match tokio::time::timeout(BALANCE_SEND_TIMEOUT, tx.send(Ok(event))).await {
+            Ok(Ok(())) => heartbeat.reset(),
+            Ok(Err(_)) => break,
+            Err(_) => {
+                warn!("Balance stream client stopped reading, disconnecting");
+                break;
+            }
+        }
+    }
+
+    debug!("Balance stream closed");
+}
+
+fn net_worth_point(snapshot: &NetWorthSnapshot) -> NetWorthPoint {
+    NetWorthPoint {
+        date: snapshot.snapshot_date.format("%Y-%m-%d").to_string(),
+        currency_code: snapshot.currency_code.clone(),
+        assets: snapshot.assets,
+        liabilities: snapshot.liabilities,
+        net_worth: snapshot.net_worth,
+        account_count: snapshot.account_count,
+    }
+}
+
+fn balance_point(snapshot: &BalanceSnapshot) -> BalancePoint {
+    BalancePoint {
+        date: snapshot.recorded_at.date_naive().format("%Y-%m-%d").to_string(),
+        recorded_at: snapshot.recorded_at.timestamp(),
+        available: snapshot.available_balance,
+        current: snapshot.current_balance,
+        iso_currency_code: snapshot.iso_currency_code.clone(),
+        unofficial_currency_code: snapshot.unofficial_currency_code.clone(),
+    }
+}
+
+fn granularity_from_proto(value: i32) -> Result<Granularity, AppError> {
+    match NetWorthGranularity::try_from(value) {
+        Ok(NetWorthGranularity::Unspecified) | Ok(NetWorthGranularity::Day) => Ok(Granularity::Day),
+        Ok(NetWorthGranularity::Week) => Ok(Granularity::Week),
+        Ok(NetWorthGranularity::Month) => Ok(Granularity::Month),
+        Err(_) => Err(AppError::validation("Unknown granularity")),
+    }
+}
+
+fn spending_amount(total: &SpendingTotal) -> SpendingAmount {
+    SpendingAmount {
+        key: total.group_key.clone(),
+        currency_code: total.currency_code.clone(),
+        amount: total.amount,
+        transaction_count: total.transaction_count,
+        previous_amount: total.previous_amount,
+        previous_transaction_count: total.previous_transaction_count,
+        change_percent: total.change_percent(),
+    }
+}
+
+/// Validate and upper-case an ISO-4217 code
+fn normalize_currency(value: &str) -> Result<String, AppError> {
+    let value = value.trim();
+    if value.len() != 3 || !value.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(AppError::validation("Currency must be a three-letter ISO-4217 code"));
+    }
+    Ok(value.to_ascii_uppercase())
+}
+
+/// Fill in the display-currency amounts of a spending row
+fn convert_spending_amount(amount: &mut SpendingAmount, rates: &FxRates, display_currency: &str) {
+    amount.converted_amount = rates.convert(amount.amount, &amount.currency_code, display_currency);
+    amount.converted_previous_amount = rates.convert(amount.previous_amount, &amount.currency_code, display_currency);
+}
+
+/// Sum converted per-currency totals into one display-currency total; returns the currencies that could not be converted
+fn combine_spending_totals(totals: &[SpendingAmount], display_currency: &str) -> (SpendingAmount, Vec<String>) {
+    let mut combined = SpendingAmount {
+        currency_code: display_currency.to_string(),
+        ..Default::default()
+    };
+    let mut unconverted = Vec::new();
+
+    for total in totals {
+        match (total.converted_amount, total.converted_previous_amount) {
+            (Some(amount), Some(previous_amount)) => {
+                combined.amount += amount;
+                combined.previous_amount += previous_amount;
+                combined.transaction_count += total.transaction_count;
+                combined.previous_transaction_count += total.previous_transaction_count;
+            }
+            _ => unconverted.push(total.currency_code.clone()),
+        }
+    }
+
+    combined.converted_amount = Some(combined.amount);
+    combined.converted_previous_amount = Some(combined.previous_amount);
+    combined.change_percent = if combined.previous_amount.abs() < f64::EPSILON {
+        None
+    } else {
+        Some((combined.amount - combined.previous_amount) / combined.previous_amount * 100.0)
+    };
+    (combined, unconverted)
+}
+
+/// Combine each date's per-currency snapshots into one display-currency point
+fn convert_net_worth(
+    snapshots: &[NetWorthSnapshot],
+    rates: &FxRates,
+    display_currency: &str,
+) -> (Vec<NetWorthPoint>, Vec<String>) {
+    let mut points: Vec<NetWorthPoint> = Vec::new();
+    let mut unconverted: Vec<String> = Vec::new();
+
+    // Snapshots arrive ordered by date, so each date's rows are contiguous
+    for snapshot in snapshots {
+        let converted = (
+            rates.convert(snapshot.assets, &snapshot.currency_code, display_currency),
+            rates.convert(snapshot.liabilities, &snapshot.currency_code, display_currency),
+        );
+        let (Some(assets), Some(liabilities)) = converted else {
+            if !unconverted.contains(&snapshot.currency_code) {
+                unconverted.push(snapshot.currency_code.clone());
+            }
+            continue;
+        };
+
+        let date = snapshot.snapshot_date.format("%Y-%m-%d").to_string();
+        if !matches!(points.last(), Some(point) if point.date == date) {
+            points.push(NetWorthPoint {
+                date,
+                currency_code: display_currency.to_string(),
+                ..Default::default()
+            });
+        }
+        if let Some(point) = points.last_mut() {
+            point.assets += assets;
+            point.liabilities += liabilities;
+            point.net_worth += assets - liabilities;
+            point.account_count += snapshot.account_count;
+        }
+    }
+
+    (points, unconverted)
+}
+
+fn spending_group_by_from_proto(value: i32) -> Result<SpendingGroupBy, AppError> {
+    match ProtoSpendingGroupBy::try_from(value) {
+        Ok(ProtoSpendingGroupBy::Unspecified) | Ok(ProtoSpendingGroupBy::Category) => Ok(SpendingGroupBy::Category),
+        Ok(ProtoSpendingGroupBy::Merchant) => Ok(SpendingGroupBy::Merchant),
+        Ok(ProtoSpendingGroupBy::Month) => Ok(SpendingGroupBy::Month),
+        Err(_) => Err(AppError::validation("Unknown group_by")),
+    }
+}
+
+fn export_format_from_proto(value: i32) -> Result<ExportFormat, AppError> {
+    match ProtoExportFormat::try_from(value) {
+        Ok(ProtoExportFormat::Unspecified) | Ok(ProtoExportFormat::Csv) => Ok(ExportFormat::Csv),
+        Ok(ProtoExportFormat::JsonLines) => Ok(ExportFormat::JsonLines),
+        Err(_) => Err(AppError::validation("Unknown export format")),
+    }
+}
+
+/// Page through the filtered transactions and send each page as one chunk, so memory stays bounded by a page
+async fn export_transactions(
+    user_id: Uuid,
+    filter: TransactionFilter,
+    format: ExportFormat,
+    transaction_repository: TransactionRepository,
+    category_repository: TransactionCategoryRepository,
+    tx: mpsc::Sender<Result<ExportTransactionsChunk, Status>>,
+) {
+    let mut after: Option<(NaiveDate, String)> = None;
+    let mut data = format.header().unwrap_or_default();
+    let mut content_type = format.content_type().to_string();
+    let mut exported = 0;
+
+    loop {
+        let page = match transaction_repository
+            .list_page_after(user_id, &filter, after.as_ref().map(|(date, id)| (*date, id.as_str())))
+            .await
+        {
+            Ok(page) => page,
+            Err(e) => {
+                error!("Failed to load transactions for export: {:?}", e);
+                let _ = tx.send(Err(AppError::internal("Failed to export transactions").into())).await;
+                return;
+            }
+        };
+
+        let ids: Vec<String> = page.iter().map(|t| t.transaction_id.clone()).collect();
+        let categories = match category_repository.find_for_transactions(user_id, &ids).await {
+            Ok(categories) => categories,
+            Err(e) => {
+                error!("Failed to load transaction categories for export: {:?}", e);
+                let _ = tx.send(Err(AppError::internal("Failed to export transactions").into())).await;
+                return;
+            }
+        };
+
+        for transaction in &page {
+            let category = categories.get(&transaction.transaction_id).map(|c| c.category.as_str());
+            match ExportRecord::new(transaction, category).to_line(format) {
+                Ok(line) => data.push_str(&line),
+                Err(e) => {
+                    error!("Failed to format transaction for export: {:?}", e);
+                    let _ = tx.send(Err(AppError::internal("Failed to export transactions").into())).await;
+                    return;
+                }
+            }
+        }
+
+        let last_page = (page.len() as i64) < EXPORT_PAGE_SIZE;
+        exported += page.len();
+        after = page.last().map(|t| (t.date, t.transaction_id.clone()));
+
+        // An empty export still sends the header, so clients always receive a well-formed file
+        if !data.is_empty() || exported == 0 {
+            let chunk = ExportTransactionsChunk {
+                data: std::mem::take(&mut data).into_bytes(),
+                row_count: page.len() as i32,
+                content_type: std::mem::take(&mut content_type),
+            };
+            if tx.send(Ok(chunk)).await.is_err() {
+                debug!(exported, "Export stream closed by client");
+                return;
+            }
+        }
+        if last_page {
+            break;
+        }
+    }
+
+    info!(user_id = %user_id, exported, "Transaction export finished");
+}
+
+fn import_format_from_proto(format: i32, mapping: Option<CsvColumnMapping>) -> Result<ImportFormat, AppError> {
+    match ProtoImportFormat::try_from(format) {
+        Ok(ProtoImportFormat::Csv) => Ok(ImportFormat::Csv(csv_mapping_from_proto(mapping)?)),
+        Ok(ProtoImportFormat::Ofx) => Ok(ImportFormat::Ofx),
+        Ok(ProtoImportFormat::Unspecified) => Err(AppError::validation("Import format is required")),
+        Err(_) => Err(AppError::validation("Unknown import format")),
+    }
+}
+
+/// Column mapping for a CSV import; unset fields keep the defaults
+fn csv_mapping_from_proto(mapping: Option<CsvColumnMapping>) -> Result<CsvMapping, AppError> {
+    let defaults = CsvMapping::default();
+    let Some(mapping) = mapping else {
+        return Ok(defaults);
+    };
+    let non_empty = |value: String, default: String| if value.trim().is_empty() { default } else { value };
+
+    let delimiter = match mapping.delimiter.as_deref() {
+        None | Some("") => defaults.delimiter,
+        Some(d) if d.chars().count() == 1 && d != "\"" && d != "\n" => d.chars().next().unwrap_or(defaults.delimiter),
+        Some(d) => return Err(AppError::validation(format!("Invalid delimiter '{}'", d))),
+    };
+    let has_split_columns = mapping.debit_column.is_some() || mapping.credit_column.is_some();
+
+    Ok(CsvMapping {
+        date_column: non_empty(mapping.date_column, defaults.date_column),
+        date_format: non_empty(mapping.date_format, defaults.date_format),
+        description_column: non_empty(mapping.description_column, defaults.description_column),
+        amount_column: match mapping.amount_column {
+            Some(column) => Some(column),
+            None if has_split_columns => None,
+            None => defaults.amount_column,
+        },
+        debit_column: mapping.debit_column,
+        credit_column: mapping.credit_column,
+        merchant_column: mapping.merchant_column,
+        currency_column: mapping.currency_column,
+        outflows_negative: !mapping.outflows_positive,
+        delimiter,
+    })
+}
+
+/// Imported record in Plaid's transaction shape, so it is stored alongside synced transactions
+fn imported_bank_transaction(
+    transaction: &import::ParsedTransaction,
+    transaction_id: String,
+    account: &StoredBankAccount,
+) -> BankTransaction {
+    BankTransaction {
+        transaction_id,
+        account_id: account.account_id.clone(),
+        amount: transaction.amount,
+        iso_currency_code: transaction
+            .iso_currency_code
+            .clone()
+            .or_else(|| account.iso_currency_code.clone()),
+        unofficial_currency_code: None,
+        category: Vec::new(),
+        category_id: None,
+        check_number: None,
+        date: transaction.date.format("%Y-%m-%d").to_string(),
+        datetime: None,
+        authorized_date: None,
+        authorized_datetime: None,
+        location: None,
+        name: transaction.name.clone(),
+        merchant_name: transaction.merchant_name.clone(),
+        original_description: Some(transaction.name.clone()),
+        payment_meta: None,
+        pending: false,
+        pending_transaction_id: None,
+        account_owner: None,
+        transaction_type: "other".to_string(),
+        transaction_code: None,
+    }
+}
+
+fn identity_to_proto(identity: &StoredAccountIdentity, user_email: &str) -> GetAccountIdentityResponse {
+    let contact = |c: &crate::adapter::plaid::IdentityContact| ProtoIdentityContact {
+        data: c.data.clone(),
+        primary: c.primary,
+        r#type: c.contact_type.clone(),
+    };
+
+    GetAccountIdentityResponse {
+        account_id: identity.account_id.clone(),
+        owners: identity
+            .owners
+            .iter()
+            .map(|owner| ProtoIdentityOwner {
+                names: owner.names.clone(),
+                emails: owner.emails.iter().map(contact).collect(),
+                phone_numbers: owner.phone_numbers.iter().map(contact).collect(),
+                addresses: owner
+                    .addresses
+                    .iter()
+                    .map(|a| ProtoIdentityAddress {
+                        street: a.street.clone(),
+                        city: a.city.clone(),
+                        region: a.region.clone(),
+                        postal_code: a.postal_code.clone(),
+                        country: a.country.clone(),
+                        primary: a.primary,
+                    })
+                    .collect(),
+            })
+            .collect(),
+        fetched_at: identity.fetched_at.timestamp(),
+        email_matches_user: identity.has_owner_email(user_email),
+    }
+}
+
+fn balances_response(balances: &CachedBalances, from_cache: bool) -> RefreshBalancesResponse {
+    RefreshBalancesResponse {
+        accounts: balances.accounts.iter().map(AccountsHandler::account_to_proto).collect(),
+        refreshed_at: balances.refreshed_at.timestamp(),
+        from_cache,
+    }
+}
+
+fn sync_result_to_proto(outcome: &ItemSyncOutcome) -> ItemSyncResult {
+    ItemSyncResult {
+        item_id: outcome.item_id.clone(),
+        succeeded: outcome.succeeded(),
+        skipped: outcome.skipped,
+        added: outcome.summary.added as i32,
+        modified: outcome.summary.modified as i32,
+        removed: outcome.summary.removed as i32,
+        error: outcome.error.clone(),
+    }
+}
+
+#[tonic::async_trait]
+impl AccountsService for AccountsHandler {
+    type ExportTransactionsStream =
+        Pin<Box<dyn Stream<Item = Result<ExportTransactionsChunk, Status>> + Send + 'static>>;
+
+    #[instrument(skip(self, request))]
+    async fn export_transactions(
+        &self,
+        request: Request<ExportTransactionsRequest>,
+    ) -> Result<Response<Self::ExportTransactionsStream>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::TransactionsRead)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!("Exporting transactions");
+
+        let format = export_format_from_proto(req.format)?;
+        let account_id = req.account_id.filter(|id| !id.is_empty());
+        let owner_id = self
+            .transactions_owner(user_id, account_id.as_slice(), ShareAccess::Read)
+            .await?;
+
+        let filter = TransactionFilter {
+            account_id,
+            start_date: parse_date(req.start_date.as_deref(), "start_date")?,
+            end_date: parse_date(req.end_date.as_deref(), "end_date")?,
+            limit: EXPORT_PAGE_SIZE,
+            offset: 0,
+            include_details: false,
+        };
+        if let (Some(start), Some(end)) = (filter.start_date, filter.end_date) {
+            if start > end {
+                return Err(AppError::validation("start_date must not be after end_date").into());
+            }
+        }
+
+        let (tx, rx) = mpsc::channel(EXPORT_STREAM_BUFFER);
+        tokio::spawn(export_transactions(
+            owner_id,
+            filter,
+            format,
+            self.transaction_repository.clone(),
+            self.category_repository.clone(),
+            tx,
+        ));
+
+        let stream = futures::stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|chunk| (chunk, rx))
+        });
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    type StreamBalancesStream = Pin<Box<dyn Stream<Item = Result<BalanceEvent, Status>> + Send + 'static>>;
+
+    #[instrument(skip(self, request))]
+    async fn create_link_token(
+        &self,
+        request: Request<CreateLinkTokenRequest>,
+    ) -> Result<Response<CreateLinkTokenResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsWrite)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!("Creating link token");
+
+        let provider = self.providers.for_country(req.country_code.as_deref());
+        let mut link_request = LinkTokenRequest {
+            user_id: user_id.to_string(),
+            redirect_uri: req.redirect_uri,
+            ..Default::default()
+        };
+        if let Some(country_code) = req.country_code {
+            link_request.country_codes = vec![country_code.trim().to_ascii_uppercase()];
+        }
+
+        let link_token = provider
+            .create_link_token(link_request)
+            .await
+            .map_err(|e| {
+                error!("Failed to create link token: {:?}", e);
+                map_plaid_error(e, "Failed to create link token")
+            })?;
+
+        info!(user_id = %user_id, provider = provider.name(), "Link token created successfully");
+        Ok(Response::new(CreateLinkTokenResponse {
+            link_token: link_token.link_token,
+            expiration: link_token.expiration.timestamp(),
+            provider: provider.name().to_string(),
+        }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn exchange_public_token(
+        &self,
+        request: Request<ExchangePublicTokenRequest>,
+    ) -> Result<Response<ExchangePublicTokenResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsWrite)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!("Exchanging public token");
+
+        if req.public_token.is_empty() {
+            return Err(AppError::validation("Public token is required").into());
+        }
+        let provider = match req.provider.as_deref() {
+            Some(name) => self
+                .providers
+                .get(name)
+                .map_err(|_| AppError::validation(format!("Unknown provider '{}'", name)))?,
+            None => self.providers.default_provider(),
+        };
+
+        let response = self.link_item(user_id, provider, req.public_token).await?;
+        Ok(Response::new(response))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn link_exchange_account(
+        &self,
+        request: Request<LinkExchangeAccountRequest>,
+    ) -> Result<Response<ExchangePublicTokenResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsWrite)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!("Linking exchange account");
+
+        let name = if req.provider.is_empty() { COINBASE_PROVIDER } else { req.provider.as_str() };
+        if name != COINBASE_PROVIDER {
+            return Err(AppError::validation(format!("Unsupported exchange '{}'", name)).into());
+        }
+        let credentials = CoinbaseCredentials {
+            api_key: req.api_key.trim().to_string(),
+            api_secret: req.api_secret.trim().to_string(),
+        };
+        if credentials.api_key.is_empty() || credentials.api_secret.is_empty() {
+            return Err(AppError::validation("API key and secret are required").into());
+        }
+        if credentials.api_key.contains(':') {
+            return Err(AppError::validation("Invalid API key").into());
+        }
+        let provider = self
+            .providers
+            .get(name)
+            .map_err(|_| AppError::validation(format!("Exchange '{}' is not configured", name)))?;
+
+        let response = self.link_item(user_id, provider, credentials.encode()).await?;
+        Ok(Response::new(response))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn list_bank_accounts(
+        &self,
+        request: Request<ListBankAccountsRequest>,
+    ) -> Result<Response<ListBankAccountsResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsRead)?;
+        let user_id = auth.user_id;
+        debug!("Listing persisted bank accounts");
+
+        let accounts = self
+            .account_repository
+            .list_by_user(user_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to list bank accounts: {:?}", e);
+                AppError::internal("Failed to list bank accounts")
+            })?;
+        let shared = self
+            .account_repository
+            .list_shared_with(user_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to list shared bank accounts: {:?}", e);
+                AppError::internal("Failed to list bank accounts")
+            })?;
+        let accounts: Vec<(&StoredBankAccount, Option<&str>)> = accounts
+            .iter()
+            .map(|account| (account, None))
+            .chain(shared.iter().map(|s| (&s.account, Some(s.access.as_str()))))
+            .collect();
+
+        let mut institution_ids: Vec<String> = accounts.iter().filter_map(|(a, _)| a.institution_id.clone()).collect();
+        institution_ids.sort();
+        institution_ids.dedup();
+        let institutions = self.institutions(&institution_ids).await;
+
+        info!(
+            user_id = %user_id,
+            account_count = accounts.len(),
+            shared_count = shared.len(),
+            "Listed bank accounts"
+        );
+        Ok(Response::new(ListBankAccountsResponse {
+            accounts: accounts
+                .iter()
+                .map(|(account, shared_access)| {
+                    let mut proto = Self::account_to_proto(&account.to_bank_account());
+                    proto.shared_access = shared_access.map(str::to_string);
+                    proto.institution = account
+                        .institution_id
+                        .as_ref()
+                        .and_then(|id| institutions.get(id))
+                        .map(Self::institution_to_proto);
+                    if proto.institution_name.is_none() {
+                        proto.institution_name = proto.institution.as_ref().map(|i| i.name.clone());
+                    }
+                    proto
+                })
+                .collect(),
+        }))
+    }
+
+    #[instrument(skip(self, request), fields(institution_id = %request.get_ref().institution_id))]
+    async fn get_institution(
+        &self,
+        request: Request<GetInstitutionRequest>,
+    ) -> Result<Response<ProtoInstitution>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsRead)?;
+        let req = request.into_inner();
+        debug!("Getting institution");
+
+        if req.institution_id.is_empty() {
+            return Err(AppError::validation("Institution ID is required").into());
+        }
+
+        let cached = self
+            .institution_cache
+            .get_many(std::slice::from_ref(&req.institution_id))
+            .await
+            .unwrap_or_else(|e| {
+                warn!("Failed to read cached institution: {:?}", e);
+                HashMap::new()
+            })
+            .remove(&req.institution_id);
+        let institution = match cached {
+            Some(institution) => institution,
+            None => {
+                let institution = self
+                    .providers
+                    .default_provider()
+                    .get_institution(&req.institution_id, INSTITUTION_COUNTRY_CODES.to_vec())
+                    .await
+                    .map_err(|e| map_plaid_error(e, "Failed to get institution"))?;
+                if let Err(e) = self.institution_cache.put(&institution).await {
+                    warn!("Failed to cache institution: {:?}", e);
+                }
+                institution
+            }
+        };
+
+        Ok(Response::new(Self::institution_to_proto(&institution)))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn list_transactions(
+        &self,
+        request: Request<ListTransactionsRequest>,
+    ) -> Result<Response<ListTransactionsResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::TransactionsRead)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!("Listing transactions");
+
+        let read_mask = ReadMask::from_proto(req.read_mask, TRANSACTION_FIELDS)?;
+        let page_size = page_size(req.page_size, DEFAULT_TRANSACTION_PAGE_SIZE, MAX_TRANSACTION_PAGE_SIZE);
+
+        let account_id = req.account_id.filter(|id| !id.is_empty());
+        let owner_id = self
+            .transactions_owner(user_id, account_id.as_slice(), ShareAccess::Read)
+            .await?;
+
+        let filter = TransactionFilter {
+            account_id,
+            start_date: parse_date(req.start_date.as_deref(), "start_date")?,
+            end_date: parse_date(req.end_date.as_deref(), "end_date")?,
+            limit: page_size as i64,
+            offset: req.offset.max(0) as i64,
+            include_details: TRANSACTION_DETAIL_FIELDS.iter().any(|f| read_mask.includes(f)),
+        };
+
+        let transactions = self
+            .transaction_repository
+            .list_by_user(owner_id, &filter)
+            .await
+            .map_err(|e| {
+                error!("Failed to list transactions: {:?}", e);
+                AppError::internal("Failed to list transactions")
+            })?;
+
+        let transactions = self
+            .transactions_to_proto(owner_id, &transactions, &read_mask, "Failed to list transactions")
+            .await?;
+
+        info!(
+            user_id = %user_id,
+            transaction_count = transactions.len(),
+            masked = !read_mask.is_all(),
+            "Listed transactions"
+        );
+        Ok(Response::new(ListTransactionsResponse { transactions }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn search_transactions(
+        &self,
+        request: Request<SearchTransactionsRequest>,
+    ) -> Result<Response<SearchTransactionsResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::TransactionsRead)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!("Searching transactions");
+
+        let read_mask = ReadMask::from_proto(req.read_mask, TRANSACTION_FIELDS)?;
+        let page_size = page_size(req.page_size, DEFAULT_TRANSACTION_PAGE_SIZE, MAX_TRANSACTION_PAGE_SIZE);
+        let after: Option<TransactionPageToken> = if req.page_token.is_empty() {
+            None
+        } else {
+            Some(decode_cursor(&req.page_token, "page token")?)
+        };
+
+        if req.min_amount.is_some_and(|a| a < 0.0) || req.max_amount.is_some_and(|a| a < 0.0) {
+            return Err(AppError::validation("Amount bounds must not be negative").into());
+        }
+        if let (Some(min), Some(max)) = (req.min_amount, req.max_amount) {
+            if min > max {
+                return Err(AppError::validation("min_amount must not exceed max_amount").into());
+            }
+        }
+        let categories = req
+            .categories
+            .iter()
+            .map(|c| {
+                taxonomy_category(c)
+                    .map(str::to_string)
+                    .ok_or_else(|| AppError::validation(format!("Unknown category '{}'", c)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let account_ids: Vec<String> = req.account_ids.into_iter().filter(|id| !id.is_empty()).collect();
+        let owner_id = self.transactions_owner(user_id, &account_ids, ShareAccess::Read).await?;
+
+        let search = TransactionSearch {
+            query: Some(req.query).filter(|q| !q.trim().is_empty()),
+            min_amount: req.min_amount,
+            max_amount: req.max_amount,
+            start_date: parse_date(req.start_date.as_deref(), "start_date")?,
+            end_date: parse_date(req.end_date.as_deref(), "end_date")?,
+            categories,
+            account_ids,
+            pending: req.pending,
+            limit: page_size as i64 + 1,
+            include_details: TRANSACTION_DETAIL_FIELDS.iter().any(|f| read_mask.includes(f)),
+        };
+
+        let transactions = self
+            .transaction_repository
+            .search(
+                owner_id,
+                &search,
+                after.as_ref().map(|t| (t.date, t.transaction_id.as_str())),
+            )
+            .await
+            .map_err(|e| {
+                error!("Failed to search transactions: {:?}", e);
+                AppError::internal("Failed to search transactions")
+            })?;
+        let (transactions, has_more) = split_page(transactions, page_size as usize);
+
+        let next_page_token = match transactions.last() {
+            Some(last) if has_more => encode_cursor(&TransactionPageToken {
+                date: last.date,
+                transaction_id: last.transaction_id.clone(),
+            }),
+            _ => String::new(),
+        };
+        let transactions = self
+            .transactions_to_proto(owner_id, &transactions, &read_mask, "Failed to search transactions")
+            .await?;
+
+        info!(user_id = %user_id, transaction_count = transactions.len(), has_more, "Searched transactions");
+        Ok(Response::new(SearchTransactionsResponse {
+            transactions,
+            next_page_token,
+        }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn semantic_search_transactions(
+        &self,
+        request: Request<SemanticSearchTransactionsRequest>,
+    ) -> Result<Response<SemanticSearchTransactionsResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::TransactionsRead)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!("Semantic transaction search");
+
+        let embedder = self
+            .transaction_embedder
+            .as_ref()
+            .ok_or_else(|| AppError::upstream("embeddings", "Semantic search is not configured"))?;
+        let query = req.query.trim();
+        if query.is_empty() {
+            return Err(AppError::validation("query must not be empty").into());
+        }
+        if query.chars().count() > MAX_SEMANTIC_QUERY_CHARS {
+            return Err(AppError::validation(format!("query can have at most {} characters", MAX_SEMANTIC_QUERY_CHARS)).into());
+        }
+        let read_mask = ReadMask::from_proto(req.read_mask, TRANSACTION_FIELDS)?;
+        let page_size = page_size(req.page_size, DEFAULT_SEMANTIC_PAGE_SIZE, MAX_SEMANTIC_PAGE_SIZE);
+
+        // Explicit dates win over a range named in the query, such as "last month"
+        let mut start_date = parse_date(req.start_date.as_deref(), "start_date")?;
+        let mut end_date = parse_date(req.end_date.as_deref(), "end_date")?;
+        if start_date.is_none() && end_date.is_none() {
+            if let Some((start, end)) = relative_date_range(query, Utc::now().date_naive()) {
+                start_date = Some(start);
+                end_date = Some(end);
+            }
+        }
+
+        let account_ids: Vec<String> = req.account_ids.into_iter().filter(|id| !id.is_empty()).collect();
+        let owner_id = self.transactions_owner(user_id, &account_ids, ShareAccess::Read).await?;
+
+        let matches = embedder
+            .search(
+                owner_id,
+                query,
+                &SemanticSearch {
+                    start_date,
+                    end_date,
+                    account_ids,
+                    max_distance: Some(MAX_SEMANTIC_DISTANCE),
+                    limit: page_size as i64,
+                },
+            )
+            .await
+            .map_err(|e| {
+                error!("Failed semantic transaction search: {:?}", e);
+                AppError::upstream("embeddings", "Failed to search transactions")
+            })?;
+
+        let (transactions, distances): (Vec<Transaction>, Vec<f64>) =
+            matches.into_iter().map(|m| (m.transaction, m.distance)).unzip();
+        let transactions = self
+            .transactions_to_proto(owner_id, &transactions, &read_mask, "Failed to search transactions")
+            .await?;
+
+        info!(user_id = %user_id, match_count = transactions.len(), "Semantic transaction search completed");
+        Ok(Response::new(SemanticSearchTransactionsResponse {
+            matches: transactions
+                .into_iter()
+                .zip(distances)
+                .map(|(transaction, distance)| SemanticMatch {
+                    transaction: Some(transaction),
+                    similarity: 1.0 - distance,
+                })
+                .collect(),
+            start_date: start_date.map(|d| d.to_string()).unwrap_or_default(),
+            end_date: end_date.map(|d| d.to_string()).unwrap_or_default(),
+        }))
+    }
+
+    #[instrument(skip(self, request), fields(transaction_id = %request.get_ref().transaction_id))]
+    async fn set_transaction_category(
+        &self,
+        request: Request<SetTransactionCategoryRequest>,
+    ) -> Result<Response<SetTransactionCategoryResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::TransactionsWrite)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!(category = ?req.category, "Setting transaction category");
+
+        if req.transaction_id.is_empty() {
+            return Err(AppError::validation("Transaction ID is required").into());
+        }
+
+        // Categories are stored against the account owner, so a full-access grantee edits theirs
+        let transaction = self
+            .require_transaction_access(user_id, &req.transaction_id, ShareAccess::Full)
+            .await?;
+        let owner_id = transaction.user_id;
+
+        let categorization = match req.category.as_deref().filter(|c| !c.is_empty()) {
+            Some(category) => {
+                let category = taxonomy_category(category)
+                    .ok_or_else(|| AppError::validation(format!("Unknown category '{}'", category)))?;
+                let stored = self
+                    .category_repository
+                    .set_manual(owner_id, &req.transaction_id, category)
+                    .await
+                    .map_err(|e| {
+                        error!("Failed to set transaction category: {:?}", e);
+                        AppError::internal("Failed to set transaction category")
+                    })?
+                    .ok_or_else(|| AppError::not_found("Transaction not found"))?;
+                Some(Self::categorization_to_proto(&stored))
+            }
+            None => {
+                let cleared = self
+                    .category_repository
+                    .clear_manual(owner_id, &req.transaction_id)
+                    .await
+                    .map_err(|e| {
+                        error!("Failed to clear transaction category: {:?}", e);
+                        AppError::internal("Failed to clear transaction category")
+                    })?;
+                debug!(cleared, "Cleared transaction category override");
+
+                // Without an override the AI category is unchanged; a removed override is recategorized on the next run
+                self.category_repository
+                    .find_for_transactions(owner_id, std::slice::from_ref(&req.transaction_id))
+                    .await
+                    .map_err(|e| {
+                        error!("Failed to load transaction category: {:?}", e);
+                        AppError::internal("Failed to clear transaction category")
+                    })?
+                    .get(&req.transaction_id)
+                    .map(Self::categorization_to_proto)
+            }
+        };
+
+        info!(user_id = %user_id, overridden = req.category.is_some(), "Transaction category updated");
+        Ok(Response::new(SetTransactionCategoryResponse { categorization }))
+    }
+
+    #[instrument(skip(self, request), fields(transaction_id = %request.get_ref().transaction_id))]
+    async fn set_transaction_notes(
+        &self,
+        request: Request<SetTransactionNotesRequest>,
+    ) -> Result<Response<SetTransactionNotesResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::TransactionsWrite)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!("Setting transaction notes");
+
+        if req.transaction_id.is_empty() {
+            return Err(AppError::validation("Transaction ID is required").into());
+        }
+        let notes = req.notes.as_deref().map(str::trim).filter(|n| !n.is_empty());
+        if notes.is_some_and(|n| n.chars().count() > MAX_NOTES_CHARS) {
+            return Err(AppError::validation(format!("Notes may be at most {} characters", MAX_NOTES_CHARS)).into());
+        }
+
+        let transaction = self
+            .require_transaction_access(user_id, &req.transaction_id, ShareAccess::Full)
+            .await?;
+        let annotation = self
+            .annotation_repository
+            .set_notes(transaction.user_id, &transaction.transaction_id, notes)
+            .await
+            .map_err(|e| {
+                error!("Failed to set transaction notes: {:?}", e);
+                AppError::internal("Failed to set transaction notes")
+            })?
+            .ok_or_else(|| AppError::not_found("Transaction not found"))?;
+
+        info!(user_id = %user_id, cleared = annotation.notes.is_none(), "Transaction notes updated");
+        Ok(Response::new(SetTransactionNotesResponse { notes: annotation.notes }))
+    }
+
+    #[instrument(skip(self, request), fields(transaction_id = %request.get_ref().transaction_id))]
+    async fn set_transaction_tags(
+        &self,
+        request: Request<SetTransactionTagsRequest>,
+    ) -> Result<Response<SetTransactionTagsResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::TransactionsWrite)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!(tag_count = req.tags.len(), "Setting transaction tags");
+
+        if req.transaction_id.is_empty() {
+            return Err(AppError::validation("Transaction ID is required").into());
+        }
+        let tags = normalize_tags(&req.tags).map_err(AppError::validation)?;
+
+        let transaction = self
+            .require_transaction_access(user_id, &req.transaction_id, ShareAccess::Full)
+            .await?;
+        let annotation = self
+            .annotation_repository
+            .set_tags(transaction.user_id, &transaction.transaction_id, &tags)
+            .await
+            .map_err(|e| {
+                error!("Failed to set transaction tags: {:?}", e);
+                AppError::internal("Failed to set transaction tags")
+            })?
+            .ok_or_else(|| AppError::not_found("Transaction not found"))?;
+
+        info!(user_id = %user_id, tag_count = annotation.tags.len(), "Transaction tags updated");
+        Ok(Response::new(SetTransactionTagsResponse { tags: annotation.tags }))
+    }
+
+    #[instrument(skip(self, request), fields(transaction_id = %request.get_ref().transaction_id))]
+    async fn set_transaction_splits(
+        &self,
+        request: Request<SetTransactionSplitsRequest>,
+    ) -> Result<Response<SetTransactionSplitsResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::TransactionsWrite)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!(part_count = req.splits.len(), "Setting transaction splits");
+
+        if req.transaction_id.is_empty() {
+            return Err(AppError::validation("Transaction ID is required").into());
+        }
+        let splits = req
+            .splits
+            .into_iter()
+            .map(|split| {
+                let category = taxonomy_category(&split.category)
+                    .ok_or_else(|| AppError::validation(format!("Unknown category '{}'", split.category)))?;
+                Ok(NewSplit {
+                    amount: split.amount,
+                    category,
+                    description: split.description.map(|d| d.trim().to_string()).filter(|d| !d.is_empty()),
+                })
+            })
+            .collect::<Result<Vec<_>, AppError>>()?;
+
+        let transaction = self
+            .require_transaction_access(user_id, &req.transaction_id, ShareAccess::Full)
+            .await?;
+        if transaction.removed_at.is_some() {
+            return Err(AppError::not_found("Transaction not found").into());
+        }
+        validate_splits(transaction.amount, &splits).map_err(AppError::validation)?;
+
+        let stored = self
+            .annotation_repository
+            .replace_splits(transaction.user_id, &transaction.transaction_id, &splits)
+            .await
+            .map_err(|e| {
+                error!("Failed to store transaction splits: {:?}", e);
+                AppError::internal("Failed to split transaction")
+            })?;
+
+        info!(user_id = %user_id, part_count = stored.len(), "Transaction splits updated");
+        Ok(Response::new(SetTransactionSplitsResponse {
+            splits: stored.iter().map(Self::split_to_proto).collect(),
+        }))
+    }
+
+    #[instrument(skip(self, request), fields(transaction_id = %request.get_ref().transaction_id))]
+    async fn create_receipt_upload(
+        &self,
+        request: Request<CreateReceiptUploadRequest>,
+    ) -> Result<Response<CreateReceiptUploadResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::TransactionsWrite)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!(content_type = %req.content_type, size_bytes = req.size_bytes, "Creating receipt upload");
+
+        if req.transaction_id.is_empty() {
+            return Err(AppError::validation("Transaction ID is required").into());
+        }
+        let content_type = validate_receipt(&req.content_type, req.size_bytes).map_err(AppError::validation)?;
+        let extension = receipt_extension(&content_type).unwrap_or("bin");
+        let file_name = req.file_name.as_deref().map(str::trim).filter(|n| !n.is_empty());
+        if file_name.is_some_and(|n| n.chars().count() > MAX_RECEIPT_FILE_NAME_CHARS) {
+            return Err(AppError::validation(format!(
+                "File names may be at most {} characters",
+                MAX_RECEIPT_FILE_NAME_CHARS
+            ))
+            .into());
+        }
+        let storage = self.file_storage()?;
+
+        let transaction = self
+            .require_transaction_access(user_id, &req.transaction_id, ShareAccess::Full)
+            .await?;
+        let receipt_id = Uuid::new_v4();
+        let object_key = receipt_object_key(transaction.user_id, &transaction.transaction_id, receipt_id, extension);
+
+        let upload = storage
+            .presigned_upload_url(&object_key, &content_type, req.size_bytes)
+            .await
+            .map_err(|e| {
+                error!("Failed to presign receipt upload: {:?}", e);
+                AppError::internal("Failed to create receipt upload")
+            })?;
+        self.receipt_repository
+            .create_pending(&NewReceipt {
+                id: receipt_id,
+                transaction_id: transaction.transaction_id.clone(),
+                user_id: transaction.user_id,
+                uploaded_by: user_id,
+                object_key,
+                content_type,
+                size_bytes: req.size_bytes,
+                file_name: file_name.map(str::to_string),
+            })
+            .await
+            .map_err(|e| {
+                error!("Failed to record receipt: {:?}", e);
+                AppError::internal("Failed to create receipt upload")
+            })?;
+
+        info!(user_id = %user_id, receipt_id = %receipt_id, "Receipt upload created");
+        Ok(Response::new(CreateReceiptUploadResponse {
+            receipt_id: receipt_id.to_string(),
+            upload_url: upload.url,
+            method: upload.method,
+            headers: upload.headers.into_iter().collect(),
+            expires_at: upload.expires_at.timestamp(),
+        }))
+    }
+
+    #[instrument(skip(self, request), fields(receipt_id = %request.get_ref().receipt_id))]
+    async fn attach_receipt(&self, request: Request<AttachReceiptRequest>) -> Result<Response<ProtoReceipt>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::TransactionsWrite)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!("Attaching receipt");
+
+        let storage = self.file_storage()?;
+        let (receipt, transaction) = self
+            .require_receipt_access(user_id, &req.receipt_id, ShareAccess::Full)
+            .await?;
+        if receipt.status() == Some(ReceiptStatus::Attached) {
+            return Ok(Response::new(Self::receipt_to_proto(&receipt, transaction.amount)));
+        }
+
+        let metadata = storage
+            .head_object(&receipt.object_key)
+            .await
+            .map_err(|e| {
+                error!("Failed to check uploaded receipt: {:?}", e);
+                AppError::internal("Failed to attach receipt")
+            })?
+            .ok_or_else(|| AppError::validation("The receipt file has not been uploaded"))?;
+
+        // The presigned URL pins size and type, but verify before trusting the object
+        let type_matches = !matches!(&metadata.content_type, Some(t) if !t.eq_ignore_ascii_case(&receipt.content_type));
+        if metadata.content_length != receipt.size_bytes || !type_matches {
+            warn!(
+                receipt_id = %receipt.id,
+                declared_size = receipt.size_bytes,
+                uploaded_size = metadata.content_length,
+                "Uploaded receipt does not match its declaration"
+            );
+            if let Err(e) = storage.delete_object(&receipt.object_key).await {
+                warn!(receipt_id = %receipt.id, error = %e, "Failed to delete mismatched receipt upload");
+            }
+            return Err(AppError::validation("The uploaded file does not match the declared size and type").into());
+        }
+
+        let receipt = self
+            .receipt_repository
+            .mark_attached(receipt.id, metadata.content_length)
+            .await
+            .map_err(|e| {
+                error!("Failed to attach receipt: {:?}", e);
+                AppError::internal("Failed to attach receipt")
+            })?
+            .ok_or_else(|| AppError::not_found("Receipt not found"))?;
+
+        info!(user_id = %user_id, receipt_id = %receipt.id, size_bytes = receipt.size_bytes, "Receipt attached");
+        Ok(Response::new(Self::receipt_to_proto(&receipt, transaction.amount)))
+    }
+
+    #[instrument(skip(self, request), fields(transaction_id = %request.get_ref().transaction_id))]
+    async fn list_receipts(
+        &self,
+        request: Request<ListReceiptsRequest>,
+    ) -> Result<Response<ListReceiptsResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::TransactionsRead)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!("Listing receipts");
+
+        if req.transaction_id.is_empty() {
+            return Err(AppError::validation("Transaction ID is required").into());
+        }
+        let transaction = self
+            .require_transaction_access(user_id, &req.transaction_id, ShareAccess::Read)
+            .await?;
+
+        let receipts = self
+            .receipt_repository
+            .list_for_transaction(&transaction.transaction_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to list receipts: {:?}", e);
+                AppError::internal("Failed to list receipts")
+            })?;
+
+        Ok(Response::new(ListReceiptsResponse {
+            receipts: receipts
+                .iter()
+                .map(|receipt| Self::receipt_to_proto(receipt, transaction.amount))
+                .collect(),
+        }))
+    }
+
+    #[instrument(skip(self, request), fields(receipt_id = %request.get_ref().receipt_id))]
+    async fn get_receipt_download_url(
+        &self,
+        request: Request<GetReceiptDownloadUrlRequest>,
+    ) -> Result<Response<GetReceiptDownloadUrlResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::TransactionsRead)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!("Getting receipt download URL");
+
+        let storage = self.file_storage()?;
+        let (receipt, _) = self
+            .require_receipt_access(user_id, &req.receipt_id, ShareAccess::Read)
+            .await?;
+        if receipt.status() != Some(ReceiptStatus::Attached) {
+            return Err(AppError::not_found("Receipt not found").into());
+        }
+
+        let download = storage
+            .presigned_download_url(&receipt.object_key, receipt.file_name.as_deref())
+            .await
+            .map_err(|e| {
+                error!("Failed to presign receipt download: {:?}", e);
+                AppError::internal("Failed to create receipt download URL")
+            })?;
+
+        Ok(Response::new(GetReceiptDownloadUrlResponse {
+            download_url: download.url,
+            expires_at: download.expires_at.timestamp(),
+        }))
+    }
+
+    #[instrument(skip(self, request), fields(receipt_id = %request.get_ref().receipt_id))]
+    async fn delete_receipt(
+        &self,
+        request: Request<DeleteReceiptRequest>,
+    ) -> Result<Response<DeleteReceiptResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::TransactionsWrite)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!("Deleting receipt");
+
+        let storage = self.file_storage()?;
+        let (receipt, _) = self
+            .require_receipt_access(user_id, &req.receipt_id, ShareAccess::Full)
+            .await?;
+
+        storage.delete_object(&receipt.object_key).await.map_err(|e| {
+            error!("Failed to delete receipt file: {:?}", e);
+            AppError::internal("Failed to delete receipt")
+        })?;
+        self.receipt_repository.delete(receipt.id).await.map_err(|e| {
+            error!("Failed to delete receipt: {:?}", e);
+            AppError::internal("Failed to delete receipt")
+        })?;
+
+        info!(user_id = %user_id, receipt_id = %receipt.id, "Receipt deleted");
+        Ok(Response::new(DeleteReceiptResponse { success: true }))
+    }
+
+    #[instrument(skip(self, request), fields(receipt_id = %request.get_ref().receipt_id))]
+    async fn scan_receipt(&self, request: Request<ScanReceiptRequest>) -> Result<Response<ProtoReceipt>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::TransactionsWrite)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!("Scanning receipt");
+
+        let scanner = self
+            .receipt_scanner
+            .as_ref()
+            .ok_or_else(|| AppError::upstream("claude", "Receipt scanning is not configured"))?;
+        let (receipt, transaction) = self
+            .require_receipt_access(user_id, &req.receipt_id, ShareAccess::Full)
+            .await?;
+        if receipt.status() != Some(ReceiptStatus::Attached) {
+            return Err(AppError::validation("Only attached receipts can be scanned").into());
+        }
+        if !ReceiptScanner::supports(&receipt.content_type) {
+            return Err(AppError::validation(format!("{} receipts cannot be scanned", receipt.content_type)).into());
+        }
+
+        check_ai_quota(scanner.ai_usage(), user_id, AiFeature::ReceiptScan).await?;
+        let _permit = admit_ai_request(scanner.rate_limiter(), user_id, AiFeature::ReceiptScan).await?;
+
+        let receipt = scanner.scan(&receipt, user_id).await.map_err(|e| {
+            error!("Failed to scan receipt: {:?}", e);
+            AppError::upstream("claude", "Failed to scan receipt")
+        })?;
+        let response = Self::receipt_to_proto(&receipt, transaction.amount);
+
+        info!(
+            user_id = %user_id,
+            receipt_id = %receipt.id,
+            total_matches = response.total_matches_transaction,
+            "Receipt scanned"
+        );
+        Ok(Response::new(response))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn list_statements(
+        &self,
+        request: Request<ListStatementsRequest>,
+    ) -> Result<Response<ListStatementsResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsRead)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!(account_id = ?req.account_id, year = ?req.year, "Listing statements");
+
+        let statements = match req.account_id.as_deref().filter(|id| !id.is_empty()) {
+            Some(account_id) => {
+                let account = self
+                    .require_account_access(user_id, account_id, ShareAccess::Read)
+                    .await?;
+                self.statement_repository
+                    .list_for_account(&account.account_id, req.year)
+                    .await
+            }
+            None => {
+                let shared_ids: Vec<String> = self
+                    .account_repository
+                    .list_shared_with(user_id)
+                    .await
+                    .map_err(|e| {
+                        error!("Failed to list shared bank accounts: {:?}", e);
+                        AppError::internal("Failed to list statements")
+                    })?
+                    .into_iter()
+                    .map(|shared| shared.account.account_id)
+                    .collect();
+                self.statement_repository
+                    .list_visible(user_id, &shared_ids, req.year)
+                    .await
+            }
+        }
+        .map_err(|e| {
+            error!("Failed to list statements: {:?}", e);
+            AppError::internal("Failed to list statements")
+        })?;
+
+        info!(user_id = %user_id, statement_count = statements.len(), "Listed statements");
+        Ok(Response::new(ListStatementsResponse {
+            statements: statements.iter().map(Self::statement_to_proto).collect(),
+        }))
+    }
+
+    #[instrument(skip(self, request), fields(statement_id = %request.get_ref().statement_id))]
+    async fn get_statement_download_url(
+        &self,
+        request: Request<GetStatementDownloadUrlRequest>,
+    ) -> Result<Response<GetStatementDownloadUrlResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsRead)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!("Getting statement download URL");
+
+        let storage = self.file_storage()?;
+        let statement_id =
+            Uuid::parse_str(&req.statement_id).map_err(|_| AppError::validation("statement_id must be a UUID"))?;
+        let statement = self
+            .statement_repository
+            .find(statement_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to load statement: {:?}", e);
+                AppError::internal("Failed to load statement")
+            })?
+            .ok_or_else(|| AppError::not_found("Statement not found"))?;
+        match self.require_account_access(user_id, &statement.account_id, ShareAccess::Read).await {
+            Ok(_) => {}
+            Err(AppError::NotFound(_)) => return Err(AppError::not_found("Statement not found").into()),
+            Err(e) => return Err(e.into()),
+        }
+
+        let file_name = statement_file_name(statement.year, statement.month);
+        let download = storage
+            .presigned_download_url(&statement.object_key, Some(&file_name))
+            .await
+            .map_err(|e| {
+                error!("Failed to presign statement download: {:?}", e);
+                AppError::internal("Failed to create statement download URL")
+            })?;
+
+        info!(user_id = %user_id, statement_id = %statement.id, "Statement download URL created");
+        Ok(Response::new(GetStatementDownloadUrlResponse {
+            download_url: download.url,
+            expires_at: download.expires_at.timestamp(),
+        }))
+    }
+
+    #[instrument(skip(self, request), fields(year = request.get_ref().year))]
+    async fn generate_tax_report(
+        &self,
+        request: Request<GenerateTaxReportRequest>,
+    ) -> Result<Response<GenerateTaxReportResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::TransactionsRead)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!(format = req.format, "Generating tax report");
+
+        let (start, end) = match (
+            NaiveDate::from_ymd_opt(req.year, 1, 1),
+            NaiveDate::from_ymd_opt(req.year, 12, 31),
+        ) {
+            (Some(start), Some(end)) if req.year >= 1900 && start <= Utc::now().date_naive() => (start, end),
+            _ => return Err(AppError::validation("year must be a past or current calendar year").into()),
+        };
+        let format = ProtoTaxReportFormat::try_from(req.format).unwrap_or(ProtoTaxReportFormat::Unspecified);
+
+        let lines = self
+            .tax_report_repository
+            .lines(user_id, start, end)
+            .await
+            .map_err(|e| {
+                error!("Failed to load transactions for tax report: {:?}", e);
+                AppError::internal("Failed to generate tax report")
+            })?;
+        let report = TaxReport::build(req.year, &lines);
+
+        let (file, content_type, extension) = match format {
+            ProtoTaxReportFormat::Csv => (report.to_csv(), "text/csv", "csv"),
+            ProtoTaxReportFormat::Json | ProtoTaxReportFormat::Unspecified => {
+                let json = report.to_json().map_err(|e| {
+                    error!("Failed to serialize tax report: {:?}", e);
+                    AppError::internal("Failed to generate tax report")
+                })?;
+                (json, "application/json", "json")
+            }
+        };
+
+        info!(
+            user_id = %user_id,
+            year = req.year,
+            line_count = lines.len(),
+            candidate_count = report.deductible_candidates.len(),
+            "Tax report generated"
+        );
+        Ok(Response::new(GenerateTaxReportResponse {
+            year: report.year,
+            buckets: report
+                .buckets
+                .iter()
+                .map(|total| ProtoTaxBucketTotal {
+                    bucket: total.bucket.as_str().to_string(),
+                    currency_code: total.currency_code.clone(),
+                    amount: total.amount,
+                    transaction_count: total.transaction_count as i32,
+                })
+                .collect(),
+            deductible_candidates: report
+                .deductible_candidates
+                .iter()
+                .map(|candidate| ProtoDeductibleCandidate {
+                    transaction_id: candidate.transaction_id.clone(),
+                    date: candidate.date.clone(),
+                    description: candidate.description.clone(),
+                    amount: candidate.amount,
+                    currency_code: candidate.currency_code.clone(),
+                    bucket: candidate.bucket.as_str().to_string(),
+                    reason: candidate.reason.clone(),
+                })
+                .collect(),
+            file: file.into_bytes(),
+            content_type: content_type.to_string(),
+            file_name: format!("tax-report-{}.{}", report.year, extension),
+        }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn list_transaction_categories(
+        &self,
+        request: Request<ListTransactionCategoriesRequest>,
+    ) -> Result<Response<ListTransactionCategoriesResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::TransactionsRead)?;
+
+        Ok(Response::new(ListTransactionCategoriesResponse {
+            categories: CATEGORY_TAXONOMY.iter().map(|c| c.to_string()).collect(),
+        }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn get_spending_summary(
+        &self,
+        request: Request<GetSpendingSummaryRequest>,
+    ) -> Result<Response<GetSpendingSummaryResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::TransactionsRead)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!("Getting spending summary");
+
+        let group_by = spending_group_by_from_proto(req.group_by)?;
+        let display_currency = self.display_currency(user_id, req.display_currency.as_deref()).await?;
+        let limit = page_size(req.limit, DEFAULT_SPENDING_GROUP_LIMIT, MAX_SPENDING_GROUP_LIMIT);
+        let end = parse_date(req.end_date.as_deref(), "end_date")?.unwrap_or_else(|| Utc::now().date_naive());
+        let start = match parse_date(req.start_date.as_deref(), "start_date")? {
+            Some(start) => start,
+            None => end.with_day(1).unwrap_or(end),
+        };
+        if start > end {
+            return Err(AppError::validation("start_date must not be after end_date").into());
+        }
+        let period = SpendingPeriod::new(start, end);
+        if period.days() > MAX_SPENDING_PERIOD_DAYS {
+            return Err(AppError::validation("Spending summary period may span at most one year").into());
+        }
+
+        let (totals, groups) = tokio::join!(
+            self.spending_repository.totals(user_id, period),
+            self.spending_repository.by_group(user_id, period, group_by, limit as i64),
+        );
+        let (totals, groups) = totals.and_then(|totals| Ok((totals, groups?))).map_err(|e| {
+            error!("Failed to summarize spending: {:?}", e);
+            AppError::internal("Failed to summarize spending")
+        })?;
+
+        let mut totals: Vec<SpendingAmount> = totals.iter().map(spending_amount).collect();
+        let mut groups: Vec<SpendingAmount> = groups.iter().map(spending_amount).collect();
+        let rates = self.fx_rates(&display_currency).await?;
+        let (converted_total, unconverted_currencies) = match &rates {
+            Some(rates) => {
+                for amount in totals.iter_mut().chain(groups.iter_mut()) {
+                    convert_spending_amount(amount, rates, &display_currency);
+                }
+                let (total, unconverted) = combine_spending_totals(&totals, &display_currency);
+                (Some(total), unconverted)
+            }
+            None => (None, totals.iter().map(|t| t.currency_code.clone()).collect()),
+        };
+
+        let previous = period.previous();
+        info!(
+            user_id = %user_id,
+            group_count = groups.len(),
+            ?group_by,
+            display_currency = %display_currency,
+            "Spending summary returned"
+        );
+        Ok(Response::new(GetSpendingSummaryResponse {
+            start_date: period.start.format("%Y-%m-%d").to_string(),
+            end_date: period.end.format("%Y-%m-%d").to_string(),
+            previous_start_date: previous.start.format("%Y-%m-%d").to_string(),
+            previous_end_date: previous.end.format("%Y-%m-%d").to_string(),
+            totals,
+            groups,
+            display_currency,
+            converted_total,
+            unconverted_currencies,
+            rates_date: rates.map(|r| r.date.format("%Y-%m-%d").to_string()),
+        }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn import_transactions(
+        &self,
+        request: Request<Streaming<ImportTransactionsRequest>>,
+    ) -> Result<Response<ImportTransactionsResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::TransactionsWrite)?;
+        let user_id = auth.user_id;
+        let mut stream = request.into_inner();
+
+        let options = match stream.message().await?.and_then(|m| m.payload) {
+            Some(ImportPayload::Options(options)) => options,
+            _ => return Err(AppError::validation("The first message must carry the import options").into()),
+        };
+        debug!(account_id = %options.account_id, dry_run = options.dry_run, "Importing transactions");
+
+        if options.account_id.is_empty() {
+            return Err(AppError::validation("Account ID is required").into());
+        }
+        let format = import_format_from_proto(options.format, options.csv_mapping)?;
+        let account = self
+            .require_account_access(user_id, &options.account_id, ShareAccess::Full)
+            .await?;
+
+        let mut data = Vec::new();
+        while let Some(message) = stream.message().await? {
+            match message.payload {
+                Some(ImportPayload::Chunk(chunk)) => {
+                    if data.len() + chunk.len() > MAX_IMPORT_BYTES {
+                        return Err(AppError::validation(format!(
+                            "Import files may be at most {} MiB",
+                            MAX_IMPORT_BYTES / (1024 * 1024)
+                        ))
+                        .into());
+                    }
+                    data.extend_from_slice(&chunk);
+                }
+                Some(ImportPayload::Options(_)) => {
+                    return Err(AppError::validation("Import options may only be sent once").into());
+                }
+                None => {}
+            }
+        }
+        let data = String::from_utf8(data).map_err(|_| AppError::validation("Import file must be UTF-8 text"))?;
+
+        let parsed = import::parse(&format, &data).map_err(|e| AppError::validation(e.to_string()))?;
+        let transaction_ids = import::transaction_ids(&account.account_id, &parsed.transactions);
+
+        let duplicates = match (
+            parsed.transactions.iter().map(|t| t.date).min(),
+            parsed.transactions.iter().map(|t| t.date).max(),
+        ) {
+            (Some(first), Some(last)) => {
+                let tolerance = chrono::Duration::days(DUPLICATE_DATE_TOLERANCE_DAYS);
+                let existing: Vec<ExistingTransaction> = self
+                    .transaction_repository
+                    .list_amounts_by_account(&account.account_id, first - tolerance, last + tolerance)
+                    .await
+                    .map_err(|e| {
+                        error!("Failed to load existing transactions: {:?}", e);
+                        AppError::internal("Failed to import transactions")
+                    })?
+                    .into_iter()
+                    .map(|(transaction_id, date, amount)| ExistingTransaction {
+                        transaction_id,
+                        date,
+                        amount,
+                    })
+                    .collect();
+                import::detect_duplicates(&parsed.transactions, &transaction_ids, &existing)
+            }
+            _ => Vec::new(),
+        };
+        let duplicate_count = duplicates.iter().filter(|d| d.is_some()).count();
+
+        let imported = if options.dry_run {
+            0
+        } else {
+            let new_transactions: Vec<BankTransaction> = parsed
+                .transactions
+                .iter()
+                .zip(transaction_ids)
+                .zip(&duplicates)
+                .filter(|(_, duplicate)| duplicate.is_none())
+                .map(|((transaction, id), _)| imported_bank_transaction(transaction, id, &account))
+                .collect();
+            if new_transactions.is_empty() {
+                0
+            } else {
+                self.transaction_repository
+                    .upsert_transactions(account.user_id, &account.item_id, &new_transactions)
+                    .await
+                    .map_err(|e| {
+                        error!("Failed to store imported transactions: {:?}", e);
+                        AppError::internal("Failed to import transactions")
+                    })?;
+                new_transactions.len()
+            }
+        };
+
+        // The import is stored either way, so a failed dedup pass is only logged
+        let merged = match (
+            parsed.transactions.iter().map(|t| t.date).min(),
+            parsed.transactions.iter().map(|t| t.date).max(),
+        ) {
+            (Some(first), Some(last)) if imported > 0 => {
+                let deduplicator =
+                    TransactionDeduplicator::new(self.transaction_repository.clone(), self.audit_log.clone());
+                deduplicator
+                    .dedup_window(account.user_id, first, last, "import")
+                    .await
+                    .unwrap_or_else(|e| {
+                        warn!("Failed to merge duplicate transactions after import: {:?}", e);
+                        0
+                    })
+            }
+            _ => 0,
+        };
+
+        info!(
+            user_id = %user_id,
+            account_id = %account.account_id,
+            parsed = parsed.transactions.len(),
+            imported,
+            duplicates = duplicate_count,
+            merged,
+            errors = parsed.errors.len(),
+            dry_run = options.dry_run,
+            "Transaction import finished"
+        );
+        Ok(Response::new(ImportTransactionsResponse {
+            dry_run: options.dry_run,
+            parsed: parsed.transactions.len() as i32,
+            imported: imported as i32,
+            duplicates: duplicate_count as i32,
+            preview: parsed
+                .transactions
+                .iter()
+                .zip(duplicates)
+                .take(IMPORT_PREVIEW_ROWS)
+                .map(|(transaction, duplicate_of)| ImportPreviewRow {
+                    line: transaction.line as i32,
+                    date: transaction.date.format("%Y-%m-%d").to_string(),
+                    amount: transaction.amount,
+                    name: transaction.name.clone(),
+                    merchant_name: transaction.merchant_name.clone(),
+                    duplicate_of,
+                })
+                .collect(),
+            errors: parsed
+                .errors
+                .iter()
+                .map(|e| ImportRowError {
+                    line: e.line as i32,
+                    message: e.message.clone(),
+                })
+                .collect(),
+            merged: merged as i32,
+        }))
+    }
+
+    #[instrument(skip(self, request), fields(item_id = %request.get_ref().item_id))]
+    async fn remove_bank_connection(
+        &self,
+        request: Request<RemoveBankConnectionRequest>,
+    ) -> Result<Response<RemoveBankConnectionResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsWrite)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!("Removing bank connection");
+
+        if req.item_id.is_empty() {
+            return Err(AppError::validation("Item ID is required").into());
+        }
+
+        let item = self
+            .item_repository
+            .find_by_item_id(&req.item_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to load Plaid item: {:?}", e);
+                AppError::internal("Failed to remove bank connection")
+            })?
+            .filter(|item| item.user_id == user_id && item.status() != PlaidItemStatus::Removed)
+            .ok_or_else(|| AppError::not_found("Linked item not found"))?;
+
+        let (provider, access_token) = self.item_credentials(&item).await.map_err(|e| {
+            error!("Failed to load item credentials: {:?}", e);
+            AppError::internal("Failed to remove bank connection")
+        })?;
+        if let Err(e) = provider.remove_item(&access_token).await {
+            // An item Plaid no longer knows has nothing left to revoke, so local removal can go ahead
+            let detail = format!("{:?}", e);
+            if detail.contains("ITEM_NOT_FOUND") || detail.contains("INVALID_ACCESS_TOKEN") {
+                warn!(item_id = %item.item_id, "Plaid item already gone; removing local data");
+            } else {
+                error!("Failed to remove Plaid item: {:?}", e);
+                return Err(map_plaid_error(e, "Failed to remove bank connection").into());
+            }
+        }
+
+        let account_count = self
+            .account_repository
+            .list_by_item(&item.item_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to list item accounts: {:?}", e);
+                AppError::internal("Failed to remove bank connection")
+            })?
+            .len();
+        let transaction_count = self
+            .item_repository
+            .mark_removed(user_id, &item.item_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to mark Plaid item removed: {:?}", e);
+                AppError::internal("Failed to remove bank connection")
+            })?
+            .ok_or_else(|| AppError::not_found("Linked item not found"))?;
+
+        // The connection is already revoked, so a failed audit write is reported but does not undo it
+        if let Err(e) = self
+            .audit_log
+            .record(
+                user_id,
+                "bank_connection.removed",
+                "plaid_item",
+                &item.item_id,
+                serde_json::json!({
+                    "institution_id": item.institution_id,
+                    "institution_name": item.institution_name,
+                    "account_count": account_count,
+                    "transaction_count": transaction_count,
+                }),
+            )
+            .await
+        {
+            error!(item_id = %item.item_id, "Failed to audit-log bank connection removal: {:?}", e);
+        }
+
+        info!(
+            user_id = %user_id,
+            item_id = %item.item_id,
+            account_count,
+            transaction_count,
+            "Bank connection removed"
+        );
+        Ok(Response::new(RemoveBankConnectionResponse {
+            item_id: item.item_id,
+            removed_account_count: account_count as i32,
+            removed_transaction_count: transaction_count as i32,
+        }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn trigger_sync(
+        &self,
+        request: Request<TriggerSyncRequest>,
+    ) -> Result<Response<TriggerSyncResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::TransactionsWrite)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!("Triggering on-demand transaction sync");
+
+        let items = self
+            .item_repository
+            .list_by_user(user_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to list Plaid items: {:?}", e);
+                AppError::internal("Failed to trigger sync")
+            })?;
+
+        let items = match req.item_id.filter(|id| !id.is_empty()) {
+            Some(item_id) => {
+                let item = items
+                    .into_iter()
+                    .find(|item| item.item_id == item_id)
+                    .ok_or_else(|| AppError::not_found("Linked item not found"))?;
+                vec![item]
+            }
+            None => items,
+        };
+
+        let outcomes = self.sync_coordinator.sync_items(items).await;
+
+        info!(
+            user_id = %user_id,
+            item_count = outcomes.len(),
+            failed = outcomes.iter().filter(|o| o.error.is_some()).count(),
+            "On-demand transaction sync finished"
+        );
+        Ok(Response::new(TriggerSyncResponse {
+            results: outcomes.iter().map(sync_result_to_proto).collect(),
+        }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn refresh_balances(
+        &self,
+        request: Request<RefreshBalancesRequest>,
+    ) -> Result<Response<RefreshBalancesResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsRead)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!(force = req.force, "Refreshing balances");
+
+        if !req.force {
+            match self.balance_cache.get(user_id).await {
+                Ok(Some(cached)) => {
+                    debug!(user_id = %user_id, "Serving cached balances");
+                    return Ok(Response::new(balances_response(&cached, true)));
+                }
+                Ok(None) => {}
+                Err(e) => warn!("Failed to read cached balances: {:?}", e),
+            }
+        }
+
+        // Fail open on Redis errors: the throttle controls cost, not correctness
+        let permit = self.balance_cache.try_start_refresh(user_id).await.unwrap_or_else(|e| {
+            warn!("Failed to check balance refresh throttle: {:?}", e);
+            RefreshPermit::Granted
+        });
+        if let RefreshPermit::Throttled(retry_after) = permit {
+            if req.force {
+                return Err(AppError::rate_limited("Balances were refreshed recently", Some(retry_after)).into());
+            }
+
+            // A plain read that missed the cache gets the last persisted balances instead
+            let stored = self.account_repository.list_by_user(user_id).await.map_err(|e| {
+                error!("Failed to list bank accounts: {:?}", e);
+                AppError::internal("Failed to load balances")
+            })?;
+            let balances = CachedBalances {
+                refreshed_at: stored
+                    .iter()
+                    .map(|account| account.balances_updated_at)
+                    .max()
+                    .unwrap_or_else(Utc::now),
+                accounts: stored.iter().map(|account| account.to_bank_account()).collect(),
+            };
+            return Ok(Response::new(balances_response(&balances, true)));
+        }
+
+        let balances = self.refresh_item_balances(user_id).await?;
+        if let Err(e) = self.balance_cache.put(user_id, &balances).await {
+            warn!("Failed to cache balances: {:?}", e);
+        }
+
+        Ok(Response::new(balances_response(&balances, false)))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn list_liabilities(
+        &self,
+        request: Request<ListLiabilitiesRequest>,
+    ) -> Result<Response<ListLiabilitiesResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsRead)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!(refresh = req.refresh, "Listing liabilities");
+
+        if req.refresh {
+            self.refresh_liabilities(user_id).await?;
+        }
+
+        let liabilities = self
+            .liability_repository
+            .list_by_user(user_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to list liabilities: {:?}", e);
+                AppError::internal("Failed to list liabilities")
+            })?;
+
+        info!(user_id = %user_id, liability_count = liabilities.len(), "Listed liabilities");
+        Ok(Response::new(ListLiabilitiesResponse {
+            liabilities: liabilities.iter().map(Self::liability_to_proto).collect(),
+        }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn list_bills(
+        &self,
+        request: Request<ListBillsRequest>,
+    ) -> Result<Response<ListBillsResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::TransactionsRead)?;
+        let user_id = auth.user_id;
+        debug!(user_id = %user_id, "Listing bills");
+
+        let bills = self.bill_repository.list_by_user(user_id).await.map_err(|e| {
+            error!("Failed to list bills: {:?}", e);
+            AppError::internal("Failed to list bills")
+        })?;
+
+        info!(user_id = %user_id, bill_count = bills.len(), "Listed bills");
+        Ok(Response::new(ListBillsResponse {
+            bills: bills.iter().map(Self::bill_to_proto).collect(),
+        }))
+    }
+
+    #[instrument(skip(self, request), fields(account_id = %request.get_ref().account_id))]
+    async fn get_account_identity(
+        &self,
+        request: Request<GetAccountIdentityRequest>,
+    ) -> Result<Response<GetAccountIdentityResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsRead)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!(refresh = req.refresh, "Getting account identity");
+
+        if req.account_id.is_empty() {
+            return Err(AppError::validation("Account ID is required").into());
+        }
+
+        let account = self
+            .require_account_access(user_id, &req.account_id, ShareAccess::Read)
+            .await?;
+
+        let mut identity = if req.refresh {
+            None
+        } else {
+            self.stored_identity(account.user_id, &account.account_id).await?
+        };
+        if identity.is_none() {
+            self.refresh_identity(account.user_id, &account.item_id).await?;
+            identity = self.stored_identity(account.user_id, &account.account_id).await?;
+        }
+        let identity = identity.ok_or_else(|| AppError::not_found("Identity not available for this account"))?;
+
+        let response = identity_to_proto(&identity, &auth.claims.email);
+        info!(
+            user_id = %user_id,
+            owner_count = response.owners.len(),
+            email_matches_user = response.email_matches_user,
+            "Account identity returned"
+        );
+        Ok(Response::new(response))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn get_net_worth_history(
+        &self,
+        request: Request<GetNetWorthHistoryRequest>,
+    ) -> Result<Response<GetNetWorthHistoryResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsRead)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!("Getting net worth history");
+
+        let granularity = granularity_from_proto(req.granularity)?;
+        let display_currency = self.display_currency(user_id, req.display_currency.as_deref()).await?;
+        let end = parse_date(req.end_date.as_deref(), "end_date")?.unwrap_or_else(|| Utc::now().date_naive());
+        let start = parse_date(req.start_date.as_deref(), "start_date")?
+            .unwrap_or(end - chrono::Duration::days(DEFAULT_NET_WORTH_HISTORY_DAYS));
+        if start > end {
+            return Err(AppError::validation("start_date must not be after end_date").into());
+        }
+        if (end - start).num_days() > MAX_NET_WORTH_HISTORY_DAYS {
+            return Err(AppError::validation("Net worth history range may span at most five years").into());
+        }
+
+        let snapshots = self
+            .net_worth_repository
+            .history(user_id, start, end, granularity)
+            .await
+            .map_err(|e| {
+                error!("Failed to load net worth history: {:?}", e);
+                AppError::internal("Failed to load net worth history")
+            })?;
+
+        let currencies: Vec<String> = snapshots.iter().map(|s| s.currency_code.clone()).collect();
+        let rates = match self.fx_rates(&display_currency).await? {
+            // Crypto holdings are valued at today's spot price across the whole history
+            Some(rates) => Some(self.with_asset_prices(rates, &currencies).await),
+            None => None,
+        };
+        let (converted_points, unconverted_currencies) = match &rates {
+            Some(rates) => convert_net_worth(&snapshots, rates, &display_currency),
+            None => {
+                let mut currencies: Vec<String> = snapshots.iter().map(|s| s.currency_code.clone()).collect();
+                currencies.sort();
+                currencies.dedup();
+                (Vec::new(), currencies)
+            }
+        };
+
+        info!(
+            user_id = %user_id,
+            point_count = snapshots.len(),
+            display_currency = %display_currency,
+            "Net worth history returned"
+        );
+        Ok(Response::new(GetNetWorthHistoryResponse {
+            points: snapshots.iter().map(net_worth_point).collect(),
+            display_currency,
+            converted_points,
+            unconverted_currencies,
+            rates_date: rates.map(|r| r.date.format("%Y-%m-%d").to_string()),
+        }))
+    }
+
+    #[instrument(skip(self, request), fields(account_id = %request.get_ref().account_id))]
+    async fn get_balance_history(
+        &self,
+        request: Request<GetBalanceHistoryRequest>,
+    ) -> Result<Response<GetBalanceHistoryResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsRead)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!("Getting balance history");
+
+        let account = self
+            .require_account_access(user_id, &req.account_id, ShareAccess::Read)
+            .await?;
+        let granularity = granularity_from_proto(req.granularity)?;
+        let end = parse_date(req.end_date.as_deref(), "end_date")?.unwrap_or_else(|| Utc::now().date_naive());
+        let start = parse_date(req.start_date.as_deref(), "start_date")?
+            .unwrap_or(end - chrono::Duration::days(DEFAULT_NET_WORTH_HISTORY_DAYS));
+        if start > end {
+            return Err(AppError::validation("start_date must not be after end_date").into());
+        }
+        if (end - start).num_days() > MAX_NET_WORTH_HISTORY_DAYS {
+            return Err(AppError::validation("Balance history range may span at most five years").into());
+        }
+
+        let snapshots = self
+            .balance_history_repository
+            .history(&account.account_id, start, end, granularity)
+            .await
+            .map_err(|e| {
+                error!("Failed to load balance history: {:?}", e);
+                AppError::internal("Failed to load balance history")
+            })?;
+
+        info!(user_id = %user_id, point_count = snapshots.len(), "Balance history returned");
+        Ok(Response::new(GetBalanceHistoryResponse {
+            account_id: account.account_id,
+            points: snapshots.iter().map(balance_point).collect(),
+        }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn set_display_currency(
+        &self,
+        request: Request<SetDisplayCurrencyRequest>,
+    ) -> Result<Response<SetDisplayCurrencyResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsWrite)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+
+        let currency = normalize_currency(&req.currency_code)?;
+        let rates = self.fx_client.rates().await.map_err(|e| {
+            error!("Failed to load exchange rates: {:?}", e);
+            AppError::internal("Exchange rates are unavailable")
+        })?;
+        if !rates.supports(&currency) {
+            return Err(AppError::validation(format!("No exchange rate for {}", currency)).into());
+        }
+
+        let user = self
+            .user_repository
+            .set_display_currency(user_id, &currency)
+            .await
+            .map_err(|e| {
+                error!("Failed to set display currency: {:?}", e);
+                AppError::internal("Failed to set display currency")
+            })?
+            .ok_or_else(|| AppError::not_found("User not found"))?;
+
+        info!(user_id = %user_id, currency = %user.display_currency, "Display currency set");
+        Ok(Response::new(SetDisplayCurrencyResponse {
+            currency_code: user.display_currency,
+        }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn stream_balances(
+        &self,
+        request: Request<StreamBalancesRequest>,
+    ) -> Result<Response<Self::StreamBalancesStream>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsRead)?;
+        let user_id = auth.user_id;
+        debug!("Opening balance stream");
+
+        // Subscribe before loading the snapshot so updates landing in between are not lost
+        let updates = self.balance_updates.subscribe(user_id);
+        let initial = balance_snapshot(&self.account_repository, user_id).await?;
+
+        let (tx, rx) = mpsc::channel(BALANCE_STREAM_BUFFER);
+        tokio::spawn(forward_balance_updates(
+            user_id,
+            updates,
+            self.account_repository.clone(),
+            initial,
+            tx,
+        ));
+
+        info!(
+            user_id = %user_id,
+            subscribers = self.balance_updates.subscriber_count(user_id),
+            "Balance stream opened"
+        );
+        let stream = futures::stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|event| (event, rx))
+        });
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
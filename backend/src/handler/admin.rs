@@ -0,0 +1,981 @@
+use crate::adapter::email_templates::{placeholders, EmailLocale, EmailTemplateContent, EmailTemplateName};
+use crate::adapter::mailer::Mailer;
+use crate::adapter::ses::TemplateData;
+use crate::error::AppError;
+use crate::gen::admin::{
+    admin_service_server::AdminService, ActivateEmailTemplateVersionRequest, AdminUser, CancelEmailCampaignRequest,
+    CancelScheduledEmailRequest, CancelScheduledEmailResponse, CreateEmailCampaignRequest,
+    CreateEmailTemplateVersionRequest, EmailCampaign as ProtoEmailCampaign, EmailDelivery, EmailDeliveryEvent,
+    EmailTemplate as ProtoEmailTemplate, EmailTemplateSummary, EmailTemplateVersion, GetEmailCampaignRequest,
+    GetEmailDeliveryRequest, GetEmailTemplateRequest, GetItemStatusRequest, GetLogFilterRequest, GetOtpStatsRequest,
+    GetUserRequest, ItemStatus, ListEmailTemplatesRequest, ListEmailTemplatesResponse, ListUserEmailsRequest,
+    ListUserEmailsResponse, LogFilter, OtpStats as ProtoOtpStats, PreviewEmailRequest, PreviewEmailResponse,
+    PreviewEmailTemplateRequest, PreviewEmailTemplateResponse, RevokeUserSessionsRequest, RevokeUserSessionsResponse,
+    ScheduleEmailRequest, ScheduledEmail, SearchUsersRequest, SearchUsersResponse, SetLogFilterRequest, UserDetail,
+};
+use crate::handler::interceptor::{trusted_service, AuthContext};
+use crate::logging;
+use crate::model::audit_log::AuditLogRepository;
+use crate::model::auth::SessionManager;
+use crate::model::email_campaign::{
+    merge_keys, EmailCampaign, EmailCampaignAudience, EmailCampaignRepository, NewCampaignRecipient,
+};
+use crate::model::email_event::{delivery_status, EmailEvent, EmailEventRepository};
+use crate::model::email_queue::{EmailQueueRepository, QueuedEmail};
+use crate::model::email_template::{EmailTemplate, EmailTemplateRepository};
+use crate::model::otp::OtpRepository;
+use crate::model::plaid_item::{PlaidItem, PlaidItemRepository};
+use crate::model::user::{User, UserRepository};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde_json::json;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tonic::{Request, Response, Status};
+use tracing::{debug, error, info, instrument, warn};
+use uuid::Uuid;
+
+/// Longest accepted subject line, in characters
+const MAX_SUBJECT_CHARS: usize = 500;
+/// Longest accepted body, in characters
+const MAX_BODY_CHARS: usize = 200_000;
+/// Furthest ahead an email can be scheduled
+const MAX_SCHEDULE_DAYS: i64 = 365;
+/// Longest accepted campaign name, in characters
+const MAX_CAMPAIGN_NAME_CHARS: usize = 200;
+/// Most addresses a campaign can be given
+const MAX_CAMPAIGN_RECIPIENTS: usize = 50_000;
+/// Longest a changed log filter can be set to last before resetting itself
+const MAX_LOG_FILTER_RESET_SECONDS: u32 = 24 * 60 * 60;
+/// Shortest user search query, in characters; shorter ones match too much of the table
+const MIN_USER_QUERY_CHARS: usize = 3;
+/// Users returned by a search when the caller doesn't say
+const DEFAULT_USER_SEARCH_LIMIT: i32 = 20;
+/// Most users a search returns
+const MAX_USER_SEARCH_LIMIT: i32 = 100;
+/// Emails listed for a user when the caller doesn't say
+const DEFAULT_USER_EMAILS_LIMIT: i32 = 50;
+/// Most emails listed for a user
+const MAX_USER_EMAILS_LIMIT: i32 = 200;
+
+/// gRPC service for operators; every call requires an admin (a user granted the role with `create-admin`, or
+/// listed in `ADMIN_USER_IDS`), and the read-only status calls also accept trusted internal services
+pub struct AdminHandler {
+    /// Admins from `ADMIN_USER_IDS`, on top of the users with the stored role
+    admins: HashSet<Uuid>,
+    email_templates: EmailTemplateRepository,
+    email_queue: EmailQueueRepository,
+    email_campaigns: EmailCampaignRepository,
+    email_events: EmailEventRepository,
+    /// `None` when email is not configured
+    mailer: Option<Arc<Mailer>>,
+    audit_log: AuditLogRepository,
+    users: UserRepository,
+    sessions: SessionManager,
+    otp: OtpRepository,
+    plaid_items: PlaidItemRepository,
+}
+
+impl AdminHandler {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        admins: HashSet<Uuid>,
+        email_templates: EmailTemplateRepository,
+        email_queue: EmailQueueRepository,
+        email_campaigns: EmailCampaignRepository,
+        email_events: EmailEventRepository,
+        mailer: Option<Arc<Mailer>>,
+        audit_log: AuditLogRepository,
+        users: UserRepository,
+        sessions: SessionManager,
+        otp: OtpRepository,
+        plaid_items: PlaidItemRepository,
+    ) -> Self {
+        Self {
+            admins,
+            email_templates,
+            email_queue,
+            email_campaigns,
+            email_events,
+            mailer,
+            audit_log,
+            users,
+            sessions,
+            otp,
+            plaid_items,
+        }
+    }
+
+    /// Parse the comma-separated user UUIDs of `ADMIN_USER_IDS`
+    pub fn parse_admin_ids(value: &str) -> Result<HashSet<Uuid>> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(|id| Uuid::parse_str(id).map_err(|_| anyhow!("Invalid admin user id '{}'", id)))
+            .collect()
+    }
+
+    async fn require_admin<T>(&self, request: &Request<T>) -> Result<Uuid, AppError> {
+        let auth = AuthContext::from_request(request)?;
+        // The role is read on every call, so revoking it takes effect without a restart
+        if !self.admins.contains(&auth.user_id) && !self.users.is_admin(auth.user_id).await? {
+            warn!(user_id = %auth.user_id, "Admin call refused");
+            return Err(AppError::permission_denied("Admin access required"));
+        }
+        Ok(auth.user_id)
+    }
+
+    /// An admin, or a trusted internal service such as monitoring, for calls that only read status
+    async fn require_admin_or_service<T>(&self, request: &Request<T>) -> Result<(), AppError> {
+        if let Some(service) = trusted_service(request) {
+            debug!(service = %service.name, "Admin status call from internal service");
+            return Ok(());
+        }
+        self.require_admin(request).await.map(|_| ())
+    }
+
+    fn parse_name(name: &str) -> Result<EmailTemplateName, AppError> {
+        EmailTemplateName::parse(name).ok_or_else(|| AppError::not_found(format!("Unknown email template '{}'", name)))
+    }
+
+    fn template_to_proto(name: EmailTemplateName, content: &EmailTemplateContent, active: bool) -> ProtoEmailTemplate {
+        ProtoEmailTemplate {
+            name: name.as_str().to_string(),
+            version: content.version,
+            subject: content.subject.clone(),
+            html: content.html.clone(),
+            text: content.text.clone(),
+            active,
+        }
+    }
+
+    fn version_to_proto(template: &EmailTemplate) -> EmailTemplateVersion {
+        EmailTemplateVersion {
+            version: template.version,
+            active: template.active,
+            created_by: template.created_by.map(|id| id.to_string()).unwrap_or_default(),
+            created_at: template.created_at.timestamp(),
+            activated_at: template.activated_at.map(|t| t.timestamp()),
+        }
+    }
+
+    fn queued_email_to_proto(email: &QueuedEmail) -> ScheduledEmail {
+        ScheduledEmail {
+            id: email.id.to_string(),
+            name: email.template.clone(),
+            recipient: email.recipient.clone(),
+            locale: email.locale.clone(),
+            status: email.status.clone(),
+            send_at: email.send_at.timestamp(),
+            expires_at: email.expires_at.map(|t| t.timestamp()),
+            attempts: email.attempts,
+            last_error: email.last_error.clone(),
+            message_id: email.message_id.clone(),
+            sent_at: email.sent_at.map(|t| t.timestamp()),
+        }
+    }
+
+    fn user_to_proto(user: &User) -> AdminUser {
+        AdminUser {
+            user_id: user.id.to_string(),
+            email: user.email.clone(),
+            name: user.name.clone(),
+            locale: user.locale.clone(),
+            timezone: user.timezone.clone(),
+            created_at: user.created_at.timestamp(),
+        }
+    }
+
+    fn item_to_proto(item: &PlaidItem) -> ItemStatus {
+        ItemStatus {
+            item_id: item.item_id.clone(),
+            user_id: item.user_id.to_string(),
+            provider: item.provider.clone(),
+            institution_name: item.institution_name.clone().unwrap_or_default(),
+            status: item.status().as_str().to_string(),
+            error_code: item.error_code.clone(),
+            last_synced_at: item.last_synced_at.map(|t| t.timestamp()),
+            last_sync_attempt_at: item.last_sync_attempt_at.map(|t| t.timestamp()),
+            last_sync_error: item.last_sync_error.clone(),
+            consecutive_sync_failures: item.consecutive_sync_failures,
+            created_at: item.created_at.timestamp(),
+            removed_at: item.removed_at.map(|t| t.timestamp()),
+        }
+    }
+
+    fn parse_user_id(user_id: &str) -> Result<Uuid, AppError> {
+        Uuid::parse_str(user_id.trim()).map_err(|_| AppError::validation("Invalid user_id"))
+    }
+
+    async fn find_user(&self, user_id: Uuid) -> Result<User, AppError> {
+        self.users
+            .find_by_id(user_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to load user: {:?}", e);
+                AppError::internal("Failed to load user")
+            })?
+            .ok_or_else(|| AppError::not_found("User not found"))
+    }
+
+    fn campaign_to_proto(campaign: &EmailCampaign) -> ProtoEmailCampaign {
+        ProtoEmailCampaign {
+            id: campaign.id.to_string(),
+            name: campaign.name.clone(),
+            audience: campaign.audience.clone(),
+            status: campaign.status.clone(),
+            total_recipients: campaign.total_recipients,
+            sent_count: campaign.sent_count,
+            failed_count: campaign.failed_count,
+            send_at: campaign.send_at.timestamp(),
+            created_at: campaign.created_at.timestamp(),
+            completed_at: campaign.completed_at.map(|t| t.timestamp()),
+        }
+    }
+
+    fn event_to_proto(event: &EmailEvent) -> EmailDeliveryEvent {
+        EmailDeliveryEvent {
+            r#type: event.event_type.clone(),
+            recipients: event.recipients.clone(),
+            detail: event.detail.to_string(),
+            occurred_at: event.occurred_at.timestamp(),
+        }
+    }
+
+    /// Earliest send time of `send_at`, moved up to now when past
+    fn parse_send_at(send_at: i64, now: DateTime<Utc>) -> Result<DateTime<Utc>, AppError> {
+        let send_at = DateTime::from_timestamp(send_at, 0)
+            .ok_or_else(|| AppError::validation("Invalid send_at"))?
+            .max(now);
+        if send_at > now + Duration::days(MAX_SCHEDULE_DAYS) {
+            return Err(AppError::validation(format!(
+                "Emails can be scheduled at most {} days ahead",
+                MAX_SCHEDULE_DAYS
+            )));
+        }
+        Ok(send_at)
+    }
+
+    /// Content of `version`, or of the version being sent, and whether it is being sent
+    async fn load(
+        &self,
+        name: EmailTemplateName,
+        version: Option<i32>,
+    ) -> Result<(EmailTemplateContent, bool), AppError> {
+        let stored = match version {
+            Some(version) => self.email_templates.find(name, version).await,
+            None => self.email_templates.find_active(name).await,
+        }
+        .map_err(|e| {
+            error!("Failed to load email template: {:?}", e);
+            AppError::internal("Failed to load email template")
+        })?;
+        match (stored, version) {
+            (Some(template), _) => Ok((template.content(), template.active)),
+            (None, None) => Ok((name.default_template(), true)),
+            (None, Some(version)) => Err(AppError::not_found(format!(
+                "{} has no version {}",
+                name.as_str(),
+                version
+            ))),
+        }
+    }
+
+    /// Record who changed a template; a failed write doesn't undo the change
+    async fn audit(&self, user_id: Uuid, action: &str, template: &EmailTemplate) {
+        let metadata = json!({ "name": template.name, "version": template.version });
+        if let Err(e) = self
+            .audit_log
+            .record(user_id, action, "email_template", &template.id.to_string(), metadata)
+            .await
+        {
+            warn!(template_id = %template.id, error = %e, "Failed to audit email template change");
+        }
+    }
+
+    /// Record who created or cancelled a campaign; a failed write doesn't undo the change
+    async fn audit_campaign(&self, user_id: Uuid, action: &str, campaign: &EmailCampaign) {
+        let metadata = json!({ "name": campaign.name, "audience": campaign.audience });
+        if let Err(e) = self
+            .audit_log
+            .record(user_id, action, "email_campaign", &campaign.id.to_string(), metadata)
+            .await
+        {
+            warn!(campaign_id = %campaign.id, error = %e, "Failed to audit email campaign change");
+        }
+    }
+
+    /// Record who scheduled or cancelled an email; a failed write doesn't undo the change
+    async fn audit_email(&self, user_id: Uuid, action: &str, email_id: Uuid, metadata: serde_json::Value) {
+        if let Err(e) = self
+            .audit_log
+            .record(user_id, action, "queued_email", &email_id.to_string(), metadata)
+            .await
+        {
+            warn!(email_id = %email_id, error = %e, "Failed to audit queued email change");
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl AdminService for AdminHandler {
+    #[instrument(skip(self, request))]
+    async fn list_email_templates(
+        &self,
+        request: Request<ListEmailTemplatesRequest>,
+    ) -> Result<Response<ListEmailTemplatesResponse>, Status> {
+        let user_id = self.require_admin(&request).await?;
+        debug!(user_id = %user_id, "Listing email templates");
+
+        let mut templates = Vec::with_capacity(EmailTemplateName::ALL.len());
+        for name in EmailTemplateName::ALL {
+            let versions = self.email_templates.list(name).await.map_err(|e| {
+                error!("Failed to list email templates: {:?}", e);
+                AppError::internal("Failed to list email templates")
+            })?;
+            templates.push(EmailTemplateSummary {
+                name: name.as_str().to_string(),
+                keys: name.keys().iter().map(|key| key.to_string()).collect(),
+                required_keys: name.required_keys().iter().map(|key| key.to_string()).collect(),
+                active_version: versions.iter().find(|t| t.active).map(|t| t.version),
+                versions: versions.iter().map(Self::version_to_proto).collect(),
+            });
+        }
+
+        Ok(Response::new(ListEmailTemplatesResponse { templates }))
+    }
+
+    #[instrument(skip(self, request), fields(name = %request.get_ref().name))]
+    async fn get_email_template(
+        &self,
+        request: Request<GetEmailTemplateRequest>,
+    ) -> Result<Response<ProtoEmailTemplate>, Status> {
+        self.require_admin(&request).await?;
+        let req = request.into_inner();
+        let name = Self::parse_name(&req.name)?;
+        debug!(version = ?req.version, "Getting email template");
+
+        let (content, active) = self.load(name, req.version).await?;
+        Ok(Response::new(Self::template_to_proto(name, &content, active)))
+    }
+
+    #[instrument(skip(self, request), fields(name = %request.get_ref().name))]
+    async fn create_email_template_version(
+        &self,
+        request: Request<CreateEmailTemplateVersionRequest>,
+    ) -> Result<Response<ProtoEmailTemplate>, Status> {
+        let user_id = self.require_admin(&request).await?;
+        let req = request.into_inner();
+        let name = Self::parse_name(&req.name)?;
+        debug!(user_id = %user_id, "Creating email template version");
+
+        if req.subject.chars().count() > MAX_SUBJECT_CHARS {
+            return Err(
+                AppError::validation(format!("subject can have at most {} characters", MAX_SUBJECT_CHARS)).into(),
+            );
+        }
+        if req.html.chars().count() > MAX_BODY_CHARS || req.text.chars().count() > MAX_BODY_CHARS {
+            return Err(
+                AppError::validation(format!("html and text can have at most {} characters", MAX_BODY_CHARS)).into(),
+            );
+        }
+        let content = EmailTemplateContent {
+            subject: req.subject,
+            html: req.html,
+            text: req.text,
+            version: None,
+        };
+        content.validate(name).map_err(AppError::validation)?;
+
+        let template = self
+            .email_templates
+            .create(name, &content, user_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to create email template version: {:?}", e);
+                AppError::internal("Failed to create email template version")
+            })?;
+        self.audit(user_id, "email_template.created", &template).await;
+
+        info!(user_id = %user_id, name = name.as_str(), version = template.version, "Email template version created");
+        Ok(Response::new(Self::template_to_proto(
+            name,
+            &template.content(),
+            template.active,
+        )))
+    }
+
+    #[instrument(skip(self, request), fields(name = %request.get_ref().name))]
+    async fn preview_email_template(
+        &self,
+        request: Request<PreviewEmailTemplateRequest>,
+    ) -> Result<Response<PreviewEmailTemplateResponse>, Status> {
+        self.require_admin(&request).await?;
+        let req = request.into_inner();
+        let name = Self::parse_name(&req.name)?;
+        debug!(version = ?req.version, "Previewing email template");
+
+        let (stored, _) = self.load(name, req.version).await?;
+        let content = EmailTemplateContent {
+            subject: req.subject.unwrap_or(stored.subject),
+            html: req.html.unwrap_or(stored.html),
+            text: req.text.unwrap_or(stored.text),
+            version: stored.version,
+        };
+        let mut data = name.sample_data();
+        for (key, value) in req.data {
+            data.insert(key, value);
+        }
+        // Shown in the layout it is sent in
+        let brand = self
+            .mailer
+            .as_ref()
+            .map(|mailer| mailer.brand().clone())
+            .unwrap_or_default();
+        let rendered = content.with_layout(&brand, EmailLocale::English).render(&data);
+
+        Ok(Response::new(PreviewEmailTemplateResponse {
+            subject: rendered.subject,
+            html: rendered.html,
+            text: rendered.text,
+            problem: content.validate(name).err().unwrap_or_default(),
+        }))
+    }
+
+    #[instrument(skip(self, request), fields(name = %request.get_ref().name, locale = %request.get_ref().locale))]
+    async fn preview_email(
+        &self,
+        request: Request<PreviewEmailRequest>,
+    ) -> Result<Response<PreviewEmailResponse>, Status> {
+        self.require_admin(&request).await?;
+        let req = request.into_inner();
+        let name = Self::parse_name(&req.name)?;
+        let locale = EmailLocale::parse(&req.locale);
+        let recipient = req.recipient.as_deref().map(str::trim).filter(|r| !r.is_empty());
+        if recipient.is_some_and(|r| !r.contains('@')) {
+            return Err(AppError::validation("Invalid recipient").into());
+        }
+        debug!("Previewing email");
+
+        let mailer = self
+            .mailer
+            .as_ref()
+            .ok_or_else(|| AppError::upstream("ses", "Email delivery is not configured"))?;
+        let mut data = TemplateData::new();
+        for (key, value) in req.data {
+            data.insert(key, value);
+        }
+        let email = mailer
+            .preview_template_email(recipient, name, data, locale)
+            .await
+            .map_err(|e| {
+                error!("Failed to render email preview: {:?}", e);
+                AppError::internal("Failed to render email preview")
+            })?;
+
+        let html = email.html_body.unwrap_or_default();
+        let text = email.text_body.unwrap_or_default();
+        let mut missing_keys = Vec::new();
+        for key in [email.subject.as_str(), html.as_str(), text.as_str()]
+            .into_iter()
+            .flat_map(placeholders)
+        {
+            if !missing_keys.iter().any(|k| k == key) {
+                missing_keys.push(key.to_string());
+            }
+        }
+        Ok(Response::new(PreviewEmailResponse {
+            subject: email.subject,
+            html,
+            text,
+            locale: locale.as_str().to_string(),
+            missing_keys,
+            headers: email.headers.into_iter().collect(),
+        }))
+    }
+
+    #[instrument(skip(self, request), fields(name = %request.get_ref().name, version = request.get_ref().version))]
+    async fn activate_email_template_version(
+        &self,
+        request: Request<ActivateEmailTemplateVersionRequest>,
+    ) -> Result<Response<ProtoEmailTemplate>, Status> {
+        let user_id = self.require_admin(&request).await?;
+        let req = request.into_inner();
+        let name = Self::parse_name(&req.name)?;
+        debug!(user_id = %user_id, "Activating email template version");
+
+        let template = self
+            .email_templates
+            .activate(name, req.version)
+            .await
+            .map_err(|e| {
+                error!("Failed to activate email template version: {:?}", e);
+                AppError::internal("Failed to activate email template version")
+            })?
+            .ok_or_else(|| AppError::not_found(format!("{} has no version {}", name.as_str(), req.version)))?;
+        self.audit(user_id, "email_template.activated", &template).await;
+
+        info!(user_id = %user_id, name = name.as_str(), version = template.version, "Email template version activated");
+        Ok(Response::new(Self::template_to_proto(
+            name,
+            &template.content(),
+            template.active,
+        )))
+    }
+
+    #[instrument(skip(self, request), fields(name = %request.get_ref().name))]
+    async fn schedule_email(
+        &self,
+        request: Request<ScheduleEmailRequest>,
+    ) -> Result<Response<ScheduledEmail>, Status> {
+        let user_id = self.require_admin(&request).await?;
+        let req = request.into_inner();
+        let name = Self::parse_name(&req.name)?;
+        debug!(user_id = %user_id, send_at = req.send_at, "Scheduling email");
+
+        if !req.recipient.contains('@') {
+            return Err(AppError::validation("Invalid recipient email address").into());
+        }
+        if let Some(unknown) = req.data.keys().find(|key| !name.keys().contains(&key.as_str())) {
+            return Err(AppError::validation(format!(
+                "Unknown placeholder '{}'; {} templates can use {}",
+                unknown,
+                name.as_str(),
+                name.keys().join(", ")
+            ))
+            .into());
+        }
+        let missing: Vec<&str> = name
+            .required_keys()
+            .iter()
+            .copied()
+            .filter(|key| !req.data.contains_key(*key))
+            .collect();
+        if !missing.is_empty() {
+            return Err(AppError::validation(format!("Missing required placeholders: {}", missing.join(", "))).into());
+        }
+
+        let send_at = Self::parse_send_at(req.send_at, Utc::now())?;
+        let expires_at = match req.expires_at {
+            Some(expires_at) => {
+                let expires_at =
+                    DateTime::from_timestamp(expires_at, 0).ok_or_else(|| AppError::validation("Invalid expires_at"))?;
+                if expires_at <= send_at {
+                    return Err(AppError::validation("expires_at must be after send_at").into());
+                }
+                Some(expires_at)
+            }
+            None => None,
+        };
+
+        let mut data = TemplateData::new();
+        for (key, value) in req.data {
+            data.insert(key, value);
+        }
+        let locale = EmailLocale::parse(&req.locale);
+        let email = self
+            .email_queue
+            .schedule(&req.recipient, name, locale, &data, send_at, expires_at)
+            .await
+            .map_err(|e| {
+                error!("Failed to schedule email: {:?}", e);
+                AppError::internal("Failed to schedule email")
+            })?;
+        self.audit_email(
+            user_id,
+            "email.scheduled",
+            email.id,
+            json!({ "name": name.as_str(), "send_at": email.send_at }),
+        )
+        .await;
+
+        info!(user_id = %user_id, email_id = %email.id, name = name.as_str(), send_at = %email.send_at, "Email scheduled");
+        Ok(Response::new(Self::queued_email_to_proto(&email)))
+    }
+
+    #[instrument(skip(self, request), fields(id = %request.get_ref().id))]
+    async fn cancel_scheduled_email(
+        &self,
+        request: Request<CancelScheduledEmailRequest>,
+    ) -> Result<Response<CancelScheduledEmailResponse>, Status> {
+        let user_id = self.require_admin(&request).await?;
+        let req = request.into_inner();
+        let id = Uuid::parse_str(&req.id).map_err(|_| AppError::validation("Invalid email id"))?;
+        debug!(user_id = %user_id, "Cancelling scheduled email");
+
+        let cancelled = self.email_queue.cancel(id).await.map_err(|e| {
+            error!("Failed to cancel scheduled email: {:?}", e);
+            AppError::internal("Failed to cancel scheduled email")
+        })?;
+        if cancelled {
+            self.audit_email(user_id, "email.cancelled", id, json!({})).await;
+            info!(user_id = %user_id, email_id = %id, "Scheduled email cancelled");
+        }
+        Ok(Response::new(CancelScheduledEmailResponse { cancelled }))
+    }
+
+    #[instrument(skip(self, request), fields(audience = %request.get_ref().audience))]
+    async fn create_email_campaign(
+        &self,
+        request: Request<CreateEmailCampaignRequest>,
+    ) -> Result<Response<ProtoEmailCampaign>, Status> {
+        let user_id = self.require_admin(&request).await?;
+        let req = request.into_inner();
+        debug!(user_id = %user_id, recipients = req.recipients.len(), "Creating email campaign");
+
+        let name = req.name.trim();
+        if name.is_empty() || name.chars().count() > MAX_CAMPAIGN_NAME_CHARS {
+            return Err(AppError::validation(format!(
+                "name must have between 1 and {} characters",
+                MAX_CAMPAIGN_NAME_CHARS
+            ))
+            .into());
+        }
+        let audience = EmailCampaignAudience::parse(&req.audience).ok_or_else(|| {
+            AppError::validation("audience must be all_users, weekly_digest or recipients")
+        })?;
+        if audience == EmailCampaignAudience::Recipients {
+            if req.recipients.is_empty() || req.recipients.len() > MAX_CAMPAIGN_RECIPIENTS {
+                return Err(AppError::validation(format!(
+                    "Campaigns need between 1 and {} recipients",
+                    MAX_CAMPAIGN_RECIPIENTS
+                ))
+                .into());
+            }
+            if req.recipients.iter().any(|r| !r.email.contains('@')) {
+                return Err(AppError::validation("Invalid recipient email address").into());
+            }
+        } else if !req.recipients.is_empty() {
+            return Err(AppError::validation("recipients can only be given with the recipients audience").into());
+        }
+
+        if req.subject.chars().count() > MAX_SUBJECT_CHARS {
+            return Err(
+                AppError::validation(format!("subject can have at most {} characters", MAX_SUBJECT_CHARS)).into(),
+            );
+        }
+        if req.html.chars().count() > MAX_BODY_CHARS || req.text.chars().count() > MAX_BODY_CHARS {
+            return Err(
+                AppError::validation(format!("html and text can have at most {} characters", MAX_BODY_CHARS)).into(),
+            );
+        }
+        if req.subject.trim().is_empty() || req.html.trim().is_empty() || req.text.trim().is_empty() {
+            return Err(AppError::validation("subject, html and text must not be empty").into());
+        }
+        let recipients: Vec<NewCampaignRecipient> = req
+            .recipients
+            .into_iter()
+            .map(|r| NewCampaignRecipient {
+                email: r.email.trim().to_string(),
+                data: r.data,
+            })
+            .collect();
+        let keys = merge_keys(audience, &recipients);
+        if let Some(unknown) = [&req.subject, &req.html, &req.text]
+            .into_iter()
+            .flat_map(|part| placeholders(part))
+            .find(|key| !keys.iter().any(|k| k == key))
+        {
+            return Err(AppError::validation(format!(
+                "Unknown placeholder {{{{{}}}}}; this campaign can use {}",
+                unknown,
+                keys.join(", ")
+            ))
+            .into());
+        }
+
+        let send_at = Self::parse_send_at(req.send_at, Utc::now())?;
+        let content = EmailTemplateContent {
+            subject: req.subject,
+            html: req.html,
+            text: req.text,
+            version: None,
+        };
+        let campaign = self
+            .email_campaigns
+            .create(name, &content, audience, &recipients, send_at, user_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to create email campaign: {:?}", e);
+                AppError::internal("Failed to create email campaign")
+            })?;
+        self.audit_campaign(user_id, "email_campaign.created", &campaign).await;
+
+        info!(
+            user_id = %user_id,
+            campaign_id = %campaign.id,
+            total_recipients = campaign.total_recipients,
+            send_at = %campaign.send_at,
+            "Email campaign created"
+        );
+        Ok(Response::new(Self::campaign_to_proto(&campaign)))
+    }
+
+    #[instrument(skip(self, request), fields(id = %request.get_ref().id))]
+    async fn get_email_campaign(
+        &self,
+        request: Request<GetEmailCampaignRequest>,
+    ) -> Result<Response<ProtoEmailCampaign>, Status> {
+        self.require_admin(&request).await?;
+        let req = request.into_inner();
+        let id = Uuid::parse_str(&req.id).map_err(|_| AppError::validation("Invalid campaign id"))?;
+        debug!("Getting email campaign");
+
+        let campaign = self
+            .email_campaigns
+            .find(id)
+            .await
+            .map_err(|e| {
+                error!("Failed to load email campaign: {:?}", e);
+                AppError::internal("Failed to load email campaign")
+            })?
+            .ok_or_else(|| AppError::not_found("Email campaign not found"))?;
+        Ok(Response::new(Self::campaign_to_proto(&campaign)))
+    }
+
+    #[instrument(skip(self, request), fields(id = %request.get_ref().id))]
+    async fn cancel_email_campaign(
+        &self,
+        request: Request<CancelEmailCampaignRequest>,
+    ) -> Result<Response<ProtoEmailCampaign>, Status> {
+        let user_id = self.require_admin(&request).await?;
+        let req = request.into_inner();
+        let id = Uuid::parse_str(&req.id).map_err(|_| AppError::validation("Invalid campaign id"))?;
+        debug!(user_id = %user_id, "Cancelling email campaign");
+
+        let cancelled = self.email_campaigns.cancel(id).await.map_err(|e| {
+            error!("Failed to cancel email campaign: {:?}", e);
+            AppError::internal("Failed to cancel email campaign")
+        })?;
+        let campaign = self
+            .email_campaigns
+            .find(id)
+            .await
+            .map_err(|e| {
+                error!("Failed to load email campaign: {:?}", e);
+                AppError::internal("Failed to load email campaign")
+            })?
+            .ok_or_else(|| AppError::not_found("Email campaign not found"))?;
+        if cancelled {
+            self.audit_campaign(user_id, "email_campaign.cancelled", &campaign).await;
+            info!(user_id = %user_id, campaign_id = %id, "Email campaign cancelled");
+        }
+        Ok(Response::new(Self::campaign_to_proto(&campaign)))
+    }
+
+    #[instrument(skip(self, request), fields(message_id = %request.get_ref().message_id))]
+    async fn get_email_delivery(
+        &self,
+        request: Request<GetEmailDeliveryRequest>,
+    ) -> Result<Response<EmailDelivery>, Status> {
+        self.require_admin(&request).await?;
+        let req = request.into_inner();
+        let message_id = req.message_id.trim();
+        if message_id.is_empty() {
+            return Err(AppError::validation("message_id is required").into());
+        }
+        debug!("Getting email delivery");
+
+        let events = self.email_events.for_message(message_id).await.map_err(|e| {
+            error!("Failed to load email events: {:?}", e);
+            AppError::internal("Failed to load email delivery")
+        })?;
+        if events.is_empty() {
+            return Err(AppError::not_found("No delivery events for this message").into());
+        }
+        Ok(Response::new(EmailDelivery {
+            message_id: message_id.to_string(),
+            status: delivery_status(&events).to_string(),
+            events: events.iter().map(Self::event_to_proto).collect(),
+        }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn get_log_filter(&self, request: Request<GetLogFilterRequest>) -> Result<Response<LogFilter>, Status> {
+        self.require_admin(&request).await?;
+        let filter = logging::log_filter().ok_or_else(|| AppError::internal("Logging is not initialized"))?;
+        Ok(Response::new(LogFilter { filter }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn set_log_filter(&self, request: Request<SetLogFilterRequest>) -> Result<Response<LogFilter>, Status> {
+        let user_id = self.require_admin(&request).await?;
+        let req = request.into_inner();
+        if req.reset_after_seconds > MAX_LOG_FILTER_RESET_SECONDS {
+            return Err(AppError::validation(format!(
+                "reset_after_seconds must be at most {}",
+                MAX_LOG_FILTER_RESET_SECONDS
+            ))
+            .into());
+        }
+        let reset_after =
+            (req.reset_after_seconds > 0).then(|| std::time::Duration::from_secs(req.reset_after_seconds as u64));
+
+        // Directives that don't parse are the caller's mistake; nothing else can fail once logging is up
+        let filter =
+            logging::set_log_filter(&req.filter, reset_after).map_err(|e| AppError::validation(format!("{:#}", e)))?;
+        info!(
+            user_id = %user_id,
+            filter = %filter,
+            reset_after_seconds = req.reset_after_seconds,
+            "Log filter changed"
+        );
+
+        let metadata = json!({ "filter": filter, "reset_after_seconds": req.reset_after_seconds });
+        if let Err(e) = self
+            .audit_log
+            .record(user_id, "log_filter.changed", "log_filter", "global", metadata)
+            .await
+        {
+            warn!(error = %e, "Failed to audit log filter change");
+        }
+        Ok(Response::new(LogFilter { filter }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn search_users(
+        &self,
+        request: Request<SearchUsersRequest>,
+    ) -> Result<Response<SearchUsersResponse>, Status> {
+        self.require_admin(&request).await?;
+        let req = request.into_inner();
+        let query = req.query.trim();
+        if query.chars().count() < MIN_USER_QUERY_CHARS {
+            return Err(
+                AppError::validation(format!("query must be at least {} characters", MIN_USER_QUERY_CHARS)).into(),
+            );
+        }
+        let limit = if req.limit > 0 {
+            req.limit.min(MAX_USER_SEARCH_LIMIT)
+        } else {
+            DEFAULT_USER_SEARCH_LIMIT
+        };
+
+        let users = self.users.search(query, i64::from(limit)).await.map_err(|e| {
+            error!("Failed to search users: {:?}", e);
+            AppError::internal("Failed to search users")
+        })?;
+        debug!(count = users.len(), "Users found");
+        Ok(Response::new(SearchUsersResponse {
+            users: users.iter().map(Self::user_to_proto).collect(),
+        }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn get_user(&self, request: Request<GetUserRequest>) -> Result<Response<UserDetail>, Status> {
+        self.require_admin(&request).await?;
+        let user_id = Self::parse_user_id(&request.get_ref().user_id)?;
+        let user = self.find_user(user_id).await?;
+
+        let active_sessions = self.sessions.get_user_session_count(user_id).await.map_err(|e| {
+            error!("Failed to count user sessions: {:?}", e);
+            AppError::internal("Failed to load user sessions")
+        })?;
+        let items = self.plaid_items.list_by_user(user_id).await.map_err(|e| {
+            error!("Failed to load linked items: {:?}", e);
+            AppError::internal("Failed to load linked items")
+        })?;
+
+        Ok(Response::new(UserDetail {
+            user: Some(Self::user_to_proto(&user)),
+            active_sessions: active_sessions.min(i32::MAX as u32) as i32,
+            items: items.iter().map(Self::item_to_proto).collect(),
+        }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn revoke_user_sessions(
+        &self,
+        request: Request<RevokeUserSessionsRequest>,
+    ) -> Result<Response<RevokeUserSessionsResponse>, Status> {
+        let admin_id = self.require_admin(&request).await?;
+        let user_id = Self::parse_user_id(&request.get_ref().user_id)?;
+        self.find_user(user_id).await?;
+
+        let revoked = self.sessions.invalidate_all_user_sessions(user_id).await.map_err(|e| {
+            error!("Failed to revoke user sessions: {:?}", e);
+            AppError::internal("Failed to revoke sessions")
+        })?;
+        info!(admin_id = %admin_id, user_id = %user_id, revoked, "User sessions revoked by admin");
+
+        if let Err(e) = self
+            .audit_log
+            .record(
+                admin_id,
+                "user.sessions_revoked",
+                "user",
+                &user_id.to_string(),
+                json!({ "revoked": revoked }),
+            )
+            .await
+        {
+            warn!(user_id = %user_id, error = %e, "Failed to audit session revocation");
+        }
+        Ok(Response::new(RevokeUserSessionsResponse {
+            revoked: revoked.min(i32::MAX as u32) as i32,
+        }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn list_user_emails(
+        &self,
+        request: Request<ListUserEmailsRequest>,
+    ) -> Result<Response<ListUserEmailsResponse>, Status> {
+        self.require_admin(&request).await?;
+        let req = request.into_inner();
+        let user = self.find_user(Self::parse_user_id(&req.user_id)?).await?;
+        let limit = if req.limit > 0 {
+            req.limit.min(MAX_USER_EMAILS_LIMIT)
+        } else {
+            DEFAULT_USER_EMAILS_LIMIT
+        };
+
+        let emails = self
+            .email_queue
+            .list_by_recipient(&user.email, i64::from(limit))
+            .await
+            .map_err(|e| {
+                error!("Failed to load user emails: {:?}", e);
+                AppError::internal("Failed to load emails")
+            })?;
+        Ok(Response::new(ListUserEmailsResponse {
+            emails: emails.iter().map(Self::queued_email_to_proto).collect(),
+        }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn get_item_status(&self, request: Request<GetItemStatusRequest>) -> Result<Response<ItemStatus>, Status> {
+        self.require_admin_or_service(&request).await?;
+        let item_id = request.get_ref().item_id.trim();
+        if item_id.is_empty() {
+            return Err(AppError::validation("item_id is required").into());
+        }
+
+        let item = self
+            .plaid_items
+            .find_by_item_id(item_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to load linked item: {:?}", e);
+                AppError::internal("Failed to load linked item")
+            })?
+            .ok_or_else(|| AppError::not_found("Item not found"))?;
+        Ok(Response::new(Self::item_to_proto(&item)))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn get_otp_stats(&self, request: Request<GetOtpStatsRequest>) -> Result<Response<ProtoOtpStats>, Status> {
+        self.require_admin_or_service(&request).await?;
+        let stats = self.otp.get_otp_stats().await.map_err(|e| {
+            error!("Failed to load OTP stats: {:?}", e);
+            AppError::internal("Failed to load OTP stats")
+        })?;
+        Ok(Response::new(ProtoOtpStats {
+            sent: stats.total_24h,
+            verified: stats.successful_24h,
+            locked: stats.failed_24h,
+            success_rate: stats.success_rate_24h(),
+        }))
+    }
+}
@@ -0,0 +1,412 @@
+use crate::adapter::email_preferences::{EmailCategory, UnsubscribeLinks};
+use crate::error::AppError;
+use crate::gen::alerts::{
+    alerts_service_server::AlertsService, email_unsubscribe_service_server::EmailUnsubscribeService, AlertRule as ProtoAlertRule, AlertRuleKind as ProtoAlertRuleKind,
+    CreateAlertRuleRequest, DeleteAlertRuleRequest, DeleteAlertRuleResponse, GetNotificationPreferencesRequest,
+    ListAlertRulesRequest, ListAlertRulesResponse, ListNotificationsRequest, ListNotificationsResponse,
+    Notification as ProtoNotification, NotificationPreferences, SendWeeklyDigestRequest, SendWeeklyDigestResponse,
+    UnsubscribeRequest, UnsubscribeResponse, UpdateNotificationPreferencesRequest,
+};
+use crate::handler::interceptor::AuthContext;
+use crate::handler::pagination::page_size;
+use crate::jobs::WeeklyDigestSender;
+use crate::model::alert_rule::{AlertRule, AlertRuleKind, AlertRuleRepository, NewAlertRule};
+use crate::model::auth::Scope;
+use crate::model::bank_account::BankAccountRepository;
+use crate::model::email_preferences::{EmailPreferences, EmailPreferencesRepository};
+use crate::model::notification::{Notification, NotificationRepository};
+use crate::model::transaction_category::taxonomy_category;
+use crate::model::user::{User, UserRepository};
+use chrono::Utc;
+use tonic::{Request, Response, Status};
+use tracing::{debug, error, info, instrument};
+use uuid::Uuid;
+
+const DEFAULT_NOTIFICATION_LIMIT: i32 = 50;
+const MAX_NOTIFICATION_LIMIT: i32 = 200;
+
+/// gRPC service managing alert rules and listing the notifications they sent
+pub struct AlertsHandler {
+    rule_repository: AlertRuleRepository,
+    notification_repository: NotificationRepository,
+    account_repository: BankAccountRepository,
+    user_repository: UserRepository,
+    email_preferences: EmailPreferencesRepository,
+    /// `None` when SES is not configured
+    digest_sender: Option<WeeklyDigestSender>,
+}
+
+impl AlertsHandler {
+    pub fn new(
+        rule_repository: AlertRuleRepository,
+        notification_repository: NotificationRepository,
+        account_repository: BankAccountRepository,
+        user_repository: UserRepository,
+        email_preferences: EmailPreferencesRepository,
+        digest_sender: Option<WeeklyDigestSender>,
+    ) -> Self {
+        Self {
+            rule_repository,
+            notification_repository,
+            account_repository,
+            user_repository,
+            email_preferences,
+            digest_sender,
+        }
+    }
+
+    async fn find_user(&self, user_id: Uuid) -> Result<User, AppError> {
+        self.user_repository
+            .find_by_id(user_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to load user: {:?}", e);
+                AppError::internal("Failed to load user")
+            })?
+            .ok_or_else(|| AppError::not_found("User not found"))
+    }
+
+    async fn find_email_preferences(&self, user: &User) -> Result<EmailPreferences, AppError> {
+        self.email_preferences.find(&user.email).await.map_err(|e| {
+            error!("Failed to load email preferences: {:?}", e);
+            AppError::internal("Failed to load notification preferences")
+        })
+    }
+
+    async fn set_email_preference(&self, user: &User, category: EmailCategory, enabled: bool) -> Result<(), AppError> {
+        self.email_preferences
+            .set(&user.email, category, enabled)
+            .await
+            .map(|_| ())
+            .map_err(|e| {
+                error!("Failed to update email preference: {:?}", e);
+                AppError::internal("Failed to update notification preferences")
+            })
+    }
+
+    fn preferences_to_proto(user: &User, email_preferences: &EmailPreferences) -> NotificationPreferences {
+        NotificationPreferences {
+            weekly_digest_enabled: user.weekly_digest_enabled,
+            timezone: user.timezone.clone(),
+            transactional_emails: email_preferences.transactional,
+            marketing_emails: email_preferences.marketing,
+        }
+    }
+
+    fn kind_from_proto(kind: i32) -> Result<AlertRuleKind, AppError> {
+        match ProtoAlertRuleKind::try_from(kind) {
+            Ok(ProtoAlertRuleKind::LargeTransaction) => Ok(AlertRuleKind::LargeTransaction),
+            Ok(ProtoAlertRuleKind::LowBalance) => Ok(AlertRuleKind::LowBalance),
+            _ => Err(AppError::validation("kind is required")),
+        }
+    }
+
+    fn rule_to_proto(rule: &AlertRule) -> ProtoAlertRule {
+        let kind = match rule.kind() {
+            Some(AlertRuleKind::LargeTransaction) => ProtoAlertRuleKind::LargeTransaction,
+            Some(AlertRuleKind::LowBalance) => ProtoAlertRuleKind::LowBalance,
+            None => ProtoAlertRuleKind::Unspecified,
+        };
+        ProtoAlertRule {
+            rule_id: rule.id.to_string(),
+            kind: kind as i32,
+            account_id: rule.account_id.clone(),
+            category: rule.category.clone(),
+            threshold: rule.threshold,
+            enabled: rule.enabled,
+            created_at: rule.created_at.timestamp(),
+        }
+    }
+
+    fn notification_to_proto(notification: &Notification) -> ProtoNotification {
+        ProtoNotification {
+            notification_id: notification.id.to_string(),
+            rule_id: notification.rule_id.map(|id| id.to_string()),
+            kind: notification.kind.clone(),
+            channel: notification.channel.clone(),
+            subject: notification.subject.clone(),
+            body: notification.body.clone(),
+            status: notification.status.clone(),
+            created_at: notification.created_at.timestamp(),
+            sent_at: notification.sent_at.map(|t| t.timestamp()),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl AlertsService for AlertsHandler {
+    #[instrument(skip(self, request))]
+    async fn create_alert_rule(
+        &self,
+        request: Request<CreateAlertRuleRequest>,
+    ) -> Result<Response<ProtoAlertRule>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsWrite)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!(user_id = %user_id, kind = req.kind, "Creating alert rule");
+
+        let kind = Self::kind_from_proto(req.kind)?;
+        if !req.threshold.is_finite() || req.threshold <= 0.0 {
+            return Err(AppError::validation("threshold must be positive").into());
+        }
+
+        let category = match req.category.as_deref().map(str::trim).filter(|c| !c.is_empty()) {
+            Some(_) if kind == AlertRuleKind::LowBalance => {
+                return Err(AppError::validation("category only applies to large-transaction rules").into());
+            }
+            Some(category) => Some(
+                taxonomy_category(category)
+                    .ok_or_else(|| AppError::validation(format!("Unknown category: {}", category)))?
+                    .to_string(),
+            ),
+            None => None,
+        };
+
+        let account_id = req.account_id.filter(|id| !id.is_empty());
+        if let Some(account_id) = &account_id {
+            let account = self
+                .account_repository
+                .find_by_account_id(account_id)
+                .await
+                .map_err(|e| {
+                    error!("Failed to load account: {:?}", e);
+                    AppError::internal("Failed to create alert rule")
+                })?;
+            if !matches!(account, Some(account) if account.user_id == user_id) {
+                return Err(AppError::not_found("Account not found").into());
+            }
+        }
+
+        let rule = self
+            .rule_repository
+            .create(&NewAlertRule {
+                user_id,
+                kind,
+                account_id,
+                category,
+                threshold: req.threshold,
+            })
+            .await
+            .map_err(|e| {
+                error!("Failed to create alert rule: {:?}", e);
+                AppError::internal("Failed to create alert rule")
+            })?;
+
+        info!(user_id = %user_id, rule_id = %rule.id, "Alert rule created");
+        Ok(Response::new(Self::rule_to_proto(&rule)))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn list_alert_rules(
+        &self,
+        request: Request<ListAlertRulesRequest>,
+    ) -> Result<Response<ListAlertRulesResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsRead)?;
+        let user_id = auth.user_id;
+        debug!(user_id = %user_id, "Listing alert rules");
+
+        let rules = self.rule_repository.list_by_user(user_id).await.map_err(|e| {
+            error!("Failed to list alert rules: {:?}", e);
+            AppError::internal("Failed to list alert rules")
+        })?;
+
+        Ok(Response::new(ListAlertRulesResponse {
+            rules: rules.iter().map(Self::rule_to_proto).collect(),
+        }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn delete_alert_rule(
+        &self,
+        request: Request<DeleteAlertRuleRequest>,
+    ) -> Result<Response<DeleteAlertRuleResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsWrite)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!(user_id = %user_id, rule_id = %req.rule_id, "Deleting alert rule");
+
+        let rule_id = Uuid::parse_str(&req.rule_id)
+            .map_err(|_| AppError::validation("rule_id must be a UUID"))?;
+
+        let deleted = self.rule_repository.delete(user_id, rule_id).await.map_err(|e| {
+            error!("Failed to delete alert rule: {:?}", e);
+            AppError::internal("Failed to delete alert rule")
+        })?;
+        if !deleted {
+            return Err(AppError::not_found("Alert rule not found").into());
+        }
+
+        info!(user_id = %user_id, rule_id = %rule_id, "Alert rule deleted");
+        Ok(Response::new(DeleteAlertRuleResponse { success: true }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn list_notifications(
+        &self,
+        request: Request<ListNotificationsRequest>,
+    ) -> Result<Response<ListNotificationsResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsRead)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        let limit = page_size(req.limit, DEFAULT_NOTIFICATION_LIMIT, MAX_NOTIFICATION_LIMIT);
+        debug!(user_id = %user_id, limit, "Listing notifications");
+
+        let notifications = self
+            .notification_repository
+            .list_by_user(user_id, limit as i64)
+            .await
+            .map_err(|e| {
+                error!("Failed to list notifications: {:?}", e);
+                AppError::internal("Failed to list notifications")
+            })?;
+
+        Ok(Response::new(ListNotificationsResponse {
+            notifications: notifications.iter().map(Self::notification_to_proto).collect(),
+        }))
+    }
+    #[instrument(skip(self, request))]
+    async fn get_notification_preferences(
+        &self,
+        request: Request<GetNotificationPreferencesRequest>,
+    ) -> Result<Response<NotificationPreferences>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::ProfileRead)?;
+        let user_id = auth.user_id;
+        debug!(user_id = %user_id, "Getting notification preferences");
+
+        let user = self.find_user(user_id).await?;
+        let email_preferences = self.find_email_preferences(&user).await?;
+        Ok(Response::new(Self::preferences_to_proto(&user, &email_preferences)))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn update_notification_preferences(
+        &self,
+        request: Request<UpdateNotificationPreferencesRequest>,
+    ) -> Result<Response<NotificationPreferences>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::ProfileWrite)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!(user_id = %user_id, weekly_digest_enabled = ?req.weekly_digest_enabled, transactional_emails = ?req.transactional_emails, marketing_emails = ?req.marketing_emails, "Updating notification preferences");
+
+        if let Some(timezone) = &req.timezone {
+            if timezone.parse::<chrono_tz::Tz>().is_err() {
+                return Err(AppError::validation(format!("Unknown time zone '{}'", timezone)).into());
+            }
+            self.user_repository
+                .set_timezone(user_id, timezone)
+                .await
+                .map_err(|e| {
+                    error!("Failed to update time zone: {:?}", e);
+                    AppError::internal("Failed to update notification preferences")
+                })?
+                .ok_or_else(|| AppError::not_found("User not found"))?;
+        }
+        let user = match req.weekly_digest_enabled {
+            Some(enabled) => self
+                .user_repository
+                .set_weekly_digest(user_id, enabled)
+                .await
+                .map_err(|e| {
+                    error!("Failed to update weekly digest preference: {:?}", e);
+                    AppError::internal("Failed to update notification preferences")
+                })?
+                .ok_or_else(|| AppError::not_found("User not found"))?,
+            None => self.find_user(user_id).await?,
+        };
+        // Kept in step with the digest opt-out, which one-click unsubscribe links also change
+        if let Some(enabled) = req.weekly_digest_enabled {
+            self.set_email_preference(&user, EmailCategory::Digest, enabled).await?;
+        }
+        if let Some(enabled) = req.transactional_emails {
+            self.set_email_preference(&user, EmailCategory::Transactional, enabled).await?;
+        }
+        if let Some(enabled) = req.marketing_emails {
+            self.set_email_preference(&user, EmailCategory::Marketing, enabled).await?;
+        }
+        let email_preferences = self.find_email_preferences(&user).await?;
+
+        info!(
+            user_id = %user_id,
+            weekly_digest_enabled = user.weekly_digest_enabled,
+            transactional_emails = email_preferences.transactional,
+            marketing_emails = email_preferences.marketing,
+            timezone = %user.timezone,
+            "Notification preferences updated"
+        );
+        Ok(Response::new(Self::preferences_to_proto(&user, &email_preferences)))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn send_weekly_digest(
+        &self,
+        request: Request<SendWeeklyDigestRequest>,
+    ) -> Result<Response<SendWeeklyDigestResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsRead)?;
+        let user_id = auth.user_id;
+        debug!(user_id = %user_id, "Sending weekly digest now");
+
+        let sender = self
+            .digest_sender
+            .as_ref()
+            .ok_or_else(|| AppError::upstream("ses", "Email delivery is not configured"))?;
+        let user = self.find_user(user_id).await?;
+
+        // Keyed by the hour so repeated test sends can't flood the user's inbox
+        let now = Utc::now();
+        let dedup_key = format!("weekly_digest:test:{}", now.format("%Y-%m-%dT%H"));
+        let sent = sender.send(&user, now.date_naive(), dedup_key).await.map_err(|e| {
+            error!("Failed to send weekly digest: {:?}", e);
+            AppError::upstream("ses", "Failed to send weekly digest")
+        })?;
+
+        info!(user_id = %user_id, sent, "Weekly digest send requested");
+        Ok(Response::new(SendWeeklyDigestResponse { sent }))
+    }
+}
+
+/// Unauthenticated gRPC service behind the one-click unsubscribe links in emails; the signed token
+/// is the only credential
+pub struct UnsubscribeHandler {
+    preferences: EmailPreferencesRepository,
+    /// `None` when unsubscribe links are not configured
+    links: Option<UnsubscribeLinks>,
+}
+
+impl UnsubscribeHandler {
+    pub fn new(preferences: EmailPreferencesRepository, links: Option<UnsubscribeLinks>) -> Self {
+        Self { preferences, links }
+    }
+}
+
+#[tonic::async_trait]
+impl EmailUnsubscribeService for UnsubscribeHandler {
+    #[instrument(skip(self, request))]
+    async fn unsubscribe(&self, request: Request<UnsubscribeRequest>) -> Result<Response<UnsubscribeResponse>, Status> {
+        let req = request.into_inner();
+        let links = self
+            .links
+            .as_ref()
+            .ok_or_else(|| AppError::not_found("Unsubscribe links are not configured"))?;
+        let (email, category) = links.verify(&req.token).map_err(|e| {
+            debug!("Rejected unsubscribe token: {}", e);
+            AppError::validation("Invalid unsubscribe link")
+        })?;
+
+        self.preferences.set(&email, category, false).await.map_err(|e| {
+            error!("Failed to unsubscribe: {:?}", e);
+            AppError::internal("Failed to unsubscribe")
+        })?;
+
+        info!(category = category.as_str(), "Recipient unsubscribed");
+        Ok(Response::new(UnsubscribeResponse {
+            category: category.as_str().to_string(),
+        }))
+    }
+}
@@ -0,0 +1,583 @@
+use crate::adapter::llm::{LlmMessage, LlmProvider, LlmProviders, LlmRole, LlmRequest, LlmStream, LlmStreamEvent};
+use crate::error::AppError;
+use crate::financial_assistant::FinancialAssistant;
+use crate::gen::assistant::{
+    assistant_service_server::AssistantService, stream_message_event::Event, AiBatch as ProtoAiBatch,
+    AiBatchTaskResult, AskFinancialAssistantRequest, AskFinancialAssistantResponse, FeatureUsage as ProtoFeatureUsage,
+    GetAiBatchRequest, GetAiUsageRequest, GetAiUsageResponse, MessageRole, MessageStop, StreamMessageEvent,
+    StreamMessageRequest, SubmitAiBatchRequest, TransactionCitation,
+};
+use crate::handler::interceptor::AuthContext;
+use crate::model::ai_batch::{AiBatch, AiBatchKind, AiBatchRepository, AiBatchTask};
+use crate::model::ai_rate_limit::{AiAdmission, AiPermit, AiRateLimiter};
+use crate::model::ai_usage::{quota_period, AiFeature, AiUsageRepository, AiUsageTotals, BudgetStatus, NewAiUsage};
+use crate::model::auth::Scope;
+use crate::moderation::{ContentModerator, ModerationAction};
+use crate::prompts::{PromptKey, PromptRegistry};
+use chrono::Utc;
+use futures::{Stream, StreamExt};
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tonic::{Request, Response, Status};
+use tracing::{debug, error, info, instrument, warn};
+use uuid::Uuid;
+
+const DEFAULT_MAX_TOKENS: i32 = 1024;
+const MAX_MAX_TOKENS: i32 = 4096;
+/// Longest conversation accepted in one request
+const MAX_CONVERSATION_MESSAGES: usize = 50;
+/// Limit on the combined length of all turns, in characters
+const MAX_CONVERSATION_CHARS: usize = 100_000;
+/// Events queued per reply stream before the forwarding task waits on the client
+const REPLY_STREAM_BUFFER: usize = 32;
+/// Longest question accepted by AskFinancialAssistant
+const MAX_QUESTION_CHARS: usize = 2_000;
+/// Most tasks accepted in one batch
+const MAX_BATCH_TASKS: usize = 500;
+/// Longest input of a batch task, in characters
+const MAX_BATCH_INPUT_CHARS: usize = 200;
+
+/// Compiled default system prompt of replies without a custom one; guardrails are appended
+pub const DEFAULT_SYSTEM_PROMPT: &str = "You are a helpful personal finance assistant inside a budgeting app. \
+Answer clearly and concisely. You do not have access to the user's accounts or transactions unless \
+they are included in the conversation.";
+
+/// gRPC service streaming replies from the assistant
+pub struct AssistantHandler {
+    llm_providers: LlmProviders,
+    ai_usage: AiUsageRepository,
+    financial_assistant: FinancialAssistant,
+    moderator: Arc<ContentModerator>,
+    ai_batches: AiBatchRepository,
+    prompts: Arc<PromptRegistry>,
+    rate_limiter: Arc<AiRateLimiter>,
+}
+
+impl AssistantHandler {
+    /// Requests fail as unavailable when no provider is configured
+    pub fn new(
+        llm_providers: LlmProviders,
+        ai_usage: AiUsageRepository,
+        financial_assistant: FinancialAssistant,
+        moderator: Arc<ContentModerator>,
+        ai_batches: AiBatchRepository,
+        prompts: Arc<PromptRegistry>,
+        rate_limiter: Arc<AiRateLimiter>,
+    ) -> Self {
+        Self {
+            llm_providers,
+            ai_usage,
+            financial_assistant,
+            moderator,
+            ai_batches,
+            prompts,
+            rate_limiter,
+        }
+    }
+}
+
+/// Refuse AI requests while the user's monthly quota or the feature's monthly budget is used up
+pub async fn check_ai_quota(ai_usage: &AiUsageRepository, user_id: Uuid, feature: AiFeature) -> Result<(), AppError> {
+    let now = Utc::now();
+    match ai_usage.quota_exhausted_until(user_id, now).await {
+        Ok(None) => {}
+        Ok(Some(resets_at)) => {
+            info!(user_id = %user_id, resets_at = %resets_at, "AI usage quota reached");
+            return Err(AppError::rate_limited(
+                "Monthly AI usage quota reached",
+                (resets_at - now).to_std().ok(),
+            ));
+        }
+        Err(e) => {
+            error!("Failed to check AI usage quota: {:?}", e);
+            return Err(AppError::internal("Failed to check AI usage"));
+        }
+    }
+
+    match ai_usage.budget_status(feature, now).await {
+        Ok(BudgetStatus::Exceeded { spent_usd, budget_usd }) => {
+            warn!(feature = feature.as_str(), spent_usd, budget_usd, "AI feature budget exceeded");
+            let (_, resets_at) = quota_period(now);
+            Err(AppError::rate_limited(
+                "This AI feature is unavailable until next month",
+                (resets_at - now).to_std().ok(),
+            ))
+        }
+        Ok(_) => Ok(()),
+        Err(e) => {
+            error!("Failed to check AI budget: {:?}", e);
+            Err(AppError::internal("Failed to check AI usage"))
+        }
+    }
+}
+
+/// Admit an AI request under the rate and concurrency limits; hold the permit until the provider has replied.
+/// Requests are admitted while Redis is unreachable, so an outage there doesn't take the AI features down.
+pub async fn admit_ai_request(
+    limiter: &AiRateLimiter,
+    user_id: Uuid,
+    feature: AiFeature,
+) -> Result<AiPermit, AppError> {
+    match limiter.acquire(user_id).await {
+        Ok(AiAdmission::Admitted(permit)) => Ok(permit),
+        Ok(AiAdmission::Throttled(limit, retry_after)) => {
+            info!(
+                user_id = %user_id,
+                feature = feature.as_str(),
+                limit = limit.as_str(),
+                retry_after_secs = retry_after.as_secs(),
+                "AI request throttled"
+            );
+            Err(AppError::rate_limited(limit.message(), Some(retry_after)))
+        }
+        Err(e) => {
+            warn!(feature = feature.as_str(), "Failed to check AI rate limits; admitting request: {:?}", e);
+            Ok(AiPermit::unlimited())
+        }
+    }
+}
+
+/// Refuse user text the moderation policy blocks, before it is sent to a provider
+pub async fn moderate_input(
+    moderator: &ContentModerator,
+    user_id: Uuid,
+    feature: AiFeature,
+    text: &str,
+) -> Result<(), AppError> {
+    let verdict = moderator.moderate(user_id, feature, text).await;
+    if verdict.action != ModerationAction::Block {
+        return Ok(());
+    }
+    let reasons: Vec<&str> = verdict
+        .blocking(moderator.policy())
+        .iter()
+        .map(|c| c.block_reason())
+        .collect();
+    Err(AppError::validation(format!("The message can't be sent because {}", reasons.join(" and "))))
+}
+
+/// Record a completed AI request; failures are only logged, as the reply was already produced
+pub async fn record_ai_usage(ai_usage: &AiUsageRepository, usage: NewAiUsage<'_>) {
+    if let Err(e) = ai_usage.record(&usage).await {
+        warn!(feature = usage.feature.as_str(), "Failed to record AI usage: {:?}", e);
+    }
+}
+
+fn usage_totals_to_proto(feature: String, totals: &AiUsageTotals) -> ProtoFeatureUsage {
+    ProtoFeatureUsage {
+        feature,
+        request_count: totals.request_count,
+        input_tokens: totals.input_tokens,
+        output_tokens: totals.output_tokens,
+        cost_usd: totals.cost_usd,
+        cache_read_tokens: totals.cache_read_tokens,
+        cache_write_tokens: totals.cache_write_tokens,
+    }
+}
+
+fn batch_to_proto(batch: AiBatch, tasks: Vec<AiBatchTask>) -> ProtoAiBatch {
+    ProtoAiBatch {
+        batch_id: batch.id.to_string(),
+        kind: batch.kind,
+        status: batch.status,
+        total_tasks: batch.total_tasks,
+        succeeded_tasks: batch.succeeded_tasks,
+        failed_tasks: batch.failed_tasks,
+        created_at: batch.created_at.timestamp(),
+        completed_at: batch.completed_at.map(|t| t.timestamp()),
+        tasks: tasks
+            .into_iter()
+            .map(|task| AiBatchTaskResult {
+                input: task.input,
+                status: task.status,
+                output: task.output.map(|output| output.to_string()),
+                error: task.error,
+                attempts: task.attempts,
+            })
+            .collect(),
+    }
+}
+
+/// Provider a request names, or the default; unknown names are a validation error
+pub fn resolve_provider(providers: &LlmProviders, provider: Option<&str>) -> Result<Arc<dyn LlmProvider>, AppError> {
+    if providers.is_empty() {
+        return Err(AppError::upstream("llm", "The assistant is not configured"));
+    }
+    providers
+        .resolve(provider)
+        .map_err(|e| AppError::validation(e.to_string()))
+}
+
+/// Validate the conversation: it alternates roles, starts and ends with the user, and stays within size limits
+fn conversation_from_proto(request: &StreamMessageRequest) -> Result<Vec<LlmMessage>, AppError> {
+    if request.messages.is_empty() {
+        return Err(AppError::validation("messages must not be empty"));
+    }
+    if request.messages.len() > MAX_CONVERSATION_MESSAGES {
+        return Err(AppError::validation(format!(
+            "A conversation can have at most {} messages",
+            MAX_CONVERSATION_MESSAGES
+        )));
+    }
+    let total_chars: usize = request.messages.iter().map(|m| m.content.chars().count()).sum();
+    if total_chars > MAX_CONVERSATION_CHARS {
+        return Err(AppError::validation(format!(
+            "A conversation can have at most {} characters",
+            MAX_CONVERSATION_CHARS
+        )));
+    }
+
+    let mut messages = Vec::with_capacity(request.messages.len());
+    for (index, message) in request.messages.iter().enumerate() {
+        let expected = if index % 2 == 0 { MessageRole::User } else { MessageRole::Assistant };
+        match MessageRole::try_from(message.role) {
+            Ok(role) if role == expected => {}
+            Ok(MessageRole::User) | Ok(MessageRole::Assistant) => {
+                return Err(AppError::validation(
+                    "messages must alternate between user and assistant, starting with the user",
+                ));
+            }
+            _ => return Err(AppError::validation("Each message needs a user or assistant role")),
+        }
+        if message.content.trim().is_empty() {
+            return Err(AppError::validation("Message content must not be empty"));
+        }
+        messages.push(match expected {
+            MessageRole::Assistant => LlmMessage::assistant(&message.content),
+            _ => LlmMessage::user(&message.content),
+        });
+    }
+    if request.messages.len() % 2 == 0 {
+        return Err(AppError::validation("The last message must be from the user"));
+    }
+
+    Ok(messages)
+}
+
+/// Requested reply length, defaulting when unset
+pub fn max_tokens(requested: Option<i32>) -> Result<u32, AppError> {
+    match requested {
+        None => Ok(DEFAULT_MAX_TOKENS as u32),
+        Some(tokens) if (1..=MAX_MAX_TOKENS).contains(&tokens) => Ok(tokens as u32),
+        Some(_) => Err(AppError::validation(format!(
+            "max_tokens must be between 1 and {}",
+            MAX_MAX_TOKENS
+        ))),
+    }
+}
+
+fn event_to_proto(event: LlmStreamEvent) -> StreamMessageEvent {
+    let event = match event {
+        LlmStreamEvent::TextDelta(text) => Event::TextDelta(text),
+        LlmStreamEvent::Stop { stop_reason, usage } => Event::Stop(MessageStop {
+            stop_reason: stop_reason.unwrap_or_default(),
+            input_tokens: usage.input_tokens as i32,
+            output_tokens: usage.output_tokens as i32,
+        }),
+    };
+    StreamMessageEvent { event: Some(event) }
+}
+
+/// Who a streamed reply is generated for, to record its usage once it stops
+struct ReplyUsage {
+    ai_usage: AiUsageRepository,
+    user_id: Uuid,
+    provider: &'static str,
+    model: String,
+    /// Held until the reply ends, so the stream counts against the concurrency limits
+    _permit: AiPermit,
+}
+
+/// Forward a reply to the client until it stops; a closed client stream drops the upstream response,
+/// and its usage goes unrecorded since providers only report usage at the end
+async fn forward_reply(
+    mut reply: LlmStream,
+    usage: ReplyUsage,
+    tx: mpsc::Sender<Result<StreamMessageEvent, Status>>,
+) {
+    let mut deltas = 0usize;
+    loop {
+        match reply.next().await.transpose() {
+            Ok(Some(event)) => {
+                match &event {
+                    LlmStreamEvent::TextDelta(_) => deltas += 1,
+                    LlmStreamEvent::Stop { usage: tokens, .. } => {
+                        record_ai_usage(
+                            &usage.ai_usage,
+                            NewAiUsage {
+                                user_id: usage.user_id,
+                                feature: AiFeature::Assistant,
+                                provider: usage.provider,
+                                model: &usage.model,
+                                input_tokens: tokens.input_tokens,
+                                output_tokens: tokens.output_tokens,
+                                cache_read_tokens: tokens.cache_read_tokens,
+                                cache_write_tokens: tokens.cache_write_tokens,
+                            },
+                        )
+                        .await;
+                    }
+                }
+                if tx.send(Ok(event_to_proto(event))).await.is_err() {
+                    debug!(deltas, "Assistant stream closed by client");
+                    return;
+                }
+            }
+            Ok(None) => break,
+            Err(e) => {
+                error!("Assistant reply stream failed: {:?}", e);
+                let _ = tx
+                    .send(Err(AppError::upstream("llm", "The assistant reply was interrupted").into()))
+                    .await;
+                return;
+            }
+        }
+    }
+
+    debug!(deltas, "Assistant stream finished");
+}
+
+#[tonic::async_trait]
+impl AssistantService for AssistantHandler {
+    type StreamMessageStream = Pin<Box<dyn Stream<Item = Result<StreamMessageEvent, Status>> + Send + 'static>>;
+
+    #[instrument(skip(self, request))]
+    async fn stream_message(
+        &self,
+        request: Request<StreamMessageRequest>,
+    ) -> Result<Response<Self::StreamMessageStream>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::TransactionsRead)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!(message_count = req.messages.len(), "Streaming assistant message");
+
+        let messages = conversation_from_proto(&req)?;
+        let max_tokens = max_tokens(req.max_tokens)?;
+        let provider = resolve_provider(&self.llm_providers, req.provider.as_deref())?;
+        check_ai_quota(&self.ai_usage, user_id, AiFeature::Assistant).await?;
+        let user_turns: Vec<&str> = messages
+            .iter()
+            .filter(|m| m.role == LlmRole::User)
+            .map(|m| m.content.as_str())
+            .collect();
+        moderate_input(&self.moderator, user_id, AiFeature::Assistant, &user_turns.join("\n\n")).await?;
+        let permit = admit_ai_request(&self.rate_limiter, user_id, AiFeature::Assistant).await?;
+
+        let llm_request = LlmRequest {
+            system: Some(self.prompts.system_prompt(PromptKey::Assistant)),
+            messages,
+            max_tokens,
+            temperature: Some(0.7),
+            ..Default::default()
+        };
+        let reply = provider.stream(llm_request).await.map_err(|e| {
+            error!(provider = provider.name(), "Failed to start assistant reply: {:?}", e);
+            AppError::upstream(provider.name(), "The assistant is unavailable")
+        })?;
+
+        let (tx, rx) = mpsc::channel(REPLY_STREAM_BUFFER);
+        let usage = ReplyUsage {
+            ai_usage: self.ai_usage.clone(),
+            user_id,
+            provider: provider.name(),
+            model: provider.default_model().to_string(),
+            _permit: permit,
+        };
+        tokio::spawn(forward_reply(reply, usage, tx));
+
+        info!(user_id = %user_id, provider = provider.name(), "Assistant reply stream started");
+        let stream = futures::stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|event| (event, rx))
+        });
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn ask_financial_assistant(
+        &self,
+        request: Request<AskFinancialAssistantRequest>,
+    ) -> Result<Response<AskFinancialAssistantResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsRead)?;
+        auth.require_scope(Scope::TransactionsRead)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!(user_id = %user_id, "Asking financial assistant");
+
+        let question = req.question.trim();
+        if question.is_empty() {
+            return Err(AppError::validation("question must not be empty").into());
+        }
+        if question.chars().count() > MAX_QUESTION_CHARS {
+            return Err(AppError::validation(format!("question can have at most {} characters", MAX_QUESTION_CHARS)).into());
+        }
+        let provider = resolve_provider(&self.llm_providers, req.provider.as_deref())?;
+        check_ai_quota(&self.ai_usage, user_id, AiFeature::FinancialAssistant).await?;
+        moderate_input(&self.moderator, user_id, AiFeature::FinancialAssistant, question).await?;
+        let _permit = admit_ai_request(&self.rate_limiter, user_id, AiFeature::FinancialAssistant).await?;
+
+        let answer = self
+            .financial_assistant
+            .answer(provider.as_ref(), user_id, question, Utc::now().date_naive())
+            .await
+            .map_err(|e| {
+                error!(provider = provider.name(), "Failed to answer financial question: {:?}", e);
+                AppError::upstream(provider.name(), "The assistant is unavailable")
+            })?;
+        record_ai_usage(
+            &self.ai_usage,
+            NewAiUsage {
+                user_id,
+                feature: AiFeature::FinancialAssistant,
+                provider: provider.name(),
+                model: &answer.model,
+                input_tokens: answer.usage.input_tokens,
+                output_tokens: answer.usage.output_tokens,
+                cache_read_tokens: answer.usage.cache_read_tokens,
+                cache_write_tokens: answer.usage.cache_write_tokens,
+            },
+        )
+        .await;
+
+        info!(
+            user_id = %user_id,
+            provider = provider.name(),
+            citations = answer.citations.len(),
+            "Financial question answered"
+        );
+        Ok(Response::new(AskFinancialAssistantResponse {
+            citations: answer
+                .citations
+                .iter()
+                .map(|(label, t)| TransactionCitation {
+                    label: label.clone(),
+                    transaction_id: t.transaction_id.clone(),
+                    date: t.date.to_string(),
+                    name: t.merchant_name.clone().unwrap_or_else(|| t.name.clone()),
+                    amount: t.amount,
+                    iso_currency_code: t.iso_currency_code.clone().unwrap_or_default(),
+                })
+                .collect(),
+            answer: answer.text,
+        }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn get_ai_usage(
+        &self,
+        request: Request<GetAiUsageRequest>,
+    ) -> Result<Response<GetAiUsageResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::TransactionsRead)?;
+        let user_id = auth.user_id;
+        debug!(user_id = %user_id, "Getting AI usage");
+
+        let (period_start, period_end) = quota_period(Utc::now());
+        let features = self
+            .ai_usage
+            .usage_by_feature(user_id, period_start)
+            .await
+            .map_err(|e| {
+                error!("Failed to load AI usage: {:?}", e);
+                AppError::internal("Failed to load AI usage")
+            })?;
+
+        let mut totals = AiUsageTotals::default();
+        for feature in &features {
+            totals.request_count += feature.totals.request_count;
+            totals.input_tokens += feature.totals.input_tokens;
+            totals.output_tokens += feature.totals.output_tokens;
+            totals.cache_read_tokens += feature.totals.cache_read_tokens;
+            totals.cache_write_tokens += feature.totals.cache_write_tokens;
+            totals.cost_usd += feature.totals.cost_usd;
+        }
+        let quota = self.ai_usage.quota();
+
+        Ok(Response::new(GetAiUsageResponse {
+            period_start: period_start.timestamp(),
+            period_end: period_end.timestamp(),
+            request_count: totals.request_count,
+            input_tokens: totals.input_tokens,
+            output_tokens: totals.output_tokens,
+            cost_usd: totals.cost_usd,
+            token_quota: quota.monthly_tokens,
+            cost_quota_usd: quota.monthly_cost_usd,
+            quota_exceeded: quota.exceeded_by(&totals),
+            cache_read_tokens: totals.cache_read_tokens,
+            cache_write_tokens: totals.cache_write_tokens,
+            features: features
+                .into_iter()
+                .map(|f| usage_totals_to_proto(f.feature, &f.totals))
+                .collect(),
+        }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn submit_ai_batch(&self, request: Request<SubmitAiBatchRequest>) -> Result<Response<ProtoAiBatch>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::TransactionsWrite)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!(user_id = %user_id, kind = %req.kind, tasks = req.inputs.len(), "Submitting AI batch");
+
+        let kind = AiBatchKind::parse(&req.kind).ok_or_else(|| AppError::validation("Unknown batch kind"))?;
+        if req.inputs.is_empty() {
+            return Err(AppError::validation("inputs must not be empty").into());
+        }
+        if req.inputs.len() > MAX_BATCH_TASKS {
+            return Err(AppError::validation(format!("A batch holds at most {} tasks", MAX_BATCH_TASKS)).into());
+        }
+        if req
+            .inputs
+            .iter()
+            .any(|input| input.trim().is_empty() || input.chars().count() > MAX_BATCH_INPUT_CHARS)
+        {
+            return Err(AppError::validation(format!(
+                "Inputs must be non-empty and at most {} characters",
+                MAX_BATCH_INPUT_CHARS
+            ))
+            .into());
+        }
+        let feature = match kind {
+            AiBatchKind::Categorization => AiFeature::BatchCategorization,
+        };
+        check_ai_quota(&self.ai_usage, user_id, feature).await?;
+
+        let inputs: Vec<String> = req.inputs.iter().map(|input| input.trim().to_string()).collect();
+        let batch = self.ai_batches.create(user_id, kind, &inputs).await.map_err(|e| {
+            error!("Failed to create AI batch: {:?}", e);
+            AppError::internal("Failed to create batch")
+        })?;
+
+        info!(user_id = %user_id, batch_id = %batch.id, tasks = batch.total_tasks, "AI batch submitted");
+        Ok(Response::new(batch_to_proto(batch, Vec::new())))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn get_ai_batch(&self, request: Request<GetAiBatchRequest>) -> Result<Response<ProtoAiBatch>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::TransactionsRead)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!(user_id = %user_id, batch_id = %req.batch_id, "Getting AI batch");
+
+        let batch_id = Uuid::parse_str(&req.batch_id).map_err(|_| AppError::validation("batch_id must be a UUID"))?;
+        let batch = self
+            .ai_batches
+            .find(user_id, batch_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to load AI batch: {:?}", e);
+                AppError::internal("Failed to load batch")
+            })?
+            .ok_or_else(|| AppError::not_found("Batch not found"))?;
+        let tasks = self.ai_batches.list_tasks(batch.id).await.map_err(|e| {
+            error!("Failed to load AI batch tasks: {:?}", e);
+            AppError::internal("Failed to load batch")
+        })?;
+
+        Ok(Response::new(batch_to_proto(batch, tasks)))
+    }
+}
@@ -1,9 +1,18 @@
+use crate::adapter::email_templates::{EmailLocale, EmailTemplateName};
 use crate::adapter::google_oauth::GoogleOAuthClient;
-use crate::model::auth::{JwtManager, SessionInfo, SessionManager};
+use crate::adapter::ses::TemplateData;
+use crate::error::AppError;
+use crate::events::{EventBus, UserCreated};
+use crate::handler::etag;
+use crate::handler::field_mask::{clear_unmasked, ReadMask};
+use crate::handler::interceptor::require_scope;
+use crate::model::auth::{ClientType, JwtManager, Scope, SessionInfo, SessionManager, TokenPair};
+use crate::model::email_queue::EmailQueueRepository;
 use crate::model::otp::{OtpRepository, SendOtpRequest as ModelSendOtpRequest, VerifyOtpRequest as ModelVerifyOtpRequest};
 use crate::model::user::{CreateUserRequest, User, UserRepository};
 use crate::gen::auth::{
     auth_service_server::AuthService, CompleteOAuthRequest, CompleteOAuthResponse,
+    CreateAccessTokenRequest, CreateAccessTokenResponse,
     GetProfileRequest, GetProfileResponse, GetUserSessionsRequest, GetUserSessionsResponse,
     InitiateOAuthRequest, InitiateOAuthResponse, LogoutAllRequest, LogoutAllResponse,
     LogoutRequest, LogoutResponse, RefreshTokenRequest, RefreshTokenResponse,
@@ -12,13 +21,19 @@ use crate::gen::auth::{
     ValidateTokenRequest, ValidateTokenResponse,
 };
 use anyhow::Result;
-use chrono::Utc;
+use chrono::{Duration, Utc};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tonic::{Request, Response, Status};
 use tracing::{debug, error, info, instrument, warn};
 use uuid::Uuid;
 
+/// Fields of `UserProfile` that may appear in a `GetProfile` read mask
+const PROFILE_FIELDS: &[&str] = &[
+    "id", "google_id", "email", "name", "given_name", "family_name", "picture_url", "locale",
+    "is_active", "is_verified", "created_at", "updated_at", "last_login_at", "preferences",
+];
+
 /// gRPC Authentication Service implementation
 pub struct AuthServiceImpl {
     oauth_client: GoogleOAuthClient,
@@ -26,6 +41,8 @@ pub struct AuthServiceImpl {
     session_manager: SessionManager,
     user_repository: UserRepository,
     otp_repository: OtpRepository,
+    email_queue: EmailQueueRepository,
+    events: EventBus,
     state_storage: Arc<tokio::sync::RwLock<HashMap<String, String>>>, // In production, use Redis
 }
 
@@ -36,6 +53,8 @@ impl AuthServiceImpl {
         session_manager: SessionManager,
         user_repository: UserRepository,
         otp_repository: OtpRepository,
+        email_queue: EmailQueueRepository,
+        events: EventBus,
     ) -> Self {
         Self {
             oauth_client,
@@ -43,28 +62,111 @@ impl AuthServiceImpl {
             session_manager,
             user_repository,
             otp_repository,
+            email_queue,
+            events,
             state_storage: Arc::new(tokio::sync::RwLock::new(HashMap::new())),
         }
     }
 
     fn user_to_proto(&self, user: &User) -> UserProfile {
-        UserProfile {
-            id: user.id.to_string(),
+        user_profile(user)
+    }
+
+    /// Issue a token pair for `user` and store the session its refresh token is exchanged against
+    async fn start_session(
+        &self,
+        user: &User,
+        client_type: ClientType,
+        scopes: &[Scope],
+    ) -> Result<TokenPair, AppError> {
+        let token_pair = self
+            .jwt_manager
+            .generate_scoped_token_pair(user.id, &user.email, &user.google_id, client_type, scopes)
+            .map_err(|e| {
+                error!("Failed to generate JWT tokens: {}", e);
+                AppError::internal("Failed to generate authentication tokens")
+            })?;
+
+        // Create session keyed by the refresh token JTI
+        let session_info = SessionInfo {
+            user_id: user.id,
             google_id: user.google_id.clone(),
             email: user.email.clone(),
+            refresh_token_jti: token_pair.refresh_token_jti.clone(),
+            created_at: Utc::now(),
+            last_activity: Utc::now(),
+            last_refresh_at: None,
+        };
+
+        self.session_manager
+            .store_session(&session_info)
+            .await
+            .map_err(|e| {
+                error!("Failed to create session: {}", e);
+                AppError::internal("Failed to create session")
+            })?;
+
+        Ok(token_pair)
+    }
+
+    fn publish_user_created(&self, user: &User) {
+        self.events.publish(UserCreated {
+            user_id: user.id,
+            email: user.email.clone(),
             name: user.name.clone(),
-            given_name: Some("".to_string()), // Not stored in simplified schema
-            family_name: Some("".to_string()), // Not stored in simplified schema
-            picture_url: user.picture_url.clone(),
-            locale: Some("".to_string()), // Not stored in simplified schema
-            is_active: true, // Default value since not stored
-            is_verified: true, // Google OAuth users are verified
-            created_at: user.created_at.timestamp(),
-            updated_at: user.updated_at.timestamp(),
-            last_login_at: None, // Not stored in simplified schema
-            preferences: "{}".to_string(), // Empty JSON since not stored
+            locale: user.locale.clone(),
+        });
+    }
+}
+
+/// Convert a stored user into its profile message
+pub(crate) fn user_profile(user: &User) -> UserProfile {
+    UserProfile {
+        id: user.id.to_string(),
+        google_id: user.google_id.clone(),
+        email: user.email.clone(),
+        name: user.name.clone(),
+        given_name: Some("".to_string()), // Not stored in simplified schema
+        family_name: Some("".to_string()), // Not stored in simplified schema
+        picture_url: user.picture_url.clone(),
+        locale: Some(user.locale.clone()),
+        is_active: true, // Default value since not stored
+        is_verified: true, // Google OAuth users are verified
+        created_at: user.created_at.timestamp(),
+        updated_at: user.updated_at.timestamp(),
+        last_login_at: None, // Not stored in simplified schema
+        preferences: "{}".to_string(), // Empty JSON since not stored
+    }
+}
+
+/// Client a sign-in issues tokens to: the web dashboard unless the mobile app says otherwise
+fn sign_in_client_type(value: Option<&str>) -> Result<ClientType, AppError> {
+    match value.map(ClientType::parse) {
+        None => Ok(ClientType::Web),
+        Some(Some(client_type)) if client_type.is_first_party() => Ok(client_type),
+        Some(_) => Err(AppError::validation("client_type must be web or mobile")),
+    }
+}
+
+/// Scopes to grant a delegated client: the requested ones, all among its defaults, or every default
+fn delegated_scopes(client_type: ClientType, requested: &[String]) -> Result<Vec<Scope>, AppError> {
+    let allowed = client_type.default_scopes();
+    if requested.is_empty() {
+        return Ok(allowed);
+    }
+
+    let mut scopes = Vec::new();
+    for name in requested {
+        let scope = Scope::parse(name)
+            .filter(|scope| allowed.contains(scope))
+            .ok_or_else(|| {
+                AppError::validation(format!("Scope '{}' can't be granted to a {} token", name, client_type.as_str()))
+            })?;
+        if !scopes.contains(&scope) {
+            scopes.push(scope);
         }
     }
+    Ok(scopes)
 }
 
 #[tonic::async_trait]
@@ -102,13 +204,14 @@ impl AuthService for AuthServiceImpl {
     ) -> Result<Response<CompleteOAuthResponse>, Status> {
         let req = request.into_inner();
         debug!("Completing Google OAuth flow");
+        let client_type = sign_in_client_type(req.client_type.as_deref())?;
 
         // Validate state parameter
         {
             let state_storage = self.state_storage.read().await;
             if !state_storage.contains_key(&req.state) {
                 error!("Invalid state parameter: {}", req.state);
-                return Err(Status::invalid_argument("Invalid state parameter"));
+                return Err(AppError::validation("Invalid state parameter").into());
             }
         }
 
@@ -119,7 +222,7 @@ impl AuthService for AuthServiceImpl {
             .await
             .map_err(|e| {
                 error!("Failed to exchange code for tokens: {}", e);
-                Status::internal("Failed to exchange authorization code")
+                AppError::upstream("google", "Failed to exchange authorization code")
             })?;
 
         // Get user info from Google
@@ -129,7 +232,7 @@ impl AuthService for AuthServiceImpl {
             .await
             .map_err(|e| {
                 error!("Failed to get user info from Google: {}", e);
-                Status::internal("Failed to retrieve user information")
+                AppError::upstream("google", "Failed to retrieve user information")
             })?;
 
         // Create user request
@@ -138,6 +241,7 @@ impl AuthService for AuthServiceImpl {
             email: google_user.email,
             name: google_user.name,
             picture_url: google_user.picture,
+            locale: google_user.locale,
         };
 
         // Create or update user
@@ -147,36 +251,15 @@ impl AuthService for AuthServiceImpl {
             .await
             .map_err(|e| {
                 error!("Failed to create or update user: {}", e);
-                Status::internal("Failed to process user account")
+                AppError::internal("Failed to process user account")
             })?;
+        if is_new_user {
+            self.publish_user_created(&user);
+        }
 
-        // Generate JWT tokens
         let jwt_token_pair = self
-            .jwt_manager
-            .generate_token_pair(user.id, &user.email, &user.google_id)
-            .map_err(|e| {
-                error!("Failed to generate JWT tokens: {}", e);
-                Status::internal("Failed to generate authentication tokens")
-            })?;
-
-        // Create session - generate a JTI for the refresh token
-        let refresh_jti = Uuid::new_v4().to_string();
-        let session_info = SessionInfo {
-            user_id: user.id,
-            google_id: user.google_id.clone(),
-            email: user.email.clone(),
-            refresh_token_jti: refresh_jti,
-            created_at: Utc::now(),
-            last_activity: Utc::now(),
-        };
-
-        self.session_manager
-            .store_session(&session_info)
-            .await
-            .map_err(|e| {
-                error!("Failed to create session: {}", e);
-                Status::internal("Failed to create session")
-            })?;
+            .start_session(&user, client_type, &client_type.default_scopes())
+            .await?;
 
         // Clean up state
         {
@@ -189,7 +272,7 @@ impl AuthService for AuthServiceImpl {
             access_token: jwt_token_pair.access_token,
             refresh_token: jwt_token_pair.refresh_token,
             access_token_expires_at: now + jwt_token_pair.expires_in,
-            refresh_token_expires_at: now + (30 * 24 * 60 * 60), // 30 days for refresh token
+            refresh_token_expires_at: jwt_token_pair.refresh_token_expires_at,
             token_type: jwt_token_pair.token_type,
             user: Some(self.user_to_proto(&user)),
             is_new_user,
@@ -218,12 +301,39 @@ impl AuthService for AuthServiceImpl {
             .validate_token(&req.refresh_token)
             .map_err(|e| {
                 warn!("Invalid refresh token: {}", e);
-                Status::unauthenticated("Invalid refresh token")
+                AppError::unauthorized("Invalid refresh token")
             })?;
 
+        if claims.token_type != "refresh" {
+            return Err(AppError::unauthorized("Invalid refresh token").into());
+        }
+
+        // Enforce the inactivity window on top of the absolute expiry
+        let session = self
+            .session_manager
+            .get_session(&claims.jti)
+            .await
+            .map_err(|e| {
+                error!("Failed to load session for token refresh: {}", e);
+                AppError::internal("Failed to generate new access token")
+            })?
+            .ok_or_else(|| AppError::unauthorized("Session expired or revoked"))?;
+
+        if session.is_inactive(Utc::now(), self.jwt_manager.refresh_inactivity_window()) {
+            warn!(
+                user_id = %claims.sub,
+                last_refresh_at = ?session.last_refresh_at,
+                "Refresh token revoked due to inactivity"
+            );
+            if let Err(e) = self.session_manager.invalidate_session(&claims.jti).await {
+                error!("Failed to invalidate inactive session: {}", e);
+            }
+            return Err(AppError::unauthorized("Refresh token expired due to inactivity").into());
+        }
+
         // Parse user ID
         let user_id = Uuid::parse_str(&claims.sub)
-            .map_err(|_| Status::invalid_argument("Invalid user ID in token"))?;
+            .map_err(|_| AppError::validation("Invalid user ID in token"))?;
 
         // We need user info to generate tokens
         let user = self
@@ -232,17 +342,31 @@ impl AuthService for AuthServiceImpl {
             .await
             .map_err(|e| {
                 error!("Failed to find user for token refresh: {}", e);
-                Status::internal("Failed to generate new access token")
+                AppError::internal("Failed to generate new access token")
             })?
-            .ok_or_else(|| Status::not_found("User not found"))?;
+            .ok_or_else(|| AppError::not_found("User not found"))?;
 
-        // Generate new access token
+        // Re-issue with the refreshed token's client type and scopes, never widening them
         let token_pair = self
             .jwt_manager
-            .generate_token_pair(user.id, &user.email, &user.google_id)
+            .generate_scoped_token_pair(
+                user.id,
+                &user.email,
+                &user.google_id,
+                claims.client_type,
+                &claims.scopes(),
+            )
             .map_err(|e| {
                 error!("Failed to generate new tokens: {}", e);
-                Status::internal("Failed to generate new access token")
+                AppError::internal("Failed to generate new access token")
+            })?;
+
+        self.session_manager
+            .record_refresh(&claims.jti)
+            .await
+            .map_err(|e| {
+                error!("Failed to record session refresh: {}", e);
+                AppError::internal("Failed to generate new access token")
             })?;
 
         let now = Utc::now().timestamp();
@@ -270,7 +394,7 @@ impl AuthService for AuthServiceImpl {
             .validate_token(&req.access_token)
             .map_err(|e| {
                 warn!("Invalid access token during logout: {}", e);
-                Status::unauthenticated("Invalid access token")
+                AppError::unauthorized("Invalid access token")
             })?;
 
         // Invalidate session
@@ -279,7 +403,7 @@ impl AuthService for AuthServiceImpl {
             .await
             .map_err(|e| {
                 error!("Failed to invalidate session: {}", e);
-                Status::internal("Failed to logout")
+                AppError::internal("Failed to logout")
             })?;
 
         let response = LogoutResponse {
@@ -305,12 +429,12 @@ impl AuthService for AuthServiceImpl {
             .validate_token(&req.access_token)
             .map_err(|e| {
                 warn!("Invalid access token during logout all: {}", e);
-                Status::unauthenticated("Invalid access token")
+                AppError::unauthorized("Invalid access token")
             })?;
 
         // Parse user ID
         let user_id = Uuid::parse_str(&claims.sub)
-            .map_err(|_| Status::invalid_argument("Invalid user ID in token"))?;
+            .map_err(|_| AppError::validation("Invalid user ID in token"))?;
 
         // Invalidate all sessions for user
         let revoked_count = self
@@ -319,7 +443,7 @@ impl AuthService for AuthServiceImpl {
             .await
             .map_err(|e| {
                 error!("Failed to invalidate all sessions: {}", e);
-                Status::internal("Failed to logout from all devices")
+                AppError::internal("Failed to logout from all devices")
             })?;
 
         let response = LogoutAllResponse {
@@ -412,21 +536,24 @@ impl AuthService for AuthServiceImpl {
         &self,
         request: Request<GetProfileRequest>,
     ) -> Result<Response<GetProfileResponse>, Status> {
-        let req = request.into_inner();
+        let mut req = request.get_ref().clone();
+        let if_none_match = etag::if_none_match(&request, req.if_none_match.take());
         debug!("Getting user profile");
 
         // Validate access token
         let claims = self
             .jwt_manager
-            .validate_token(&req.access_token)
+            .validate_access_token(&req.access_token)
             .map_err(|e| {
                 warn!("Invalid access token during get profile: {}", e);
-                Status::unauthenticated("Invalid access token")
+                AppError::unauthorized("Invalid access token")
             })?;
+        require_scope(&claims, Scope::ProfileRead)?;
+        let read_mask = ReadMask::from_proto(req.read_mask, PROFILE_FIELDS)?;
 
         // Parse user ID
         let user_id = Uuid::parse_str(&claims.sub)
-            .map_err(|_| Status::invalid_argument("Invalid user ID in token"))?;
+            .map_err(|_| AppError::validation("Invalid user ID in token"))?;
 
         // Get user
         let user = self
@@ -435,12 +562,31 @@ impl AuthService for AuthServiceImpl {
             .await
             .map_err(|e| {
                 error!("Failed to find user: {}", e);
-                Status::internal("Failed to retrieve user profile")
+                AppError::internal("Failed to retrieve user profile")
             })?
-            .ok_or_else(|| Status::not_found("User not found"))?;
+            .ok_or_else(|| AppError::not_found("User not found"))?;
+
+        let mut profile = self.user_to_proto(&user);
+        clear_unmasked!(
+            read_mask, profile,
+            id, google_id, email, name, given_name, family_name, picture_url, locale,
+            is_active, is_verified, created_at, updated_at, last_login_at, preferences,
+        );
+
+        let etag = etag::entity_tag(&profile);
+        if etag::matches(if_none_match.as_deref(), &etag) {
+            debug!(user_id = %user.id, "User profile not modified");
+            return Ok(Response::new(GetProfileResponse {
+                user: None,
+                etag,
+                not_modified: true,
+            }));
+        }
 
         let response = GetProfileResponse {
-            user: Some(self.user_to_proto(&user)),
+            user: Some(profile),
+            etag,
+            not_modified: false,
         };
 
         info!(user_id = %user.id, "User profile retrieved successfully");
@@ -458,11 +604,12 @@ impl AuthService for AuthServiceImpl {
         // Validate access token
         let claims = self
             .jwt_manager
-            .validate_token(&req.access_token)
+            .validate_access_token(&req.access_token)
             .map_err(|e| {
                 warn!("Invalid access token during get sessions: {}", e);
-                Status::unauthenticated("Invalid access token")
+                AppError::unauthorized("Invalid access token")
             })?;
+        require_scope(&claims, Scope::SessionsRead)?;
 
         // For now, return empty sessions list
         // TODO: Implement proper session retrieval from SessionManager
@@ -487,15 +634,16 @@ impl AuthService for AuthServiceImpl {
         // Validate access token
         let claims = self
             .jwt_manager
-            .validate_token(&req.access_token)
+            .validate_access_token(&req.access_token)
             .map_err(|e| {
                 warn!("Invalid access token during revoke session: {}", e);
-                Status::unauthenticated("Invalid access token")
+                AppError::unauthorized("Invalid access token")
             })?;
+        require_scope(&claims, Scope::SessionsWrite)?;
 
         // Parse session ID
         let session_id = Uuid::parse_str(&req.session_id)
-            .map_err(|_| Status::invalid_argument("Invalid session ID format"))?;
+            .map_err(|_| AppError::validation("Invalid session ID format"))?;
 
         // For now, always return success
         // TODO: Implement proper session revocation in SessionManager
@@ -529,7 +677,7 @@ impl AuthService for AuthServiceImpl {
 
         // Validate email format
         if !req.email.contains('@') || req.email.is_empty() {
-            return Err(Status::invalid_argument("Invalid email address"));
+            return Err(AppError::validation("Invalid email address").into());
         }
 
         // Create model request
@@ -538,28 +686,48 @@ impl AuthService for AuthServiceImpl {
         };
 
         // Send OTP
-        let otp_code = self
+        let otp_id = self
             .otp_repository
             .send_otp(model_request)
             .await
             .map_err(|e| {
                 error!("Failed to send OTP: {}", e);
                 if e.to_string().contains("Rate limit exceeded") {
-                    Status::resource_exhausted("Too many OTP requests. Please try again later.")
+                    AppError::rate_limited(
+                        "Too many OTP requests. Please try again later.",
+                        Some(std::time::Duration::from_secs(3600)),
+                    )
                 } else {
-                    Status::internal("Failed to send OTP")
+                    AppError::internal("Failed to send OTP")
                 }
             })?;
 
-        // TODO: Send email with OTP code
-        // For now, we'll log it (remove in production)
-        info!(
-            email = %req.email,
-            otp_code = %otp_code,
-            "OTP generated (TODO: send via email service)"
-        );
+        // Queued rather than sent here, so an SES hiccup doesn't fail the request. Only the code's id
+        // is queued; the worker reads the code back when it sends, and drops the email once the code
+        // has expired.
+        let expires_minutes = self.otp_repository.expires_minutes();
+        let expires_at = Utc::now() + Duration::minutes(expires_minutes);
+        let mut template_data = TemplateData::new();
+        template_data.insert("otp_id", otp_id.to_string());
+        template_data.insert("user_name", "User");
+        template_data.insert("expires_minutes", expires_minutes.to_string());
+        // Existing users get the code in their language; a failed lookup only costs the translation
+        let locale = match self.user_repository.find_by_email(&req.email).await {
+            Ok(user) => user.map(|user| EmailLocale::parse(&user.locale)).unwrap_or_default(),
+            Err(e) => {
+                warn!("Failed to look up user locale: {}", e);
+                EmailLocale::default()
+            }
+        };
+        self.email_queue
+            .enqueue(&req.email, EmailTemplateName::OtpLogin, locale, &template_data, Some(expires_at))
+            .await
+            .map_err(|e| {
+                error!("Failed to queue OTP email: {}", e);
+                AppError::internal("Failed to send OTP")
+            })?;
 
-        let expires_at = Utc::now().timestamp() + (10 * 60); // 10 minutes from now
+        let expires_at = expires_at.timestamp();
         let response = SendOtpResponse {
             success: true,
             message: "OTP sent to your email address".to_string(),
@@ -581,8 +749,9 @@ impl AuthService for AuthServiceImpl {
 
         // Validate inputs
         if req.email.is_empty() || req.code.is_empty() {
-            return Err(Status::invalid_argument("Email and code are required"));
+            return Err(AppError::validation("Email and code are required").into());
         }
+        let client_type = sign_in_client_type(req.client_type.as_deref())?;
 
         // Create model request
         let model_request = ModelVerifyOtpRequest {
@@ -597,7 +766,7 @@ impl AuthService for AuthServiceImpl {
             .await
             .map_err(|e| {
                 error!("Failed to verify OTP: {}", e);
-                Status::internal("Failed to verify OTP")
+                AppError::internal("Failed to verify OTP")
             })?;
 
         if !verification_result.success {
@@ -628,9 +797,9 @@ impl AuthService for AuthServiceImpl {
                 .await
                 .map_err(|e| {
                     error!("Failed to find user: {}", e);
-                    Status::internal("Failed to retrieve user")
+                    AppError::internal("Failed to retrieve user")
                 })?
-                .ok_or_else(|| Status::not_found("User not found"))?
+                .ok_or_else(|| AppError::not_found("User not found"))?
         } else {
             // New user - create from email
             let create_request = CreateUserRequest {
@@ -638,44 +807,24 @@ impl AuthService for AuthServiceImpl {
                 email: req.email.clone(),
                 name: req.email.split('@').next().unwrap_or("User").to_string(), // Default name from email
                 picture_url: None,
+                locale: None,
             };
 
-            self.user_repository
+            let user = self
+                .user_repository
                 .create_user(create_request)
                 .await
                 .map_err(|e| {
                     error!("Failed to create user: {}", e);
-                    Status::internal("Failed to create user account")
-                })?
+                    AppError::internal("Failed to create user account")
+                })?;
+            self.publish_user_created(&user);
+            user
         };
 
-        // Generate JWT tokens
         let jwt_token_pair = self
-            .jwt_manager
-            .generate_token_pair(user.id, &user.email, &user.google_id)
-            .map_err(|e| {
-                error!("Failed to generate JWT tokens: {}", e);
-                Status::internal("Failed to generate authentication tokens")
-            })?;
-
-        // Create session
-        let refresh_jti = Uuid::new_v4().to_string();
-        let session_info = SessionInfo {
-            user_id: user.id,
-            google_id: user.google_id.clone(),
-            email: user.email.clone(),
-            refresh_token_jti: refresh_jti,
-            created_at: Utc::now(),
-            last_activity: Utc::now(),
-        };
-
-        self.session_manager
-            .store_session(&session_info)
-            .await
-            .map_err(|e| {
-                error!("Failed to create session: {}", e);
-                Status::internal("Failed to create session")
-            })?;
+            .start_session(&user, client_type, &client_type.default_scopes())
+            .await?;
 
         let now = Utc::now().timestamp();
         let response = VerifyOtpResponse {
@@ -684,7 +833,7 @@ impl AuthService for AuthServiceImpl {
             access_token: Some(jwt_token_pair.access_token),
             refresh_token: Some(jwt_token_pair.refresh_token),
             access_token_expires_at: Some(now + jwt_token_pair.expires_in),
-            refresh_token_expires_at: Some(now + (30 * 24 * 60 * 60)), // 30 days
+            refresh_token_expires_at: Some(jwt_token_pair.refresh_token_expires_at),
             token_type: Some(jwt_token_pair.token_type),
             user: Some(self.user_to_proto(&user)),
             is_new_user: verification_result.is_new_user,
@@ -700,4 +849,91 @@ impl AuthService for AuthServiceImpl {
 
         Ok(Response::new(response))
     }
-}
\ No newline at end of file
+    #[instrument(skip(self, request), fields(client_type = %request.get_ref().client_type))]
+    async fn create_access_token(
+        &self,
+        request: Request<CreateAccessTokenRequest>,
+    ) -> Result<Response<CreateAccessTokenResponse>, Status> {
+        let req = request.into_inner();
+        debug!("Creating delegated access token");
+
+        let claims = self
+            .jwt_manager
+            .validate_access_token(&req.access_token)
+            .map_err(|e| {
+                warn!("Invalid access token during create access token: {}", e);
+                AppError::unauthorized("Invalid access token")
+            })?;
+        require_scope(&claims, Scope::SessionsWrite)?;
+        // Delegated tokens can't mint further tokens
+        if !claims.client_type.is_first_party() {
+            return Err(AppError::permission_denied("Tokens can only be created from a signed-in session").into());
+        }
+
+        let client_type = ClientType::parse(&req.client_type)
+            .filter(|client_type| !client_type.is_first_party())
+            .ok_or_else(|| AppError::validation("client_type must be personal_token or third_party_app"))?;
+        let scopes = delegated_scopes(client_type, &req.scopes)?;
+
+        let user_id = Uuid::parse_str(&claims.sub)
+            .map_err(|_| AppError::validation("Invalid user ID in token"))?;
+        let user = self
+            .user_repository
+            .find_by_id(user_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to find user: {}", e);
+                AppError::internal("Failed to create access token")
+            })?
+            .ok_or_else(|| AppError::not_found("User not found"))?;
+
+        let token_pair = self.start_session(&user, client_type, &scopes).await?;
+
+        let now = Utc::now().timestamp();
+        let response = CreateAccessTokenResponse {
+            access_token: token_pair.access_token,
+            refresh_token: token_pair.refresh_token,
+            access_token_expires_at: now + token_pair.expires_in,
+            refresh_token_expires_at: token_pair.refresh_token_expires_at,
+            token_type: token_pair.token_type,
+            scopes: scopes.iter().map(|scope| scope.as_str().to_string()).collect(),
+        };
+
+        info!(
+            user_id = %user.id,
+            client_type = %client_type.as_str(),
+            scopes = %Scope::join(&scopes),
+            "Delegated access token created"
+        );
+        Ok(Response::new(response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_in_client_type() {
+        assert_eq!(sign_in_client_type(None).unwrap(), ClientType::Web);
+        assert_eq!(sign_in_client_type(Some("mobile")).unwrap(), ClientType::Mobile);
+        assert!(sign_in_client_type(Some("personal_token")).is_err());
+        assert!(sign_in_client_type(Some("desktop")).is_err());
+    }
+
+    #[test]
+    fn test_delegated_scopes() {
+        assert_eq!(
+            delegated_scopes(ClientType::ThirdPartyApp, &[]).unwrap(),
+            vec![Scope::AccountsRead, Scope::TransactionsRead]
+        );
+        let requested = vec!["transactions:read".to_string(), "transactions:read".to_string()];
+        assert_eq!(
+            delegated_scopes(ClientType::PersonalToken, &requested).unwrap(),
+            vec![Scope::TransactionsRead]
+        );
+        // Never more than the client type's defaults
+        assert!(delegated_scopes(ClientType::PersonalToken, &["budgets:write".to_string()]).is_err());
+        assert!(delegated_scopes(ClientType::ThirdPartyApp, &["admin".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,322 @@
+use crate::adapter::llm::{LlmMessage, LlmProviders, LlmRequest};
+use crate::error::AppError;
+use crate::gen::assistant::MessageRole;
+use crate::gen::chat::{
+    chat_service_server::ChatService, ChatMessage as ProtoChatMessage, Conversation as ProtoConversation,
+    CreateConversationRequest, ListMessagesRequest, ListMessagesResponse, SendChatMessageRequest,
+    SendChatMessageResponse,
+};
+use crate::handler::assistant::{
+    admit_ai_request, check_ai_quota, max_tokens, moderate_input, record_ai_usage, resolve_provider,
+};
+use crate::handler::interceptor::AuthContext;
+use crate::handler::pagination::{decode_cursor, encode_cursor, page_size, split_page};
+use crate::model::ai_rate_limit::AiRateLimiter;
+use crate::model::ai_usage::{AiFeature, AiUsageRepository, NewAiUsage};
+use crate::model::auth::Scope;
+use crate::model::chat::{
+    estimate_tokens, truncate_history, ChatConversation, ChatMessage, ChatReply, ChatRepository, ChatRole,
+};
+use crate::moderation::ContentModerator;
+use crate::prompts::{PromptKey, PromptRegistry};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tonic::{Request, Response, Status};
+use tracing::{debug, error, info, instrument};
+use uuid::Uuid;
+
+const DEFAULT_TITLE: &str = "New conversation";
+const MAX_TITLE_CHARS: usize = 200;
+const MAX_SYSTEM_PROMPT_CHARS: usize = 10_000;
+const MAX_MESSAGE_CHARS: usize = 20_000;
+/// Estimated tokens of prompt, history and reply sent per request, kept well below the model's window
+const CONTEXT_WINDOW_TOKENS: usize = 100_000;
+/// Most recent messages loaded when building the history of a request
+const MAX_HISTORY_MESSAGES: i64 = 200;
+const DEFAULT_MESSAGE_PAGE_SIZE: i32 = 50;
+const MAX_MESSAGE_PAGE_SIZE: i32 = 200;
+
+/// Keyset position of a ListMessages page, handed to clients as an opaque token
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct MessagePageToken {
+    before_position: i32,
+}
+
+/// gRPC service for saved conversations with the assistant
+pub struct ChatHandler {
+    llm_providers: LlmProviders,
+    chat_repository: ChatRepository,
+    ai_usage: AiUsageRepository,
+    moderator: Arc<ContentModerator>,
+    prompts: Arc<PromptRegistry>,
+    rate_limiter: Arc<AiRateLimiter>,
+}
+
+impl ChatHandler {
+    /// Sending fails as unavailable when no provider is configured
+    pub fn new(
+        llm_providers: LlmProviders,
+        chat_repository: ChatRepository,
+        ai_usage: AiUsageRepository,
+        moderator: Arc<ContentModerator>,
+        prompts: Arc<PromptRegistry>,
+        rate_limiter: Arc<AiRateLimiter>,
+    ) -> Self {
+        Self {
+            llm_providers,
+            chat_repository,
+            ai_usage,
+            moderator,
+            prompts,
+            rate_limiter,
+        }
+    }
+
+    fn conversation_to_proto(conversation: &ChatConversation) -> ProtoConversation {
+        ProtoConversation {
+            conversation_id: conversation.id.to_string(),
+            title: conversation.title.clone(),
+            system_prompt: conversation.system_prompt.clone().unwrap_or_default(),
+            created_at: conversation.created_at.timestamp(),
+            updated_at: conversation.updated_at.timestamp(),
+        }
+    }
+
+    fn message_to_proto(message: &ChatMessage) -> ProtoChatMessage {
+        let role = match message.role() {
+            Some(ChatRole::User) => MessageRole::User,
+            Some(ChatRole::Assistant) => MessageRole::Assistant,
+            None => MessageRole::Unspecified,
+        };
+        ProtoChatMessage {
+            message_id: message.id.to_string(),
+            role: role as i32,
+            content: message.content.clone(),
+            created_at: message.created_at.timestamp(),
+            input_tokens: message.input_tokens,
+            output_tokens: message.output_tokens,
+        }
+    }
+
+    /// Conversation owned by the user, or not found
+    async fn owned_conversation(&self, user_id: Uuid, conversation_id: &str) -> Result<ChatConversation, AppError> {
+        let id = Uuid::parse_str(conversation_id)
+            .map_err(|_| AppError::validation("conversation_id must be a UUID"))?;
+        self.chat_repository
+            .find_conversation(user_id, id)
+            .await
+            .map_err(|e| {
+                error!("Failed to load chat conversation: {:?}", e);
+                AppError::internal("Failed to load conversation")
+            })?
+            .ok_or_else(|| AppError::not_found("Conversation not found"))
+    }
+}
+
+#[tonic::async_trait]
+impl ChatService for ChatHandler {
+    #[instrument(skip(self, request))]
+    async fn create_conversation(
+        &self,
+        request: Request<CreateConversationRequest>,
+    ) -> Result<Response<ProtoConversation>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::TransactionsRead)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!(user_id = %user_id, "Creating chat conversation");
+
+        let title = match req.title.trim() {
+            "" => DEFAULT_TITLE,
+            title => title,
+        };
+        if title.chars().count() > MAX_TITLE_CHARS {
+            return Err(AppError::validation(format!("title can have at most {} characters", MAX_TITLE_CHARS)).into());
+        }
+        let system_prompt = req.system_prompt.as_deref().map(str::trim).filter(|p| !p.is_empty());
+        if system_prompt.is_some_and(|p| p.chars().count() > MAX_SYSTEM_PROMPT_CHARS) {
+            return Err(AppError::validation(format!(
+                "system_prompt can have at most {} characters",
+                MAX_SYSTEM_PROMPT_CHARS
+            ))
+            .into());
+        }
+
+        let conversation = self
+            .chat_repository
+            .create_conversation(user_id, title, system_prompt)
+            .await
+            .map_err(|e| {
+                error!("Failed to create chat conversation: {:?}", e);
+                AppError::internal("Failed to create conversation")
+            })?;
+
+        info!(user_id = %user_id, conversation_id = %conversation.id, "Chat conversation created");
+        Ok(Response::new(Self::conversation_to_proto(&conversation)))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn send_chat_message(
+        &self,
+        request: Request<SendChatMessageRequest>,
+    ) -> Result<Response<SendChatMessageResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::TransactionsRead)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!(user_id = %user_id, conversation_id = %req.conversation_id, "Sending chat message");
+
+        if req.content.trim().is_empty() {
+            return Err(AppError::validation("content must not be empty").into());
+        }
+        if req.content.chars().count() > MAX_MESSAGE_CHARS {
+            return Err(AppError::validation(format!("content can have at most {} characters", MAX_MESSAGE_CHARS)).into());
+        }
+        let max_tokens = max_tokens(req.max_tokens)?;
+        let provider = resolve_provider(&self.llm_providers, req.provider.as_deref())?;
+        let conversation = self.owned_conversation(user_id, &req.conversation_id).await?;
+        check_ai_quota(&self.ai_usage, user_id, AiFeature::Chat).await?;
+        moderate_input(&self.moderator, user_id, AiFeature::Chat, &req.content).await?;
+        let _permit = admit_ai_request(&self.rate_limiter, user_id, AiFeature::Chat).await?;
+
+        let mut recent = self
+            .chat_repository
+            .list_messages(conversation.id, None, MAX_HISTORY_MESSAGES)
+            .await
+            .map_err(|e| {
+                error!("Failed to load chat history: {:?}", e);
+                AppError::internal("Failed to send message")
+            })?;
+        recent.reverse();
+        let mut history: Vec<(ChatRole, &str)> = recent
+            .iter()
+            .filter_map(|m| m.role().map(|role| (role, m.content.as_str())))
+            .collect();
+        history.push((ChatRole::User, req.content.as_str()));
+
+        // The reply and the system prompt share the window with the history
+        // Custom prompts get the guardrails too
+        let system_prompt = match conversation.system_prompt.as_deref() {
+            Some(custom) => self.prompts.with_guardrails(custom),
+            None => self.prompts.system_prompt(PromptKey::Assistant),
+        };
+        let budget = CONTEXT_WINDOW_TOKENS.saturating_sub(max_tokens as usize + estimate_tokens(&system_prompt));
+        let kept = truncate_history(&history, budget);
+        if kept.is_empty() {
+            return Err(AppError::validation("content is too long for the conversation's context window").into());
+        }
+        if kept.len() < history.len() {
+            debug!(dropped = history.len() - kept.len(), "Truncated chat history to fit the context window");
+        }
+        let messages = kept
+            .iter()
+            .map(|(role, content)| match role {
+                ChatRole::User => LlmMessage::user(content),
+                ChatRole::Assistant => LlmMessage::assistant(content),
+            })
+            .collect();
+
+        let response = provider
+            .send(LlmRequest {
+                system: Some(system_prompt),
+                cache_system: true,
+                messages,
+                max_tokens,
+                temperature: Some(0.7),
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| {
+                error!(provider = provider.name(), "Failed to get chat reply: {:?}", e);
+                AppError::upstream(provider.name(), "The assistant is unavailable")
+            })?;
+        record_ai_usage(
+            &self.ai_usage,
+            NewAiUsage {
+                user_id,
+                feature: AiFeature::Chat,
+                provider: provider.name(),
+                model: &response.model,
+                input_tokens: response.usage.input_tokens,
+                output_tokens: response.usage.output_tokens,
+                cache_read_tokens: response.usage.cache_read_tokens,
+                cache_write_tokens: response.usage.cache_write_tokens,
+            },
+        )
+        .await;
+        let reply = ChatReply {
+            content: response.text,
+            input_tokens: response.usage.input_tokens as i32,
+            output_tokens: response.usage.output_tokens as i32,
+        };
+
+        // Both turns are saved only once the reply arrived, so stored history always alternates
+        let (user_message, assistant_message) = self
+            .chat_repository
+            .append_exchange(conversation.id, &req.content, &reply)
+            .await
+            .map_err(|e| {
+                error!("Failed to save chat messages: {:?}", e);
+                AppError::internal("Failed to send message")
+            })?;
+
+        info!(
+            user_id = %user_id,
+            conversation_id = %conversation.id,
+            provider = provider.name(),
+            input_tokens = reply.input_tokens,
+            output_tokens = reply.output_tokens,
+            "Chat message answered"
+        );
+        Ok(Response::new(SendChatMessageResponse {
+            user_message: Some(Self::message_to_proto(&user_message)),
+            assistant_message: Some(Self::message_to_proto(&assistant_message)),
+        }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn list_messages(
+        &self,
+        request: Request<ListMessagesRequest>,
+    ) -> Result<Response<ListMessagesResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::TransactionsRead)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!(user_id = %user_id, conversation_id = %req.conversation_id, "Listing chat messages");
+
+        let page_size = page_size(req.page_size, DEFAULT_MESSAGE_PAGE_SIZE, MAX_MESSAGE_PAGE_SIZE);
+        let before: Option<MessagePageToken> = if req.page_token.is_empty() {
+            None
+        } else {
+            Some(decode_cursor(&req.page_token, "page token")?)
+        };
+        let conversation = self.owned_conversation(user_id, &req.conversation_id).await?;
+
+        let messages = self
+            .chat_repository
+            .list_messages(
+                conversation.id,
+                before.map(|t| t.before_position),
+                page_size as i64 + 1,
+            )
+            .await
+            .map_err(|e| {
+                error!("Failed to list chat messages: {:?}", e);
+                AppError::internal("Failed to list messages")
+            })?;
+        let (messages, has_more) = split_page(messages, page_size as usize);
+
+        let next_page_token = match messages.last() {
+            Some(last) if has_more => encode_cursor(&MessagePageToken {
+                before_position: last.position,
+            }),
+            _ => String::new(),
+        };
+
+        Ok(Response::new(ListMessagesResponse {
+            messages: messages.iter().map(Self::message_to_proto).collect(),
+            next_page_token,
+        }))
+    }
+}
@@ -0,0 +1,82 @@
+use sha2::{Digest, Sha256};
+use tonic::Request;
+
+/// Version hash of a response entity, computed over its protobuf encoding.
+///
+/// Masked responses hash only the fields they return, so clients asking for
+/// different read masks get independent tags.
+pub fn entity_tag<M: prost::Message>(message: &M) -> String {
+    let digest = Sha256::digest(message.encode_to_vec());
+    format!("\"{}\"", hex_prefix(&digest, 16))
+}
+
+fn hex_prefix(bytes: &[u8], len: usize) -> String {
+    bytes.iter().take(len).map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Conditional-read precondition from the request field, falling back to `if-none-match` metadata
+pub fn if_none_match<T>(request: &Request<T>, field: Option<String>) -> Option<String> {
+    field.filter(|v| !v.is_empty()).or_else(|| {
+        request
+            .metadata()
+            .get("if-none-match")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+    })
+}
+
+/// Whether an `If-None-Match` value matches the current tag (supports `*`, lists and weak tags)
+pub fn matches(if_none_match: Option<&str>, etag: &str) -> bool {
+    let Some(value) = if_none_match else {
+        return false;
+    };
+    let current = etag.trim_start_matches("W/").trim_matches('"');
+
+    value.split(',').map(str::trim).any(|candidate| {
+        candidate == "*" || candidate.trim_start_matches("W/").trim_matches('"') == current
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen::auth::UserProfile;
+
+    fn profile(name: &str) -> UserProfile {
+        UserProfile {
+            id: "user-1".to_string(),
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_entity_tag_is_stable_and_content_sensitive() {
+        assert_eq!(entity_tag(&profile("Ada")), entity_tag(&profile("Ada")));
+        assert_ne!(entity_tag(&profile("Ada")), entity_tag(&profile("Grace")));
+        assert!(entity_tag(&profile("Ada")).starts_with('"'));
+    }
+
+    #[test]
+    fn test_matches_variants() {
+        let etag = entity_tag(&profile("Ada"));
+        let bare = etag.trim_matches('"').to_string();
+
+        assert!(matches(Some(&etag), &etag));
+        assert!(matches(Some(&bare), &etag));
+        assert!(matches(Some(&format!("W/{}", etag)), &etag));
+        assert!(matches(Some(&format!("\"stale\", {}", etag)), &etag));
+        assert!(matches(Some("*"), &etag));
+        assert!(!matches(Some("\"stale\""), &etag));
+        assert!(!matches(None, &etag));
+    }
+
+    #[test]
+    fn test_if_none_match_prefers_request_field() {
+        let mut request = Request::new(());
+        request.metadata_mut().insert("if-none-match", "\"from-header\"".parse().unwrap());
+
+        assert_eq!(if_none_match(&request, Some("\"from-field\"".to_string())).as_deref(), Some("\"from-field\""));
+        assert_eq!(if_none_match(&request, None).as_deref(), Some("\"from-header\""));
+    }
+}
@@ -0,0 +1,147 @@
+use crate::error::AppError;
+use crate::gen::google::protobuf::FieldMask;
+use std::collections::HashSet;
+
+/// Validated `read_mask` of a request; an absent or empty mask selects every field
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReadMask {
+    paths: Option<HashSet<String>>,
+}
+
+impl ReadMask {
+    /// Mask selecting every field
+    pub fn all() -> Self {
+        Self { paths: None }
+    }
+
+    /// Parse a request mask, rejecting paths whose top-level field is not in `allowed`
+    pub fn from_proto(mask: Option<FieldMask>, allowed: &[&str]) -> Result<Self, AppError> {
+        let paths: Vec<String> = mask
+            .map(|m| m.paths)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty())
+            .collect();
+
+        if paths.is_empty() || paths.iter().any(|p| p == "*") {
+            return Ok(Self::all());
+        }
+
+        for path in &paths {
+            let root = path.split('.').next().unwrap_or_default();
+            if !allowed.contains(&root) {
+                return Err(AppError::validation(format!("Unknown field '{}' in read_mask", path)));
+            }
+        }
+
+        Ok(Self {
+            paths: Some(paths.into_iter().collect()),
+        })
+    }
+
+    pub fn is_all(&self) -> bool {
+        self.paths.is_none()
+    }
+
+    /// Whether `field` (or any of its sub-fields) was requested
+    pub fn includes(&self, field: &str) -> bool {
+        match &self.paths {
+            None => true,
+            Some(paths) => paths.iter().any(|p| {
+                p == field
+                    || p.strip_prefix(field).is_some_and(|rest| rest.starts_with('.'))
+                    || field.strip_prefix(p.as_str()).is_some_and(|rest| rest.starts_with('.'))
+            }),
+        }
+    }
+
+    /// Mask relative to a message-typed field, e.g. `location` for `location.city`
+    pub fn sub_mask(&self, field: &str) -> ReadMask {
+        let Some(paths) = &self.paths else {
+            return Self::all();
+        };
+        if paths.contains(field) {
+            return Self::all();
+        }
+
+        let prefix = format!("{}.", field);
+        let nested: HashSet<String> = paths
+            .iter()
+            .filter_map(|p| p.strip_prefix(&prefix).map(str::to_string))
+            .collect();
+        Self {
+            paths: if nested.is_empty() { None } else { Some(nested) },
+        }
+    }
+}
+
+/// Reset every listed field of `$message` that `$mask` does not include
+macro_rules! clear_unmasked {
+    ($mask:expr, $message:expr, $($field:ident),+ $(,)?) => {
+        $(
+            if !$mask.includes(stringify!($field)) {
+                $message.$field = Default::default();
+            }
+        )+
+    };
+}
+
+pub(crate) use clear_unmasked;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mask(paths: &[&str]) -> Option<FieldMask> {
+        Some(FieldMask {
+            paths: paths.iter().map(|p| p.to_string()).collect(),
+        })
+    }
+
+    #[test]
+    fn test_empty_mask_selects_everything() {
+        let read_mask = ReadMask::from_proto(None, &["name"]).unwrap();
+        assert!(read_mask.is_all());
+        assert!(read_mask.includes("anything"));
+
+        let read_mask = ReadMask::from_proto(mask(&["*"]), &["name"]).unwrap();
+        assert!(read_mask.is_all());
+    }
+
+    #[test]
+    fn test_includes_parents_and_children() {
+        let read_mask = ReadMask::from_proto(mask(&["name", "location.city"]), &["name", "location"]).unwrap();
+
+        assert!(read_mask.includes("name"));
+        assert!(read_mask.includes("location"));
+        assert!(!read_mask.includes("amount"));
+        assert!(!read_mask.includes("name_suffix"));
+
+        let location = read_mask.sub_mask("location");
+        assert!(location.includes("city"));
+        assert!(!location.includes("region"));
+    }
+
+    #[test]
+    fn test_rejects_unknown_fields() {
+        let err = ReadMask::from_proto(mask(&["secret"]), &["name"]).unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[test]
+    fn test_clear_unmasked_resets_fields() {
+        #[derive(Default)]
+        struct Message {
+            name: String,
+            amount: f64,
+        }
+
+        let read_mask = ReadMask::from_proto(mask(&["name"]), &["name", "amount"]).unwrap();
+        let mut message = Message { name: "Coffee".to_string(), amount: 4.5 };
+        clear_unmasked!(read_mask, message, name, amount);
+
+        assert_eq!(message.name, "Coffee");
+        assert_eq!(message.amount, 0.0);
+    }
+}
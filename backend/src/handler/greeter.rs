@@ -3,6 +3,7 @@ use tracing::{info, error, instrument};
 use crate::gen::greeter::greeter_service_server::GreeterService;
 use crate::gen::greeter::{HelloRequest, HelloResponse};
 use crate::model::greeting::GreetingRepository;
+use crate::error::AppError;
 
 #[derive(Debug)]
 pub struct GreeterHandler {
@@ -30,7 +31,7 @@ impl GreeterService for GreeterHandler {
         // Save the greeting to the database
         if let Err(e) = self.greeting_repo.save_greeting(&name, &message).await {
             error!(error = %e, name = %name, "Failed to save greeting to database");
-            return Err(Status::internal("Failed to save greeting"));
+            return Err(AppError::internal("Failed to save greeting").into());
         }
 
         info!(name = %name, message = %message, "Greeting processed successfully");
@@ -0,0 +1,263 @@
+use crate::error::AppError;
+use crate::model::auth::{JwtManager, Scope, TokenClaims};
+use crate::tls::ServiceIdentity;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tonic::service::Interceptor;
+use tonic::{Request, Status};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+/// Authenticated caller, inserted into request extensions by `AuthInterceptor`
+#[derive(Debug, Clone)]
+pub struct AuthContext {
+    pub user_id: Uuid,
+    pub claims: TokenClaims,
+}
+
+impl AuthContext {
+    /// Fetch the authenticated caller from a request that passed through `AuthInterceptor`
+    pub fn from_request<T>(request: &Request<T>) -> Result<AuthContext, AppError> {
+        request
+            .extensions()
+            .get::<AuthContext>()
+            .cloned()
+            .ok_or_else(|| AppError::unauthorized("Missing authentication"))
+    }
+
+    /// Require that the caller's token grants the given scope
+    pub fn require_scope(&self, scope: Scope) -> Result<(), AppError> {
+        require_scope(&self.claims, scope)
+    }
+}
+
+/// Reject the call unless the token claims grant `scope`
+pub fn require_scope(claims: &TokenClaims, scope: Scope) -> Result<(), AppError> {
+    if claims.has_scope(scope) {
+        return Ok(());
+    }
+
+    warn!(
+        user_id = %claims.sub,
+        required_scope = %scope.as_str(),
+        client_type = %claims.client_type.as_str(),
+        "Rejected call missing required scope"
+    );
+    Err(AppError::permission_denied(format!(
+        "Token is missing required scope '{}'",
+        scope.as_str()
+    )))
+}
+
+/// Trusted internal service that called with its client certificate instead of an access token.
+/// `AuthInterceptor` lets no other call through without an `AuthContext`.
+pub fn trusted_service<T>(request: &Request<T>) -> Option<ServiceIdentity> {
+    match request.extensions().get::<AuthContext>() {
+        Some(_) => None,
+        None => ServiceIdentity::from_request(request),
+    }
+}
+
+/// gRPC interceptor validating `authorization: Bearer <access token>` metadata.
+///
+/// Services that wrap their server with this interceptor can read the caller via
+/// `AuthContext::from_request` and enforce per-RPC scopes with `require_scope`.
+/// `required_scopes` are checked for every call routed through the interceptor.
+///
+/// Over mutual TLS the caller's `ServiceIdentity` is inserted as well, and trusted services may call
+/// without a token unless `required_scopes` are set; such calls carry no `AuthContext`, so RPCs acting for
+/// a user still refuse them, and RPCs open to services accept them through `trusted_service`.
+#[derive(Clone)]
+pub struct AuthInterceptor {
+    jwt_manager: JwtManager,
+    required_scopes: Vec<Scope>,
+    trusted_services: Arc<HashSet<String>>,
+}
+
+impl AuthInterceptor {
+    pub fn new(jwt_manager: JwtManager) -> Self {
+        Self {
+            jwt_manager,
+            required_scopes: Vec::new(),
+            trusted_services: Arc::default(),
+        }
+    }
+
+    /// Accept calls without an access token from these services, identified by their client certificate
+    pub fn trust_services(mut self, services: HashSet<String>) -> Self {
+        self.trusted_services = Arc::new(services);
+        self
+    }
+
+    /// Require a scope on every call routed through this interceptor
+    pub fn require(mut self, scope: Scope) -> Self {
+        self.required_scopes.push(scope);
+        self
+    }
+
+    fn authenticate<T>(&self, request: &Request<T>) -> Result<AuthContext, AppError> {
+        let header = request
+            .metadata()
+            .get("authorization")
+            .ok_or_else(|| AppError::unauthorized("Missing authorization header"))?
+            .to_str()
+            .map_err(|_| AppError::unauthorized("Invalid authorization header"))?;
+
+        self.authenticate_header(header)
+    }
+
+    /// Authenticate a call whose connection presented `peer`'s client certificate
+    fn admit(&self, mut request: Request<()>, peer: Option<ServiceIdentity>) -> Result<Request<()>, Status> {
+        if let Some(service) = peer {
+            // A scope can only be granted by an access token
+            let trusted = self.required_scopes.is_empty() && self.trusted_services.contains(&service.name);
+            request.extensions_mut().insert(service.clone());
+            if trusted && request.metadata().get("authorization").is_none() {
+                debug!(service = %service.name, "Authenticated internal service by client certificate");
+                return Ok(request);
+            }
+        }
+
+        let context = self.authenticate(&request)?;
+        request.extensions_mut().insert(context);
+        Ok(request)
+    }
+
+    /// Validate a raw `Bearer <access token>` header value, for transports other than gRPC
+    pub fn authenticate_header(&self, header: &str) -> Result<AuthContext, AppError> {
+        let token = JwtManager::extract_token_from_header(header)
+            .map_err(|e| AppError::unauthorized(e.to_string()))?;
+
+        let claims = self.jwt_manager.validate_token(token).map_err(|e| {
+            debug!(error = %e, "Access token validation failed");
+            AppError::unauthorized("Invalid access token")
+        })?;
+
+        if claims.token_type != "access" {
+            return Err(AppError::unauthorized("Access token required"));
+        }
+
+        for scope in &self.required_scopes {
+            require_scope(&claims, *scope)?;
+        }
+
+        let user_id = Uuid::parse_str(&claims.sub)
+            .map_err(|_| AppError::unauthorized("Invalid user ID in token"))?;
+
+        Ok(AuthContext { user_id, claims })
+    }
+}
+
+impl Interceptor for AuthInterceptor {
+    fn call(&mut self, request: Request<()>) -> Result<Request<()>, Status> {
+        let peer = ServiceIdentity::from_peer(&request);
+        self.admit(request, peer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::auth::{ClientType, JwtConfig};
+    use tonic::Code;
+
+    fn request_with_token(token: &str) -> Request<()> {
+        let mut request = Request::new(());
+        request
+            .metadata_mut()
+            .insert("authorization", format!("Bearer {}", token).parse().unwrap());
+        request
+    }
+
+    #[test]
+    fn test_interceptor_inserts_auth_context() {
+        let jwt_manager = JwtManager::new(JwtConfig::default());
+        let user_id = Uuid::new_v4();
+        let tokens = jwt_manager
+            .generate_token_pair(user_id, "test@example.com", "google_123")
+            .unwrap();
+
+        let mut interceptor = AuthInterceptor::new(jwt_manager);
+        let request = interceptor.call(request_with_token(&tokens.access_token)).unwrap();
+
+        let context = AuthContext::from_request(&request).unwrap();
+        assert_eq!(context.user_id, user_id);
+        assert!(context.require_scope(Scope::AccountsRead).is_ok());
+    }
+
+    #[test]
+    fn test_interceptor_rejects_missing_and_refresh_tokens() {
+        let jwt_manager = JwtManager::new(JwtConfig::default());
+        let tokens = jwt_manager
+            .generate_token_pair(Uuid::new_v4(), "test@example.com", "google_123")
+            .unwrap();
+        let mut interceptor = AuthInterceptor::new(jwt_manager);
+
+        let status = interceptor.call(Request::new(())).unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated);
+
+        let status = interceptor.call(request_with_token(&tokens.refresh_token)).unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated);
+    }
+
+    #[test]
+    fn test_trusted_service_needs_client_certificate() {
+        let jwt_manager = JwtManager::new(JwtConfig::default());
+        let mut interceptor = AuthInterceptor::new(jwt_manager).trust_services(HashSet::from(["billing".to_string()]));
+
+        // An identity in the extensions is not a certificate on the connection
+        let mut request = Request::new(());
+        request.extensions_mut().insert(ServiceIdentity {
+            name: "billing".to_string(),
+            fingerprint: String::new(),
+        });
+        let status = interceptor.call(request).unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated);
+    }
+
+    #[test]
+    fn test_trusted_service_calls_without_token() {
+        let jwt_manager = JwtManager::new(JwtConfig::default());
+        let tokens = jwt_manager
+            .generate_token_pair(Uuid::new_v4(), "test@example.com", "google_123")
+            .unwrap();
+        let interceptor = AuthInterceptor::new(jwt_manager).trust_services(HashSet::from(["billing".to_string()]));
+        let peer = |name: &str| {
+            Some(ServiceIdentity {
+                name: name.to_string(),
+                fingerprint: String::new(),
+            })
+        };
+
+        let request = interceptor.admit(Request::new(()), peer("billing")).unwrap();
+        assert_eq!(trusted_service(&request).unwrap().name, "billing");
+        assert!(AuthContext::from_request(&request).is_err());
+
+        // With a token the call acts for the user, not the service
+        let request = interceptor
+            .admit(request_with_token(&tokens.access_token), peer("billing"))
+            .unwrap();
+        assert!(trusted_service(&request).is_none());
+        assert!(AuthContext::from_request(&request).is_ok());
+
+        let status = interceptor.admit(Request::new(()), peer("ledger")).unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated);
+
+        // Services requiring a scope need a token that grants it
+        let scoped = interceptor.clone().require(Scope::AccountsRead);
+        let status = scoped.admit(Request::new(()), peer("billing")).unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated);
+    }
+
+    #[test]
+    fn test_interceptor_enforces_required_scope() {
+        let jwt_manager = JwtManager::new(JwtConfig::default());
+        let tokens = jwt_manager
+            .generate_token_pair_for_client(Uuid::new_v4(), "test@example.com", "google_123", ClientType::ThirdPartyApp)
+            .unwrap();
+
+        let mut interceptor = AuthInterceptor::new(jwt_manager).require(Scope::BudgetsWrite);
+        let status = interceptor.call(request_with_token(&tokens.access_token)).unwrap_err();
+        assert_eq!(status.code(), Code::PermissionDenied);
+    }
+}
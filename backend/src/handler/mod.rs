@@ -1,2 +1,15 @@
 pub mod greeter;
-pub mod auth;
\ No newline at end of file
+pub mod auth;
+pub mod accounts;
+pub mod interceptor;
+pub mod field_mask;
+pub mod etag;
+pub mod pagination;
+pub mod sync;
+pub mod alerts;
+pub mod sharing;
+pub mod transfers;
+pub mod assistant;
+pub mod chat;
+pub mod admin;
+pub mod webhooks;
\ No newline at end of file
@@ -0,0 +1,71 @@
+use crate::error::AppError;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Requested page size, falling back to `default` when unset and capped at `max`
+pub fn page_size(requested: i32, default: i32, max: i32) -> i32 {
+    if requested <= 0 {
+        default
+    } else {
+        requested.min(max)
+    }
+}
+
+/// Encode a keyset position as an opaque, URL-safe page token
+pub fn encode_cursor<T: Serialize>(position: &T) -> String {
+    URL_SAFE_NO_PAD.encode(serde_json::to_vec(position).unwrap_or_default())
+}
+
+/// Decode a token produced by `encode_cursor`; `what` names the token in the validation error
+pub fn decode_cursor<T: DeserializeOwned>(token: &str, what: &str) -> Result<T, AppError> {
+    let invalid = || AppError::validation(format!("Invalid {}", what));
+    let bytes = URL_SAFE_NO_PAD.decode(token).map_err(|_| invalid())?;
+    serde_json::from_slice(&bytes).map_err(|_| invalid())
+}
+
+/// Trim rows fetched with a limit of `page_size + 1` to the page, reporting whether more follow
+pub fn split_page<T>(mut rows: Vec<T>, page_size: usize) -> (Vec<T>, bool) {
+    let has_more = rows.len() > page_size;
+    rows.truncate(page_size);
+    (rows, has_more)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Position {
+        date: String,
+        id: String,
+    }
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let position = Position {
+            date: "2024-03-01".to_string(),
+            id: "txn_1".to_string(),
+        };
+        let token = encode_cursor(&position);
+        assert!(!token.contains(['+', '/', '=']));
+        assert_eq!(decode_cursor::<Position>(&token, "page token").unwrap(), position);
+    }
+
+    #[test]
+    fn test_cursor_rejects_garbage() {
+        assert!(decode_cursor::<Position>("not a token", "page token").is_err());
+        assert!(decode_cursor::<Position>(&URL_SAFE_NO_PAD.encode(b"{}"), "page token").is_err());
+    }
+
+    #[test]
+    fn test_page_size_and_split() {
+        assert_eq!(page_size(0, 50, 500), 50);
+        assert_eq!(page_size(20, 50, 500), 20);
+        assert_eq!(page_size(1000, 50, 500), 500);
+
+        assert_eq!(split_page(vec![1, 2, 3], 2), (vec![1, 2], true));
+        assert_eq!(split_page(vec![1, 2], 2), (vec![1, 2], false));
+    }
+}
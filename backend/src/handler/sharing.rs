@@ -0,0 +1,218 @@
+use crate::error::AppError;
+use crate::gen::sharing::{
+    sharing_service_server::SharingService, AccountShare as ProtoAccountShare, ListSharingInvitationsRequest,
+    ListSharingInvitationsResponse, RespondToInvitationRequest, RevokeShareRequest, RevokeShareResponse,
+    ShareAccess as ProtoShareAccess, ShareAccountRequest,
+};
+use crate::handler::interceptor::AuthContext;
+use crate::model::account_share::{AccountShare, AccountShareRepository, ShareAccess};
+use crate::model::audit_log::AuditLogRepository;
+use crate::model::auth::Scope;
+use crate::model::bank_account::BankAccountRepository;
+use serde_json::json;
+use tonic::{Request, Response, Status};
+use tracing::{debug, error, info, instrument, warn};
+use uuid::Uuid;
+
+/// gRPC service for sharing bank accounts with other users through email invitations
+pub struct SharingHandler {
+    share_repository: AccountShareRepository,
+    account_repository: BankAccountRepository,
+    audit_log: AuditLogRepository,
+}
+
+impl SharingHandler {
+    pub fn new(
+        share_repository: AccountShareRepository,
+        account_repository: BankAccountRepository,
+        audit_log: AuditLogRepository,
+    ) -> Self {
+        Self {
+            share_repository,
+            account_repository,
+            audit_log,
+        }
+    }
+
+    fn access_from_proto(access: i32) -> Result<ShareAccess, AppError> {
+        match ProtoShareAccess::try_from(access) {
+            Ok(ProtoShareAccess::Read) => Ok(ShareAccess::Read),
+            Ok(ProtoShareAccess::Full) => Ok(ShareAccess::Full),
+            _ => Err(AppError::validation("access is required")),
+        }
+    }
+
+    fn share_to_proto(share: &AccountShare) -> ProtoAccountShare {
+        let access = match share.access() {
+            Some(ShareAccess::Read) => ProtoShareAccess::Read,
+            Some(ShareAccess::Full) => ProtoShareAccess::Full,
+            None => ProtoShareAccess::Unspecified,
+        };
+        ProtoAccountShare {
+            share_id: share.id.to_string(),
+            account_id: share.account_id.clone(),
+            owner_user_id: share.owner_user_id.to_string(),
+            invitee_email: share.invitee_email.clone(),
+            grantee_user_id: share.grantee_user_id.map(|id| id.to_string()),
+            access: access as i32,
+            status: share.status.clone(),
+            created_at: share.created_at.timestamp(),
+            responded_at: share.responded_at.map(|t| t.timestamp()),
+        }
+    }
+
+    fn parse_share_id(share_id: &str) -> Result<Uuid, AppError> {
+        Uuid::parse_str(share_id).map_err(|_| AppError::validation("share_id must be a UUID"))
+    }
+
+    /// Sharing changes are audited; the change has happened either way, so failures are only logged
+    async fn audit(&self, user_id: Uuid, action: &str, share: &AccountShare) {
+        let metadata = json!({
+            "account_id": share.account_id,
+            "invitee_email": share.invitee_email,
+            "access": share.access,
+        });
+        if let Err(e) = self
+            .audit_log
+            .record(user_id, action, "account_share", &share.id.to_string(), metadata)
+            .await
+        {
+            warn!(share_id = %share.id, error = %e, "Failed to audit account share change");
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl SharingService for SharingHandler {
+    #[instrument(skip(self, request), fields(account_id = %request.get_ref().account_id))]
+    async fn share_account(
+        &self,
+        request: Request<ShareAccountRequest>,
+    ) -> Result<Response<ProtoAccountShare>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsWrite)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!(user_id = %user_id, access = req.access, "Sharing account");
+
+        let access = Self::access_from_proto(req.access)?;
+        let email = req.email.trim().to_lowercase();
+        if !email.contains('@') {
+            return Err(AppError::validation("A valid email is required").into());
+        }
+        if email == auth.claims.email.to_lowercase() {
+            return Err(AppError::validation("Accounts cannot be shared with yourself").into());
+        }
+
+        let account = self
+            .account_repository
+            .find_by_account_id(&req.account_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to load account: {:?}", e);
+                AppError::internal("Failed to share account")
+            })?;
+        // Only the owner may share; grantees cannot re-share
+        if !matches!(account, Some(account) if account.user_id == user_id) {
+            return Err(AppError::not_found("Account not found").into());
+        }
+
+        let share = self
+            .share_repository
+            .invite(user_id, &req.account_id, &email, access)
+            .await
+            .map_err(|e| {
+                error!("Failed to create account share: {:?}", e);
+                AppError::internal("Failed to share account")
+            })?
+            .ok_or_else(|| AppError::conflict("The account is already shared with this email"))?;
+        self.audit(user_id, "account_share.created", &share).await;
+
+        info!(user_id = %user_id, share_id = %share.id, "Account share invitation created");
+        Ok(Response::new(Self::share_to_proto(&share)))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn list_sharing_invitations(
+        &self,
+        request: Request<ListSharingInvitationsRequest>,
+    ) -> Result<Response<ListSharingInvitationsResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsRead)?;
+        let user_id = auth.user_id;
+        debug!(user_id = %user_id, "Listing account shares");
+
+        let outgoing = self.share_repository.list_outgoing(user_id).await.map_err(|e| {
+            error!("Failed to list outgoing account shares: {:?}", e);
+            AppError::internal("Failed to list account shares")
+        })?;
+        let incoming = self
+            .share_repository
+            .list_incoming(user_id, &auth.claims.email)
+            .await
+            .map_err(|e| {
+                error!("Failed to list incoming account shares: {:?}", e);
+                AppError::internal("Failed to list account shares")
+            })?;
+
+        Ok(Response::new(ListSharingInvitationsResponse {
+            outgoing: outgoing.iter().map(Self::share_to_proto).collect(),
+            incoming: incoming.iter().map(Self::share_to_proto).collect(),
+        }))
+    }
+
+    #[instrument(skip(self, request), fields(share_id = %request.get_ref().share_id))]
+    async fn respond_to_invitation(
+        &self,
+        request: Request<RespondToInvitationRequest>,
+    ) -> Result<Response<ProtoAccountShare>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsWrite)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!(user_id = %user_id, accept = req.accept, "Responding to account share invitation");
+
+        let share_id = Self::parse_share_id(&req.share_id)?;
+        let share = self
+            .share_repository
+            .respond(share_id, user_id, &auth.claims.email, req.accept)
+            .await
+            .map_err(|e| {
+                error!("Failed to respond to account share invitation: {:?}", e);
+                AppError::internal("Failed to respond to invitation")
+            })?
+            .ok_or_else(|| AppError::not_found("Invitation not found"))?;
+        let action = if req.accept { "account_share.accepted" } else { "account_share.declined" };
+        self.audit(user_id, action, &share).await;
+
+        info!(user_id = %user_id, share_id = %share.id, accepted = req.accept, "Account share invitation answered");
+        Ok(Response::new(Self::share_to_proto(&share)))
+    }
+
+    #[instrument(skip(self, request), fields(share_id = %request.get_ref().share_id))]
+    async fn revoke_share(
+        &self,
+        request: Request<RevokeShareRequest>,
+    ) -> Result<Response<RevokeShareResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsWrite)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!(user_id = %user_id, "Revoking account share");
+
+        let share_id = Self::parse_share_id(&req.share_id)?;
+        let share = self
+            .share_repository
+            .revoke(share_id, user_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to revoke account share: {:?}", e);
+                AppError::internal("Failed to revoke share")
+            })?
+            .ok_or_else(|| AppError::not_found("Share not found"))?;
+        self.audit(user_id, "account_share.revoked", &share).await;
+
+        info!(user_id = %user_id, share_id = %share.id, "Account share revoked");
+        Ok(Response::new(RevokeShareResponse { success: true }))
+    }
+}
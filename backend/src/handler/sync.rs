@@ -0,0 +1,265 @@
+use crate::error::AppError;
+use crate::gen::sync::{
+    sync_service_server::SyncService, EntityType, GetChangesSinceRequest, GetChangesSinceResponse,
+    Tombstone,
+};
+use crate::handler::accounts::AccountsHandler;
+use crate::handler::auth::user_profile;
+use crate::handler::field_mask::ReadMask;
+use crate::handler::interceptor::AuthContext;
+use crate::handler::pagination::{decode_cursor, encode_cursor, page_size, split_page};
+use crate::model::auth::Scope;
+use crate::model::bank_account::BankAccountRepository;
+use crate::model::transaction::TransactionRepository;
+use crate::model::user::UserRepository;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use tonic::{Request, Response, Status};
+use tracing::{debug, error, info, instrument};
+
+const DEFAULT_PAGE_SIZE: i32 = 500;
+const MAX_PAGE_SIZE: i32 = 1000;
+
+/// Rows committed by transactions that started before the watermark can become visible
+/// slightly later, so a sync only reads transactions changed at least this long ago; newer
+/// ones are picked up by the next sync.
+const WATERMARK_LAG_SECONDS: i64 = 30;
+
+/// Position in the change feed, handed to clients as an opaque base64 token
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct SyncToken {
+    /// Changes strictly after this time (microseconds since epoch) are returned
+    since_micros: i64,
+    /// Keyset tie-breaker for transactions sharing `since_micros`
+    #[serde(default)]
+    after_transaction_id: String,
+    /// Watermark the pages of an unfinished sync are read up to; 0 once the sync has caught up
+    #[serde(default)]
+    until_micros: i64,
+}
+
+impl SyncToken {
+    fn initial() -> Self {
+        Self {
+            since_micros: 0,
+            after_transaction_id: String::new(),
+            until_micros: 0,
+        }
+    }
+
+    fn encode(&self) -> String {
+        encode_cursor(self)
+    }
+
+    fn decode(token: &str) -> Result<Self, AppError> {
+        decode_cursor(token, "sync token")
+    }
+
+    fn since(&self) -> DateTime<Utc> {
+        Utc.timestamp_micros(self.since_micros)
+            .single()
+            .unwrap_or_else(|| Utc.timestamp_micros(0).unwrap())
+    }
+
+    /// Watermark of the sync this token continues, if it is mid-way through one
+    fn until(&self) -> Option<DateTime<Utc>> {
+        (self.until_micros > 0)
+            .then(|| Utc.timestamp_micros(self.until_micros).single())
+            .flatten()
+    }
+}
+
+/// gRPC delta-sync service for clients maintaining a local cache
+pub struct SyncHandler {
+    account_repository: BankAccountRepository,
+    transaction_repository: TransactionRepository,
+    user_repository: UserRepository,
+}
+
+impl SyncHandler {
+    pub fn new(
+        account_repository: BankAccountRepository,
+        transaction_repository: TransactionRepository,
+        user_repository: UserRepository,
+    ) -> Self {
+        Self {
+            account_repository,
+            transaction_repository,
+            user_repository,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl SyncService for SyncHandler {
+    #[instrument(skip(self, request))]
+    async fn get_changes_since(
+        &self,
+        request: Request<GetChangesSinceRequest>,
+    ) -> Result<Response<GetChangesSinceResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsRead)?;
+        auth.require_scope(Scope::TransactionsRead)?;
+        let req = request.into_inner();
+
+        let full_resync = req.sync_token.is_empty();
+        let token = if full_resync {
+            SyncToken::initial()
+        } else {
+            SyncToken::decode(&req.sync_token)?
+        };
+        let since = token.since();
+        let page_size = page_size(req.page_size, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE);
+        debug!(user_id = %auth.user_id, since = %since, full_resync, "Fetching changes");
+
+        // Every page of one sync is read up to the same watermark, captured before its first read,
+        // so paging never moves past a change that is yet to commit
+        let watermark = token
+            .until()
+            .unwrap_or_else(|| Utc::now() - Duration::seconds(WATERMARK_LAG_SECONDS));
+
+        let transactions = self
+            .transaction_repository
+            .list_changed_since(
+                auth.user_id,
+                since,
+                &token.after_transaction_id,
+                watermark,
+                page_size as i64 + 1,
+            )
+            .await
+            .map_err(|e| {
+                error!("Failed to load changed transactions: {:?}", e);
+                AppError::internal("Failed to load changes")
+            })?;
+        let (transactions, has_more) = split_page(transactions, page_size as usize);
+
+        let account_changes = self
+            .account_repository
+            .list_changed_since(auth.user_id, since)
+            .await
+            .map_err(|e| {
+                error!("Failed to load changed accounts: {:?}", e);
+                AppError::internal("Failed to load changes")
+            })?;
+
+        let profile = if auth.claims.has_scope(Scope::ProfileRead) {
+            self.user_repository
+                .find_by_id(auth.user_id)
+                .await
+                .map_err(|e| {
+                    error!("Failed to load user: {:?}", e);
+                    AppError::internal("Failed to load changes")
+                })?
+                .filter(|user| user.updated_at > since)
+                .map(|user| user_profile(&user))
+        } else {
+            None
+        };
+
+        let mut tombstones = Vec::new();
+        let mut accounts = Vec::new();
+        for change in &account_changes {
+            if change.item_removed {
+                if !full_resync {
+                    tombstones.push(Tombstone {
+                        entity_type: EntityType::Account as i32,
+                        id: change.account.account_id.clone(),
+                        deleted_at: change.changed_at.timestamp(),
+                    });
+                }
+            } else {
+                accounts.push(AccountsHandler::account_to_proto(&change.account.to_bank_account()));
+            }
+        }
+
+        let mut proto_transactions = Vec::new();
+        for transaction in &transactions {
+            match transaction.removed_at {
+                Some(removed_at) if !full_resync => tombstones.push(Tombstone {
+                    entity_type: EntityType::Transaction as i32,
+                    id: transaction.transaction_id.clone(),
+                    deleted_at: removed_at.timestamp(),
+                }),
+                Some(_) => {}
+                None => proto_transactions.push(AccountsHandler::transaction_to_proto(transaction, &ReadMask::all())),
+            }
+        }
+
+        let next_token = match transactions.last() {
+            Some(last) if has_more => SyncToken {
+                since_micros: last.updated_at.timestamp_micros(),
+                after_transaction_id: last.transaction_id.clone(),
+                until_micros: watermark.timestamp_micros(),
+            },
+            _ => SyncToken {
+                since_micros: watermark.timestamp_micros().max(0),
+                after_transaction_id: String::new(),
+                until_micros: 0,
+            },
+        };
+
+        info!(
+            user_id = %auth.user_id,
+            accounts = accounts.len(),
+            transactions = proto_transactions.len(),
+            tombstones = tombstones.len(),
+            has_more,
+            "Changes returned"
+        );
+
+        Ok(Response::new(GetChangesSinceResponse {
+            accounts,
+            transactions: proto_transactions,
+            profile,
+            tombstones,
+            next_sync_token: next_token.encode(),
+            has_more,
+            full_resync,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+
+    #[test]
+    fn test_sync_token_round_trip() {
+        let token = SyncToken {
+            since_micros: 1_700_000_000_123_456,
+            after_transaction_id: "txn_1".to_string(),
+            until_micros: 1_700_000_030_000_000,
+        };
+        let decoded = SyncToken::decode(&token.encode()).unwrap();
+        assert_eq!(decoded, token);
+        assert_eq!(decoded.since().timestamp_micros(), 1_700_000_000_123_456);
+        assert_eq!(decoded.until().unwrap().timestamp_micros(), 1_700_000_030_000_000);
+    }
+
+    #[test]
+    fn test_caught_up_tokens_have_no_watermark() {
+        let token = SyncToken {
+            since_micros: 1_700_000_000_123_456,
+            after_transaction_id: String::new(),
+            until_micros: 0,
+        };
+        assert_eq!(token.until(), None);
+
+        // Tokens issued before pages carried a watermark
+        let legacy = encode_cursor(&serde_json::json!({ "since_micros": 1_700_000_000_123_456 }));
+        assert_eq!(SyncToken::decode(&legacy).unwrap(), token);
+    }
+
+    #[test]
+    fn test_sync_token_rejects_garbage() {
+        assert!(SyncToken::decode("not a token").is_err());
+        assert!(SyncToken::decode(&URL_SAFE_NO_PAD.encode(b"{}")).is_err());
+    }
+
+    #[test]
+    fn test_initial_token_starts_at_epoch() {
+        assert_eq!(SyncToken::initial().since().timestamp(), 0);
+    }
+}
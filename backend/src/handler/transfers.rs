@@ -0,0 +1,561 @@
+use crate::adapter::bank_data::PLAID_PROVIDER;
+use crate::adapter::plaid::{PlaidClient, TransferAuthorizationRequest, TransferDirection};
+use crate::adapter::plaid_webhook::PlaidWebhookVerifier;
+use crate::adapter::sqs::SqsQueue;
+use crate::error::AppError;
+use crate::gen::transfers::{
+    transfers_service_server::TransfersService, CancelTransferRequest, CreateTransferAuthorizationRequest,
+    CreateTransferRequest, GetTransferRequest, ListTransfersRequest, ListTransfersResponse, Transfer as ProtoTransfer,
+};
+use crate::handler::accounts::map_plaid_error;
+use crate::handler::interceptor::AuthContext;
+use crate::handler::pagination::page_size;
+use crate::jobs::transfers::TransferWebhook;
+use crate::jobs::TransferEventSync;
+use crate::model::audit_log::AuditLogRepository;
+use crate::model::auth::Scope;
+use crate::model::bank_account::{BankAccountRepository, StoredBankAccount};
+use crate::model::plaid_item::{PlaidItem, PlaidItemRepository};
+use crate::model::transfer::{NewTransfer, Transfer, TransferRepository, TransferStatus};
+use crate::model::user::UserRepository;
+use serde_json::json;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::sync::Notify;
+use tonic::{Request, Response, Status};
+use tracing::{debug, error, info, instrument, warn};
+use uuid::Uuid;
+
+const DEFAULT_TRANSFER_LIMIT: i32 = 50;
+const MAX_TRANSFER_LIMIT: i32 = 200;
+/// Plaid's limit on the description shown on bank statements
+const MAX_DESCRIPTION_LENGTH: usize = 15;
+const DEFAULT_DESCRIPTION: &str = "Transfer";
+
+/// gRPC service moving money between a user's linked accounts with Plaid Transfer
+pub struct TransfersHandler {
+    plaid_client: Arc<PlaidClient>,
+    transfer_repository: TransferRepository,
+    item_repository: PlaidItemRepository,
+    account_repository: BankAccountRepository,
+    user_repository: UserRepository,
+    audit_log: AuditLogRepository,
+}
+
+impl TransfersHandler {
+    pub fn new(
+        plaid_client: Arc<PlaidClient>,
+        transfer_repository: TransferRepository,
+        item_repository: PlaidItemRepository,
+        account_repository: BankAccountRepository,
+        user_repository: UserRepository,
+        audit_log: AuditLogRepository,
+    ) -> Self {
+        Self {
+            plaid_client,
+            transfer_repository,
+            item_repository,
+            account_repository,
+            user_repository,
+            audit_log,
+        }
+    }
+
+    fn transfer_to_proto(transfer: &Transfer) -> ProtoTransfer {
+        ProtoTransfer {
+            transfer_id: transfer.id.to_string(),
+            from_account_id: transfer.from_account_id.clone(),
+            to_account_id: transfer.to_account_id.clone(),
+            amount: transfer.amount,
+            iso_currency_code: transfer.iso_currency_code.clone(),
+            description: transfer.description.clone(),
+            status: transfer.status.clone(),
+            failure_reason: transfer.failure_reason.clone(),
+            created_at: transfer.created_at.timestamp(),
+            updated_at: transfer.updated_at.timestamp(),
+        }
+    }
+
+    fn parse_transfer_id(transfer_id: &str) -> Result<Uuid, AppError> {
+        Uuid::parse_str(transfer_id).map_err(|_| AppError::validation("transfer_id must be a UUID"))
+    }
+
+    /// ACH moves whole cents between US depository accounts
+    fn validate_amount(amount: f64) -> Result<f64, AppError> {
+        if !amount.is_finite() || amount <= 0.0 {
+            return Err(AppError::validation("amount must be positive"));
+        }
+        let cents = amount * 100.0;
+        if (cents - cents.round()).abs() > 1e-6 {
+            return Err(AppError::validation("amount must have at most two decimal places"));
+        }
+        Ok(cents.round() / 100.0)
+    }
+
+    fn validate_description(description: &str) -> Result<String, AppError> {
+        let description = description.trim();
+        if description.is_empty() {
+            return Ok(DEFAULT_DESCRIPTION.to_string());
+        }
+        if description.chars().count() > MAX_DESCRIPTION_LENGTH {
+            return Err(AppError::validation(format!(
+                "description must be at most {} characters",
+                MAX_DESCRIPTION_LENGTH
+            )));
+        }
+        Ok(description.to_string())
+    }
+
+    /// Depository account owned by the caller and linked through Plaid, with its item;
+    /// shared accounts cannot move money
+    async fn transferable_account(
+        &self,
+        user_id: Uuid,
+        account_id: &str,
+    ) -> Result<(StoredBankAccount, PlaidItem), AppError> {
+        let account = self
+            .account_repository
+            .find_by_account_id(account_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to load account: {:?}", e);
+                AppError::internal("Failed to load account")
+            })?
+            .filter(|account| account.user_id == user_id)
+            .ok_or_else(|| AppError::not_found("Account not found"))?;
+
+        if account.account_type != "depository" {
+            return Err(AppError::validation("Transfers are only supported between checking and savings accounts"));
+        }
+        if account.iso_currency_code.as_deref() != Some("USD") {
+            return Err(AppError::validation("Transfers are only supported between USD accounts"));
+        }
+        let item = self.plaid_item(&account).await?;
+        Ok((account, item))
+    }
+
+    /// Item of an account; transfers go through Plaid Transfer, so the item must be a Plaid one
+    async fn plaid_item(&self, account: &StoredBankAccount) -> Result<PlaidItem, AppError> {
+        let item = self
+            .item_repository
+            .find_by_item_id(&account.item_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to load Plaid item: {:?}", e);
+                AppError::internal("Failed to load bank connection")
+            })?
+            .ok_or_else(|| AppError::not_found("Linked item not found"))?;
+
+        if item.provider != PLAID_PROVIDER {
+            return Err(AppError::validation("Transfers are only supported for accounts linked through Plaid"));
+        }
+        Ok(item)
+    }
+
+    async fn access_token(&self, item: &PlaidItem) -> Result<String, AppError> {
+        self.item_repository.access_token(item).await.map_err(|e| {
+            error!("Failed to decrypt access token: {:?}", e);
+            AppError::internal("Failed to load bank connection")
+        })
+    }
+
+    async fn owned_transfer(&self, user_id: Uuid, transfer_id: &str) -> Result<Transfer, AppError> {
+        let transfer_id = Self::parse_transfer_id(transfer_id)?;
+        self.transfer_repository
+            .find(transfer_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to load transfer: {:?}", e);
+                AppError::internal("Failed to load transfer")
+            })?
+            .filter(|transfer| transfer.user_id == user_id)
+            .ok_or_else(|| AppError::not_found("Transfer not found"))
+    }
+
+    /// Transfers are audited; the change has happened either way, so failures are only logged
+    async fn audit(&self, user_id: Uuid, action: &str, transfer: &Transfer) {
+        let metadata = json!({
+            "from_account_id": transfer.from_account_id,
+            "to_account_id": transfer.to_account_id,
+            "amount": transfer.amount,
+            "status": transfer.status,
+        });
+        if let Err(e) = self
+            .audit_log
+            .record(user_id, action, "transfer", &transfer.id.to_string(), metadata)
+            .await
+        {
+            warn!(transfer_id = %transfer.id, error = %e, "Failed to audit transfer change");
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl TransfersService for TransfersHandler {
+    #[instrument(skip(self, request))]
+    async fn create_transfer_authorization(
+        &self,
+        request: Request<CreateTransferAuthorizationRequest>,
+    ) -> Result<Response<ProtoTransfer>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsWrite)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!(user_id = %user_id, from_account_id = %req.from_account_id, to_account_id = %req.to_account_id, "Authorizing transfer");
+
+        let amount = Self::validate_amount(req.amount)?;
+        let description = Self::validate_description(&req.description)?;
+        if req.from_account_id == req.to_account_id {
+            return Err(AppError::validation("from_account_id and to_account_id must differ").into());
+        }
+        let (from_account, from_item) = self.transferable_account(user_id, &req.from_account_id).await?;
+        self.transferable_account(user_id, &req.to_account_id).await?;
+
+        let user = self
+            .user_repository
+            .find_by_id(user_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to load user: {:?}", e);
+                AppError::internal("Failed to authorize transfer")
+            })?
+            .ok_or_else(|| AppError::not_found("User not found"))?;
+        let access_token = self.access_token(&from_item).await?;
+
+        let authorization = self
+            .plaid_client
+            .authorize_transfer(TransferAuthorizationRequest {
+                access_token,
+                account_id: from_account.account_id.clone(),
+                direction: TransferDirection::Debit,
+                amount,
+                legal_name: user.name,
+                idempotency_key: Uuid::new_v4().to_string(),
+            })
+            .await
+            .map_err(|e| {
+                error!("Failed to authorize transfer: {:?}", e);
+                map_plaid_error(e, "Failed to authorize transfer")
+            })?;
+
+        let (status, failure_reason) = if authorization.is_approved() {
+            (TransferStatus::Authorized, None)
+        } else if authorization.decision == "user_action_required" {
+            (
+                TransferStatus::Declined,
+                Some("Bank connection requires re-authentication before transferring".to_string()),
+            )
+        } else {
+            (TransferStatus::Declined, authorization.decision_rationale.clone())
+        };
+
+        let transfer = self
+            .transfer_repository
+            .create(&NewTransfer {
+                user_id,
+                from_account_id: req.from_account_id,
+                to_account_id: req.to_account_id,
+                amount,
+                iso_currency_code: "USD".to_string(),
+                description,
+                status,
+                debit_authorization_id: authorization.authorization_id,
+                failure_reason,
+            })
+            .await
+            .map_err(|e| {
+                error!("Failed to record transfer: {:?}", e);
+                AppError::internal("Failed to authorize transfer")
+            })?;
+        self.audit(user_id, "transfer.authorized", &transfer).await;
+
+        info!(user_id = %user_id, transfer_id = %transfer.id, status = %transfer.status, "Transfer authorization recorded");
+        Ok(Response::new(Self::transfer_to_proto(&transfer)))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn create_transfer(
+        &self,
+        request: Request<CreateTransferRequest>,
+    ) -> Result<Response<ProtoTransfer>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsWrite)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!(user_id = %user_id, transfer_id = %req.transfer_id, "Confirming transfer");
+
+        let transfer = self.owned_transfer(user_id, &req.transfer_id).await?;
+        if transfer.status() != Some(TransferStatus::Authorized) {
+            return Err(AppError::conflict(format!("Transfer is {}", transfer.status)).into());
+        }
+        let authorization_id = transfer
+            .debit_authorization_id
+            .as_deref()
+            .ok_or_else(|| AppError::internal("Transfer has no authorization"))?;
+        let from_account = self
+            .account_repository
+            .find_by_account_id(&transfer.from_account_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to load account: {:?}", e);
+                AppError::internal("Failed to load account")
+            })?
+            .ok_or_else(|| AppError::not_found("Source account is no longer linked"))?;
+        let from_item = self.plaid_item(&from_account).await?;
+        let access_token = self.access_token(&from_item).await?;
+
+        let debit = self
+            .plaid_client
+            .create_transfer(&access_token, &transfer.from_account_id, authorization_id, &transfer.description)
+            .await
+            .map_err(|e| {
+                error!("Failed to create transfer: {:?}", e);
+                map_plaid_error(e, "Failed to create transfer")
+            })?;
+
+        let updated = self
+            .transfer_repository
+            .mark_debit_pending(transfer.id, &debit.transfer_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to record transfer debit: {:?}", e);
+                AppError::internal("Failed to create transfer")
+            })?;
+        let Some(updated) = updated else {
+            // Cancelled while the debit was being created; take the debit back out
+            warn!(transfer_id = %transfer.id, debit_transfer_id = %debit.transfer_id, "Transfer changed during confirmation");
+            if let Err(e) = self.plaid_client.cancel_transfer(&debit.transfer_id).await {
+                error!("Failed to cancel orphaned transfer debit: {:?}", e);
+            }
+            return Err(AppError::conflict("Transfer was cancelled").into());
+        };
+        self.audit(user_id, "transfer.confirmed", &updated).await;
+
+        info!(user_id = %user_id, transfer_id = %updated.id, debit_transfer_id = %debit.transfer_id, "Transfer debit sent");
+        Ok(Response::new(Self::transfer_to_proto(&updated)))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn get_transfer(
+        &self,
+        request: Request<GetTransferRequest>,
+    ) -> Result<Response<ProtoTransfer>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsRead)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!(user_id = %user_id, transfer_id = %req.transfer_id, "Getting transfer");
+
+        let transfer = self.owned_transfer(user_id, &req.transfer_id).await?;
+        Ok(Response::new(Self::transfer_to_proto(&transfer)))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn list_transfers(
+        &self,
+        request: Request<ListTransfersRequest>,
+    ) -> Result<Response<ListTransfersResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsRead)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        let limit = page_size(req.limit, DEFAULT_TRANSFER_LIMIT, MAX_TRANSFER_LIMIT);
+        debug!(user_id = %user_id, limit, "Listing transfers");
+
+        let transfers = self
+            .transfer_repository
+            .list_by_user(user_id, limit as i64)
+            .await
+            .map_err(|e| {
+                error!("Failed to list transfers: {:?}", e);
+                AppError::internal("Failed to list transfers")
+            })?;
+
+        Ok(Response::new(ListTransfersResponse {
+            transfers: transfers.iter().map(Self::transfer_to_proto).collect(),
+        }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn cancel_transfer(
+        &self,
+        request: Request<CancelTransferRequest>,
+    ) -> Result<Response<ProtoTransfer>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsWrite)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!(user_id = %user_id, transfer_id = %req.transfer_id, "Cancelling transfer");
+
+        let transfer = self.owned_transfer(user_id, &req.transfer_id).await?;
+        match transfer.status() {
+            Some(TransferStatus::Authorized) => {}
+            Some(TransferStatus::DebitPending) => {
+                let debit_transfer_id = transfer.debit_transfer_id.as_deref().unwrap_or_default();
+                self.plaid_client.cancel_transfer(debit_transfer_id).await.map_err(|e| {
+                    warn!(transfer_id = %transfer.id, error = ?e, "Plaid refused to cancel transfer debit");
+                    AppError::conflict("Transfer can no longer be cancelled")
+                })?;
+            }
+            _ => return Err(AppError::conflict(format!("Transfer is {}", transfer.status)).into()),
+        }
+
+        let cancelled = self
+            .transfer_repository
+            .mark_cancelled(transfer.id)
+            .await
+            .map_err(|e| {
+                error!("Failed to cancel transfer: {:?}", e);
+                AppError::internal("Failed to cancel transfer")
+            })?
+            .ok_or_else(|| AppError::conflict("Transfer can no longer be cancelled"))?;
+        self.audit(user_id, "transfer.cancelled", &cancelled).await;
+
+        info!(user_id = %user_id, transfer_id = %cancelled.id, "Transfer cancelled");
+        Ok(Response::new(Self::transfer_to_proto(&cancelled)))
+    }
+}
+
+/// Runs a sync in the background when prompted. Prompts arriving while a sync is waiting to run
+/// collapse into it, so a burst of webhooks leads to at most one more sync after the one in progress.
+struct SyncTrigger {
+    prompts: Arc<Notify>,
+}
+
+impl SyncTrigger {
+    fn spawn<F, Fut>(sync: F) -> Self
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let prompts = Arc::new(Notify::new());
+        let waiting = prompts.clone();
+        tokio::spawn(async move {
+            loop {
+                waiting.notified().await;
+                sync().await;
+            }
+        });
+        Self { prompts }
+    }
+
+    fn prompt(&self) {
+        // Stores a single permit while no sync waits, which later prompts don't add to
+        self.prompts.notify_one();
+    }
+}
+
+/// Receiver of Plaid Transfer webhooks, posted to `/api/webhooks/plaid/transfer` through the REST gateway;
+/// configure that URL in the Plaid dashboard. Webhooks carry no transfer data, they only prompt a sync
+/// that pulls events from Plaid with our credentials, and are only accepted with a valid
+/// `Plaid-Verification` signature.
+pub struct TransferWebhookHandler {
+    verifier: PlaidWebhookVerifier,
+    /// Queue webhooks are handed to, so a sync that fails or is cut short by a restart is retried
+    queue: Option<SqsQueue>,
+    syncs: SyncTrigger,
+}
+
+impl TransferWebhookHandler {
+    pub fn new(verifier: PlaidWebhookVerifier, event_sync: Arc<TransferEventSync>) -> Self {
+        let syncs = SyncTrigger::spawn(move || {
+            let event_sync = event_sync.clone();
+            async move {
+                match event_sync.sync().await {
+                    Ok(applied) => info!(applied, "Transfer events synced from webhook"),
+                    Err(e) => error!("Failed to sync transfer events: {:?}", e),
+                }
+            }
+        });
+        Self {
+            verifier,
+            queue: None,
+            syncs,
+        }
+    }
+
+    /// Queue webhooks for a `QueueWorker` instead of syncing on a background task
+    pub fn with_queue(mut self, queue: SqsQueue) -> Self {
+        self.queue = Some(queue);
+        self
+    }
+
+    async fn enqueue(&self, queue: &SqsQueue, webhook: &TransferWebhook) -> anyhow::Result<()> {
+        let body = serde_json::to_string(webhook)?;
+        queue.send(&body, std::time::Duration::ZERO).await?;
+        Ok(())
+    }
+
+    /// Handle a webhook from its `Plaid-Verification` header and raw body, which the signature covers
+    #[instrument(skip_all)]
+    pub async fn receive(&self, verification: Option<&str>, body: &[u8]) -> Result<(), AppError> {
+        let verification = verification.ok_or_else(|| AppError::unauthorized("Missing Plaid-Verification header"))?;
+        if let Err(e) = self.verifier.verify(verification, body).await {
+            warn!(error = ?e, "Rejected Plaid transfer webhook");
+            return Err(AppError::unauthorized("Invalid Plaid-Verification header"));
+        }
+
+        let webhook: TransferWebhook =
+            serde_json::from_slice(body).map_err(|_| AppError::validation("Invalid webhook body"))?;
+        debug!(
+            webhook_type = %webhook.webhook_type,
+            webhook_code = %webhook.webhook_code,
+            "Received Plaid transfer webhook"
+        );
+        if !webhook.is_events_update() {
+            return Ok(());
+        }
+
+        if let Some(queue) = &self.queue {
+            match self.enqueue(queue, &webhook).await {
+                Ok(()) => return Ok(()),
+                // The scheduled sync catches up if the fallback below fails too
+                Err(e) => warn!(error = ?e, "Failed to queue transfer webhook; syncing in the background"),
+            }
+        }
+        // Plaid expects a prompt acknowledgement, so events are synced in the background
+        self.syncs.prompt();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use tokio::sync::Semaphore;
+
+    #[tokio::test]
+    async fn test_prompts_collapse_into_one_pending_sync() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let release = Arc::new(Semaphore::new(0));
+        let trigger = {
+            let (runs, release) = (runs.clone(), release.clone());
+            SyncTrigger::spawn(move || {
+                let (runs, release) = (runs.clone(), release.clone());
+                async move {
+                    runs.fetch_add(1, Ordering::SeqCst);
+                    release.acquire().await.unwrap().forget();
+                }
+            })
+        };
+
+        trigger.prompt();
+        while runs.load(Ordering::SeqCst) == 0 {
+            tokio::task::yield_now().await;
+        }
+        // Webhooks arriving during the sync queue one more, however many there are
+        for _ in 0..5 {
+            trigger.prompt();
+        }
+        release.add_permits(5);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+
+        // A webhook arriving once the syncs are done prompts a new one
+        trigger.prompt();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+    }
+}
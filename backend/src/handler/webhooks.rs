@@ -0,0 +1,316 @@
+use crate::adapter::webhooks::{generate_secret, WebhookClient};
+use crate::error::AppError;
+use crate::gen::webhooks::{
+    webhooks_service_server::WebhooksService, CreateWebhookEndpointRequest, CreateWebhookEndpointResponse,
+    DeleteWebhookEndpointRequest, DeleteWebhookEndpointResponse, ListWebhookDeliveriesRequest,
+    ListWebhookDeliveriesResponse, ListWebhookEndpointsRequest, ListWebhookEndpointsResponse,
+    RotateWebhookSecretRequest, RotateWebhookSecretResponse, WebhookDelivery as ProtoWebhookDelivery,
+    WebhookEndpoint as ProtoWebhookEndpoint,
+};
+use crate::handler::interceptor::AuthContext;
+use crate::handler::pagination::{decode_cursor, encode_cursor, page_size, split_page};
+use crate::model::audit_log::AuditLogRepository;
+use crate::model::auth::Scope;
+use crate::model::webhook::{
+    WebhookDelivery, WebhookDeliveryStatus, WebhookEndpoint, WebhookEventType, WebhookRepository,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tonic::{Request, Response, Status};
+use tracing::{debug, error, instrument, warn};
+use uuid::Uuid;
+
+const DEFAULT_DELIVERY_PAGE_SIZE: i32 = 50;
+const MAX_DELIVERY_PAGE_SIZE: i32 = 200;
+const MAX_DESCRIPTION_LENGTH: usize = 200;
+/// Endpoints a user may register
+const MAX_ENDPOINTS_PER_USER: usize = 10;
+
+/// Keyset position of a ListWebhookDeliveries page, handed to clients as an opaque token
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct DeliveryPageToken {
+    created_at: DateTime<Utc>,
+    delivery_id: Uuid,
+}
+
+/// gRPC service managing a user's webhook endpoints and their delivery logs
+pub struct WebhooksHandler {
+    repository: WebhookRepository,
+    client: WebhookClient,
+    audit_log: AuditLogRepository,
+}
+
+impl WebhooksHandler {
+    pub fn new(repository: WebhookRepository, client: WebhookClient, audit_log: AuditLogRepository) -> Self {
+        Self {
+            repository,
+            client,
+            audit_log,
+        }
+    }
+
+    fn endpoint_to_proto(endpoint: &WebhookEndpoint) -> ProtoWebhookEndpoint {
+        ProtoWebhookEndpoint {
+            endpoint_id: endpoint.id.to_string(),
+            url: endpoint.url.clone(),
+            description: endpoint.description.clone(),
+            event_types: endpoint.event_types.clone(),
+            enabled: endpoint.enabled,
+            created_at: endpoint.created_at.timestamp(),
+        }
+    }
+
+    fn delivery_to_proto(delivery: &WebhookDelivery) -> ProtoWebhookDelivery {
+        let pending = delivery.status() == Some(WebhookDeliveryStatus::Pending);
+        ProtoWebhookDelivery {
+            delivery_id: delivery.id.to_string(),
+            event_id: delivery.event_id.to_string(),
+            event_type: delivery.event_type.clone(),
+            status: delivery.status.clone(),
+            attempts: delivery.attempts,
+            response_status: delivery.response_status,
+            last_error: delivery.last_error.clone(),
+            duration_ms: delivery.duration_ms,
+            created_at: delivery.created_at.timestamp(),
+            next_attempt_at: pending.then(|| delivery.next_attempt_at.timestamp()),
+            delivered_at: delivery.delivered_at.map(|t| t.timestamp()),
+        }
+    }
+
+    fn parse_endpoint_id(endpoint_id: &str) -> Result<Uuid, AppError> {
+        Uuid::parse_str(endpoint_id).map_err(|_| AppError::validation("endpoint_id must be a UUID"))
+    }
+
+    /// Known event types, without repeats; at least one is required
+    fn validate_event_types(event_types: &[String]) -> Result<Vec<WebhookEventType>, AppError> {
+        let mut parsed = Vec::new();
+        for event_type in event_types {
+            let event_type = WebhookEventType::parse(event_type.trim()).ok_or_else(|| {
+                let known: Vec<&str> = WebhookEventType::ALL.iter().map(WebhookEventType::as_str).collect();
+                AppError::validation(format!(
+                    "Unknown event type '{}'; expected one of: {}",
+                    event_type,
+                    known.join(", ")
+                ))
+            })?;
+            if !parsed.contains(&event_type) {
+                parsed.push(event_type);
+            }
+        }
+        if parsed.is_empty() {
+            return Err(AppError::validation("event_types must not be empty"));
+        }
+        Ok(parsed)
+    }
+
+    fn validate_description(description: &str) -> Result<String, AppError> {
+        let description = description.trim();
+        if description.chars().count() > MAX_DESCRIPTION_LENGTH {
+            return Err(AppError::validation(format!(
+                "description must be at most {} characters",
+                MAX_DESCRIPTION_LENGTH
+            )));
+        }
+        Ok(description.to_string())
+    }
+
+    async fn owned_endpoint(&self, user_id: Uuid, endpoint_id: &str) -> Result<WebhookEndpoint, AppError> {
+        let endpoint_id = Self::parse_endpoint_id(endpoint_id)?;
+        self.repository
+            .find_endpoint(user_id, endpoint_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to load webhook endpoint: {:?}", e);
+                AppError::internal("Failed to load webhook endpoint")
+            })?
+            .ok_or_else(|| AppError::not_found("Webhook endpoint not found"))
+    }
+
+    /// Endpoint changes are audited; the change has happened either way, so failures are only logged
+    async fn audit(&self, user_id: Uuid, action: &str, endpoint: &WebhookEndpoint) {
+        let metadata = json!({
+            "url": endpoint.url,
+            "event_types": endpoint.event_types,
+        });
+        if let Err(e) = self
+            .audit_log
+            .record(user_id, action, "webhook_endpoint", &endpoint.id.to_string(), metadata)
+            .await
+        {
+            warn!(endpoint_id = %endpoint.id, error = %e, "Failed to audit webhook endpoint change");
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl WebhooksService for WebhooksHandler {
+    #[instrument(skip(self, request))]
+    async fn create_webhook_endpoint(
+        &self,
+        request: Request<CreateWebhookEndpointRequest>,
+    ) -> Result<Response<CreateWebhookEndpointResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsWrite)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+        debug!(user_id = %user_id, "Registering webhook endpoint");
+
+        let url = self
+            .client
+            .validate_url(req.url.trim())
+            .await
+            .map_err(|e| AppError::validation(e.to_string()))?;
+        let event_types = Self::validate_event_types(&req.event_types)?;
+        let description = Self::validate_description(&req.description)?;
+
+        let existing = self.repository.list_endpoints(user_id).await.map_err(|e| {
+            error!("Failed to list webhook endpoints: {:?}", e);
+            AppError::internal("Failed to register webhook endpoint")
+        })?;
+        if existing.len() >= MAX_ENDPOINTS_PER_USER {
+            return Err(AppError::validation(format!(
+                "At most {} webhook endpoints can be registered",
+                MAX_ENDPOINTS_PER_USER
+            ))
+            .into());
+        }
+
+        let secret = generate_secret();
+        let endpoint = self
+            .repository
+            .create_endpoint(user_id, url.as_str(), &description, &event_types, &secret)
+            .await
+            .map_err(|e| {
+                error!("Failed to register webhook endpoint: {:?}", e);
+                AppError::internal("Failed to register webhook endpoint")
+            })?;
+        self.audit(user_id, "webhook_endpoint.created", &endpoint).await;
+
+        Ok(Response::new(CreateWebhookEndpointResponse {
+            endpoint: Some(Self::endpoint_to_proto(&endpoint)),
+            secret,
+        }))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn list_webhook_endpoints(
+        &self,
+        request: Request<ListWebhookEndpointsRequest>,
+    ) -> Result<Response<ListWebhookEndpointsResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsRead)?;
+        let user_id = auth.user_id;
+
+        let endpoints = self.repository.list_endpoints(user_id).await.map_err(|e| {
+            error!("Failed to list webhook endpoints: {:?}", e);
+            AppError::internal("Failed to list webhook endpoints")
+        })?;
+
+        Ok(Response::new(ListWebhookEndpointsResponse {
+            endpoints: endpoints.iter().map(Self::endpoint_to_proto).collect(),
+        }))
+    }
+
+    #[instrument(skip(self, request), fields(endpoint_id = %request.get_ref().endpoint_id))]
+    async fn delete_webhook_endpoint(
+        &self,
+        request: Request<DeleteWebhookEndpointRequest>,
+    ) -> Result<Response<DeleteWebhookEndpointResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsWrite)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+
+        let endpoint = self.owned_endpoint(user_id, &req.endpoint_id).await?;
+        let deleted = self
+            .repository
+            .delete_endpoint(user_id, endpoint.id)
+            .await
+            .map_err(|e| {
+                error!("Failed to delete webhook endpoint: {:?}", e);
+                AppError::internal("Failed to delete webhook endpoint")
+            })?;
+        if !deleted {
+            return Err(AppError::not_found("Webhook endpoint not found").into());
+        }
+        self.audit(user_id, "webhook_endpoint.deleted", &endpoint).await;
+
+        Ok(Response::new(DeleteWebhookEndpointResponse {}))
+    }
+
+    #[instrument(skip(self, request), fields(endpoint_id = %request.get_ref().endpoint_id))]
+    async fn rotate_webhook_secret(
+        &self,
+        request: Request<RotateWebhookSecretRequest>,
+    ) -> Result<Response<RotateWebhookSecretResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsWrite)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+
+        let endpoint = self.owned_endpoint(user_id, &req.endpoint_id).await?;
+        let secret = generate_secret();
+        let rotated = self
+            .repository
+            .rotate_secret(user_id, endpoint.id, &secret)
+            .await
+            .map_err(|e| {
+                error!("Failed to rotate webhook secret: {:?}", e);
+                AppError::internal("Failed to rotate webhook secret")
+            })?;
+        if !rotated {
+            return Err(AppError::not_found("Webhook endpoint not found").into());
+        }
+        self.audit(user_id, "webhook_endpoint.secret_rotated", &endpoint).await;
+
+        Ok(Response::new(RotateWebhookSecretResponse { secret }))
+    }
+
+    #[instrument(skip(self, request), fields(endpoint_id = %request.get_ref().endpoint_id))]
+    async fn list_webhook_deliveries(
+        &self,
+        request: Request<ListWebhookDeliveriesRequest>,
+    ) -> Result<Response<ListWebhookDeliveriesResponse>, Status> {
+        let auth = AuthContext::from_request(&request)?;
+        auth.require_scope(Scope::AccountsRead)?;
+        let user_id = auth.user_id;
+        let req = request.into_inner();
+
+        let page_size = page_size(req.page_size, DEFAULT_DELIVERY_PAGE_SIZE, MAX_DELIVERY_PAGE_SIZE);
+        let after: Option<DeliveryPageToken> = if req.page_token.is_empty() {
+            None
+        } else {
+            Some(decode_cursor(&req.page_token, "page token")?)
+        };
+        let endpoint = self.owned_endpoint(user_id, &req.endpoint_id).await?;
+
+        let deliveries = self
+            .repository
+            .list_deliveries(
+                user_id,
+                endpoint.id,
+                after.map(|t| (t.created_at, t.delivery_id)),
+                page_size as i64 + 1,
+            )
+            .await
+            .map_err(|e| {
+                error!("Failed to list webhook deliveries: {:?}", e);
+                AppError::internal("Failed to list webhook deliveries")
+            })?;
+        let (deliveries, has_more) = split_page(deliveries, page_size as usize);
+
+        let next_page_token = match deliveries.last() {
+            Some(last) if has_more => encode_cursor(&DeliveryPageToken {
+                created_at: last.created_at,
+                delivery_id: last.id,
+            }),
+            _ => String::new(),
+        };
+
+        Ok(Response::new(ListWebhookDeliveriesResponse {
+            deliveries: deliveries.iter().map(Self::delivery_to_proto).collect(),
+            next_page_token,
+        }))
+    }
+}
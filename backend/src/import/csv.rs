@@ -0,0 +1,232 @@
+use crate::import::{parse_amount, ImportError, ImportParse, ParsedTransaction};
+use anyhow::{bail, Result};
+use chrono::NaiveDate;
+
+/// How the columns of a CSV export map onto transaction fields.
+///
+/// Columns are referenced by their header name, compared case-insensitively.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvMapping {
+    pub date_column: String,
+    /// `chrono` format of the date column, e.g. `%m/%d/%Y`
+    pub date_format: String,
+    pub description_column: String,
+    /// Signed amount; leave empty when the file splits amounts into debit and credit columns
+    pub amount_column: Option<String>,
+    /// Outflow amounts, used when there is no amount column
+    pub debit_column: Option<String>,
+    /// Inflow amounts, used when there is no amount column
+    pub credit_column: Option<String>,
+    pub merchant_column: Option<String>,
+    pub currency_column: Option<String>,
+    /// The amount column records outflows as negative numbers (most bank exports do)
+    pub outflows_negative: bool,
+    pub delimiter: char,
+}
+
+impl Default for CsvMapping {
+    fn default() -> Self {
+        Self {
+            date_column: "Date".to_string(),
+            date_format: "%Y-%m-%d".to_string(),
+            description_column: "Description".to_string(),
+            amount_column: Some("Amount".to_string()),
+            debit_column: None,
+            credit_column: None,
+            merchant_column: None,
+            currency_column: None,
+            outflows_negative: true,
+            delimiter: ',',
+        }
+    }
+}
+
+/// CSV record with the line it starts on
+struct Record {
+    line: usize,
+    fields: Vec<String>,
+}
+
+/// Split CSV text into records, honouring quoted fields with embedded delimiters, quotes and newlines
+fn records(data: &str, delimiter: char) -> Vec<Record> {
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut chars = data.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if in_quotes => in_quotes = false,
+            '"' if field.is_empty() => in_quotes = true,
+            '\n' if !in_quotes => {
+                fields.push(std::mem::take(&mut field));
+                records.push(Record {
+                    line: record_line,
+                    fields: std::mem::take(&mut fields),
+                });
+                line += 1;
+                record_line = line;
+            }
+            '\r' if !in_quotes && chars.peek() == Some(&'\n') => {}
+            c if c == delimiter && !in_quotes => fields.push(std::mem::take(&mut field)),
+            c => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+        }
+    }
+    if !field.is_empty() || !fields.is_empty() {
+        fields.push(field);
+        records.push(Record { line: record_line, fields });
+    }
+
+    records
+        .into_iter()
+        .filter(|r| r.fields.iter().any(|f| !f.trim().is_empty()))
+        .collect()
+}
+
+/// Parse a CSV export whose first non-empty record is the header
+pub fn parse(data: &str, mapping: &CsvMapping) -> Result<ImportParse> {
+    let data = data.trim_start_matches('\u{feff}');
+    let mut records = records(data, mapping.delimiter).into_iter();
+    let Some(header) = records.next() else {
+        bail!("CSV file is empty");
+    };
+
+    let column = |name: &str| -> Result<usize> {
+        match header.fields.iter().position(|h| h.trim().eq_ignore_ascii_case(name.trim())) {
+            Some(index) => Ok(index),
+            None => bail!("CSV header has no '{}' column", name),
+        }
+    };
+    let optional_column = |name: &Option<String>| -> Result<Option<usize>> {
+        name.as_deref().filter(|n| !n.is_empty()).map(|n| column(n)).transpose()
+    };
+
+    let date_column = column(&mapping.date_column)?;
+    let description_column = column(&mapping.description_column)?;
+    let amount_column = optional_column(&mapping.amount_column)?;
+    let debit_column = optional_column(&mapping.debit_column)?;
+    let credit_column = optional_column(&mapping.credit_column)?;
+    let merchant_column = optional_column(&mapping.merchant_column)?;
+    let currency_column = optional_column(&mapping.currency_column)?;
+    if amount_column.is_none() && debit_column.is_none() && credit_column.is_none() {
+        bail!("Column mapping needs an amount column or debit/credit columns");
+    }
+
+    let mut parsed = ImportParse::default();
+    for record in records {
+        let field = |index: usize| record.fields.get(index).map(|f| f.trim()).unwrap_or("");
+        let optional_field = |index: Option<usize>| index.map(field).filter(|f| !f.is_empty()).map(str::to_string);
+        let error = |message: String| ImportError { line: record.line, message };
+
+        let date = match NaiveDate::parse_from_str(field(date_column), &mapping.date_format) {
+            Ok(date) => date,
+            Err(_) => {
+                parsed.errors.push(error(format!(
+                    "Date '{}' does not match format '{}'",
+                    field(date_column),
+                    mapping.date_format
+                )));
+                continue;
+            }
+        };
+
+        // Plaid convention: positive amounts are money leaving the account
+        let amount = match amount_column {
+            Some(index) => parse_amount(field(index)).map(|a| if mapping.outflows_negative { -a } else { a }),
+            None => {
+                let debit = debit_column.and_then(|i| parse_amount(field(i)));
+                let credit = credit_column.and_then(|i| parse_amount(field(i)));
+                match (debit, credit) {
+                    (None, None) => None,
+                    (debit, credit) => Some(debit.unwrap_or(0.0).abs() - credit.unwrap_or(0.0).abs()),
+                }
+            }
+        };
+        let Some(amount) = amount else {
+            parsed.errors.push(error("Missing or invalid amount".to_string()));
+            continue;
+        };
+
+        let name = field(description_column);
+        if name.is_empty() {
+            parsed.errors.push(error("Missing description".to_string()));
+            continue;
+        }
+
+        parsed.transactions.push(ParsedTransaction {
+            line: record.line,
+            date,
+            amount,
+            name: name.to_string(),
+            merchant_name: optional_field(merchant_column),
+            iso_currency_code: optional_field(currency_column).map(|c| c.to_ascii_uppercase()),
+            external_id: None,
+        });
+    }
+
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_with_default_mapping() {
+        let data = "\u{feff}Date,Description,Amount\r\n\
+                    2024-03-01,\"Coffee, Blue Bottle\",-4.50\r\n\
+                    2024-03-02,Payroll,\"1,200.00\"\r\n\
+                    \r\n\
+                    03/04/2024,Bad date,-1.00\r\n\
+                    2024-03-05,No amount,\r\n";
+
+        let parsed = parse(data, &CsvMapping::default()).unwrap();
+        assert_eq!(parsed.transactions.len(), 2);
+        assert_eq!(parsed.transactions[0].name, "Coffee, Blue Bottle");
+        assert_eq!(parsed.transactions[0].amount, 4.5);
+        assert_eq!(parsed.transactions[1].amount, -1200.0);
+        assert_eq!(parsed.transactions[1].line, 3);
+
+        let lines: Vec<usize> = parsed.errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, vec![5, 6]);
+    }
+
+    #[test]
+    fn test_parse_debit_credit_columns() {
+        let mapping = CsvMapping {
+            date_column: "Posted".to_string(),
+            date_format: "%m/%d/%Y".to_string(),
+            description_column: "Memo".to_string(),
+            amount_column: None,
+            debit_column: Some("Debit".to_string()),
+            credit_column: Some("Credit".to_string()),
+            delimiter: ';',
+            ..Default::default()
+        };
+        let data = "posted;memo;debit;credit\n03/01/2024;\"Rent \"\"March\"\"\";1500.00;\n03/02/2024;Refund;;25.00\n";
+
+        let parsed = parse(data, &mapping).unwrap();
+        assert!(parsed.errors.is_empty());
+        assert_eq!(parsed.transactions[0].name, "Rent \"March\"");
+        assert_eq!(parsed.transactions[0].amount, 1500.0);
+        assert_eq!(parsed.transactions[1].amount, -25.0);
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_columns() {
+        assert!(parse("When,What,HowMuch\n", &CsvMapping::default()).is_err());
+        assert!(parse("", &CsvMapping::default()).is_err());
+    }
+}
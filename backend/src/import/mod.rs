@@ -0,0 +1,213 @@
+// Transaction file import
+pub mod csv;
+pub mod ofx;
+
+pub use self::csv::CsvMapping;
+
+use chrono::NaiveDate;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+/// Days two transactions with the same amount may be apart and still count as duplicates
+pub const DUPLICATE_DATE_TOLERANCE_DAYS: i64 = 2;
+
+/// Prefix of the IDs given to imported transactions, telling them apart from Plaid's
+pub const IMPORTED_ID_PREFIX: &str = "import_";
+
+/// Supported import file formats
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportFormat {
+    Csv(CsvMapping),
+    /// OFX 1.x (SGML) or 2.x (XML) bank statement
+    Ofx,
+}
+
+/// Transaction read from an import file, with Plaid's sign convention (positive amounts are outflows)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedTransaction {
+    /// 1-based line of the record in the file
+    pub line: usize,
+    pub date: NaiveDate,
+    pub amount: f64,
+    pub name: String,
+    pub merchant_name: Option<String>,
+    pub iso_currency_code: Option<String>,
+    /// Identifier assigned by the institution (OFX `FITID`), when the format has one
+    pub external_id: Option<String>,
+}
+
+/// Record that could not be parsed
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportError {
+    pub line: usize,
+    pub message: String,
+}
+
+/// Parsed records and per-record failures
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportParse {
+    pub transactions: Vec<ParsedTransaction>,
+    pub errors: Vec<ImportError>,
+}
+
+/// Parse an import file; malformed records are reported in `errors` rather than failing the import
+pub fn parse(format: &ImportFormat, data: &str) -> anyhow::Result<ImportParse> {
+    match format {
+        ImportFormat::Csv(mapping) => self::csv::parse(data, mapping),
+        ImportFormat::Ofx => ofx::parse(data),
+    }
+}
+
+/// Stable transaction IDs for parsed records, so importing the same file twice updates rather than duplicates.
+///
+/// Records without an institution ID are keyed by their contents; identical records in one
+/// file are told apart by their order of occurrence.
+pub fn transaction_ids(account_id: &str, transactions: &[ParsedTransaction]) -> Vec<String> {
+    let mut occurrences: HashMap<String, usize> = HashMap::new();
+
+    transactions
+        .iter()
+        .map(|transaction| {
+            let key = match &transaction.external_id {
+                Some(external_id) => format!("fitid|{}", external_id),
+                None => format!(
+                    "row|{}|{:.2}|{}",
+                    transaction.date,
+                    transaction.amount,
+                    transaction.name.trim().to_lowercase()
+                ),
+            };
+            let occurrence = occurrences.entry(key.clone()).or_default();
+            *occurrence += 1;
+
+            let digest = Sha256::digest(format!("{}|{}|{}", account_id, key, occurrence).as_bytes());
+            let hex: String = digest.iter().take(16).map(|b| format!("{:02x}", b)).collect();
+            format!("{}{}", IMPORTED_ID_PREFIX, hex)
+        })
+        .collect()
+}
+
+/// Stored transaction a parsed record may duplicate
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExistingTransaction {
+    pub transaction_id: String,
+    pub date: NaiveDate,
+    pub amount: f64,
+}
+
+/// For each parsed record, the stored transaction it duplicates, if any.
+///
+/// A record duplicates a stored transaction with the same ID, or else one on the same account
+/// with the same amount within `DUPLICATE_DATE_TOLERANCE_DAYS`; each stored transaction is
+/// matched at most once so repeated identical purchases are kept.
+pub fn detect_duplicates(
+    transactions: &[ParsedTransaction],
+    transaction_ids: &[String],
+    existing: &[ExistingTransaction],
+) -> Vec<Option<String>> {
+    let cents = |amount: f64| (amount * 100.0).round() as i64;
+    let mut used = vec![false; existing.len()];
+
+    // Exact ID matches first, so a re-import never pairs a record with some other transaction
+    let mut duplicates: Vec<Option<String>> = transaction_ids
+        .iter()
+        .map(|id| {
+            let index = existing.iter().position(|e| &e.transaction_id == id)?;
+            used[index] = true;
+            Some(id.clone())
+        })
+        .collect();
+
+    for (transaction, duplicate) in transactions.iter().zip(duplicates.iter_mut()) {
+        if duplicate.is_some() {
+            continue;
+        }
+        let candidate = existing
+            .iter()
+            .enumerate()
+            .filter(|(index, e)| {
+                !used[*index]
+                    && cents(e.amount) == cents(transaction.amount)
+                    && (e.date - transaction.date).num_days().abs() <= DUPLICATE_DATE_TOLERANCE_DAYS
+            })
+            .min_by_key(|(_, e)| (e.date - transaction.date).num_days().abs());
+        if let Some((index, e)) = candidate {
+            used[index] = true;
+            *duplicate = Some(e.transaction_id.clone());
+        }
+    }
+
+    duplicates
+}
+
+/// Parse a money amount, accepting currency symbols, thousands separators and `(1.00)` negatives
+pub(crate) fn parse_amount(value: &str) -> Option<f64> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    let negative = (value.starts_with('(') && value.ends_with(')')) || value.contains('-');
+    let digits: String = value.chars().filter(|c| c.is_ascii_digit() || *c == '.').collect();
+    let amount: f64 = digits.parse().ok()?;
+    Some(if negative { -amount } else { amount })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(line: usize, day: u32, amount: f64, name: &str) -> ParsedTransaction {
+        ParsedTransaction {
+            line,
+            date: NaiveDate::from_ymd_opt(2024, 3, day).unwrap(),
+            amount,
+            name: name.to_string(),
+            merchant_name: None,
+            iso_currency_code: None,
+            external_id: None,
+        }
+    }
+
+    #[test]
+    fn test_transaction_ids_are_stable_and_distinguish_repeats() {
+        let transactions = [parsed(2, 1, 4.5, "Coffee"), parsed(3, 1, 4.5, "coffee "), parsed(4, 2, 4.5, "Coffee")];
+        let ids = transaction_ids("acc_1", &transactions);
+        assert_eq!(ids, transaction_ids("acc_1", &transactions));
+        assert_ne!(ids[0], ids[1]);
+        assert_ne!(ids[0], ids[2]);
+        assert_ne!(ids[0], transaction_ids("acc_2", &transactions)[0]);
+        assert!(ids[0].starts_with("import_"));
+    }
+
+    #[test]
+    fn test_detect_duplicates() {
+        let transactions = [parsed(2, 1, 4.5, "Coffee"), parsed(3, 1, 4.5, "Coffee"), parsed(4, 10, 80.0, "Gas")];
+        let ids = vec!["import_a".to_string(), "import_b".to_string(), "import_c".to_string()];
+        let existing = [
+            ExistingTransaction {
+                transaction_id: "plaid_1".to_string(),
+                date: NaiveDate::from_ymd_opt(2024, 3, 2).unwrap(),
+                amount: 4.5,
+            },
+            ExistingTransaction {
+                transaction_id: "import_c".to_string(),
+                date: NaiveDate::from_ymd_opt(2024, 3, 10).unwrap(),
+                amount: 80.0,
+            },
+        ];
+
+        assert_eq!(
+            detect_duplicates(&transactions, &ids, &existing),
+            vec![Some("plaid_1".to_string()), None, Some("import_c".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_parse_amount() {
+        assert_eq!(parse_amount("$1,234.50"), Some(1234.5));
+        assert_eq!(parse_amount("-12.00"), Some(-12.0));
+        assert_eq!(parse_amount("(7.25)"), Some(-7.25));
+        assert_eq!(parse_amount(""), None);
+        assert_eq!(parse_amount("n/a"), None);
+    }
+}
@@ -0,0 +1,138 @@
+use crate::import::{parse_amount, ImportError, ImportParse, ParsedTransaction};
+use anyhow::{bail, Result};
+use chrono::NaiveDate;
+
+/// Value of the first `<TAG>` in `block`.
+///
+/// OFX 1.x is SGML and leaves leaf elements unclosed, so the value runs to the next tag;
+/// OFX 2.x closes them, which ends the value at the same place.
+fn tag_value(block: &str, upper: &str, tag: &str) -> Option<String> {
+    let open = format!("<{}>", tag);
+    let start = upper.find(&open)? + open.len();
+    let rest = &block[start..];
+    let end = rest.find('<').unwrap_or(rest.len());
+    let value = decode_entities(rest[..end].trim());
+    if value.is_empty() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+fn decode_entities(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Parse the `YYYYMMDD` prefix of an OFX datetime such as `20240301120000.000[-5:EST]`
+fn parse_ofx_date(value: &str) -> Option<NaiveDate> {
+    let digits = value.get(..8)?;
+    NaiveDate::parse_from_str(digits, "%Y%m%d").ok()
+}
+
+/// Parse the `STMTTRN` records of an OFX bank or credit card statement
+pub fn parse(data: &str) -> Result<ImportParse> {
+    // ASCII upper-casing keeps byte offsets identical, so positions found in `upper` index `data`
+    let upper = data.to_ascii_uppercase();
+    if !upper.contains("<OFX>") {
+        bail!("File is not an OFX statement");
+    }
+    let currency = tag_value(data, &upper, "CURDEF").map(|c| c.to_ascii_uppercase());
+
+    let mut parsed = ImportParse::default();
+    let mut offset = 0;
+    while let Some(found) = upper[offset..].find("<STMTTRN>") {
+        let start = offset + found;
+        let end = upper[start..]
+            .find("</STMTTRN>")
+            .or_else(|| upper[start + 1..].find("<STMTTRN>").map(|next| next + 1))
+            .map(|len| start + len)
+            .unwrap_or(upper.len());
+        offset = end;
+
+        let block = &data[start..end];
+        let block_upper = &upper[start..end];
+        let line = data[..start].matches('\n').count() + 1;
+        let error = |message: &str| ImportError { line, message: message.to_string() };
+
+        let Some(date) = tag_value(block, block_upper, "DTPOSTED").as_deref().and_then(parse_ofx_date) else {
+            parsed.errors.push(error("Missing or invalid DTPOSTED"));
+            continue;
+        };
+        let Some(amount) = tag_value(block, block_upper, "TRNAMT").as_deref().and_then(parse_amount) else {
+            parsed.errors.push(error("Missing or invalid TRNAMT"));
+            continue;
+        };
+        let name = tag_value(block, block_upper, "NAME")
+            .or_else(|| tag_value(block, block_upper, "PAYEE"))
+            .or_else(|| tag_value(block, block_upper, "MEMO"));
+        let Some(name) = name else {
+            parsed.errors.push(error("Missing NAME or MEMO"));
+            continue;
+        };
+
+        parsed.transactions.push(ParsedTransaction {
+            line,
+            date,
+            // OFX amounts are signed from the account's view: debits are negative
+            amount: -amount,
+            name,
+            merchant_name: None,
+            iso_currency_code: currency.clone(),
+            external_id: tag_value(block, block_upper, "FITID"),
+        });
+    }
+
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sgml_statement() {
+        let data = "OFXHEADER:100\nDATA:OFXSGML\n\n<OFX>\n<BANKMSGSRSV1><STMTTRNRS><STMTRS>\n<CURDEF>usd\n<BANKTRANLIST>\n\
+                    <STMTTRN>\n<TRNTYPE>DEBIT\n<DTPOSTED>20240301120000.000[-5:EST]\n<TRNAMT>-4.50\n<FITID>2024030101\n<NAME>BLUE BOTTLE &amp; CO\n\
+                    <STMTTRN>\n<TRNTYPE>CREDIT\n<DTPOSTED>20240302\n<TRNAMT>1200.00\n<FITID>2024030201\n<MEMO>Payroll\n\
+                    <STMTTRN>\n<TRNTYPE>DEBIT\n<TRNAMT>-1.00\n<NAME>No date\n\
+                    </BANKTRANLIST></STMTRS></STMTTRNRS></BANKMSGSRSV1></OFX>\n";
+
+        let parsed = parse(data).unwrap();
+        assert_eq!(parsed.transactions.len(), 2);
+
+        let coffee = &parsed.transactions[0];
+        assert_eq!(coffee.date, NaiveDate::from_ymd_opt(2024, 3, 1).unwrap());
+        assert_eq!(coffee.amount, 4.5);
+        assert_eq!(coffee.name, "BLUE BOTTLE & CO");
+        assert_eq!(coffee.external_id.as_deref(), Some("2024030101"));
+        assert_eq!(coffee.iso_currency_code.as_deref(), Some("USD"));
+        assert_eq!(parsed.transactions[1].amount, -1200.0);
+        assert_eq!(parsed.transactions[1].name, "Payroll");
+
+        assert_eq!(parsed.errors.len(), 1);
+        assert_eq!(parsed.errors[0].line, 20);
+    }
+
+    #[test]
+    fn test_parse_xml_statement() {
+        let data = r#"<?xml version="1.0"?><OFX><CREDITCARDMSGSRSV1><CCSTMTTRNRS><CCSTMTRS><CURDEF>EUR</CURDEF>
+<BANKTRANLIST><STMTTRN><TRNTYPE>DEBIT</TRNTYPE><DTPOSTED>20240305</DTPOSTED><TRNAMT>-20.00</TRNAMT><FITID>A1</FITID><NAME>Train</NAME></STMTTRN></BANKTRANLIST>
+</CCSTMTRS></CCSTMTTRNRS></CREDITCARDMSGSRSV1></OFX>"#;
+
+        let parsed = parse(data).unwrap();
+        assert!(parsed.errors.is_empty());
+        assert_eq!(parsed.transactions[0].amount, 20.0);
+        assert_eq!(parsed.transactions[0].line, 2);
+        assert_eq!(parsed.transactions[0].iso_currency_code.as_deref(), Some("EUR"));
+    }
+
+    #[test]
+    fn test_rejects_non_ofx() {
+        assert!(parse("Date,Description,Amount\n").is_err());
+    }
+}
@@ -0,0 +1,329 @@
+use crate::adapter::claude_ai::ClaudeStatusError;
+use crate::jobs::categorization::TransactionCategorizer;
+use crate::jobs::scheduler::Job;
+use crate::model::ai_batch::{AiBatchKind, AiBatchRepository, ClaimedTask};
+use crate::model::ai_usage::{AiFeature, AiUsageRepository, NewAiUsage};
+use crate::model::transaction::TransactionRepository;
+use anyhow::Result;
+use futures::stream::{self, StreamExt};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use tracing::{debug, info, instrument, warn};
+use uuid::Uuid;
+
+/// Seconds a claimed task may run before another worker may claim it again
+const TASK_LEASE_SECONDS: i64 = 600;
+
+/// Spaces out requests to the model so a run stays under a requests-per-minute limit
+pub struct RequestPacer {
+    interval: Duration,
+    next: Mutex<Instant>,
+}
+
+impl RequestPacer {
+    pub fn new(requests_per_minute: u32) -> Self {
+        Self {
+            interval: pacing_interval(requests_per_minute),
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Wait for the next request slot
+    pub async fn wait(&self) {
+        let slot = {
+            let mut next = self.next.lock().await;
+            let slot = (*next).max(Instant::now());
+            *next = slot + self.interval;
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+    }
+}
+
+/// Minimum time between requests; zero means unpaced
+fn pacing_interval(requests_per_minute: u32) -> Duration {
+    if requests_per_minute == 0 {
+        Duration::ZERO
+    } else {
+        Duration::from_secs(60) / requests_per_minute
+    }
+}
+
+/// Group claimed tasks into chunks sent to the model together; a chunk belongs to one user and kind
+fn chunk_tasks(tasks: Vec<ClaimedTask>, chunk_size: usize) -> Vec<Vec<ClaimedTask>> {
+    let mut groups: BTreeMap<(Uuid, String), Vec<ClaimedTask>> = BTreeMap::new();
+    for task in tasks {
+        groups.entry((task.user_id, task.kind.clone())).or_default().push(task);
+    }
+    groups
+        .into_values()
+        .flat_map(|group| {
+            group
+                .chunks(chunk_size.max(1))
+                .map(<[ClaimedTask]>::to_vec)
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Settings of the batch processor
+#[derive(Debug, Clone)]
+pub struct AiBatchSettings {
+    /// Tasks claimed per run
+    pub tasks_per_run: i64,
+    /// Tasks sent to the model per request
+    pub chunk_size: usize,
+    /// Requests in flight at once
+    pub concurrency: usize,
+    /// Requests started per minute; 0 for no limit
+    pub requests_per_minute: u32,
+    /// Attempts before a task fails
+    pub max_attempts: i32,
+}
+
+/// Scheduled job working through queued AI batch tasks with bounded concurrency.
+///
+/// When the provider rate limits a request, its tasks and any not yet started are put back in the
+/// queue without using up an attempt, and the rest of the run is skipped.
+pub struct AiBatchProcessor {
+    batches: AiBatchRepository,
+    transactions: TransactionRepository,
+    categorizer: Arc<TransactionCategorizer>,
+    ai_usage: AiUsageRepository,
+    settings: AiBatchSettings,
+    pacer: RequestPacer,
+}
+
+impl AiBatchProcessor {
+    pub fn new(
+        batches: AiBatchRepository,
+        transactions: TransactionRepository,
+        categorizer: Arc<TransactionCategorizer>,
+        ai_usage: AiUsageRepository,
+        settings: AiBatchSettings,
+    ) -> Self {
+        Self {
+            pacer: RequestPacer::new(settings.requests_per_minute),
+            batches,
+            transactions,
+            categorizer,
+            ai_usage,
+            settings,
+        }
+    }
+
+    /// Claim and process one round of tasks; returns how many were claimed
+    pub async fn run_once(&self) -> Result<usize> {
+        let abandoned = self
+            .batches
+            .fail_abandoned(TASK_LEASE_SECONDS, self.settings.max_attempts)
+            .await?;
+        let tasks = self
+            .batches
+            .claim(
+                self.settings.tasks_per_run,
+                TASK_LEASE_SECONDS,
+                self.settings.max_attempts,
+            )
+            .await?;
+        let claimed = tasks.len();
+
+        let mut batch_ids: HashSet<Uuid> = abandoned.into_iter().collect();
+        batch_ids.extend(tasks.iter().map(|t| t.batch_id));
+
+        let rate_limited = AtomicBool::new(false);
+        stream::iter(chunk_tasks(tasks, self.settings.chunk_size))
+            .map(|chunk| {
+                let rate_limited = &rate_limited;
+                async move { self.process_chunk(chunk, rate_limited).await }
+            })
+            .buffer_unordered(self.settings.concurrency.max(1))
+            .collect::<Vec<()>>()
+            .await;
+
+        if !batch_ids.is_empty() {
+            let batch_ids: Vec<Uuid> = batch_ids.into_iter().collect();
+            self.batches.refresh(&batch_ids).await?;
+        }
+        if rate_limited.load(Ordering::Relaxed) {
+            warn!("AI batch processing paused by provider rate limits");
+        }
+        Ok(claimed)
+    }
+
+    /// Run one chunk; errors are recorded on its tasks rather than failing the run
+    async fn process_chunk(&self, chunk: Vec<ClaimedTask>, rate_limited: &AtomicBool) {
+        if !rate_limited.load(Ordering::Relaxed) {
+            self.pacer.wait().await;
+        }
+        // Checked again after waiting, as another chunk may have hit the limit meanwhile
+        if rate_limited.load(Ordering::Relaxed) {
+            self.requeue(&chunk, "Paused by rate limits", true).await;
+            return;
+        }
+
+        let result = match chunk.first().and_then(|t| AiBatchKind::parse(&t.kind)) {
+            Some(AiBatchKind::Categorization) => self.categorize(&chunk).await,
+            None => {
+                self.fail(&chunk, "Unknown task kind").await;
+                return;
+            }
+        };
+        if let Err(e) = result {
+            if ClaudeStatusError::find(&e).is_some_and(ClaudeStatusError::is_rate_limited) {
+                rate_limited.store(true, Ordering::Relaxed);
+                self.requeue(&chunk, "Rate limited", true).await;
+            } else {
+                warn!(error = ?e, tasks = chunk.len(), "AI batch chunk failed");
+                self.retry_or_fail(&chunk, &e.to_string()).await;
+            }
+        }
+    }
+
+    /// Categorize the transactions of one user's chunk and store the results
+    #[instrument(skip(self, chunk), fields(tasks = chunk.len()))]
+    async fn categorize(&self, chunk: &[ClaimedTask]) -> Result<()> {
+        let user_id = chunk[0].user_id;
+        let ids: Vec<String> = chunk.iter().map(|t| t.input.clone()).collect();
+        let transactions = self.transactions.find_by_transaction_ids(user_id, &ids).await?;
+
+        let (found, missing): (Vec<&ClaimedTask>, Vec<&ClaimedTask>) = chunk
+            .iter()
+            .partition(|t| transactions.iter().any(|tx| tx.transaction_id == t.input));
+        for task in missing {
+            self.fail_task(task, "Transaction not found").await;
+        }
+        if found.is_empty() {
+            return Ok(());
+        }
+
+        let categorized = self.categorizer.categorize(&transactions).await?;
+        self.categorizer.store(&categorized).await?;
+        if let Err(e) = self
+            .ai_usage
+            .record(&NewAiUsage {
+                user_id,
+                feature: AiFeature::BatchCategorization,
+                provider: "claude",
+                model: &categorized.model,
+                input_tokens: categorized.usage.input_tokens,
+                output_tokens: categorized.usage.output_tokens,
+                cache_read_tokens: categorized.usage.cache_read_input_tokens,
+                cache_write_tokens: categorized.usage.cache_creation_input_tokens,
+            })
+            .await
+        {
+            warn!(error = ?e, "Failed to record AI batch usage");
+        }
+
+        for task in found {
+            let assignment = categorized.assignments.iter().find(|a| a.transaction_id == task.input);
+            match assignment {
+                Some(assignment) => {
+                    let output = serde_json::json!({
+                        "category": assignment.category,
+                        "confidence": assignment.confidence,
+                    });
+                    if let Err(e) = self.batches.succeed(task.id, &output).await {
+                        warn!(task_id = task.id, error = ?e, "Failed to store AI batch task result");
+                    }
+                }
+                None => self.retry_or_fail(std::slice::from_ref(task), "Not categorized").await,
+            }
+        }
+        Ok(())
+    }
+
+    /// Requeue tasks with attempts left and fail the others
+    async fn retry_or_fail(&self, tasks: &[ClaimedTask], error: &str) {
+        let (retry, exhausted): (Vec<ClaimedTask>, Vec<ClaimedTask>) = tasks
+            .iter()
+            .cloned()
+            .partition(|t| t.attempts < self.settings.max_attempts);
+        self.requeue(&retry, error, false).await;
+        self.fail(&exhausted, error).await;
+    }
+
+    async fn requeue(&self, tasks: &[ClaimedTask], error: &str, refund_attempt: bool) {
+        if tasks.is_empty() {
+            return;
+        }
+        let ids: Vec<i64> = tasks.iter().map(|t| t.id).collect();
+        if let Err(e) = self.batches.requeue(&ids, error, refund_attempt).await {
+            warn!(error = ?e, "Failed to requeue AI batch tasks; they are retried once their lease expires");
+        }
+    }
+
+    async fn fail(&self, tasks: &[ClaimedTask], error: &str) {
+        for task in tasks {
+            self.fail_task(task, error).await;
+        }
+    }
+
+    async fn fail_task(&self, task: &ClaimedTask, error: &str) {
+        if let Err(e) = self.batches.fail(task.id, error).await {
+            warn!(task_id = task.id, error = ?e, "Failed to mark AI batch task failed");
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Job for AiBatchProcessor {
+    fn name(&self) -> &'static str {
+        "ai_batch_processor"
+    }
+
+    async fn run(&self) -> Result<()> {
+        let claimed = self.run_once().await?;
+        if claimed > 0 {
+            info!(claimed, "AI batch tasks processed");
+        } else {
+            debug!("No queued AI batch tasks");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: i64, user_id: Uuid) -> ClaimedTask {
+        ClaimedTask {
+            id,
+            batch_id: Uuid::nil(),
+            user_id,
+            kind: "categorization".to_string(),
+            input: format!("txn-{}", id),
+            attempts: 1,
+        }
+    }
+
+    #[test]
+    fn test_pacing_interval() {
+        assert_eq!(pacing_interval(60), Duration::from_secs(1));
+        assert_eq!(pacing_interval(120), Duration::from_millis(500));
+        assert_eq!(pacing_interval(0), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_chunk_tasks_by_user() {
+        let alice = Uuid::from_u128(1);
+        let bob = Uuid::from_u128(2);
+        let tasks = vec![
+            task(1, alice),
+            task(2, bob),
+            task(3, alice),
+            task(4, alice),
+            task(5, bob),
+        ];
+        let chunks = chunk_tasks(tasks, 2);
+        let ids: Vec<Vec<i64>> = chunks.iter().map(|c| c.iter().map(|t| t.id).collect()).collect();
+        assert_eq!(ids, vec![vec![1, 3], vec![4], vec![2, 5]]);
+        assert!(chunks.iter().all(|c| c.iter().all(|t| t.user_id == c[0].user_id)));
+    }
+}
@@ -0,0 +1,139 @@
+use crate::adapter::alerting::{Alert, AlertSeverity, AlertSink};
+use crate::jobs::scheduler::Job;
+use crate::model::ai_usage::{quota_period, AiFeature, AiUsageRepository, BudgetStatus};
+use anyhow::Result;
+use chrono::Utc;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use tracing::{debug, warn};
+
+/// Alert for a feature whose spending passed its warning threshold or budget
+fn budget_alert(environment: &str, feature: AiFeature, status: &BudgetStatus) -> Option<Alert> {
+    let (severity, level, spent_usd, budget_usd) = match *status {
+        BudgetStatus::Within => return None,
+        BudgetStatus::Warning { spent_usd, budget_usd } => (AlertSeverity::Ticket, "warning", spent_usd, budget_usd),
+        BudgetStatus::Exceeded { spent_usd, budget_usd } => (AlertSeverity::Page, "exceeded", spent_usd, budget_usd),
+    };
+    let summary = match status {
+        BudgetStatus::Exceeded { .. } => format!("AI budget for {} exhausted in {}", feature.as_str(), environment),
+        _ => format!("AI budget for {} nearly used in {}", feature.as_str(), environment),
+    };
+    Some(Alert {
+        key: format!("ai_budget:{}:{}:{}", environment, feature.as_str(), level),
+        severity,
+        summary,
+        details: format!(
+            "Spent ${:.2} of the ${:.2} monthly budget ({:.0}%). Requests are refused once the budget is reached.",
+            spent_usd,
+            budget_usd,
+            spent_usd / budget_usd * 100.0
+        ),
+        resolved: false,
+    })
+}
+
+/// Scheduled job raising alerts as features approach and reach their monthly AI budgets, and
+/// resolving them when a new month starts
+pub struct AiBudgetMonitor {
+    ai_usage: AiUsageRepository,
+    sink: Arc<dyn AlertSink>,
+    /// Alerts currently firing, resent as resolved once their status clears
+    firing: Mutex<Vec<Alert>>,
+}
+
+impl AiBudgetMonitor {
+    pub fn new(ai_usage: AiUsageRepository, sink: Arc<dyn AlertSink>) -> Self {
+        Self {
+            ai_usage,
+            sink,
+            firing: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Alerts whose state changed since the previous evaluation
+    fn transitions(&self, statuses: &[(AiFeature, BudgetStatus)]) -> Vec<Alert> {
+        let Ok(mut firing) = self.firing.lock() else {
+            return Vec::new();
+        };
+        let environment = self.ai_usage.environment();
+        let current: Vec<Alert> = statuses
+            .iter()
+            .filter_map(|(feature, status)| budget_alert(environment, *feature, status))
+            .collect();
+        let current_keys: HashSet<&str> = current.iter().map(|a| a.key.as_str()).collect();
+        let firing_keys: HashSet<String> = firing.iter().map(|a| a.key.clone()).collect();
+
+        let mut alerts: Vec<Alert> = firing
+            .iter()
+            .filter(|a| !current_keys.contains(a.key.as_str()))
+            .map(|a| Alert {
+                resolved: true,
+                ..a.clone()
+            })
+            .collect();
+        alerts.extend(current.iter().filter(|a| !firing_keys.contains(&a.key)).cloned());
+        *firing = current;
+        alerts
+    }
+}
+
+#[async_trait::async_trait]
+impl Job for AiBudgetMonitor {
+    fn name(&self) -> &'static str {
+        "ai_budget_monitor"
+    }
+
+    async fn run(&self) -> Result<()> {
+        let budgets = self.ai_usage.budgets();
+        if budgets.is_empty() {
+            return Ok(());
+        }
+        let (start, _) = quota_period(Utc::now());
+        let usage = self.ai_usage.environment_usage_by_feature(start).await?;
+        let statuses: Vec<(AiFeature, BudgetStatus)> = AiFeature::ALL
+            .into_iter()
+            .map(|feature| {
+                let spent_usd = usage
+                    .iter()
+                    .find(|u| u.feature == feature.as_str())
+                    .map_or(0.0, |u| u.totals.cost_usd);
+                (feature, budgets.status(feature, spent_usd))
+            })
+            .collect();
+
+        let alerts = self.transitions(&statuses);
+        debug!(alerts = alerts.len(), "Evaluated AI feature budgets");
+        for alert in &alerts {
+            if let Err(e) = self.sink.send(alert).await {
+                warn!(alert_key = %alert.key, error = ?e, "Failed to deliver alert");
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_alert_levels() {
+        let warning = BudgetStatus::Warning {
+            spent_usd: 85.0,
+            budget_usd: 100.0,
+        };
+        let alert = budget_alert("prod", AiFeature::Chat, &warning).unwrap();
+        assert_eq!(alert.key, "ai_budget:prod:chat:warning");
+        assert_eq!(alert.severity, AlertSeverity::Ticket);
+        assert!(alert.details.contains("(85%)"));
+
+        let exceeded = BudgetStatus::Exceeded {
+            spent_usd: 100.0,
+            budget_usd: 100.0,
+        };
+        let alert = budget_alert("prod", AiFeature::Chat, &exceeded).unwrap();
+        assert_eq!(alert.key, "ai_budget:prod:chat:exceeded");
+        assert_eq!(alert.severity, AlertSeverity::Page);
+        assert!(budget_alert("prod", AiFeature::Chat, &BudgetStatus::Within).is_none());
+    }
+}
@@ -0,0 +1,198 @@
+use crate::adapter::email_templates::EmailLocale;
+use crate::adapter::mailer::Mailer;
+use crate::adapter::ses::EmailPriority;
+use crate::model::alert_rule::{alert_category, AlertRule, AlertRuleRepository};
+use crate::model::bank_account::{BankAccountRepository, StoredBankAccount};
+use crate::model::notification::{NewNotification, NotificationRepository};
+use crate::model::plaid_item::PlaidItem;
+use crate::model::transaction::{Transaction, TransactionRepository};
+use crate::model::transaction_category::TransactionCategoryRepository;
+use crate::model::user::UserRepository;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use tracing::{debug, info, instrument, warn};
+
+/// Evaluates a user's alert rules against freshly synced data and emails what trips them
+#[derive(Clone)]
+pub struct AlertEvaluator {
+    rules: AlertRuleRepository,
+    notifications: NotificationRepository,
+    transactions: TransactionRepository,
+    accounts: BankAccountRepository,
+    categories: TransactionCategoryRepository,
+    users: UserRepository,
+    mailer: Arc<Mailer>,
+}
+
+impl AlertEvaluator {
+    pub fn new(
+        rules: AlertRuleRepository,
+        notifications: NotificationRepository,
+        transactions: TransactionRepository,
+        accounts: BankAccountRepository,
+        categories: TransactionCategoryRepository,
+        users: UserRepository,
+        mailer: Arc<Mailer>,
+    ) -> Self {
+        Self {
+            rules,
+            notifications,
+            transactions,
+            accounts,
+            categories,
+            users,
+            mailer,
+        }
+    }
+
+    /// Check an item's new transactions and current balances; returns the number of notifications sent
+    #[instrument(skip(self, item), fields(item_id = %item.item_id, user_id = %item.user_id))]
+    pub async fn evaluate_item(&self, item: &PlaidItem, since: DateTime<Utc>) -> Result<usize> {
+        let rules = self.rules.list_enabled_by_user(item.user_id).await?;
+        if rules.is_empty() {
+            return Ok(0);
+        }
+
+        let transactions = self.transactions.list_added_since(&item.item_id, since).await?;
+        let transaction_ids: Vec<String> = transactions.iter().map(|t| t.transaction_id.clone()).collect();
+        let stored_categories = self
+            .categories
+            .find_for_transactions(item.user_id, &transaction_ids)
+            .await?;
+        let accounts = self.accounts.list_by_item(&item.item_id).await?;
+
+        let mut pending = Vec::new();
+        for rule in &rules {
+            for transaction in &transactions {
+                let category = alert_category(
+                    stored_categories.get(&transaction.transaction_id).map(|c| c.category.as_str()),
+                    &transaction.category,
+                );
+                if rule.matches_transaction(transaction, category.as_deref()) {
+                    pending.push(large_transaction_notification(rule, transaction));
+                }
+            }
+            for account in &accounts {
+                if let Some(balance) = rule.breached_balance(account) {
+                    pending.push(low_balance_notification(rule, account, balance));
+                }
+            }
+        }
+
+        if pending.is_empty() {
+            debug!(rule_count = rules.len(), "No alert rules tripped");
+            return Ok(0);
+        }
+
+        let user = self
+            .users
+            .find_by_id(item.user_id)
+            .await
+            .context("Failed to load user")?
+            .context("User not found")?;
+
+        let mut sent = 0;
+        for notification in pending {
+            let delivered = send_email_notification(
+                &self.notifications,
+                &self.mailer,
+                &user.email,
+                EmailLocale::parse(&user.locale),
+                &notification,
+                EmailPriority::High,
+            )
+            .await?;
+            if delivered {
+                sent += 1;
+            }
+        }
+
+        info!(sent, "Alert evaluation finished");
+        Ok(sent)
+    }
+}
+
+/// Record a notification and email it, unless the same event was already notified.
+///
+/// Claiming first means each event is emailed at most once, even across concurrent runs.
+/// Returns whether an email was sent; delivery failures are recorded on the notification.
+pub(crate) async fn send_email_notification(
+    notifications: &NotificationRepository,
+    mailer: &Mailer,
+    to_email: &str,
+    locale: EmailLocale,
+    notification: &NewNotification,
+    priority: EmailPriority,
+) -> Result<bool> {
+    let Some(claimed) = notifications.claim(notification).await? else {
+        return Ok(false);
+    };
+    match mailer
+        .send_notification_email(to_email, &claimed.subject, claimed.body.clone(), priority, locale)
+        .await
+    {
+        Ok(_) => {
+            notifications.mark_sent(claimed.id).await?;
+            Ok(true)
+        }
+        Err(e) => {
+            warn!(notification_id = %claimed.id, kind = %claimed.kind, error = %e, "Failed to send notification email");
+            notifications.mark_failed(claimed.id, &e.to_string()).await?;
+            Ok(false)
+        }
+    }
+}
+
+fn large_transaction_notification(rule: &AlertRule, transaction: &Transaction) -> NewNotification {
+    // A posted transaction replacing a pending one is the same event
+    let event_id = transaction
+        .pending_transaction_id
+        .as_deref()
+        .unwrap_or(&transaction.transaction_id);
+    let currency = transaction.iso_currency_code.as_deref().unwrap_or("USD");
+    let merchant = transaction.merchant_name.as_deref().unwrap_or(&transaction.name);
+
+    NewNotification {
+        user_id: rule.user_id,
+        rule_id: Some(rule.id),
+        kind: rule.kind.clone(),
+        dedup_key: format!("{}:{}:{}", rule.kind, rule.id, event_id),
+        subject: format!("Large transaction: {:.2} {} at {}", transaction.amount.abs(), currency, merchant),
+        body: format!(
+            "A transaction of {:.2} {} at {} on {} reached your alert threshold of {:.2}.",
+            transaction.amount.abs(),
+            currency,
+            merchant,
+            transaction.date.format("%Y-%m-%d"),
+            rule.threshold
+        ),
+    }
+}
+
+fn low_balance_notification(rule: &AlertRule, account: &StoredBankAccount, balance: f64) -> NewNotification {
+    let currency = account.iso_currency_code.as_deref().unwrap_or("USD");
+    let account_name = match account.mask.as_deref() {
+        Some(mask) => format!("{} (...{})", account.name, mask),
+        None => account.name.clone(),
+    };
+
+    NewNotification {
+        user_id: rule.user_id,
+        rule_id: Some(rule.id),
+        kind: rule.kind.clone(),
+        // At most one low-balance email per account and rule each day
+        dedup_key: format!(
+            "{}:{}:{}:{}",
+            rule.kind,
+            rule.id,
+            account.account_id,
+            Utc::now().date_naive().format("%Y-%m-%d")
+        ),
+        subject: format!("Low balance: {}", account_name),
+        body: format!(
+            "The balance of {} is {:.2} {}, below your alert threshold of {:.2}.",
+            account_name, balance, currency, rule.threshold
+        ),
+    }
+}
@@ -0,0 +1,259 @@
+use crate::adapter::email_templates::{EmailLocale, EmailTemplateName};
+use crate::adapter::ses::TemplateData;
+use crate::jobs::scheduler::Job;
+use crate::model::bill::{detect_bills, Bill, BillRepository};
+use crate::model::email_queue::{next_local_time, EmailQueueRepository};
+use crate::model::notification::{NewNotification, NotificationRepository};
+use crate::model::plaid_item::PlaidItemRepository;
+use crate::model::transaction::TransactionRepository;
+use crate::model::user::{User, UserRepository};
+use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use std::collections::{BTreeSet, HashMap};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// History scanned for recurring payments; a little over a year so annual bills are seen twice
+const DETECTION_LOOKBACK_DAYS: i64 = 400;
+
+/// Scheduled job re-detecting every linked user's bills and their next due dates
+pub struct BillDetectionJob {
+    items: PlaidItemRepository,
+    transactions: TransactionRepository,
+    bills: BillRepository,
+}
+
+impl BillDetectionJob {
+    pub fn new(items: PlaidItemRepository, transactions: TransactionRepository, bills: BillRepository) -> Self {
+        Self {
+            items,
+            transactions,
+            bills,
+        }
+    }
+
+    async fn detect_for_user(&self, user_id: Uuid, today: NaiveDate) -> Result<(usize, u64)> {
+        let since = today - Duration::days(DETECTION_LOOKBACK_DAYS);
+        let outflows = self.transactions.list_posted_outflows_since(user_id, since).await?;
+        let detected = detect_bills(&outflows, today);
+        let dropped = self.bills.replace_for_user(user_id, &detected).await?;
+        Ok((detected.len(), dropped))
+    }
+}
+
+#[async_trait::async_trait]
+impl Job for BillDetectionJob {
+    fn name(&self) -> &'static str {
+        "bill_detection"
+    }
+
+    async fn run(&self) -> Result<()> {
+        let today = Utc::now().date_naive();
+        let user_ids: BTreeSet<Uuid> = self
+            .items
+            .list_active()
+            .await?
+            .into_iter()
+            .map(|item| item.user_id)
+            .collect();
+
+        let (mut detected, mut dropped, mut failed) = (0, 0, 0);
+        for user_id in &user_ids {
+            match self.detect_for_user(*user_id, today).await {
+                Ok((user_detected, user_dropped)) => {
+                    detected += user_detected;
+                    dropped += user_dropped;
+                }
+                Err(e) => {
+                    warn!(user_id = %user_id, error = %e, "Bill detection failed for user");
+                    failed += 1;
+                }
+            }
+        }
+
+        info!(user_count = user_ids.len(), detected, dropped, failed, "Bill detection finished");
+        Ok(())
+    }
+}
+
+/// Scheduled job queueing a reminder for each bill due within the next `days_before` days, sent at
+/// `local_hour` in the user's time zone
+pub struct BillReminderJob {
+    bills: BillRepository,
+    notifications: NotificationRepository,
+    users: UserRepository,
+    queue: EmailQueueRepository,
+    days_before: i64,
+    local_hour: u32,
+}
+
+impl BillReminderJob {
+    pub fn new(
+        bills: BillRepository,
+        notifications: NotificationRepository,
+        users: UserRepository,
+        queue: EmailQueueRepository,
+        days_before: i64,
+        local_hour: u32,
+    ) -> Self {
+        Self {
+            bills,
+            notifications,
+            users,
+            queue,
+            days_before: days_before.max(0),
+            local_hour: local_hour.min(23),
+        }
+    }
+
+    /// Queue the reminder unless it was already queued; returns whether it was queued
+    async fn queue_reminder(
+        &self,
+        user: &User,
+        bill: &Bill,
+        send_at: DateTime<Utc>,
+        local_day: NaiveDate,
+    ) -> Result<bool> {
+        let notification = reminder(bill, local_day);
+        let Some(claimed) = self.notifications.claim(&notification).await? else {
+            return Ok(false);
+        };
+
+        let mut data = TemplateData::new();
+        data.insert("subject", claimed.subject.as_str());
+        data.insert("message", claimed.body.as_str());
+        // Not worth sending once the day it was written for is over
+        let expires_at = send_at + Duration::days(1);
+        match self
+            .queue
+            .schedule(
+                &user.email,
+                EmailTemplateName::Notification,
+                EmailLocale::parse(&user.locale),
+                &data,
+                send_at,
+                Some(expires_at),
+            )
+            .await
+        {
+            Ok(_) => {
+                // Delivery is up to the email queue from here
+                self.notifications.mark_sent(claimed.id).await?;
+                Ok(true)
+            }
+            Err(e) => {
+                self.notifications.mark_failed(claimed.id, &e.to_string()).await?;
+                Err(e)
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Job for BillReminderJob {
+    fn name(&self) -> &'static str {
+        "bill_reminder"
+    }
+
+    async fn run(&self) -> Result<()> {
+        let now = Utc::now();
+        let today = now.date_naive();
+        // Reminders go out on the user's next local morning, which can be a day later than today in UTC
+        let due = self
+            .bills
+            .list_due_between(today, today + Duration::days(self.days_before + 1))
+            .await?;
+
+        let mut users: HashMap<Uuid, Option<User>> = HashMap::new();
+        let (mut queued, mut failed) = (0, 0);
+        for bill in &due {
+            if !users.contains_key(&bill.user_id) {
+                let user = self.users.find_by_id(bill.user_id).await?;
+                users.insert(bill.user_id, user);
+            }
+            let Some(user) = users.get(&bill.user_id).and_then(|user| user.as_ref()) else {
+                continue;
+            };
+
+            let time_zone = user.time_zone();
+            let send_at = next_local_time(time_zone, self.local_hour, now);
+            let local_day = send_at.with_timezone(&time_zone).date_naive();
+            let days_left = (bill.next_due_date - local_day).num_days();
+            if !(0..=self.days_before).contains(&days_left) {
+                continue;
+            }
+
+            match self.queue_reminder(user, bill, send_at, local_day).await {
+                Ok(true) => queued += 1,
+                Ok(false) => {}
+                Err(e) => {
+                    warn!(bill_id = %bill.id, error = %e, "Failed to queue bill reminder");
+                    failed += 1;
+                }
+            }
+        }
+
+        info!(due = due.len(), queued, failed, days_before = self.days_before, "Bill reminders finished");
+        Ok(())
+    }
+}
+
+/// Reminder for one due date; the dedup key makes it go out once even though the bill is due for several runs
+fn reminder(bill: &Bill, today: NaiveDate) -> NewNotification {
+    let due = bill.next_due_date.format("%Y-%m-%d");
+    let when = match (bill.next_due_date - today).num_days() {
+        0 => "today".to_string(),
+        1 => "tomorrow".to_string(),
+        days => format!("in {} days, on {}", days, due),
+    };
+    let currency = bill.iso_currency_code.as_deref().unwrap_or("USD");
+
+    NewNotification {
+        user_id: bill.user_id,
+        rule_id: None,
+        kind: "bill_due".to_string(),
+        dedup_key: format!("bill_due:{}:{}", bill.id, due),
+        subject: format!("Upcoming bill: {} due {}", bill.name, when),
+        body: format!(
+            "Your {} bill from {} of about {:.2} {} is due {}.",
+            bill.cadence, bill.name, bill.typical_amount, currency, when
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reminder_text_and_key() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let bill = Bill {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            account_id: "acc_1".to_string(),
+            merchant_key: "city power".to_string(),
+            name: "City Power".to_string(),
+            cadence: "monthly".to_string(),
+            typical_amount: 88.0,
+            iso_currency_code: Some("USD".to_string()),
+            occurrences: 3,
+            last_paid_date: NaiveDate::from_ymd_opt(2024, 2, 4).unwrap(),
+            next_due_date: NaiveDate::from_ymd_opt(2024, 3, 4).unwrap(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        let notification = reminder(&bill, today);
+        assert_eq!(notification.dedup_key, format!("bill_due:{}:2024-03-04", bill.id));
+        assert_eq!(notification.subject, "Upcoming bill: City Power due in 3 days, on 2024-03-04");
+        assert_eq!(
+            notification.body,
+            "Your monthly bill from City Power of about 88.00 USD is due in 3 days, on 2024-03-04."
+        );
+        assert_eq!(
+            reminder(&bill, NaiveDate::from_ymd_opt(2024, 3, 3).unwrap()).subject,
+            "Upcoming bill: City Power due tomorrow"
+        );
+    }
+}
@@ -0,0 +1,213 @@
+use crate::adapter::email_preferences::{EmailCategory, EmailSuppressed};
+use crate::adapter::email_templates::{EmailLocale, EmailTemplateContent};
+use crate::adapter::mailer::Mailer;
+use crate::adapter::ses::{EmailPriority, EmailRequest, TemplateData};
+use crate::jobs::ai_batch::RequestPacer;
+use crate::jobs::digest::{html_escape, plain};
+use crate::jobs::scheduler::Job;
+use crate::model::email_campaign::{ClaimedRecipient, EmailCampaign, EmailCampaignRepository};
+use anyhow::Result;
+use futures::stream::{self, StreamExt};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+/// Seconds a claimed recipient may take to send before another sender may claim it again
+const SEND_LEASE_SECONDS: i64 = 120;
+
+/// Settings of the campaign sender
+#[derive(Debug, Clone)]
+pub struct EmailCampaignSettings {
+    /// Recipients claimed per run
+    pub batch_size: i64,
+    /// Sends in flight at once
+    pub concurrency: usize,
+    /// Sends started per second; keep it and the email queue under the SES maximum send rate
+    pub sends_per_second: u32,
+    /// Attempts before a recipient fails
+    pub max_attempts: i32,
+}
+
+/// Scheduled job sending announcement campaigns to their recipients at a steady rate
+pub struct EmailCampaignSender {
+    campaigns: EmailCampaignRepository,
+    mailer: Arc<Mailer>,
+    settings: EmailCampaignSettings,
+    pacer: RequestPacer,
+}
+
+impl EmailCampaignSender {
+    pub fn new(campaigns: EmailCampaignRepository, mailer: Arc<Mailer>, settings: EmailCampaignSettings) -> Self {
+        Self {
+            pacer: RequestPacer::new(settings.sends_per_second.saturating_mul(60)),
+            campaigns,
+            mailer,
+            settings,
+        }
+    }
+
+    /// Claim and send one round of recipients; returns how many were claimed
+    pub async fn run_once(&self) -> Result<usize> {
+        let abandoned = self
+            .campaigns
+            .fail_abandoned(SEND_LEASE_SECONDS, self.settings.max_attempts)
+            .await?;
+        let recipients = self
+            .campaigns
+            .claim(self.settings.batch_size, SEND_LEASE_SECONDS, self.settings.max_attempts)
+            .await?;
+        let claimed = recipients.len();
+
+        let mut campaign_ids: HashSet<Uuid> = abandoned.into_iter().collect();
+        campaign_ids.extend(recipients.iter().map(|r| r.campaign_id));
+        let campaign_ids: Vec<Uuid> = campaign_ids.into_iter().collect();
+        if campaign_ids.is_empty() {
+            return Ok(0);
+        }
+        let campaigns: HashMap<Uuid, EmailCampaign> = self
+            .campaigns
+            .find_many(&campaign_ids)
+            .await?
+            .into_iter()
+            .map(|campaign| (campaign.id, campaign))
+            .collect();
+
+        let sent = stream::iter(recipients)
+            .map(|recipient| {
+                let campaign = campaigns.get(&recipient.campaign_id);
+                async move { self.deliver(campaign, recipient).await }
+            })
+            .buffer_unordered(self.settings.concurrency.max(1))
+            .filter(|sent| std::future::ready(*sent))
+            .count()
+            .await;
+
+        self.campaigns.refresh(&campaign_ids).await?;
+        if claimed > 0 {
+            info!(claimed, sent, "Email campaign recipients processed");
+        }
+        Ok(claimed)
+    }
+
+    /// Send one recipient their copy; errors are recorded on the recipient rather than failing the
+    /// run. Returns whether it was sent.
+    async fn deliver(&self, campaign: Option<&EmailCampaign>, recipient: ClaimedRecipient) -> bool {
+        let Some(campaign) = campaign else {
+            self.record(
+                self.campaigns.fail(recipient.id, "Campaign not found").await,
+                recipient.id,
+            );
+            return false;
+        };
+
+        self.pacer.wait().await;
+        let request = EmailRequest::from_template(
+            vec![recipient.email.as_str()],
+            render(&campaign.content(), &recipient.data()),
+        )
+        .with_priority(EmailPriority::Low)
+        .with_tag("email_type", "campaign")
+        .with_tag("campaign_id", campaign.id.to_string())
+        .with_idempotency_key(format!("campaign:{}:{}", campaign.id, recipient.id));
+        match self
+            .mailer
+            .send_categorized_email(request, EmailCategory::Marketing, EmailLocale::English)
+            .await
+        {
+            Ok(response) => {
+                // Left claimed if this fails, so it is picked up again once its lease expires; the
+                // idempotency key keeps it from being delivered twice
+                self.record(
+                    self.campaigns.mark_sent(recipient.id, &response.message_id).await,
+                    recipient.id,
+                );
+                debug!(campaign_id = %campaign.id, message_id = %response.message_id, "Campaign email sent");
+                true
+            }
+            Err(e) if EmailSuppressed::is(&e) => {
+                self.record(self.campaigns.fail(recipient.id, "Unsubscribed").await, recipient.id);
+                debug!(campaign_id = %campaign.id, recipient_id = recipient.id, "Campaign email not sent; recipient unsubscribed");
+                false
+            }
+            Err(e) => {
+                let reason = format!("{:#}", e);
+                let recorded = if recipient.attempts >= self.settings.max_attempts {
+                    warn!(campaign_id = %campaign.id, recipient_id = recipient.id, error = %reason, "Campaign email failed");
+                    self.campaigns.fail(recipient.id, &reason).await
+                } else {
+                    debug!(campaign_id = %campaign.id, recipient_id = recipient.id, error = %reason, "Campaign email send failed; retrying");
+                    self.campaigns.retry(recipient.id, &reason).await
+                };
+                self.record(recorded, recipient.id);
+                false
+            }
+        }
+    }
+
+    fn record(&self, result: Result<()>, recipient_id: i64) {
+        if let Err(e) = result {
+            warn!(recipient_id, error = ?e, "Failed to record campaign recipient");
+        }
+    }
+}
+
+/// Campaign content with a recipient's merge variables filled in. Values are escaped in the HTML
+/// body and can't add placeholders of their own.
+fn render(content: &EmailTemplateContent, data: &TemplateData) -> EmailTemplateContent {
+    let mut text_data = TemplateData::new();
+    let mut html_data = TemplateData::new();
+    for (key, value) in data.values() {
+        let value = plain(value);
+        html_data.insert(key.as_str(), html_escape(&value));
+        text_data.insert(key.as_str(), value);
+    }
+    EmailTemplateContent {
+        subject: text_data.render_template(&content.subject),
+        html: html_data.render_template(&content.html),
+        text: text_data.render_template(&content.text),
+        version: None,
+    }
+}
+
+#[async_trait::async_trait]
+impl Job for EmailCampaignSender {
+    fn name(&self) -> &'static str {
+        "email_campaigns"
+    }
+
+    async fn run(&self) -> Result<()> {
+        let claimed = self.run_once().await?;
+        if claimed == 0 {
+            debug!("No campaign emails due");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_escapes_html_only() {
+        let content = EmailTemplateContent {
+            subject: "News for {{user_name}}".to_string(),
+            html: "<p>Hi {{user_name}}, {{email}}</p>".to_string(),
+            text: "Hi {{user_name}}".to_string(),
+            version: Some(3),
+        };
+        let mut data = TemplateData::new();
+        data.insert("user_name", "Ana & <Bo> {{email}}");
+        data.insert("email", "ana@example.com");
+
+        let rendered = render(&content, &data);
+        assert_eq!(rendered.subject, "News for Ana & <Bo> { {email} }");
+        assert_eq!(
+            rendered.html,
+            "<p>Hi Ana &amp; &lt;Bo&gt; { {email} }, ana@example.com</p>"
+        );
+        assert_eq!(rendered.text, "Hi Ana & <Bo> { {email} }");
+        assert_eq!(rendered.version, None);
+    }
+}
@@ -0,0 +1,337 @@
+use crate::adapter::claude_ai::{ClaudeAIClient, ClaudeUsage};
+use crate::jobs::scheduler::Job;
+use crate::model::ai_response_cache::{AiResponseCache, CacheMode};
+use crate::model::transaction::Transaction;
+use crate::model::transaction_category::{
+    taxonomy_category, CategoryAssignment, TransactionCategoryRepository, CATEGORY_TAXONOMY, UNCATEGORIZED,
+};
+use crate::prompts::{PromptKey, PromptRegistry};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::{debug, info, instrument, warn};
+
+/// Output tokens allowed per transaction in a batch response
+const MAX_TOKENS_PER_TRANSACTION: u32 = 40;
+
+/// Prefilled start of the assistant turn, forcing the reply to continue a JSON object
+const RESPONSE_PREFILL: &str = "{\"categories\": [";
+
+#[derive(Debug, Deserialize)]
+struct CategoriesPayload {
+    categories: Vec<CategoryPayload>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CategoryPayload {
+    id: usize,
+    category: String,
+    #[serde(default)]
+    confidence: f64,
+}
+
+/// Compiled default system prompt
+pub fn system_prompt() -> String {
+    format!(
+        "You categorize bank transactions. Assign each transaction exactly one category from this list: {}.\n\
+         Positive amounts are money leaving the account; negative amounts are money coming in.\n\
+         Reply with JSON only, in the form {{\"categories\": [{{\"id\": <transaction id>, \"category\": \"<category>\", \
+         \"confidence\": <0.0 to 1.0>}}]}}, with one entry per transaction. \
+         Use {} with a low confidence when unsure.",
+        CATEGORY_TAXONOMY.join(", "),
+        UNCATEGORIZED
+    )
+}
+
+/// One line per transaction, referenced by its position in the batch
+fn build_prompt(batch: &[Transaction]) -> String {
+    let mut prompt = String::from("Categorize these transactions:\n");
+    for (id, transaction) in batch.iter().enumerate() {
+        let line = serde_json::json!({
+            "id": id,
+            "name": transaction.name,
+            "merchant": transaction.merchant_name,
+            "amount": transaction.amount,
+            "currency": transaction.iso_currency_code,
+            "channel": transaction.transaction_type,
+            "institution_category": transaction.category,
+        });
+        prompt.push_str(&line.to_string());
+        prompt.push('\n');
+    }
+    prompt
+}
+
+/// Validate the model's reply against the batch and the taxonomy.
+///
+/// Unknown categories and answers below `min_confidence` fall back to `UNCATEGORIZED`
+/// with the reported confidence; transactions the reply skips are left out so the next
+/// run retries them.
+fn parse_categories(reply: &str, batch: &[Transaction], min_confidence: f64) -> Result<Vec<CategoryAssignment>> {
+    let json = format!("{}{}", RESPONSE_PREFILL, reply.trim());
+    let payload: CategoriesPayload = serde_json::from_str(&json).context("Categorizer reply is not valid JSON")?;
+
+    let mut assigned = vec![false; batch.len()];
+    let mut assignments = Vec::with_capacity(batch.len());
+
+    for entry in payload.categories {
+        let Some(transaction) = batch.get(entry.id) else {
+            warn!(id = entry.id, "Categorizer referenced an unknown transaction");
+            continue;
+        };
+        if std::mem::replace(&mut assigned[entry.id], true) {
+            continue;
+        }
+
+        let confidence = if entry.confidence.is_finite() {
+            entry.confidence.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let category = match taxonomy_category(&entry.category) {
+            Some(category) if confidence >= min_confidence => category,
+            Some(_) => UNCATEGORIZED,
+            None => {
+                warn!(
+                    transaction_id = %transaction.transaction_id,
+                    category = %entry.category,
+                    "Categorizer returned a category outside the taxonomy"
+                );
+                UNCATEGORIZED
+            }
+        };
+
+        assignments.push(CategoryAssignment {
+            transaction_id: transaction.transaction_id.clone(),
+            user_id: transaction.user_id,
+            category,
+            confidence,
+        });
+    }
+
+    Ok(assignments)
+}
+
+/// Categories the model assigned to one batch, before they are stored
+#[derive(Debug, Clone)]
+pub struct CategorizedBatch {
+    pub assignments: Vec<CategoryAssignment>,
+    /// Model that generated the reply
+    pub model: String,
+    pub usage: ClaudeUsage,
+}
+
+/// Assigns taxonomy categories to uncategorized transactions in batches through Claude
+pub struct TransactionCategorizer {
+    client: Arc<ClaudeAIClient>,
+    categories: TransactionCategoryRepository,
+    batch_size: usize,
+    max_batches: usize,
+    min_confidence: f64,
+    response_cache: Option<(AiResponseCache, CacheMode)>,
+    prompts: Arc<PromptRegistry>,
+}
+
+impl TransactionCategorizer {
+    pub fn new(
+        client: Arc<ClaudeAIClient>,
+        categories: TransactionCategoryRepository,
+        batch_size: usize,
+        max_batches: usize,
+        min_confidence: f64,
+        prompts: Arc<PromptRegistry>,
+    ) -> Self {
+        Self {
+            client,
+            categories,
+            batch_size: batch_size.max(1),
+            max_batches: max_batches.max(1),
+            min_confidence,
+            response_cache: None,
+            prompts,
+        }
+    }
+
+    /// Send batches through a response cache, so a batch retried with the same transactions isn't paid for twice
+    pub fn with_response_cache(mut self, cache: AiResponseCache, mode: CacheMode) -> Self {
+        self.response_cache = Some((cache, mode));
+        self
+    }
+
+    /// Categorize one batch and store the results; returns how many were stored
+    pub async fn categorize_batch(&self, batch: &[Transaction]) -> Result<usize> {
+        if batch.is_empty() {
+            return Ok(0);
+        }
+        let categorized = self.categorize(batch).await?;
+        self.store(&categorized).await
+    }
+
+    /// Store a categorized batch; manual overrides are left untouched. Returns how many were stored
+    pub async fn store(&self, categorized: &CategorizedBatch) -> Result<usize> {
+        self.categories
+            .store_ai_categories(&categorized.assignments, &categorized.model)
+            .await
+    }
+
+    /// Ask the model for the categories of one batch without storing them; transactions the reply
+    /// skipped are missing from the result
+    #[instrument(skip(self, batch), fields(batch_size = batch.len()))]
+    pub async fn categorize(&self, batch: &[Transaction]) -> Result<CategorizedBatch> {
+        // The taxonomy prompt is identical for every batch, so it is cached server-side
+        let system = self.prompts.text(PromptKey::Categorization);
+        let request = self
+            .client
+            .conversation_request(
+                vec![
+                    ClaudeAIClient::user_message(&build_prompt(batch)),
+                    ClaudeAIClient::assistant_message(RESPONSE_PREFILL),
+                ],
+                Some(&system),
+                Some(MAX_TOKENS_PER_TRANSACTION * batch.len() as u32 + 64),
+                Some(0.0),
+            )
+            .with_cached_system();
+        let response = match &self.response_cache {
+            Some((cache, mode)) => cache.send_message(&self.client, request, *mode).await,
+            None => self.client.send_message(request).await,
+        }
+        .context("Categorization request failed")?;
+        debug!(
+            cache_read_tokens = response.usage.cache_read_input_tokens,
+            cache_write_tokens = response.usage.cache_creation_input_tokens,
+            "Categorization prompt cache usage"
+        );
+
+        let reply: String = response.content.iter().map(|block| block.text.as_str()).collect();
+        let assignments = parse_categories(&reply, batch, self.min_confidence)?;
+        if assignments.len() < batch.len() {
+            warn!(
+                returned = assignments.len(),
+                expected = batch.len(),
+                "Categorizer skipped transactions; they will be retried"
+            );
+        }
+
+        Ok(CategorizedBatch {
+            assignments,
+            model: response.model,
+            usage: response.usage,
+        })
+    }
+
+    /// Categorize up to `max_batches` batches of uncategorized transactions
+    pub async fn run_once(&self) -> Result<usize> {
+        let mut stored = 0;
+
+        for _ in 0..self.max_batches {
+            let batch = self.categories.list_uncategorized(self.batch_size as i64).await?;
+            if batch.is_empty() {
+                break;
+            }
+            let count = self.categorize_batch(&batch).await?;
+            stored += count;
+            // Nothing stored means every transaction in the batch would be fetched again
+            if count == 0 || batch.len() < self.batch_size {
+                break;
+            }
+        }
+
+        Ok(stored)
+    }
+}
+
+#[async_trait::async_trait]
+impl Job for TransactionCategorizer {
+    fn name(&self) -> &'static str {
+        "transaction_categorization"
+    }
+
+    async fn run(&self) -> Result<()> {
+        let stored = self.run_once().await?;
+        info!(stored, "Scheduled transaction categorization finished");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, Utc};
+    use uuid::Uuid;
+
+    fn transaction(transaction_id: &str, name: &str) -> Transaction {
+        Transaction {
+            id: Uuid::new_v4(),
+            transaction_id: transaction_id.to_string(),
+            account_id: "acc_1".to_string(),
+            item_id: "item_1".to_string(),
+            user_id: Uuid::nil(),
+            amount: 12.5,
+            iso_currency_code: Some("USD".to_string()),
+            unofficial_currency_code: None,
+            date: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            datetime: None,
+            authorized_date: None,
+            authorized_datetime: None,
+            name: name.to_string(),
+            merchant_name: None,
+            original_description: None,
+            category: vec![],
+            category_id: None,
+            check_number: None,
+            location: None,
+            payment_meta: None,
+            pending: false,
+            pending_transaction_id: None,
+            account_owner: None,
+            transaction_type: "in store".to_string(),
+            transaction_code: None,
+            removed_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_prompt_lists_taxonomy_and_batch() {
+        assert!(system_prompt().contains("FOOD_AND_DRINK"));
+
+        let prompt = build_prompt(&[transaction("tx_1", "Blue Bottle Coffee"), transaction("tx_2", "Uber")]);
+        assert!(prompt.contains("\"id\":0"));
+        assert!(prompt.contains("\"id\":1"));
+        assert!(prompt.contains("Blue Bottle Coffee"));
+        assert!(!prompt.contains("tx_1"));
+    }
+
+    #[test]
+    fn test_parse_categories_validates_reply() {
+        let batch = [
+            transaction("tx_1", "Blue Bottle Coffee"),
+            transaction("tx_2", "Uber"),
+            transaction("tx_3", "Landlord"),
+        ];
+        let reply = r#"{"id": 0, "category": "food_and_drink", "confidence": 0.95},
+            {"id": 1, "category": "RIDESHARE", "confidence": 0.9},
+            {"id": 0, "category": "TRAVEL", "confidence": 0.5},
+            {"id": 7, "category": "TRAVEL", "confidence": 0.5},
+            {"id": 2, "category": "RENT_AND_UTILITIES", "confidence": 0.2}]}"#;
+
+        let assignments = parse_categories(reply, &batch, 0.5).unwrap();
+        let categories: Vec<_> = assignments.iter().map(|a| (a.transaction_id.as_str(), a.category)).collect();
+        assert_eq!(
+            categories,
+            vec![("tx_1", "FOOD_AND_DRINK"), ("tx_2", UNCATEGORIZED), ("tx_3", UNCATEGORIZED)]
+        );
+        assert_eq!(assignments[2].confidence, 0.2);
+    }
+
+    #[test]
+    fn test_parse_categories_rejects_malformed_reply() {
+        let batch = [transaction("tx_1", "Blue Bottle Coffee")];
+        assert!(parse_categories("Sure! Here are the categories", &batch, 0.5).is_err());
+
+        let assignments = parse_categories(r#"{"id": 0, "category": "TRAVEL", "confidence": 7}]}"#, &batch, 0.5).unwrap();
+        assert_eq!(assignments[0].confidence, 1.0);
+    }
+}
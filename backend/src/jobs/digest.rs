@@ -0,0 +1,532 @@
+use crate::adapter::mailer::Mailer;
+use crate::adapter::ses::TemplateData;
+use crate::email_drafting::{DraftKind, EmailDrafter, EMAIL_DRAFTS_FLAG};
+use crate::jobs::scheduler::Job;
+use crate::model::bill::{Bill, BillRepository};
+use crate::model::notification::{NewNotification, NotificationRepository};
+use crate::model::spending::{SpendingPeriod, SpendingRepository, SpendingTotal};
+use crate::model::transaction::{Transaction, TransactionFilter, TransactionRepository};
+use crate::model::user::{User, UserRepository};
+use crate::settings::FeatureFlags;
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Timelike, Utc, Weekday};
+use chrono_tz::Tz;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Largest outflows listed in the email
+const TOP_TRANSACTIONS: usize = 5;
+
+/// Transactions loaded for one week; the count in the email is capped at this
+const MAX_WEEK_TRANSACTIONS: i64 = 1000;
+
+/// Bills due within this many days after the week are listed as upcoming
+const BILL_LOOKAHEAD_DAYS: i64 = 7;
+
+/// Transaction as shown in the digest; positive amounts are outflows
+#[derive(Debug, Clone, PartialEq)]
+pub struct DigestTransaction {
+    pub date: NaiveDate,
+    pub description: String,
+    pub amount: f64,
+    pub currency_code: String,
+}
+
+impl From<&Transaction> for DigestTransaction {
+    fn from(transaction: &Transaction) -> Self {
+        Self {
+            date: transaction.date,
+            description: transaction.merchant_name.clone().unwrap_or_else(|| transaction.name.clone()),
+            amount: transaction.amount,
+            currency_code: transaction
+                .iso_currency_code
+                .clone()
+                .or_else(|| transaction.unofficial_currency_code.clone())
+                .unwrap_or_else(|| "USD".to_string()),
+        }
+    }
+}
+
+/// Bill as shown in the digest
+#[derive(Debug, Clone, PartialEq)]
+pub struct DigestBill {
+    pub name: String,
+    pub due: NaiveDate,
+    pub amount: f64,
+    pub currency_code: String,
+}
+
+impl From<&Bill> for DigestBill {
+    fn from(bill: &Bill) -> Self {
+        Self {
+            name: bill.name.clone(),
+            due: bill.next_due_date,
+            amount: bill.typical_amount,
+            currency_code: bill.iso_currency_code.clone().unwrap_or_else(|| "USD".to_string()),
+        }
+    }
+}
+
+/// One user's week: new transactions, spending against the week before, and bills coming up
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeeklyDigest {
+    pub week_start: NaiveDate,
+    pub week_end: NaiveDate,
+    pub transaction_count: usize,
+    /// Inflows per currency, in cents
+    pub money_in: BTreeMap<String, i64>,
+    /// Outflows per currency, in cents
+    pub money_out: BTreeMap<String, i64>,
+    pub largest: Vec<DigestTransaction>,
+    pub spending: Vec<SpendingTotal>,
+    pub upcoming_bills: Vec<DigestBill>,
+}
+
+impl WeeklyDigest {
+    pub fn build(
+        week_start: NaiveDate,
+        week_end: NaiveDate,
+        transactions: &[DigestTransaction],
+        spending: Vec<SpendingTotal>,
+        bills: &[DigestBill],
+    ) -> Self {
+        let mut money_in: BTreeMap<String, i64> = BTreeMap::new();
+        let mut money_out: BTreeMap<String, i64> = BTreeMap::new();
+        for transaction in transactions {
+            let cents = (transaction.amount * 100.0).round() as i64;
+            let totals = if cents < 0 { &mut money_in } else { &mut money_out };
+            *totals.entry(transaction.currency_code.clone()).or_default() += cents.abs();
+        }
+
+        let mut largest: Vec<DigestTransaction> = transactions.iter().filter(|t| t.amount > 0.0).cloned().collect();
+        largest.sort_by(|a, b| b.amount.total_cmp(&a.amount));
+        largest.truncate(TOP_TRANSACTIONS);
+
+        let bills_until = week_end + Duration::days(BILL_LOOKAHEAD_DAYS);
+        let mut upcoming_bills: Vec<DigestBill> = bills
+            .iter()
+            .filter(|bill| bill.due > week_end && bill.due <= bills_until)
+            .cloned()
+            .collect();
+        upcoming_bills.sort_by(|a, b| a.due.cmp(&b.due).then_with(|| a.name.cmp(&b.name)));
+
+        Self {
+            week_start,
+            week_end,
+            transaction_count: transactions.len(),
+            money_in,
+            money_out,
+            largest,
+            spending,
+            upcoming_bills,
+        }
+    }
+
+    /// One-line summary stored as the notification body
+    pub fn summary(&self) -> String {
+        format!(
+            "{} new transactions ({} in, {} out); {} bills due in the next {} days.",
+            self.transaction_count,
+            format_totals(&self.money_in),
+            format_totals(&self.money_out),
+            self.upcoming_bills.len(),
+            BILL_LOOKAHEAD_DAYS
+        )
+    }
+
+    fn spending_lines(&self) -> Vec<String> {
+        if self.spending.is_empty() {
+            return vec!["No spending this week.".to_string()];
+        }
+        self.spending
+            .iter()
+            .map(|total| {
+                let change = match total.change_percent() {
+                    Some(percent) if percent >= 0.5 => format!("up {:.0}% from the week before", percent),
+                    Some(percent) if percent <= -0.5 => format!("down {:.0}% from the week before", -percent),
+                    Some(_) => "about the same as the week before".to_string(),
+                    None => "nothing spent the week before".to_string(),
+                };
+                format!("Spent {:.2} {}, {}", total.amount, total.currency_code, change)
+            })
+            .collect()
+    }
+
+    fn transaction_lines(&self) -> Vec<String> {
+        self.largest
+            .iter()
+            .map(|t| format!("{} {}: {:.2} {}", t.date.format("%b %d"), t.description, t.amount, t.currency_code))
+            .collect()
+    }
+
+    fn bill_lines(&self) -> Vec<String> {
+        if self.upcoming_bills.is_empty() {
+            return vec!["No bills due in the coming week.".to_string()];
+        }
+        self.upcoming_bills
+            .iter()
+            .map(|b| format!("{} due {}: about {:.2} {}", b.name, b.due.format("%b %d"), b.amount, b.currency_code))
+            .collect()
+    }
+
+    /// Facts an LLM may use to write the digest's spending insight, one per line
+    pub fn insight_facts(&self) -> String {
+        let largest = self.transaction_lines().into_iter().map(|line| format!("Large outflow {}", line));
+        let bills = self.upcoming_bills.iter().map(|b| {
+            format!("Bill {} due {}: about {:.2} {}", b.name, b.due.format("%b %d"), b.amount, b.currency_code)
+        });
+        std::iter::once(format!("Week: {} to {}", self.week_start.format("%b %d"), self.week_end.format("%b %d")))
+            .chain(self.spending_lines())
+            .chain(largest)
+            .chain(bills)
+            .map(|line| format!("{}\n", plain(&line)))
+            .collect()
+    }
+
+    /// Values for the weekly digest email template; `insight` is an optional plain-text paragraph
+    pub fn template_data(&self, user_name: &str, insight: Option<&str>) -> TemplateData {
+        let mut data = TemplateData::new();
+        // The name appears in both bodies; escaping keeps the HTML safe at the cost of entities in the text
+        data.insert("user_name", html_escape(&plain(user_name)));
+        data.insert("week_start", self.week_start.format("%b %d").to_string());
+        data.insert("week_end", self.week_end.format("%b %d").to_string());
+        data.insert("transaction_count", self.transaction_count.to_string());
+        data.insert("money_in", format_totals(&self.money_in));
+        data.insert("money_out", format_totals(&self.money_out));
+        for (key, lines) in [
+            ("spending", self.spending_lines()),
+            ("transactions", self.transaction_lines()),
+            ("bills", self.bill_lines()),
+        ] {
+            data.insert(format!("{}_html", key), html_list(&lines));
+            data.insert(format!("{}_text", key), text_list(&lines));
+        }
+        let insight = insight.map(plain).unwrap_or_default();
+        if insight.is_empty() {
+            data.insert("insight_html", String::new());
+        } else {
+            data.insert("insight_html", format!("<p>{}</p>", html_escape(&insight)));
+        }
+        data.insert("insight_text", insight);
+        data
+    }
+}
+
+/// Amounts per currency, e.g. `12.50 USD, 3.00 EUR`
+fn format_totals(totals: &BTreeMap<String, i64>) -> String {
+    if totals.is_empty() {
+        return "0.00".to_string();
+    }
+    totals
+        .iter()
+        .map(|(currency, cents)| format!("{:.2} {}", *cents as f64 / 100.0, currency))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Merchant-controlled text must not inject template placeholders
+pub(crate) fn plain(value: &str) -> String {
+    value.replace("{{", "{ {").replace("}}", "} }")
+}
+
+pub(crate) fn html_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+fn html_list(lines: &[String]) -> String {
+    if lines.is_empty() {
+        return String::new();
+    }
+    let items: String = lines
+        .iter()
+        .map(|line| format!("<li>{}</li>", html_escape(&plain(line))))
+        .collect();
+    format!("<ul>{}</ul>", items)
+}
+
+fn text_list(lines: &[String]) -> String {
+    lines.iter().map(|line| format!("- {}", plain(line))).collect::<Vec<_>>().join("\n")
+}
+
+/// Builds and emails weekly digests; shared by the scheduled job and the send-now RPC
+#[derive(Clone)]
+pub struct WeeklyDigestSender {
+    transactions: TransactionRepository,
+    spending: SpendingRepository,
+    bills: BillRepository,
+    notifications: NotificationRepository,
+    mailer: Arc<Mailer>,
+    /// Drafts are written only while the flag is on
+    drafter: Option<(Arc<EmailDrafter>, Arc<FeatureFlags>)>,
+}
+
+impl WeeklyDigestSender {
+    pub fn new(
+        transactions: TransactionRepository,
+        spending: SpendingRepository,
+        bills: BillRepository,
+        notifications: NotificationRepository,
+        mailer: Arc<Mailer>,
+    ) -> Self {
+        Self {
+            transactions,
+            spending,
+            bills,
+            notifications,
+            mailer,
+            drafter: None,
+        }
+    }
+
+    /// Add a spending insight written by an LLM while the `ai-email-drafts` flag is on; the email is sent
+    /// without one when drafting fails
+    pub fn with_drafter(mut self, drafter: Arc<EmailDrafter>, flags: Arc<FeatureFlags>) -> Self {
+        self.drafter = Some((drafter, flags));
+        self
+    }
+
+    /// Digest of the seven days ending on `week_end`
+    pub async fn build(&self, user_id: Uuid, week_end: NaiveDate) -> Result<WeeklyDigest> {
+        let week_start = week_end - Duration::days(6);
+        let filter = TransactionFilter {
+            start_date: Some(week_start),
+            end_date: Some(week_end),
+            limit: MAX_WEEK_TRANSACTIONS,
+            ..Default::default()
+        };
+        let transactions: Vec<DigestTransaction> = self
+            .transactions
+            .list_by_user(user_id, &filter)
+            .await?
+            .iter()
+            .filter(|t| !t.pending)
+            .map(DigestTransaction::from)
+            .collect();
+        let spending = self
+            .spending
+            .totals(user_id, SpendingPeriod::new(week_start, week_end))
+            .await?;
+        let bills: Vec<DigestBill> = self.bills.list_by_user(user_id).await?.iter().map(DigestBill::from).collect();
+
+        Ok(WeeklyDigest::build(week_start, week_end, &transactions, spending, &bills))
+    }
+
+    /// Email the digest unless one with `dedup_key` was already sent; returns whether it was sent
+    pub async fn send(&self, user: &User, week_end: NaiveDate, dedup_key: String) -> Result<bool> {
+        let digest = self.build(user.id, week_end).await?;
+        let notification = NewNotification {
+            user_id: user.id,
+            rule_id: None,
+            kind: "weekly_digest".to_string(),
+            dedup_key,
+            subject: format!(
+                "Your week in review: {} to {}",
+                digest.week_start.format("%b %d"),
+                digest.week_end.format("%b %d")
+            ),
+            body: digest.summary(),
+        };
+        let Some(claimed) = self.notifications.claim(&notification).await? else {
+            return Ok(false);
+        };
+
+        let draft = match &self.drafter {
+            Some((drafter, flags)) if flags.is_enabled(EMAIL_DRAFTS_FLAG) => drafter
+                .draft(user.id, DraftKind::SpendingInsight, &digest.insight_facts())
+                .await
+                .unwrap_or_else(|e| {
+                    warn!(user_id = %user.id, "Failed to draft digest insight: {:?}", e);
+                    None
+                }),
+            _ => None,
+        };
+        let usable = draft.as_ref().filter(|d| d.usable_content().is_some());
+        let insight = usable.map(|d| d.content.as_str());
+
+        match self
+            .mailer
+            .send_weekly_digest_email(user.email.as_str(), digest.template_data(&user.name, insight))
+            .await
+        {
+            Ok(_) => {
+                self.notifications.mark_sent(claimed.id).await?;
+                if let (Some((drafter, _)), Some(draft)) = (&self.drafter, usable) {
+                    drafter.mark_used(draft).await;
+                }
+                Ok(true)
+            }
+            Err(e) => {
+                self.notifications.mark_failed(claimed.id, &e.to_string()).await?;
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Scheduled job emailing last week's digest to every user who opted in, on Monday at `local_hour`
+/// in each user's time zone. It must run at least hourly for every time zone to get its hour.
+pub struct WeeklyDigestJob {
+    users: UserRepository,
+    sender: WeeklyDigestSender,
+    local_hour: u32,
+}
+
+impl WeeklyDigestJob {
+    pub fn new(users: UserRepository, sender: WeeklyDigestSender, local_hour: u32) -> Self {
+        Self {
+            users,
+            sender,
+            local_hour: local_hour.min(23),
+        }
+    }
+}
+
+/// Last day of the week to send the digest for, if it is the hour to send it at `now` in `time_zone`
+fn digest_week_end(time_zone: Tz, local_hour: u32, now: DateTime<Utc>) -> Option<NaiveDate> {
+    let local = now.with_timezone(&time_zone);
+    (local.weekday() == Weekday::Mon && local.hour() == local_hour).then(|| local.date_naive() - Duration::days(1))
+}
+
+#[async_trait::async_trait]
+impl Job for WeeklyDigestJob {
+    fn name(&self) -> &'static str {
+        "weekly_digest"
+    }
+
+    async fn run(&self) -> Result<()> {
+        let now = Utc::now();
+        let recipients = self.users.list_weekly_digest_recipients().await?;
+
+        let (mut due, mut sent, mut failed) = (0, 0, 0);
+        for user in &recipients {
+            // The week that ended yesterday in the user's time zone; its start date keys the
+            // notification so reruns don't resend
+            let Some(week_end) = digest_week_end(user.time_zone(), self.local_hour, now) else {
+                continue;
+            };
+            due += 1;
+            let dedup_key = format!("weekly_digest:{}", week_end - Duration::days(6));
+            match self.sender.send(user, week_end, dedup_key).await {
+                Ok(true) => sent += 1,
+                Ok(false) => {}
+                Err(e) => {
+                    warn!(user_id = %user.id, error = %e, "Failed to send weekly digest");
+                    failed += 1;
+                }
+            }
+        }
+
+        if due > 0 {
+            info!(recipients = recipients.len(), due, sent, failed, "Weekly digest finished");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, day).unwrap()
+    }
+
+    fn transaction(description: &str, amount: f64) -> DigestTransaction {
+        DigestTransaction {
+            date: date(5),
+            description: description.to_string(),
+            amount,
+            currency_code: "USD".to_string(),
+        }
+    }
+
+    fn bill(name: &str, due: NaiveDate) -> DigestBill {
+        DigestBill {
+            name: name.to_string(),
+            due,
+            amount: 50.0,
+            currency_code: "USD".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_build_weekly_digest() {
+        let transactions = vec![
+            transaction("Payroll", -2000.0),
+            transaction("Grocer", 80.25),
+            transaction("Cafe", 4.5),
+        ];
+        let bills = vec![bill("Rent", date(20)), bill("Power", date(12)), bill("Past", date(9))];
+        let digest = WeeklyDigest::build(date(4), date(10), &transactions, Vec::new(), &bills);
+
+        assert_eq!(digest.transaction_count, 3);
+        assert_eq!(digest.money_in.get("USD"), Some(&200000));
+        assert_eq!(digest.money_out.get("USD"), Some(&8475));
+        assert_eq!(digest.largest[0].description, "Grocer");
+        assert_eq!(digest.largest.len(), 2);
+        let bill_names: Vec<&str> = digest.upcoming_bills.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(bill_names, vec!["Power"]);
+        assert_eq!(
+            digest.summary(),
+            "3 new transactions (2000.00 USD in, 84.75 USD out); 1 bills due in the next 7 days."
+        );
+    }
+
+    #[test]
+    fn test_template_data_escapes_merchant_text() {
+        let transactions = vec![transaction("<b>Shop</b> {{user_name}}", 10.0)];
+        let digest = WeeklyDigest::build(date(4), date(10), &transactions, Vec::new(), &[]);
+        let data = digest.template_data("Ana", None);
+
+        let html = data.get("transactions_html").unwrap();
+        assert!(html.contains("&lt;b&gt;Shop&lt;/b&gt;"));
+        assert!(!html.contains("{{"));
+        assert_eq!(data.get("bills_text").map(String::as_str), Some("- No bills due in the coming week."));
+        assert_eq!(data.get("money_in").map(String::as_str), Some("0.00"));
+        assert_eq!(data.get("insight_html").map(String::as_str), Some(""));
+    }
+
+    #[test]
+    fn test_template_data_insight() {
+        let digest = WeeklyDigest::build(date(4), date(10), &[transaction("Grocer", 80.25)], Vec::new(), &[]);
+        let data = digest.template_data("Ana", Some("Groceries & rent {{user_name}} were steady."));
+
+        assert_eq!(
+            data.get("insight_html").map(String::as_str),
+            Some("<p>Groceries &amp; rent { {user_name} } were steady.</p>")
+        );
+        assert_eq!(
+            data.get("insight_text").map(String::as_str),
+            Some("Groceries & rent { {user_name} } were steady.")
+        );
+
+        let facts = digest.insight_facts();
+        assert!(facts.starts_with("Week: Mar 04 to Mar 10\n"));
+        assert!(facts.contains("Large outflow Mar 05 Grocer: 80.25 USD\n"));
+    }
+
+    #[test]
+    fn test_digest_week_end_at_local_hour() {
+        let at = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+
+        // Monday 08:00 in Madrid is 07:00 UTC in winter
+        assert_eq!(
+            digest_week_end(chrono_tz::Europe::Madrid, 8, at("2024-03-04T07:00:00Z")),
+            Some(date(3))
+        );
+        assert_eq!(digest_week_end(chrono_tz::Europe::Madrid, 8, at("2024-03-04T08:00:00Z")), None);
+        // Still Sunday evening in Los Angeles
+        assert_eq!(digest_week_end(chrono_tz::America::Los_Angeles, 8, at("2024-03-04T07:00:00Z")), None);
+        assert_eq!(
+            digest_week_end(chrono_tz::America::Los_Angeles, 8, at("2024-03-04T16:00:00Z")),
+            Some(date(3))
+        );
+    }
+}
@@ -0,0 +1,259 @@
+use crate::adapter::sqs::{QueueMessage, SqsQueue, MAX_RECEIVE_MESSAGES};
+use crate::jobs::scheduler::Job;
+use crate::model::email_event::{EmailEventRepository, EmailEventType, NewEmailEvent};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use tracing::{debug, info, warn};
+
+/// Receives per run, so one run can't hold the scheduler while a flood of events drains
+const MAX_BATCHES_PER_RUN: usize = 20;
+
+/// What a queue message held
+#[derive(Debug, Clone, PartialEq)]
+enum Notification {
+    Event(NewEmailEvent),
+    /// Something other than an SES event, such as an SNS subscription confirmation
+    Ignored(String),
+}
+
+/// Scheduled job reading the SES events a configuration set publishes to SNS, from the SQS queue
+/// subscribed to the topic, into `email_events`
+pub struct EmailEventConsumer {
+    queue: SqsQueue,
+    events: EmailEventRepository,
+}
+
+impl EmailEventConsumer {
+    pub fn new(queue: SqsQueue, events: EmailEventRepository) -> Self {
+        Self { queue, events }
+    }
+
+    /// Drain the queue, up to a limit; returns how many events were stored
+    pub async fn run_once(&self) -> Result<usize> {
+        let mut stored = 0;
+        for _ in 0..MAX_BATCHES_PER_RUN {
+            let messages = self.queue.receive(MAX_RECEIVE_MESSAGES).await?;
+            if messages.is_empty() {
+                break;
+            }
+            for message in &messages {
+                if self.handle(message).await {
+                    stored += 1;
+                }
+            }
+        }
+        Ok(stored)
+    }
+
+    /// Store one message's event and delete the message; returns whether a new event was stored.
+    /// Messages that fail to store are left to be redelivered.
+    async fn handle(&self, message: &QueueMessage) -> bool {
+        let stored = match parse_notification(&message.body, &message.message_id) {
+            Ok(Notification::Event(event)) => match self.events.record(&event).await {
+                Ok(stored) => {
+                    debug!(
+                        message_id = %event.message_id,
+                        event_type = event.event_type.as_str(),
+                        duplicate = !stored,
+                        "Email event recorded"
+                    );
+                    stored
+                }
+                Err(e) => {
+                    warn!(sqs_message_id = %message.message_id, error = ?e, "Failed to record email event");
+                    return false;
+                }
+            },
+            Ok(Notification::Ignored(kind)) => {
+                info!(sqs_message_id = %message.message_id, kind = %kind, "Ignoring email event queue message");
+                false
+            }
+            // Redelivering can't fix it, so it is dropped rather than retried forever
+            Err(e) => {
+                warn!(sqs_message_id = %message.message_id, error = %e, "Dropping unreadable email event");
+                false
+            }
+        };
+
+        if let Err(e) = self.queue.delete(message).await {
+            warn!(sqs_message_id = %message.message_id, error = ?e, "Failed to delete email event message");
+        }
+        stored
+    }
+}
+
+/// Read an SES event from a queue message: an SNS notification wrapping the event, or the bare
+/// event when the subscription uses raw message delivery. `fallback_id` identifies the event when
+/// there is no SNS notification ID.
+fn parse_notification(body: &str, fallback_id: &str) -> Result<Notification> {
+    let value: Value = serde_json::from_str(body).context("Message is not JSON")?;
+    let (event_id, event) = match value.get("Type").and_then(Value::as_str) {
+        Some("Notification") => {
+            let message = value
+                .get("Message")
+                .and_then(Value::as_str)
+                .ok_or_else(|| anyhow!("SNS notification without a message"))?;
+            let event: Value = serde_json::from_str(message).context("SNS message is not JSON")?;
+            let event_id = value.get("MessageId").and_then(Value::as_str).unwrap_or(fallback_id);
+            (event_id.to_string(), event)
+        }
+        Some(other) => return Ok(Notification::Ignored(other.to_string())),
+        None => (fallback_id.to_string(), value),
+    };
+
+    let kind = event
+        .get("eventType")
+        .or_else(|| event.get("notificationType"))
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow!("Not an SES event"))?;
+    let Some(event_type) = EmailEventType::from_ses(kind) else {
+        return Ok(Notification::Ignored(kind.to_string()));
+    };
+    let mail = event.get("mail").ok_or_else(|| anyhow!("SES event without mail"))?;
+    let message_id = mail
+        .get("messageId")
+        .and_then(Value::as_str)
+        .filter(|id| !id.is_empty())
+        .ok_or_else(|| anyhow!("SES event without a message ID"))?;
+
+    let mut detail = event
+        .get(event_type.ses_detail_key())
+        .filter(|detail| detail.is_object())
+        .cloned()
+        .unwrap_or_else(|| Value::Object(Default::default()));
+    let occurred_at = timestamp(&detail).or_else(|| timestamp(mail)).unwrap_or_else(Utc::now);
+    let recipients = recipients(&detail).unwrap_or_else(|| addresses(mail.get("destination")));
+    // The recipient's IP address isn't needed to tell what happened to the email
+    if let Some(object) = detail.as_object_mut() {
+        object.remove("ipAddress");
+    }
+
+    Ok(Notification::Event(NewEmailEvent {
+        event_id,
+        message_id: message_id.to_string(),
+        event_type,
+        recipients,
+        detail,
+        occurred_at,
+    }))
+}
+
+fn timestamp(value: &Value) -> Option<DateTime<Utc>> {
+    let timestamp = value.get("timestamp")?.as_str()?;
+    DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .map(|at| at.with_timezone(&Utc))
+}
+
+/// Addresses an event names, from whichever list its type uses
+fn recipients(detail: &Value) -> Option<Vec<String>> {
+    if let Some(recipients) = detail.get("recipients") {
+        return Some(addresses(Some(recipients)));
+    }
+    ["bouncedRecipients", "complainedRecipients", "delayedRecipients"]
+        .iter()
+        .find_map(|key| detail.get(*key)?.as_array())
+        .map(|entries| {
+            entries
+                .iter()
+                .filter_map(|entry| entry.get("emailAddress")?.as_str())
+                .map(str::to_string)
+                .collect()
+        })
+}
+
+fn addresses(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(Value::as_array)
+        .map(|values| values.iter().filter_map(Value::as_str).map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+#[async_trait::async_trait]
+impl Job for EmailEventConsumer {
+    fn name(&self) -> &'static str {
+        "email_events"
+    }
+
+    async fn run(&self) -> Result<()> {
+        let stored = self.run_once().await?;
+        if stored > 0 {
+            info!(stored, "Email events recorded");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sns(event: Value) -> String {
+        json!({
+            "Type": "Notification",
+            "MessageId": "sns-1",
+            "TopicArn": "arn:aws:sns:us-east-1:123456789012:ses-events",
+            "Message": event.to_string(),
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_parse_bounce_notification() {
+        let body = sns(json!({
+            "eventType": "Bounce",
+            "mail": {
+                "timestamp": "2024-03-04T12:00:00.000Z",
+                "messageId": "msg-1",
+                "destination": ["ana@example.com", "bo@example.com"]
+            },
+            "bounce": {
+                "bounceType": "Permanent",
+                "bounceSubType": "General",
+                "bouncedRecipients": [{ "emailAddress": "bo@example.com" }],
+                "timestamp": "2024-03-04T12:00:05.000Z"
+            }
+        }));
+
+        let Notification::Event(event) = parse_notification(&body, "sqs-1").unwrap() else {
+            panic!("expected an event");
+        };
+        assert_eq!(event.event_id, "sns-1");
+        assert_eq!(event.message_id, "msg-1");
+        assert_eq!(event.event_type, EmailEventType::Bounce);
+        assert_eq!(event.recipients, vec!["bo@example.com"]);
+        assert_eq!(event.detail["bounceType"], "Permanent");
+        assert_eq!(event.occurred_at.to_rfc3339(), "2024-03-04T12:00:05+00:00");
+    }
+
+    #[test]
+    fn test_parse_raw_open_event() {
+        let body = json!({
+            "eventType": "Open",
+            "mail": { "timestamp": "2024-03-04T12:00:00Z", "messageId": "msg-2", "destination": ["ana@example.com"] },
+            "open": { "timestamp": "2024-03-04T13:00:00Z", "ipAddress": "192.0.2.1", "userAgent": "Mail" }
+        })
+        .to_string();
+
+        let Notification::Event(event) = parse_notification(&body, "sqs-2").unwrap() else {
+            panic!("expected an event");
+        };
+        assert_eq!(event.event_id, "sqs-2");
+        assert_eq!(event.recipients, vec!["ana@example.com"]);
+        assert!(event.detail.get("ipAddress").is_none());
+        assert_eq!(event.detail["userAgent"], "Mail");
+    }
+
+    #[test]
+    fn test_parse_other_messages() {
+        let confirmation = json!({ "Type": "SubscriptionConfirmation", "MessageId": "sns-3" }).to_string();
+        assert_eq!(
+            parse_notification(&confirmation, "sqs-3").unwrap(),
+            Notification::Ignored("SubscriptionConfirmation".to_string())
+        );
+        assert!(parse_notification("not json", "sqs-4").is_err());
+        assert!(parse_notification(&json!({ "eventType": "Send" }).to_string(), "sqs-5").is_err());
+    }
+}
@@ -0,0 +1,392 @@
+use crate::adapter::email_preferences::EmailSuppressed;
+use crate::adapter::email_templates::EmailTemplateName;
+use crate::adapter::mailer::Mailer;
+use crate::adapter::ses::TemplateData;
+use crate::adapter::sqs::QueueMessage;
+use crate::jobs::queue_worker::{MessageHandler, PoisonMessage};
+use crate::jobs::scheduler::Job;
+use crate::model::email_queue::{retry_delay, EmailQueueBacklog, EmailQueueRepository, EmailQueued, QueuedEmail};
+use crate::model::otp::OtpRepository;
+use anyhow::Result;
+use chrono::Utc;
+use futures::stream::{self, StreamExt};
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Arc;
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+/// Seconds a claimed email may take to send before another worker may claim it again
+const SEND_LEASE_SECONDS: i64 = 120;
+/// Seconds a due email may wait before the backlog is reported as falling behind
+const BACKLOG_WARNING_SECONDS: i64 = 600;
+
+/// Settings of the email queue worker
+#[derive(Debug, Clone)]
+pub struct EmailQueueSettings {
+    /// Emails claimed per run
+    pub batch_size: i64,
+    /// Sends in flight at once
+    pub concurrency: usize,
+    /// Attempts before an email is dead
+    pub max_attempts: i32,
+}
+
+/// What happened to a claimed email
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Delivery {
+    Sent,
+    /// The recipient opted out of the email's category
+    Suppressed,
+    Retrying,
+    Dead,
+}
+
+/// Counters of the email queue worker, readable while it runs
+#[derive(Debug, Default)]
+struct EmailQueueMetrics {
+    sent: AtomicU64,
+    suppressed: AtomicU64,
+    retried: AtomicU64,
+    dead: AtomicU64,
+    due: AtomicI64,
+    oldest_due_seconds: AtomicI64,
+}
+
+/// Point-in-time copy of the worker's counters and the backlog seen after its last run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EmailQueueSnapshot {
+    pub sent: u64,
+    pub suppressed: u64,
+    pub retried: u64,
+    pub dead: u64,
+    /// Emails due but not yet sent
+    pub due: i64,
+    /// Seconds the longest-waiting due email has waited
+    pub oldest_due_seconds: i64,
+}
+
+impl EmailQueueMetrics {
+    fn record(&self, delivery: Delivery) {
+        let counter = match delivery {
+            Delivery::Sent => &self.sent,
+            Delivery::Suppressed => &self.suppressed,
+            Delivery::Retrying => &self.retried,
+            Delivery::Dead => &self.dead,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_backlog(&self, due: i64, oldest_due_seconds: i64) {
+        self.due.store(due, Ordering::Relaxed);
+        self.oldest_due_seconds.store(oldest_due_seconds, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> EmailQueueSnapshot {
+        EmailQueueSnapshot {
+            sent: self.sent.load(Ordering::Relaxed),
+            suppressed: self.suppressed.load(Ordering::Relaxed),
+            retried: self.retried.load(Ordering::Relaxed),
+            dead: self.dead.load(Ordering::Relaxed),
+            due: self.due.load(Ordering::Relaxed),
+            oldest_due_seconds: self.oldest_due_seconds.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Scheduled job sending queued emails, retrying failures with exponential backoff
+pub struct EmailQueueWorker {
+    queue: EmailQueueRepository,
+    /// Sign-in codes, which are queued by id and only read back when sent
+    otp: OtpRepository,
+    mailer: Arc<Mailer>,
+    settings: EmailQueueSettings,
+    metrics: EmailQueueMetrics,
+}
+
+impl EmailQueueWorker {
+    pub fn new(
+        queue: EmailQueueRepository,
+        otp: OtpRepository,
+        mailer: Arc<Mailer>,
+        settings: EmailQueueSettings,
+    ) -> Self {
+        Self {
+            queue,
+            otp,
+            mailer,
+            settings,
+            metrics: EmailQueueMetrics::default(),
+        }
+    }
+
+    pub fn metrics(&self) -> EmailQueueSnapshot {
+        self.metrics.snapshot()
+    }
+
+    /// Claim and send one round of emails; returns how many were claimed
+    pub async fn run_once(&self) -> Result<usize> {
+        let abandoned = self
+            .queue
+            .bury_abandoned(SEND_LEASE_SECONDS, self.settings.max_attempts)
+            .await?;
+        if abandoned > 0 {
+            error!(abandoned, "Queued emails dead after their last send did not finish");
+        }
+        let expired = self.queue.bury_expired().await?;
+        if expired > 0 {
+            warn!(expired, "Queued emails expired before they could be sent");
+        }
+        if let Err(e) = self.otp.forget_expired_codes().await {
+            warn!(error = ?e, "Failed to drop expired sign-in codes");
+        }
+
+        let emails = self
+            .queue
+            .claim(self.settings.batch_size, SEND_LEASE_SECONDS, self.settings.max_attempts)
+            .await?;
+        let claimed = emails.len();
+
+        let deliveries = stream::iter(emails)
+            .map(|email| self.deliver(email))
+            .buffer_unordered(self.settings.concurrency.max(1))
+            .collect::<Vec<Delivery>>()
+            .await;
+        for delivery in &deliveries {
+            self.metrics.record(*delivery);
+        }
+
+        let count = |delivery: Delivery| deliveries.iter().filter(|d| **d == delivery).count();
+        if claimed > 0 {
+            info!(
+                claimed,
+                sent = count(Delivery::Sent),
+                suppressed = count(Delivery::Suppressed),
+                retrying = count(Delivery::Retrying),
+                dead = count(Delivery::Dead),
+                "Email queue processed"
+            );
+        }
+
+        // Only worth a query when the worker might be falling behind
+        if claimed as i64 >= self.settings.batch_size {
+            self.report_backlog().await;
+        } else {
+            self.metrics.record_backlog(0, 0);
+        }
+        Ok(claimed)
+    }
+
+    /// Record what is still waiting after a full batch, warning when due emails wait too long
+    async fn report_backlog(&self) {
+        let backlog = match self.queue.backlog().await {
+            Ok(backlog) => backlog,
+            Err(e) => {
+                warn!(error = ?e, "Failed to read email queue backlog");
+                return;
+            }
+        };
+        let oldest_due_seconds = backlog.oldest_due_age(Utc::now()).map_or(0, |age| age.num_seconds());
+        self.metrics.record_backlog(backlog.due, oldest_due_seconds);
+
+        let EmailQueueBacklog {
+            due,
+            scheduled,
+            sending,
+            ..
+        } = backlog;
+        if falling_behind(oldest_due_seconds) {
+            warn!(
+                due,
+                scheduled, sending, oldest_due_seconds, "Email queue falling behind"
+            );
+            return;
+        }
+        info!(due, scheduled, sending, oldest_due_seconds, "Email queue backlog");
+    }
+
+    async fn deliver(&self, email: QueuedEmail) -> Delivery {
+        let now = Utc::now();
+        let (reason, permanent) = if email.is_expired(now) {
+            ("Expired before it could be sent".to_string(), true)
+        } else if let Some(name) = email.template_name() {
+            let data = match self.template_data(&email, name).await {
+                Ok(Some(data)) => data,
+                Ok(None) => {
+                    return self
+                        .fail(&email, "Sign-in code used or expired before it could be sent", true)
+                        .await;
+                }
+                Err(e) => return self.fail(&email, &format!("{:#}", e), false).await,
+            };
+            match self
+                .mailer
+                .send_template_email(
+                    email.recipient.as_str(),
+                    name,
+                    data,
+                    email.locale(),
+                    Some(email.idempotency_key()),
+                )
+                .await
+            {
+                Ok(response) => {
+                    if let Err(e) = self.queue.mark_sent(email.id, &response.message_id).await {
+                        // Left claimed, so it is picked up again once its lease expires; its
+                        // idempotency key keeps it from being delivered twice
+                        warn!(email_id = %email.id, error = ?e, "Failed to record sent email");
+                    }
+                    debug!(email_id = %email.id, message_id = %response.message_id, "Queued email sent");
+                    return Delivery::Sent;
+                }
+                Err(e) if EmailSuppressed::is(&e) => {
+                    // Kept as dead so the queue shows why it wasn't sent
+                    if let Err(e) = self.queue.bury(email.id, &e.to_string()).await {
+                        warn!(email_id = %email.id, error = ?e, "Failed to record suppressed email");
+                    }
+                    info!(email_id = %email.id, template = %email.template, "Queued email not sent; recipient unsubscribed");
+                    return Delivery::Suppressed;
+                }
+                Err(e) => (format!("{:#}", e), false),
+            }
+        } else {
+            (format!("Unknown template '{}'", email.template), true)
+        };
+        self.fail(&email, &reason, permanent).await
+    }
+
+    /// Placeholder values of `email`, with a sign-in code read back from its OTP; `None` when the
+    /// code was used, replaced or expired
+    async fn template_data(&self, email: &QueuedEmail, name: EmailTemplateName) -> Result<Option<TemplateData>> {
+        let mut data = email.data();
+        if name == EmailTemplateName::OtpLogin {
+            let Some(otp_id) = data.get("otp_id").and_then(|id| Uuid::parse_str(id).ok()) else {
+                return Ok(None);
+            };
+            let Some(code) = self.otp.pending_code(otp_id).await? else {
+                return Ok(None);
+            };
+            data.insert("otp_code", code);
+        }
+        Ok(Some(data))
+    }
+
+    /// Retry a failed send, or bury the email when the failure is permanent or it is out of attempts
+    async fn fail(&self, email: &QueuedEmail, reason: &str, permanent: bool) -> Delivery {
+        let now = Utc::now();
+        let delivery = after_failure(email.attempts, self.settings.max_attempts, permanent);
+        let recorded = match delivery {
+            Delivery::Retrying => {
+                let delay =
+                    chrono::Duration::from_std(retry_delay(email.attempts)).unwrap_or(chrono::Duration::hours(1));
+                warn!(email_id = %email.id, attempts = email.attempts, error = %reason, "Email send failed; retrying");
+                self.queue.retry(email.id, reason, now + delay).await
+            }
+            _ => {
+                error!(
+                    email_id = %email.id,
+                    template = %email.template,
+                    attempts = email.attempts,
+                    error = %reason,
+                    "Email dead"
+                );
+                self.queue.bury(email.id, reason).await
+            }
+        };
+        if let Err(e) = recorded {
+            // Left claimed; picked up again once its lease expires
+            warn!(email_id = %email.id, error = ?e, "Failed to record email failure");
+        }
+        delivery
+    }
+}
+
+/// Retry a failed send unless the failure is permanent or the attempts are used up
+fn after_failure(attempts: i32, max_attempts: i32, permanent: bool) -> Delivery {
+    if permanent || attempts >= max_attempts {
+        Delivery::Dead
+    } else {
+        Delivery::Retrying
+    }
+}
+
+/// Whether due emails have waited long enough that sending can't keep up
+fn falling_behind(oldest_due_seconds: i64) -> bool {
+    oldest_due_seconds >= BACKLOG_WARNING_SECONDS
+}
+
+#[async_trait::async_trait]
+impl Job for EmailQueueWorker {
+    fn name(&self) -> &'static str {
+        "email_queue"
+    }
+
+    async fn run(&self) -> Result<()> {
+        let claimed = self.run_once().await?;
+        if claimed == 0 {
+            debug!("No queued emails due");
+        }
+        Ok(())
+    }
+}
+
+/// Sends emails as they are announced, when the repository announces them on a queue. Failed sends
+/// are retried by the scheduled runs, as the table records the attempt.
+#[async_trait::async_trait]
+impl MessageHandler for EmailQueueWorker {
+    fn name(&self) -> &'static str {
+        "email_queue"
+    }
+
+    async fn handle(&self, message: &QueueMessage) -> Result<()> {
+        let queued: EmailQueued = serde_json::from_str(&message.body).map_err(|e| PoisonMessage(e.to_string()))?;
+        // Nothing to do when it was already sent, isn't due yet or is being sent by a scheduled run
+        let Some(email) = self
+            .queue
+            .claim_by_id(queued.email_id, SEND_LEASE_SECONDS, self.settings.max_attempts)
+            .await?
+        else {
+            debug!(email_id = %queued.email_id, "Announced email not claimable");
+            return Ok(());
+        };
+        let delivery = self.deliver(email).await;
+        self.metrics.record(delivery);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_after_failure() {
+        assert_eq!(after_failure(1, 5, false), Delivery::Retrying);
+        assert_eq!(after_failure(4, 5, false), Delivery::Retrying);
+        assert_eq!(after_failure(5, 5, false), Delivery::Dead);
+        assert_eq!(after_failure(1, 5, true), Delivery::Dead);
+    }
+
+    #[test]
+    fn test_metrics_count_deliveries() {
+        let metrics = EmailQueueMetrics::default();
+        for delivery in [
+            Delivery::Sent,
+            Delivery::Sent,
+            Delivery::Suppressed,
+            Delivery::Retrying,
+            Delivery::Dead,
+        ] {
+            metrics.record(delivery);
+        }
+        metrics.record_backlog(250, 720);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.sent, 2);
+        assert_eq!(snapshot.suppressed, 1);
+        assert_eq!(snapshot.retried, 1);
+        assert_eq!(snapshot.dead, 1);
+        assert_eq!(snapshot.due, 250);
+        assert!(falling_behind(snapshot.oldest_due_seconds));
+        assert!(!falling_behind(60));
+    }
+}
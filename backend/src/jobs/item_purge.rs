@@ -0,0 +1,34 @@
+use crate::jobs::scheduler::Job;
+use crate::model::plaid_item::PlaidItemRepository;
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use tracing::info;
+
+/// Scheduled job deleting the data of items unlinked longer ago than the retention period
+pub struct RemovedItemPurgeJob {
+    items: PlaidItemRepository,
+    retention_days: i64,
+}
+
+impl RemovedItemPurgeJob {
+    pub fn new(items: PlaidItemRepository, retention_days: i64) -> Self {
+        Self {
+            items,
+            retention_days: retention_days.max(0),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Job for RemovedItemPurgeJob {
+    fn name(&self) -> &'static str {
+        "removed_item_purge"
+    }
+
+    async fn run(&self) -> Result<()> {
+        let removed_before = Utc::now() - Duration::days(self.retention_days);
+        let purged = self.items.purge_removed(removed_before).await?;
+        info!(purged, retention_days = self.retention_days, "Purged removed Plaid items");
+        Ok(())
+    }
+}
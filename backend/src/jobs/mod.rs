@@ -0,0 +1,217 @@
+// Background jobs
+pub mod ai_batch;
+pub mod ai_budgets;
+pub mod alerts;
+pub mod bills;
+pub mod campaigns;
+pub mod categorization;
+pub mod digest;
+pub mod email_events;
+pub mod email_queue;
+pub mod item_purge;
+pub mod net_worth;
+pub mod partitions;
+pub mod queue_worker;
+pub mod scheduler;
+pub mod spending;
+pub mod statements;
+pub mod transaction_sync;
+pub mod transfers;
+pub mod webhooks;
+
+pub use ai_batch::{AiBatchProcessor, AiBatchSettings};
+pub use ai_budgets::AiBudgetMonitor;
+pub use alerts::AlertEvaluator;
+pub use bills::{BillDetectionJob, BillReminderJob};
+pub use campaigns::{EmailCampaignSender, EmailCampaignSettings};
+pub use categorization::TransactionCategorizer;
+pub use digest::{WeeklyDigestJob, WeeklyDigestSender};
+pub use email_events::EmailEventConsumer;
+pub use email_queue::{EmailQueueSettings, EmailQueueWorker};
+pub use item_purge::RemovedItemPurgeJob;
+pub use net_worth::NetWorthSnapshotJob;
+pub use partitions::TransactionPartitionJob;
+pub use queue_worker::{MessageHandler, PoisonMessage, QueueWorker, QueueWorkerSettings};
+pub use scheduler::{Job, Scheduler};
+pub use spending::SpendingAggregateJob;
+pub use statements::StatementFetchJob;
+pub use transaction_sync::{ItemSyncOutcome, SyncCoordinator, SyncMetricsSnapshot, TransactionSyncJob};
+pub use transfers::TransferEventSync;
+pub use webhooks::{WebhookDeliverySettings, WebhookDeliveryWorker};
+
+use crate::settings::SettingValues;
+
+/// Background job settings, a section of `Settings`
+#[derive(Debug, Clone)]
+pub struct JobsConfig {
+    /// Run scheduled jobs in this process
+    pub enabled: bool,
+    /// Cron expression for the transaction sync job
+    pub transaction_sync_schedule: String,
+    /// Items synced in parallel per run
+    pub transaction_sync_concurrency: usize,
+    /// Cron expression for SLO burn-rate evaluation
+    pub slo_monitor_schedule: String,
+    /// Cron expression for checking AI feature spending against monthly budgets
+    pub ai_budget_monitor_schedule: String,
+    /// Cron expression for AI transaction categorization
+    pub categorization_schedule: String,
+    /// Transactions sent to the model per request
+    pub categorization_batch_size: usize,
+    /// Batches categorized per run
+    pub categorization_max_batches: usize,
+    /// Answers below this confidence are stored as uncategorized
+    pub categorization_min_confidence: f64,
+    /// Always ask the model instead of reusing cached replies for identical batches
+    pub categorization_bypass_cache: bool,
+    /// Cron expression for processing queued AI batch tasks
+    pub ai_batch_schedule: String,
+    /// AI batch tasks claimed per run
+    pub ai_batch_tasks_per_run: i64,
+    /// AI batch tasks sent to the model per request
+    pub ai_batch_chunk_size: usize,
+    /// AI batch requests in flight at once
+    pub ai_batch_concurrency: usize,
+    /// AI batch requests started per minute; 0 for no limit
+    pub ai_batch_requests_per_minute: u32,
+    /// Attempts before an AI batch task fails
+    pub ai_batch_max_attempts: i32,
+    /// Cron expression for the daily net worth snapshot
+    pub net_worth_snapshot_schedule: String,
+    /// Cron expression for deleting the data of unlinked items
+    pub removed_item_purge_schedule: String,
+    /// Days an unlinked item's data is kept before it is deleted
+    pub removed_item_retention_days: i64,
+    /// Cron expression for recurring bill detection
+    pub bill_detection_schedule: String,
+    /// Cron expression for bill due-date reminders
+    pub bill_reminder_schedule: String,
+    /// Days before a bill's due date its reminder is sent
+    pub bill_reminder_days_before: i64,
+    /// Hour of the day, in the user's time zone, bill reminders are sent at
+    pub bill_reminder_local_hour: u32,
+    /// Cron expression for copying new Plaid statements into S3
+    pub statement_fetch_schedule: String,
+    /// Cron expression for checking whose weekly summary email is due; must fire at least hourly
+    pub weekly_digest_schedule: String,
+    /// Hour on Monday, in the user's time zone, the weekly summary email is sent at
+    pub weekly_digest_local_hour: u32,
+    /// Cron expression for catching up on transfer events, in case a Plaid webhook was missed
+    pub transfer_event_sync_schedule: String,
+    /// Cron expression for creating upcoming monthly transaction partitions
+    pub transaction_partition_schedule: String,
+    /// Months of transaction partitions kept created ahead of the current month
+    pub transaction_partition_months_ahead: u32,
+    /// Cron expression for refreshing the monthly spending totals of changed months
+    pub spending_aggregate_schedule: String,
+    /// User-months recomputed per refresh batch
+    pub spending_aggregate_batch_size: i64,
+    /// Cron expression for sending queued emails
+    pub email_queue_schedule: String,
+    /// Queued emails claimed per run
+    pub email_queue_batch_size: i64,
+    /// Queued emails sent at once
+    pub email_queue_concurrency: usize,
+    /// Attempts before a queued email is dead
+    pub email_queue_max_attempts: i32,
+    /// Cron expression for sending email campaigns
+    pub email_campaign_schedule: String,
+    /// Campaign recipients claimed per run
+    pub email_campaign_batch_size: i64,
+    /// Campaign emails sent at once
+    pub email_campaign_concurrency: usize,
+    /// Campaign emails started per second
+    pub email_campaign_sends_per_second: u32,
+    /// Attempts before a campaign recipient fails
+    pub email_campaign_max_attempts: i32,
+    /// Cron expression for reading SES delivery events from their queue
+    pub email_events_schedule: String,
+    /// Cron expression for calling webhook endpoints with queued events
+    pub webhook_delivery_schedule: String,
+    /// Webhook deliveries claimed per run
+    pub webhook_delivery_batch_size: i64,
+    /// Webhook calls in flight at once
+    pub webhook_delivery_concurrency: usize,
+    /// Attempts before a webhook delivery fails for good
+    pub webhook_delivery_max_attempts: i32,
+}
+
+impl JobsConfig {
+    /// Read from settings; each key is the field name, e.g. TRANSACTION_SYNC_SCHEDULE for
+    /// `transaction_sync_schedule`, and JOBS_ENABLED=false turns every job off
+    pub fn from_values(values: &mut SettingValues) -> Self {
+        Self {
+            enabled: values.flag("jobs-enabled", true),
+            transaction_sync_schedule: values.or("transaction-sync-schedule", "0 0 */4 * * *"),
+            transaction_sync_concurrency: values.parsed("transaction-sync-concurrency", 4),
+            slo_monitor_schedule: values.or("slo-monitor-schedule", "0 * * * * *"),
+            ai_budget_monitor_schedule: values.or("ai-budget-monitor-schedule", "0 */10 * * * *"),
+            categorization_schedule: values.or("categorization-schedule", "0 */15 * * * *"),
+            categorization_batch_size: values.parsed("categorization-batch-size", 50),
+            categorization_max_batches: values.parsed("categorization-max-batches", 10),
+            categorization_min_confidence: values.parsed("categorization-min-confidence", 0.5),
+            categorization_bypass_cache: values.flag("categorization-bypass-cache", false),
+            ai_batch_schedule: values.or("ai-batch-schedule", "*/15 * * * * *"),
+            ai_batch_tasks_per_run: values
+                .parsed_optional("ai-batch-tasks-per-run")
+                .filter(|n: &i64| *n > 0)
+                .unwrap_or(200),
+            ai_batch_chunk_size: values.parsed("ai-batch-chunk-size", 25),
+            ai_batch_concurrency: values.parsed("ai-batch-concurrency", 2),
+            ai_batch_requests_per_minute: values.parsed("ai-batch-requests-per-minute", 30),
+            ai_batch_max_attempts: values.parsed("ai-batch-max-attempts", 3),
+            net_worth_snapshot_schedule: values.or("net-worth-snapshot-schedule", "0 55 23 * * *"),
+            removed_item_purge_schedule: values.or("removed-item-purge-schedule", "0 30 3 * * *"),
+            removed_item_retention_days: values.parsed("removed-item-retention-days", 30),
+            bill_detection_schedule: values.or("bill-detection-schedule", "0 15 2 * * *"),
+            bill_reminder_schedule: values.or("bill-reminder-schedule", "0 0 14 * * *"),
+            bill_reminder_days_before: values.parsed("bill-reminder-days-before", 3),
+            bill_reminder_local_hour: values
+                .parsed_optional("bill-reminder-local-hour")
+                .filter(|hour: &u32| *hour < 24)
+                .unwrap_or(9),
+            statement_fetch_schedule: values.or("statement-fetch-schedule", "0 45 4 * * *"),
+            weekly_digest_schedule: values.or("weekly-digest-schedule", "0 0 * * * *"),
+            weekly_digest_local_hour: values
+                .parsed_optional("weekly-digest-local-hour")
+                .filter(|hour: &u32| *hour < 24)
+                .unwrap_or(8),
+            transfer_event_sync_schedule: values.or("transfer-event-sync-schedule", "0 */30 * * * *"),
+            transaction_partition_schedule: values.or("transaction-partition-schedule", "0 10 1 * * *"),
+            transaction_partition_months_ahead: values.parsed("transaction-partition-months-ahead", 3),
+            spending_aggregate_schedule: values.or("spending-aggregate-schedule", "0 */5 * * * *"),
+            spending_aggregate_batch_size: values
+                .parsed_optional("spending-aggregate-batch-size")
+                .filter(|size: &i64| *size > 0)
+                .unwrap_or(500),
+            email_queue_schedule: values.or("email-queue-schedule", "*/5 * * * * *"),
+            email_queue_batch_size: values
+                .parsed_optional("email-queue-batch-size")
+                .filter(|size: &i64| *size > 0)
+                .unwrap_or(50),
+            email_queue_concurrency: values.parsed("email-queue-concurrency", 4),
+            email_queue_max_attempts: values.parsed("email-queue-max-attempts", 8),
+            email_campaign_schedule: values.or("email-campaign-schedule", "*/10 * * * * *"),
+            email_campaign_batch_size: values
+                .parsed_optional("email-campaign-batch-size")
+                .filter(|size: &i64| *size > 0)
+                .unwrap_or(100),
+            email_campaign_concurrency: values.parsed("email-campaign-concurrency", 4),
+            // Below the 14 per second SES allows new production accounts, leaving room for the queue
+            email_campaign_sends_per_second: values
+                .parsed_optional("email-campaign-sends-per-second")
+                .filter(|rate: &u32| *rate > 0)
+                .unwrap_or(10),
+            email_campaign_max_attempts: values.parsed("email-campaign-max-attempts", 3),
+            email_events_schedule: values.or("email-events-schedule", "*/15 * * * * *"),
+            webhook_delivery_schedule: values.or("webhook-delivery-schedule", "*/5 * * * * *"),
+            webhook_delivery_batch_size: values
+                .parsed_optional("webhook-delivery-batch-size")
+                .filter(|size: &i64| *size > 0)
+                .unwrap_or(50),
+            webhook_delivery_concurrency: values.parsed("webhook-delivery-concurrency", 8),
+            // With the backoff doubling from 30 seconds, 12 attempts span about 15 hours
+            webhook_delivery_max_attempts: values.parsed("webhook-delivery-max-attempts", 12),
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use crate::jobs::scheduler::Job;
+use crate::model::net_worth::NetWorthRepository;
+use anyhow::Result;
+use chrono::Utc;
+use tracing::info;
+
+/// Scheduled job recording today's net worth snapshot for every user
+pub struct NetWorthSnapshotJob {
+    snapshots: NetWorthRepository,
+}
+
+impl NetWorthSnapshotJob {
+    pub fn new(snapshots: NetWorthRepository) -> Self {
+        Self { snapshots }
+    }
+}
+
+#[async_trait::async_trait]
+impl Job for NetWorthSnapshotJob {
+    fn name(&self) -> &'static str {
+        "net_worth_snapshot"
+    }
+
+    async fn run(&self) -> Result<()> {
+        let today = Utc::now().date_naive();
+        let recorded = self.snapshots.record_snapshots(today).await?;
+        info!(snapshot_date = %today, recorded, "Scheduled net worth snapshot finished");
+        Ok(())
+    }
+}
@@ -0,0 +1,62 @@
+use crate::jobs::scheduler::Job;
+use crate::model::transaction::TransactionRepository;
+use anyhow::Result;
+use chrono::{Datelike, Months, NaiveDate, Utc};
+use tracing::info;
+
+/// First day of the month containing `today` and of each of the next `months_ahead` months
+fn upcoming_months(today: NaiveDate, months_ahead: u32) -> Vec<NaiveDate> {
+    let first = today.with_day(1).unwrap_or(today);
+    (0..=months_ahead)
+        .filter_map(|offset| first.checked_add_months(Months::new(offset)))
+        .collect()
+}
+
+/// Scheduled job creating monthly transaction partitions ahead of time, so new
+/// transactions never land in the default partition
+pub struct TransactionPartitionJob {
+    transactions: TransactionRepository,
+    months_ahead: u32,
+}
+
+impl TransactionPartitionJob {
+    pub fn new(transactions: TransactionRepository, months_ahead: u32) -> Self {
+        Self {
+            transactions,
+            months_ahead,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Job for TransactionPartitionJob {
+    fn name(&self) -> &'static str {
+        "transaction_partitions"
+    }
+
+    async fn run(&self) -> Result<()> {
+        let months = upcoming_months(Utc::now().date_naive(), self.months_ahead);
+        let created = self.transactions.ensure_partitions(&months).await?;
+        info!(created, months_ahead = self.months_ahead, "Transaction partition maintenance finished");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upcoming_months_cross_year() {
+        let months = upcoming_months(NaiveDate::from_ymd_opt(2024, 11, 30).unwrap(), 3);
+        assert_eq!(
+            months,
+            vec![
+                NaiveDate::from_ymd_opt(2024, 11, 1).unwrap(),
+                NaiveDate::from_ymd_opt(2024, 12, 1).unwrap(),
+                NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+                NaiveDate::from_ymd_opt(2025, 2, 1).unwrap(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,254 @@
+use crate::adapter::sqs::{QueueMessage, SqsQueue, MAX_RECEIVE_MESSAGES};
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::{interval_at, Instant};
+use tracing::{debug, error, info, instrument, warn};
+
+/// Visibility timeout SQS gives a queue unless it is configured otherwise
+const DEFAULT_VISIBILITY_TIMEOUT: Duration = Duration::from_secs(30);
+/// Wait before the first retry of a failed message; doubled on every further receive
+const BASE_RETRY_DELAY: Duration = Duration::from_secs(10);
+/// Longest wait before a retry
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60 * 60);
+/// Pause after a failed receive, so an unreachable queue isn't polled in a tight loop
+const RECEIVE_ERROR_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Handles messages received from one queue
+#[async_trait]
+pub trait MessageHandler: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Handle one message. On success it is deleted; on failure it is retried with backoff, unless
+    /// the error is a `PoisonMessage`.
+    async fn handle(&self, message: &QueueMessage) -> Result<()>;
+}
+
+/// The message can never be handled, so retrying it is pointless
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Unprocessable queue message: {0}")]
+pub struct PoisonMessage(pub String);
+
+impl PoisonMessage {
+    /// Whether `error` is, or was caused by, an unprocessable message
+    pub fn is(error: &anyhow::Error) -> bool {
+        error.chain().any(|cause| cause.is::<PoisonMessage>())
+    }
+}
+
+/// Settings of a queue worker
+#[derive(Debug, Clone)]
+pub struct QueueWorkerSettings {
+    /// Messages handled at once
+    pub concurrency: usize,
+    /// Receives before a failing message is moved to the dead-letter queue
+    pub max_receives: u32,
+}
+
+impl Default for QueueWorkerSettings {
+    fn default() -> Self {
+        Self {
+            concurrency: MAX_RECEIVE_MESSAGES as usize,
+            max_receives: 5,
+        }
+    }
+}
+
+/// What became of a received message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Disposition {
+    Handled,
+    /// Hidden for the delay, then received again
+    Retrying(Duration),
+    DeadLettered,
+    /// Unprocessable and there is no dead-letter queue to keep it in
+    Dropped,
+}
+
+/// Where a failed message goes next
+fn after_failure(receive_count: u32, max_receives: u32, poison: bool, has_dead_letter_queue: bool) -> Disposition {
+    match (poison || receive_count >= max_receives, has_dead_letter_queue) {
+        (true, true) => Disposition::DeadLettered,
+        (true, false) if poison => Disposition::Dropped,
+        // Without a dead-letter queue here, the queue's own redrive policy decides when to give up
+        _ => Disposition::Retrying(retry_delay(receive_count)),
+    }
+}
+
+/// Wait before a message received `receive_count` times is received again
+fn retry_delay(receive_count: u32) -> Duration {
+    let doublings = receive_count.saturating_sub(1).min(16);
+    BASE_RETRY_DELAY.saturating_mul(1 << doublings).min(MAX_RETRY_DELAY)
+}
+
+/// Long-running consumer of an SQS queue.
+///
+/// Receives in batches, keeps a message hidden for as long as its handler runs, deletes it once
+/// handled, and backs off failed messages by changing their visibility. Messages that keep failing,
+/// or can never be handled, are moved to the queue's dead-letter queue when it has one.
+pub struct QueueWorker {
+    queue: SqsQueue,
+    dead_letters: Option<SqsQueue>,
+    handler: Arc<dyn MessageHandler>,
+    settings: QueueWorkerSettings,
+}
+
+impl QueueWorker {
+    pub fn new(queue: SqsQueue, handler: Arc<dyn MessageHandler>, settings: QueueWorkerSettings) -> Self {
+        let dead_letters = queue.dead_letter_queue();
+        Self {
+            queue,
+            dead_letters,
+            handler,
+            settings,
+        }
+    }
+
+    /// Receive and handle one batch; returns how many messages were handled
+    pub async fn run_once(&self) -> Result<usize> {
+        let messages = self.queue.receive(MAX_RECEIVE_MESSAGES).await?;
+        let dispositions = stream::iter(messages)
+            .map(|message| self.process(message))
+            .buffer_unordered(self.settings.concurrency.max(1))
+            .collect::<Vec<Disposition>>()
+            .await;
+        Ok(dispositions.iter().filter(|d| **d == Disposition::Handled).count())
+    }
+
+    /// Move up to `limit` messages from the dead-letter queue back onto the queue, e.g. once the
+    /// bug that failed them is fixed; returns how many were moved
+    pub async fn redrive(&self, limit: usize) -> Result<usize> {
+        match &self.dead_letters {
+            Some(dead_letters) => self.queue.redrive_from(dead_letters, limit).await,
+            None => Ok(0),
+        }
+    }
+
+    /// Handle messages until the process exits
+    pub fn spawn(self: Arc<Self>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            info!(handler = self.handler.name(), queue_url = %self.queue.queue_url(), "Queue worker started");
+            loop {
+                // Receives long-poll, so an empty queue doesn't spin
+                if let Err(e) = self.run_once().await {
+                    warn!(handler = self.handler.name(), error = ?e, "Failed to receive queue messages");
+                    tokio::time::sleep(RECEIVE_ERROR_BACKOFF).await;
+                }
+            }
+        })
+    }
+
+    #[instrument(
+        skip(self, message),
+        fields(handler = self.handler.name(), message_id = %message.message_id, receive_count = message.receive_count)
+    )]
+    async fn process(&self, message: QueueMessage) -> Disposition {
+        let result = self.handle_visible(&message).await;
+        let error = match result {
+            Ok(()) => {
+                if let Err(e) = self.queue.delete(&message).await {
+                    // Redelivered once its visibility timeout runs out; handlers tolerate repeats
+                    warn!(error = ?e, "Failed to delete handled message");
+                }
+                debug!("Queue message handled");
+                return Disposition::Handled;
+            }
+            Err(e) => e,
+        };
+
+        let disposition = after_failure(
+            message.receive_count,
+            self.settings.max_receives,
+            PoisonMessage::is(&error),
+            self.dead_letters.is_some(),
+        );
+        match disposition {
+            Disposition::Retrying(delay) => {
+                warn!(error = %format!("{:#}", error), retry_in_secs = delay.as_secs(), "Queue message failed; retrying");
+                if let Err(e) = self.queue.change_visibility(&message, delay).await {
+                    warn!(error = ?e, "Failed to delay retry of message");
+                }
+            }
+            Disposition::DeadLettered => {
+                error!(error = %format!("{:#}", error), "Queue message failed; moving it to the dead-letter queue");
+                if let Some(dead_letters) = &self.dead_letters {
+                    // Only deleted once it is safely in the dead-letter queue
+                    let moved = match dead_letters.send(&message.body, Duration::ZERO).await {
+                        Ok(_) => self.queue.delete(&message).await,
+                        Err(e) => Err(e),
+                    };
+                    if let Err(e) = moved {
+                        warn!(error = ?e, "Failed to move message to the dead-letter queue");
+                    }
+                }
+            }
+            Disposition::Dropped => {
+                error!(error = %format!("{:#}", error), "Dropping unprocessable queue message");
+                if let Err(e) = self.queue.delete(&message).await {
+                    warn!(error = ?e, "Failed to delete unprocessable message");
+                }
+            }
+            Disposition::Handled => {}
+        }
+        disposition
+    }
+
+    /// Run the handler, extending the message's visibility before it runs out so no other consumer
+    /// receives it meanwhile
+    async fn handle_visible(&self, message: &QueueMessage) -> Result<()> {
+        let timeout = self.queue.visibility_timeout().unwrap_or(DEFAULT_VISIBILITY_TIMEOUT);
+        let period = (timeout / 2).max(Duration::from_secs(1));
+        let mut heartbeat = interval_at(Instant::now() + period, period);
+
+        let handling = self.handler.handle(message);
+        tokio::pin!(handling);
+        loop {
+            tokio::select! {
+                result = &mut handling => return result,
+                _ = heartbeat.tick() => {
+                    if let Err(e) = self.queue.change_visibility(message, timeout).await {
+                        warn!(error = ?e, "Failed to extend message visibility");
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    #[test]
+    fn test_failed_messages_retry_with_backoff() {
+        assert_eq!(
+            after_failure(1, 5, false, true),
+            Disposition::Retrying(Duration::from_secs(10))
+        );
+        assert_eq!(
+            after_failure(3, 5, false, true),
+            Disposition::Retrying(Duration::from_secs(40))
+        );
+        assert_eq!(retry_delay(30), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn test_exhausted_and_poison_messages() {
+        assert_eq!(after_failure(5, 5, false, true), Disposition::DeadLettered);
+        assert_eq!(after_failure(1, 5, true, true), Disposition::DeadLettered);
+        assert_eq!(after_failure(1, 5, true, false), Disposition::Dropped);
+        // Left for the queue's redrive policy
+        assert!(matches!(after_failure(5, 5, false, false), Disposition::Retrying(_)));
+    }
+
+    #[test]
+    fn test_poison_is_found_through_context() {
+        let error = anyhow::Error::new(PoisonMessage("not JSON".to_string())).context("Failed to read webhook");
+        assert!(PoisonMessage::is(&error));
+        assert!(!PoisonMessage::is(&anyhow!("database unavailable")));
+    }
+}
@@ -0,0 +1,105 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use cron::Schedule;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::{error, info, instrument, warn};
+
+/// A unit of background work run on a schedule
+#[async_trait::async_trait]
+pub trait Job: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    async fn run(&self) -> Result<()>;
+}
+
+struct ScheduledJob {
+    schedule: Schedule,
+    job: Arc<dyn Job>,
+}
+
+/// Runs registered jobs on cron schedules (`sec min hour day month weekday`).
+///
+/// Each job has its own task; a run that overlaps the next fire time delays that
+/// fire rather than running concurrently with itself.
+#[derive(Default)]
+pub struct Scheduler {
+    jobs: Vec<ScheduledJob>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a job with a cron expression, e.g. `0 */15 * * * *` for every 15 minutes
+    pub fn add(mut self, expression: &str, job: Arc<dyn Job>) -> Result<Self> {
+        let schedule = Schedule::from_str(expression)
+            .with_context(|| format!("Invalid schedule '{}' for job {}", expression, job.name()))?;
+        self.jobs.push(ScheduledJob { schedule, job });
+        Ok(self)
+    }
+
+    pub fn len(&self) -> usize {
+        self.jobs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    /// Spawn one task per job
+    pub fn start(self) -> Vec<JoinHandle<()>> {
+        self.jobs
+            .into_iter()
+            .map(|scheduled| tokio::spawn(run_job_loop(scheduled)))
+            .collect()
+    }
+}
+
+#[instrument(skip(scheduled), fields(job = %scheduled.job.name()))]
+async fn run_job_loop(scheduled: ScheduledJob) {
+    info!("Background job scheduled");
+
+    loop {
+        let Some(next) = scheduled.schedule.upcoming(Utc).next() else {
+            warn!("Schedule has no upcoming fire times, stopping job");
+            return;
+        };
+        let wait = (next - Utc::now()).to_std().unwrap_or_default();
+        tokio::time::sleep(wait).await;
+
+        let started = std::time::Instant::now();
+        match scheduled.job.run().await {
+            Ok(()) => info!(duration_ms = started.elapsed().as_millis() as u64, "Background job completed"),
+            Err(e) => error!(error = ?e, duration_ms = started.elapsed().as_millis() as u64, "Background job failed"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct NoopJob;
+
+    #[async_trait::async_trait]
+    impl Job for NoopJob {
+        fn name(&self) -> &'static str {
+            "noop"
+        }
+
+        async fn run(&self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_add_validates_expression() {
+        let scheduler = Scheduler::new().add("0 */15 * * * *", Arc::new(NoopJob)).unwrap();
+        assert_eq!(scheduler.len(), 1);
+
+        assert!(Scheduler::new().add("every now and then", Arc::new(NoopJob)).is_err());
+    }
+}
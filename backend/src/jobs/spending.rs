@@ -0,0 +1,38 @@
+use crate::jobs::scheduler::Job;
+use crate::model::spending::SpendingRepository;
+use anyhow::Result;
+use tracing::info;
+
+/// Scheduled job recomputing the monthly spending totals of months whose transactions,
+/// categories or splits changed
+pub struct SpendingAggregateJob {
+    spending: SpendingRepository,
+    batch_size: i64,
+}
+
+impl SpendingAggregateJob {
+    pub fn new(spending: SpendingRepository, batch_size: i64) -> Self {
+        Self { spending, batch_size }
+    }
+}
+
+#[async_trait::async_trait]
+impl Job for SpendingAggregateJob {
+    fn name(&self) -> &'static str {
+        "spending_aggregates"
+    }
+
+    /// Drain the refresh queue one batch at a time
+    async fn run(&self) -> Result<()> {
+        let mut refreshed = 0;
+        loop {
+            let batch = self.spending.refresh_monthly_totals(self.batch_size).await?;
+            refreshed += batch;
+            if (batch as i64) < self.batch_size {
+                break;
+            }
+        }
+        info!(refreshed, "Spending aggregate refresh finished");
+        Ok(())
+    }
+}
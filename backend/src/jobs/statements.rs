@@ -0,0 +1,158 @@
+use crate::adapter::bank_data::BankDataProviders;
+use crate::adapter::plaid::Statement;
+use crate::adapter::s3::S3Client;
+use crate::jobs::scheduler::Job;
+use crate::model::bank_account::BankAccountRepository;
+use crate::model::plaid_item::{PlaidItem, PlaidItemRepository};
+use crate::model::statement::{statement_object_key, NewBankStatement, StatementRepository};
+use anyhow::Result;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+
+/// Statements listed by Plaid that are new for an item and belong to one of its known accounts
+fn statements_to_fetch<'a>(
+    listed: &'a [Statement],
+    known: &HashSet<String>,
+    account_ids: &HashSet<String>,
+) -> Vec<&'a Statement> {
+    listed
+        .iter()
+        .filter(|statement| !known.contains(&statement.statement_id) && account_ids.contains(&statement.account_id))
+        .collect()
+}
+
+/// Scheduled job copying new monthly statement PDFs from each item's provider (Plaid Statements) into S3
+pub struct StatementFetchJob {
+    providers: BankDataProviders,
+    items: PlaidItemRepository,
+    accounts: BankAccountRepository,
+    statements: StatementRepository,
+    storage: Arc<S3Client>,
+}
+
+impl StatementFetchJob {
+    pub fn new(
+        providers: BankDataProviders,
+        items: PlaidItemRepository,
+        accounts: BankAccountRepository,
+        statements: StatementRepository,
+        storage: Arc<S3Client>,
+    ) -> Self {
+        Self {
+            providers,
+            items,
+            accounts,
+            statements,
+            storage,
+        }
+    }
+
+    /// Store the item's statements not seen before, returning how many were stored
+    async fn fetch_for_item(&self, item: &PlaidItem) -> Result<usize> {
+        let provider = self.providers.get(&item.provider)?;
+        let access_token = self.items.access_token(item).await?;
+        let listed = match provider.list_statements(&access_token).await {
+            Ok(listed) => listed,
+            Err(e) => {
+                // Statements is an opt-in product; most items were linked without it
+                let detail = format!("{:?}", e);
+                if detail.contains("PRODUCTS_NOT_SUPPORTED") || detail.contains("ADDITIONAL_CONSENT_REQUIRED") {
+                    debug!(item_id = %item.item_id, "Item does not provide statements");
+                    return Ok(0);
+                }
+                return Err(e);
+            }
+        };
+
+        let known = self.statements.known_statement_ids(&item.item_id).await?;
+        let account_ids: HashSet<String> = self
+            .accounts
+            .list_by_item(&item.item_id)
+            .await?
+            .into_iter()
+            .map(|account| account.account_id)
+            .collect();
+
+        let mut stored = 0;
+        for statement in statements_to_fetch(&listed, &known, &account_ids) {
+            let pdf = provider.download_statement(&access_token, &statement.statement_id).await?;
+            let object_key = statement_object_key(
+                item.user_id,
+                &statement.account_id,
+                statement.year,
+                statement.month,
+                &statement.statement_id,
+            );
+            let size_bytes = pdf.len() as i64;
+            self.storage.put_object(&object_key, pdf, "application/pdf").await?;
+
+            let recorded = self
+                .statements
+                .record(&NewBankStatement {
+                    statement_id: statement.statement_id.clone(),
+                    user_id: item.user_id,
+                    item_id: item.item_id.clone(),
+                    account_id: statement.account_id.clone(),
+                    year: statement.year,
+                    month: statement.month as i32,
+                    object_key,
+                    size_bytes,
+                })
+                .await?;
+            if recorded.is_some() {
+                stored += 1;
+            }
+        }
+        Ok(stored)
+    }
+}
+
+#[async_trait::async_trait]
+impl Job for StatementFetchJob {
+    fn name(&self) -> &'static str {
+        "statement_fetch"
+    }
+
+    async fn run(&self) -> Result<()> {
+        let items = self.items.list_active().await?;
+
+        let (mut stored, mut failed) = (0, 0);
+        for item in &items {
+            match self.fetch_for_item(item).await {
+                Ok(item_stored) => stored += item_stored,
+                Err(e) => {
+                    warn!(item_id = %item.item_id, error = %e, "Statement fetch failed for item");
+                    failed += 1;
+                }
+            }
+        }
+
+        info!(item_count = items.len(), stored, failed, "Statement fetch finished");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn statement(statement_id: &str, account_id: &str) -> Statement {
+        Statement {
+            statement_id: statement_id.to_string(),
+            account_id: account_id.to_string(),
+            year: 2024,
+            month: 1,
+        }
+    }
+
+    #[test]
+    fn test_statements_to_fetch() {
+        let listed = vec![statement("st_1", "acc_1"), statement("st_2", "acc_1"), statement("st_3", "acc_gone")];
+        let known: HashSet<String> = ["st_1".to_string()].into_iter().collect();
+        let account_ids: HashSet<String> = ["acc_1".to_string()].into_iter().collect();
+
+        let to_fetch = statements_to_fetch(&listed, &known, &account_ids);
+        assert_eq!(to_fetch, vec![&listed[1]]);
+    }
+}
@@ -0,0 +1,390 @@
+use crate::dedup::TransactionDeduplicator;
+use crate::events::{EventBus, ItemSynced};
+use crate::jobs::alerts::AlertEvaluator;
+use crate::jobs::scheduler::Job;
+use crate::model::plaid_item::{PlaidItem, PlaidItemRepository, PlaidItemStatus};
+use crate::model::transaction::SyncSummary;
+use crate::model::transaction_embedding::TransactionEmbedder;
+use crate::model::transaction_sync::TransactionSyncer;
+use anyhow::Result;
+use chrono::Utc;
+use futures::stream::{self, StreamExt};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, instrument, warn};
+
+/// How long a sync holds its item's database claim; a crashed instance's claim lapses after this
+const SYNC_CLAIM_LEASE: Duration = Duration::from_secs(15 * 60);
+
+/// Result of syncing a single item
+#[derive(Debug, Clone)]
+pub struct ItemSyncOutcome {
+    pub item_id: String,
+    pub summary: SyncSummary,
+    pub error: Option<String>,
+    /// Another sync of the same item was already running
+    pub skipped: bool,
+}
+
+impl ItemSyncOutcome {
+    fn skipped(item_id: &str) -> Self {
+        Self {
+            item_id: item_id.to_string(),
+            summary: SyncSummary::default(),
+            error: None,
+            skipped: true,
+        }
+    }
+
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none() && !self.skipped
+    }
+}
+
+/// Process-wide transaction sync counters
+#[derive(Debug, Default)]
+pub struct SyncMetrics {
+    item_syncs: AtomicU64,
+    item_failures: AtomicU64,
+    transactions_added: AtomicU64,
+    transactions_modified: AtomicU64,
+    transactions_removed: AtomicU64,
+}
+
+/// Point-in-time copy of `SyncMetrics`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SyncMetricsSnapshot {
+    pub item_syncs: u64,
+    pub item_failures: u64,
+    pub transactions_added: u64,
+    pub transactions_modified: u64,
+    pub transactions_removed: u64,
+}
+
+impl SyncMetrics {
+    fn record(&self, outcome: &ItemSyncOutcome) {
+        if outcome.skipped {
+            return;
+        }
+        self.item_syncs.fetch_add(1, Ordering::Relaxed);
+        if outcome.error.is_some() {
+            self.item_failures.fetch_add(1, Ordering::Relaxed);
+        }
+        self.transactions_added.fetch_add(outcome.summary.added as u64, Ordering::Relaxed);
+        self.transactions_modified.fetch_add(outcome.summary.modified as u64, Ordering::Relaxed);
+        self.transactions_removed.fetch_add(outcome.summary.removed as u64, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> SyncMetricsSnapshot {
+        SyncMetricsSnapshot {
+            item_syncs: self.item_syncs.load(Ordering::Relaxed),
+            item_failures: self.item_failures.load(Ordering::Relaxed),
+            transactions_added: self.transactions_added.load(Ordering::Relaxed),
+            transactions_modified: self.transactions_modified.load(Ordering::Relaxed),
+            transactions_removed: self.transactions_removed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Item status implied by a Plaid error, if the error is not transient
+fn status_for_error(detail: &str) -> Option<PlaidItemStatus> {
+    if detail.contains("ITEM_LOGIN_REQUIRED") || detail.contains("PENDING_EXPIRATION") {
+        Some(PlaidItemStatus::LoginRequired)
+    } else if detail.contains("ITEM_NOT_FOUND") || detail.contains("ACCESS_NOT_GRANTED") {
+        Some(PlaidItemStatus::Error)
+    } else {
+        None
+    }
+}
+
+fn error_code(detail: &str) -> Option<&'static str> {
+    [
+        "ITEM_LOGIN_REQUIRED",
+        "PENDING_EXPIRATION",
+        "ITEM_NOT_FOUND",
+        "ACCESS_NOT_GRANTED",
+    ]
+    .into_iter()
+    .find(|code| detail.contains(code))
+}
+
+/// Removes an item from the in-flight set when its sync finishes
+struct InFlightGuard {
+    in_flight: Arc<Mutex<HashSet<String>>>,
+    item_id: String,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        if let Ok(mut in_flight) = self.in_flight.lock() {
+            in_flight.remove(&self.item_id);
+        }
+    }
+}
+
+/// Runs item syncs for both the scheduler and on-demand requests, recording status and metrics
+#[derive(Clone)]
+pub struct SyncCoordinator {
+    syncer: TransactionSyncer,
+    items: PlaidItemRepository,
+    concurrency: usize,
+    in_flight: Arc<Mutex<HashSet<String>>>,
+    metrics: Arc<SyncMetrics>,
+    dedup: Option<TransactionDeduplicator>,
+    alerts: Option<Arc<AlertEvaluator>>,
+    embedder: Option<TransactionEmbedder>,
+    events: Option<EventBus>,
+}
+
+impl SyncCoordinator {
+    pub fn new(syncer: TransactionSyncer, items: PlaidItemRepository, concurrency: usize) -> Self {
+        Self {
+            syncer,
+            items,
+            concurrency: concurrency.max(1),
+            in_flight: Arc::new(Mutex::new(HashSet::new())),
+            metrics: Arc::new(SyncMetrics::default()),
+            dedup: None,
+            alerts: None,
+            embedder: None,
+            events: None,
+        }
+    }
+
+    /// Merge duplicate transactions around what each successful item sync stored
+    pub fn with_dedup(mut self, deduplicator: TransactionDeduplicator) -> Self {
+        self.dedup = Some(deduplicator);
+        self
+    }
+
+    /// Evaluate the user's alert rules after each successful item sync
+    pub fn with_alerts(mut self, evaluator: AlertEvaluator) -> Self {
+        self.alerts = Some(Arc::new(evaluator));
+        self
+    }
+
+    /// Embed new and changed transactions for semantic search after each successful item sync
+    pub fn with_embeddings(mut self, embedder: TransactionEmbedder) -> Self {
+        self.embedder = Some(embedder);
+        self
+    }
+
+    /// Publish `ItemSynced` after each successful item sync that stored new transactions
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    pub fn metrics(&self) -> SyncMetricsSnapshot {
+        self.metrics.snapshot()
+    }
+
+    fn acquire(&self, item_id: &str) -> Option<InFlightGuard> {
+        let mut in_flight = self.in_flight.lock().ok()?;
+        if !in_flight.insert(item_id.to_string()) {
+            return None;
+        }
+        Some(InFlightGuard {
+            in_flight: self.in_flight.clone(),
+            item_id: item_id.to_string(),
+        })
+    }
+
+    /// Sync one item unless it is already syncing here or on another instance, then persist
+    /// its sync status
+    #[instrument(skip(self, item), fields(item_id = %item.item_id))]
+    pub async fn sync_item(&self, item: &PlaidItem) -> ItemSyncOutcome {
+        let Some(_guard) = self.acquire(&item.item_id) else {
+            info!("Item sync already in progress, skipping");
+            return ItemSyncOutcome::skipped(&item.item_id);
+        };
+        // The claimed row carries the latest committed cursor, not the caller's snapshot
+        let item = match self.items.claim_sync(&item.item_id, SYNC_CLAIM_LEASE).await {
+            Ok(Some(claimed)) => claimed,
+            Ok(None) => {
+                info!("Item sync claimed by another instance, skipping");
+                return ItemSyncOutcome::skipped(&item.item_id);
+            }
+            Err(e) => {
+                warn!(error = %e, "Failed to claim item sync");
+                let outcome = ItemSyncOutcome {
+                    item_id: item.item_id.clone(),
+                    summary: SyncSummary::default(),
+                    error: Some(e.to_string()),
+                    skipped: false,
+                };
+                self.metrics.record(&outcome);
+                return outcome;
+            }
+        };
+        let item = &item;
+
+        let started = Instant::now();
+        let started_at = Utc::now();
+        let result = self.syncer.sync_item(item).await;
+        let duration_ms = started.elapsed().as_millis().min(i32::MAX as u128) as i32;
+
+        let outcome = match result {
+            Ok(summary) => ItemSyncOutcome {
+                item_id: item.item_id.clone(),
+                summary,
+                error: None,
+                skipped: false,
+            },
+            Err(e) => {
+                let detail = format!("{:?}", e);
+                warn!(error = %detail, "Item sync failed");
+                if let Some(status) = status_for_error(&detail) {
+                    if let Err(e) = self.items.update_status(&item.item_id, status, error_code(&detail)).await {
+                        warn!(error = %e, "Failed to update item status");
+                    }
+                }
+                ItemSyncOutcome {
+                    item_id: item.item_id.clone(),
+                    summary: SyncSummary::default(),
+                    error: Some(e.to_string()),
+                    skipped: false,
+                }
+            }
+        };
+
+        if let Err(e) = self
+            .items
+            .record_sync_result(&item.item_id, outcome.succeeded(), outcome.error.as_deref(), duration_ms)
+            .await
+        {
+            warn!(error = %e, "Failed to record sync result");
+        }
+        // An unreleased claim only delays the next sync until the lease lapses
+        if let Err(e) = self.items.release_sync(&item.item_id).await {
+            warn!(error = %e, "Failed to release item sync claim");
+        }
+        self.metrics.record(&outcome);
+
+        // Duplicates are merged first so alerts don't fire twice for the same purchase
+        if let Some(dedup) = self.dedup.as_ref().filter(|_| outcome.succeeded()) {
+            if let Err(e) = dedup.dedup_item(item, started_at).await {
+                warn!(error = %e, "Failed to merge duplicate transactions");
+            }
+        }
+        if let Some(alerts) = self.alerts.as_ref().filter(|_| outcome.succeeded()) {
+            if let Err(e) = alerts.evaluate_item(item, started_at).await {
+                warn!(error = %e, "Failed to evaluate alert rules");
+            }
+        }
+        // Transactions left unembedded by a failure are picked up after the next sync
+        if let Some(embedder) = self.embedder.as_ref().filter(|_| outcome.succeeded()) {
+            if let Err(e) = embedder.embed_item(&item.item_id).await {
+                warn!(error = %e, "Failed to embed transactions");
+            }
+        }
+        if let Some(events) = self
+            .events
+            .as_ref()
+            .filter(|_| outcome.succeeded() && outcome.summary.added > 0)
+        {
+            events.publish(ItemSynced {
+                user_id: item.user_id,
+                item_id: item.item_id.clone(),
+                started_at,
+                added: outcome.summary.added,
+            });
+        }
+        outcome
+    }
+
+    /// Sync several items with bounded concurrency
+    pub async fn sync_items(&self, items: Vec<PlaidItem>) -> Vec<ItemSyncOutcome> {
+        stream::iter(items)
+            .map(|item| async move { self.sync_item(&item).await })
+            .buffer_unordered(self.concurrency)
+            .collect()
+            .await
+    }
+}
+
+/// Scheduled job syncing every active item
+pub struct TransactionSyncJob {
+    coordinator: SyncCoordinator,
+    items: PlaidItemRepository,
+}
+
+impl TransactionSyncJob {
+    pub fn new(coordinator: SyncCoordinator, items: PlaidItemRepository) -> Self {
+        Self { coordinator, items }
+    }
+}
+
+#[async_trait::async_trait]
+impl Job for TransactionSyncJob {
+    fn name(&self) -> &'static str {
+        "transaction_sync"
+    }
+
+    async fn run(&self) -> Result<()> {
+        let items = self.items.list_active().await?;
+        let item_count = items.len();
+
+        let outcomes = self.coordinator.sync_items(items).await;
+        let failed = outcomes.iter().filter(|o| o.error.is_some()).count();
+        let skipped = outcomes.iter().filter(|o| o.skipped).count();
+        let metrics = self.coordinator.metrics();
+
+        info!(
+            item_count,
+            failed,
+            skipped,
+            total_item_syncs = metrics.item_syncs,
+            total_item_failures = metrics.item_failures,
+            "Scheduled transaction sync finished"
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_for_error() {
+        assert_eq!(
+            status_for_error("Plaid error ITEM_LOGIN_REQUIRED: the login details changed"),
+            Some(PlaidItemStatus::LoginRequired)
+        );
+        assert_eq!(status_for_error("ITEM_NOT_FOUND"), Some(PlaidItemStatus::Error));
+        assert_eq!(status_for_error("connection reset by peer"), None);
+        assert_eq!(error_code("code: ITEM_LOGIN_REQUIRED"), Some("ITEM_LOGIN_REQUIRED"));
+    }
+
+    #[test]
+    fn test_metrics_ignore_skipped_outcomes() {
+        let metrics = SyncMetrics::default();
+        metrics.record(&ItemSyncOutcome {
+            item_id: "item_1".to_string(),
+            summary: SyncSummary { added: 3, modified: 1, removed: 2 },
+            error: None,
+            skipped: false,
+        });
+        metrics.record(&ItemSyncOutcome {
+            item_id: "item_2".to_string(),
+            summary: SyncSummary::default(),
+            error: None,
+            skipped: true,
+        });
+        metrics.record(&ItemSyncOutcome {
+            item_id: "item_3".to_string(),
+            summary: SyncSummary::default(),
+            error: Some("boom".to_string()),
+            skipped: false,
+        });
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.item_syncs, 2);
+        assert_eq!(snapshot.item_failures, 1);
+        assert_eq!(snapshot.transactions_added, 3);
+        assert_eq!(snapshot.transactions_removed, 2);
+    }
+}
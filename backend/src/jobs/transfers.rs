@@ -0,0 +1,330 @@
+use crate::adapter::plaid::{PlaidClient, TransferAuthorizationRequest, TransferDirection, TransferEvent};
+use crate::adapter::sqs::QueueMessage;
+use crate::jobs::queue_worker::{MessageHandler, PoisonMessage};
+use crate::jobs::scheduler::Job;
+use crate::model::bank_account::BankAccountRepository;
+use crate::model::plaid_item::PlaidItemRepository;
+use crate::model::transfer::{Transfer, TransferRepository, TransferStatus};
+use crate::model::user::UserRepository;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+/// Plaid transfer webhook, as queued for processing when webhooks are handled through SQS
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransferWebhook {
+    pub webhook_type: String,
+    pub webhook_code: String,
+}
+
+impl TransferWebhook {
+    /// Whether new transfer events are waiting to be synced
+    pub fn is_events_update(&self) -> bool {
+        self.webhook_type == "TRANSFER" && self.webhook_code == "TRANSFER_EVENTS_UPDATE"
+    }
+}
+
+/// What a Plaid transfer event means for the transfer owning that leg
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TransferUpdate {
+    /// Debit funds are available in the ledger, so the credit can go out
+    StartCredit,
+    Complete,
+    Fail(String),
+    Cancel,
+    Ignore,
+}
+
+fn transfer_update(transfer: &Transfer, event: &TransferEvent) -> TransferUpdate {
+    let is_debit = transfer.debit_transfer_id.as_deref() == Some(event.transfer_id.as_str());
+    let leg = if is_debit {
+        "Debit from the source account"
+    } else {
+        "Credit to the destination account"
+    };
+    let failure = || {
+        let reason = event.failure_reason.as_deref().unwrap_or("no reason given");
+        TransferUpdate::Fail(format!("{} {}: {}", leg, event.event_type, reason))
+    };
+
+    match (is_debit, event.event_type.as_str()) {
+        // Credits draw on the ledger's available balance, which a debit only reaches once its funds are available
+        (true, "funds_available") if transfer.status() == Some(TransferStatus::DebitPending) => TransferUpdate::StartCredit,
+        (true, "cancelled") => TransferUpdate::Cancel,
+        (true, "failed") | (true, "returned") => failure(),
+        (false, "settled") => TransferUpdate::Complete,
+        (false, "failed") | (false, "returned") | (false, "cancelled") => failure(),
+        _ => TransferUpdate::Ignore,
+    }
+}
+
+/// Applies Plaid transfer events to stored transfers, sending the credit leg once a debit's funds
+/// are available. Runs on a schedule and whenever Plaid's transfer webhook fires.
+pub struct TransferEventSync {
+    plaid: Arc<PlaidClient>,
+    transfers: TransferRepository,
+    items: PlaidItemRepository,
+    accounts: BankAccountRepository,
+    users: UserRepository,
+    /// Webhooks and the schedule can overlap; events are applied by one sync at a time
+    lock: Mutex<()>,
+}
+
+impl TransferEventSync {
+    pub fn new(
+        plaid: Arc<PlaidClient>,
+        transfers: TransferRepository,
+        items: PlaidItemRepository,
+        accounts: BankAccountRepository,
+        users: UserRepository,
+    ) -> Self {
+        Self {
+            plaid,
+            transfers,
+            items,
+            accounts,
+            users,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Apply every event since the stored cursor, returning how many were applied.
+    /// On failure the cursor stops before the failed event so the next sync retries it.
+    pub async fn sync(&self) -> Result<usize> {
+        let _guard = self.lock.lock().await;
+        let mut after_id = self.transfers.last_event_id().await?;
+        let mut applied = 0;
+
+        loop {
+            let page = self.plaid.sync_transfer_events(after_id).await?;
+            for event in &page.events {
+                if let Err(e) = self.apply(event).await {
+                    self.transfers.save_last_event_id(after_id).await?;
+                    return Err(e.context(format!("Failed to apply transfer event {}", event.event_id)));
+                }
+                after_id = event.event_id;
+                applied += 1;
+            }
+            self.transfers.save_last_event_id(after_id).await?;
+
+            if !page.has_more || page.events.is_empty() {
+                break;
+            }
+        }
+
+        Ok(applied)
+    }
+
+    async fn apply(&self, event: &TransferEvent) -> Result<()> {
+        let Some(transfer) = self.transfers.find_by_plaid_transfer_id(&event.transfer_id).await? else {
+            debug!(plaid_transfer_id = %event.transfer_id, "Ignoring event for unknown transfer");
+            return Ok(());
+        };
+
+        match transfer_update(&transfer, event) {
+            TransferUpdate::StartCredit => self.start_credit(&transfer).await?,
+            TransferUpdate::Complete => {
+                if self.transfers.mark_completed(transfer.id).await?.is_some() {
+                    info!(transfer_id = %transfer.id, "Transfer completed");
+                }
+            }
+            TransferUpdate::Fail(reason) => {
+                if self.transfers.mark_failed(transfer.id, &reason).await?.is_some() {
+                    warn!(transfer_id = %transfer.id, reason = %reason, "Transfer failed");
+                }
+            }
+            TransferUpdate::Cancel => {
+                if self.transfers.mark_cancelled(transfer.id).await?.is_some() {
+                    info!(transfer_id = %transfer.id, "Transfer cancelled");
+                }
+            }
+            TransferUpdate::Ignore => {}
+        }
+        Ok(())
+    }
+
+    /// Authorize and send the credit to the destination account. Plaid calls use keys derived from
+    /// the transfer, so retrying after a partial failure does not send a second credit.
+    async fn start_credit(&self, transfer: &Transfer) -> Result<()> {
+        let Some(account) = self.accounts.find_by_account_id(&transfer.to_account_id).await? else {
+            self.fail_credit(transfer, "Destination account is no longer linked").await?;
+            return Ok(());
+        };
+        let item = self
+            .items
+            .find_by_item_id(&account.item_id)
+            .await?
+            .ok_or_else(|| anyhow!("Item {} of destination account not found", account.item_id))?;
+        let access_token = self.items.access_token(&item).await?;
+        let user = self
+            .users
+            .find_by_id(transfer.user_id)
+            .await?
+            .ok_or_else(|| anyhow!("User {} not found", transfer.user_id))?;
+
+        let authorization = self
+            .plaid
+            .authorize_transfer(TransferAuthorizationRequest {
+                access_token: access_token.clone(),
+                account_id: transfer.to_account_id.clone(),
+                direction: TransferDirection::Credit,
+                amount: transfer.amount,
+                legal_name: user.name,
+                idempotency_key: format!("{}-credit", transfer.id),
+            })
+            .await?;
+        if !authorization.is_approved() {
+            let reason = format!(
+                "Credit to the destination account was {}: {}",
+                authorization.decision,
+                authorization.decision_rationale.as_deref().unwrap_or("no reason given")
+            );
+            self.fail_credit(transfer, &reason).await?;
+            return Ok(());
+        }
+
+        let created = self
+            .plaid
+            .create_transfer(
+                &access_token,
+                &transfer.to_account_id,
+                &authorization.authorization_id,
+                &transfer.description,
+            )
+            .await?;
+        self.transfers
+            .mark_credit_pending(transfer.id, &created.transfer_id)
+            .await
+            .context("Failed to record credit leg")?;
+
+        info!(transfer_id = %transfer.id, credit_transfer_id = %created.transfer_id, "Transfer credit sent");
+        Ok(())
+    }
+
+    /// The debited funds stay in the ledger when the credit cannot go out; flag it loudly
+    async fn fail_credit(&self, transfer: &Transfer, reason: &str) -> Result<()> {
+        if self.transfers.mark_failed(transfer.id, reason).await?.is_some() {
+            warn!(
+                transfer_id = %transfer.id,
+                amount = transfer.amount,
+                reason = %reason,
+                "Transfer credit not sent; debited funds need to be returned manually"
+            );
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl Job for TransferEventSync {
+    fn name(&self) -> &'static str {
+        "transfer_event_sync"
+    }
+
+    async fn run(&self) -> Result<()> {
+        let applied = self.sync().await?;
+        info!(applied, "Transfer event sync finished");
+        Ok(())
+    }
+}
+
+/// Processes queued transfer webhooks; a failed sync is retried by the queue
+#[async_trait::async_trait]
+impl MessageHandler for TransferEventSync {
+    fn name(&self) -> &'static str {
+        "transfer_webhooks"
+    }
+
+    async fn handle(&self, message: &QueueMessage) -> Result<()> {
+        let webhook: TransferWebhook = serde_json::from_str(&message.body).map_err(|e| PoisonMessage(e.to_string()))?;
+        if !webhook.is_events_update() {
+            debug!(webhook_code = %webhook.webhook_code, "Ignoring queued transfer webhook");
+            return Ok(());
+        }
+        let applied = self.sync().await?;
+        info!(applied, "Transfer events synced from queued webhook");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn transfer(status: TransferStatus) -> Transfer {
+        Transfer {
+            id: Uuid::nil(),
+            user_id: Uuid::nil(),
+            from_account_id: "acc_from".to_string(),
+            to_account_id: "acc_to".to_string(),
+            amount: 25.0,
+            iso_currency_code: "USD".to_string(),
+            description: "Transfer".to_string(),
+            status: status.as_str().to_string(),
+            debit_authorization_id: Some("auth_1".to_string()),
+            debit_transfer_id: Some("tr_debit".to_string()),
+            credit_transfer_id: Some("tr_credit".to_string()),
+            failure_reason: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn event(transfer_id: &str, event_type: &str) -> TransferEvent {
+        TransferEvent {
+            event_id: 1,
+            transfer_id: transfer_id.to_string(),
+            event_type: event_type.to_string(),
+            failure_reason: Some("Insufficient funds (R01)".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_debit_events() {
+        let pending = transfer(TransferStatus::DebitPending);
+        assert_eq!(transfer_update(&pending, &event("tr_debit", "posted")), TransferUpdate::Ignore);
+        assert_eq!(transfer_update(&pending, &event("tr_debit", "settled")), TransferUpdate::Ignore);
+        assert_eq!(
+            transfer_update(&pending, &event("tr_debit", "funds_available")),
+            TransferUpdate::StartCredit
+        );
+        assert_eq!(
+            transfer_update(&pending, &event("tr_debit", "returned")),
+            TransferUpdate::Fail("Debit from the source account returned: Insufficient funds (R01)".to_string())
+        );
+        assert_eq!(transfer_update(&pending, &event("tr_debit", "cancelled")), TransferUpdate::Cancel);
+
+        // A replayed event must not send a second credit
+        let crediting = transfer(TransferStatus::CreditPending);
+        assert_eq!(
+            transfer_update(&crediting, &event("tr_debit", "funds_available")),
+            TransferUpdate::Ignore
+        );
+    }
+
+    #[test]
+    fn test_credit_events() {
+        let crediting = transfer(TransferStatus::CreditPending);
+        assert_eq!(transfer_update(&crediting, &event("tr_credit", "posted")), TransferUpdate::Ignore);
+        assert_eq!(transfer_update(&crediting, &event("tr_credit", "settled")), TransferUpdate::Complete);
+        assert!(matches!(
+            transfer_update(&crediting, &event("tr_credit", "failed")),
+            TransferUpdate::Fail(reason) if reason.starts_with("Credit to the destination account failed")
+        ));
+    }
+    #[test]
+    fn test_transfer_webhook() {
+        let webhook: TransferWebhook =
+            serde_json::from_str(r#"{"webhook_type":"TRANSFER","webhook_code":"TRANSFER_EVENTS_UPDATE"}"#).unwrap();
+        assert!(webhook.is_events_update());
+        let other = TransferWebhook {
+            webhook_code: "RECURRING_NEW_TRANSFER".to_string(),
+            ..webhook
+        };
+        assert!(!other.is_events_update());
+    }
+}
@@ -0,0 +1,241 @@
+use crate::adapter::webhooks::WebhookClient;
+use crate::jobs::scheduler::Job;
+use crate::model::webhook::{retry_delay, WebhookDelivery, WebhookRepository};
+use anyhow::Result;
+use chrono::Utc;
+use futures::stream::{self, StreamExt};
+use std::time::Instant;
+use tracing::{debug, error, info, warn};
+
+/// Seconds a claimed delivery may take before another worker may claim it again; well above the
+/// client's request timeout
+const DELIVERY_LEASE_SECONDS: i64 = 120;
+
+/// Settings of the webhook delivery worker
+#[derive(Debug, Clone)]
+pub struct WebhookDeliverySettings {
+    /// Deliveries claimed per run
+    pub batch_size: i64,
+    /// Calls in flight at once
+    pub concurrency: usize,
+    /// Attempts before a delivery fails for good
+    pub max_attempts: i32,
+}
+
+/// What happened to a claimed delivery
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Delivered,
+    Retrying,
+    Failed,
+}
+
+/// Result of one call, as recorded in the delivery log
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Attempt {
+    response_status: Option<i32>,
+    error: Option<String>,
+    duration_ms: i32,
+}
+
+/// Scheduled job calling webhook endpoints with their queued events, retrying failures with
+/// exponential backoff
+pub struct WebhookDeliveryWorker {
+    repository: WebhookRepository,
+    client: WebhookClient,
+    settings: WebhookDeliverySettings,
+}
+
+impl WebhookDeliveryWorker {
+    pub fn new(repository: WebhookRepository, client: WebhookClient, settings: WebhookDeliverySettings) -> Self {
+        Self {
+            repository,
+            client,
+            settings,
+        }
+    }
+
+    /// Claim and deliver one round of events; returns how many were claimed
+    pub async fn run_once(&self) -> Result<usize> {
+        let abandoned = self
+            .repository
+            .fail_abandoned(DELIVERY_LEASE_SECONDS, self.settings.max_attempts)
+            .await?;
+        if abandoned > 0 {
+            error!(
+                abandoned,
+                "Webhook deliveries failed after their last attempt did not finish"
+            );
+        }
+
+        let deliveries = self
+            .repository
+            .claim(
+                self.settings.batch_size,
+                DELIVERY_LEASE_SECONDS,
+                self.settings.max_attempts,
+            )
+            .await?;
+        let claimed = deliveries.len();
+
+        let outcomes = stream::iter(deliveries)
+            .map(|delivery| self.deliver(delivery))
+            .buffer_unordered(self.settings.concurrency.max(1))
+            .collect::<Vec<Outcome>>()
+            .await;
+
+        if claimed > 0 {
+            let count = |outcome: Outcome| outcomes.iter().filter(|o| **o == outcome).count();
+            info!(
+                claimed,
+                delivered = count(Outcome::Delivered),
+                retrying = count(Outcome::Retrying),
+                failed = count(Outcome::Failed),
+                "Webhook deliveries processed"
+            );
+        }
+        Ok(claimed)
+    }
+
+    async fn deliver(&self, delivery: WebhookDelivery) -> Outcome {
+        let attempt = self.attempt(&delivery).await;
+        let (outcome, recorded) = match attempt.error.as_deref() {
+            None => {
+                debug!(delivery_id = %delivery.id, status = ?attempt.response_status, "Webhook delivered");
+                let recorded = self
+                    .repository
+                    .mark_delivered(
+                        delivery.id,
+                        attempt.response_status.unwrap_or_default(),
+                        attempt.duration_ms,
+                    )
+                    .await;
+                (Outcome::Delivered, recorded)
+            }
+            Some(reason) if delivery.attempts < self.settings.max_attempts => {
+                let delay =
+                    chrono::Duration::from_std(retry_delay(delivery.attempts)).unwrap_or(chrono::Duration::hours(1));
+                warn!(
+                    delivery_id = %delivery.id,
+                    endpoint_id = %delivery.endpoint_id,
+                    attempts = delivery.attempts,
+                    error = %reason,
+                    "Webhook delivery failed; retrying"
+                );
+                let recorded = self
+                    .repository
+                    .record_failure(
+                        delivery.id,
+                        attempt.response_status,
+                        reason,
+                        attempt.duration_ms,
+                        Some(Utc::now() + delay),
+                    )
+                    .await;
+                (Outcome::Retrying, recorded)
+            }
+            Some(reason) => {
+                error!(
+                    delivery_id = %delivery.id,
+                    endpoint_id = %delivery.endpoint_id,
+                    attempts = delivery.attempts,
+                    error = %reason,
+                    "Webhook delivery failed"
+                );
+                let recorded = self
+                    .repository
+                    .record_failure(delivery.id, attempt.response_status, reason, attempt.duration_ms, None)
+                    .await;
+                (Outcome::Failed, recorded)
+            }
+        };
+        if let Err(e) = recorded {
+            // Left claimed; picked up again once its lease expires
+            warn!(delivery_id = %delivery.id, error = ?e, "Failed to record webhook delivery");
+        }
+        outcome
+    }
+
+    /// Make one call for the delivery
+    async fn attempt(&self, delivery: &WebhookDelivery) -> Attempt {
+        let target = match self.repository.target(delivery.endpoint_id).await {
+            Ok(Some(target)) => target,
+            Ok(None) => return failed_attempt("Endpoint is disabled".to_string(), 0),
+            Err(e) => return failed_attempt(format!("{:#}", e), 0),
+        };
+        let body = match serde_json::to_vec(&delivery.payload) {
+            Ok(body) => body,
+            Err(e) => return failed_attempt(format!("Failed to serialize payload: {}", e), 0),
+        };
+
+        let started = Instant::now();
+        let result = self
+            .client
+            .send(&target.url, &target.secret, delivery.id, &delivery.event_type, body)
+            .await;
+        let duration_ms = started.elapsed().as_millis().min(i32::MAX as u128) as i32;
+        match result {
+            Ok(response) if response.is_success() => Attempt {
+                response_status: Some(i32::from(response.status)),
+                error: None,
+                duration_ms,
+            },
+            Ok(response) => {
+                // The body stays out of the delivery log, which the endpoint's owner reads back
+                debug!(delivery_id = %delivery.id, status = response.status, excerpt = %response.excerpt, "Webhook rejected");
+                Attempt {
+                    response_status: Some(i32::from(response.status)),
+                    error: Some(failure_reason(response.status)),
+                    duration_ms,
+                }
+            }
+            Err(e) => failed_attempt(format!("{:#}", e), duration_ms),
+        }
+    }
+}
+
+fn failed_attempt(error: String, duration_ms: i32) -> Attempt {
+    Attempt {
+        response_status: None,
+        error: Some(error),
+        duration_ms,
+    }
+}
+
+/// Delivery log entry for an endpoint answering with an error status
+fn failure_reason(status: u16) -> String {
+    format!("Endpoint answered {}", status)
+}
+
+#[async_trait::async_trait]
+impl Job for WebhookDeliveryWorker {
+    fn name(&self) -> &'static str {
+        "webhook_delivery"
+    }
+
+    async fn run(&self) -> Result<()> {
+        let claimed = self.run_once().await?;
+        if claimed == 0 {
+            debug!("No webhook deliveries due");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failure_reason() {
+        assert_eq!(failure_reason(503), "Endpoint answered 503");
+    }
+
+    #[test]
+    fn test_failed_attempt_has_no_status() {
+        let attempt = failed_attempt("connection refused".to_string(), 12);
+        assert_eq!(attempt.response_status, None);
+        assert_eq!(attempt.error.as_deref(), Some("connection refused"));
+        assert_eq!(attempt.duration_ms, 12);
+    }
+}
@@ -2,15 +2,78 @@
 pub use prost_types::Timestamp;
 
 pub mod gen {
+    pub mod accounts {
+        include!(concat!(env!("CARGO_MANIFEST_DIR"), "/../proto/rust/gen/accounts.rs"));
+    }
+
     pub mod auth {
         include!(concat!(env!("CARGO_MANIFEST_DIR"), "/../proto/rust/gen/auth.rs"));
     }
 
+    pub mod sync {
+        include!(concat!(env!("CARGO_MANIFEST_DIR"), "/../proto/rust/gen/sync.rs"));
+    }
+
+    pub mod alerts {
+        include!(concat!(env!("CARGO_MANIFEST_DIR"), "/../proto/rust/gen/alerts.rs"));
+    }
+
+    pub mod sharing {
+        include!(concat!(env!("CARGO_MANIFEST_DIR"), "/../proto/rust/gen/sharing.rs"));
+    }
+
+    pub mod transfers {
+        include!(concat!(env!("CARGO_MANIFEST_DIR"), "/../proto/rust/gen/transfers.rs"));
+    }
+
+    pub mod assistant {
+        include!(concat!(env!("CARGO_MANIFEST_DIR"), "/../proto/rust/gen/assistant.rs"));
+    }
+
+    pub mod chat {
+        include!(concat!(env!("CARGO_MANIFEST_DIR"), "/../proto/rust/gen/chat.rs"));
+    }
+
+    pub mod admin {
+        include!(concat!(env!("CARGO_MANIFEST_DIR"), "/../proto/rust/gen/admin.rs"));
+    }
+
+    pub mod webhooks {
+        include!(concat!(env!("CARGO_MANIFEST_DIR"), "/../proto/rust/gen/webhooks.rs"));
+    }
+
     pub mod greeter {
         include!(concat!(env!("CARGO_MANIFEST_DIR"), "/../proto/rust/gen/greeter.rs"));
     }
+
+    pub mod google {
+        pub mod protobuf {
+            include!(concat!(env!("CARGO_MANIFEST_DIR"), "/../proto/rust/gen/google.protobuf.rs"));
+        }
+    }
 }
 pub mod adapter;
+pub mod cli;
+pub mod dedup;
+pub mod email_drafting;
+pub mod error;
+pub mod events;
+pub mod export;
+pub mod financial_assistant;
+#[cfg(feature = "rest-gateway")]
+pub mod gateway;
+#[cfg(feature = "graphql")]
+pub mod graphql;
 pub mod handler;
+pub mod import;
+pub mod jobs;
 pub mod model;
-pub mod logging;
\ No newline at end of file
+pub mod logging;
+pub mod metrics;
+pub mod moderation;
+pub mod prompts;
+pub mod receipt_scan;
+pub mod report;
+pub mod settings;
+pub mod tls;
+pub mod request_id;
\ No newline at end of file
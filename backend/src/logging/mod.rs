@@ -0,0 +1,293 @@
+pub mod redact;
+
+use crate::logging::redact::{RedactingFields, RedactingJson, Redactor};
+use anyhow::{anyhow, Context};
+use opentelemetry::propagation::Extractor;
+use opentelemetry::trace::{TraceContextExt, TraceError, TraceId};
+use opentelemetry::{global, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{self as sdktrace, Sampler};
+use opentelemetry_sdk::Resource;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tonic::codegen::http;
+use tracing::{field, info_span, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+/// Filter used when `RUST_LOG` is unset or invalid
+const DEFAULT_LOG_FILTER: &str = "info";
+
+/// Handle on the filter `init_tracing` installed, so operators can change it without a redeploy
+struct LogFilterControl {
+    handle: reload::Handle<EnvFilter, Registry>,
+    /// Directives the process started with, restored by an empty filter
+    startup: String,
+    /// Bumped on every change, so a timed reset doesn't undo a later change
+    generation: AtomicU64,
+}
+
+static LOG_FILTER: OnceLock<LogFilterControl> = OnceLock::new();
+
+/// How log lines are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// One JSON object per line, for log collection in production
+    Json,
+    /// Human-readable lines, for local development
+    Text,
+}
+
+impl LogFormat {
+    /// Read `LOG_FORMAT`: `json` (default) or `text`
+    pub fn from_env() -> Self {
+        match std::env::var("LOG_FORMAT").map(|v| v.to_ascii_lowercase()) {
+            Ok(format) if format == "text" || format == "pretty" => LogFormat::Text,
+            _ => LogFormat::Json,
+        }
+    }
+}
+
+/// Where spans are exported, if anywhere
+#[derive(Debug, Clone)]
+pub struct TracingConfig {
+    /// OTLP gRPC endpoint of a collector; `None` keeps traces local to the logs
+    pub otlp_endpoint: Option<String>,
+    pub service_name: String,
+    /// Fraction of new traces sampled; requests that arrive in a sampled trace are always sampled
+    pub sample_ratio: f64,
+}
+
+impl TracingConfig {
+    /// Load the configuration from the standard OpenTelemetry environment variables
+    /// - OTEL_EXPORTER_OTLP_ENDPOINT: collector endpoint, e.g. http://otel-collector:4317 (default: no export)
+    /// - OTEL_SERVICE_NAME: service name on exported spans (default: origin-backend)
+    /// - OTEL_TRACES_SAMPLER_ARG: ratio of new traces sampled, 0.0-1.0 (default: 1.0)
+    pub fn from_env() -> Self {
+        Self {
+            otlp_endpoint: std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
+                .ok()
+                .filter(|v| !v.trim().is_empty()),
+            service_name: std::env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| "origin-backend".to_string()),
+            sample_ratio: std::env::var("OTEL_TRACES_SAMPLER_ARG")
+                .ok()
+                .and_then(|v| v.parse::<f64>().ok())
+                .map(|ratio| ratio.clamp(0.0, 1.0))
+                .unwrap_or(1.0),
+        }
+    }
+}
+
+fn otlp_tracer(config: &TracingConfig, endpoint: &str) -> Result<sdktrace::Tracer, TraceError> {
+    opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(opentelemetry_otlp::new_exporter().tonic().with_endpoint(endpoint))
+        .with_trace_config(
+            sdktrace::config()
+                .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+                    config.sample_ratio,
+                ))))
+                .with_resource(Resource::new(vec![KeyValue::new(
+                    "service.name",
+                    config.service_name.clone(),
+                )])),
+        )
+        .install_batch(opentelemetry_sdk::runtime::Tokio)
+}
+
+/// Install the log subscriber, exporting spans over OTLP when a collector is configured.
+/// Emails, tokens and one-time codes are masked in every field (see `Redactor`), and the `RUST_LOG`
+/// filter can be replaced at runtime with `set_log_filter`.
+/// Must run inside the Tokio runtime, which the span exporter uses.
+pub fn init_tracing() {
+    let filter = std::env::var("RUST_LOG")
+        .ok()
+        .and_then(|directives| EnvFilter::try_new(directives).ok())
+        .unwrap_or_else(|| EnvFilter::new(DEFAULT_LOG_FILTER));
+    let startup = filter.to_string();
+    let (filter_layer, handle) = reload::Layer::new(filter);
+
+    // W3C trace context is read from incoming requests even when spans aren't exported, so the
+    // caller's trace ID still appears in the logs
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    let config = TracingConfig::from_env();
+    let (otel_layer, otel_error) = match config
+        .otlp_endpoint
+        .as_deref()
+        .map(|endpoint| otlp_tracer(&config, endpoint))
+    {
+        Some(Ok(tracer)) => (Some(tracing_opentelemetry::layer().with_tracer(tracer)), None),
+        Some(Err(e)) => (None, Some(e)),
+        None => (None, None),
+    };
+
+    let redactor = Arc::new(Redactor::from_env().expect("Invalid log redaction patterns"));
+    let (json_layer, text_layer) = match LogFormat::from_env() {
+        LogFormat::Json => (
+            Some(
+                tracing_subscriber::fmt::layer()
+                    .fmt_fields(RedactingFields::json(redactor.clone()))
+                    .event_format(RedactingJson::new(redactor)),
+            ),
+            None,
+        ),
+        LogFormat::Text => (
+            None,
+            Some(
+                tracing_subscriber::fmt::layer()
+                    .fmt_fields(RedactingFields::text(redactor))
+                    .with_line_number(true)
+                    .with_file(true)
+                    .with_target(true)
+                    .with_thread_ids(true),
+            ),
+        ),
+    };
+
+    let subscriber = tracing_subscriber::registry()
+        .with(filter_layer)
+        .with(json_layer)
+        .with(text_layer)
+        .with(otel_layer);
+
+    tracing::subscriber::set_global_default(subscriber)
+        .expect("Failed to set subscriber");
+    let _ = LOG_FILTER.set(LogFilterControl {
+        handle,
+        startup,
+        generation: AtomicU64::new(0),
+    });
+
+    match (&config.otlp_endpoint, otel_error) {
+        (Some(endpoint), None) => tracing::info!(
+            endpoint = %endpoint,
+            service_name = %config.service_name,
+            sample_ratio = config.sample_ratio,
+            "Exporting traces over OTLP"
+        ),
+        (_, Some(e)) => tracing::error!(error = %e, "Failed to start the OTLP trace exporter; traces are not exported"),
+        (None, None) => {}
+    }
+}
+
+/// Log filter directives in effect, or `None` before `init_tracing`
+pub fn log_filter() -> Option<String> {
+    let control = LOG_FILTER.get()?;
+    control.handle.with_current(|filter| filter.to_string()).ok()
+}
+
+/// Replace the log filter with `EnvFilter` directives, e.g. `info,template::adapter::plaid=debug`; empty
+/// directives restore the startup filter. With `reset_after`, the startup filter comes back after that long
+/// unless the filter was changed again. Returns the directives now in effect.
+pub fn set_log_filter(directives: &str, reset_after: Option<Duration>) -> anyhow::Result<String> {
+    let control = LOG_FILTER.get().ok_or_else(|| anyhow!("Logging is not initialized"))?;
+    let directives = match directives.trim() {
+        "" => control.startup.as_str(),
+        directives => directives,
+    };
+    let filter = EnvFilter::try_new(directives).with_context(|| format!("Invalid log filter '{}'", directives))?;
+    let applied = filter.to_string();
+    control.handle.reload(filter).context("Failed to replace log filter")?;
+    let generation = control.generation.fetch_add(1, Ordering::SeqCst) + 1;
+
+    if let Some(reset_after) = reset_after {
+        tokio::spawn(async move {
+            tokio::time::sleep(reset_after).await;
+            if control.generation.load(Ordering::SeqCst) != generation {
+                return;
+            }
+            match EnvFilter::try_new(&control.startup).map(|filter| control.handle.reload(filter)) {
+                Ok(Ok(())) => tracing::info!(filter = %control.startup, "Log filter reset"),
+                _ => tracing::warn!("Failed to reset log filter"),
+            }
+        });
+    }
+    Ok(applied)
+}
+
+/// Flush spans still waiting to be exported; call before the process exits
+pub fn shutdown_tracing() {
+    global::shutdown_tracer_provider();
+}
+
+/// Reads trace context from HTTP/2 headers, which carry gRPC metadata
+struct HeaderExtractor<'a>(&'a http::HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+/// Root span of an incoming gRPC call, continuing the caller's trace from its `traceparent` metadata.
+/// The trace ID and request ID are recorded as fields, so every log line inside the call carries them.
+pub fn grpc_request_span(request: &http::Request<()>) -> Span {
+    let parent = global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(request.headers())));
+    let span = info_span!(
+        "grpc_request",
+        rpc = %request.uri().path(),
+        trace_id = field::Empty,
+        request_id = field::Empty
+    );
+    span.set_parent(parent.clone());
+
+    // Without the OpenTelemetry layer the span has no context of its own; fall back to the caller's
+    let trace_id = [span.context(), parent]
+        .iter()
+        .map(|cx| cx.span().span_context().trace_id())
+        .find(|trace_id| *trace_id != TraceId::INVALID);
+    if let Some(trace_id) = trace_id {
+        span.record("trace_id", field::display(trace_id));
+    }
+    span
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::TracerProvider as _;
+
+    #[test]
+    fn test_request_span_continues_caller_trace() {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let tracer = sdktrace::TracerProvider::builder().build().tracer("test");
+        let subscriber = tracing_subscriber::registry().with(tracing_opentelemetry::layer().with_tracer(tracer));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let request = http::Request::builder()
+            .uri("/auth.AuthService/GetProfile")
+            .header("traceparent", "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01")
+            .body(())
+            .unwrap();
+        let span = grpc_request_span(&request);
+
+        assert_eq!(
+            span.context().span().span_context().trace_id().to_string(),
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+    }
+
+    #[test]
+    fn test_log_filter_needs_initialized_logging() {
+        assert_eq!(log_filter(), None);
+        assert!(set_log_filter("debug", None).is_err());
+    }
+
+    #[test]
+    fn test_request_span_without_trace_context() {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let request = http::Request::builder()
+            .uri("/auth.AuthService/GetProfile")
+            .body(())
+            .unwrap();
+        let span = grpc_request_span(&request);
+        assert_eq!(span.context().span().span_context().trace_id(), TraceId::INVALID);
+    }
+}
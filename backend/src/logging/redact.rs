@@ -0,0 +1,378 @@
+// Log formatting that masks personal data and credentials before a line is written
+use anyhow::{Context, Result};
+use chrono::{SecondsFormat, Utc};
+use regex::Regex;
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::fmt::{self, Write as _};
+use std::sync::Arc;
+use tracing::field::{Field, Visit};
+use tracing::{span, Event, Subscriber};
+use tracing_subscriber::field::RecordFields;
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields, FormattedFields};
+use tracing_subscriber::registry::LookupSpan;
+
+/// Replacement for values that are dropped entirely
+const REDACTED: &str = "[REDACTED]";
+
+/// Masks emails, tokens and one-time codes in log field values
+#[derive(Debug)]
+pub struct Redactor {
+    /// Fields logged verbatim
+    allowlist: HashSet<String>,
+    email: Regex,
+    jwt: Regex,
+    bearer: Regex,
+    /// `token: "..."`-style pairs in `Debug` output of request and model structs
+    debug_secret: Regex,
+}
+
+impl Redactor {
+    pub fn new(allowlist: HashSet<String>) -> Result<Self> {
+        Ok(Self {
+            allowlist,
+            email: Regex::new(r"\b([\w.+-])[\w.+-]*@([\w-]+(?:\.[\w-]+)+)").context("Invalid redaction pattern")?,
+            jwt: Regex::new(r"\beyJ[\w-]+\.[\w-]+\.[\w-]*").context("Invalid redaction pattern")?,
+            bearer: Regex::new(r"(?i)\b(bearer)\s+[\w.~+/=-]+").context("Invalid redaction pattern")?,
+            debug_secret: Regex::new(
+                r#"(?i)\b(\w*(?:token|secret|password|otp|code|api_key|authorization|subject)\w*:\s*)(?:Some\()?"(?:[^"\\]|\\.)*"\)?"#,
+            )
+            .context("Invalid redaction pattern")?,
+        })
+    }
+
+    /// Load the allowlist from `LOG_REDACTION_ALLOWLIST`, comma-separated field names logged verbatim
+    pub fn from_env() -> Result<Self> {
+        let allowlist = std::env::var("LOG_REDACTION_ALLOWLIST")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect();
+        Self::new(allowlist)
+    }
+
+    /// Whether a field's value is dropped whatever it holds
+    fn is_sensitive(&self, field: &str) -> bool {
+        if self.allowlist.contains(field) {
+            return false;
+        }
+        let field = field.to_ascii_lowercase();
+        matches!(field.as_str(), "subject" | "code" | "otp" | "authorization" | "cookie")
+            || ["token", "secret", "password", "api_key", "otp_code"]
+                .iter()
+                .any(|part| field.contains(part))
+    }
+
+    /// Value of `field` as it may be logged
+    pub fn redact(&self, field: &str, value: &str) -> String {
+        if self.allowlist.contains(field) {
+            return value.to_string();
+        }
+        if self.is_sensitive(field) {
+            return REDACTED.to_string();
+        }
+        let value = self.jwt.replace_all(value, REDACTED);
+        let value = self.bearer.replace_all(&value, format!("$1 {}", REDACTED));
+        let value = self.debug_secret.replace_all(&value, format!("${{1}}\"{}\"", REDACTED));
+        self.email.replace_all(&value, "$1***@$2").into_owned()
+    }
+}
+
+/// Collects redacted fields as JSON values
+struct JsonVisitor<'a> {
+    redactor: &'a Redactor,
+    fields: &'a mut Map<String, Value>,
+}
+
+impl JsonVisitor<'_> {
+    fn record_value(&mut self, field: &Field, value: Value) {
+        let value = if self.redactor.is_sensitive(field.name()) {
+            Value::String(REDACTED.to_string())
+        } else {
+            value
+        };
+        self.fields.insert(field.name().to_string(), value);
+    }
+}
+
+impl Visit for JsonVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        let value = self.redactor.redact(field.name(), value);
+        self.fields.insert(field.name().to_string(), Value::String(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.record_str(field, &format!("{:?}", value));
+    }
+
+    fn record_error(&mut self, field: &Field, value: &(dyn std::error::Error + 'static)) {
+        self.record_str(field, &value.to_string());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.record_value(field, Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.record_value(field, Value::from(value));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.record_value(field, Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.record_value(field, Value::from(value));
+    }
+}
+
+/// Writes redacted fields as `key=value` pairs, the event message first without a key
+struct TextVisitor<'a> {
+    redactor: &'a Redactor,
+    line: String,
+}
+
+impl Visit for TextVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        let value = self.redactor.redact(field.name(), value);
+        if !self.line.is_empty() {
+            self.line.push(' ');
+        }
+        if field.name() == "message" {
+            self.line.push_str(&value);
+        } else {
+            let _ = write!(self.line, "{}={}", field.name(), value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.record_str(field, &format!("{:?}", value));
+    }
+}
+
+/// Field formatter for spans and events, in JSON or text
+#[derive(Debug, Clone)]
+pub struct RedactingFields {
+    redactor: Arc<Redactor>,
+    json: bool,
+}
+
+impl RedactingFields {
+    /// Fields as a JSON object, for `RedactingJson`
+    pub fn json(redactor: Arc<Redactor>) -> Self {
+        Self { redactor, json: true }
+    }
+
+    /// Fields as `key=value` text, for the human-readable formats
+    pub fn text(redactor: Arc<Redactor>) -> Self {
+        Self { redactor, json: false }
+    }
+
+    fn record_json(&self, fields: &mut Map<String, Value>, record: impl RecordFields) {
+        record.record(&mut JsonVisitor {
+            redactor: &self.redactor,
+            fields,
+        });
+    }
+}
+
+impl<'writer> FormatFields<'writer> for RedactingFields {
+    fn format_fields<R: RecordFields>(&self, mut writer: Writer<'writer>, fields: R) -> fmt::Result {
+        if self.json {
+            let mut map = Map::new();
+            self.record_json(&mut map, fields);
+            write!(writer, "{}", Value::Object(map))
+        } else {
+            let mut visitor = TextVisitor {
+                redactor: &self.redactor,
+                line: String::new(),
+            };
+            fields.record(&mut visitor);
+            write!(writer, "{}", visitor.line)
+        }
+    }
+
+    /// Fields recorded after a span was created, such as its trace ID, are merged into the ones it has
+    fn add_fields(&self, current: &'writer mut FormattedFields<Self>, fields: &span::Record<'_>) -> fmt::Result {
+        if self.json {
+            let mut map = match serde_json::from_str(&current.fields) {
+                Ok(Value::Object(map)) => map,
+                _ => Map::new(),
+            };
+            self.record_json(&mut map, fields);
+            current.fields = Value::Object(map).to_string();
+            Ok(())
+        } else {
+            if !current.fields.is_empty() {
+                current.fields.push(' ');
+            }
+            self.format_fields(current.as_writer(), fields)
+        }
+    }
+}
+
+/// One JSON object per event, with the same keys as `tracing_subscriber`'s JSON format, whose fields and
+/// span fields are redacted. Spans must be formatted with `RedactingFields::json`.
+#[derive(Debug, Clone)]
+pub struct RedactingJson {
+    redactor: Arc<Redactor>,
+}
+
+impl RedactingJson {
+    pub fn new(redactor: Arc<Redactor>) -> Self {
+        Self { redactor }
+    }
+}
+
+impl<S, N> FormatEvent<S, N> for RedactingJson
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        let metadata = event.metadata();
+        let mut fields = Map::new();
+        event.record(&mut JsonVisitor {
+            redactor: &self.redactor,
+            fields: &mut fields,
+        });
+
+        let mut spans = Vec::new();
+        if let Some(scope) = ctx.event_scope() {
+            for span in scope.from_root() {
+                let mut object = span
+                    .extensions()
+                    .get::<FormattedFields<N>>()
+                    .and_then(|formatted| serde_json::from_str(&formatted.fields).ok())
+                    .and_then(|value: Value| value.as_object().cloned())
+                    .unwrap_or_default();
+                object.insert("name".to_string(), Value::from(span.name()));
+                spans.push(Value::Object(object));
+            }
+        }
+
+        let mut line = Map::new();
+        line.insert(
+            "timestamp".to_string(),
+            Value::from(Utc::now().to_rfc3339_opts(SecondsFormat::Micros, true)),
+        );
+        line.insert("level".to_string(), Value::from(metadata.level().as_str()));
+        line.insert("fields".to_string(), Value::Object(fields));
+        line.insert("target".to_string(), Value::from(metadata.target()));
+        if let Some(file) = metadata.file() {
+            line.insert("filename".to_string(), Value::from(file));
+        }
+        if let Some(number) = metadata.line() {
+            line.insert("line_number".to_string(), Value::from(number));
+        }
+        if let Some(current) = spans.last() {
+            line.insert("span".to_string(), current.clone());
+        }
+        if !spans.is_empty() {
+            line.insert("spans".to_string(), Value::Array(spans));
+        }
+        line.insert(
+            "threadId".to_string(),
+            Value::from(format!("{:?}", std::thread::current().id())),
+        );
+
+        writeln!(writer, "{}", Value::Object(line))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redactor() -> Redactor {
+        Redactor::new(HashSet::from(["debug_subject".to_string()])).unwrap()
+    }
+
+    #[test]
+    fn test_emails_are_masked_anywhere() {
+        let redactor = redactor();
+        assert_eq!(redactor.redact("email", "ana.silva@example.com"), "a***@example.com");
+        assert_eq!(
+            redactor.redact("message", "Sent OTP to ana@example.com and bo@mail.example.org"),
+            "Sent OTP to a***@example.com and b***@mail.example.org"
+        );
+        assert_eq!(redactor.redact("user_id", "42"), "42");
+    }
+
+    #[test]
+    fn test_tokens_and_codes_are_dropped() {
+        let redactor = redactor();
+        assert_eq!(redactor.redact("access_token", "abc"), REDACTED);
+        assert_eq!(redactor.redact("code", "123456"), REDACTED);
+        assert_eq!(redactor.redact("subject", "Your statement is ready"), REDACTED);
+        assert_eq!(
+            redactor.redact("header", "Bearer eyJhbGciOi.eyJzdWIiOiIxIn0.sig"),
+            "Bearer [REDACTED]"
+        );
+        assert_eq!(
+            redactor.redact(
+                "request",
+                r#"VerifyOtpRequest { email: "ana@example.com", code: "123456" }"#
+            ),
+            r#"VerifyOtpRequest { email: "a***@example.com", code: "[REDACTED]" }"#
+        );
+        assert_eq!(
+            redactor.redact("request", r#"Refresh { refresh_token: Some("opaque") }"#),
+            r#"Refresh { refresh_token: "[REDACTED]" }"#
+        );
+    }
+
+    #[test]
+    fn test_allowlisted_fields_are_verbatim() {
+        let redactor = redactor();
+        assert_eq!(redactor.redact("debug_subject", "ana@example.com"), "ana@example.com");
+        assert!(!redactor.is_sensitive("debug_subject"));
+    }
+
+    #[test]
+    fn test_json_lines_redact_event_and_span_fields() {
+        use std::sync::Mutex;
+        use tracing_subscriber::prelude::*;
+
+        #[derive(Clone, Default)]
+        struct Buffer(Arc<Mutex<Vec<u8>>>);
+        impl std::io::Write for Buffer {
+            fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(bytes);
+                Ok(bytes.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let redactor = Arc::new(redactor());
+        let subscriber = tracing_subscriber::registry().with(
+            tracing_subscriber::fmt::layer()
+                .fmt_fields(RedactingFields::json(redactor.clone()))
+                .event_format(RedactingJson::new(redactor))
+                .with_writer(move || writer.clone()),
+        );
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("send_otp", email = %"ana@example.com", request_id = tracing::field::Empty);
+            span.record("request_id", "req-1");
+            let _entered = span.enter();
+            tracing::info!(otp = 123456, to = "bo@example.com", "Sending code");
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let line: Value = serde_json::from_str(output.trim()).unwrap();
+        assert_eq!(line["fields"]["message"], "Sending code");
+        assert_eq!(line["fields"]["otp"], REDACTED);
+        assert_eq!(line["fields"]["to"], "b***@example.com");
+        assert_eq!(line["span"]["email"], "a***@example.com");
+        assert_eq!(line["span"]["request_id"], "req-1");
+        assert_eq!(line["spans"][0]["name"], "send_otp");
+    }
+}
@@ -1,113 +1,1076 @@
-use std::env;
+use std::sync::Arc;
+use tonic::service::interceptor::InterceptedService;
 use tonic::transport::Server;
+use clap::Parser;
 use dotenv::dotenv;
 use tower_http::cors::{CorsLayer, Any};
 use tower::ServiceBuilder;
 use tracing::{info, error, instrument};
 
-use sqlx::PgPool;
+use template::cli::{Cli, Command};
+use template::events::reactions::{SyncOnLink, WebhookFanout, WelcomeEmail};
+use template::events::{EventBus, ItemLinked, ItemSynced, UserCreated};
 use template::handler::greeter::GreeterHandler;
 use template::handler::auth::AuthServiceImpl;
+use template::handler::accounts::AccountsHandler;
+use template::handler::interceptor::AuthInterceptor;
+use template::handler::sync::SyncHandler;
+use template::handler::alerts::{AlertsHandler, UnsubscribeHandler};
+use template::handler::assistant::AssistantHandler;
+use template::handler::chat::ChatHandler;
+use template::handler::admin::AdminHandler;
+use template::handler::sharing::SharingHandler;
+use template::handler::transfers::TransfersHandler;
+use template::handler::webhooks::WebhooksHandler;
 use template::model::greeting::GreetingRepository;
 use template::model::user::UserRepository;
+use template::model::read_pool::ReadPool;
 use template::model::auth::{JwtManager, SessionManager};
+use template::model::auth_session::AuthSessionRepository;
+use template::model::dual_write::{DualWrite, OTP_STORE_MIGRATION, SESSION_STORE_MIGRATION};
 use template::model::otp::OtpRepository;
+use template::model::otp_cache::OtpCache;
+use template::model::plaid_item::PlaidItemRepository;
+use template::model::bank_account::BankAccountRepository;
+use template::model::transaction::TransactionRepository;
+use template::model::liability::LiabilityRepository;
+use template::model::account_identity::AccountIdentityRepository;
+use template::model::transaction_category::TransactionCategoryRepository;
+use template::model::net_worth::NetWorthRepository;
+use template::model::spending::SpendingRepository;
+use template::model::transaction_sync::TransactionSyncer;
+use template::model::pubsub::BalanceUpdates;
+use template::model::balance_cache::BalanceCache;
+use template::model::balance_history::BalanceHistoryRepository;
+use template::model::institution_cache::InstitutionCache;
+use template::model::audit_log::AuditLogRepository;
+use template::model::alert_rule::AlertRuleRepository;
+use template::model::notification::NotificationRepository;
+use template::model::bill::BillRepository;
+use template::model::account_share::AccountShareRepository;
+use template::model::transaction_annotation::TransactionAnnotationRepository;
+use template::model::receipt::ReceiptRepository;
+use template::model::statement::StatementRepository;
+use template::model::tax_report::TaxReportRepository;
+use template::model::transfer::TransferRepository;
+use template::model::chat::ChatRepository;
+use template::model::transaction_embedding::{TransactionEmbedder, TransactionEmbeddingRepository};
+use template::model::ai_batch::AiBatchRepository;
+use template::model::ai_usage::AiUsageRepository;
+use template::model::ai_response_cache::{AiResponseCache, CacheMode};
+use template::model::ai_rate_limit::AiRateLimiter;
+use template::model::email_template::EmailTemplateRepository;
+use template::model::email_queue::EmailQueueRepository;
+use template::model::email_campaign::EmailCampaignRepository;
+use template::model::email_preferences::EmailPreferencesRepository;
+use template::model::email_event::EmailEventRepository;
+use template::model::email_idempotency::EmailIdempotencyCache;
+use template::model::webhook::WebhookRepository;
+use template::receipt_scan::ReceiptScanner;
+use template::email_drafting::{EmailDraftRepository, EmailDrafter};
+use template::prompts::{PromptLoader, PromptRegistry};
+use template::financial_assistant::FinancialAssistant;
+use template::dedup::TransactionDeduplicator;
+use template::jobs::{
+    AiBatchProcessor, AiBatchSettings, AiBudgetMonitor, AlertEvaluator, BillDetectionJob, BillReminderJob, EmailCampaignSender, EmailCampaignSettings, EmailEventConsumer, EmailQueueSettings, EmailQueueWorker, NetWorthSnapshotJob, QueueWorker, QueueWorkerSettings, RemovedItemPurgeJob,
+    Scheduler, SpendingAggregateJob, StatementFetchJob, SyncCoordinator, TransactionCategorizer,
+    TransactionPartitionJob, TransactionSyncJob, TransferEventSync, WebhookDeliverySettings, WebhookDeliveryWorker,
+    WeeklyDigestJob, WeeklyDigestSender,
+};
 use template::adapter::google_oauth::GoogleOAuthClient;
-use template::adapter::AppConfig;
+use template::adapter::plaid::PlaidClient;
+use template::adapter::bank_data::BankDataProviders;
+use template::adapter::coinbase::CoinbaseClient;
+use template::adapter::encryption::EnvelopeCipher;
+use template::settings::{Settings, SettingsRefresher, SettingsSources};
+use template::adapter::claude_ai::ClaudeAIClient;
+use template::adapter::embeddings::EmbeddingsClient;
+use template::adapter::llm::LlmProviders;
+use template::adapter::openai::OpenAIClient;
+use template::adapter::fx::FxClient;
+use template::adapter::alerting::{AlertSink, EmailAlertSink, LogAlertSink};
+use template::adapter::s3::S3Client;
+use template::adapter::sqs::SqsQueue;
+use template::adapter::mailer::Mailer;
+use template::adapter::webhooks::WebhookClient;
+use template::metrics::{defined_methods, ResourceGauges, RpcMetrics, RpcMetricsLayer, SloMonitor};
+use template::tls::{GrpcTls, TlsConfig};
+use template::moderation::ContentModerator;
 use template::gen::greeter::greeter_service_server::GreeterServiceServer;
 use template::gen::auth::auth_service_server::AuthServiceServer;
+use template::gen::accounts::accounts_service_server::AccountsServiceServer;
+use template::gen::sync::sync_service_server::SyncServiceServer;
+use template::gen::alerts::alerts_service_server::AlertsServiceServer;
+use template::gen::alerts::email_unsubscribe_service_server::EmailUnsubscribeServiceServer;
+use template::gen::assistant::assistant_service_server::AssistantServiceServer;
+use template::gen::chat::chat_service_server::ChatServiceServer;
+use template::gen::admin::admin_service_server::AdminServiceServer;
+use template::gen::sharing::sharing_service_server::SharingServiceServer;
+use template::gen::transfers::transfers_service_server::TransfersServiceServer;
+use template::gen::webhooks::webhooks_service_server::WebhooksServiceServer;
 use template::logging;
+use template::request_id::RequestIdLayer;
 
 #[tokio::main]
 #[instrument]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // `--help` and bad arguments exit here, before anything is loaded
+    let cli = Cli::parse();
+
     // Initialize tracing
     logging::init_tracing();
 
     // Load environment variables from .env file if it exists (for local development)
     dotenv().ok();
 
-    // Load configuration from Parameter Store (falls back to env vars for local dev)
+    // Load settings from Parameter Store, Secrets Manager (see SECRET_SOURCES) and env vars; invalid settings
+    // stop the server here. Parameter reads share one cached client (see PARAMETER_CACHE_TTL_SECS).
     info!("Loading application configuration...");
-    let config = AppConfig::load().await;
-    info!("Configuration loaded successfully");
+    let settings_sources = SettingsSources::from_env().await?;
+    let settings = Settings::load(&settings_sources).await.map_err(|e| {
+        error!("Failed to load settings: {:#}", e);
+        e
+    })?;
+    info!(environment = %settings.environment, "Configuration loaded successfully");
+
+    // Without a subcommand the servers run, as they always have
+    let result = match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => serve(settings_sources, settings).await,
+        command => template::cli::run(command, &settings).await.map_err(|e| {
+            error!("Task failed: {:#}", e);
+            e.into()
+        }),
+    };
 
-    // Get server address from environment variable or use default
-    let grpc_addr = env::var("GRPC_ADDR")
-        .unwrap_or_else(|_| "[::0]:50051".to_string())
-        .parse()?;
+    logging::shutdown_tracing();
+    result
+}
+
+/// Run the gRPC server, its optional HTTP servers and the background jobs until the server stops
+async fn serve(settings_sources: SettingsSources, settings: Settings) -> Result<(), Box<dyn std::error::Error>> {
+    let grpc_addr = settings.grpc_addr;
 
     info!("Connecting to database...");
-    let pool = PgPool::connect(&config.database_url).await?;
+    // Pool size and timeouts come from DATABASE_MAX_CONNECTIONS and friends; checkouts are timed every
+    // DATABASE_ACQUIRE_PROBE_SECS and those waiting past DATABASE_SLOW_ACQUIRE_MS are logged
+    let pool = settings
+        .database_pool
+        .connect("primary", &settings.database_url, false)
+        .await
+        .map_err(|e| {
+            error!("Failed to connect to database: {:#}", e);
+            e
+        })?;
     info!("Database connection established");
+    // Lists, searches and summaries that tolerate replication lag read from DATABASE_READ_URL while it passes
+    // health checks every DATABASE_READ_CHECK_SECS, and from the primary otherwise
+    let mut read_pool = ReadPool::new(pool.clone());
+    if let Some(url) = &settings.database_read_url {
+        let replica = settings
+            .database_pool
+            .for_replica()
+            .connect("replica", url, true)
+            .await
+            .map_err(|e| {
+                error!("Failed to configure read replica: {:#}", e);
+                e
+            })?;
+        read_pool = read_pool.with_replica(replica);
+        read_pool.check().await;
+        read_pool.clone().spawn_health_checks(settings.database_read_check_interval);
+    }
 
     // Create the greeter handler with repository access
     let greeting_repo = GreetingRepository::new(pool.clone());
     let greeter = GreeterHandler::new(greeting_repo);
 
     // Create auth service dependencies
-    let oauth_client = GoogleOAuthClient::from_env()
+    let oauth_client = GoogleOAuthClient::new(settings.google_oauth.clone())
         .map_err(|e| {
             error!("Failed to create OAuth client: {}", e);
             e
         })?;
     
-    // Create JWT manager with config from Parameter Store
-    let jwt_config = template::model::auth::JwtConfig {
-        secret_key: config.jwt_secret.clone(),
-        issuer: env::var("JWT_ISSUER").unwrap_or_else(|_| "auth-service".to_string()),
-        audience: env::var("JWT_AUDIENCE").unwrap_or_else(|_| "api".to_string()),
-        access_token_expires_minutes: env::var("JWT_ACCESS_TOKEN_EXPIRES_MINUTES")
-            .unwrap_or_else(|_| "15".to_string())
-            .parse()
-            .unwrap_or(15),
-        refresh_token_expires_days: env::var("JWT_REFRESH_TOKEN_EXPIRES_DAYS")
-            .unwrap_or_else(|_| "30".to_string())
-            .parse()
-            .unwrap_or(30),
-    };
-    let jwt_manager = JwtManager::new(jwt_config);
-    
-    // Create session manager with Redis URL from Parameter Store
-    let session_ttl_hours = env::var("SESSION_TTL_HOURS")
-        .unwrap_or_else(|_| "24".to_string())
-        .parse()
-        .unwrap_or(24);
-    let session_manager = SessionManager::new(&config.redis_url, session_ttl_hours)
+    let jwt_manager = JwtManager::new(settings.jwt.clone());
+    let session_manager = SessionManager::from_settings(&settings).map_err(|e| {
+        error!("Failed to create session manager: {:#}", e);
+        e
+    })?;
+    // Sessions move to Postgres and codes to Redis in the phases their `*-store-*` flags select
+    let session_manager = session_manager.with_migration(
+        AuthSessionRepository::new(pool.clone()),
+        DualWrite::new(SESSION_STORE_MIGRATION, settings.flags.clone()),
+    );
+
+    let user_repository = UserRepository::new(pool.clone()).with_read_pool(read_pool.clone());
+    let token_cipher = EnvelopeCipher::new(settings.token_encryption.key_id.clone(), &settings.token_encryption.key)
         .map_err(|e| {
-            error!("Failed to create session manager: {}", e);
+            error!("Failed to load token encryption key: {}", e);
             e
         })?;
+    let token_cipher = Arc::new(token_cipher);
+    let otp_repository = OtpRepository::new(pool.clone(), token_cipher.clone()).with_migration(
+        OtpCache::new(&settings.redis_url)?,
+        DualWrite::new(OTP_STORE_MIGRATION, settings.flags.clone()),
+    );
+    // Emails sent from request paths go through this queue and a background worker. With
+    // EMAIL_SEND_QUEUE_URL set, each queued email is also announced on SQS so it goes out right away.
+    let email_send_queue = match &settings.queues.email_send {
+        Some(config) => Some(SqsQueue::new(config.clone()).await?),
+        None => {
+            info!("Queued emails are sent on schedule only: EMAIL_SEND_QUEUE_URL not set");
+            None
+        }
+    };
+    let mut email_queue_repository = EmailQueueRepository::new(pool.clone());
+    if let Some(queue) = &email_send_queue {
+        email_queue_repository = email_queue_repository.with_notifications(queue.clone());
+    }
+
+    // Pool utilization and queue depth are sampled into gauges served with the RPC metrics
+    let metrics_config = &settings.metrics;
+    if template::metrics::exporter::install(metrics_config)? {
+        let gauges = ResourceGauges::new(pool.clone(), email_queue_repository.clone())
+            .with_redis_pool("sessions", session_manager.redis_pool().clone());
+        Arc::new(gauges).spawn(metrics_config.sample_interval);
+    }
     
-    let user_repository = UserRepository::new(pool.clone());
-    let otp_repository = OtpRepository::new(pool.clone());
-    
+    // Reactions to domain events run on their own tasks, outside the RPC that published the event
+    let events = EventBus::new();
+    events.subscribe::<UserCreated>(Arc::new(WelcomeEmail::new(email_queue_repository.clone())));
+
     // Create the auth service handler
-    let auth_service = AuthServiceImpl::new(
+    let auth_service = Arc::new(AuthServiceImpl::new(
         oauth_client,
-        jwt_manager,
+        jwt_manager.clone(),
+        session_manager.clone(),
+        user_repository.clone(),
+        otp_repository.clone(),
+        email_queue_repository.clone(),
+        events.clone(),
+    ));
+
+    // Create the accounts handler backed by Plaid
+    let plaid_client = PlaidClient::new(settings.plaid.clone()).map_err(|e| {
+        error!("Failed to create Plaid client: {}", e);
+        e
+    })?;
+    let plaid_item_repository = PlaidItemRepository::new(pool.clone(), token_cipher.clone());
+    let bank_account_repository = BankAccountRepository::new(pool.clone());
+    let transaction_repository = TransactionRepository::new(pool.clone()).with_read_pool(read_pool.clone());
+    let category_repository = TransactionCategoryRepository::new(pool.clone());
+    let net_worth_repository = NetWorthRepository::new(pool.clone());
+    let plaid_client = Arc::new(plaid_client);
+    // Coinbase accounts are linked per user with an API key; the client also serves crypto spot prices
+    let coinbase_client = CoinbaseClient::new(settings.coinbase.clone()).map_err(|e| {
+        error!("Failed to create Coinbase client: {}", e);
+        e
+    })?;
+    // Items are served by the provider they were linked through; new links are routed by country
+    let bank_data_providers = BankDataProviders::new(plaid_client.clone())
+        .with_provider(Arc::new(coinbase_client.clone()))
+        .with_regions(&settings.bank_data_provider_regions)
+        .map_err(|e| {
+            error!("Failed to configure bank data providers: {}", e);
+            e
+        })?;
+
+    // In-process topic fanning balance changes, from links, syncs and refreshes, out to
+    // StreamBalances clients
+    let balance_updates = BalanceUpdates::default();
+
+    // Transaction sync shared by the scheduled job and the TriggerSync RPC
+    let jobs_config = settings.jobs.clone();
+    let mut sync_coordinator = SyncCoordinator::new(
+        TransactionSyncer::new(
+            bank_data_providers.clone(),
+            plaid_item_repository.clone(),
+            transaction_repository.clone(),
+            bank_account_repository.clone(),
+        )
+        .with_balance_updates(balance_updates.clone()),
+        plaid_item_repository.clone(),
+        jobs_config.transaction_sync_concurrency,
+    )
+    .with_dedup(TransactionDeduplicator::new(
+        transaction_repository.clone(),
+        AuditLogRepository::new(pool.clone()),
+    ));
+
+    // Emails use the active version of each template, falling back to the built-in one
+    let email_template_repository = EmailTemplateRepository::new(pool.clone());
+
+    // User notifications (alerts, bill reminders) are emailed through SES when it is configured, or
+    // captured locally when ENVIRONMENT=local
+    let alert_rule_repository = AlertRuleRepository::new(pool.clone());
+    let notification_repository = NotificationRepository::new(pool.clone());
+    let bill_repository = BillRepository::new(pool.clone());
+    // Optional emails skip recipients who opted out, and carry a signed one-click unsubscribe link
+    // when EMAIL_UNSUBSCRIBE_URL and EMAIL_UNSUBSCRIBE_SECRET are set
+    let email_preferences_repository = EmailPreferencesRepository::new(pool.clone());
+    let unsubscribe_links = settings.email.unsubscribe_links.clone();
+    if unsubscribe_links.is_none() {
+        info!("Unsubscribe links disabled: EMAIL_UNSUBSCRIBE_URL or EMAIL_UNSUBSCRIBE_SECRET not set");
+    }
+    // Emails with an idempotency key, such as queued ones, are delivered at most once
+    let email_idempotency = EmailIdempotencyCache::new(
+        &settings.redis_url,
+        settings.caches.email_idempotency_ttl_seconds,
+        settings.caches.email_idempotency_claim_seconds,
+    )
+    .map_err(|e| {
+        error!("Failed to create email idempotency cache: {}", e);
+        e
+    })?;
+    let notification_mailer = match Mailer::from_settings(&settings.email).await {
+        Ok(mailer) => Some(Arc::new(
+            mailer
+                .with_templates(Arc::new(email_template_repository.clone()))
+                .with_preferences(Arc::new(email_preferences_repository.clone()), unsubscribe_links.clone())
+                .with_idempotency(Arc::new(email_idempotency.clone())),
+        )),
+        Err(e) => {
+            info!("User notifications disabled: {}", e);
+            None
+        }
+    };
+    // Transactions are embedded after each sync for semantic search when an embeddings API key is configured
+    let transaction_embedder = match &settings.embeddings {
+        Some(config) => Some(TransactionEmbedder::new(
+            Arc::new(EmbeddingsClient::new(config.clone())?),
+            TransactionEmbeddingRepository::new(pool.clone()),
+        )),
+        None => {
+            info!("Semantic transaction search disabled: EMBEDDINGS_API_KEY or OPENAI_API_KEY not set");
+            None
+        }
+    };
+    if let Some(embedder) = &transaction_embedder {
+        sync_coordinator = sync_coordinator.with_embeddings(embedder.clone());
+    }
+    // Alert rules are evaluated after each sync
+    if let Some(mailer) = &notification_mailer {
+        sync_coordinator = sync_coordinator.with_alerts(AlertEvaluator::new(
+            alert_rule_repository.clone(),
+            notification_repository.clone(),
+            transaction_repository.clone(),
+            bank_account_repository.clone(),
+            category_repository.clone(),
+            user_repository.clone(),
+            mailer.clone(),
+        ));
+    }
+    // Syncs that store new transactions are published, so webhook endpoints can be sent them
+    sync_coordinator = sync_coordinator.with_events(events.clone());
+    // The weekly digest is sent by a scheduled job and on demand through the alerts service
+    let digest_sender = notification_mailer.as_ref().map(|mailer| {
+        WeeklyDigestSender::new(
+            transaction_repository.clone(),
+            SpendingRepository::new(pool.clone()).with_read_pool(read_pool.clone()),
+            bill_repository.clone(),
+            notification_repository.clone(),
+            mailer.clone(),
+        )
+    });
+
+    // Receipt and statement files are stored in S3 when a bucket is configured
+    let file_storage = match &settings.s3 {
+        Some(config) => Some(Arc::new(S3Client::new(config.clone()).await?)),
+        None => {
+            info!("Receipt attachments and statements disabled: AWS_S3_BUCKET not set");
+            None
+        }
+    };
+    // AI spend is priced per model and budgeted per feature within this environment
+    let environment = settings.environment.clone();
+    let ai_usage_repository = AiUsageRepository::new(pool.clone(), settings.ai.quota)
+        .with_cost_model(settings.ai.cost_model.clone())
+        .with_budgets(settings.ai.budgets.clone())
+        .with_environment(&environment);
+    // AI prompts start from the compiled defaults. Overrides in Parameter Store, when it is used, are read at
+    // startup and on every settings refresh; AI_PROMPT_VERSIONS pins prompts to a version.
+    let prompts = Arc::new(PromptRegistry::default());
+    let prompt_loader = match &settings_sources.parameters {
+        Some(store) => {
+            let loader = Arc::new(
+                PromptLoader::new(store.clone(), &environment, prompts.clone())
+                    .with_pinned_versions(settings.pinned_prompt_versions.clone()),
+            );
+            loader.reload().await;
+            Some(loader)
+        }
+        None => None,
+    };
+    // Feature flags and prompts are re-read from Parameter Store in one batch every SETTINGS_REFRESH_SECS
+    // (formerly AI_PROMPT_RELOAD_SECS; 0 disables refreshing), which is also when secret rotations are noticed
+    if let Some(interval) = settings.refresh_interval {
+        let mut refresher = SettingsRefresher::new(settings_sources.clone(), &settings);
+        if let Some(loader) = prompt_loader {
+            refresher = refresher.with_prompts(loader);
+        }
+        Arc::new(refresher).spawn(interval);
+    }
+    // AI RPCs share per-user and global request rates and in-flight caps through Redis (see AiRateLimits)
+    let ai_rate_limiter = AiRateLimiter::new(&settings.redis_url, settings.ai.rate_limits.clone()).map_err(|e| {
+        error!("Failed to create AI rate limiter: {}", e);
+        e
+    })?;
+    let ai_rate_limiter = Arc::new(ai_rate_limiter);
+    // Receipt OCR needs both the stored files and a Claude API key
+    let receipt_scanner = match (&file_storage, ClaudeAIClient::from_settings(&settings)) {
+        (Some(storage), Ok(claude)) => Some(ReceiptScanner::new(
+            Arc::new(claude),
+            storage.clone(),
+            ReceiptRepository::new(pool.clone()),
+            ai_usage_repository.clone(),
+            prompts.clone(),
+            ai_rate_limiter.clone(),
+        )),
+        (None, _) => None,
+        (Some(_), Err(e)) => {
+            info!("Receipt scanning disabled: {}", e);
+            None
+        }
+    };
+
+    // Streaming and saved assistant conversations on any configured LLM provider;
+    // LLM_PROVIDER picks the default, and without an API key both services report themselves unavailable
+    let mut llm_providers = LlmProviders::default();
+    match ClaudeAIClient::from_settings(&settings) {
+        Ok(claude) => llm_providers = llm_providers.with_provider(Arc::new(claude)),
+        Err(e) => info!("Claude assistant provider disabled: {}", e),
+    }
+    match &settings.openai {
+        Some(config) => llm_providers = llm_providers.with_provider(Arc::new(OpenAIClient::new(config.clone())?)),
+        None => info!("OpenAI assistant provider disabled: OPENAI_API_KEY not set"),
+    }
+    let llm_providers = match &settings.llm_provider {
+        Some(provider) => llm_providers.with_default(provider),
+        None => Ok(llm_providers),
+    }
+    .map_err(|e| {
+        error!("Invalid LLM provider configuration: {}", e);
+        e
+    })?;
+    // While the ai-email-drafts flag is on (AI_EMAIL_DRAFTS_ENABLED or the flags/ai-email-drafts parameter),
+    // weekly digests open with a spending insight written by the default provider; every draft is recorded for
+    // review and drafts failing the safety checks are never sent
+    let digest_sender = match digest_sender {
+        Some(sender) if !llm_providers.is_empty() => {
+            let drafter = EmailDrafter::new(
+                llm_providers.clone(),
+                EmailDraftRepository::new(pool.clone()),
+                ai_usage_repository.clone(),
+                prompts.clone(),
+            )?;
+            Some(sender.with_drafter(Arc::new(drafter), settings.flags.clone()))
+        }
+        sender => sender,
+    };
+
+    let balance_cache = BalanceCache::new(
+        &settings.redis_url,
+        settings.caches.balance_ttl_seconds,
+        settings.caches.balance_refresh_min_interval_seconds,
+    )
+    .map_err(|e| {
+        error!("Failed to create balance cache: {}", e);
+        e
+    })?;
+
+    let institution_cache = InstitutionCache::new(&settings.redis_url, settings.caches.institution_ttl_seconds)
+        .map_err(|e| {
+            error!("Failed to create institution cache: {}", e);
+            e
+        })?;
+
+    let fx_client = FxClient::new(settings.fx.clone()).map_err(|e| {
+        error!("Failed to create exchange rate client: {}", e);
+        e
+    })?;
+
+    // Newly linked items are synced right away rather than on the next scheduled run
+    events.subscribe::<ItemLinked>(Arc::new(SyncOnLink::new(
+        sync_coordinator.clone(),
+        plaid_item_repository.clone(),
+    )));
+
+    // Users' webhook endpoints are sent linked items and new transactions, signed with their secrets
+    let webhook_repository = WebhookRepository::new(pool.clone(), token_cipher.clone());
+    let webhook_client = WebhookClient::new(settings.webhooks.clone()).map_err(|e| {
+        error!("Failed to create webhook client: {}", e);
+        e
+    })?;
+    let webhook_fanout = Arc::new(WebhookFanout::new(webhook_repository.clone(), transaction_repository.clone()));
+    events.subscribe::<ItemLinked>(webhook_fanout.clone());
+    events.subscribe::<ItemSynced>(webhook_fanout);
+
+    let accounts_service = Arc::new(AccountsHandler::new(
+        bank_data_providers.clone(),
+        pool.clone(),
+        plaid_item_repository.clone(),
+        bank_account_repository.clone(),
+        transaction_repository.clone(),
+        LiabilityRepository::new(pool.clone()),
+        AccountIdentityRepository::new(pool.clone(), token_cipher),
+        category_repository.clone(),
+        net_worth_repository.clone(),
+        SpendingRepository::new(pool.clone()).with_read_pool(read_pool.clone()),
+        user_repository.clone(),
+        fx_client,
+        coinbase_client,
+        sync_coordinator.clone(),
+        balance_updates.clone(),
+        balance_cache,
+        institution_cache,
+        AuditLogRepository::new(pool.clone()),
+        bill_repository.clone(),
+        BalanceHistoryRepository::new(pool.clone()),
+        AccountShareRepository::new(pool.clone()),
+        TransactionAnnotationRepository::new(pool.clone()),
+        ReceiptRepository::new(pool.clone()),
+        file_storage.clone(),
+        receipt_scanner,
+        StatementRepository::new(pool.clone()),
+        TaxReportRepository::new(pool.clone()),
+        transaction_embedder,
+        events.clone(),
+    ));
+
+    // Transfer events are applied on Plaid's webhook and on a schedule as a fallback
+    let transfer_repository = TransferRepository::new(pool.clone());
+    let transfer_event_sync = Arc::new(TransferEventSync::new(
+        plaid_client.clone(),
+        transfer_repository.clone(),
+        plaid_item_repository.clone(),
+        bank_account_repository.clone(),
+        user_repository.clone(),
+    ));
+    // Plaid webhooks are handed to a queue worker when PLAID_WEBHOOK_QUEUE_URL is set, so failed
+    // processing is retried and, after repeated failures, kept in PLAID_WEBHOOK_DLQ_URL
+    let plaid_webhook_queue = match &settings.queues.plaid_webhooks {
+        Some(config) => Some(SqsQueue::new(config.clone()).await?),
+        None => {
+            info!("Plaid webhooks are processed in-process: PLAID_WEBHOOK_QUEUE_URL not set");
+            None
+        }
+    };
+
+    // Per-method RPC metrics feeding SLO burn-rate alerts
+    let rpc_metrics = RpcMetrics::new();
+    // Calls are recorded per method the protos define; anything else is recorded as one unknown method
+    let rpc_methods = defined_methods().map_err(|e| {
+        error!("Failed to read the served RPC methods: {:#}", e);
+        e
+    })?;
+    let alert_sink: Arc<dyn AlertSink> = if settings.alert_email_recipients.is_empty() {
+        Arc::new(LogAlertSink)
+    } else {
+        let mailer = Mailer::from_settings(&settings.email).await.map_err(|e| {
+            error!("Failed to create mailer for alerts: {}", e);
+            e
+        })?;
+        let mailer = mailer.with_templates(Arc::new(email_template_repository.clone()));
+        Arc::new(EmailAlertSink::new(
+            Arc::new(mailer),
+            settings.alert_email_recipients.clone(),
+        ))
+    };
+
+    // Start background jobs
+    if jobs_config.enabled {
+        let mut scheduler = Scheduler::new()
+            .add(
+                &jobs_config.transaction_sync_schedule,
+                Arc::new(TransactionSyncJob::new(sync_coordinator, plaid_item_repository.clone())),
+            )
+            .and_then(|scheduler| {
+                scheduler.add(
+                    &jobs_config.net_worth_snapshot_schedule,
+                    Arc::new(NetWorthSnapshotJob::new(net_worth_repository)),
+                )
+            })
+            .and_then(|scheduler| {
+                scheduler.add(
+                    &jobs_config.transaction_partition_schedule,
+                    Arc::new(TransactionPartitionJob::new(
+                        transaction_repository.clone(),
+                        jobs_config.transaction_partition_months_ahead,
+                    )),
+                )
+            })
+            .and_then(|scheduler| {
+                scheduler.add(
+                    &jobs_config.spending_aggregate_schedule,
+                    Arc::new(SpendingAggregateJob::new(
+                        SpendingRepository::new(pool.clone()),
+                        jobs_config.spending_aggregate_batch_size,
+                    )),
+                )
+            })
+            .and_then(|scheduler| {
+                scheduler.add(
+                    &jobs_config.removed_item_purge_schedule,
+                    Arc::new(RemovedItemPurgeJob::new(
+                        plaid_item_repository.clone(),
+                        jobs_config.removed_item_retention_days,
+                    )),
+                )
+            })
+            .and_then(|scheduler| {
+                scheduler.add(
+                    &jobs_config.bill_detection_schedule,
+                    Arc::new(BillDetectionJob::new(
+                        plaid_item_repository.clone(),
+                        transaction_repository.clone(),
+                        bill_repository.clone(),
+                    )),
+                )
+            })
+            .and_then(|scheduler| {
+                scheduler.add(
+                    &jobs_config.slo_monitor_schedule,
+                    Arc::new(SloMonitor::new(
+                        settings.slo.clone(),
+                        rpc_metrics.clone(),
+                        alert_sink.clone(),
+                    )),
+                )
+            })
+            .and_then(|scheduler| {
+                scheduler.add(
+                    &jobs_config.ai_budget_monitor_schedule,
+                    Arc::new(AiBudgetMonitor::new(ai_usage_repository.clone(), alert_sink)),
+                )
+            })
+            .map_err(|e| {
+                error!("Failed to configure background jobs: {}", e);
+                e
+            })?;
+
+        let ai_response_cache = AiResponseCache::new(&settings.redis_url, settings.caches.ai_response_ttl_seconds)
+            .map_err(|e| {
+                error!("Failed to create AI response cache: {}", e);
+                e
+            })?;
+        // AI categorization runs only when a Claude API key is configured
+        match ClaudeAIClient::from_settings(&settings) {
+            Ok(claude_client) => {
+                let claude_client = Arc::new(claude_client);
+                // Submitted batches are processed separately from the scheduled categorization
+                let batch_categorizer = TransactionCategorizer::new(
+                    claude_client.clone(),
+                    category_repository.clone(),
+                    jobs_config.ai_batch_chunk_size,
+                    1,
+                    jobs_config.categorization_min_confidence,
+                    prompts.clone(),
+                );
+                let batch_processor = AiBatchProcessor::new(
+                    AiBatchRepository::new(pool.clone()),
+                    transaction_repository.clone(),
+                    Arc::new(batch_categorizer),
+                    ai_usage_repository.clone(),
+                    AiBatchSettings {
+                        tasks_per_run: jobs_config.ai_batch_tasks_per_run,
+                        chunk_size: jobs_config.ai_batch_chunk_size,
+                        concurrency: jobs_config.ai_batch_concurrency,
+                        requests_per_minute: jobs_config.ai_batch_requests_per_minute,
+                        max_attempts: jobs_config.ai_batch_max_attempts,
+                    },
+                );
+                let categorizer = TransactionCategorizer::new(
+                    claude_client,
+                    category_repository,
+                    jobs_config.categorization_batch_size,
+                    jobs_config.categorization_max_batches,
+                    jobs_config.categorization_min_confidence,
+                    prompts.clone(),
+                )
+                .with_response_cache(
+                    ai_response_cache,
+                    if jobs_config.categorization_bypass_cache {
+                        CacheMode::Bypass
+                    } else {
+                        CacheMode::Use
+                    },
+                );
+                scheduler = scheduler
+                    .add(&jobs_config.categorization_schedule, Arc::new(categorizer))
+                    .and_then(|scheduler| scheduler.add(&jobs_config.ai_batch_schedule, Arc::new(batch_processor)))
+                    .map_err(|e| {
+                        error!("Failed to configure categorization jobs: {}", e);
+                        e
+                    })?;
+            }
+            Err(e) => info!("Transaction categorization disabled: {}", e),
+        }
+        // Statements are copied into S3, so fetching needs file storage
+        if let Some(storage) = file_storage {
+            let statements = StatementFetchJob::new(
+                bank_data_providers.clone(),
+                plaid_item_repository.clone(),
+                bank_account_repository.clone(),
+                StatementRepository::new(pool.clone()),
+                storage,
+            );
+            scheduler = scheduler
+                .add(&jobs_config.statement_fetch_schedule, Arc::new(statements))
+                .map_err(|e| {
+                    error!("Failed to configure statement fetch job: {}", e);
+                    e
+                })?;
+        }
+        // Queued emails are sent when email is configured and wait in the queue otherwise
+        match notification_mailer.clone() {
+            Some(mailer) => {
+                let worker = Arc::new(EmailQueueWorker::new(
+                    email_queue_repository.clone(),
+                    otp_repository.clone(),
+                    mailer,
+                    EmailQueueSettings {
+                        batch_size: jobs_config.email_queue_batch_size,
+                        concurrency: jobs_config.email_queue_concurrency,
+                        max_attempts: jobs_config.email_queue_max_attempts,
+                    },
+                ));
+                scheduler = scheduler
+                    .add(&jobs_config.email_queue_schedule, worker.clone())
+                    .map_err(|e| {
+                        error!("Failed to configure email queue job: {}", e);
+                        e
+                    })?;
+                if let Some(queue) = email_send_queue {
+                    let settings = QueueWorkerSettings {
+                        concurrency: jobs_config.email_queue_concurrency,
+                        ..QueueWorkerSettings::default()
+                    };
+                    Arc::new(QueueWorker::new(queue, worker, settings)).spawn();
+                }
+            }
+            None => info!("Queued emails won't be sent until email is configured"),
+        }
+        // Campaigns wait like the queue until email is configured
+        if let Some(mailer) = notification_mailer.clone() {
+            let campaigns = EmailCampaignSender::new(
+                EmailCampaignRepository::new(pool.clone()),
+                mailer,
+                EmailCampaignSettings {
+                    batch_size: jobs_config.email_campaign_batch_size,
+                    concurrency: jobs_config.email_campaign_concurrency,
+                    sends_per_second: jobs_config.email_campaign_sends_per_second,
+                    max_attempts: jobs_config.email_campaign_max_attempts,
+                },
+            );
+            scheduler = scheduler
+                .add(&jobs_config.email_campaign_schedule, Arc::new(campaigns))
+                .map_err(|e| {
+                    error!("Failed to configure email campaign job: {}", e);
+                    e
+                })?;
+        }
+        // Delivery, open and click events arrive when the SES configuration set publishes them to an
+        // SNS topic with this SQS queue subscribed
+        match &settings.queues.email_events {
+            Some(config) => {
+                let queue = SqsQueue::new(config.clone()).await?;
+                let consumer = EmailEventConsumer::new(queue, EmailEventRepository::new(pool.clone()));
+                scheduler = scheduler
+                    .add(&jobs_config.email_events_schedule, Arc::new(consumer))
+                    .map_err(|e| {
+                        error!("Failed to configure email event job: {}", e);
+                        e
+                    })?;
+            }
+            None => info!("Email delivery events disabled: EMAIL_EVENTS_QUEUE_URL not set"),
+        }
+        // Bill reminders are queued for the users' mornings, so only when the queue is being sent
+        if notification_mailer.is_some() {
+            let reminders = BillReminderJob::new(
+                bill_repository.clone(),
+                notification_repository.clone(),
+                user_repository.clone(),
+                email_queue_repository.clone(),
+                jobs_config.bill_reminder_days_before,
+                jobs_config.bill_reminder_local_hour,
+            );
+            scheduler = scheduler
+                .add(&jobs_config.bill_reminder_schedule, Arc::new(reminders))
+                .map_err(|e| {
+                    error!("Failed to configure bill reminder job: {}", e);
+                    e
+                })?;
+        }
+        if let Some(queue) = plaid_webhook_queue.clone() {
+            Arc::new(QueueWorker::new(
+                queue,
+                transfer_event_sync.clone(),
+                QueueWorkerSettings::default(),
+            ))
+            .spawn();
+        }
+        scheduler = scheduler
+            .add(&jobs_config.transfer_event_sync_schedule, transfer_event_sync.clone())
+            .map_err(|e| {
+                error!("Failed to configure transfer event sync job: {}", e);
+                e
+            })?;
+        let webhook_delivery = WebhookDeliveryWorker::new(
+            webhook_repository.clone(),
+            webhook_client.clone(),
+            WebhookDeliverySettings {
+                batch_size: jobs_config.webhook_delivery_batch_size,
+                concurrency: jobs_config.webhook_delivery_concurrency,
+                max_attempts: jobs_config.webhook_delivery_max_attempts,
+            },
+        );
+        scheduler = scheduler
+            .add(&jobs_config.webhook_delivery_schedule, Arc::new(webhook_delivery))
+            .map_err(|e| {
+                error!("Failed to configure webhook delivery job: {}", e);
+                e
+            })?;
+        if let Some(sender) = digest_sender.clone() {
+            scheduler = scheduler
+                .add(
+                    &jobs_config.weekly_digest_schedule,
+                    Arc::new(WeeklyDigestJob::new(
+                        user_repository.clone(),
+                        sender,
+                        jobs_config.weekly_digest_local_hour,
+                    )),
+                )
+                .map_err(|e| {
+                    error!("Failed to configure weekly digest job: {}", e);
+                    e
+                })?;
+        }
+        info!(job_count = scheduler.len(), "Starting background job scheduler");
+        scheduler.start();
+    } else {
+        info!("Background jobs disabled");
+    }
+
+    // Delta-sync service for offline-capable clients, authenticated via bearer tokens
+    let sync_service = SyncHandler::new(
+        bank_account_repository.clone(),
+        transaction_repository.clone(),
+        user_repository.clone(),
+    );
+
+    let alerts_service = AlertsHandler::new(
+        alert_rule_repository,
+        notification_repository,
+        bank_account_repository.clone(),
+        user_repository.clone(),
+        email_preferences_repository.clone(),
+        digest_sender,
+    );
+    let unsubscribe_service = Arc::new(UnsubscribeHandler::new(email_preferences_repository, unsubscribe_links));
+
+    // Household sharing of bank accounts; access checks live in the accounts service
+    let sharing_service = SharingHandler::new(
+        AccountShareRepository::new(pool.clone()),
+        bank_account_repository.clone(),
+        AuditLogRepository::new(pool.clone()),
+    );
+
+    // ACH transfers between a user's linked accounts, plus the Plaid webhook that advances them
+    let transfers_service = TransfersHandler::new(
+        plaid_client.clone(),
+        transfer_repository,
+        plaid_item_repository.clone(),
+        bank_account_repository.clone(),
+        user_repository.clone(),
+        AuditLogRepository::new(pool.clone()),
+    );
+
+    let financial_assistant = FinancialAssistant::new(
+        bank_account_repository.clone(),
+        transaction_repository.clone(),
+        SpendingRepository::new(pool.clone()).with_read_pool(read_pool.clone()),
+        bill_repository.clone(),
+        prompts.clone(),
+    );
+    // User text is screened before reaching a provider; MODERATION_ALLOW/FLAG/BLOCK adjust the policy
+    let content_moderator = Arc::new(ContentModerator::new(
+        settings.moderation.clone(),
+        AuditLogRepository::new(pool.clone()),
+    )?);
+    let assistant_service = AssistantHandler::new(
+        llm_providers.clone(),
+        ai_usage_repository.clone(),
+        financial_assistant,
+        content_moderator.clone(),
+        AiBatchRepository::new(pool.clone()),
+        prompts.clone(),
+        ai_rate_limiter.clone(),
+    );
+    // Operator RPCs, limited to users with the admin role or listed in ADMIN_USER_IDS
+    let admin_ids = settings.admin_user_ids.clone();
+    let webhooks_service = WebhooksHandler::new(
+        webhook_repository,
+        webhook_client,
+        AuditLogRepository::new(pool.clone()),
+    );
+
+    let admin_service = AdminHandler::new(
+        admin_ids,
+        email_template_repository,
+        email_queue_repository,
+        EmailCampaignRepository::new(pool.clone()),
+        EmailEventRepository::new(pool.clone()),
+        notification_mailer.clone(),
+        AuditLogRepository::new(pool.clone()),
+        user_repository.clone(),
         session_manager,
-        user_repository,
         otp_repository,
+        plaid_item_repository.clone(),
     );
 
+    let chat_service = ChatHandler::new(
+        llm_providers,
+        ChatRepository::new(pool.clone()),
+        ai_usage_repository,
+        content_moderator,
+        prompts,
+        ai_rate_limiter,
+    );
+
+    // Serve the read-only GraphQL dashboard endpoint alongside gRPC
+    #[cfg(feature = "graphql")]
+    {
+        let graphql_config = settings.graphql.clone();
+        let schema = template::graphql::build_schema(
+            bank_account_repository.clone(),
+            plaid_item_repository.clone(),
+            transaction_repository.clone(),
+            graphql_config.limits,
+        );
+        let interceptor = AuthInterceptor::new(jwt_manager.clone());
+        tokio::spawn(async move {
+            if let Err(e) = template::graphql::server::serve(graphql_config, schema, interceptor).await {
+                error!("GraphQL server error: {}", e);
+            }
+        });
+    }
+
     // Configure CORS middleware
     let cors = CorsLayer::new()
         .allow_origin(Any)
         .allow_methods(Any)
         .allow_headers(Any);
 
+    // TLS is terminated here when GRPC_TLS_CERT and GRPC_TLS_KEY are set (see TlsConfig); without them
+    // the server speaks plaintext, as in local development
+    let tls_config = settings.tls.clone();
+    // With GRPC_TLS_CLIENT_CA, internal services listed in GRPC_TLS_TRUSTED_SERVICES may call with their
+    // client certificate instead of an access token
+    let auth_interceptor = AuthInterceptor::new(jwt_manager.clone())
+        .trust_services(tls_config.as_ref().map(TlsConfig::trusted_services).unwrap_or_default());
+    let grpc_tls = match tls_config {
+        Some(tls_config) => {
+            let reload_interval = tls_config.reload_interval;
+            let tls = Arc::new(GrpcTls::new(tls_config).await.map_err(|e| {
+                error!("Failed to load gRPC TLS certificates: {:#}", e);
+                e
+            })?);
+            if let Some(interval) = reload_interval {
+                tls.clone().spawn_reload(interval);
+            }
+            Some(tls)
+        }
+        None => None,
+    };
+
+    // Serve the auth and accounts RPCs as JSON over HTTP alongside gRPC, on the same handler instances,
+    // and receive Plaid's transfer webhooks
+    #[cfg(feature = "rest-gateway")]
+    {
+        let verifier = template::adapter::plaid_webhook::PlaidWebhookVerifier::new(plaid_client.clone());
+        let mut transfer_webhooks =
+            template::handler::transfers::TransferWebhookHandler::new(verifier, transfer_event_sync);
+        if let Some(queue) = plaid_webhook_queue {
+            transfer_webhooks = transfer_webhooks.with_queue(queue);
+        }
+        let gateway_config = settings.gateway.clone();
+        let gateway_state = template::gateway::GatewayState::new(
+            auth_service.clone(),
+            accounts_service.clone(),
+            unsubscribe_service.clone(),
+            Arc::new(transfer_webhooks),
+            auth_interceptor.clone(),
+            pool.clone(),
+        );
+        tokio::spawn(async move {
+            if let Err(e) = template::gateway::serve(gateway_config, gateway_state).await {
+                error!("REST gateway error: {}", e);
+            }
+        });
+    }
+
     // Build and run the gRPC server
-    let grpc_server = Server::builder()
-        .layer(ServiceBuilder::new().layer(cors))
+    let grpc_router = Server::builder()
+        .trace_fn(logging::grpc_request_span)
+        .layer(
+            ServiceBuilder::new()
+                .layer(cors)
+                .layer(RequestIdLayer)
+                .layer(RpcMetricsLayer::new(rpc_metrics, rpc_methods)),
+        )
         .add_service(GreeterServiceServer::new(greeter))
-        .add_service(AuthServiceServer::new(auth_service))
-        .serve(grpc_addr);
-
-    info!("gRPC server listening on {}", grpc_addr);
+        .add_service(AuthServiceServer::from_arc(auth_service))
+        .add_service(InterceptedService::new(
+            AccountsServiceServer::from_arc(accounts_service),
+            auth_interceptor.clone(),
+        ))
+        .add_service(SyncServiceServer::with_interceptor(
+            sync_service,
+            auth_interceptor.clone(),
+        ))
+        .add_service(AlertsServiceServer::with_interceptor(
+            alerts_service,
+            auth_interceptor.clone(),
+        ))
+        .add_service(SharingServiceServer::with_interceptor(
+            sharing_service,
+            auth_interceptor.clone(),
+        ))
+        .add_service(TransfersServiceServer::with_interceptor(
+            transfers_service,
+            auth_interceptor.clone(),
+        ))
+        .add_service(EmailUnsubscribeServiceServer::from_arc(unsubscribe_service))
+        .add_service(AssistantServiceServer::with_interceptor(
+            assistant_service,
+            auth_interceptor.clone(),
+        ))
+        .add_service(ChatServiceServer::with_interceptor(
+            chat_service,
+            auth_interceptor.clone(),
+        ))
+        .add_service(AdminServiceServer::with_interceptor(
+            admin_service,
+            auth_interceptor.clone(),
+        ))
+        .add_service(WebhooksServiceServer::with_interceptor(
+            webhooks_service,
+            auth_interceptor.clone(),
+        ));
 
     // Run the gRPC server
-    if let Err(e) = grpc_server.await {
+    let grpc_result = match grpc_tls {
+        Some(tls) => {
+            let incoming = tls.incoming(grpc_addr).await?;
+            info!("gRPC server listening on {} (TLS)", grpc_addr);
+            grpc_router.serve_with_incoming(incoming).await
+        }
+        None => {
+            info!("gRPC server listening on {}", grpc_addr);
+            grpc_router.serve(grpc_addr).await
+        }
+    };
+    if let Err(e) = grpc_result {
         error!("gRPC server error: {}", e);
     }
 
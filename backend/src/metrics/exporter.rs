@@ -0,0 +1,73 @@
+use crate::metrics::rpc::LATENCY_BUCKETS_MS;
+use crate::settings::SettingValues;
+use anyhow::{Context, Result};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tracing::info;
+
+/// Histogram of gRPC call latencies recorded by `RpcMetricsLayer`
+pub const RPC_LATENCY_METRIC: &str = "grpc_server_handling_seconds";
+/// Counter of finished gRPC calls by method and status code
+pub const RPC_HANDLED_METRIC: &str = "grpc_server_handled_total";
+
+/// Prometheus exporter configuration
+#[derive(Debug, Clone)]
+pub struct MetricsConfig {
+    /// Address `/metrics` is served on; `None` disables the exporter
+    pub addr: Option<SocketAddr>,
+    /// How often pool and queue gauges are sampled
+    pub sample_interval: Duration,
+}
+
+impl MetricsConfig {
+    /// Read from settings
+    /// - METRICS_ADDR: listen address of the Prometheus endpoint, or `off` (default: [::0]:9090)
+    /// - METRICS_SAMPLE_SECS: gauge sampling interval in seconds (default: 15)
+    pub fn from_values(values: &mut SettingValues) -> Self {
+        let addr = match values.optional("metrics-addr").as_deref() {
+            Some("off") => None,
+            _ => Some(values.parsed("metrics-addr", SocketAddr::from(([0u16; 8], 9090)))),
+        };
+        Self {
+            addr,
+            sample_interval: Duration::from_secs(values.parsed("metrics-sample-secs", 15u64).max(1)),
+        }
+    }
+}
+
+/// Latency buckets in seconds, matching the in-process SLO histogram
+fn latency_buckets() -> Vec<f64> {
+    LATENCY_BUCKETS_MS.iter().map(|ms| *ms as f64 / 1000.0).collect()
+}
+
+/// Install the global Prometheus recorder and serve it over HTTP; returns whether it was installed.
+/// Must run inside the Tokio runtime, which serves the endpoint.
+pub fn install(config: &MetricsConfig) -> Result<bool> {
+    let Some(addr) = config.addr else {
+        info!("Prometheus metrics endpoint disabled");
+        return Ok(false);
+    };
+    PrometheusBuilder::new()
+        .with_http_listener(addr)
+        .set_buckets_for_metric(Matcher::Full(RPC_LATENCY_METRIC.to_string()), &latency_buckets())
+        .context("Invalid latency buckets")?
+        .install()
+        .context("Failed to install the Prometheus exporter")?;
+
+    info!("Prometheus metrics listening on {}", addr);
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_buckets_in_seconds() {
+        let buckets = latency_buckets();
+        assert_eq!(buckets.len(), LATENCY_BUCKETS_MS.len());
+        assert_eq!(buckets[0], 0.005);
+        assert_eq!(buckets[buckets.len() - 1], 10.0);
+    }
+}
@@ -0,0 +1,10 @@
+// Service metrics, their Prometheus export and SLO evaluation
+pub mod exporter;
+pub mod resources;
+pub mod rpc;
+pub mod slo;
+
+pub use exporter::MetricsConfig;
+pub use resources::ResourceGauges;
+pub use rpc::{defined_methods, RpcMetrics, RpcMetricsLayer, WindowStats, UNKNOWN_METHOD};
+pub use slo::{BurnRateStatus, SloConfig, SloDefinition, SloMonitor};
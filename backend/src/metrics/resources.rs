@@ -0,0 +1,97 @@
+use crate::model::email_queue::EmailQueueRepository;
+use chrono::Utc;
+use metrics::gauge;
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+
+/// Fraction of a pool's capacity checked out, in [0, 1]
+pub fn utilization(in_use: usize, max_size: usize) -> f64 {
+    if max_size == 0 {
+        return 0.0;
+    }
+    (in_use as f64 / max_size as f64).min(1.0)
+}
+
+/// Samples connection pool and background queue gauges for the Prometheus exporter
+#[derive(Clone)]
+pub struct ResourceGauges {
+    db: PgPool,
+    redis: Vec<(String, deadpool_redis::Pool)>,
+    email_queue: EmailQueueRepository,
+}
+
+impl ResourceGauges {
+    pub fn new(db: PgPool, email_queue: EmailQueueRepository) -> Self {
+        Self {
+            db,
+            redis: Vec::new(),
+            email_queue,
+        }
+    }
+
+    /// Also sample a Redis pool, labelled `pool = name`
+    pub fn with_redis_pool<N: Into<String>>(mut self, name: N, pool: deadpool_redis::Pool) -> Self {
+        self.redis.push((name.into(), pool));
+        self
+    }
+
+    /// Record every gauge once
+    pub async fn sample(&self) {
+        let max_connections = self.db.options().get_max_connections() as usize;
+        let idle = self.db.num_idle();
+        let in_use = (self.db.size() as usize).saturating_sub(idle);
+        gauge!("db_pool_connections", "state" => "in_use").set(in_use as f64);
+        gauge!("db_pool_connections", "state" => "idle").set(idle as f64);
+        gauge!("db_pool_max_connections").set(max_connections as f64);
+        gauge!("db_pool_utilization").set(utilization(in_use, max_connections));
+
+        for (name, pool) in &self.redis {
+            let status = pool.status();
+            let in_use = status.size.saturating_sub(status.available);
+            gauge!("redis_pool_connections", "pool" => name.clone(), "state" => "in_use").set(in_use as f64);
+            gauge!("redis_pool_connections", "pool" => name.clone(), "state" => "idle").set(status.available as f64);
+            gauge!("redis_pool_waiting", "pool" => name.clone()).set(status.waiting as f64);
+            gauge!("redis_pool_utilization", "pool" => name.clone()).set(utilization(in_use, status.max_size));
+        }
+
+        match self.email_queue.backlog().await {
+            Ok(backlog) => {
+                gauge!("job_queue_depth", "queue" => "email", "state" => "due").set(backlog.due as f64);
+                gauge!("job_queue_depth", "queue" => "email", "state" => "scheduled").set(backlog.scheduled as f64);
+                gauge!("job_queue_depth", "queue" => "email", "state" => "running").set(backlog.sending as f64);
+                let oldest_due_seconds = backlog
+                    .oldest_due_age(Utc::now())
+                    .map(|age| age.num_seconds() as f64)
+                    .unwrap_or(0.0);
+                gauge!("job_queue_oldest_due_seconds", "queue" => "email").set(oldest_due_seconds);
+            }
+            Err(e) => warn!(error = ?e, "Failed to read email queue backlog for metrics"),
+        }
+    }
+
+    /// Sample every `interval` for the life of the process
+    pub fn spawn(self: Arc<Self>, interval: Duration) {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                self.sample().await;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utilization() {
+        assert_eq!(utilization(0, 10), 0.0);
+        assert_eq!(utilization(5, 10), 0.5);
+        assert_eq!(utilization(12, 10), 1.0);
+        assert_eq!(utilization(3, 0), 0.0);
+    }
+}
@@ -0,0 +1,333 @@
+use crate::metrics::exporter::{RPC_HANDLED_METRIC, RPC_LATENCY_METRIC};
+use anyhow::{Context as _, Result};
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use prost::Message;
+use prost_types::FileDescriptorSet;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tonic::codegen::http;
+use tonic::Code;
+use tower::{Layer, Service};
+
+/// Upper bounds (inclusive) of the latency histogram buckets; slower requests land in an overflow bucket
+pub const LATENCY_BUCKETS_MS: [u64; 11] = [5, 10, 25, 50, 100, 250, 500, 1000, 2500, 5000, 10000];
+
+const BUCKET_SECONDS: i64 = 60;
+
+/// Per-minute buckets kept per method; covers the longest burn-rate window
+const RETAINED_MINUTES: i64 = 6 * 60;
+
+/// Descriptors of every proto the server is built from, written by build.rs
+const FILE_DESCRIPTOR_SET: &[u8] = include_bytes!("../../envoy/proto.pb");
+
+/// Method recorded for paths no proto defines, so scanners and typos can't add series
+pub const UNKNOWN_METHOD: &str = "unknown";
+
+/// gRPC paths of every method the protos define, e.g. `/accounts.AccountsService/ListTransactions`
+pub fn defined_methods() -> Result<HashSet<String>> {
+    let descriptors = FileDescriptorSet::decode(FILE_DESCRIPTOR_SET).context("Invalid proto descriptor set")?;
+    Ok(descriptors
+        .file
+        .iter()
+        .flat_map(|file| {
+            let package = file.package();
+            file.service.iter().flat_map(move |service| {
+                let service_name = match package {
+                    "" => service.name().to_string(),
+                    package => format!("{}.{}", package, service.name()),
+                };
+                service
+                    .method
+                    .iter()
+                    .map(move |method| format!("/{}/{}", service_name, method.name()))
+            })
+        })
+        .collect())
+}
+
+/// Status codes that count against availability; client errors do not
+pub fn is_server_error(code: Code) -> bool {
+    matches!(
+        code,
+        Code::Unknown
+            | Code::DeadlineExceeded
+            | Code::Unimplemented
+            | Code::Internal
+            | Code::Unavailable
+            | Code::DataLoss
+    )
+}
+
+/// Request counts and latency histogram for one method over some window
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WindowStats {
+    pub total: u64,
+    pub server_errors: u64,
+    latency: [u64; LATENCY_BUCKETS_MS.len() + 1],
+}
+
+impl WindowStats {
+    fn observe(&mut self, code: Code, latency: Duration) {
+        self.total += 1;
+        if is_server_error(code) {
+            self.server_errors += 1;
+        }
+        let latency_ms = latency.as_millis() as u64;
+        let bucket = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|bound| latency_ms <= *bound)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.latency[bucket] += 1;
+    }
+
+    fn merge(&mut self, other: &WindowStats) {
+        self.total += other.total;
+        self.server_errors += other.server_errors;
+        for (count, other) in self.latency.iter_mut().zip(other.latency.iter()) {
+            *count += other;
+        }
+    }
+
+    /// Requests slower than `threshold_ms`.
+    ///
+    /// Thresholds between bucket bounds are rounded down to the nearest bound, so the
+    /// count errs towards reporting requests as slow.
+    pub fn slower_than(&self, threshold_ms: u64) -> u64 {
+        let first_slow_bucket = LATENCY_BUCKETS_MS
+            .iter()
+            .rposition(|bound| *bound <= threshold_ms)
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        self.latency[first_slow_bucket..].iter().sum()
+    }
+}
+
+#[derive(Debug, Default)]
+struct MethodSeries {
+    /// (minute since epoch, stats) ordered oldest first
+    buckets: VecDeque<(i64, WindowStats)>,
+}
+
+/// In-process per-method RPC counters with minute resolution, feeding SLO evaluation
+#[derive(Debug, Clone, Default)]
+pub struct RpcMetrics {
+    series: Arc<Mutex<HashMap<String, MethodSeries>>>,
+}
+
+impl RpcMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a finished call to `method` (the gRPC path, e.g. `/accounts.AccountsService/ListTransactions`),
+    /// here and in the Prometheus recorder if one is installed
+    pub fn record(&self, method: &str, code: Code, latency: Duration) {
+        self.record_at(method, code, latency, Utc::now());
+        metrics::counter!(RPC_HANDLED_METRIC, "grpc_method" => method.to_string(), "grpc_code" => format!("{:?}", code))
+            .increment(1);
+        metrics::histogram!(RPC_LATENCY_METRIC, "grpc_method" => method.to_string()).record(latency.as_secs_f64());
+    }
+
+    fn record_at(&self, method: &str, code: Code, latency: Duration, now: DateTime<Utc>) {
+        let minute = now.timestamp().div_euclid(BUCKET_SECONDS);
+        let Ok(mut series) = self.series.lock() else {
+            return;
+        };
+        let buckets = &mut series.entry(method.to_string()).or_default().buckets;
+
+        match buckets.back_mut() {
+            Some((last, stats)) if *last == minute => stats.observe(code, latency),
+            _ => {
+                let mut stats = WindowStats::default();
+                stats.observe(code, latency);
+                buckets.push_back((minute, stats));
+            }
+        }
+        while buckets.front().is_some_and(|(m, _)| *m <= minute - RETAINED_MINUTES) {
+            buckets.pop_front();
+        }
+    }
+
+    /// Aggregate stats for `method` over the trailing `window`
+    pub fn window(&self, method: &str, window: Duration) -> WindowStats {
+        self.window_at(method, window, Utc::now())
+    }
+
+    fn window_at(&self, method: &str, window: Duration, now: DateTime<Utc>) -> WindowStats {
+        let minute = now.timestamp().div_euclid(BUCKET_SECONDS);
+        let window_minutes = (window.as_secs() as i64 / BUCKET_SECONDS).max(1);
+        let mut total = WindowStats::default();
+
+        let Ok(series) = self.series.lock() else {
+            return total;
+        };
+        if let Some(method_series) = series.get(method) {
+            for (_, stats) in method_series
+                .buckets
+                .iter()
+                .filter(|(m, _)| *m > minute - window_minutes && *m <= minute)
+            {
+                total.merge(stats);
+            }
+        }
+        total
+    }
+
+    /// Methods that have received at least one call
+    pub fn methods(&self) -> Vec<String> {
+        self.series
+            .lock()
+            .map(|series| series.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Tower layer recording every gRPC call into `RpcMetrics`, under its path when it is one of
+/// `methods` and as `UNKNOWN_METHOD` otherwise
+#[derive(Debug, Clone)]
+pub struct RpcMetricsLayer {
+    metrics: RpcMetrics,
+    methods: Arc<HashSet<String>>,
+}
+
+impl RpcMetricsLayer {
+    pub fn new(metrics: RpcMetrics, methods: HashSet<String>) -> Self {
+        Self {
+            metrics,
+            methods: Arc::new(methods),
+        }
+    }
+}
+
+impl<S> Layer<S> for RpcMetricsLayer {
+    type Service = RpcMetricsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RpcMetricsService {
+            inner,
+            metrics: self.metrics.clone(),
+            methods: self.methods.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RpcMetricsService<S> {
+    inner: S,
+    metrics: RpcMetrics,
+    methods: Arc<HashSet<String>>,
+}
+
+impl<S> RpcMetricsService<S> {
+    /// Method a call to `path` is recorded under
+    fn method(&self, path: &str) -> String {
+        if self.methods.contains(path) {
+            path.to_string()
+        } else {
+            UNKNOWN_METHOD.to_string()
+        }
+    }
+}
+
+impl<S, ReqBody, ResBody> Service<http::Request<ReqBody>> for RpcMetricsService<S>
+where
+    S: Service<http::Request<ReqBody>, Response = http::Response<ResBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<ReqBody>) -> Self::Future {
+        let method = self.method(request.uri().path());
+        let metrics = self.metrics.clone();
+        let started = Instant::now();
+        let future = self.inner.call(request);
+
+        Box::pin(async move {
+            let result = future.await;
+            // Handler errors are sent as trailers-only responses, so the status is in the
+            // headers; a response without one is a success whose status follows in trailers.
+            let code = match &result {
+                Ok(response) => response
+                    .headers()
+                    .get("grpc-status")
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.parse::<i32>().ok())
+                    .map(Code::from_i32)
+                    .unwrap_or(Code::Ok),
+                Err(_) => Code::Unavailable,
+            };
+            metrics.record(&method, code, started.elapsed());
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const METHOD: &str = "/accounts.AccountsService/ListTransactions";
+
+    #[test]
+    fn test_window_aggregates_recent_minutes_only() {
+        let metrics = RpcMetrics::new();
+        let now = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 30).unwrap();
+
+        metrics.record_at(METHOD, Code::Ok, Duration::from_millis(20), now - chrono::Duration::minutes(10));
+        metrics.record_at(METHOD, Code::Internal, Duration::from_millis(20), now - chrono::Duration::minutes(2));
+        metrics.record_at(METHOD, Code::NotFound, Duration::from_millis(800), now);
+
+        let last_five = metrics.window_at(METHOD, Duration::from_secs(300), now);
+        assert_eq!(last_five.total, 2);
+        assert_eq!(last_five.server_errors, 1);
+
+        let last_hour = metrics.window_at(METHOD, Duration::from_secs(3600), now);
+        assert_eq!(last_hour.total, 3);
+        assert_eq!(metrics.window_at("/unknown", Duration::from_secs(3600), now).total, 0);
+    }
+
+    #[test]
+    fn test_slower_than_rounds_threshold_down_to_bucket_bound() {
+        let mut stats = WindowStats::default();
+        for latency_ms in [3, 40, 90, 300, 20_000] {
+            stats.observe(Code::Ok, Duration::from_millis(latency_ms));
+        }
+
+        assert_eq!(stats.slower_than(100), 2);
+        // 300ms rounds down to the 250ms bound
+        assert_eq!(stats.slower_than(300), 2);
+        assert_eq!(stats.slower_than(10_000), 1);
+        assert_eq!(stats.slower_than(1), 5);
+    }
+
+    #[test]
+    fn test_undefined_paths_share_one_method() {
+        let methods = defined_methods().unwrap();
+        assert!(methods.contains(METHOD));
+        assert!(methods.contains("/auth.AuthService/SendOtp"));
+
+        let service = RpcMetricsLayer::new(RpcMetrics::new(), methods).layer(());
+        assert_eq!(service.method(METHOD), METHOD);
+        assert_eq!(service.method("/accounts.AccountsService/NoSuchMethod"), UNKNOWN_METHOD);
+        assert_eq!(service.method("/wp-login.php"), UNKNOWN_METHOD);
+    }
+
+    #[test]
+    fn test_server_errors_exclude_client_errors() {
+        assert!(is_server_error(Code::Internal));
+        assert!(is_server_error(Code::Unavailable));
+        assert!(!is_server_error(Code::InvalidArgument));
+        assert!(!is_server_error(Code::PermissionDenied));
+        assert!(!is_server_error(Code::Ok));
+    }
+}
@@ -0,0 +1,395 @@
+use crate::adapter::alerting::{Alert, AlertSeverity, AlertSink};
+use crate::jobs::scheduler::Job;
+use crate::metrics::rpc::{RpcMetrics, WindowStats};
+use crate::settings::SettingValues;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// SLOs shipped with the service, used when `SLO_CONFIG_PATH` is unset
+const DEFAULT_SLO_CONFIG: &str = include_str!("../../config/slo.json");
+
+/// Calls required in the long window before a burn rate is trusted
+const DEFAULT_MIN_REQUESTS: u64 = 20;
+
+/// Objectives for one gRPC method
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SloDefinition {
+    /// gRPC path, e.g. `/accounts.AccountsService/ListTransactions`
+    pub method: String,
+    /// Fraction of calls that must not fail with a server error, e.g. 0.999
+    #[serde(default)]
+    pub availability: Option<f64>,
+    /// 99% of calls must complete within this many milliseconds
+    #[serde(default)]
+    pub latency_p99_ms: Option<u64>,
+}
+
+/// Per-method SLOs loaded from JSON config
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SloConfig {
+    #[serde(default = "default_min_requests")]
+    pub min_requests: u64,
+    pub objectives: Vec<SloDefinition>,
+}
+
+fn default_min_requests() -> u64 {
+    DEFAULT_MIN_REQUESTS
+}
+
+impl SloConfig {
+    pub fn from_json(json: &str) -> Result<Self> {
+        let config: SloConfig = serde_json::from_str(json).context("Invalid SLO config")?;
+        for objective in &config.objectives {
+            if !objective.method.starts_with('/') {
+                bail!("SLO method '{}' must be a gRPC path starting with '/'", objective.method);
+            }
+            if let Some(target) = objective.availability {
+                if !(0.0..1.0).contains(&target) {
+                    bail!("Availability target for {} must be in [0, 1)", objective.method);
+                }
+            }
+            if objective.availability.is_none() && objective.latency_p99_ms.is_none() {
+                bail!("SLO for {} defines no objective", objective.method);
+            }
+        }
+        Ok(config)
+    }
+
+    /// Load from the file at `SLO_CONFIG_PATH`, or the built-in defaults
+    pub fn from_values(values: &mut SettingValues) -> Self {
+        let config = match values.optional("slo-config-path") {
+            Some(path) => std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read SLO config {}", path))
+                .and_then(|json| Self::from_json(&json)),
+            None => Self::from_json(DEFAULT_SLO_CONFIG),
+        };
+        config.unwrap_or_else(|e| {
+            values.error(format!("slo-config-path is invalid: {:#}", e));
+            Self {
+                min_requests: DEFAULT_MIN_REQUESTS,
+                objectives: Vec::new(),
+            }
+        })
+    }
+}
+
+/// Service level indicator an objective is measured on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Sli {
+    Availability,
+    Latency,
+}
+
+impl Sli {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Sli::Availability => "availability",
+            Sli::Latency => "latency",
+        }
+    }
+}
+
+/// Multi-window burn-rate alerting rule: both windows must burn faster than `threshold`
+#[derive(Debug, Clone, Copy)]
+pub struct BurnRateRule {
+    pub severity: AlertSeverity,
+    pub long_window: Duration,
+    pub short_window: Duration,
+    pub threshold: f64,
+}
+
+/// Page when 2% of a 30-day budget burns in an hour; ticket when 5% burns in six hours
+pub const BURN_RATE_RULES: [BurnRateRule; 2] = [
+    BurnRateRule {
+        severity: AlertSeverity::Page,
+        long_window: Duration::from_secs(60 * 60),
+        short_window: Duration::from_secs(5 * 60),
+        threshold: 14.4,
+    },
+    BurnRateRule {
+        severity: AlertSeverity::Ticket,
+        long_window: Duration::from_secs(6 * 60 * 60),
+        short_window: Duration::from_secs(30 * 60),
+        threshold: 6.0,
+    },
+];
+
+/// How many times faster than sustainable the error budget is being spent
+pub fn burn_rate(bad: u64, total: u64, target: f64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    let budget = 1.0 - target;
+    if budget <= 0.0 {
+        return f64::INFINITY;
+    }
+    (bad as f64 / total as f64) / budget
+}
+
+/// Burn rate of one SLI under one rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct BurnRateStatus {
+    pub method: String,
+    pub sli: Sli,
+    pub severity: AlertSeverity,
+    pub long_burn_rate: f64,
+    pub short_burn_rate: f64,
+    pub threshold: f64,
+    pub requests: u64,
+    pub breached: bool,
+}
+
+impl BurnRateStatus {
+    fn alert_key(&self) -> String {
+        format!("slo:{}:{}:{}", self.method, self.sli.as_str(), self.severity.as_str())
+    }
+
+    fn to_alert(&self, resolved: bool) -> Alert {
+        Alert {
+            key: self.alert_key(),
+            severity: self.severity,
+            summary: format!("{} {} SLO burn rate high", self.method, self.sli.as_str()),
+            details: format!(
+                "Error budget burn rate is {:.1}x over the long window and {:.1}x over the short window \
+                 (threshold {:.1}x, {} requests).",
+                self.long_burn_rate, self.short_burn_rate, self.threshold, self.requests
+            ),
+            resolved,
+        }
+    }
+}
+
+/// Evaluate every objective under every burn-rate rule against the recorded metrics
+pub fn evaluate(config: &SloConfig, window: impl Fn(&str, Duration) -> WindowStats) -> Vec<BurnRateStatus> {
+    let mut statuses = Vec::new();
+
+    for objective in &config.objectives {
+        for rule in &BURN_RATE_RULES {
+            let long = window(&objective.method, rule.long_window);
+            let short = window(&objective.method, rule.short_window);
+
+            let mut push = |sli: Sli, long_bad: u64, short_bad: u64, target: f64| {
+                let long_burn_rate = burn_rate(long_bad, long.total, target);
+                let short_burn_rate = burn_rate(short_bad, short.total, target);
+                statuses.push(BurnRateStatus {
+                    method: objective.method.clone(),
+                    sli,
+                    severity: rule.severity,
+                    long_burn_rate,
+                    short_burn_rate,
+                    threshold: rule.threshold,
+                    requests: long.total,
+                    breached: long.total >= config.min_requests
+                        && long_burn_rate >= rule.threshold
+                        && short_burn_rate >= rule.threshold,
+                });
+            };
+
+            if let Some(target) = objective.availability {
+                push(Sli::Availability, long.server_errors, short.server_errors, target);
+            }
+            if let Some(threshold_ms) = objective.latency_p99_ms {
+                push(Sli::Latency, long.slower_than(threshold_ms), short.slower_than(threshold_ms), 0.99);
+            }
+        }
+    }
+
+    statuses
+}
+
+/// Scheduled job raising and resolving SLO burn-rate alerts
+pub struct SloMonitor {
+    config: SloConfig,
+    metrics: RpcMetrics,
+    sink: Arc<dyn AlertSink>,
+    /// Keys of alerts the sink accepted and no resolution was delivered for yet
+    firing: Mutex<HashSet<String>>,
+}
+
+impl SloMonitor {
+    pub fn new(config: SloConfig, metrics: RpcMetrics, sink: Arc<dyn AlertSink>) -> Self {
+        Self {
+            config,
+            metrics,
+            sink,
+            firing: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Alerts whose state differs from the last one delivered; an alert the sink failed to take
+    /// is still pending, so it is sent again on the next evaluation
+    fn transitions(&self, statuses: &[BurnRateStatus]) -> Vec<Alert> {
+        let Ok(firing) = self.firing.lock() else {
+            return Vec::new();
+        };
+
+        statuses
+            .iter()
+            .filter_map(|status| match (status.breached, firing.contains(&status.alert_key())) {
+                (true, false) => Some(status.to_alert(false)),
+                (false, true) => Some(status.to_alert(true)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Record that the sink accepted `alert`
+    fn delivered(&self, alert: &Alert) {
+        let Ok(mut firing) = self.firing.lock() else {
+            return;
+        };
+        if alert.resolved {
+            firing.remove(&alert.key);
+        } else {
+            firing.insert(alert.key.clone());
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Job for SloMonitor {
+    fn name(&self) -> &'static str {
+        "slo_monitor"
+    }
+
+    async fn run(&self) -> Result<()> {
+        let statuses = evaluate(&self.config, |method, window| self.metrics.window(method, window));
+        let alerts = self.transitions(&statuses);
+        debug!(objectives = statuses.len(), alerts = alerts.len(), "Evaluated SLO burn rates");
+
+        for alert in &alerts {
+            match self.sink.send(alert).await {
+                Ok(()) => self.delivered(alert),
+                Err(e) => {
+                    warn!(alert_key = %alert.key, error = ?e, "Failed to deliver alert; retrying on the next run")
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const METHOD: &str = "/accounts.AccountsService/ListTransactions";
+
+    fn config() -> SloConfig {
+        SloConfig::from_json(&format!(
+            r#"{{"min_requests": 10, "objectives": [{{"method": "{}", "availability": 0.999}}]}}"#,
+            METHOD
+        ))
+        .unwrap()
+    }
+
+    fn stats(total: u64, server_errors: u64) -> WindowStats {
+        WindowStats {
+            total,
+            server_errors,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_default_config_is_valid() {
+        let config = SloConfig::from_json(DEFAULT_SLO_CONFIG).unwrap();
+        assert!(!config.objectives.is_empty());
+    }
+
+    #[test]
+    fn test_config_validation() {
+        assert!(SloConfig::from_json(r#"{"objectives": [{"method": "NoSlash", "availability": 0.99}]}"#).is_err());
+        assert!(SloConfig::from_json(r#"{"objectives": [{"method": "/a.B/C", "availability": 1.5}]}"#).is_err());
+        assert!(SloConfig::from_json(r#"{"objectives": [{"method": "/a.B/C"}]}"#).is_err());
+    }
+
+    #[test]
+    fn test_burn_rate() {
+        assert_eq!(burn_rate(0, 0, 0.999), 0.0);
+        assert!((burn_rate(1, 1000, 0.999) - 1.0).abs() < 1e-9);
+        assert!((burn_rate(20, 1000, 0.99) - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_evaluate_requires_both_windows_to_burn() {
+        let config = config();
+
+        // 2% errors against a 0.1% budget burns at 20x in both windows
+        let statuses = evaluate(&config, |_, _| stats(1000, 20));
+        assert!(statuses.iter().all(|s| s.breached));
+
+        // Burst has ended: the short window is clean
+        let statuses = evaluate(&config, |_, window| {
+            if window <= Duration::from_secs(30 * 60) {
+                stats(100, 0)
+            } else {
+                stats(1000, 20)
+            }
+        });
+        assert!(statuses.iter().all(|s| !s.breached));
+
+        // Too little traffic to judge
+        let statuses = evaluate(&config, |_, _| stats(5, 5));
+        assert!(statuses.iter().all(|s| !s.breached));
+    }
+
+    /// Sink failing while `failing` is set, counting the alerts it accepted
+    #[derive(Default)]
+    struct FlakySink {
+        failing: std::sync::atomic::AtomicBool,
+        accepted: Mutex<Vec<Alert>>,
+    }
+
+    #[async_trait::async_trait]
+    impl AlertSink for FlakySink {
+        async fn send(&self, alert: &Alert) -> Result<()> {
+            if self.failing.load(std::sync::atomic::Ordering::Relaxed) {
+                bail!("Alert destination unavailable");
+            }
+            self.accepted.lock().unwrap().push(alert.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_transitions_fire_once_and_resolve() {
+        let monitor = SloMonitor::new(config(), RpcMetrics::new(), Arc::new(crate::adapter::alerting::LogAlertSink));
+
+        let breached = evaluate(&monitor.config, |_, _| stats(1000, 20));
+        let alerts = monitor.transitions(&breached);
+        assert_eq!(alerts.len(), 2);
+        assert!(alerts.iter().all(|a| !a.resolved));
+        alerts.iter().for_each(|alert| monitor.delivered(alert));
+        assert!(monitor.transitions(&breached).is_empty());
+
+        let healthy = evaluate(&monitor.config, |_, _| stats(1000, 0));
+        let alerts = monitor.transitions(&healthy);
+        assert_eq!(alerts.len(), 2);
+        assert!(alerts.iter().all(|a| a.resolved));
+    }
+
+    #[tokio::test]
+    async fn test_undelivered_alerts_stay_pending() {
+        let sink = Arc::new(FlakySink::default());
+        sink.failing.store(true, std::sync::atomic::Ordering::Relaxed);
+        let metrics = RpcMetrics::new();
+        let monitor = SloMonitor::new(config(), metrics.clone(), sink.clone());
+        for _ in 0..100 {
+            metrics.record(METHOD, tonic::Code::Internal, Duration::from_millis(5));
+        }
+
+        monitor.run().await.unwrap();
+        assert!(sink.accepted.lock().unwrap().is_empty());
+
+        sink.failing.store(false, std::sync::atomic::Ordering::Relaxed);
+        monitor.run().await.unwrap();
+        assert_eq!(sink.accepted.lock().unwrap().len(), 2);
+        monitor.run().await.unwrap();
+        assert_eq!(sink.accepted.lock().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,185 @@
+use crate::adapter::encryption::{EncryptedSecret, EnvelopeCipher};
+use crate::adapter::plaid::{AccountIdentity, IdentityOwner};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::{debug, info, instrument};
+use uuid::Uuid;
+
+/// Persisted identity row; owners stay encrypted until read through the repository
+#[derive(Debug, Clone, sqlx::FromRow)]
+struct AccountIdentityRow {
+    account_id: String,
+    item_id: String,
+    owners_ciphertext: Vec<u8>,
+    owners_nonce: Vec<u8>,
+    encrypted_data_key: Vec<u8>,
+    encryption_key_id: String,
+    fetched_at: DateTime<Utc>,
+}
+
+/// Decrypted account-owner identity
+#[derive(Debug, Clone)]
+pub struct StoredAccountIdentity {
+    pub account_id: String,
+    pub item_id: String,
+    pub owners: Vec<IdentityOwner>,
+    pub fetched_at: DateTime<Utc>,
+}
+
+impl StoredAccountIdentity {
+    /// Whether any owner lists `email`, compared case-insensitively
+    pub fn has_owner_email(&self, email: &str) -> bool {
+        let email = email.trim();
+        !email.is_empty()
+            && self
+                .owners
+                .iter()
+                .flat_map(|owner| owner.emails.iter())
+                .any(|contact| contact.data.trim().eq_ignore_ascii_case(email))
+    }
+}
+
+/// Account identity repository; owners are envelope-encrypted with the account ID as context
+#[derive(Clone)]
+pub struct AccountIdentityRepository {
+    pool: PgPool,
+    cipher: Arc<EnvelopeCipher>,
+}
+
+impl AccountIdentityRepository {
+    pub fn new(pool: PgPool, cipher: Arc<EnvelopeCipher>) -> Self {
+        Self { pool, cipher }
+    }
+
+    /// Insert or refresh the identities of an item's accounts in a single transaction.
+    ///
+    /// Identities of accounts that are not stored for the user are skipped.
+    #[instrument(skip(self, identities), fields(account_count = identities.len()))]
+    pub async fn upsert_identities(
+        &self,
+        user_id: Uuid,
+        item_id: &str,
+        identities: &[AccountIdentity],
+    ) -> Result<usize> {
+        debug!(user_id = %user_id, item_id = %item_id, "Storing account identities");
+
+        let mut tx = self.pool.begin().await?;
+        let mut stored = 0;
+
+        for identity in identities {
+            let owners = serde_json::to_string(&identity.owners).context("Failed to serialize owners")?;
+            let secret = self
+                .cipher
+                .encrypt(&owners, &identity.account_id)
+                .context("Failed to encrypt account identity")?;
+
+            let result = sqlx::query(
+                r#"
+                INSERT INTO account_identities (
+                    account_id, item_id, user_id, owners_ciphertext, owners_nonce,
+                    encrypted_data_key, encryption_key_id
+                )
+                SELECT $1, $2, $3, $4, $5, $6, $7
+                WHERE EXISTS (
+                    SELECT 1 FROM bank_accounts WHERE account_id = $1 AND user_id = $3
+                )
+                ON CONFLICT (account_id) DO UPDATE SET
+                    owners_ciphertext = EXCLUDED.owners_ciphertext,
+                    owners_nonce = EXCLUDED.owners_nonce,
+                    encrypted_data_key = EXCLUDED.encrypted_data_key,
+                    encryption_key_id = EXCLUDED.encryption_key_id,
+                    fetched_at = NOW(),
+                    updated_at = NOW()
+                WHERE account_identities.user_id = EXCLUDED.user_id
+                "#,
+            )
+            .bind(&identity.account_id)
+            .bind(item_id)
+            .bind(user_id)
+            .bind(&secret.ciphertext)
+            .bind(&secret.nonce)
+            .bind(&secret.encrypted_data_key)
+            .bind(&secret.key_id)
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("Failed to store identity for account {}", identity.account_id))?;
+
+            stored += result.rows_affected() as usize;
+        }
+
+        tx.commit().await?;
+
+        info!(user_id = %user_id, item_id = %item_id, stored, "Successfully stored account identities");
+        Ok(stored)
+    }
+
+    /// Find and decrypt the identity of a user's account
+    #[instrument(skip(self))]
+    pub async fn find_by_account(&self, user_id: Uuid, account_id: &str) -> Result<Option<StoredAccountIdentity>> {
+        let row = sqlx::query_as::<_, AccountIdentityRow>(
+            r#"
+            SELECT account_id, item_id, owners_ciphertext, owners_nonce, encrypted_data_key,
+                   encryption_key_id, fetched_at
+            FROM account_identities
+            WHERE account_id = $1 AND user_id = $2
+            "#,
+        )
+        .bind(account_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        let secret = EncryptedSecret {
+            ciphertext: row.owners_ciphertext,
+            nonce: row.owners_nonce,
+            encrypted_data_key: row.encrypted_data_key,
+            key_id: row.encryption_key_id,
+        };
+        let owners = self
+            .cipher
+            .decrypt(&secret, &row.account_id)
+            .context("Failed to decrypt account identity")?;
+        let owners = serde_json::from_str(&owners).context("Failed to deserialize owners")?;
+
+        Ok(Some(StoredAccountIdentity {
+            account_id: row.account_id,
+            item_id: row.item_id,
+            owners,
+            fetched_at: row.fetched_at,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapter::plaid::IdentityContact;
+
+    #[test]
+    fn test_has_owner_email_ignores_case() {
+        let identity = StoredAccountIdentity {
+            account_id: "acc_1".to_string(),
+            item_id: "item_1".to_string(),
+            owners: vec![IdentityOwner {
+                names: vec!["Alberta Charleson".to_string()],
+                emails: vec![IdentityContact {
+                    data: "Alberta@Example.com".to_string(),
+                    primary: true,
+                    contact_type: "primary".to_string(),
+                }],
+                ..Default::default()
+            }],
+            fetched_at: Utc::now(),
+        };
+
+        assert!(identity.has_owner_email("alberta@example.com"));
+        assert!(!identity.has_owner_email("someone@example.com"));
+        assert!(!identity.has_owner_email(""));
+    }
+}
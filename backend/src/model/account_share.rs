@@ -0,0 +1,252 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use tracing::{info, instrument};
+use uuid::Uuid;
+
+/// What a user may do with an account shared with them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShareAccess {
+    /// View the account, its balances and transactions
+    Read,
+    /// Also import transactions and recategorize them
+    Full,
+}
+
+impl ShareAccess {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ShareAccess::Read => "read",
+            ShareAccess::Full => "full",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "read" => Some(ShareAccess::Read),
+            "full" => Some(ShareAccess::Full),
+            _ => None,
+        }
+    }
+
+    /// Whether this access level covers `needed`
+    pub fn allows(&self, needed: ShareAccess) -> bool {
+        *self == ShareAccess::Full || needed == ShareAccess::Read
+    }
+}
+
+/// Lifecycle of a share
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShareStatus {
+    /// Invitation not yet answered
+    Pending,
+    Accepted,
+    Declined,
+    /// Ended by the owner or the grantee
+    Revoked,
+}
+
+impl ShareStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ShareStatus::Pending => "pending",
+            ShareStatus::Accepted => "accepted",
+            ShareStatus::Declined => "declined",
+            ShareStatus::Revoked => "revoked",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(ShareStatus::Pending),
+            "accepted" => Some(ShareStatus::Accepted),
+            "declined" => Some(ShareStatus::Declined),
+            "revoked" => Some(ShareStatus::Revoked),
+            _ => None,
+        }
+    }
+}
+
+/// Account shared, or offered, by its owner to another user
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct AccountShare {
+    pub id: Uuid,
+    pub account_id: String,
+    pub owner_user_id: Uuid,
+    pub invitee_email: String,
+    /// Set once the invitee accepts
+    pub grantee_user_id: Option<Uuid>,
+    pub access: String,
+    pub status: String,
+    pub responded_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl AccountShare {
+    pub fn access(&self) -> Option<ShareAccess> {
+        ShareAccess::parse(&self.access)
+    }
+
+    pub fn status(&self) -> Option<ShareStatus> {
+        ShareStatus::parse(&self.status)
+    }
+}
+
+/// Account share repository for database operations
+#[derive(Debug, Clone)]
+pub struct AccountShareRepository {
+    pool: PgPool,
+}
+
+impl AccountShareRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Invite `invitee_email` to an account; returns `None` when an open invitation or share already exists
+    #[instrument(skip(self, invitee_email))]
+    pub async fn invite(
+        &self,
+        owner_user_id: Uuid,
+        account_id: &str,
+        invitee_email: &str,
+        access: ShareAccess,
+    ) -> Result<Option<AccountShare>> {
+        let share = sqlx::query_as::<_, AccountShare>(
+            r#"
+            INSERT INTO account_shares (account_id, owner_user_id, invitee_email, access)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (account_id, LOWER(invitee_email)) WHERE status IN ('pending', 'accepted') DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(account_id)
+        .bind(owner_user_id)
+        .bind(invitee_email)
+        .bind(access.as_str())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        if let Some(share) = &share {
+            info!(share_id = %share.id, "Created account share invitation");
+        }
+        Ok(share)
+    }
+
+    /// Open invitations and shares of accounts the user owns, newest first
+    #[instrument(skip(self))]
+    pub async fn list_outgoing(&self, owner_user_id: Uuid) -> Result<Vec<AccountShare>> {
+        let shares = sqlx::query_as::<_, AccountShare>(
+            r#"
+            SELECT * FROM account_shares
+            WHERE owner_user_id = $1 AND status IN ('pending', 'accepted')
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(owner_user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(shares)
+    }
+
+    /// Invitations addressed to `email` and shares the user accepted, newest first
+    #[instrument(skip(self, email))]
+    pub async fn list_incoming(&self, user_id: Uuid, email: &str) -> Result<Vec<AccountShare>> {
+        let shares = sqlx::query_as::<_, AccountShare>(
+            r#"
+            SELECT * FROM account_shares
+            WHERE (status = 'pending' AND LOWER(invitee_email) = LOWER($2) AND owner_user_id <> $1)
+               OR (status = 'accepted' AND grantee_user_id = $1)
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(user_id)
+        .bind(email)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(shares)
+    }
+
+    /// Accept or decline a pending invitation addressed to `email`; returns `None` when there is no such invitation
+    #[instrument(skip(self, email))]
+    pub async fn respond(&self, share_id: Uuid, user_id: Uuid, email: &str, accept: bool) -> Result<Option<AccountShare>> {
+        let status = if accept { ShareStatus::Accepted } else { ShareStatus::Declined };
+        let share = sqlx::query_as::<_, AccountShare>(
+            r#"
+            UPDATE account_shares
+            SET status = $4,
+                grantee_user_id = CASE WHEN $4 = 'accepted' THEN $2 END,
+                responded_at = NOW(),
+                updated_at = NOW()
+            WHERE id = $1 AND status = 'pending' AND LOWER(invitee_email) = LOWER($3) AND owner_user_id <> $2
+            RETURNING *
+            "#,
+        )
+        .bind(share_id)
+        .bind(user_id)
+        .bind(email)
+        .bind(status.as_str())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(share)
+    }
+
+    /// End an open invitation or share; either the owner or the grantee may revoke it
+    #[instrument(skip(self))]
+    pub async fn revoke(&self, share_id: Uuid, user_id: Uuid) -> Result<Option<AccountShare>> {
+        let share = sqlx::query_as::<_, AccountShare>(
+            r#"
+            UPDATE account_shares
+            SET status = 'revoked', revoked_at = NOW(), updated_at = NOW()
+            WHERE id = $1
+              AND status IN ('pending', 'accepted')
+              AND (owner_user_id = $2 OR grantee_user_id = $2)
+            RETURNING *
+            "#,
+        )
+        .bind(share_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(share)
+    }
+
+    /// Access a user has been granted to another user's account
+    #[instrument(skip(self))]
+    pub async fn access_for(&self, user_id: Uuid, account_id: &str) -> Result<Option<ShareAccess>> {
+        let access: Option<String> = sqlx::query_scalar(
+            r#"
+            SELECT access FROM account_shares
+            WHERE account_id = $1 AND grantee_user_id = $2 AND status = 'accepted'
+            "#,
+        )
+        .bind(account_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(access.as_deref().and_then(ShareAccess::parse))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_access_levels() {
+        assert!(ShareAccess::Full.allows(ShareAccess::Full));
+        assert!(ShareAccess::Full.allows(ShareAccess::Read));
+        assert!(ShareAccess::Read.allows(ShareAccess::Read));
+        assert!(!ShareAccess::Read.allows(ShareAccess::Full));
+        assert_eq!(ShareAccess::parse("full"), Some(ShareAccess::Full));
+        assert_eq!(ShareStatus::parse("revoked"), Some(ShareStatus::Revoked));
+        assert_eq!(ShareStatus::parse("expired"), None);
+    }
+}
@@ -0,0 +1,390 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use tracing::{info, instrument};
+use uuid::Uuid;
+
+/// Kind of AI work a batch performs; each kind has its own runner
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AiBatchKind {
+    /// Assign taxonomy categories to transactions; inputs are transaction IDs
+    Categorization,
+}
+
+impl AiBatchKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AiBatchKind::Categorization => "categorization",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "categorization" => Some(AiBatchKind::Categorization),
+            _ => None,
+        }
+    }
+}
+
+/// Progress of a batch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AiBatchStatus {
+    /// No task has been claimed yet
+    Queued,
+    /// Some tasks are being processed or waiting
+    Running,
+    /// Every task succeeded or failed
+    Completed,
+}
+
+impl AiBatchStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AiBatchStatus::Queued => "queued",
+            AiBatchStatus::Running => "running",
+            AiBatchStatus::Completed => "completed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "queued" => Some(AiBatchStatus::Queued),
+            "running" => Some(AiBatchStatus::Running),
+            "completed" => Some(AiBatchStatus::Completed),
+            _ => None,
+        }
+    }
+}
+
+/// Progress of one task
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AiTaskStatus {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+impl AiTaskStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AiTaskStatus::Queued => "queued",
+            AiTaskStatus::Running => "running",
+            AiTaskStatus::Succeeded => "succeeded",
+            AiTaskStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "queued" => Some(AiTaskStatus::Queued),
+            "running" => Some(AiTaskStatus::Running),
+            "succeeded" => Some(AiTaskStatus::Succeeded),
+            "failed" => Some(AiTaskStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// Persisted batch with its task counts
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct AiBatch {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub kind: String,
+    pub status: String,
+    pub total_tasks: i32,
+    pub succeeded_tasks: i32,
+    pub failed_tasks: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl AiBatch {
+    pub fn kind(&self) -> Option<AiBatchKind> {
+        AiBatchKind::parse(&self.kind)
+    }
+
+    pub fn status(&self) -> Option<AiBatchStatus> {
+        AiBatchStatus::parse(&self.status)
+    }
+}
+
+/// Persisted task; `output` is set when it succeeded and `error` when it failed
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct AiBatchTask {
+    pub id: i64,
+    pub batch_id: Uuid,
+    pub input: String,
+    pub status: String,
+    pub attempts: i32,
+    pub output: Option<serde_json::Value>,
+    pub error: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl AiBatchTask {
+    pub fn status(&self) -> Option<AiTaskStatus> {
+        AiTaskStatus::parse(&self.status)
+    }
+}
+
+/// Task claimed for processing, with what its runner needs from the batch
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ClaimedTask {
+    pub id: i64,
+    pub batch_id: Uuid,
+    pub user_id: Uuid,
+    pub kind: String,
+    pub input: String,
+    pub attempts: i32,
+}
+
+/// Batches and their tasks
+#[derive(Debug, Clone)]
+pub struct AiBatchRepository {
+    pool: PgPool,
+}
+
+impl AiBatchRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Create a queued batch with one task per input
+    #[instrument(skip(self, inputs), fields(task_count = inputs.len()))]
+    pub async fn create(&self, user_id: Uuid, kind: AiBatchKind, inputs: &[String]) -> Result<AiBatch> {
+        let mut tx = self.pool.begin().await?;
+        let batch = sqlx::query_as::<_, AiBatch>(
+            r#"
+            INSERT INTO ai_batches (user_id, kind, total_tasks)
+            VALUES ($1, $2, $3)
+            RETURNING *
+            "#,
+        )
+        .bind(user_id)
+        .bind(kind.as_str())
+        .bind(inputs.len() as i32)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO ai_batch_tasks (batch_id, input)
+            SELECT $1, input FROM UNNEST($2::TEXT[]) WITH ORDINALITY AS t(input, ordinal)
+            ORDER BY ordinal
+            "#,
+        )
+        .bind(batch.id)
+        .bind(inputs)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        info!(batch_id = %batch.id, kind = kind.as_str(), "AI batch created");
+        Ok(batch)
+    }
+
+    /// A user's batch
+    #[instrument(skip(self))]
+    pub async fn find(&self, user_id: Uuid, batch_id: Uuid) -> Result<Option<AiBatch>> {
+        let batch = sqlx::query_as::<_, AiBatch>("SELECT * FROM ai_batches WHERE id = $1 AND user_id = $2")
+            .bind(batch_id)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(batch)
+    }
+
+    /// Tasks of a batch in submission order
+    #[instrument(skip(self))]
+    pub async fn list_tasks(&self, batch_id: Uuid) -> Result<Vec<AiBatchTask>> {
+        let tasks = sqlx::query_as::<_, AiBatchTask>(
+            r#"
+            SELECT id, batch_id, input, status, attempts, output, error, updated_at
+            FROM ai_batch_tasks
+            WHERE batch_id = $1
+            ORDER BY id
+            "#,
+        )
+        .bind(batch_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(tasks)
+    }
+
+    /// Claim up to `limit` queued tasks, oldest first, along with running tasks whose claim is older
+    /// than `lease_seconds`. Concurrent workers claim disjoint tasks.
+    #[instrument(skip(self))]
+    pub async fn claim(&self, limit: i64, lease_seconds: i64, max_attempts: i32) -> Result<Vec<ClaimedTask>> {
+        let tasks = sqlx::query_as::<_, ClaimedTask>(
+            r#"
+            WITH claimed AS (
+                UPDATE ai_batch_tasks
+                SET status = 'running', attempts = attempts + 1, claimed_at = NOW(), updated_at = NOW()
+                WHERE id IN (
+                    SELECT id FROM ai_batch_tasks
+                    WHERE attempts < $3
+                      AND (status = 'queued'
+                           OR (status = 'running' AND claimed_at < NOW() - make_interval(secs => $2)))
+                    ORDER BY id
+                    LIMIT $1
+                    FOR UPDATE SKIP LOCKED
+                )
+                RETURNING id, batch_id, input, attempts
+            ),
+            started AS (
+                UPDATE ai_batches
+                SET status = 'running', updated_at = NOW()
+                WHERE id IN (SELECT batch_id FROM claimed) AND status = 'queued'
+            )
+            SELECT c.id, c.batch_id, b.user_id, b.kind, c.input, c.attempts
+            FROM claimed c
+            JOIN ai_batches b ON b.id = c.batch_id
+            ORDER BY c.id
+            "#,
+        )
+        .bind(limit)
+        .bind(lease_seconds as f64)
+        .bind(max_attempts)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(tasks)
+    }
+
+    /// Fail tasks whose last claim expired after their final attempt, such as when a worker crashed;
+    /// returns the batches they belong to
+    #[instrument(skip(self))]
+    pub async fn fail_abandoned(&self, lease_seconds: i64, max_attempts: i32) -> Result<Vec<Uuid>> {
+        let batch_ids: Vec<Uuid> = sqlx::query_scalar(
+            r#"
+            UPDATE ai_batch_tasks
+            SET status = 'failed', error = 'Task did not finish', updated_at = NOW()
+            WHERE status = 'running' AND attempts >= $2 AND claimed_at < NOW() - make_interval(secs => $1)
+            RETURNING batch_id
+            "#,
+        )
+        .bind(lease_seconds as f64)
+        .bind(max_attempts)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(batch_ids)
+    }
+
+    #[instrument(skip(self, output))]
+    pub async fn succeed(&self, task_id: i64, output: &serde_json::Value) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE ai_batch_tasks
+            SET status = 'succeeded', output = $2, error = NULL, updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(task_id)
+        .bind(output)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    pub async fn fail(&self, task_id: i64, error: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE ai_batch_tasks
+            SET status = 'failed', error = $2, updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(task_id)
+        .bind(error)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Put tasks back in the queue. With `refund_attempt`, the attempt doesn't count towards the
+    /// limit, as when the provider was rate limiting rather than the task failing.
+    #[instrument(skip(self, task_ids), fields(task_count = task_ids.len()))]
+    pub async fn requeue(&self, task_ids: &[i64], error: &str, refund_attempt: bool) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE ai_batch_tasks
+            SET status = 'queued',
+                attempts = CASE WHEN $3 THEN GREATEST(attempts - 1, 0) ELSE attempts END,
+                error = $2,
+                claimed_at = NULL,
+                updated_at = NOW()
+            WHERE id = ANY($1)
+            "#,
+        )
+        .bind(task_ids)
+        .bind(error)
+        .bind(refund_attempt)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Recount the tasks of batches and complete those with nothing left to process
+    #[instrument(skip(self, batch_ids), fields(batch_count = batch_ids.len()))]
+    pub async fn refresh(&self, batch_ids: &[Uuid]) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            UPDATE ai_batches b
+            SET succeeded_tasks = c.succeeded,
+                failed_tasks = c.failed,
+                status = CASE WHEN c.pending = 0 THEN 'completed' ELSE b.status END,
+                completed_at = CASE WHEN c.pending = 0 THEN COALESCE(b.completed_at, NOW()) ELSE NULL END,
+                updated_at = NOW()
+            FROM (
+                SELECT batch_id,
+                       COUNT(*) FILTER (WHERE status = 'succeeded') AS succeeded,
+                       COUNT(*) FILTER (WHERE status = 'failed') AS failed,
+                       COUNT(*) FILTER (WHERE status IN ('queued', 'running')) AS pending
+                FROM ai_batch_tasks
+                WHERE batch_id = ANY($1)
+                GROUP BY batch_id
+            ) c
+            WHERE b.id = c.batch_id
+            "#,
+        )
+        .bind(batch_ids)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_round_trip() {
+        for kind in [AiBatchKind::Categorization] {
+            assert_eq!(AiBatchKind::parse(kind.as_str()), Some(kind));
+        }
+        for status in [AiBatchStatus::Queued, AiBatchStatus::Running, AiBatchStatus::Completed] {
+            assert_eq!(AiBatchStatus::parse(status.as_str()), Some(status));
+        }
+        for status in [
+            AiTaskStatus::Queued,
+            AiTaskStatus::Running,
+            AiTaskStatus::Succeeded,
+            AiTaskStatus::Failed,
+        ] {
+            assert_eq!(AiTaskStatus::parse(status.as_str()), Some(status));
+        }
+        assert_eq!(AiBatchKind::parse("enrichment"), None);
+    }
+}
@@ -0,0 +1,347 @@
+use crate::settings::SettingValues;
+use anyhow::{Context, Result};
+use chrono::Utc;
+use deadpool_redis::Pool;
+use std::time::Duration;
+use tracing::{debug, instrument, warn};
+use uuid::Uuid;
+
+/// Suggested wait when too many requests are in progress, as it depends on when they finish
+const CONCURRENCY_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// Token buckets of a user and of all users; a request takes a token from both or from neither.
+/// Returns `{0, 0}` when admitted, otherwise the milliseconds until both buckets have a token and the
+/// bucket that is empty longest (1 for the user's, 2 for the global one).
+const TAKE_TOKEN_SCRIPT: &str = r#"
+local now = tonumber(ARGV[1])
+local wait = 0
+local empty = 0
+local levels = {}
+for i = 1, 2 do
+    local capacity = tonumber(ARGV[i * 2])
+    local per_ms = tonumber(ARGV[i * 2 + 1])
+    if capacity > 0 then
+        local state = redis.call('HMGET', KEYS[i], 'tokens', 'at')
+        local tokens = tonumber(state[1]) or capacity
+        local at = tonumber(state[2]) or now
+        tokens = math.min(capacity, tokens + math.max(0, now - at) * per_ms)
+        levels[i] = tokens
+        if tokens < 1 then
+            local bucket_wait = math.ceil((1 - tokens) / per_ms)
+            if bucket_wait > wait then
+                wait = bucket_wait
+                empty = i
+            end
+        end
+    end
+end
+if wait > 0 then
+    return {wait, empty}
+end
+for i = 1, 2 do
+    if levels[i] then
+        local capacity = tonumber(ARGV[i * 2])
+        local per_ms = tonumber(ARGV[i * 2 + 1])
+        redis.call('HSET', KEYS[i], 'tokens', levels[i] - 1, 'at', now)
+        redis.call('PEXPIRE', KEYS[i], math.ceil(capacity / per_ms))
+    end
+end
+return {0, 0}
+"#;
+
+/// In-flight leases of a user and of all users; expired leases of crashed requests are dropped first.
+/// Returns 0 when admitted, 1 at the user's limit and 2 at the global limit.
+const ACQUIRE_LEASE_SCRIPT: &str = r#"
+local now = tonumber(ARGV[1])
+local expires = tonumber(ARGV[2])
+for i = 1, 2 do
+    local limit = tonumber(ARGV[i + 3])
+    if limit > 0 then
+        redis.call('ZREMRANGEBYSCORE', KEYS[i], '-inf', now)
+        if redis.call('ZCARD', KEYS[i]) >= limit then
+            return i
+        end
+    end
+end
+for i = 1, 2 do
+    if tonumber(ARGV[i + 3]) > 0 then
+        redis.call('ZADD', KEYS[i], expires, ARGV[3])
+        redis.call('PEXPIREAT', KEYS[i], expires)
+    end
+end
+return 0
+"#;
+
+/// Limits on AI requests; a zero disables that limit
+#[derive(Debug, Clone, PartialEq)]
+pub struct AiRateLimits {
+    /// Requests a user may start per minute once their burst is spent
+    pub user_requests_per_minute: u32,
+    /// Requests a user may start at once after being idle
+    pub user_burst: u32,
+    /// Requests all users together may start per minute, to stay within the provider's quota
+    pub global_requests_per_minute: u32,
+    pub global_burst: u32,
+    /// Requests of one user in progress at once
+    pub user_concurrency: u32,
+    /// Requests of all users in progress at once
+    pub global_concurrency: u32,
+    /// Seconds after which an unreleased in-flight slot is reclaimed, e.g. when a server stops mid-request
+    pub lease_seconds: u64,
+}
+
+impl Default for AiRateLimits {
+    fn default() -> Self {
+        Self {
+            user_requests_per_minute: 10,
+            user_burst: 5,
+            global_requests_per_minute: 600,
+            global_burst: 100,
+            user_concurrency: 2,
+            global_concurrency: 50,
+            lease_seconds: 300,
+        }
+    }
+}
+
+impl AiRateLimits {
+    /// Read `AI_USER_REQUESTS_PER_MINUTE`, `AI_USER_BURST`, `AI_GLOBAL_REQUESTS_PER_MINUTE`, `AI_GLOBAL_BURST`,
+    /// `AI_USER_CONCURRENCY`, `AI_GLOBAL_CONCURRENCY` and `AI_REQUEST_LEASE_SECONDS`
+    pub fn from_values(values: &mut SettingValues) -> Self {
+        let defaults = Self::default();
+        Self {
+            user_requests_per_minute: values.parsed("ai-user-requests-per-minute", defaults.user_requests_per_minute),
+            user_burst: values.parsed("ai-user-burst", defaults.user_burst),
+            global_requests_per_minute: values
+                .parsed("ai-global-requests-per-minute", defaults.global_requests_per_minute),
+            global_burst: values.parsed("ai-global-burst", defaults.global_burst),
+            user_concurrency: values.parsed("ai-user-concurrency", defaults.user_concurrency),
+            global_concurrency: values.parsed("ai-global-concurrency", defaults.global_concurrency),
+            lease_seconds: values
+                .parsed_optional("ai-request-lease-seconds")
+                .filter(|v: &u64| *v > 0)
+                .unwrap_or(defaults.lease_seconds),
+        }
+    }
+}
+
+/// Bucket size and refill rate in tokens per millisecond; a zero size means unlimited
+fn bucket(requests_per_minute: u32, burst: u32) -> (u32, f64) {
+    if requests_per_minute == 0 {
+        return (0, 0.0);
+    }
+    (burst.max(1), requests_per_minute as f64 / 60_000.0)
+}
+
+/// Which limit refused a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AiLimit {
+    UserRate,
+    GlobalRate,
+    UserConcurrency,
+    GlobalConcurrency,
+}
+
+impl AiLimit {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AiLimit::UserRate => "user_rate",
+            AiLimit::GlobalRate => "global_rate",
+            AiLimit::UserConcurrency => "user_concurrency",
+            AiLimit::GlobalConcurrency => "global_concurrency",
+        }
+    }
+
+    /// Explanation shown to the user
+    pub fn message(&self) -> &'static str {
+        match self {
+            AiLimit::UserRate => "Too many AI requests; please slow down",
+            AiLimit::GlobalRate => "The assistant is busy; please try again shortly",
+            AiLimit::UserConcurrency => "Wait for your other AI requests to finish",
+            AiLimit::GlobalConcurrency => "The assistant is busy; please try again shortly",
+        }
+    }
+}
+
+/// Outcome of asking to start an AI request
+pub enum AiAdmission {
+    Admitted(AiPermit),
+    /// Refused by `limit`; retry after this long
+    Throttled(AiLimit, Duration),
+}
+
+/// In-flight slot of an admitted request, released when dropped
+pub struct AiPermit {
+    lease: Option<Lease>,
+}
+
+struct Lease {
+    redis_pool: Pool,
+    keys: [String; 2],
+    id: String,
+}
+
+impl AiPermit {
+    /// Permit that holds no slot, for requests admitted without checking the limits
+    pub fn unlimited() -> Self {
+        Self { lease: None }
+    }
+}
+
+impl Drop for AiPermit {
+    fn drop(&mut self) {
+        let Some(lease) = self.lease.take() else {
+            return;
+        };
+        // Drop can't wait; without a runtime the lease simply expires
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        runtime.spawn(async move {
+            let result = async {
+                let mut conn = lease.redis_pool.get().await?;
+                redis::pipe()
+                    .cmd("ZREM")
+                    .arg(&lease.keys[0])
+                    .arg(&lease.id)
+                    .ignore()
+                    .cmd("ZREM")
+                    .arg(&lease.keys[1])
+                    .arg(&lease.id)
+                    .ignore()
+                    .query_async::<_, ()>(&mut conn)
+                    .await?;
+                anyhow::Ok(())
+            }
+            .await;
+            if let Err(e) = result {
+                warn!(error = ?e, "Failed to release AI request slot; it expires with its lease");
+            }
+        });
+    }
+}
+
+/// Time until a throttled request may retry, rounded up to whole seconds for clients
+fn retry_after(wait_ms: i64) -> Duration {
+    Duration::from_secs((wait_ms.max(1) as u64).div_ceil(1000))
+}
+
+fn user_rate_key(user_id: Uuid) -> String {
+    format!("ai_rate:user:{}", user_id)
+}
+
+fn user_inflight_key(user_id: Uuid) -> String {
+    format!("ai_inflight:user:{}", user_id)
+}
+
+const GLOBAL_RATE_KEY: &str = "ai_rate:global";
+const GLOBAL_INFLIGHT_KEY: &str = "ai_inflight:global";
+
+/// Redis rate limiter and concurrency caps for AI requests, shared by every server.
+///
+/// Token buckets bound how fast a user and all users together start requests, and in-flight leases
+/// bound how many run at once, so a single user cannot use up the provider quota.
+#[derive(Clone)]
+pub struct AiRateLimiter {
+    redis_pool: Pool,
+    limits: AiRateLimits,
+}
+
+impl AiRateLimiter {
+    pub fn new(redis_url: &str, limits: AiRateLimits) -> Result<Self> {
+        let cfg = deadpool_redis::Config::from_url(redis_url);
+        let redis_pool = cfg
+            .create_pool(Some(deadpool_redis::Runtime::Tokio1))
+            .context("Failed to create Redis connection pool")?;
+
+        Ok(Self { redis_pool, limits })
+    }
+
+    pub fn limits(&self) -> &AiRateLimits {
+        &self.limits
+    }
+
+    /// Take a token from the user's and the global bucket, then an in-flight slot of each
+    #[instrument(skip(self))]
+    pub async fn acquire(&self, user_id: Uuid) -> Result<AiAdmission> {
+        let mut conn = self.redis_pool.get().await
+            .context("Failed to get Redis connection from pool")?;
+        let now_ms = Utc::now().timestamp_millis();
+
+        let (user_capacity, user_per_ms) = bucket(self.limits.user_requests_per_minute, self.limits.user_burst);
+        let (global_capacity, global_per_ms) = bucket(self.limits.global_requests_per_minute, self.limits.global_burst);
+        let (wait_ms, empty): (i64, i64) = redis::cmd("EVAL")
+            .arg(TAKE_TOKEN_SCRIPT)
+            .arg(2)
+            .arg(user_rate_key(user_id))
+            .arg(GLOBAL_RATE_KEY)
+            .arg(now_ms)
+            .arg(user_capacity)
+            .arg(user_per_ms)
+            .arg(global_capacity)
+            .arg(global_per_ms)
+            .query_async(&mut conn)
+            .await
+            .context("Failed to check AI rate limit")?;
+        if wait_ms > 0 {
+            let limit = if empty == 1 {
+                AiLimit::UserRate
+            } else {
+                AiLimit::GlobalRate
+            };
+            debug!(limit = limit.as_str(), wait_ms, "AI request throttled");
+            return Ok(AiAdmission::Throttled(limit, retry_after(wait_ms)));
+        }
+
+        if self.limits.user_concurrency == 0 && self.limits.global_concurrency == 0 {
+            return Ok(AiAdmission::Admitted(AiPermit::unlimited()));
+        }
+        let lease_id = Uuid::new_v4().to_string();
+        let keys = [user_inflight_key(user_id), GLOBAL_INFLIGHT_KEY.to_string()];
+        let expires_ms = now_ms + (self.limits.lease_seconds * 1000) as i64;
+        let refused: i64 = redis::cmd("EVAL")
+            .arg(ACQUIRE_LEASE_SCRIPT)
+            .arg(2)
+            .arg(&keys[0])
+            .arg(&keys[1])
+            .arg(now_ms)
+            .arg(expires_ms)
+            .arg(&lease_id)
+            .arg(self.limits.user_concurrency)
+            .arg(self.limits.global_concurrency)
+            .query_async(&mut conn)
+            .await
+            .context("Failed to claim AI request slot")?;
+        match refused {
+            0 => Ok(AiAdmission::Admitted(AiPermit {
+                lease: Some(Lease {
+                    redis_pool: self.redis_pool.clone(),
+                    keys,
+                    id: lease_id,
+                }),
+            })),
+            1 => Ok(AiAdmission::Throttled(AiLimit::UserConcurrency, CONCURRENCY_RETRY_AFTER)),
+            _ => Ok(AiAdmission::Throttled(AiLimit::GlobalConcurrency, CONCURRENCY_RETRY_AFTER)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket() {
+        assert_eq!(bucket(60, 5), (5, 0.001));
+        assert_eq!(bucket(60, 0), (1, 0.001));
+        assert_eq!(bucket(0, 5), (0, 0.0));
+    }
+
+    #[test]
+    fn test_retry_after_rounds_up() {
+        assert_eq!(retry_after(1), Duration::from_secs(1));
+        assert_eq!(retry_after(1000), Duration::from_secs(1));
+        assert_eq!(retry_after(1001), Duration::from_secs(2));
+        assert_eq!(retry_after(0), Duration::from_secs(1));
+    }
+}
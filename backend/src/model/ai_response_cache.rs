@@ -0,0 +1,149 @@
+use crate::adapter::claude_ai::{ClaudeAIClient, ClaudeRequest, ClaudeResponse};
+use anyhow::{Context, Result};
+use deadpool_redis::Pool;
+use redis::AsyncCommands;
+use sha2::{Digest, Sha256};
+use tracing::{debug, instrument, warn};
+
+/// Replies to the same prompt stay valid for a day by default
+pub const DEFAULT_TTL_SECONDS: u64 = 24 * 60 * 60;
+
+/// Whether a request may be answered from the cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CacheMode {
+    /// Reuse a cached reply and cache new ones
+    #[default]
+    Use,
+    /// Always call the API; the fresh reply still replaces the cached one
+    Bypass,
+}
+
+/// Key of a request: a hash of everything that shapes the reply, so any change to model, prompt or parameters misses
+pub fn cache_key(request: &ClaudeRequest) -> Result<String> {
+    let body = serde_json::to_vec(request).context("Failed to serialize Claude request")?;
+    Ok(format!("ai_response:{:x}", Sha256::digest(body)))
+}
+
+/// Redis cache of Claude replies to deterministic prompts, such as categorization batches.
+///
+/// Only requests whose reply depends on nothing but the prompt should go through it; replies are
+/// shared by every caller that sends the same request.
+#[derive(Clone)]
+pub struct AiResponseCache {
+    redis_pool: Pool,
+    ttl_seconds: u64,
+}
+
+impl AiResponseCache {
+    pub fn new(redis_url: &str, ttl_seconds: u64) -> Result<Self> {
+        let cfg = deadpool_redis::Config::from_url(redis_url);
+        let redis_pool = cfg
+            .create_pool(Some(deadpool_redis::Runtime::Tokio1))
+            .context("Failed to create Redis connection pool")?;
+
+        Ok(Self {
+            redis_pool,
+            ttl_seconds: ttl_seconds.max(1),
+        })
+    }
+
+    #[instrument(skip(self))]
+    pub async fn get(&self, key: &str) -> Result<Option<ClaudeResponse>> {
+        let mut conn = self.redis_pool.get().await
+            .context("Failed to get Redis connection from pool")?;
+
+        let value: Option<String> = conn.get(key).await
+            .context("Failed to read cached AI response")?;
+        let Some(value) = value else { return Ok(None) };
+
+        match serde_json::from_str(&value) {
+            Ok(response) => Ok(Some(response)),
+            // Treated as a miss so the entry is overwritten by the next reply
+            Err(e) => {
+                warn!(error = %e, "Discarding unreadable cached AI response");
+                Ok(None)
+            }
+        }
+    }
+
+    #[instrument(skip(self, response))]
+    pub async fn put(&self, key: &str, response: &ClaudeResponse) -> Result<()> {
+        let mut conn = self.redis_pool.get().await
+            .context("Failed to get Redis connection from pool")?;
+
+        let data = serde_json::to_string(response).context("Failed to serialize AI response")?;
+        conn.set_ex::<_, _, ()>(key, data, self.ttl_seconds).await
+            .context("Failed to cache AI response")?;
+
+        debug!(ttl_seconds = self.ttl_seconds, "Cached AI response");
+        Ok(())
+    }
+
+    /// Send a non-streamed request through the cache.
+    ///
+    /// Cache failures only cost the saving: the request then goes to the API and the reply is returned uncached.
+    #[instrument(skip(self, client, request), fields(model = %request.model))]
+    pub async fn send_message(
+        &self,
+        client: &ClaudeAIClient,
+        request: ClaudeRequest,
+        mode: CacheMode,
+    ) -> Result<ClaudeResponse> {
+        let key = cache_key(&request)?;
+
+        if mode == CacheMode::Use {
+            match self.get(&key).await {
+                Ok(Some(response)) => {
+                    debug!("AI response cache hit");
+                    return Ok(response);
+                }
+                Ok(None) => debug!("AI response cache miss"),
+                Err(e) => warn!("Failed to read AI response cache: {:?}", e),
+            }
+        }
+
+        let response = client.send_message(request).await?;
+        if let Err(e) = self.put(&key, &response).await {
+            warn!("Failed to write AI response cache: {:?}", e);
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> ClaudeRequest {
+        ClaudeRequest {
+            model: "claude-3-haiku-20240307".to_string(),
+            max_tokens: 256,
+            temperature: Some(0.0),
+            messages: vec![ClaudeAIClient::user_message("Categorize: COFFEE SHOP 4.50")],
+            system: Some("You categorize bank transactions.".into()),
+            stop_sequences: None,
+            stream: Some(false),
+        }
+    }
+
+    #[test]
+    fn test_cache_key_covers_model_prompt_and_params() {
+        let key = cache_key(&request()).unwrap();
+        assert_eq!(key, cache_key(&request()).unwrap());
+        assert!(key.starts_with("ai_response:"));
+
+        let variants = [
+            ClaudeRequest { model: "claude-3-sonnet-20240229".to_string(), ..request() },
+            ClaudeRequest { max_tokens: 512, ..request() },
+            ClaudeRequest { temperature: Some(0.5), ..request() },
+            ClaudeRequest { system: None, ..request() },
+            ClaudeRequest {
+                messages: vec![ClaudeAIClient::user_message("Categorize: GROCERY 52.10")],
+                ..request()
+            },
+        ];
+        for variant in &variants {
+            assert_ne!(cache_key(variant).unwrap(), key);
+        }
+    }
+}
@@ -0,0 +1,617 @@
+use crate::settings::SettingValues;
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Datelike, Months, NaiveDate, TimeZone, Utc};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use tracing::{debug, instrument};
+use uuid::Uuid;
+
+/// Product feature an AI request was made for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AiFeature {
+    /// Streamed assistant replies
+    Assistant,
+    /// Questions answered from the user's own data
+    FinancialAssistant,
+    /// Saved chat conversations
+    Chat,
+    /// Receipt OCR
+    ReceiptScan,
+    /// Queued transaction categorization batches
+    BatchCategorization,
+    /// Notification email copy
+    EmailDrafting,
+}
+
+impl AiFeature {
+    pub const ALL: [AiFeature; 6] = [
+        AiFeature::Assistant,
+        AiFeature::FinancialAssistant,
+        AiFeature::Chat,
+        AiFeature::ReceiptScan,
+        AiFeature::BatchCategorization,
+        AiFeature::EmailDrafting,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AiFeature::Assistant => "assistant",
+            AiFeature::FinancialAssistant => "financial_assistant",
+            AiFeature::Chat => "chat",
+            AiFeature::ReceiptScan => "receipt_scan",
+            AiFeature::BatchCategorization => "batch_categorization",
+            AiFeature::EmailDrafting => "email_drafting",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "assistant" => Some(AiFeature::Assistant),
+            "financial_assistant" => Some(AiFeature::FinancialAssistant),
+            "chat" => Some(AiFeature::Chat),
+            "receipt_scan" => Some(AiFeature::ReceiptScan),
+            "batch_categorization" => Some(AiFeature::BatchCategorization),
+            "email_drafting" => Some(AiFeature::EmailDrafting),
+            _ => None,
+        }
+    }
+}
+
+/// List prices in USD per million tokens, matched by model name prefix; more specific prefixes first
+const MODEL_PRICES: &[(&str, f64, f64)] = &[
+    ("claude-3-opus", 15.0, 75.0),
+    ("claude-opus", 15.0, 75.0),
+    ("claude-3-5-haiku", 0.8, 4.0),
+    ("claude-3-haiku", 0.25, 1.25),
+    ("claude-haiku", 0.8, 4.0),
+    ("claude", 3.0, 15.0),
+    ("gpt-4o-mini", 0.15, 0.6),
+    ("gpt-4o", 2.5, 10.0),
+];
+
+/// Price of models missing from the table, so unknown models still count against cost quotas
+const DEFAULT_PRICE: (f64, f64) = (3.0, 15.0);
+
+/// Environment recorded when `ENVIRONMENT` is unset, matching the parameter store default
+pub const DEFAULT_ENVIRONMENT: &str = "dev";
+
+/// Prompt cache reads and writes, as multiples of the input price
+const CACHE_READ_PRICE_FACTOR: f64 = 0.1;
+const CACHE_WRITE_PRICE_FACTOR: f64 = 1.25;
+
+/// Per-model token prices converting usage into estimated USD
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CostModel {
+    /// Prices in USD per million input and output tokens, by model name prefix, checked before the list prices
+    overrides: Vec<(String, f64, f64)>,
+}
+
+impl CostModel {
+    /// Parse `AI_MODEL_PRICES`, a comma-separated list of `model-prefix=input/output` prices in USD
+    /// per million tokens, e.g. `claude-3-7-sonnet=3/15,my-finetune=1.5/6`
+    pub fn from_values(values: &mut SettingValues) -> Self {
+        Self::parse(&values.or("ai-model-prices", "")).unwrap_or_else(|e| {
+            values.error(format!("ai-model-prices is invalid: {}", e));
+            Self::default()
+        })
+    }
+
+    pub fn parse(prices: &str) -> Result<Self> {
+        let overrides = prices
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (prefix, price) = entry
+                    .split_once('=')
+                    .ok_or_else(|| anyhow!("Model price '{}' must look like model=input/output", entry))?;
+                let (input, output) = price
+                    .split_once('/')
+                    .ok_or_else(|| anyhow!("Model price '{}' must look like model=input/output", entry))?;
+                let parse = |value: &str| {
+                    value
+                        .trim()
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|v| v.is_finite() && *v >= 0.0)
+                        .ok_or_else(|| anyhow!("Invalid price '{}' in '{}'", value.trim(), entry))
+                };
+                Ok((prefix.trim().to_string(), parse(input)?, parse(output)?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { overrides })
+    }
+
+    /// Prices in USD per million input and output tokens of `model`
+    pub fn prices(&self, model: &str) -> (f64, f64) {
+        self.overrides
+            .iter()
+            .map(|(prefix, input, output)| (prefix.as_str(), *input, *output))
+            .chain(MODEL_PRICES.iter().copied())
+            .find(|(prefix, _, _)| model.starts_with(prefix))
+            .map(|(_, input, output)| (input, output))
+            .unwrap_or(DEFAULT_PRICE)
+    }
+
+    /// Estimated cost in USD of a request
+    pub fn cost(&self, usage: &NewAiUsage<'_>) -> f64 {
+        let (input_price, output_price) = self.prices(usage.model);
+        let input = usage.input_tokens as f64
+            + usage.cache_read_tokens as f64 * CACHE_READ_PRICE_FACTOR
+            + usage.cache_write_tokens as f64 * CACHE_WRITE_PRICE_FACTOR;
+        (input * input_price + usage.output_tokens as f64 * output_price) / 1_000_000.0
+    }
+}
+
+/// Start of the calendar month (UTC) containing `now`; quotas reset at the next one
+pub fn quota_period(now: DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+    let month = NaiveDate::from_ymd_opt(now.year(), now.month(), 1).expect("first of month is a valid date");
+    let next = month + Months::new(1);
+    let midnight = |date: NaiveDate| Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).expect("midnight is valid"));
+    (midnight(month), midnight(next))
+}
+
+/// Monthly per-user limits on AI usage; unset limits don't apply
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AiQuota {
+    /// Input plus output tokens
+    pub monthly_tokens: Option<i64>,
+    /// Estimated cost in USD
+    pub monthly_cost_usd: Option<f64>,
+}
+
+impl AiQuota {
+    /// Read `AI_MONTHLY_TOKEN_QUOTA` and `AI_MONTHLY_COST_QUOTA_USD`
+    pub fn from_values(values: &mut SettingValues) -> Self {
+        Self {
+            monthly_tokens: values
+                .parsed_optional("ai-monthly-token-quota")
+                .filter(|v: &i64| *v > 0),
+            monthly_cost_usd: values
+                .parsed_optional("ai-monthly-cost-quota-usd")
+                .filter(|v: &f64| v.is_finite() && *v > 0.0),
+        }
+    }
+
+    /// Whether the period's usage has reached either limit
+    pub fn exceeded_by(&self, usage: &AiUsageTotals) -> bool {
+        self.monthly_tokens.is_some_and(|limit| usage.total_tokens() >= limit)
+            || self.monthly_cost_usd.is_some_and(|limit| usage.cost_usd >= limit)
+    }
+}
+
+/// Where a feature's spending stands against its monthly budget
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BudgetStatus {
+    /// No budget, or spending below the warning threshold
+    Within,
+    /// Spending passed the warning threshold
+    Warning { spent_usd: f64, budget_usd: f64 },
+    /// Spending reached the budget; the feature is stopped until the next month
+    Exceeded { spent_usd: f64, budget_usd: f64 },
+}
+
+/// Monthly spending limits per feature, over all users of one environment
+#[derive(Debug, Clone, PartialEq)]
+pub struct AiBudgets {
+    monthly_usd: HashMap<AiFeature, f64>,
+    /// Share of a budget at which warnings start
+    warning_ratio: f64,
+}
+
+impl Default for AiBudgets {
+    fn default() -> Self {
+        Self {
+            monthly_usd: HashMap::new(),
+            warning_ratio: 0.8,
+        }
+    }
+}
+
+impl AiBudgets {
+    /// Read `AI_FEATURE_BUDGETS_USD`, e.g. `chat=200,receipt_scan=50`, and `AI_BUDGET_WARNING_RATIO`
+    pub fn from_values(values: &mut SettingValues) -> Self {
+        let budgets = Self::parse(&values.or("ai-feature-budgets-usd", "")).unwrap_or_else(|e| {
+            values.error(format!("ai-feature-budgets-usd is invalid: {}", e));
+            Self::default()
+        });
+        let Some(ratio) = values.parsed_optional("ai-budget-warning-ratio") else {
+            return budgets;
+        };
+        budgets.clone().with_warning_ratio(ratio).unwrap_or_else(|e| {
+            values.error(format!("ai-budget-warning-ratio is invalid: {}", e));
+            budgets
+        })
+    }
+
+    pub fn parse(budgets: &str) -> Result<Self> {
+        let mut monthly_usd = HashMap::new();
+        for entry in budgets.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (feature, amount) = entry
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Budget '{}' must look like feature=amount", entry))?;
+            let feature = AiFeature::parse(feature.trim())
+                .ok_or_else(|| anyhow!("Unknown AI feature '{}' in budget", feature.trim()))?;
+            let amount: f64 = amount
+                .trim()
+                .parse()
+                .ok()
+                .filter(|v: &f64| v.is_finite() && *v > 0.0)
+                .ok_or_else(|| anyhow!("Invalid budget amount in '{}'", entry))?;
+            monthly_usd.insert(feature, amount);
+        }
+        Ok(Self {
+            monthly_usd,
+            ..Self::default()
+        })
+    }
+
+    pub fn with_warning_ratio(mut self, ratio: f64) -> Result<Self> {
+        if !(ratio > 0.0 && ratio <= 1.0) {
+            bail!("Budget warning ratio must be in (0, 1], got {}", ratio);
+        }
+        self.warning_ratio = ratio;
+        Ok(self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.monthly_usd.is_empty()
+    }
+
+    pub fn budget(&self, feature: AiFeature) -> Option<f64> {
+        self.monthly_usd.get(&feature).copied()
+    }
+
+    /// Status of `feature` having spent `spent_usd` this month
+    pub fn status(&self, feature: AiFeature, spent_usd: f64) -> BudgetStatus {
+        match self.budget(feature) {
+            Some(budget_usd) if spent_usd >= budget_usd => BudgetStatus::Exceeded { spent_usd, budget_usd },
+            Some(budget_usd) if spent_usd >= budget_usd * self.warning_ratio => {
+                BudgetStatus::Warning { spent_usd, budget_usd }
+            }
+            _ => BudgetStatus::Within,
+        }
+    }
+}
+
+/// Usage summed over a period, overall or for one feature
+#[derive(Debug, Clone, Default, PartialEq, sqlx::FromRow)]
+pub struct AiUsageTotals {
+    pub request_count: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_read_tokens: i64,
+    pub cache_write_tokens: i64,
+    pub cost_usd: f64,
+}
+
+impl AiUsageTotals {
+    /// All tokens read and generated, including prompt cache reads and writes
+    pub fn total_tokens(&self) -> i64 {
+        self.input_tokens + self.output_tokens + self.cache_read_tokens + self.cache_write_tokens
+    }
+}
+
+/// Usage of one feature over a period
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct FeatureUsage {
+    pub feature: String,
+    #[sqlx(flatten)]
+    pub totals: AiUsageTotals,
+}
+
+/// One completed AI request to record
+#[derive(Debug, Clone)]
+pub struct NewAiUsage<'a> {
+    pub user_id: Uuid,
+    pub feature: AiFeature,
+    pub provider: &'a str,
+    pub model: &'a str,
+    /// Input tokens read outside the prompt cache
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    pub cache_read_tokens: u32,
+    pub cache_write_tokens: u32,
+}
+
+/// AI usage repository; also holds the configured quota, prices and budgets
+#[derive(Debug, Clone)]
+pub struct AiUsageRepository {
+    pool: PgPool,
+    quota: AiQuota,
+    cost_model: CostModel,
+    budgets: AiBudgets,
+    /// Deployment the usage is recorded for, so environments sharing a database are budgeted apart
+    environment: String,
+}
+
+impl AiUsageRepository {
+    pub fn new(pool: PgPool, quota: AiQuota) -> Self {
+        Self {
+            pool,
+            quota,
+            cost_model: CostModel::default(),
+            budgets: AiBudgets::default(),
+            environment: DEFAULT_ENVIRONMENT.to_string(),
+        }
+    }
+
+    pub fn with_cost_model(mut self, cost_model: CostModel) -> Self {
+        self.cost_model = cost_model;
+        self
+    }
+
+    pub fn with_budgets(mut self, budgets: AiBudgets) -> Self {
+        self.budgets = budgets;
+        self
+    }
+
+    pub fn with_environment(mut self, environment: &str) -> Self {
+        self.environment = environment.to_string();
+        self
+    }
+
+    pub fn quota(&self) -> AiQuota {
+        self.quota
+    }
+
+    pub fn budgets(&self) -> &AiBudgets {
+        &self.budgets
+    }
+
+    pub fn environment(&self) -> &str {
+        &self.environment
+    }
+
+    #[instrument(skip(self, usage), fields(user_id = %usage.user_id, feature = usage.feature.as_str()))]
+    pub async fn record(&self, usage: &NewAiUsage<'_>) -> Result<()> {
+        let cost_usd = self.cost_model.cost(usage);
+        sqlx::query(
+            r#"
+            INSERT INTO ai_usage (
+                user_id, feature, provider, model, input_tokens, output_tokens,
+                cache_read_tokens, cache_write_tokens, cost_usd, environment
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            "#,
+        )
+        .bind(usage.user_id)
+        .bind(usage.feature.as_str())
+        .bind(usage.provider)
+        .bind(usage.model)
+        .bind(usage.input_tokens as i32)
+        .bind(usage.output_tokens as i32)
+        .bind(usage.cache_read_tokens as i32)
+        .bind(usage.cache_write_tokens as i32)
+        .bind(cost_usd)
+        .bind(&self.environment)
+        .execute(&self.pool)
+        .await?;
+
+        debug!(
+            input_tokens = usage.input_tokens,
+            output_tokens = usage.output_tokens,
+            cache_read_tokens = usage.cache_read_tokens,
+            cache_write_tokens = usage.cache_write_tokens,
+            cost_usd,
+            "AI usage recorded"
+        );
+        Ok(())
+    }
+
+    /// A user's usage since `since`, per feature
+    #[instrument(skip(self))]
+    pub async fn usage_by_feature(&self, user_id: Uuid, since: DateTime<Utc>) -> Result<Vec<FeatureUsage>> {
+        let usage = sqlx::query_as::<_, FeatureUsage>(
+            r#"
+            SELECT
+                feature,
+                COUNT(*) AS request_count,
+                COALESCE(SUM(input_tokens), 0)::BIGINT AS input_tokens,
+                COALESCE(SUM(output_tokens), 0)::BIGINT AS output_tokens,
+                COALESCE(SUM(cache_read_tokens), 0)::BIGINT AS cache_read_tokens,
+                COALESCE(SUM(cache_write_tokens), 0)::BIGINT AS cache_write_tokens,
+                COALESCE(SUM(cost_usd), 0) AS cost_usd
+            FROM ai_usage
+            WHERE user_id = $1 AND created_at >= $2
+            GROUP BY feature
+            ORDER BY feature
+            "#,
+        )
+        .bind(user_id)
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(usage)
+    }
+
+    /// A user's usage since `since`, over all features
+    #[instrument(skip(self))]
+    pub async fn totals(&self, user_id: Uuid, since: DateTime<Utc>) -> Result<AiUsageTotals> {
+        let totals = sqlx::query_as::<_, AiUsageTotals>(
+            r#"
+            SELECT
+                COUNT(*) AS request_count,
+                COALESCE(SUM(input_tokens), 0)::BIGINT AS input_tokens,
+                COALESCE(SUM(output_tokens), 0)::BIGINT AS output_tokens,
+                COALESCE(SUM(cache_read_tokens), 0)::BIGINT AS cache_read_tokens,
+                COALESCE(SUM(cache_write_tokens), 0)::BIGINT AS cache_write_tokens,
+                COALESCE(SUM(cost_usd), 0) AS cost_usd
+            FROM ai_usage
+            WHERE user_id = $1 AND created_at >= $2
+            "#,
+        )
+        .bind(user_id)
+        .bind(since)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(totals)
+    }
+
+    /// Usage of all users of this environment since `since`, per feature
+    #[instrument(skip(self))]
+    pub async fn environment_usage_by_feature(&self, since: DateTime<Utc>) -> Result<Vec<FeatureUsage>> {
+        let usage = sqlx::query_as::<_, FeatureUsage>(
+            r#"
+            SELECT
+                feature,
+                COUNT(*) AS request_count,
+                COALESCE(SUM(input_tokens), 0)::BIGINT AS input_tokens,
+                COALESCE(SUM(output_tokens), 0)::BIGINT AS output_tokens,
+                COALESCE(SUM(cache_read_tokens), 0)::BIGINT AS cache_read_tokens,
+                COALESCE(SUM(cache_write_tokens), 0)::BIGINT AS cache_write_tokens,
+                COALESCE(SUM(cost_usd), 0) AS cost_usd
+            FROM ai_usage
+            WHERE environment = $1 AND created_at >= $2
+            GROUP BY feature
+            ORDER BY feature
+            "#,
+        )
+        .bind(&self.environment)
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(usage)
+    }
+
+    /// Where `feature` stands against its budget this month, across all users of this environment
+    #[instrument(skip(self), fields(feature = feature.as_str()))]
+    pub async fn budget_status(&self, feature: AiFeature, now: DateTime<Utc>) -> Result<BudgetStatus> {
+        if self.budgets.budget(feature).is_none() {
+            return Ok(BudgetStatus::Within);
+        }
+        let (start, _) = quota_period(now);
+        let spent_usd: f64 = sqlx::query_scalar(
+            r#"
+            SELECT COALESCE(SUM(cost_usd), 0)
+            FROM ai_usage
+            WHERE environment = $1 AND feature = $2 AND created_at >= $3
+            "#,
+        )
+        .bind(&self.environment)
+        .bind(feature.as_str())
+        .bind(start)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(self.budgets.status(feature, spent_usd))
+    }
+
+    /// When the user's quota is used up, the time it resets.
+    ///
+    /// The check runs before a request and usage is recorded after it, so concurrent
+    /// requests can overshoot the quota by their own size.
+    pub async fn quota_exhausted_until(&self, user_id: Uuid, now: DateTime<Utc>) -> Result<Option<DateTime<Utc>>> {
+        if self.quota == AiQuota::default() {
+            return Ok(None);
+        }
+        let (start, end) = quota_period(now);
+        let totals = self.totals(user_id, start).await?;
+        Ok(self.quota.exceeded_by(&totals).then_some(end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feature_round_trip() {
+        for feature in AiFeature::ALL {
+            assert_eq!(AiFeature::parse(feature.as_str()), Some(feature));
+        }
+        assert_eq!(AiFeature::parse("categorization"), None);
+    }
+
+    fn usage(model: &str, input_tokens: u32, output_tokens: u32) -> NewAiUsage<'_> {
+        NewAiUsage {
+            user_id: Uuid::nil(),
+            feature: AiFeature::Chat,
+            provider: "claude",
+            model,
+            input_tokens,
+            output_tokens,
+            cache_read_tokens: 0,
+            cache_write_tokens: 0,
+        }
+    }
+
+    #[test]
+    fn test_cost_by_model_prefix() {
+        let prices = CostModel::default();
+        assert!((prices.cost(&usage("claude-3-haiku-20240307", 1_000_000, 0)) - 0.25).abs() < 1e-9);
+        assert!((prices.cost(&usage("claude-3-sonnet-20240229", 1_000, 1_000)) - 0.018).abs() < 1e-9);
+        assert!((prices.cost(&usage("gpt-4o-mini", 0, 1_000_000)) - 0.6).abs() < 1e-9);
+        assert!((prices.cost(&usage("gpt-4o-2024-08-06", 0, 1_000_000)) - 10.0).abs() < 1e-9);
+        assert!((prices.cost(&usage("llama3", 1_000_000, 0)) - DEFAULT_PRICE.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_cost_of_cache_tokens() {
+        let cached = NewAiUsage {
+            cache_read_tokens: 1_000_000,
+            cache_write_tokens: 1_000_000,
+            ..usage("claude-3-sonnet-20240229", 0, 0)
+        };
+        assert!((CostModel::default().cost(&cached) - (0.3 + 3.75)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_cost_model_overrides() {
+        let prices = CostModel::parse("claude-3-sonnet=1/2, my-model=0.5/0.5").unwrap();
+        assert_eq!(prices.prices("claude-3-sonnet-20240229"), (1.0, 2.0));
+        assert_eq!(prices.prices("my-model-v2"), (0.5, 0.5));
+        assert_eq!(prices.prices("gpt-4o-mini"), (0.15, 0.6));
+        assert!(CostModel::parse("claude").is_err());
+        assert!(CostModel::parse("claude=1").is_err());
+        assert!(CostModel::parse("claude=-1/2").is_err());
+    }
+
+    #[test]
+    fn test_budget_status() {
+        let budgets = AiBudgets::parse("chat=100, receipt_scan=10").unwrap();
+        assert_eq!(budgets.status(AiFeature::Chat, 50.0), BudgetStatus::Within);
+        assert_eq!(
+            budgets.status(AiFeature::Chat, 80.0),
+            BudgetStatus::Warning { spent_usd: 80.0, budget_usd: 100.0 }
+        );
+        assert_eq!(
+            budgets.status(AiFeature::ReceiptScan, 12.5),
+            BudgetStatus::Exceeded { spent_usd: 12.5, budget_usd: 10.0 }
+        );
+        assert_eq!(budgets.status(AiFeature::Assistant, 1_000.0), BudgetStatus::Within);
+
+        let strict = budgets.with_warning_ratio(0.5).unwrap();
+        assert!(matches!(strict.status(AiFeature::Chat, 50.0), BudgetStatus::Warning { .. }));
+        assert!(AiBudgets::default().with_warning_ratio(1.5).is_err());
+        assert!(AiBudgets::parse("categorization=10").is_err());
+        assert!(AiBudgets::parse("chat=0").is_err());
+    }
+
+    #[test]
+    fn test_quota_period_spans_calendar_month() {
+        let now = Utc.with_ymd_and_hms(2024, 12, 15, 8, 30, 0).unwrap();
+        let (start, end) = quota_period(now);
+        assert_eq!(start, Utc.with_ymd_and_hms(2024, 12, 1, 0, 0, 0).unwrap());
+        assert_eq!(end, Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_quota_limits() {
+        let usage = AiUsageTotals {
+            request_count: 3,
+            input_tokens: 800,
+            output_tokens: 150,
+            cache_read_tokens: 40,
+            cache_write_tokens: 10,
+            cost_usd: 0.5,
+        };
+        assert!(!AiQuota::default().exceeded_by(&usage));
+        assert!(AiQuota { monthly_tokens: Some(1_000), monthly_cost_usd: None }.exceeded_by(&usage));
+        assert!(!AiQuota { monthly_tokens: Some(1_001), monthly_cost_usd: Some(0.75) }.exceeded_by(&usage));
+        assert!(AiQuota { monthly_tokens: None, monthly_cost_usd: Some(0.5) }.exceeded_by(&usage));
+    }
+}
@@ -0,0 +1,292 @@
+use crate::model::bank_account::StoredBankAccount;
+use crate::model::net_worth::LIABILITY_ACCOUNT_TYPES;
+use crate::model::transaction::Transaction;
+use crate::model::transaction_category::taxonomy_category;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use tracing::{info, instrument};
+use uuid::Uuid;
+
+/// What an alert rule watches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertRuleKind {
+    /// A transaction whose absolute amount reaches the threshold
+    LargeTransaction,
+    /// An account balance below the threshold
+    LowBalance,
+}
+
+impl AlertRuleKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertRuleKind::LargeTransaction => "large_transaction",
+            AlertRuleKind::LowBalance => "low_balance",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "large_transaction" => Some(AlertRuleKind::LargeTransaction),
+            "low_balance" => Some(AlertRuleKind::LowBalance),
+            _ => None,
+        }
+    }
+}
+
+/// Persisted alert rule; `account_id` and `category` narrow it when set
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct AlertRule {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub kind: String,
+    pub account_id: Option<String>,
+    pub category: Option<String>,
+    pub threshold: f64,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl AlertRule {
+    pub fn kind(&self) -> Option<AlertRuleKind> {
+        AlertRuleKind::parse(&self.kind)
+    }
+
+    /// Whether a newly synced transaction trips this rule; `category` is its effective category
+    pub fn matches_transaction(&self, transaction: &Transaction, category: Option<&str>) -> bool {
+        self.enabled
+            && self.kind() == Some(AlertRuleKind::LargeTransaction)
+            && !matches!(self.account_id.as_deref(), Some(id) if id != transaction.account_id)
+            && !matches!(
+                self.category.as_deref(),
+                Some(wanted) if !category.is_some_and(|c| c.eq_ignore_ascii_case(wanted))
+            )
+            && transaction.amount.abs() >= self.threshold
+    }
+
+    /// The balance that breached this rule, if the account is below the threshold.
+    ///
+    /// Rules without an account only watch asset accounts; a credit card "balance" is debt.
+    pub fn breached_balance(&self, account: &StoredBankAccount) -> Option<f64> {
+        if !self.enabled || self.kind() != Some(AlertRuleKind::LowBalance) {
+            return None;
+        }
+        match self.account_id.as_deref() {
+            Some(id) if id != account.account_id => return None,
+            None if LIABILITY_ACCOUNT_TYPES.contains(&account.account_type.as_str()) => return None,
+            _ => {}
+        }
+        let balance = account.available_balance.or(account.current_balance)?;
+        (balance < self.threshold).then_some(balance)
+    }
+}
+
+/// Category a transaction is alerted under: its stored category, else Plaid's top-level category
+/// mapped onto the taxonomy (`"Food and Drink"` becomes `FOOD_AND_DRINK`)
+pub fn alert_category(stored: Option<&str>, plaid_category: &[String]) -> Option<String> {
+    stored.map(str::to_string).or_else(|| {
+        plaid_category
+            .first()
+            .map(|c| c.trim().to_ascii_uppercase().replace(' ', "_"))
+            .and_then(|c| taxonomy_category(&c))
+            .map(str::to_string)
+    })
+}
+
+/// Fields of a rule being created
+#[derive(Debug, Clone)]
+pub struct NewAlertRule {
+    pub user_id: Uuid,
+    pub kind: AlertRuleKind,
+    pub account_id: Option<String>,
+    pub category: Option<String>,
+    pub threshold: f64,
+}
+
+/// Alert rule repository for database operations
+#[derive(Debug, Clone)]
+pub struct AlertRuleRepository {
+    pool: PgPool,
+}
+
+impl AlertRuleRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    #[instrument(skip(self, rule), fields(user_id = %rule.user_id, kind = %rule.kind.as_str()))]
+    pub async fn create(&self, rule: &NewAlertRule) -> Result<AlertRule> {
+        let created = sqlx::query_as::<_, AlertRule>(
+            r#"
+            INSERT INTO alert_rules (user_id, kind, account_id, category, threshold)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING *
+            "#,
+        )
+        .bind(rule.user_id)
+        .bind(rule.kind.as_str())
+        .bind(&rule.account_id)
+        .bind(&rule.category)
+        .bind(rule.threshold)
+        .fetch_one(&self.pool)
+        .await?;
+
+        info!(rule_id = %created.id, "Created alert rule");
+        Ok(created)
+    }
+
+    /// A user's rules, oldest first
+    #[instrument(skip(self))]
+    pub async fn list_by_user(&self, user_id: Uuid) -> Result<Vec<AlertRule>> {
+        let rules = sqlx::query_as::<_, AlertRule>(
+            "SELECT * FROM alert_rules WHERE user_id = $1 ORDER BY created_at"
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rules)
+    }
+
+    /// A user's enabled rules
+    #[instrument(skip(self))]
+    pub async fn list_enabled_by_user(&self, user_id: Uuid) -> Result<Vec<AlertRule>> {
+        let rules = sqlx::query_as::<_, AlertRule>(
+            "SELECT * FROM alert_rules WHERE user_id = $1 AND enabled ORDER BY created_at"
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rules)
+    }
+
+    /// Delete a user's rule; returns false when the user has no such rule
+    #[instrument(skip(self))]
+    pub async fn delete(&self, user_id: Uuid, rule_id: Uuid) -> Result<bool> {
+        let deleted = sqlx::query("DELETE FROM alert_rules WHERE id = $1 AND user_id = $2")
+            .bind(rule_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+
+        Ok(deleted > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn rule(kind: AlertRuleKind, account_id: Option<&str>, category: Option<&str>, threshold: f64) -> AlertRule {
+        AlertRule {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            kind: kind.as_str().to_string(),
+            account_id: account_id.map(str::to_string),
+            category: category.map(str::to_string),
+            threshold,
+            enabled: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn transaction(account_id: &str, amount: f64) -> Transaction {
+        Transaction {
+            id: Uuid::new_v4(),
+            transaction_id: "txn_1".to_string(),
+            account_id: account_id.to_string(),
+            item_id: "item_1".to_string(),
+            user_id: Uuid::new_v4(),
+            amount,
+            iso_currency_code: Some("USD".to_string()),
+            unofficial_currency_code: None,
+            date: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            datetime: None,
+            authorized_date: None,
+            authorized_datetime: None,
+            name: "Electronics store".to_string(),
+            merchant_name: None,
+            original_description: None,
+            category: vec!["Shops".to_string()],
+            category_id: None,
+            check_number: None,
+            location: None,
+            payment_meta: None,
+            pending: false,
+            pending_transaction_id: None,
+            account_owner: None,
+            transaction_type: "place".to_string(),
+            transaction_code: None,
+            removed_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn account(account_type: &str, available: Option<f64>, current: Option<f64>) -> StoredBankAccount {
+        StoredBankAccount {
+            id: Uuid::new_v4(),
+            account_id: "acc_1".to_string(),
+            item_id: "item_1".to_string(),
+            user_id: Uuid::new_v4(),
+            name: "Checking".to_string(),
+            official_name: None,
+            mask: None,
+            account_type: account_type.to_string(),
+            account_subtype: None,
+            available_balance: available,
+            current_balance: current,
+            credit_limit: None,
+            iso_currency_code: Some("USD".to_string()),
+            unofficial_currency_code: None,
+            institution_id: None,
+            institution_name: None,
+            balances_updated_at: Utc::now(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_large_transaction_rule() {
+        let any = rule(AlertRuleKind::LargeTransaction, None, None, 500.0);
+        assert!(any.matches_transaction(&transaction("acc_1", 750.0), None));
+        assert!(any.matches_transaction(&transaction("acc_1", -500.0), None));
+        assert!(!any.matches_transaction(&transaction("acc_1", 499.99), None));
+
+        let narrowed = rule(AlertRuleKind::LargeTransaction, Some("acc_2"), Some("TRAVEL"), 100.0);
+        assert!(!narrowed.matches_transaction(&transaction("acc_1", 750.0), Some("TRAVEL")));
+        assert!(!narrowed.matches_transaction(&transaction("acc_2", 750.0), Some("FOOD_AND_DRINK")));
+        assert!(narrowed.matches_transaction(&transaction("acc_2", 750.0), Some("travel")));
+
+        let low_balance = rule(AlertRuleKind::LowBalance, None, None, 100.0);
+        assert!(!low_balance.matches_transaction(&transaction("acc_1", 750.0), None));
+    }
+
+    #[test]
+    fn test_low_balance_rule() {
+        let any = rule(AlertRuleKind::LowBalance, None, None, 100.0);
+        assert_eq!(any.breached_balance(&account("depository", Some(40.0), Some(500.0))), Some(40.0));
+        assert_eq!(any.breached_balance(&account("depository", None, Some(99.0))), Some(99.0));
+        assert_eq!(any.breached_balance(&account("depository", Some(100.0), None)), None);
+        assert_eq!(any.breached_balance(&account("credit", Some(10.0), None)), None);
+
+        let credit = rule(AlertRuleKind::LowBalance, Some("acc_1"), None, 100.0);
+        assert_eq!(credit.breached_balance(&account("credit", Some(10.0), None)), Some(10.0));
+    }
+
+    #[test]
+    fn test_alert_category() {
+        assert_eq!(alert_category(Some("TRAVEL"), &["Food and Drink".to_string()]).as_deref(), Some("TRAVEL"));
+        assert_eq!(
+            alert_category(None, &["Food and Drink".to_string(), "Restaurants".to_string()]).as_deref(),
+            Some("FOOD_AND_DRINK")
+        );
+        assert_eq!(alert_category(None, &["Shops".to_string()]), None);
+    }
+}
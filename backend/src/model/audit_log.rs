@@ -0,0 +1,74 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sqlx::types::Json;
+use sqlx::PgPool;
+use tracing::{info, instrument};
+use uuid::Uuid;
+
+/// Recorded user action
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct AuditEntry {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: String,
+    pub metadata: Json<Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Append-only log of security-relevant user actions
+#[derive(Debug, Clone)]
+pub struct AuditLogRepository {
+    pool: PgPool,
+}
+
+impl AuditLogRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Record `action` by `user_id` on the target, e.g. `("item.removed", "plaid_item", item_id)`
+    #[instrument(skip(self, metadata))]
+    pub async fn record(
+        &self,
+        user_id: Uuid,
+        action: &str,
+        target_type: &str,
+        target_id: &str,
+        metadata: Value,
+    ) -> Result<AuditEntry> {
+        let entry = sqlx::query_as::<_, AuditEntry>(
+            r#"
+            INSERT INTO audit_log (user_id, action, target_type, target_id, metadata)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING *
+            "#,
+        )
+        .bind(user_id)
+        .bind(action)
+        .bind(target_type)
+        .bind(target_id)
+        .bind(Json(&metadata))
+        .fetch_one(&self.pool)
+        .await?;
+
+        info!(user_id = %user_id, action = %action, target_type = %target_type, target_id = %target_id, "Audit event recorded");
+        Ok(entry)
+    }
+
+    /// A user's audit entries, newest first
+    #[instrument(skip(self))]
+    pub async fn list_by_user(&self, user_id: Uuid, limit: i64) -> Result<Vec<AuditEntry>> {
+        let entries = sqlx::query_as::<_, AuditEntry>(
+            "SELECT * FROM audit_log WHERE user_id = $1 ORDER BY created_at DESC LIMIT $2"
+        )
+        .bind(user_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(entries)
+    }
+}
@@ -1,3 +1,6 @@
+use crate::model::auth_session::AuthSessionRepository;
+use crate::model::dual_write::{DualWrite, MigrationPhase};
+use crate::settings::Settings;
 use anyhow::{Context, Result};
 use chrono::{DateTime, Duration, Utc};
 use deadpool_redis::Pool;
@@ -30,6 +33,140 @@ pub struct TokenClaims {
     pub email: String,
     /// User Google ID
     pub google_id: String,
+    /// Space-delimited granted scopes (e.g. "accounts:read transactions:read")
+    #[serde(default)]
+    pub scope: String,
+    /// Client type the token was issued to
+    #[serde(default)]
+    pub client_type: ClientType,
+}
+
+impl TokenClaims {
+    /// Scopes granted to this token
+    pub fn scopes(&self) -> Vec<Scope> {
+        self.scope
+            .split_whitespace()
+            .filter_map(Scope::parse)
+            .collect()
+    }
+
+    /// Check whether the token grants the given scope
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.scope.split_whitespace().any(|s| s == scope.as_str())
+    }
+}
+
+/// Fine-grained permission carried in the `scope` claim of access tokens
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Scope {
+    ProfileRead,
+    ProfileWrite,
+    SessionsRead,
+    SessionsWrite,
+    AccountsRead,
+    AccountsWrite,
+    TransactionsRead,
+    TransactionsWrite,
+    BudgetsRead,
+    BudgetsWrite,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 10] = [
+        Scope::ProfileRead,
+        Scope::ProfileWrite,
+        Scope::SessionsRead,
+        Scope::SessionsWrite,
+        Scope::AccountsRead,
+        Scope::AccountsWrite,
+        Scope::TransactionsRead,
+        Scope::TransactionsWrite,
+        Scope::BudgetsRead,
+        Scope::BudgetsWrite,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::ProfileRead => "profile:read",
+            Scope::ProfileWrite => "profile:write",
+            Scope::SessionsRead => "sessions:read",
+            Scope::SessionsWrite => "sessions:write",
+            Scope::AccountsRead => "accounts:read",
+            Scope::AccountsWrite => "accounts:write",
+            Scope::TransactionsRead => "transactions:read",
+            Scope::TransactionsWrite => "transactions:write",
+            Scope::BudgetsRead => "budgets:read",
+            Scope::BudgetsWrite => "budgets:write",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|scope| scope.as_str() == value)
+    }
+
+    /// Join scopes into the space-delimited claim format
+    pub fn join(scopes: &[Scope]) -> String {
+        scopes
+            .iter()
+            .map(|scope| scope.as_str())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Type of client a token is issued to; determines the default granted scopes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClientType {
+    /// First-party web dashboard
+    #[default]
+    Web,
+    /// First-party mobile app
+    Mobile,
+    /// User-generated personal access token
+    PersonalToken,
+    /// Third-party integration authorized by the user
+    ThirdPartyApp,
+}
+
+impl ClientType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ClientType::Web => "web",
+            ClientType::Mobile => "mobile",
+            ClientType::PersonalToken => "personal_token",
+            ClientType::ThirdPartyApp => "third_party_app",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "web" => Some(ClientType::Web),
+            "mobile" => Some(ClientType::Mobile),
+            "personal_token" => Some(ClientType::PersonalToken),
+            "third_party_app" => Some(ClientType::ThirdPartyApp),
+            _ => None,
+        }
+    }
+
+    /// Whether users sign in as this client; tokens for other clients are issued from such a session
+    pub fn is_first_party(&self) -> bool {
+        matches!(self, ClientType::Web | ClientType::Mobile)
+    }
+
+    /// Least-privilege scopes granted to this client type by default
+    pub fn default_scopes(&self) -> Vec<Scope> {
+        match self {
+            ClientType::Web | ClientType::Mobile => Scope::ALL.to_vec(),
+            ClientType::PersonalToken => vec![
+                Scope::ProfileRead,
+                Scope::AccountsRead,
+                Scope::TransactionsRead,
+                Scope::BudgetsRead,
+            ],
+            ClientType::ThirdPartyApp => vec![Scope::AccountsRead, Scope::TransactionsRead],
+        }
+    }
 }
 
 /// JWT token pair (access + refresh)
@@ -39,10 +176,14 @@ pub struct TokenPair {
     pub refresh_token: String,
     pub expires_in: i64,
     pub token_type: String,
+    /// JTI of the refresh token, used as the session key
+    pub refresh_token_jti: String,
+    /// Absolute refresh token expiry (Unix timestamp)
+    pub refresh_token_expires_at: i64,
 }
 
-/// Session information stored in Redis (simplified for Redis-only sessions)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Session of a refresh token, as stored in Redis and in `auth_sessions`
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct SessionInfo {
     pub user_id: Uuid,
     pub google_id: String,
@@ -50,6 +191,34 @@ pub struct SessionInfo {
     pub refresh_token_jti: String,
     pub created_at: DateTime<Utc>,
     pub last_activity: DateTime<Utc>,
+    /// Last time the refresh token was exchanged for a new access token
+    #[serde(default)]
+    pub last_refresh_at: Option<DateTime<Utc>>,
+}
+
+impl SessionInfo {
+    /// Whether the refresh token has gone unused for longer than the inactivity window.
+    /// Sessions that were never refreshed are measured from creation.
+    pub fn is_inactive(&self, now: DateTime<Utc>, inactivity_window: Duration) -> bool {
+        let last_used = self.last_refresh_at.unwrap_or(self.created_at);
+        now - last_used > inactivity_window
+    }
+}
+
+/// Whether two stores hold the same session, to the millisecond Redis and Postgres timestamps share
+fn same_session(a: &Option<SessionInfo>, b: &Option<SessionInfo>) -> bool {
+    let millis = |time: Option<DateTime<Utc>>| time.map(|time| time.timestamp_millis());
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            a.user_id == b.user_id
+                && a.refresh_token_jti == b.refresh_token_jti
+                && a.created_at.timestamp_millis() == b.created_at.timestamp_millis()
+                && a.last_activity.timestamp_millis() == b.last_activity.timestamp_millis()
+                && millis(a.last_refresh_at) == millis(b.last_refresh_at)
+        }
+        (None, None) => true,
+        _ => false,
+    }
 }
 
 /// Configuration for JWT token management
@@ -63,8 +232,10 @@ pub struct JwtConfig {
     pub audience: String,
     /// Access token expiration time in minutes
     pub access_token_expires_minutes: i64,
-    /// Refresh token expiration time in days
+    /// Refresh token expiration time in days (absolute)
     pub refresh_token_expires_days: i64,
+    /// Refresh tokens unused for this many days are revoked even before absolute expiry
+    pub refresh_token_inactivity_days: i64,
 }
 
 impl Default for JwtConfig {
@@ -75,6 +246,7 @@ impl Default for JwtConfig {
             audience: "api".to_string(),
             access_token_expires_minutes: 15, // 15 minutes
             refresh_token_expires_days: 30,   // 30 days
+            refresh_token_inactivity_days: 14, // 14 days
         }
     }
 }
@@ -100,38 +272,47 @@ impl JwtManager {
         }
     }
 
-    /// Create JWT manager from environment variables
+    /// Create JWT manager from `Settings::from_env`
     pub fn from_env() -> Result<Self> {
-        let config = JwtConfig {
-            secret_key: std::env::var("JWT_SECRET_KEY")
-                .context("JWT_SECRET_KEY environment variable not set")?,
-            issuer: std::env::var("JWT_ISSUER")
-                .unwrap_or_else(|_| "auth-service".to_string()),
-            audience: std::env::var("JWT_AUDIENCE")
-                .unwrap_or_else(|_| "api".to_string()),
-            access_token_expires_minutes: std::env::var("JWT_ACCESS_TOKEN_EXPIRES_MINUTES")
-                .unwrap_or_else(|_| "15".to_string())
-                .parse()
-                .unwrap_or(15),
-            refresh_token_expires_days: std::env::var("JWT_REFRESH_TOKEN_EXPIRES_DAYS")
-                .unwrap_or_else(|_| "30".to_string())
-                .parse()
-                .unwrap_or(30),
-        };
-
-        Ok(Self::new(config))
+        Ok(Self::new(Settings::from_env()?.jwt))
     }
 
-    /// Generate a new token pair (access + refresh tokens)
-    #[instrument(skip(self), fields(user_id = %user_id, email = %email))]
+    /// Generate a new token pair (access + refresh tokens) for the first-party web client
     pub fn generate_token_pair(
         &self,
         user_id: Uuid,
         email: &str,
         google_id: &str,
+    ) -> Result<TokenPair> {
+        self.generate_token_pair_for_client(user_id, email, google_id, ClientType::Web)
+    }
+
+    /// Generate a new token pair scoped to the given client type
+    #[instrument(skip(self), fields(user_id = %user_id, email = %email, client_type = %client_type.as_str()))]
+    pub fn generate_token_pair_for_client(
+        &self,
+        user_id: Uuid,
+        email: &str,
+        google_id: &str,
+        client_type: ClientType,
+    ) -> Result<TokenPair> {
+        self.generate_scoped_token_pair(user_id, email, google_id, client_type, &client_type.default_scopes())
+    }
+
+    /// Generate a new token pair with an explicit scope set
+    #[instrument(skip(self, scopes), fields(user_id = %user_id, email = %email, client_type = %client_type.as_str()))]
+    pub fn generate_scoped_token_pair(
+        &self,
+        user_id: Uuid,
+        email: &str,
+        google_id: &str,
+        client_type: ClientType,
+        scopes: &[Scope],
     ) -> Result<TokenPair> {
         debug!("Generating JWT token pair for user");
 
+        let scope = Scope::join(scopes);
+
         let now = Utc::now();
         let access_token_exp = now + Duration::minutes(self.config.access_token_expires_minutes);
         let refresh_token_exp = now + Duration::days(self.config.refresh_token_expires_days);
@@ -152,6 +333,8 @@ impl JwtManager {
             token_type: "access".to_string(),
             email: email.to_string(),
             google_id: google_id.to_string(),
+            scope: scope.clone(),
+            client_type,
         };
 
         // Create refresh token claims
@@ -162,10 +345,12 @@ impl JwtManager {
             nbf: now.timestamp(),
             iss: self.config.issuer.clone(),
             aud: self.config.audience.clone(),
-            jti: refresh_jti,
+            jti: refresh_jti.clone(),
             token_type: "refresh".to_string(),
             email: email.to_string(),
             google_id: google_id.to_string(),
+            scope,
+            client_type,
         };
 
         // Encode tokens
@@ -180,6 +365,8 @@ impl JwtManager {
             refresh_token,
             expires_in: self.config.access_token_expires_minutes * 60, // Convert to seconds
             token_type: "Bearer".to_string(),
+            refresh_token_jti: refresh_jti,
+            refresh_token_expires_at: refresh_token_exp.timestamp(),
         };
 
         info!(
@@ -224,6 +411,18 @@ impl JwtManager {
         Ok(claims)
     }
 
+    /// Validate a token and require that it is an access token; scopes are checked by the caller
+    #[instrument(skip(self, token))]
+    pub fn validate_access_token(&self, token: &str) -> Result<TokenClaims> {
+        let claims = self.validate_token(token)?;
+
+        if claims.token_type != "access" {
+            return Err(anyhow::anyhow!("Expected access token, got '{}'", claims.token_type));
+        }
+
+        Ok(claims)
+    }
+
     /// Extract token from Authorization header
     pub fn extract_token_from_header(auth_header: &str) -> Result<&str> {
         if !auth_header.starts_with("Bearer ") {
@@ -241,17 +440,104 @@ impl JwtManager {
         Ok(token)
     }
 
+    /// Inactivity window after which unused refresh tokens are revoked
+    pub fn refresh_inactivity_window(&self) -> Duration {
+        Duration::days(self.config.refresh_token_inactivity_days)
+    }
+
     /// Get the current configuration
     pub fn config(&self) -> &JwtConfig {
         &self.config
     }
 }
 
+/// Secondary-region Redis used for session replication and failover lookups
+#[derive(Clone)]
+pub struct SessionReplica {
+    pub region: String,
+    pool: Pool,
+}
+
+/// Region name of the primary Redis when settings don't name one
+pub const DEFAULT_SESSION_REGION: &str = "us-east-1";
+
 /// Session manager for Redis-backed session storage using deadpool_redis
 #[derive(Clone)]
 pub struct SessionManager {
     redis_pool: Pool,
     session_ttl_seconds: u64,
+    region: String,
+    replica: Option<SessionReplica>,
+    /// Postgres store sessions are moving to, and the phase of the move
+    migration: Option<(AuthSessionRepository, DualWrite)>,
+}
+
+async fn write_session(pool: &Pool, session: &SessionInfo, ttl_seconds: u64) -> Result<()> {
+    let mut conn = pool.get().await
+        .context("Failed to get Redis connection from pool")?;
+
+    let session_key = format!("session:{}", session.refresh_token_jti);
+    let session_data = serde_json::to_string(session)
+        .context("Failed to serialize session data")?;
+
+    // Store session with TTL
+    conn.set_ex::<_, _, ()>(&session_key, session_data, ttl_seconds).await
+        .context("Failed to store session in Redis")?;
+
+    // Also create a user -> session mapping for easy cleanup
+    let user_sessions_key = format!("user_sessions:{}", session.user_id);
+    conn.sadd::<_, _, ()>(&user_sessions_key, &session.refresh_token_jti).await
+        .context("Failed to add session to user sessions set")?;
+    conn.expire::<_, ()>(&user_sessions_key, ttl_seconds as i64).await
+        .context("Failed to set TTL on user sessions set")?;
+
+    Ok(())
+}
+
+/// Reads a session unless it was revoked; the tombstone wins over a session written back by a
+/// replication that landed after the logout
+async fn read_session(pool: &Pool, refresh_token_jti: &str) -> Result<Option<SessionInfo>> {
+    let mut conn = pool.get().await
+        .context("Failed to get Redis connection from pool")?;
+
+    let revoked: bool = conn.exists(revoked_key(refresh_token_jti)).await
+        .context("Failed to check session revocation in Redis")?;
+    if revoked {
+        return Ok(None);
+    }
+
+    let session_key = format!("session:{}", refresh_token_jti);
+    let session_data: Option<String> = conn.get(&session_key).await
+        .context("Failed to retrieve session from Redis")?;
+
+    session_data
+        .map(|data| serde_json::from_str(&data).context("Failed to deserialize session data"))
+        .transpose()
+}
+
+fn revoked_key(refresh_token_jti: &str) -> String {
+    format!("revoked_session:{}", refresh_token_jti)
+}
+
+/// Deletes a session and leaves a tombstone that outlives any copy of it still in flight
+async fn delete_session(pool: &Pool, user_id: Option<Uuid>, refresh_token_jti: &str, ttl_seconds: u64) -> Result<u32> {
+    let mut conn = pool.get().await
+        .context("Failed to get Redis connection from pool")?;
+
+    conn.set_ex::<_, _, ()>(revoked_key(refresh_token_jti), 1, ttl_seconds).await
+        .context("Failed to record session revocation in Redis")?;
+
+    if let Some(user_id) = user_id {
+        let user_sessions_key = format!("user_sessions:{}", user_id);
+        conn.srem::<_, _, ()>(&user_sessions_key, refresh_token_jti).await
+            .context("Failed to remove session from user sessions set")?;
+    }
+
+    let session_key = format!("session:{}", refresh_token_jti);
+    let removed: u32 = conn.del(&session_key).await
+        .context("Failed to delete session from Redis")?;
+
+    Ok(removed)
 }
 
 impl SessionManager {
@@ -264,76 +550,182 @@ impl SessionManager {
         Ok(Self {
             redis_pool,
             session_ttl_seconds: session_ttl_hours * 3600,
+            region: DEFAULT_SESSION_REGION.to_string(),
+            replica: None,
+            migration: None,
         })
     }
 
-    /// Create session manager from environment variables
-    pub fn from_env() -> Result<Self> {
-        let redis_url = std::env::var("REDIS_URL")
-            .unwrap_or_else(|_| "redis://localhost:6379".to_string());
-        let session_ttl_hours = std::env::var("SESSION_TTL_HOURS")
-            .unwrap_or_else(|_| "24".to_string())
-            .parse()
-            .unwrap_or(24);
+    /// Connection pool of the primary Redis
+    pub fn redis_pool(&self) -> &Pool {
+        &self.redis_pool
+    }
 
-        Self::new(&redis_url, session_ttl_hours)
+    /// Set the region name of the primary Redis
+    pub fn with_region<R: Into<String>>(mut self, region: R) -> Self {
+        self.region = region.into();
+        self
     }
 
-    /// Store session information in Redis
-    #[instrument(skip(self), fields(user_id = %session.user_id, refresh_token_jti = %session.refresh_token_jti))]
-    pub async fn store_session(&self, session: &SessionInfo) -> Result<()> {
-        debug!("Storing session in Redis");
+    /// Replicate sessions to a secondary-region Redis.
+    ///
+    /// Writes are replicated asynchronously; lookups fall back to the replica only when the
+    /// primary is unreachable, so a regional failover does not log every user out. Revocations
+    /// are written to both regions before they succeed.
+    pub fn with_replica<R: Into<String>>(mut self, redis_url: &str, region: R) -> Result<Self> {
+        let cfg = deadpool_redis::Config::from_url(redis_url);
+        let pool = cfg.create_pool(Some(deadpool_redis::Runtime::Tokio1))
+            .context("Failed to create secondary Redis connection pool")?;
+
+        self.replica = Some(SessionReplica {
+            region: region.into(),
+            pool,
+        });
+        Ok(self)
+    }
 
-        let mut conn = self.redis_pool.get().await
-            .context("Failed to get Redis connection from pool")?;
+    /// Move sessions to Postgres in the phases `migration`'s flags select. Redis stays the only store
+    /// until they are turned on.
+    pub fn with_migration(mut self, store: AuthSessionRepository, migration: DualWrite) -> Self {
+        self.migration = Some((store, migration));
+        self
+    }
 
-        let session_key = format!("session:{}", session.refresh_token_jti);
-        let session_data = serde_json::to_string(session)
-            .context("Failed to serialize session data")?;
+    fn phase(&self) -> MigrationPhase {
+        self.migration
+            .as_ref()
+            .map(|(_, migration)| migration.phase())
+            .unwrap_or_default()
+    }
 
-        // Store session with TTL
-        conn.set_ex::<_, _, ()>(&session_key, session_data, self.session_ttl_seconds).await
-            .context("Failed to store session in Redis")?;
+    /// Create session manager from settings. Sessions must outlive the refresh inactivity window, which is
+    /// enforced from session timestamps, so the TTL defaults to that window.
+    pub fn from_settings(settings: &Settings) -> Result<Self> {
+        let default_ttl_hours = settings.jwt.refresh_token_inactivity_days as u64 * 24;
+        let ttl_hours = settings.sessions.ttl_hours.unwrap_or(default_ttl_hours);
+        let manager = Self::new(&settings.redis_url, ttl_hours)?.with_region(settings.sessions.region.clone());
+        match &settings.sessions.secondary {
+            Some((secondary_url, secondary_region)) => {
+                info!(secondary_region = %secondary_region, "Enabling multi-region session replication");
+                manager
+                    .with_replica(secondary_url, secondary_region.clone())
+                    .context("Failed to create secondary session store")
+            }
+            None => Ok(manager),
+        }
+    }
 
-        // Also create a user -> session mapping for easy cleanup
-        let user_sessions_key = format!("user_sessions:{}", session.user_id);
-        conn.sadd::<_, _, ()>(&user_sessions_key, &session.refresh_token_jti).await
-            .context("Failed to add session to user sessions set")?;
-        conn.expire::<_, ()>(&user_sessions_key, self.session_ttl_seconds as i64).await
-            .context("Failed to set TTL on user sessions set")?;
+    /// Create session manager from `Settings::from_env`
+    pub fn from_env() -> Result<Self> {
+        Self::from_settings(&Settings::from_env()?)
+    }
+
+    /// Store session information in the stores the migration phase writes to
+    #[instrument(skip(self), fields(user_id = %session.user_id, refresh_token_jti = %session.refresh_token_jti))]
+    pub async fn store_session(&self, session: &SessionInfo) -> Result<()> {
+        let Some((store, migration)) = &self.migration else {
+            return self.store_redis_session(session).await;
+        };
+        migration
+            .write(
+                self.store_redis_session(session),
+                store.store(session, self.session_ttl_seconds),
+            )
+            .await
+    }
+
+    async fn store_redis_session(&self, session: &SessionInfo) -> Result<()> {
+        debug!("Storing session in Redis");
+
+        write_session(&self.redis_pool, session, self.session_ttl_seconds).await?;
 
         info!(
-            session_key = %session_key,
+            refresh_token_jti = %session.refresh_token_jti,
+            region = %self.region,
             ttl_seconds = self.session_ttl_seconds,
             "Successfully stored session"
         );
 
+        self.replicate_session(session);
+
         Ok(())
     }
 
-    /// Retrieve session information from Redis
+    /// Asynchronously copy a session to the secondary region
+    fn replicate_session(&self, session: &SessionInfo) {
+        if let Some(replica) = self.replica.clone() {
+            let session = session.clone();
+            let ttl_seconds = self.session_ttl_seconds;
+            tokio::spawn(async move {
+                if let Err(e) = write_session(&replica.pool, &session, ttl_seconds).await {
+                    warn!(
+                        region = %replica.region,
+                        refresh_token_jti = %session.refresh_token_jti,
+                        error = %e,
+                        "Failed to replicate session to secondary region"
+                    );
+                } else {
+                    debug!(region = %replica.region, "Replicated session to secondary region");
+                }
+            });
+        }
+    }
+
+    /// Retrieve session information from the store the migration phase reads from
     #[instrument(skip(self))]
     pub async fn get_session(&self, refresh_token_jti: &str) -> Result<Option<SessionInfo>> {
-        debug!(refresh_token_jti = %refresh_token_jti, "Retrieving session from Redis");
+        let Some((store, migration)) = &self.migration else {
+            return self.get_redis_session(refresh_token_jti).await;
+        };
+        migration
+            .read_with(
+                self.get_redis_session(refresh_token_jti),
+                store.get(refresh_token_jti),
+                same_session,
+            )
+            .await
+    }
 
-        let mut conn = self.redis_pool.get().await
-            .context("Failed to get Redis connection from pool")?;
+    /// Retrieve session information from Redis, falling back to the secondary region while the
+    /// primary is unreachable. A session missing from the primary is never looked up in the
+    /// replica, which may still hold it after a logout.
+    async fn get_redis_session(&self, refresh_token_jti: &str) -> Result<Option<SessionInfo>> {
+        debug!(refresh_token_jti = %refresh_token_jti, "Retrieving session from Redis");
 
-        let session_key = format!("session:{}", refresh_token_jti);
-        let session_data: Option<String> = conn.get(&session_key).await
-            .context("Failed to retrieve session from Redis")?;
+        let primary_error = match read_session(&self.redis_pool, refresh_token_jti).await {
+            Ok(session) => {
+                match &session {
+                    Some(_) => debug!("Successfully retrieved session from Redis"),
+                    None => debug!("No session found in Redis"),
+                }
+                return Ok(session);
+            }
+            Err(e) => e,
+        };
 
-        match session_data {
-            Some(data) => {
-                let session: SessionInfo = serde_json::from_str(&data)
-                    .context("Failed to deserialize session data")?;
+        let Some(replica) = &self.replica else {
+            return Err(primary_error);
+        };
+        warn!(
+            region = %self.region,
+            error = %primary_error,
+            "Primary session store unavailable, falling back to secondary region"
+        );
 
-                debug!("Successfully retrieved session from Redis");
-                Ok(Some(session))
+        match read_session(&replica.pool, refresh_token_jti).await {
+            Ok(session) => {
+                if session.is_some() {
+                    info!(
+                        region = %replica.region,
+                        refresh_token_jti = %refresh_token_jti,
+                        "Session served from secondary region"
+                    );
+                }
+                Ok(session)
             }
-            None => {
-                debug!("No session found in Redis");
-                Ok(None)
+            Err(replica_error) => {
+                error!(region = %replica.region, error = %replica_error, "Secondary session store lookup failed");
+                Err(primary_error)
             }
         }
     }
@@ -354,26 +746,44 @@ impl SessionManager {
         Ok(())
     }
 
-    /// Invalidate a specific session
+    /// Record a refresh token exchange, sliding the session TTL forward
+    #[instrument(skip(self))]
+    pub async fn record_refresh(&self, refresh_token_jti: &str) -> Result<Option<SessionInfo>> {
+        debug!(refresh_token_jti = %refresh_token_jti, "Recording session refresh");
+
+        match self.get_session(refresh_token_jti).await? {
+            Some(mut session) => {
+                let now = Utc::now();
+                session.last_refresh_at = Some(now);
+                session.last_activity = now;
+                self.store_session(&session).await?;
+                Ok(Some(session))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Invalidate a specific session in every region
     #[instrument(skip(self))]
     pub async fn invalidate_session(&self, refresh_token_jti: &str) -> Result<()> {
         debug!(refresh_token_jti = %refresh_token_jti, "Invalidating session");
 
-        let mut conn = self.redis_pool.get().await
-            .context("Failed to get Redis connection from pool")?;
-
         // Get session to find user ID
-        if let Some(session) = self.get_session(refresh_token_jti).await? {
-            // Remove from user sessions set
-            let user_sessions_key = format!("user_sessions:{}", session.user_id);
-            conn.srem::<_, _, ()>(&user_sessions_key, refresh_token_jti).await
-                .context("Failed to remove session from user sessions set")?;
-        }
-
-        // Remove session
-        let session_key = format!("session:{}", refresh_token_jti);
-        let removed: u32 = conn.del(&session_key).await
-            .context("Failed to delete session from Redis")?;
+        let session = self.get_session(refresh_token_jti).await?;
+        let user_id = session.as_ref().map(|s| s.user_id);
+
+        // Like the secondary region, the store not read from must not keep a live copy
+        let removed = match &self.migration {
+            Some((store, migration)) => {
+                migration
+                    .write_both(
+                        self.invalidate_redis_session(user_id, refresh_token_jti),
+                        store.revoke(refresh_token_jti, session.as_ref(), self.session_ttl_seconds),
+                    )
+                    .await?
+            }
+            None => self.invalidate_redis_session(user_id, refresh_token_jti).await?,
+        };
 
         if removed > 0 {
             info!(refresh_token_jti = %refresh_token_jti, "Successfully invalidated session");
@@ -384,19 +794,63 @@ impl SessionManager {
         Ok(())
     }
 
+    /// Delete a session from Redis in every region
+    async fn invalidate_redis_session(&self, user_id: Option<Uuid>, refresh_token_jti: &str) -> Result<u32> {
+        let removed = delete_session(&self.redis_pool, user_id, refresh_token_jti, self.session_ttl_seconds).await?;
+
+        // Revocations are replicated synchronously so a failover cannot resurrect the session; the
+        // logout fails rather than leave a live copy in the secondary region
+        if let Some(replica) = &self.replica {
+            delete_session(&replica.pool, user_id, refresh_token_jti, self.session_ttl_seconds)
+                .await
+                .with_context(|| format!("Failed to invalidate session in secondary region {}", replica.region))?;
+        }
+
+        Ok(removed)
+    }
+
     /// Invalidate all sessions for a user (useful for logout all devices)
     #[instrument(skip(self))]
     pub async fn invalidate_all_user_sessions(&self, user_id: Uuid) -> Result<u32> {
         debug!(user_id = %user_id, "Invalidating all user sessions");
 
-        let mut conn = self.redis_pool.get().await
-            .context("Failed to get Redis connection from pool")?;
-
+        let phase = self.phase();
         let user_sessions_key = format!("user_sessions:{}", user_id);
-        let session_jtis: Vec<String> = conn.smembers(&user_sessions_key).await
-            .context("Failed to get user sessions from Redis")?;
+        let mut session_jtis: Vec<String> = Vec::new();
+        let mut conn = None;
+        let mut replica_conn = None;
+        if phase.writes_old() {
+            let mut primary = self.redis_pool.get().await
+                .context("Failed to get Redis connection from pool")?;
+            session_jtis = primary.smembers(&user_sessions_key).await
+                .context("Failed to get user sessions from Redis")?;
+            conn = Some(primary);
+
+            // Sessions replicated after the primary lost them are only listed in the secondary region
+            if let Some(replica) = &self.replica {
+                let mut secondary = replica.pool.get().await
+                    .context("Secondary session store unavailable during logout all")?;
+                let replica_jtis: Vec<String> = secondary.smembers(&user_sessions_key).await
+                    .context("Failed to get user sessions from secondary region")?;
+                for jti in replica_jtis {
+                    if !session_jtis.contains(&jti) {
+                        session_jtis.push(jti);
+                    }
+                }
+                replica_conn = Some(secondary);
+            }
+        }
+        // Sessions written while Redis missed them are only listed in Postgres
+        if let Some((store, _)) = self.migration.as_ref().filter(|_| phase.writes_new()) {
+            for jti in store.user_session_jtis(user_id).await? {
+                if !session_jtis.contains(&jti) {
+                    session_jtis.push(jti);
+                }
+            }
+        }
 
         let mut invalidated_count = 0;
+        let mut failed_count = 0;
 
         for jti in session_jtis {
             if let Err(e) = self.invalidate_session(&jti).await {
@@ -406,14 +860,30 @@ impl SessionManager {
                     error = %e,
                     "Failed to invalidate individual session"
                 );
+                failed_count += 1;
             } else {
                 invalidated_count += 1;
             }
         }
 
+        // The sets are kept so a retry finds the sessions still alive
+        if failed_count > 0 {
+            return Err(anyhow::anyhow!(
+                "Failed to invalidate {} of the user's sessions",
+                failed_count
+            ));
+        }
+
         // Clean up user sessions set
-        conn.del::<_, ()>(&user_sessions_key).await
-            .context("Failed to delete user sessions set")?;
+        if let Some(conn) = &mut conn {
+            conn.del::<_, ()>(&user_sessions_key).await
+                .context("Failed to delete user sessions set")?;
+        }
+
+        if let Some(replica_conn) = &mut replica_conn {
+            replica_conn.del::<_, ()>(&user_sessions_key).await
+                .context("Failed to delete user sessions set in secondary region")?;
+        }
 
         info!(
             user_id = %user_id,
@@ -427,6 +897,15 @@ impl SessionManager {
     /// Get active session count for a user
     #[instrument(skip(self))]
     pub async fn get_user_session_count(&self, user_id: Uuid) -> Result<u32> {
+        let Some((store, migration)) = &self.migration else {
+            return self.redis_session_count(user_id).await;
+        };
+        migration
+            .read(self.redis_session_count(user_id), store.count(user_id))
+            .await
+    }
+
+    async fn redis_session_count(&self, user_id: Uuid) -> Result<u32> {
         debug!(user_id = %user_id, "Getting user session count");
 
         let mut conn = self.redis_pool.get().await
@@ -445,6 +924,7 @@ impl SessionManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::Timelike;
 
     #[test]
     fn test_jwt_config_default() {
@@ -453,6 +933,89 @@ mod tests {
         assert_eq!(config.audience, "api");
         assert_eq!(config.access_token_expires_minutes, 15);
         assert_eq!(config.refresh_token_expires_days, 30);
+        assert_eq!(config.refresh_token_inactivity_days, 14);
+    }
+
+    #[test]
+    fn test_session_inactivity() {
+        let now = Utc::now();
+        let mut session = SessionInfo {
+            user_id: Uuid::new_v4(),
+            google_id: "google_123".to_string(),
+            email: "test@example.com".to_string(),
+            refresh_token_jti: Uuid::new_v4().to_string(),
+            created_at: now - Duration::days(20),
+            last_activity: now - Duration::days(20),
+            last_refresh_at: None,
+        };
+
+        // Never refreshed: measured from creation
+        assert!(session.is_inactive(now, Duration::days(14)));
+
+        session.last_refresh_at = Some(now - Duration::days(3));
+        assert!(!session.is_inactive(now, Duration::days(14)));
+
+        session.last_refresh_at = Some(now - Duration::days(15));
+        assert!(session.is_inactive(now, Duration::days(14)));
+    }
+
+    #[test]
+    fn test_same_session_across_stores() {
+        let now = Utc::now();
+        let session = SessionInfo {
+            user_id: Uuid::new_v4(),
+            google_id: "google_123".to_string(),
+            email: "test@example.com".to_string(),
+            refresh_token_jti: Uuid::new_v4().to_string(),
+            created_at: now,
+            last_activity: now,
+            last_refresh_at: Some(now),
+        };
+
+        // Postgres keeps microseconds of the nanoseconds Redis stores
+        let mut stored = session.clone();
+        stored.created_at = now.with_nanosecond(now.nanosecond() / 1000 * 1000).unwrap();
+        assert!(same_session(&Some(session.clone()), &Some(stored.clone())));
+
+        stored.last_refresh_at = None;
+        assert!(!same_session(&Some(session.clone()), &Some(stored)));
+        assert!(!same_session(&Some(session), &None));
+        assert!(same_session(&None, &None));
+    }
+
+    #[tokio::test]
+    #[ignore] // Needs Redis at REDIS_URL
+    async fn test_logout_survives_late_replication() {
+        let url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string());
+        let replica_url = format!("{}/15", url.trim_end_matches('/'));
+        let manager = SessionManager::new(&format!("{}/14", url.trim_end_matches('/')), 1)
+            .unwrap()
+            .with_replica(&replica_url, "us-west-2")
+            .unwrap();
+        let now = Utc::now();
+        let session = SessionInfo {
+            user_id: Uuid::new_v4(),
+            google_id: "google_123".to_string(),
+            email: "test@example.com".to_string(),
+            refresh_token_jti: Uuid::new_v4().to_string(),
+            created_at: now,
+            last_activity: now,
+            last_refresh_at: None,
+        };
+        let jti = session.refresh_token_jti.clone();
+
+        write_session(manager.redis_pool(), &session, 3600).await.unwrap();
+        manager.invalidate_session(&jti).await.unwrap();
+
+        // A refresh's replication lands in the secondary region after the logout
+        let replica = manager.replica.clone().unwrap();
+        write_session(&replica.pool, &session, 3600).await.unwrap();
+
+        assert!(manager.get_session(&jti).await.unwrap().is_none());
+        assert!(manager.record_refresh(&jti).await.unwrap().is_none());
+        // Nor does the secondary region serve it once it takes over
+        let failover = SessionManager::new(&replica_url, 1).unwrap();
+        assert!(failover.get_session(&jti).await.unwrap().is_none());
     }
 
     #[tokio::test]
@@ -475,6 +1038,7 @@ mod tests {
         assert!(!token_pair.access_token.is_empty());
         assert!(!token_pair.refresh_token.is_empty());
         assert_eq!(token_pair.token_type, "Bearer");
+        assert!(token_pair.refresh_token_expires_at > Utc::now().timestamp());
 
         // Validate access token
         let access_claims = jwt_manager
@@ -495,6 +1059,49 @@ mod tests {
         assert_eq!(refresh_claims.email, email);
         assert_eq!(refresh_claims.google_id, google_id);
         assert_eq!(refresh_claims.token_type, "refresh");
+        assert_eq!(refresh_claims.jti, token_pair.refresh_token_jti);
+    }
+
+    #[test]
+    fn test_client_type_default_scopes() {
+        let jwt_manager = JwtManager::new(JwtConfig::default());
+        let user_id = Uuid::new_v4();
+
+        let web = jwt_manager
+            .generate_token_pair_for_client(user_id, "test@example.com", "google_123", ClientType::Web)
+            .unwrap();
+        let web_claims = jwt_manager.validate_token(&web.access_token).unwrap();
+        assert!(web_claims.has_scope(Scope::BudgetsWrite));
+        assert_eq!(web_claims.client_type, ClientType::Web);
+
+        let integration = jwt_manager
+            .generate_token_pair_for_client(user_id, "test@example.com", "google_123", ClientType::ThirdPartyApp)
+            .unwrap();
+        let claims = jwt_manager.validate_token(&integration.access_token).unwrap();
+        assert_eq!(claims.scopes(), vec![Scope::AccountsRead, Scope::TransactionsRead]);
+        assert!(!claims.has_scope(Scope::AccountsWrite));
+    }
+
+    #[test]
+    fn test_validate_access_token() {
+        let jwt_manager = JwtManager::new(JwtConfig::default());
+        let token_pair = jwt_manager
+            .generate_token_pair_for_client(Uuid::new_v4(), "test@example.com", "google_123", ClientType::PersonalToken)
+            .unwrap();
+
+        let claims = jwt_manager.validate_access_token(&token_pair.access_token).unwrap();
+        assert!(claims.has_scope(Scope::TransactionsRead));
+        assert!(!claims.has_scope(Scope::BudgetsWrite));
+        // Refresh tokens are never accepted in place of access tokens
+        assert!(jwt_manager.validate_access_token(&token_pair.refresh_token).is_err());
+    }
+
+    #[test]
+    fn test_scope_parse_round_trip() {
+        for scope in Scope::ALL {
+            assert_eq!(Scope::parse(scope.as_str()), Some(scope));
+        }
+        assert_eq!(Scope::parse("admin:everything"), None);
     }
 
     #[test]
@@ -0,0 +1,153 @@
+use crate::model::auth::SessionInfo;
+use anyhow::Result;
+use sqlx::PgPool;
+use tracing::{debug, instrument};
+use uuid::Uuid;
+
+const SESSION_COLUMNS: &str =
+    "user_id, google_id, email, refresh_token_jti, created_at, last_activity, last_refresh_at";
+
+/// Sessions in Postgres, the store `SessionManager` moves to from Redis (see `SESSION_STORE_MIGRATION`).
+/// Like the Redis keys, a session expires `ttl_seconds` after it was last written; a revoked session keeps
+/// its row until then as the tombstone that stops a racing write from reviving it.
+#[derive(Debug, Clone)]
+pub struct AuthSessionRepository {
+    pool: PgPool,
+}
+
+impl AuthSessionRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Insert or update a session, unless it was revoked
+    #[instrument(skip(self, session), fields(user_id = %session.user_id))]
+    pub async fn store(&self, session: &SessionInfo, ttl_seconds: u64) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO auth_sessions (
+                refresh_token_jti, user_id, google_id, email, created_at, last_activity, last_refresh_at, expires_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, NOW() + make_interval(secs => $8))
+            ON CONFLICT (refresh_token_jti) DO UPDATE SET
+                last_activity = EXCLUDED.last_activity,
+                last_refresh_at = EXCLUDED.last_refresh_at,
+                expires_at = EXCLUDED.expires_at
+            WHERE auth_sessions.revoked_at IS NULL
+            "#,
+        )
+        .bind(&session.refresh_token_jti)
+        .bind(session.user_id)
+        .bind(&session.google_id)
+        .bind(&session.email)
+        .bind(session.created_at)
+        .bind(session.last_activity)
+        .bind(session.last_refresh_at)
+        .bind(ttl_seconds as f64)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// A live session; `None` once it was revoked or expired
+    #[instrument(skip(self))]
+    pub async fn get(&self, refresh_token_jti: &str) -> Result<Option<SessionInfo>> {
+        let session = sqlx::query_as::<_, SessionInfo>(&format!(
+            "SELECT {} FROM auth_sessions WHERE refresh_token_jti = $1 AND revoked_at IS NULL AND expires_at > NOW()",
+            SESSION_COLUMNS
+        ))
+        .bind(refresh_token_jti)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(session)
+    }
+
+    /// Revoke a session; returns how many live sessions were revoked. With `session` given, a tombstone is
+    /// written even if the session never reached this store, so a copy written later stays revoked.
+    #[instrument(skip(self, session))]
+    pub async fn revoke(
+        &self,
+        refresh_token_jti: &str,
+        session: Option<&SessionInfo>,
+        ttl_seconds: u64,
+    ) -> Result<u32> {
+        let Some(session) = session else {
+            let result = sqlx::query(
+                "UPDATE auth_sessions SET revoked_at = NOW() WHERE refresh_token_jti = $1 AND revoked_at IS NULL",
+            )
+            .bind(refresh_token_jti)
+            .execute(&self.pool)
+            .await?;
+            return Ok(result.rows_affected() as u32);
+        };
+
+        let revoked: bool = sqlx::query_scalar(
+            r#"
+            INSERT INTO auth_sessions (
+                refresh_token_jti, user_id, google_id, email, created_at, last_activity, last_refresh_at, expires_at,
+                revoked_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, NOW() + make_interval(secs => $8), NOW())
+            ON CONFLICT (refresh_token_jti) DO UPDATE SET revoked_at = COALESCE(auth_sessions.revoked_at, NOW())
+            RETURNING revoked_at = NOW()
+            "#,
+        )
+        .bind(refresh_token_jti)
+        .bind(session.user_id)
+        .bind(&session.google_id)
+        .bind(&session.email)
+        .bind(session.created_at)
+        .bind(session.last_activity)
+        .bind(session.last_refresh_at)
+        .bind(ttl_seconds as f64)
+        .fetch_one(&self.pool)
+        .await?;
+
+        debug!(revoked, "Revoked session in Postgres");
+        Ok(revoked as u32)
+    }
+
+    /// Refresh token IDs of the user's live sessions
+    #[instrument(skip(self))]
+    pub async fn user_session_jtis(&self, user_id: Uuid) -> Result<Vec<String>> {
+        let jtis = sqlx::query_scalar(
+            r#"
+            SELECT refresh_token_jti FROM auth_sessions
+            WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > NOW()
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(jtis)
+    }
+
+    /// Number of the user's live sessions
+    #[instrument(skip(self))]
+    pub async fn count(&self, user_id: Uuid) -> Result<u32> {
+        let count: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM auth_sessions
+            WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > NOW()
+            "#,
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count as u32)
+    }
+
+    /// Delete expired sessions and tombstones
+    #[instrument(skip(self))]
+    pub async fn delete_expired(&self) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM auth_sessions WHERE expires_at <= NOW()")
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,118 @@
+use crate::adapter::plaid::BankAccount;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use deadpool_redis::Pool;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::{debug, instrument};
+use uuid::Uuid;
+
+/// Refreshed balances are served for five minutes by default
+pub const DEFAULT_TTL_SECONDS: u64 = 5 * 60;
+/// A user may start a refresh once a minute by default
+pub const DEFAULT_MIN_REFRESH_INTERVAL_SECONDS: u64 = 60;
+
+/// Balances from the last real-time refresh of a user's items
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedBalances {
+    pub accounts: Vec<BankAccount>,
+    pub refreshed_at: DateTime<Utc>,
+}
+
+/// Outcome of asking to start a Plaid balance refresh
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefreshPermit {
+    Granted,
+    /// The user refreshed recently; retry after this long
+    Throttled(Duration),
+}
+
+/// Redis cache of refreshed balances plus a per-user refresh throttle
+#[derive(Clone)]
+pub struct BalanceCache {
+    redis_pool: Pool,
+    ttl_seconds: u64,
+    min_refresh_interval_seconds: u64,
+}
+
+fn balances_key(user_id: Uuid) -> String {
+    format!("balances:{}", user_id)
+}
+
+fn refresh_lock_key(user_id: Uuid) -> String {
+    format!("balance_refresh:{}", user_id)
+}
+
+impl BalanceCache {
+    pub fn new(redis_url: &str, ttl_seconds: u64, min_refresh_interval_seconds: u64) -> Result<Self> {
+        let cfg = deadpool_redis::Config::from_url(redis_url);
+        let redis_pool = cfg
+            .create_pool(Some(deadpool_redis::Runtime::Tokio1))
+            .context("Failed to create Redis connection pool")?;
+
+        Ok(Self {
+            redis_pool,
+            ttl_seconds: ttl_seconds.max(1),
+            min_refresh_interval_seconds,
+        })
+    }
+
+    #[instrument(skip(self))]
+    pub async fn get(&self, user_id: Uuid) -> Result<Option<CachedBalances>> {
+        let mut conn = self.redis_pool.get().await
+            .context("Failed to get Redis connection from pool")?;
+
+        let data: Option<String> = conn.get(balances_key(user_id)).await
+            .context("Failed to read cached balances")?;
+
+        data.map(|data| serde_json::from_str(&data).context("Failed to deserialize cached balances"))
+            .transpose()
+    }
+
+    #[instrument(skip(self, balances), fields(account_count = balances.accounts.len()))]
+    pub async fn put(&self, user_id: Uuid, balances: &CachedBalances) -> Result<()> {
+        let mut conn = self.redis_pool.get().await
+            .context("Failed to get Redis connection from pool")?;
+
+        let data = serde_json::to_string(balances).context("Failed to serialize balances")?;
+        conn.set_ex::<_, _, ()>(balances_key(user_id), data, self.ttl_seconds).await
+            .context("Failed to cache balances")?;
+
+        debug!(user_id = %user_id, ttl_seconds = self.ttl_seconds, "Cached balances");
+        Ok(())
+    }
+
+    /// Claim the user's refresh slot; at most one refresh is granted per minimum interval
+    #[instrument(skip(self))]
+    pub async fn try_start_refresh(&self, user_id: Uuid) -> Result<RefreshPermit> {
+        if self.min_refresh_interval_seconds == 0 {
+            return Ok(RefreshPermit::Granted);
+        }
+
+        let mut conn = self.redis_pool.get().await
+            .context("Failed to get Redis connection from pool")?;
+        let key = refresh_lock_key(user_id);
+
+        let acquired: Option<String> = redis::cmd("SET")
+            .arg(&key)
+            .arg(Utc::now().timestamp())
+            .arg("NX")
+            .arg("EX")
+            .arg(self.min_refresh_interval_seconds)
+            .query_async(&mut conn)
+            .await
+            .context("Failed to claim balance refresh slot")?;
+        if acquired.is_some() {
+            return Ok(RefreshPermit::Granted);
+        }
+
+        let remaining: i64 = conn.ttl(&key).await.context("Failed to read refresh throttle")?;
+        let retry_after = if remaining > 0 {
+            remaining as u64
+        } else {
+            self.min_refresh_interval_seconds
+        };
+        Ok(RefreshPermit::Throttled(Duration::from_secs(retry_after)))
+    }
+}
@@ -0,0 +1,64 @@
+use crate::model::net_worth::Granularity;
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::PgPool;
+use tracing::instrument;
+use uuid::Uuid;
+
+/// Account balance as recorded when it was fetched from Plaid
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct BalanceSnapshot {
+    pub id: Uuid,
+    pub account_id: String,
+    pub user_id: Uuid,
+    pub available_balance: Option<f64>,
+    pub current_balance: Option<f64>,
+    pub iso_currency_code: Option<String>,
+    pub unofficial_currency_code: Option<String>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// Balance snapshot repository; snapshots are written by `BankAccountRepository::upsert_accounts`
+#[derive(Debug, Clone)]
+pub struct BalanceHistoryRepository {
+    pool: PgPool,
+}
+
+impl BalanceHistoryRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// An account's snapshots recorded between `start` and `end` (inclusive, UTC days), oldest first,
+    /// keeping the latest snapshot in each `granularity` bucket
+    #[instrument(skip(self))]
+    pub async fn history(
+        &self,
+        account_id: &str,
+        start: NaiveDate,
+        end: NaiveDate,
+        granularity: Granularity,
+    ) -> Result<Vec<BalanceSnapshot>> {
+        let snapshots = sqlx::query_as::<_, BalanceSnapshot>(
+            r#"
+            SELECT * FROM (
+                SELECT DISTINCT ON (date_trunc($4, recorded_at AT TIME ZONE 'UTC')) *
+                FROM balance_snapshots
+                WHERE account_id = $1
+                  AND recorded_at >= $2::DATE AT TIME ZONE 'UTC'
+                  AND recorded_at < ($3::DATE + 1) AT TIME ZONE 'UTC'
+                ORDER BY date_trunc($4, recorded_at AT TIME ZONE 'UTC'), recorded_at DESC
+            ) latest
+            ORDER BY recorded_at
+            "#,
+        )
+        .bind(account_id)
+        .bind(start)
+        .bind(end)
+        .bind(granularity.as_sql_unit())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(snapshots)
+    }
+}
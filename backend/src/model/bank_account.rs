@@ -0,0 +1,301 @@
+use crate::adapter::plaid::{AccountBalances, BankAccount};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use tracing::{debug, info, instrument};
+use uuid::Uuid;
+
+/// Persisted bank account row, refreshed from Plaid on every item sync
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct StoredBankAccount {
+    pub id: Uuid,
+    pub account_id: String,
+    pub item_id: String,
+    pub user_id: Uuid,
+    pub name: String,
+    pub official_name: Option<String>,
+    pub mask: Option<String>,
+    pub account_type: String,
+    pub account_subtype: Option<String>,
+    pub available_balance: Option<f64>,
+    pub current_balance: Option<f64>,
+    pub credit_limit: Option<f64>,
+    pub iso_currency_code: Option<String>,
+    pub unofficial_currency_code: Option<String>,
+    pub institution_id: Option<String>,
+    pub institution_name: Option<String>,
+    pub balances_updated_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl StoredBankAccount {
+    /// Convert back into the adapter representation shared with live Plaid responses
+    pub fn to_bank_account(&self) -> BankAccount {
+        BankAccount {
+            account_id: self.account_id.clone(),
+            item_id: self.item_id.clone(),
+            mask: self.mask.clone(),
+            name: self.name.clone(),
+            official_name: self.official_name.clone(),
+            account_type: self.account_type.clone(),
+            account_subtype: self.account_subtype.clone(),
+            balances: AccountBalances {
+                available: self.available_balance,
+                current: self.current_balance,
+                limit: self.credit_limit,
+                iso_currency_code: self.iso_currency_code.clone(),
+                unofficial_currency_code: self.unofficial_currency_code.clone(),
+            },
+            institution_id: self.institution_id.clone(),
+            institution_name: self.institution_name.clone(),
+        }
+    }
+}
+
+/// Account changed after a sync watermark, for delta-sync clients
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct AccountChange {
+    #[sqlx(flatten)]
+    pub account: StoredBankAccount,
+    /// The account's item was unlinked; clients should delete the account
+    pub item_removed: bool,
+    pub changed_at: DateTime<Utc>,
+}
+
+/// Account another user shared with the listing user
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct SharedBankAccount {
+    #[sqlx(flatten)]
+    pub account: StoredBankAccount,
+    /// Access granted by the owner, `read` or `full`
+    pub access: String,
+}
+
+/// Bank account repository for database operations
+#[derive(Debug, Clone)]
+pub struct BankAccountRepository {
+    pool: PgPool,
+}
+
+impl BankAccountRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Insert or refresh the accounts of an item in a single transaction, recording a balance snapshot for each
+    #[instrument(skip(self, accounts), fields(account_count = accounts.len()))]
+    pub async fn upsert_accounts(
+        &self,
+        user_id: Uuid,
+        item_id: &str,
+        accounts: &[BankAccount],
+    ) -> Result<Vec<StoredBankAccount>> {
+        debug!(user_id = %user_id, item_id = %item_id, "Upserting bank accounts");
+
+        let mut tx = self.pool.begin().await?;
+        let mut stored = Vec::with_capacity(accounts.len());
+
+        for account in accounts {
+            let row = sqlx::query_as::<_, StoredBankAccount>(
+                r#"
+                INSERT INTO bank_accounts (
+                    account_id, item_id, user_id, name, official_name, mask,
+                    account_type, account_subtype, available_balance, current_balance,
+                    credit_limit, iso_currency_code, unofficial_currency_code,
+                    institution_id, institution_name
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+                ON CONFLICT (account_id) DO UPDATE SET
+                    name = EXCLUDED.name,
+                    official_name = EXCLUDED.official_name,
+                    mask = EXCLUDED.mask,
+                    account_type = EXCLUDED.account_type,
+                    account_subtype = EXCLUDED.account_subtype,
+                    available_balance = EXCLUDED.available_balance,
+                    current_balance = EXCLUDED.current_balance,
+                    credit_limit = EXCLUDED.credit_limit,
+                    iso_currency_code = EXCLUDED.iso_currency_code,
+                    unofficial_currency_code = EXCLUDED.unofficial_currency_code,
+                    institution_id = COALESCE(EXCLUDED.institution_id, bank_accounts.institution_id),
+                    institution_name = COALESCE(EXCLUDED.institution_name, bank_accounts.institution_name),
+                    balances_updated_at = NOW(),
+                    updated_at = NOW()
+                WHERE bank_accounts.user_id = EXCLUDED.user_id
+                RETURNING *
+                "#,
+            )
+            .bind(&account.account_id)
+            .bind(item_id)
+            .bind(user_id)
+            .bind(&account.name)
+            .bind(&account.official_name)
+            .bind(&account.mask)
+            .bind(&account.account_type)
+            .bind(&account.account_subtype)
+            .bind(account.balances.available)
+            .bind(account.balances.current)
+            .bind(account.balances.limit)
+            .bind(&account.balances.iso_currency_code)
+            .bind(&account.balances.unofficial_currency_code)
+            .bind(&account.institution_id)
+            .bind(&account.institution_name)
+            .fetch_one(&mut *tx)
+            .await
+            .with_context(|| format!("Failed to upsert bank account {}", account.account_id))?;
+
+            if row.available_balance.is_some() || row.current_balance.is_some() {
+                sqlx::query(
+                    r#"
+                    INSERT INTO balance_snapshots (
+                        account_id, user_id, available_balance, current_balance,
+                        iso_currency_code, unofficial_currency_code
+                    )
+                    VALUES ($1, $2, $3, $4, $5, $6)
+                    "#,
+                )
+                .bind(&row.account_id)
+                .bind(user_id)
+                .bind(row.available_balance)
+                .bind(row.current_balance)
+                .bind(&row.iso_currency_code)
+                .bind(&row.unofficial_currency_code)
+                .execute(&mut *tx)
+                .await
+                .with_context(|| format!("Failed to record balance snapshot for {}", account.account_id))?;
+            }
+
+            stored.push(row);
+        }
+
+        tx.commit().await?;
+
+        info!(user_id = %user_id, item_id = %item_id, account_count = stored.len(), "Successfully stored bank accounts");
+        Ok(stored)
+    }
+
+    /// List a user's accounts on items that have not been removed
+    #[instrument(skip(self))]
+    pub async fn list_by_user(&self, user_id: Uuid) -> Result<Vec<StoredBankAccount>> {
+        debug!(user_id = %user_id, "Listing bank accounts for user");
+
+        let accounts = sqlx::query_as::<_, StoredBankAccount>(
+            r#"
+            SELECT a.* FROM bank_accounts a
+            JOIN plaid_items i ON i.item_id = a.item_id
+            WHERE a.user_id = $1 AND i.status <> 'removed'
+            ORDER BY a.institution_name NULLS LAST, a.name
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(accounts)
+    }
+
+    /// List accounts other users shared with `user_id` through an accepted share
+    #[instrument(skip(self))]
+    pub async fn list_shared_with(&self, user_id: Uuid) -> Result<Vec<SharedBankAccount>> {
+        let accounts = sqlx::query_as::<_, SharedBankAccount>(
+            r#"
+            SELECT a.*, s.access FROM bank_accounts a
+            JOIN account_shares s ON s.account_id = a.account_id
+            JOIN plaid_items i ON i.item_id = a.item_id
+            WHERE s.grantee_user_id = $1 AND s.status = 'accepted' AND i.status <> 'removed'
+            ORDER BY a.institution_name NULLS LAST, a.name
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(accounts)
+    }
+
+    /// List accounts updated after `since`, including accounts of items removed since then
+    #[instrument(skip(self))]
+    pub async fn list_changed_since(&self, user_id: Uuid, since: DateTime<Utc>) -> Result<Vec<AccountChange>> {
+        let changes = sqlx::query_as::<_, AccountChange>(
+            r#"
+            SELECT a.*,
+                   (i.status = 'removed') AS item_removed,
+                   GREATEST(a.updated_at, i.updated_at) AS changed_at
+            FROM bank_accounts a
+            JOIN plaid_items i ON i.item_id = a.item_id
+            WHERE a.user_id = $1
+              AND (a.updated_at > $2 OR (i.status = 'removed' AND i.updated_at > $2))
+            ORDER BY a.updated_at
+            "#,
+        )
+        .bind(user_id)
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(changes)
+    }
+
+    /// List the accounts stored for an item
+    #[instrument(skip(self))]
+    pub async fn list_by_item(&self, item_id: &str) -> Result<Vec<StoredBankAccount>> {
+        let accounts = sqlx::query_as::<_, StoredBankAccount>(
+            "SELECT * FROM bank_accounts WHERE item_id = $1 ORDER BY name"
+        )
+        .bind(item_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(accounts)
+    }
+
+    /// Find an account by Plaid account ID
+    #[instrument(skip(self))]
+    pub async fn find_by_account_id(&self, account_id: &str) -> Result<Option<StoredBankAccount>> {
+        let account = sqlx::query_as::<_, StoredBankAccount>(
+            "SELECT * FROM bank_accounts WHERE account_id = $1"
+        )
+        .bind(account_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(account)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_bank_account_maps_balances() {
+        let now = Utc::now();
+        let stored = StoredBankAccount {
+            id: Uuid::new_v4(),
+            account_id: "acc_1".to_string(),
+            item_id: "item_1".to_string(),
+            user_id: Uuid::new_v4(),
+            name: "Plaid Checking".to_string(),
+            official_name: None,
+            mask: Some("0000".to_string()),
+            account_type: "depository".to_string(),
+            account_subtype: Some("checking".to_string()),
+            available_balance: Some(100.0),
+            current_balance: Some(110.0),
+            credit_limit: None,
+            iso_currency_code: Some("USD".to_string()),
+            unofficial_currency_code: None,
+            institution_id: Some("ins_1".to_string()),
+            institution_name: Some("First Platypus Bank".to_string()),
+            balances_updated_at: now,
+            created_at: now,
+            updated_at: now,
+        };
+
+        let account = stored.to_bank_account();
+        assert_eq!(account.account_id, "acc_1");
+        assert_eq!(account.balances.available, Some(100.0));
+        assert_eq!(account.balances.current, Some(110.0));
+        assert_eq!(account.balances.iso_currency_code.as_deref(), Some("USD"));
+    }
+}
@@ -0,0 +1,410 @@
+use crate::model::transaction::Transaction;
+use anyhow::Result;
+use chrono::{DateTime, Duration, Months, NaiveDate, Utc};
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+use tracing::{debug, instrument};
+use uuid::Uuid;
+
+/// Largest relative deviation from the median amount a payment may have and still count as the same bill
+const AMOUNT_TOLERANCE: f64 = 0.3;
+
+/// Plaid top-level categories that are never bills
+const EXCLUDED_CATEGORIES: &[&str] = &["Transfer"];
+
+/// How often a bill recurs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BillCadence {
+    Weekly,
+    Biweekly,
+    Monthly,
+    Quarterly,
+    Annual,
+}
+
+impl BillCadence {
+    const ALL: [BillCadence; 5] = [
+        BillCadence::Weekly,
+        BillCadence::Biweekly,
+        BillCadence::Monthly,
+        BillCadence::Quarterly,
+        BillCadence::Annual,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BillCadence::Weekly => "weekly",
+            BillCadence::Biweekly => "biweekly",
+            BillCadence::Monthly => "monthly",
+            BillCadence::Quarterly => "quarterly",
+            BillCadence::Annual => "annual",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|cadence| cadence.as_str() == value)
+    }
+
+    /// Days between consecutive payments, allowing for weekends and posting delays
+    fn interval_days(&self) -> RangeInclusive<i64> {
+        match self {
+            BillCadence::Weekly => 6..=8,
+            BillCadence::Biweekly => 12..=16,
+            BillCadence::Monthly => 26..=35,
+            BillCadence::Quarterly => 84..=98,
+            BillCadence::Annual => 350..=380,
+        }
+    }
+
+    /// Payments needed before the series is treated as a bill; a year of history holds only two annual ones
+    fn min_occurrences(&self) -> usize {
+        match self {
+            BillCadence::Annual => 2,
+            _ => 3,
+        }
+    }
+
+    /// Expected date of the payment after one made on `date`
+    pub fn next_date(&self, date: NaiveDate) -> NaiveDate {
+        let months = match self {
+            BillCadence::Weekly => return date + Duration::days(7),
+            BillCadence::Biweekly => return date + Duration::days(14),
+            BillCadence::Monthly => 1,
+            BillCadence::Quarterly => 3,
+            BillCadence::Annual => 12,
+        };
+        date.checked_add_months(Months::new(months)).unwrap_or(date)
+    }
+}
+
+/// Key grouping payments to the same payee: the merchant (or description) without digits or punctuation,
+/// which often carry reference numbers that change every month
+pub fn merchant_key(transaction: &Transaction) -> String {
+    normalize_payee(transaction.merchant_name.as_deref().unwrap_or(&transaction.name))
+}
+
+/// Lowercase words of a payee name, without digits or punctuation
+pub fn normalize_payee(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if c.is_alphabetic() { c.to_ascii_lowercase() } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Recurring payment found in a user's history
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectedBill {
+    pub account_id: String,
+    pub merchant_key: String,
+    /// Display name from the most recent payment
+    pub name: String,
+    pub cadence: BillCadence,
+    /// Median payment amount
+    pub typical_amount: f64,
+    pub iso_currency_code: Option<String>,
+    pub occurrences: usize,
+    pub last_paid_date: NaiveDate,
+    pub next_due_date: NaiveDate,
+}
+
+/// Find bills among posted outflows (oldest first).
+///
+/// A series of payments to one payee from one account is a bill when every gap between payments fits a
+/// single cadence and every amount is close to the median. Series whose next payment is overdue by more
+/// than a full interval are treated as cancelled.
+pub fn detect_bills(transactions: &[Transaction], today: NaiveDate) -> Vec<DetectedBill> {
+    let mut series: BTreeMap<(&str, String), Vec<&Transaction>> = BTreeMap::new();
+    for transaction in transactions {
+        let excluded = transaction
+            .category
+            .first()
+            .is_some_and(|c| EXCLUDED_CATEGORIES.contains(&c.as_str()));
+        if transaction.pending || transaction.amount <= 0.0 || excluded {
+            continue;
+        }
+        let key = merchant_key(transaction);
+        if !key.is_empty() {
+            series.entry((transaction.account_id.as_str(), key)).or_default().push(transaction);
+        }
+    }
+
+    series
+        .into_iter()
+        .filter_map(|((account_id, key), mut payments)| {
+            payments.sort_by_key(|t| t.date);
+            detect_series(account_id, key, &payments, today)
+        })
+        .collect()
+}
+
+fn detect_series(
+    account_id: &str,
+    merchant_key: String,
+    payments: &[&Transaction],
+    today: NaiveDate,
+) -> Option<DetectedBill> {
+    if payments.len() < 2 {
+        return None;
+    }
+
+    let intervals: Vec<i64> = payments
+        .windows(2)
+        .map(|pair| (pair[1].date - pair[0].date).num_days())
+        .collect();
+    let cadence = BillCadence::ALL
+        .into_iter()
+        .find(|cadence| intervals.iter().all(|days| cadence.interval_days().contains(days)))?;
+    if payments.len() < cadence.min_occurrences() {
+        return None;
+    }
+
+    let typical_amount = median(payments.iter().map(|t| t.amount).collect());
+    if payments
+        .iter()
+        .any(|t| (t.amount - typical_amount).abs() > typical_amount * AMOUNT_TOLERANCE)
+    {
+        return None;
+    }
+
+    let last = payments.last()?;
+    let next_due_date = cadence.next_date(last.date);
+    if today - next_due_date > Duration::days(*cadence.interval_days().end()) {
+        return None;
+    }
+
+    Some(DetectedBill {
+        account_id: account_id.to_string(),
+        merchant_key,
+        name: last.merchant_name.clone().unwrap_or_else(|| last.name.clone()),
+        cadence,
+        typical_amount: (typical_amount * 100.0).round() / 100.0,
+        iso_currency_code: last.iso_currency_code.clone(),
+        occurrences: payments.len(),
+        last_paid_date: last.date,
+        next_due_date,
+    })
+}
+
+fn median(mut values: Vec<f64>) -> f64 {
+    values.sort_by(|a, b| a.total_cmp(b));
+    let mid = values.len() / 2;
+    if values.len() % 2 == 0 {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+/// Persisted bill
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Bill {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub account_id: String,
+    pub merchant_key: String,
+    pub name: String,
+    pub cadence: String,
+    pub typical_amount: f64,
+    pub iso_currency_code: Option<String>,
+    pub occurrences: i32,
+    pub last_paid_date: NaiveDate,
+    pub next_due_date: NaiveDate,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Bill {
+    pub fn cadence(&self) -> Option<BillCadence> {
+        BillCadence::parse(&self.cadence)
+    }
+}
+
+/// Bill repository for database operations
+#[derive(Debug, Clone)]
+pub struct BillRepository {
+    pool: PgPool,
+}
+
+impl BillRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Replace a user's bills with the latest detection results; returns the number of bills dropped
+    #[instrument(skip(self, bills), fields(bill_count = bills.len()))]
+    pub async fn replace_for_user(&self, user_id: Uuid, bills: &[DetectedBill]) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+
+        let mut kept = Vec::with_capacity(bills.len());
+        for bill in bills {
+            let id: Uuid = sqlx::query_scalar(
+                r#"
+                INSERT INTO bills (
+                    user_id, account_id, merchant_key, name, cadence, typical_amount,
+                    iso_currency_code, occurrences, last_paid_date, next_due_date
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                ON CONFLICT (user_id, account_id, merchant_key) DO UPDATE SET
+                    name = EXCLUDED.name,
+                    cadence = EXCLUDED.cadence,
+                    typical_amount = EXCLUDED.typical_amount,
+                    iso_currency_code = EXCLUDED.iso_currency_code,
+                    occurrences = EXCLUDED.occurrences,
+                    last_paid_date = EXCLUDED.last_paid_date,
+                    next_due_date = EXCLUDED.next_due_date,
+                    updated_at = NOW()
+                RETURNING id
+                "#,
+            )
+            .bind(user_id)
+            .bind(&bill.account_id)
+            .bind(&bill.merchant_key)
+            .bind(&bill.name)
+            .bind(bill.cadence.as_str())
+            .bind(bill.typical_amount)
+            .bind(&bill.iso_currency_code)
+            .bind(bill.occurrences as i32)
+            .bind(bill.last_paid_date)
+            .bind(bill.next_due_date)
+            .fetch_one(&mut *tx)
+            .await?;
+            kept.push(id);
+        }
+
+        let dropped = sqlx::query("DELETE FROM bills WHERE user_id = $1 AND NOT (id = ANY($2))")
+            .bind(user_id)
+            .bind(&kept)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        tx.commit().await?;
+        debug!(dropped, "Replaced bills");
+        Ok(dropped)
+    }
+
+    /// A user's bills, soonest due first
+    #[instrument(skip(self))]
+    pub async fn list_by_user(&self, user_id: Uuid) -> Result<Vec<Bill>> {
+        let bills = sqlx::query_as::<_, Bill>(
+            "SELECT * FROM bills WHERE user_id = $1 ORDER BY next_due_date, name"
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(bills)
+    }
+
+    /// All users' bills due between two dates, inclusive
+    #[instrument(skip(self))]
+    pub async fn list_due_between(&self, start_date: NaiveDate, end_date: NaiveDate) -> Result<Vec<Bill>> {
+        let bills = sqlx::query_as::<_, Bill>(
+            "SELECT * FROM bills WHERE next_due_date BETWEEN $1 AND $2 ORDER BY user_id, next_due_date"
+        )
+        .bind(start_date)
+        .bind(end_date)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(bills)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn payment(name: &str, on: NaiveDate, amount: f64) -> Transaction {
+        Transaction {
+            id: Uuid::new_v4(),
+            transaction_id: format!("txn_{}", on),
+            account_id: "acc_1".to_string(),
+            item_id: "item_1".to_string(),
+            user_id: Uuid::new_v4(),
+            amount,
+            iso_currency_code: Some("USD".to_string()),
+            unofficial_currency_code: None,
+            date: on,
+            datetime: None,
+            authorized_date: None,
+            authorized_datetime: None,
+            name: name.to_string(),
+            merchant_name: None,
+            original_description: None,
+            category: vec!["Service".to_string()],
+            category_id: None,
+            check_number: None,
+            location: None,
+            payment_meta: None,
+            pending: false,
+            pending_transaction_id: None,
+            account_owner: None,
+            transaction_type: "special".to_string(),
+            transaction_code: None,
+            removed_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_detects_monthly_bill() {
+        let transactions = vec![
+            payment("CITY POWER #1041", date(2024, 1, 3), 82.10),
+            payment("Coffee", date(2024, 1, 9), 4.50),
+            payment("CITY POWER #1187", date(2024, 2, 2), 95.40),
+            payment("CITY POWER #1302", date(2024, 3, 4), 88.00),
+        ];
+
+        let bills = detect_bills(&transactions, date(2024, 3, 20));
+        assert_eq!(bills.len(), 1);
+        let bill = &bills[0];
+        assert_eq!(bill.merchant_key, "city power");
+        assert_eq!(bill.cadence, BillCadence::Monthly);
+        assert_eq!(bill.typical_amount, 88.0);
+        assert_eq!(bill.occurrences, 3);
+        assert_eq!(bill.next_due_date, date(2024, 4, 4));
+    }
+
+    #[test]
+    fn test_rejects_irregular_or_cancelled_series() {
+        let irregular_amounts = vec![
+            payment("Gym", date(2024, 1, 1), 30.0),
+            payment("Gym", date(2024, 2, 1), 90.0),
+            payment("Gym", date(2024, 3, 1), 30.0),
+        ];
+        assert!(detect_bills(&irregular_amounts, date(2024, 3, 10)).is_empty());
+
+        let irregular_dates = vec![
+            payment("Gym", date(2024, 1, 1), 30.0),
+            payment("Gym", date(2024, 1, 20), 30.0),
+            payment("Gym", date(2024, 3, 1), 30.0),
+        ];
+        assert!(detect_bills(&irregular_dates, date(2024, 3, 10)).is_empty());
+
+        let regular = vec![
+            payment("Gym", date(2024, 1, 1), 30.0),
+            payment("Gym", date(2024, 2, 1), 30.0),
+            payment("Gym", date(2024, 3, 1), 30.0),
+        ];
+        assert_eq!(detect_bills(&regular, date(2024, 3, 10)).len(), 1);
+        assert!(detect_bills(&regular, date(2024, 6, 1)).is_empty());
+    }
+
+    #[test]
+    fn test_next_date() {
+        assert_eq!(BillCadence::Monthly.next_date(date(2024, 1, 31)), date(2024, 2, 29));
+        assert_eq!(BillCadence::Biweekly.next_date(date(2024, 1, 31)), date(2024, 2, 14));
+        assert_eq!(BillCadence::Annual.next_date(date(2024, 2, 29)), date(2025, 2, 28));
+        assert_eq!(BillCadence::parse("quarterly"), Some(BillCadence::Quarterly));
+    }
+}
@@ -0,0 +1,268 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use tracing::{info, instrument};
+use uuid::Uuid;
+
+/// Tokens added to each turn's text estimate for role and formatting overhead
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+
+/// Author of a chat turn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatRole {
+    User,
+    Assistant,
+}
+
+impl ChatRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChatRole::User => "user",
+            ChatRole::Assistant => "assistant",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "user" => Some(ChatRole::User),
+            "assistant" => Some(ChatRole::Assistant),
+            _ => None,
+        }
+    }
+}
+
+/// Conversation with the assistant; `system_prompt` overrides the default when set
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ChatConversation {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub title: String,
+    pub system_prompt: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Persisted turn of a conversation; token counts are set on assistant replies
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ChatMessage {
+    pub id: Uuid,
+    pub conversation_id: Uuid,
+    pub position: i32,
+    pub role: String,
+    pub content: String,
+    pub input_tokens: Option<i32>,
+    pub output_tokens: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ChatMessage {
+    pub fn role(&self) -> Option<ChatRole> {
+        ChatRole::parse(&self.role)
+    }
+}
+
+/// Assistant reply to store with the user message it answers
+#[derive(Debug, Clone)]
+pub struct ChatReply {
+    pub content: String,
+    pub input_tokens: i32,
+    pub output_tokens: i32,
+}
+
+/// Rough token count of a turn, at about four characters per token
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4) + MESSAGE_OVERHEAD_TOKENS
+}
+
+/// Newest turns of `history` (oldest first) whose estimated size fits in `budget_tokens`.
+///
+/// The kept turns always start with a user message, as the messages API requires, so an
+/// assistant reply whose question was truncated away is dropped as well.
+pub fn truncate_history<T: AsRef<str>>(history: &[(ChatRole, T)], budget_tokens: usize) -> &[(ChatRole, T)] {
+    let mut used = 0;
+    let mut start = history.len();
+    while start > 0 {
+        let tokens = estimate_tokens(history[start - 1].1.as_ref());
+        if used + tokens > budget_tokens {
+            break;
+        }
+        used += tokens;
+        start -= 1;
+    }
+
+    let kept = &history[start..];
+    let first_user = kept
+        .iter()
+        .position(|(role, _)| *role == ChatRole::User)
+        .unwrap_or(kept.len());
+    &kept[first_user..]
+}
+
+/// Chat repository for database operations
+#[derive(Debug, Clone)]
+pub struct ChatRepository {
+    pool: PgPool,
+}
+
+impl ChatRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    #[instrument(skip(self, system_prompt))]
+    pub async fn create_conversation(
+        &self,
+        user_id: Uuid,
+        title: &str,
+        system_prompt: Option<&str>,
+    ) -> Result<ChatConversation> {
+        let conversation = sqlx::query_as::<_, ChatConversation>(
+            r#"
+            INSERT INTO chat_conversations (user_id, title, system_prompt)
+            VALUES ($1, $2, $3)
+            RETURNING *
+            "#,
+        )
+        .bind(user_id)
+        .bind(title)
+        .bind(system_prompt)
+        .fetch_one(&self.pool)
+        .await?;
+
+        info!(conversation_id = %conversation.id, "Chat conversation created");
+        Ok(conversation)
+    }
+
+    /// Conversation owned by the user
+    #[instrument(skip(self))]
+    pub async fn find_conversation(&self, user_id: Uuid, id: Uuid) -> Result<Option<ChatConversation>> {
+        let conversation = sqlx::query_as::<_, ChatConversation>(
+            "SELECT * FROM chat_conversations WHERE id = $1 AND user_id = $2",
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(conversation)
+    }
+
+    /// Up to `limit` messages before `before_position` (or the newest), newest first
+    #[instrument(skip(self))]
+    pub async fn list_messages(
+        &self,
+        conversation_id: Uuid,
+        before_position: Option<i32>,
+        limit: i64,
+    ) -> Result<Vec<ChatMessage>> {
+        let messages = sqlx::query_as::<_, ChatMessage>(
+            r#"
+            SELECT * FROM chat_messages
+            WHERE conversation_id = $1 AND ($2::INTEGER IS NULL OR position < $2)
+            ORDER BY position DESC
+            LIMIT $3
+            "#,
+        )
+        .bind(conversation_id)
+        .bind(before_position)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(messages)
+    }
+
+    /// Store a user message and the assistant's reply as the next two turns.
+    ///
+    /// The conversation row is locked so concurrent sends get consecutive positions.
+    #[instrument(skip(self, user_content, reply))]
+    pub async fn append_exchange(
+        &self,
+        conversation_id: Uuid,
+        user_content: &str,
+        reply: &ChatReply,
+    ) -> Result<(ChatMessage, ChatMessage)> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("SELECT id FROM chat_conversations WHERE id = $1 FOR UPDATE")
+            .bind(conversation_id)
+            .fetch_one(&mut *tx)
+            .await
+            .context("Failed to lock chat conversation")?;
+        let next_position: i32 = sqlx::query_scalar(
+            "SELECT COALESCE(MAX(position) + 1, 0) FROM chat_messages WHERE conversation_id = $1",
+        )
+        .bind(conversation_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let user_message = sqlx::query_as::<_, ChatMessage>(
+            r#"
+            INSERT INTO chat_messages (conversation_id, position, role, content)
+            VALUES ($1, $2, 'user', $3)
+            RETURNING *
+            "#,
+        )
+        .bind(conversation_id)
+        .bind(next_position)
+        .bind(user_content)
+        .fetch_one(&mut *tx)
+        .await?;
+        let assistant_message = sqlx::query_as::<_, ChatMessage>(
+            r#"
+            INSERT INTO chat_messages (conversation_id, position, role, content, input_tokens, output_tokens)
+            VALUES ($1, $2, 'assistant', $3, $4, $5)
+            RETURNING *
+            "#,
+        )
+        .bind(conversation_id)
+        .bind(next_position + 1)
+        .bind(&reply.content)
+        .bind(reply.input_tokens)
+        .bind(reply.output_tokens)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query("UPDATE chat_conversations SET updated_at = NOW() WHERE id = $1")
+            .bind(conversation_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok((user_message, assistant_message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chat_role_round_trip() {
+        for role in [ChatRole::User, ChatRole::Assistant] {
+            assert_eq!(ChatRole::parse(role.as_str()), Some(role));
+        }
+        assert_eq!(ChatRole::parse("system"), None);
+    }
+
+    #[test]
+    fn test_truncate_history_keeps_newest_turns() {
+        let history = vec![
+            (ChatRole::User, "a".repeat(40)),
+            (ChatRole::Assistant, "b".repeat(40)),
+            (ChatRole::User, "c".repeat(40)),
+            (ChatRole::Assistant, "d".repeat(40)),
+            (ChatRole::User, "e".repeat(40)),
+        ];
+        // Each turn is estimated at 10 + 4 tokens
+        assert_eq!(estimate_tokens(&history[0].1), 14);
+
+        assert_eq!(truncate_history(&history, 1_000).len(), 5);
+        assert_eq!(truncate_history(&history, 42).len(), 3);
+        // Four turns fit, but the oldest kept one would be an assistant reply
+        let kept = truncate_history(&history, 56);
+        assert_eq!(kept.len(), 3);
+        assert_eq!(kept[0], (ChatRole::User, "c".repeat(40)));
+        assert!(truncate_history(&history, 10).is_empty());
+    }
+}
@@ -0,0 +1,242 @@
+use crate::settings::SettingValues;
+use anyhow::{Context, Result};
+use metrics::histogram;
+use sqlx::postgres::{PgConnectOptions, PgPool, PgPoolOptions};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Longest a read waits for a replica connection, so reads fail fast while an unreachable replica
+/// waits for the next health check to route them back to the primary
+const REPLICA_ACQUIRE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Sizing and timeouts of the Postgres connection pool
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbPoolConfig {
+    pub max_connections: u32,
+    /// Connections kept open while idle
+    pub min_connections: u32,
+    /// Longest a query waits for a free connection before failing
+    pub acquire_timeout: Duration,
+    /// Longest a statement may run before Postgres cancels it; `None` leaves the server's setting
+    pub statement_timeout: Option<Duration>,
+    /// Acquisitions waiting longer than this are logged
+    pub slow_acquire_threshold: Duration,
+    /// How often the acquire probe times a checkout
+    pub acquire_probe_interval: Duration,
+}
+
+impl Default for DbPoolConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: 10,
+            min_connections: 0,
+            acquire_timeout: Duration::from_secs(30),
+            statement_timeout: None,
+            slow_acquire_threshold: Duration::from_millis(500),
+            acquire_probe_interval: Duration::from_secs(10),
+        }
+    }
+}
+
+impl DbPoolConfig {
+    /// Read from settings
+    /// - DATABASE_MAX_CONNECTIONS: pool size (default: 10)
+    /// - DATABASE_MIN_CONNECTIONS: connections kept open while idle (default: 0)
+    /// - DATABASE_ACQUIRE_TIMEOUT_SECS: seconds a query waits for a connection (default: 30)
+    /// - DATABASE_STATEMENT_TIMEOUT_MS: milliseconds a statement may run; 0 for no limit (default: 0)
+    /// - DATABASE_SLOW_ACQUIRE_MS: waits for a connection longer than this are logged (default: 500)
+    /// - DATABASE_ACQUIRE_PROBE_SECS: seconds between timed checkouts (default: 10)
+    pub fn from_values(values: &mut SettingValues) -> Self {
+        let defaults = Self::default();
+        let max_connections = values
+            .parsed("database-max-connections", defaults.max_connections)
+            .max(1);
+        Self {
+            max_connections,
+            min_connections: values
+                .parsed("database-min-connections", defaults.min_connections)
+                .min(max_connections),
+            acquire_timeout: values
+                .parsed_optional("database-acquire-timeout-secs")
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.acquire_timeout),
+            statement_timeout: values
+                .parsed_optional("database-statement-timeout-ms")
+                .filter(|ms| *ms > 0)
+                .map(Duration::from_millis),
+            slow_acquire_threshold: values
+                .parsed_optional("database-slow-acquire-ms")
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.slow_acquire_threshold),
+            acquire_probe_interval: values
+                .parsed_optional("database-acquire-probe-secs")
+                .map(|secs: u64| Duration::from_secs(secs.max(1)))
+                .unwrap_or(defaults.acquire_probe_interval),
+        }
+    }
+
+    /// The same sizing and timeouts for the read replica, except that reads wait at most
+    /// `REPLICA_ACQUIRE_TIMEOUT` for a connection
+    pub fn for_replica(&self) -> Self {
+        Self {
+            acquire_timeout: self.acquire_timeout.min(REPLICA_ACQUIRE_TIMEOUT),
+            ..self.clone()
+        }
+    }
+
+    /// Connect options for `url`, with the statement timeout applied to every session
+    pub fn connect_options(&self, url: &str) -> Result<PgConnectOptions> {
+        let options = PgConnectOptions::from_str(url).context("Invalid database URL")?;
+        Ok(match self.statement_timeout {
+            Some(timeout) => options.options([("statement_timeout", timeout.as_millis().to_string())]),
+            None => options,
+        })
+    }
+
+    /// Pool options with this sizing
+    pub fn pool_options(&self) -> PgPoolOptions {
+        PgPoolOptions::new()
+            .max_connections(self.max_connections)
+            .min_connections(self.min_connections)
+            .acquire_timeout(self.acquire_timeout)
+    }
+
+    /// Open the pool named `name` (e.g. `primary`) to `url`. A `lazy` pool opens connections on first
+    /// use, so a database that is down at startup doesn't stop the server. Checkouts are timed every
+    /// `acquire_probe_interval` for the life of the process.
+    pub async fn connect(&self, name: &'static str, url: &str, lazy: bool) -> Result<PgPool> {
+        let options = self.connect_options(url)?;
+        let pool = if lazy {
+            self.pool_options().connect_lazy_with(options)
+        } else {
+            self.pool_options()
+                .connect_with(options)
+                .await
+                .context("Failed to connect to the database")?
+        };
+        info!(
+            pool = name,
+            max_connections = self.max_connections,
+            min_connections = self.min_connections,
+            acquire_timeout_secs = self.acquire_timeout.as_secs(),
+            statement_timeout_ms = self.statement_timeout.map(|t| t.as_millis() as u64),
+            lazy,
+            "Database pool opened"
+        );
+        self.spawn_acquire_probe(name, pool.clone());
+        Ok(pool)
+    }
+
+    /// Time a checkout from `pool` every `acquire_probe_interval` for the life of the process,
+    /// recording the wait in `db_pool_acquire_wait_seconds` and logging slow ones. sqlx has no hook
+    /// around acquisitions, so the probe's wait stands in for what queries see at that moment.
+    fn spawn_acquire_probe(&self, name: &'static str, pool: PgPool) {
+        let interval = self.acquire_probe_interval;
+        let slow_threshold = self.slow_acquire_threshold;
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                probe_acquire(name, &pool, slow_threshold).await;
+            }
+        });
+    }
+}
+
+/// Check a connection out of `pool` and back in; returns how long the checkout waited
+async fn probe_acquire(name: &'static str, pool: &PgPool, slow_threshold: Duration) -> Option<Duration> {
+    let started = Instant::now();
+    let result = pool.acquire().await;
+    let wait = started.elapsed();
+    histogram!("db_pool_acquire_wait_seconds", "pool" => name).record(wait.as_secs_f64());
+    let idle = pool.num_idle();
+    let in_use = (pool.size() as usize).saturating_sub(idle);
+    match result {
+        Ok(_connection) => {
+            if wait > slow_threshold {
+                warn!(
+                    pool = name,
+                    wait_ms = wait.as_millis() as u64,
+                    in_use,
+                    idle,
+                    max_connections = pool.options().get_max_connections(),
+                    "Slow database connection acquisition"
+                );
+            }
+            Some(wait)
+        }
+        Err(e) => {
+            warn!(
+                pool = name,
+                error = %e,
+                wait_ms = wait.as_millis() as u64,
+                in_use,
+                "Failed to acquire a database connection"
+            );
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_statement_timeout_is_a_session_option() {
+        let config = DbPoolConfig {
+            statement_timeout: Some(Duration::from_secs(5)),
+            ..Default::default()
+        };
+        assert!(config.connect_options("postgres://localhost/origin").is_ok());
+        assert!(config.connect_options("not a url").is_err());
+    }
+
+    #[test]
+    fn test_pool_options_follow_config() {
+        let config = DbPoolConfig {
+            max_connections: 25,
+            min_connections: 2,
+            ..Default::default()
+        };
+        let options = config.pool_options();
+        assert_eq!(options.get_max_connections(), 25);
+        assert_eq!(options.get_min_connections(), 2);
+        assert_eq!(options.get_acquire_timeout(), Duration::from_secs(30));
+    }
+
+    #[tokio::test]
+    async fn test_probe_reports_failed_acquire() {
+        let config = DbPoolConfig {
+            acquire_timeout: Duration::from_millis(100),
+            ..Default::default()
+        };
+        let pool = config
+            .pool_options()
+            .connect_lazy_with(config.connect_options("postgres://localhost:1/origin").unwrap());
+        assert_eq!(
+            probe_acquire("primary", &pool, config.slow_acquire_threshold).await,
+            None
+        );
+    }
+
+    #[test]
+    fn test_replica_fails_fast() {
+        let config = DbPoolConfig {
+            max_connections: 25,
+            statement_timeout: Some(Duration::from_secs(5)),
+            ..Default::default()
+        };
+        let replica = config.for_replica();
+        assert_eq!(replica.acquire_timeout, REPLICA_ACQUIRE_TIMEOUT);
+        assert_eq!(replica.max_connections, 25);
+        assert_eq!(replica.statement_timeout, Some(Duration::from_secs(5)));
+
+        let quick = DbPoolConfig {
+            acquire_timeout: Duration::from_secs(1),
+            ..Default::default()
+        };
+        assert_eq!(quick.for_replica().acquire_timeout, Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,395 @@
+use crate::settings::FeatureFlags;
+use anyhow::Result;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tracing::warn;
+
+/// Counter of shadow writes, compared reads and their failures, by migration and event
+pub const MIGRATION_EVENTS_METRIC: &str = "store_migration_events_total";
+
+/// Redis sessions -> Postgres `auth_sessions`, so sessions survive Redis evictions and can be listed with
+/// the user's other data
+pub const SESSION_STORE_MIGRATION: &str = "session-store";
+
+/// Postgres `otp_codes` -> Redis, whose TTLs expire codes without a cleanup job
+pub const OTP_STORE_MIGRATION: &str = "otp-store";
+
+/// Rollout phase of a store migration, ordered from the legacy store to the new one.
+///
+/// Each phase is safe to deploy on top of the previous one and to roll back to it:
+/// the store that is not authoritative keeps receiving writes until `NewOnly`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MigrationPhase {
+    /// Only the old store is used
+    #[default]
+    OldOnly,
+    /// Writes go to both stores, reads come from the old store and are shadow-compared against the new one
+    DualWriteReadOld,
+    /// Writes go to both stores, reads come from the new store and are shadow-compared against the old one
+    DualWriteReadNew,
+    /// Only the new store is used
+    NewOnly,
+}
+
+impl MigrationPhase {
+    /// Phase of migration `name` from its feature flags:
+    /// - `<name>-write-new`: also write the new store
+    /// - `<name>-read-new`: read from the new store, still writing both
+    /// - `<name>-retire-old`: stop using the old store, once reads come from the new one
+    ///
+    /// Each flag implies the ones before it, so a rollback only turns off the last flag turned on.
+    pub fn from_flags(flags: &FeatureFlags, name: &str) -> Self {
+        if flags.is_enabled(&format!("{}-read-new", name)) {
+            if flags.is_enabled(&format!("{}-retire-old", name)) {
+                MigrationPhase::NewOnly
+            } else {
+                MigrationPhase::DualWriteReadNew
+            }
+        } else if flags.is_enabled(&format!("{}-write-new", name)) {
+            MigrationPhase::DualWriteReadOld
+        } else {
+            MigrationPhase::OldOnly
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MigrationPhase::OldOnly => "old_only",
+            MigrationPhase::DualWriteReadOld => "dual_write_read_old",
+            MigrationPhase::DualWriteReadNew => "dual_write_read_new",
+            MigrationPhase::NewOnly => "new_only",
+        }
+    }
+
+    /// Whether writes go to the old store
+    pub fn writes_old(&self) -> bool {
+        !matches!(self, MigrationPhase::NewOnly)
+    }
+
+    /// Whether writes go to the new store
+    pub fn writes_new(&self) -> bool {
+        !matches!(self, MigrationPhase::OldOnly)
+    }
+
+    /// Whether reads are answered by the new store
+    pub fn reads_new(&self) -> bool {
+        matches!(self, MigrationPhase::DualWriteReadNew | MigrationPhase::NewOnly)
+    }
+
+    fn shadow_reads(&self) -> bool {
+        matches!(
+            self,
+            MigrationPhase::DualWriteReadOld | MigrationPhase::DualWriteReadNew
+        )
+    }
+}
+
+/// Consistency counters of a migration, also exported as `MIGRATION_EVENTS_METRIC`
+#[derive(Debug, Default)]
+struct ConsistencyMetrics {
+    shadow_writes: AtomicU64,
+    shadow_write_failures: AtomicU64,
+    reads_compared: AtomicU64,
+    read_mismatches: AtomicU64,
+    shadow_read_failures: AtomicU64,
+    fallback_reads: AtomicU64,
+}
+
+/// Point-in-time copy of a migration's consistency counters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ConsistencySnapshot {
+    pub shadow_writes: u64,
+    pub shadow_write_failures: u64,
+    pub reads_compared: u64,
+    pub read_mismatches: u64,
+    pub shadow_read_failures: u64,
+    pub fallback_reads: u64,
+}
+
+impl ConsistencySnapshot {
+    /// Fraction of compared reads where both stores agreed (1.0 when nothing was compared)
+    pub fn match_rate(&self) -> f64 {
+        if self.reads_compared == 0 {
+            return 1.0;
+        }
+        (self.reads_compared - self.read_mismatches) as f64 / self.reads_compared as f64
+    }
+}
+
+/// Routes reads and writes between an old and a new store according to the migration's `MigrationPhase`,
+/// which is re-read from the feature flags on every call so a rollout step needs no restart.
+///
+/// Operations are passed as unstarted futures; a future for a store the phase does not
+/// touch is dropped without being polled. Failures of the non-authoritative store are
+/// logged and counted but never surfaced to the caller, except by `write_both`.
+#[derive(Debug, Clone)]
+pub struct DualWrite {
+    name: &'static str,
+    flags: Arc<FeatureFlags>,
+    metrics: Arc<ConsistencyMetrics>,
+}
+
+impl DualWrite {
+    pub fn new(name: &'static str, flags: Arc<FeatureFlags>) -> Self {
+        Self {
+            name,
+            flags,
+            metrics: Arc::new(ConsistencyMetrics::default()),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn phase(&self) -> MigrationPhase {
+        MigrationPhase::from_flags(&self.flags, self.name)
+    }
+
+    pub fn metrics(&self) -> ConsistencySnapshot {
+        let metrics = &self.metrics;
+        ConsistencySnapshot {
+            shadow_writes: metrics.shadow_writes.load(Ordering::Relaxed),
+            shadow_write_failures: metrics.shadow_write_failures.load(Ordering::Relaxed),
+            reads_compared: metrics.reads_compared.load(Ordering::Relaxed),
+            read_mismatches: metrics.read_mismatches.load(Ordering::Relaxed),
+            shadow_read_failures: metrics.shadow_read_failures.load(Ordering::Relaxed),
+            fallback_reads: metrics.fallback_reads.load(Ordering::Relaxed),
+        }
+    }
+
+    fn count(&self, counter: &AtomicU64, event: &'static str) {
+        counter.fetch_add(1, Ordering::Relaxed);
+        metrics::counter!(MIGRATION_EVENTS_METRIC, "migration" => self.name, "event" => event).increment(1);
+    }
+
+    /// Apply a write to the stores enabled by the current phase, authoritative store first
+    pub async fn write<T, O, N>(&self, old: O, new: N) -> Result<T>
+    where
+        O: Future<Output = Result<T>>,
+        N: Future<Output = Result<T>>,
+    {
+        // A failed shadow write is logged and counted by `shadow_write`
+        match self.phase() {
+            MigrationPhase::OldOnly => old.await,
+            MigrationPhase::NewOnly => new.await,
+            MigrationPhase::DualWriteReadOld => {
+                let result = old.await?;
+                self.shadow_write(new, "new").await.ok();
+                Ok(result)
+            }
+            MigrationPhase::DualWriteReadNew => {
+                let result = new.await?;
+                self.shadow_write(old, "old").await.ok();
+                Ok(result)
+            }
+        }
+    }
+
+    /// Like `write`, but fails unless every store the phase writes to succeeds. For deletions and
+    /// revocations, which must not survive in the store reads switch to next.
+    pub async fn write_both<T, O, N>(&self, old: O, new: N) -> Result<T>
+    where
+        O: Future<Output = Result<T>>,
+        N: Future<Output = Result<T>>,
+    {
+        match self.phase() {
+            MigrationPhase::OldOnly => old.await,
+            MigrationPhase::NewOnly => new.await,
+            MigrationPhase::DualWriteReadOld => {
+                let result = old.await?;
+                self.shadow_write(new, "new").await?;
+                Ok(result)
+            }
+            MigrationPhase::DualWriteReadNew => {
+                let result = new.await?;
+                self.shadow_write(old, "old").await?;
+                Ok(result)
+            }
+        }
+    }
+
+    async fn shadow_write<T, F>(&self, write: F, store: &'static str) -> Result<()>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        self.count(&self.metrics.shadow_writes, "shadow_write");
+        match write.await {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                self.count(&self.metrics.shadow_write_failures, "shadow_write_failure");
+                warn!(migration = %self.name, store = %store, error = %e, "Shadow write failed");
+                Err(e)
+            }
+        }
+    }
+
+    /// Read from the authoritative store, shadow-comparing against the other store during dual-write phases
+    pub async fn read<T, O, N>(&self, old: O, new: N) -> Result<T>
+    where
+        T: PartialEq,
+        O: Future<Output = Result<T>>,
+        N: Future<Output = Result<T>>,
+    {
+        self.read_with(old, new, |a, b| a == b).await
+    }
+
+    /// Like `read`, with a custom equivalence for types that carry store-specific fields
+    pub async fn read_with<T, O, N, E>(&self, old: O, new: N, same: E) -> Result<T>
+    where
+        O: Future<Output = Result<T>>,
+        N: Future<Output = Result<T>>,
+        E: Fn(&T, &T) -> bool,
+    {
+        let phase = self.phase();
+        if !phase.shadow_reads() {
+            return if phase.reads_new() { new.await } else { old.await };
+        }
+
+        let (old_result, new_result) = tokio::join!(old, new);
+        let (primary, shadow, shadow_store) = if phase.reads_new() {
+            (new_result, old_result, "old")
+        } else {
+            (old_result, new_result, "new")
+        };
+
+        match (primary, shadow) {
+            (Ok(primary), Ok(shadow)) => {
+                self.count(&self.metrics.reads_compared, "read_compared");
+                if !same(&primary, &shadow) {
+                    self.count(&self.metrics.read_mismatches, "read_mismatch");
+                    // Values aren't logged: sessions and codes carry emails and secrets
+                    warn!(migration = %self.name, shadow_store = %shadow_store, "Dual-read mismatch between stores");
+                }
+                Ok(primary)
+            }
+            (Ok(primary), Err(e)) => {
+                self.count(&self.metrics.shadow_read_failures, "shadow_read_failure");
+                warn!(migration = %self.name, shadow_store = %shadow_store, error = %e, "Shadow read failed");
+                Ok(primary)
+            }
+            (Err(e), Ok(shadow)) if phase.reads_new() => {
+                // The old store is still complete while dual-writing, so it can cover new-store outages
+                self.count(&self.metrics.fallback_reads, "fallback_read");
+                warn!(migration = %self.name, error = %e, "New store read failed, falling back to old store");
+                Ok(shadow)
+            }
+            (Err(e), _) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use std::collections::HashMap;
+    use std::sync::atomic::AtomicUsize;
+
+    fn migration(flags: &[&str]) -> DualWrite {
+        let values = flags
+            .iter()
+            .map(|flag| (format!("test-store-{}", flag), true))
+            .collect::<HashMap<_, _>>();
+        let feature_flags = FeatureFlags::default();
+        feature_flags.replace(values);
+        DualWrite::new("test-store", Arc::new(feature_flags))
+    }
+
+    #[test]
+    fn test_phase_from_flags() {
+        assert_eq!(migration(&[]).phase(), MigrationPhase::OldOnly);
+        assert_eq!(migration(&["write-new"]).phase(), MigrationPhase::DualWriteReadOld);
+        assert_eq!(migration(&["read-new"]).phase(), MigrationPhase::DualWriteReadNew);
+        assert_eq!(
+            migration(&["write-new", "read-new", "retire-old"]).phase(),
+            MigrationPhase::NewOnly
+        );
+        // Retiring the old store before reading from the new one is ignored
+        assert_eq!(migration(&["retire-old"]).phase(), MigrationPhase::OldOnly);
+    }
+
+    #[tokio::test]
+    async fn test_old_only_never_touches_new_store() {
+        let calls = AtomicUsize::new(0);
+        let migration = migration(&[]);
+
+        let value = migration
+            .write(async { Ok(1) }, async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Ok(2)
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(value, 1);
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        assert_eq!(migration.metrics().shadow_writes, 0);
+    }
+
+    #[tokio::test]
+    async fn test_shadow_write_failure_is_counted_not_returned() {
+        let migration = migration(&["write-new"]);
+
+        let value = migration
+            .write(async { Ok("old") }, async { Err(anyhow!("new store down")) })
+            .await
+            .unwrap();
+
+        assert_eq!(value, "old");
+        let metrics = migration.metrics();
+        assert_eq!(metrics.shadow_writes, 1);
+        assert_eq!(metrics.shadow_write_failures, 1);
+    }
+
+    #[tokio::test]
+    async fn test_required_shadow_write_failure_is_returned() {
+        let migration = migration(&["write-new"]);
+
+        let result = migration
+            .write_both(async { Ok(()) }, async { Err(anyhow!("new store down")) })
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(migration.metrics().shadow_write_failures, 1);
+    }
+
+    #[tokio::test]
+    async fn test_authoritative_write_failure_skips_shadow() {
+        let migration = migration(&["read-new"]);
+
+        let result: Result<()> = migration
+            .write(async { Ok(()) }, async { Err(anyhow!("new store down")) })
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(migration.metrics().shadow_writes, 0);
+    }
+
+    #[tokio::test]
+    async fn test_dual_read_records_mismatches() {
+        let migration = migration(&["write-new"]);
+
+        assert_eq!(migration.read(async { Ok(1) }, async { Ok(1) }).await.unwrap(), 1);
+        assert_eq!(migration.read(async { Ok(1) }, async { Ok(2) }).await.unwrap(), 1);
+
+        let metrics = migration.metrics();
+        assert_eq!(metrics.reads_compared, 2);
+        assert_eq!(metrics.read_mismatches, 1);
+        assert!((metrics.match_rate() - 0.5).abs() < f64::EPSILON);
+    }
+
+    #[tokio::test]
+    async fn test_read_new_falls_back_to_old_store() {
+        let migration = migration(&["read-new"]);
+
+        let value = migration
+            .read(async { Ok(7) }, async { Err(anyhow!("new store down")) })
+            .await
+            .unwrap();
+
+        assert_eq!(value, 7);
+        assert_eq!(migration.metrics().fallback_reads, 1);
+    }
+}
@@ -0,0 +1,553 @@
+use crate::adapter::email_templates::EmailTemplateContent;
+use crate::adapter::ses::TemplateData;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use tracing::{info, instrument};
+use uuid::Uuid;
+
+/// Merge variables every recipient has: their address, and their name when they are a user
+pub const USER_MERGE_KEYS: [&str; 2] = ["email", "user_name"];
+
+/// Who a campaign is sent to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmailCampaignAudience {
+    /// Every user
+    AllUsers,
+    /// Users opted in to the weekly summary email
+    WeeklyDigest,
+    /// Addresses given with the campaign, each with its own merge variables
+    Recipients,
+}
+
+impl EmailCampaignAudience {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmailCampaignAudience::AllUsers => "all_users",
+            EmailCampaignAudience::WeeklyDigest => "weekly_digest",
+            EmailCampaignAudience::Recipients => "recipients",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "all_users" => Some(EmailCampaignAudience::AllUsers),
+            "weekly_digest" => Some(EmailCampaignAudience::WeeklyDigest),
+            "recipients" => Some(EmailCampaignAudience::Recipients),
+            _ => None,
+        }
+    }
+}
+
+/// Progress of a campaign
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmailCampaignStatus {
+    /// Waiting for its send time
+    Queued,
+    /// Some recipients are being sent or waiting
+    Sending,
+    /// Every recipient was sent or failed
+    Completed,
+    /// Stopped before every recipient was sent
+    Cancelled,
+}
+
+impl EmailCampaignStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmailCampaignStatus::Queued => "queued",
+            EmailCampaignStatus::Sending => "sending",
+            EmailCampaignStatus::Completed => "completed",
+            EmailCampaignStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "queued" => Some(EmailCampaignStatus::Queued),
+            "sending" => Some(EmailCampaignStatus::Sending),
+            "completed" => Some(EmailCampaignStatus::Completed),
+            "cancelled" => Some(EmailCampaignStatus::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+/// Persisted campaign with its recipient counts
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct EmailCampaign {
+    pub id: Uuid,
+    pub name: String,
+    pub subject: String,
+    pub html: String,
+    pub text: String,
+    pub audience: String,
+    pub status: String,
+    pub total_recipients: i32,
+    pub sent_count: i32,
+    pub failed_count: i32,
+    pub created_by: Option<Uuid>,
+    pub send_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl EmailCampaign {
+    pub fn audience(&self) -> Option<EmailCampaignAudience> {
+        EmailCampaignAudience::parse(&self.audience)
+    }
+
+    pub fn status(&self) -> Option<EmailCampaignStatus> {
+        EmailCampaignStatus::parse(&self.status)
+    }
+
+    pub fn content(&self) -> EmailTemplateContent {
+        EmailTemplateContent {
+            subject: self.subject.clone(),
+            html: self.html.clone(),
+            text: self.text.clone(),
+            version: None,
+        }
+    }
+}
+
+/// Address given with a campaign and the values filled into its placeholders
+#[derive(Debug, Clone)]
+pub struct NewCampaignRecipient {
+    pub email: String,
+    pub data: HashMap<String, String>,
+}
+
+/// Merge variables the recipients of `audience` all have, which are the placeholders a campaign
+/// may use
+pub fn merge_keys(audience: EmailCampaignAudience, recipients: &[NewCampaignRecipient]) -> Vec<String> {
+    match audience {
+        EmailCampaignAudience::AllUsers | EmailCampaignAudience::WeeklyDigest => {
+            USER_MERGE_KEYS.iter().map(|key| key.to_string()).collect()
+        }
+        EmailCampaignAudience::Recipients => {
+            let Some((first, rest)) = recipients.split_first() else {
+                return vec!["email".to_string()];
+            };
+            let mut keys: Vec<String> = first
+                .data
+                .keys()
+                .filter(|key| rest.iter().all(|r| r.data.contains_key(*key)))
+                .cloned()
+                .chain(std::iter::once("email".to_string()))
+                .collect();
+            keys.sort();
+            keys.dedup();
+            keys
+        }
+    }
+}
+
+/// Recipient claimed for sending
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ClaimedRecipient {
+    pub id: i64,
+    pub campaign_id: Uuid,
+    pub email: String,
+    pub merge_data: serde_json::Value,
+    pub attempts: i32,
+}
+
+impl ClaimedRecipient {
+    /// Merge variables, with the address as `email`; non-string values are skipped
+    pub fn data(&self) -> TemplateData {
+        let mut data = TemplateData::new();
+        if let Some(values) = self.merge_data.as_object() {
+            for (key, value) in values {
+                if let Some(value) = value.as_str() {
+                    data.insert(key.as_str(), value);
+                }
+            }
+        }
+        data.insert("email", self.email.as_str());
+        data
+    }
+}
+
+/// Campaigns and their recipients
+#[derive(Debug, Clone)]
+pub struct EmailCampaignRepository {
+    pool: PgPool,
+}
+
+impl EmailCampaignRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Create a campaign sent from `send_at` on to `audience`; `recipients` are its addresses when
+    /// the audience is [`EmailCampaignAudience::Recipients`]. Users are selected now, so later
+    /// sign-ups aren't sent the campaign; duplicate addresses are sent once and addresses that
+    /// unsubscribed from marketing emails are left out.
+    #[instrument(skip(self, content, recipients), fields(audience = audience.as_str(), recipient_count = recipients.len()))]
+    pub async fn create(
+        &self,
+        name: &str,
+        content: &EmailTemplateContent,
+        audience: EmailCampaignAudience,
+        recipients: &[NewCampaignRecipient],
+        send_at: DateTime<Utc>,
+        created_by: Uuid,
+    ) -> Result<EmailCampaign> {
+        let mut tx = self.pool.begin().await?;
+        let campaign_id: Uuid = sqlx::query_scalar(
+            r#"
+            INSERT INTO email_campaigns (name, subject, html, text, audience, send_at, created_by)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING id
+            "#,
+        )
+        .bind(name)
+        .bind(&content.subject)
+        .bind(&content.html)
+        .bind(&content.text)
+        .bind(audience.as_str())
+        .bind(send_at)
+        .bind(created_by)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        match audience {
+            EmailCampaignAudience::AllUsers | EmailCampaignAudience::WeeklyDigest => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO email_campaign_recipients (campaign_id, email, merge_data)
+                    SELECT $1, u.email, jsonb_build_object('user_name', u.name)
+                    FROM users u
+                    LEFT JOIN email_preferences p ON p.email = LOWER(u.email)
+                    WHERE ($2 OR u.weekly_digest_enabled) AND COALESCE(p.marketing, TRUE)
+                    ORDER BY u.id
+                    ON CONFLICT (campaign_id, email) DO NOTHING
+                    "#,
+                )
+                .bind(campaign_id)
+                .bind(audience == EmailCampaignAudience::AllUsers)
+                .execute(&mut *tx)
+                .await?;
+            }
+            EmailCampaignAudience::Recipients => {
+                let rows: Vec<serde_json::Value> = recipients
+                    .iter()
+                    .map(|r| json!({ "email": r.email, "data": r.data }))
+                    .collect();
+                sqlx::query(
+                    r#"
+                    INSERT INTO email_campaign_recipients (campaign_id, email, merge_data)
+                    SELECT $1, r.value->>'email', COALESCE(r.value->'data', '{}')
+                    FROM jsonb_array_elements($2) WITH ORDINALITY AS r(value, ordinal)
+                    LEFT JOIN email_preferences p ON p.email = LOWER(r.value->>'email')
+                    WHERE COALESCE(p.marketing, TRUE)
+                    ORDER BY r.ordinal
+                    ON CONFLICT (campaign_id, email) DO NOTHING
+                    "#,
+                )
+                .bind(campaign_id)
+                .bind(serde_json::Value::Array(rows))
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+
+        let campaign = sqlx::query_as::<_, EmailCampaign>(
+            r#"
+            UPDATE email_campaigns
+            SET total_recipients = (SELECT COUNT(*) FROM email_campaign_recipients WHERE campaign_id = $1)
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(campaign_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        info!(
+            campaign_id = %campaign.id,
+            audience = audience.as_str(),
+            total_recipients = campaign.total_recipients,
+            "Email campaign created"
+        );
+        Ok(campaign)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn find(&self, id: Uuid) -> Result<Option<EmailCampaign>> {
+        let campaign = sqlx::query_as::<_, EmailCampaign>("SELECT * FROM email_campaigns WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(campaign)
+    }
+
+    /// Campaigns by ID, in no particular order
+    #[instrument(skip(self, ids), fields(campaign_count = ids.len()))]
+    pub async fn find_many(&self, ids: &[Uuid]) -> Result<Vec<EmailCampaign>> {
+        let campaigns = sqlx::query_as::<_, EmailCampaign>("SELECT * FROM email_campaigns WHERE id = ANY($1)")
+            .bind(ids)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(campaigns)
+    }
+
+    /// Stop a queued or sending campaign; recipients already claimed may still be sent. Returns
+    /// whether it was stopped.
+    #[instrument(skip(self))]
+    pub async fn cancel(&self, id: Uuid) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query(
+            r#"
+            UPDATE email_campaigns
+            SET status = 'cancelled', completed_at = NOW(), updated_at = NOW()
+            WHERE id = $1 AND status IN ('queued', 'sending')
+            "#,
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            r#"
+            UPDATE email_campaign_recipients
+            SET status = 'cancelled', updated_at = NOW()
+            WHERE campaign_id = $1 AND status = 'pending'
+            "#,
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        if result.rows_affected() > 0 {
+            info!(campaign_id = %id, "Email campaign cancelled");
+        }
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Claim up to `limit` pending recipients of campaigns due to be sent, oldest first, along with
+    /// recipients whose claim is older than `lease_seconds`. Concurrent senders claim disjoint
+    /// recipients.
+    #[instrument(skip(self))]
+    pub async fn claim(&self, limit: i64, lease_seconds: i64, max_attempts: i32) -> Result<Vec<ClaimedRecipient>> {
+        let recipients = sqlx::query_as::<_, ClaimedRecipient>(
+            r#"
+            WITH claimed AS (
+                UPDATE email_campaign_recipients
+                SET status = 'sending', attempts = attempts + 1, claimed_at = NOW(), updated_at = NOW()
+                WHERE id IN (
+                    SELECT r.id FROM email_campaign_recipients r
+                    JOIN email_campaigns c ON c.id = r.campaign_id
+                    WHERE c.status IN ('queued', 'sending')
+                      AND c.send_at <= NOW()
+                      AND r.attempts < $3
+                      AND (r.status = 'pending'
+                           OR (r.status = 'sending' AND r.claimed_at < NOW() - make_interval(secs => $2)))
+                    ORDER BY r.id
+                    LIMIT $1
+                    FOR UPDATE OF r SKIP LOCKED
+                )
+                RETURNING id, campaign_id, email, merge_data, attempts
+            ),
+            started AS (
+                UPDATE email_campaigns
+                SET status = 'sending', started_at = NOW(), updated_at = NOW()
+                WHERE id IN (SELECT campaign_id FROM claimed) AND status = 'queued'
+            )
+            SELECT id, campaign_id, email, merge_data, attempts
+            FROM claimed
+            ORDER BY id
+            "#,
+        )
+        .bind(limit)
+        .bind(lease_seconds as f64)
+        .bind(max_attempts)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(recipients)
+    }
+
+    /// Fail recipients whose last claim expired after their final attempt, such as when a sender
+    /// crashed; returns the campaigns they belong to
+    #[instrument(skip(self))]
+    pub async fn fail_abandoned(&self, lease_seconds: i64, max_attempts: i32) -> Result<Vec<Uuid>> {
+        let campaign_ids: Vec<Uuid> = sqlx::query_scalar(
+            r#"
+            UPDATE email_campaign_recipients
+            SET status = 'failed', error = 'Send did not finish', updated_at = NOW()
+            WHERE status = 'sending' AND attempts >= $2 AND claimed_at < NOW() - make_interval(secs => $1)
+            RETURNING campaign_id
+            "#,
+        )
+        .bind(lease_seconds as f64)
+        .bind(max_attempts)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(campaign_ids)
+    }
+
+    /// Record the send and clear the merge variables
+    #[instrument(skip(self))]
+    pub async fn mark_sent(&self, id: i64, message_id: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE email_campaign_recipients
+            SET status = 'sent', message_id = $2, merge_data = '{}', error = NULL, updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(message_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Put a recipient back to be claimed by a later run
+    #[instrument(skip(self))]
+    pub async fn retry(&self, id: i64, error: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE email_campaign_recipients
+            SET status = 'pending', error = $2, claimed_at = NULL, updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(error)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Give up on a recipient
+    #[instrument(skip(self))]
+    pub async fn fail(&self, id: i64, error: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE email_campaign_recipients
+            SET status = 'failed', error = $2, claimed_at = NULL, updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(error)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Recount the recipients of campaigns and complete sending ones with nothing left to send
+    #[instrument(skip(self, campaign_ids), fields(campaign_count = campaign_ids.len()))]
+    pub async fn refresh(&self, campaign_ids: &[Uuid]) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            UPDATE email_campaigns b
+            SET sent_count = c.sent,
+                failed_count = c.failed,
+                status = CASE WHEN c.pending = 0 AND b.status = 'sending' THEN 'completed' ELSE b.status END,
+                completed_at = CASE
+                    WHEN c.pending = 0 AND b.status = 'sending' THEN COALESCE(b.completed_at, NOW())
+                    ELSE b.completed_at
+                END,
+                updated_at = NOW()
+            FROM (
+                SELECT campaign_id,
+                       COUNT(*) FILTER (WHERE status = 'sent') AS sent,
+                       COUNT(*) FILTER (WHERE status = 'failed') AS failed,
+                       COUNT(*) FILTER (WHERE status IN ('pending', 'sending')) AS pending
+                FROM email_campaign_recipients
+                WHERE campaign_id = ANY($1)
+                GROUP BY campaign_id
+            ) c
+            WHERE b.id = c.campaign_id
+            "#,
+        )
+        .bind(campaign_ids)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recipient(email: &str, keys: &[&str]) -> NewCampaignRecipient {
+        NewCampaignRecipient {
+            email: email.to_string(),
+            data: keys.iter().map(|key| (key.to_string(), "value".to_string())).collect(),
+        }
+    }
+
+    #[test]
+    fn test_status_round_trip() {
+        for audience in [
+            EmailCampaignAudience::AllUsers,
+            EmailCampaignAudience::WeeklyDigest,
+            EmailCampaignAudience::Recipients,
+        ] {
+            assert_eq!(EmailCampaignAudience::parse(audience.as_str()), Some(audience));
+        }
+        for status in [
+            EmailCampaignStatus::Queued,
+            EmailCampaignStatus::Sending,
+            EmailCampaignStatus::Completed,
+            EmailCampaignStatus::Cancelled,
+        ] {
+            assert_eq!(EmailCampaignStatus::parse(status.as_str()), Some(status));
+        }
+        assert_eq!(EmailCampaignAudience::parse("everyone"), None);
+    }
+
+    #[test]
+    fn test_merge_keys() {
+        assert_eq!(
+            merge_keys(EmailCampaignAudience::AllUsers, &[]),
+            vec!["email".to_string(), "user_name".to_string()]
+        );
+        let recipients = [
+            recipient("ana@example.com", &["first_name", "plan"]),
+            recipient("bo@example.com", &["plan", "first_name", "city"]),
+            recipient("cy@example.com", &["plan", "email"]),
+        ];
+        assert_eq!(
+            merge_keys(EmailCampaignAudience::Recipients, &recipients),
+            vec!["email".to_string(), "plan".to_string()]
+        );
+        assert_eq!(
+            merge_keys(EmailCampaignAudience::Recipients, &[]),
+            vec!["email".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_recipient_data_includes_address() {
+        let claimed = ClaimedRecipient {
+            id: 1,
+            campaign_id: Uuid::nil(),
+            email: "ana@example.com".to_string(),
+            merge_data: json!({ "plan": "Pro", "email": "other@example.com", "count": 3 }),
+            attempts: 1,
+        };
+        let data = claimed.data();
+        assert_eq!(data.get("plan").map(String::as_str), Some("Pro"));
+        assert_eq!(data.get("email").map(String::as_str), Some("ana@example.com"));
+        assert_eq!(data.get("count"), None);
+    }
+}
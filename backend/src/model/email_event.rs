@@ -0,0 +1,257 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use tracing::instrument;
+
+/// Kind of event SES publishes for a sent email
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EmailEventType {
+    /// SES accepted the email
+    Send,
+    /// The recipient's mail server accepted it
+    Delivery,
+    /// Delivery is being retried
+    DeliveryDelay,
+    Open,
+    Click,
+    Bounce,
+    /// The recipient marked it as spam
+    Complaint,
+    /// SES refused to send it, e.g. because it contained a virus
+    Reject,
+    /// A template placeholder couldn't be rendered
+    RenderingFailure,
+    /// The recipient changed their subscription through an SES list header
+    Subscription,
+}
+
+impl EmailEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmailEventType::Send => "send",
+            EmailEventType::Delivery => "delivery",
+            EmailEventType::DeliveryDelay => "delivery_delay",
+            EmailEventType::Open => "open",
+            EmailEventType::Click => "click",
+            EmailEventType::Bounce => "bounce",
+            EmailEventType::Complaint => "complaint",
+            EmailEventType::Reject => "reject",
+            EmailEventType::RenderingFailure => "rendering_failure",
+            EmailEventType::Subscription => "subscription",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "send" => Some(EmailEventType::Send),
+            "delivery" => Some(EmailEventType::Delivery),
+            "delivery_delay" => Some(EmailEventType::DeliveryDelay),
+            "open" => Some(EmailEventType::Open),
+            "click" => Some(EmailEventType::Click),
+            "bounce" => Some(EmailEventType::Bounce),
+            "complaint" => Some(EmailEventType::Complaint),
+            "reject" => Some(EmailEventType::Reject),
+            "rendering_failure" => Some(EmailEventType::RenderingFailure),
+            "subscription" => Some(EmailEventType::Subscription),
+            _ => None,
+        }
+    }
+
+    /// Type of an SES `eventType` (or legacy `notificationType`), e.g. "Rendering Failure"
+    pub fn from_ses(value: &str) -> Option<Self> {
+        match value {
+            "Send" => Some(EmailEventType::Send),
+            "Delivery" => Some(EmailEventType::Delivery),
+            "DeliveryDelay" => Some(EmailEventType::DeliveryDelay),
+            "Open" => Some(EmailEventType::Open),
+            "Click" => Some(EmailEventType::Click),
+            "Bounce" => Some(EmailEventType::Bounce),
+            "Complaint" => Some(EmailEventType::Complaint),
+            "Reject" => Some(EmailEventType::Reject),
+            "Rendering Failure" => Some(EmailEventType::RenderingFailure),
+            "Subscription" => Some(EmailEventType::Subscription),
+            _ => None,
+        }
+    }
+
+    /// Key of the object holding the event's details in an SES event
+    pub fn ses_detail_key(&self) -> &'static str {
+        match self {
+            EmailEventType::Send => "send",
+            EmailEventType::Delivery => "delivery",
+            EmailEventType::DeliveryDelay => "deliveryDelay",
+            EmailEventType::Open => "open",
+            EmailEventType::Click => "click",
+            EmailEventType::Bounce => "bounce",
+            EmailEventType::Complaint => "complaint",
+            EmailEventType::Reject => "reject",
+            EmailEventType::RenderingFailure => "failure",
+            EmailEventType::Subscription => "subscription",
+        }
+    }
+
+    /// Delivery status a message has after this event, and how far along it is; later events
+    /// don't undo a failure or complaint
+    fn status(&self) -> Option<(u8, &'static str)> {
+        match self {
+            EmailEventType::Subscription => None,
+            EmailEventType::Send => Some((1, "sent")),
+            EmailEventType::DeliveryDelay => Some((2, "delayed")),
+            EmailEventType::Delivery => Some((3, "delivered")),
+            EmailEventType::Open => Some((4, "opened")),
+            EmailEventType::Click => Some((5, "clicked")),
+            EmailEventType::Bounce => Some((6, "bounced")),
+            EmailEventType::Complaint => Some((7, "complained")),
+            EmailEventType::Reject => Some((8, "rejected")),
+            EmailEventType::RenderingFailure => Some((8, "failed")),
+        }
+    }
+}
+
+/// Event for a sent email
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct EmailEvent {
+    pub id: i64,
+    /// SNS notification ID the event arrived in
+    pub event_id: String,
+    /// SES message ID of the email
+    pub message_id: String,
+    pub event_type: String,
+    /// Addresses the event is about
+    pub recipients: Vec<String>,
+    /// Event-specific details from SES, such as the bounce type or clicked link
+    pub detail: serde_json::Value,
+    pub occurred_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl EmailEvent {
+    pub fn event_type(&self) -> Option<EmailEventType> {
+        EmailEventType::parse(&self.event_type)
+    }
+}
+
+/// Event to record
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewEmailEvent {
+    pub event_id: String,
+    pub message_id: String,
+    pub event_type: EmailEventType,
+    pub recipients: Vec<String>,
+    pub detail: serde_json::Value,
+    pub occurred_at: DateTime<Utc>,
+}
+
+/// Delivery status of a message given its events: "unknown" without any, otherwise the furthest
+/// it got, e.g. "delivered", "opened" or "bounced"
+pub fn delivery_status(events: &[EmailEvent]) -> &'static str {
+    events
+        .iter()
+        .filter_map(|event| event.event_type()?.status())
+        .max_by_key(|(rank, _)| *rank)
+        .map_or("unknown", |(_, status)| status)
+}
+
+/// Events SES published for sent emails
+#[derive(Debug, Clone)]
+pub struct EmailEventRepository {
+    pool: PgPool,
+}
+
+impl EmailEventRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Store an event; returns false when it was already stored
+    #[instrument(skip(self, event), fields(message_id = %event.message_id, event_type = event.event_type.as_str()))]
+    pub async fn record(&self, event: &NewEmailEvent) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO email_events (event_id, message_id, event_type, recipients, detail, occurred_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (event_id) DO NOTHING
+            "#,
+        )
+        .bind(&event.event_id)
+        .bind(&event.message_id)
+        .bind(event.event_type.as_str())
+        .bind(&event.recipients)
+        .bind(&event.detail)
+        .bind(event.occurred_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Events of a message, oldest first
+    #[instrument(skip(self))]
+    pub async fn for_message(&self, message_id: &str) -> Result<Vec<EmailEvent>> {
+        let events = sqlx::query_as::<_, EmailEvent>(
+            "SELECT * FROM email_events WHERE message_id = $1 ORDER BY occurred_at, id",
+        )
+        .bind(message_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(event_type: EmailEventType) -> EmailEvent {
+        EmailEvent {
+            id: 1,
+            event_id: "sns-1".to_string(),
+            message_id: "msg-1".to_string(),
+            event_type: event_type.as_str().to_string(),
+            recipients: vec!["ana@example.com".to_string()],
+            detail: serde_json::json!({}),
+            occurred_at: Utc::now(),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_type_round_trip() {
+        for ses in [
+            "Send",
+            "Delivery",
+            "DeliveryDelay",
+            "Open",
+            "Click",
+            "Bounce",
+            "Complaint",
+            "Reject",
+            "Rendering Failure",
+            "Subscription",
+        ] {
+            let event_type = EmailEventType::from_ses(ses).unwrap();
+            assert_eq!(EmailEventType::parse(event_type.as_str()), Some(event_type));
+        }
+        assert_eq!(EmailEventType::from_ses("delivery"), None);
+    }
+
+    #[test]
+    fn test_delivery_status() {
+        assert_eq!(delivery_status(&[]), "unknown");
+        let events = [
+            event(EmailEventType::Send),
+            event(EmailEventType::Open),
+            event(EmailEventType::Delivery),
+        ];
+        assert_eq!(delivery_status(&events), "opened");
+        let events = [
+            event(EmailEventType::Send),
+            event(EmailEventType::Delivery),
+            event(EmailEventType::Complaint),
+            event(EmailEventType::Click),
+        ];
+        assert_eq!(delivery_status(&events), "complained");
+        assert_eq!(delivery_status(&[event(EmailEventType::Subscription)]), "unknown");
+    }
+}
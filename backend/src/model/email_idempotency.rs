@@ -0,0 +1,138 @@
+use crate::adapter::email_idempotency::{EmailIdempotency, IdempotencyClaim};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use deadpool_redis::Pool;
+use redis::AsyncCommands;
+use sha2::{Digest, Sha256};
+use tracing::{debug, instrument};
+
+/// Sent emails are remembered for a week by default, longer than any queue retries
+pub const DEFAULT_TTL_SECONDS: u64 = 7 * 24 * 60 * 60;
+/// A claim whose send never finished, e.g. because the process died, lapses after this long
+pub const DEFAULT_CLAIM_SECONDS: u64 = 5 * 60;
+/// Value of a key claimed for a send that hasn't finished
+const IN_PROGRESS: &str = "sending";
+/// Prefix of the value of a key whose email was sent, followed by the message ID
+const SENT_PREFIX: &str = "sent:";
+
+/// Redis key of an idempotency key; hashed, as keys may contain email addresses
+fn redis_key(key: &str) -> String {
+    format!("email_sent:{:x}", Sha256::digest(key.as_bytes()))
+}
+
+/// Claim state stored under a key
+fn parse_claim(value: &str) -> IdempotencyClaim {
+    match value.strip_prefix(SENT_PREFIX) {
+        Some(message_id) => IdempotencyClaim::Sent(message_id.to_string()),
+        None => IdempotencyClaim::InProgress,
+    }
+}
+
+/// Redis store of email idempotency keys
+#[derive(Clone)]
+pub struct EmailIdempotencyCache {
+    redis_pool: Pool,
+    ttl_seconds: u64,
+    claim_seconds: u64,
+}
+
+impl EmailIdempotencyCache {
+    pub fn new(redis_url: &str, ttl_seconds: u64, claim_seconds: u64) -> Result<Self> {
+        let cfg = deadpool_redis::Config::from_url(redis_url);
+        let redis_pool = cfg
+            .create_pool(Some(deadpool_redis::Runtime::Tokio1))
+            .context("Failed to create Redis connection pool")?;
+
+        Ok(Self {
+            redis_pool,
+            ttl_seconds: ttl_seconds.max(1),
+            claim_seconds: claim_seconds.max(1),
+        })
+    }
+}
+
+#[async_trait]
+impl EmailIdempotency for EmailIdempotencyCache {
+    #[instrument(skip(self, key))]
+    async fn claim(&self, key: &str) -> Result<IdempotencyClaim> {
+        let mut conn = self
+            .redis_pool
+            .get()
+            .await
+            .context("Failed to get Redis connection from pool")?;
+        let key = redis_key(key);
+
+        // A claim can lapse between the SET and the GET, so try once more before giving up
+        for _ in 0..2 {
+            let claimed: Option<String> = redis::cmd("SET")
+                .arg(&key)
+                .arg(IN_PROGRESS)
+                .arg("NX")
+                .arg("EX")
+                .arg(self.claim_seconds)
+                .query_async(&mut conn)
+                .await
+                .context("Failed to claim email idempotency key")?;
+            if claimed.is_some() {
+                return Ok(IdempotencyClaim::Claimed);
+            }
+            let value: Option<String> = conn.get(&key).await.context("Failed to read email idempotency key")?;
+            if let Some(value) = value {
+                return Ok(parse_claim(&value));
+            }
+        }
+        Ok(IdempotencyClaim::InProgress)
+    }
+
+    #[instrument(skip(self, key))]
+    async fn complete(&self, key: &str, message_id: &str) -> Result<()> {
+        let mut conn = self
+            .redis_pool
+            .get()
+            .await
+            .context("Failed to get Redis connection from pool")?;
+        conn.set_ex::<_, _, ()>(
+            redis_key(key),
+            format!("{}{}", SENT_PREFIX, message_id),
+            self.ttl_seconds,
+        )
+        .await
+        .context("Failed to record sent email")?;
+
+        debug!(ttl_seconds = self.ttl_seconds, "Recorded email idempotency key");
+        Ok(())
+    }
+
+    #[instrument(skip(self, key))]
+    async fn release(&self, key: &str) -> Result<()> {
+        let mut conn = self
+            .redis_pool
+            .get()
+            .await
+            .context("Failed to get Redis connection from pool")?;
+        conn.del::<_, ()>(redis_key(key))
+            .await
+            .context("Failed to release email idempotency key")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redis_key_hides_address() {
+        let key = redis_key("otp:ana@example.com:1700000000");
+        assert!(key.starts_with("email_sent:"));
+        assert!(!key.contains("ana@example.com"));
+        assert_eq!(key, redis_key("otp:ana@example.com:1700000000"));
+        assert_ne!(key, redis_key("otp:ana@example.com:1700000001"));
+    }
+
+    #[test]
+    fn test_parse_claim() {
+        assert_eq!(parse_claim("sent:msg-1"), IdempotencyClaim::Sent("msg-1".to_string()));
+        assert_eq!(parse_claim(IN_PROGRESS), IdempotencyClaim::InProgress);
+    }
+}
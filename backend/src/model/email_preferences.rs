@@ -0,0 +1,141 @@
+use crate::adapter::email_preferences::{EmailCategory, EmailPreferenceSource};
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use tracing::{info, instrument};
+
+/// Kinds of email an address receives
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct EmailPreferences {
+    pub email: String,
+    pub transactional: bool,
+    pub digest: bool,
+    pub marketing: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl EmailPreferences {
+    /// Preferences of an address that never changed them
+    pub fn defaults(email: &str) -> Self {
+        Self {
+            email: normalize(email),
+            transactional: true,
+            digest: true,
+            marketing: true,
+            updated_at: Utc::now(),
+        }
+    }
+
+    pub fn allows(&self, category: EmailCategory) -> bool {
+        match category {
+            EmailCategory::Critical => true,
+            EmailCategory::Transactional => self.transactional,
+            EmailCategory::Digest => self.digest,
+            EmailCategory::Marketing => self.marketing,
+        }
+    }
+}
+
+/// Addresses are matched case-insensitively
+fn normalize(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// Opt-outs by email address
+#[derive(Debug, Clone)]
+pub struct EmailPreferencesRepository {
+    pool: PgPool,
+}
+
+impl EmailPreferencesRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Preferences of `email`, with every kind allowed if it never changed them
+    #[instrument(skip(self, email))]
+    pub async fn find(&self, email: &str) -> Result<EmailPreferences> {
+        let preferences = sqlx::query_as::<_, EmailPreferences>("SELECT * FROM email_preferences WHERE email = $1")
+            .bind(normalize(email))
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(preferences.unwrap_or_else(|| EmailPreferences::defaults(email)))
+    }
+
+    /// Turn a kind of email on or off for `email`. Turning the digest off also turns off the weekly
+    /// summary of the user with that address, and the other way round.
+    #[instrument(skip(self, email), fields(category = category.as_str()))]
+    pub async fn set(&self, email: &str, category: EmailCategory, enabled: bool) -> Result<EmailPreferences> {
+        let email = normalize(email);
+        let mut tx = self.pool.begin().await?;
+        let preferences = sqlx::query_as::<_, EmailPreferences>(
+            r#"
+            INSERT INTO email_preferences (email, transactional, digest, marketing)
+            VALUES (
+                $1,
+                CASE WHEN $2 = 'transactional' THEN $3 ELSE TRUE END,
+                CASE WHEN $2 = 'digest' THEN $3 ELSE TRUE END,
+                CASE WHEN $2 = 'marketing' THEN $3 ELSE TRUE END
+            )
+            ON CONFLICT (email) DO UPDATE SET
+                transactional = CASE WHEN $2 = 'transactional' THEN $3 ELSE email_preferences.transactional END,
+                digest = CASE WHEN $2 = 'digest' THEN $3 ELSE email_preferences.digest END,
+                marketing = CASE WHEN $2 = 'marketing' THEN $3 ELSE email_preferences.marketing END,
+                updated_at = NOW()
+            RETURNING *
+            "#,
+        )
+        .bind(&email)
+        .bind(category.as_str())
+        .bind(enabled)
+        .fetch_one(&mut *tx)
+        .await?;
+        if category == EmailCategory::Digest {
+            sqlx::query(
+                r#"
+                UPDATE users SET weekly_digest_enabled = $2, updated_at = NOW()
+                WHERE LOWER(email) = $1 AND weekly_digest_enabled <> $2
+                "#,
+            )
+            .bind(&email)
+            .bind(enabled)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        info!(category = category.as_str(), enabled, "Email preference updated");
+        Ok(preferences)
+    }
+}
+
+#[async_trait]
+impl EmailPreferenceSource for EmailPreferencesRepository {
+    async fn allows(&self, email: &str, category: EmailCategory) -> Result<bool> {
+        if !category.is_optional() {
+            return Ok(true);
+        }
+        Ok(self.find(email).await?.allows(category))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allows() {
+        let mut preferences = EmailPreferences::defaults(" Ana@Example.com ");
+        assert_eq!(preferences.email, "ana@example.com");
+        assert!(EmailCategory::OPTIONAL.iter().all(|c| preferences.allows(*c)));
+
+        preferences.marketing = false;
+        preferences.transactional = false;
+        assert!(!preferences.allows(EmailCategory::Marketing));
+        assert!(!preferences.allows(EmailCategory::Transactional));
+        assert!(preferences.allows(EmailCategory::Digest));
+        assert!(preferences.allows(EmailCategory::Critical));
+    }
+}
@@ -0,0 +1,547 @@
+use crate::adapter::email_templates::{EmailLocale, EmailTemplateName};
+use crate::adapter::ses::TemplateData;
+use crate::adapter::sqs::SqsQueue;
+use anyhow::Result;
+use chrono::{DateTime, Days, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::time::Duration;
+use tracing::{info, instrument, warn};
+use uuid::Uuid;
+
+/// Wait before the first retry; doubled after each further failure
+const BASE_RETRY_DELAY: Duration = Duration::from_secs(30);
+/// Longest wait between attempts
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(3600);
+
+/// Delivery state of a queued email
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmailQueueStatus {
+    /// Waiting for its next attempt
+    Pending,
+    /// Claimed by a worker
+    Sending,
+    /// Accepted by SES
+    Sent,
+    /// Out of attempts or expired; kept for inspection and not retried
+    Dead,
+}
+
+impl EmailQueueStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmailQueueStatus::Pending => "pending",
+            EmailQueueStatus::Sending => "sending",
+            EmailQueueStatus::Sent => "sent",
+            EmailQueueStatus::Dead => "dead",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(EmailQueueStatus::Pending),
+            "sending" => Some(EmailQueueStatus::Sending),
+            "sent" => Some(EmailQueueStatus::Sent),
+            "dead" => Some(EmailQueueStatus::Dead),
+            _ => None,
+        }
+    }
+}
+
+/// Wait before retrying an email that failed `attempts` times
+pub fn retry_delay(attempts: i32) -> Duration {
+    let doublings = attempts.saturating_sub(1).clamp(0, 16) as u32;
+    BASE_RETRY_DELAY.saturating_mul(1 << doublings).min(MAX_RETRY_DELAY)
+}
+
+/// First time after `after` that it is `hour`:00 in `time_zone`. An hour skipped by a daylight
+/// saving change moves to the hour after it.
+pub fn next_local_time(time_zone: Tz, hour: u32, after: DateTime<Utc>) -> DateTime<Utc> {
+    let today = after.with_timezone(&time_zone).date_naive();
+    (0..3)
+        .filter_map(|days| today.checked_add_days(Days::new(days)))
+        .filter_map(|date| {
+            let local = date.and_hms_opt(hour.min(23), 0, 0)?;
+            time_zone
+                .from_local_datetime(&local)
+                .earliest()
+                .or_else(|| time_zone.from_local_datetime(&(local + chrono::Duration::hours(1))).earliest())
+        })
+        .map(|at| at.with_timezone(&Utc))
+        .find(|at| *at > after)
+        .unwrap_or(after)
+}
+
+/// Email waiting in, or sent from, the queue
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct QueuedEmail {
+    pub id: Uuid,
+    pub template: String,
+    pub recipient: String,
+    pub template_data: serde_json::Value,
+    /// Language tag the email is rendered in
+    pub locale: String,
+    pub status: String,
+    /// Earliest time the email may be sent
+    pub send_at: DateTime<Utc>,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub claimed_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub message_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub sent_at: Option<DateTime<Utc>>,
+}
+
+impl QueuedEmail {
+    pub fn status(&self) -> Option<EmailQueueStatus> {
+        EmailQueueStatus::parse(&self.status)
+    }
+
+    /// Key the email is sent under, so a send whose result wasn't recorded isn't repeated when
+    /// its lease expires
+    pub fn idempotency_key(&self) -> String {
+        format!("email_queue:{}", self.id)
+    }
+
+    pub fn template_name(&self) -> Option<EmailTemplateName> {
+        EmailTemplateName::parse(&self.template)
+    }
+
+    pub fn locale(&self) -> EmailLocale {
+        EmailLocale::parse(&self.locale)
+    }
+
+    /// Placeholder values; non-string values are skipped
+    pub fn data(&self) -> TemplateData {
+        let mut data = TemplateData::new();
+        if let Some(values) = self.template_data.as_object() {
+            for (key, value) in values {
+                if let Some(value) = value.as_str() {
+                    data.insert(key.as_str(), value);
+                }
+            }
+        }
+        data
+    }
+
+    /// Whether the email is no longer worth sending
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// Emails waiting to be sent, to tell whether the worker keeps up
+#[derive(Debug, Clone, Default, sqlx::FromRow)]
+pub struct EmailQueueBacklog {
+    /// Pending emails whose next attempt is due
+    pub due: i64,
+    /// Pending emails due later
+    pub scheduled: i64,
+    /// Emails claimed by a worker
+    pub sending: i64,
+    /// When the longest-waiting due email became due
+    pub oldest_due_at: Option<DateTime<Utc>>,
+}
+
+impl EmailQueueBacklog {
+    /// How long the longest-waiting due email has waited
+    pub fn oldest_due_age(&self, now: DateTime<Utc>) -> Option<chrono::Duration> {
+        self.oldest_due_at.map(|at| (now - at).max(chrono::Duration::zero()))
+    }
+}
+
+/// Queue message announcing a newly queued email, so a worker can send it without waiting for the
+/// next scheduled run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmailQueued {
+    pub email_id: Uuid,
+}
+
+/// Outbox of emails for the background worker
+#[derive(Debug, Clone)]
+pub struct EmailQueueRepository {
+    pool: PgPool,
+    /// Queue each newly queued email is announced on
+    notifications: Option<SqsQueue>,
+}
+
+impl EmailQueueRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            notifications: None,
+        }
+    }
+
+    /// Announce each queued email on `queue`. The table stays the source of truth: an email whose
+    /// announcement is lost is still sent by the scheduled worker.
+    pub fn with_notifications(mut self, queue: SqsQueue) -> Self {
+        self.notifications = Some(queue);
+        self
+    }
+
+    /// Announce a queued email, delayed until it is due (SQS delays at most 15 minutes; later
+    /// emails are announced early and left for the scheduled worker if still not due)
+    async fn announce(&self, email: &QueuedEmail) {
+        let Some(queue) = &self.notifications else {
+            return;
+        };
+        let delay = (email.send_at - Utc::now()).to_std().unwrap_or_default();
+        let announced = match serde_json::to_string(&EmailQueued { email_id: email.id }) {
+            Ok(body) => queue.send(&body, delay).await.map(|_| ()),
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = announced {
+            warn!(email_id = %email.id, error = ?e, "Failed to announce queued email");
+        }
+    }
+
+    /// Queue the template `name` in `locale` for `recipient`; it isn't sent after `expires_at`
+    pub async fn enqueue(
+        &self,
+        recipient: &str,
+        name: EmailTemplateName,
+        locale: EmailLocale,
+        data: &TemplateData,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<QueuedEmail> {
+        self.schedule(recipient, name, locale, data, Utc::now(), expires_at).await
+    }
+
+    /// Queue the template `name` in `locale` for `recipient`, to be sent from `send_at` on; it
+    /// isn't sent after `expires_at`
+    #[instrument(skip(self, recipient, data), fields(template = name.as_str(), locale = locale.as_str()))]
+    pub async fn schedule(
+        &self,
+        recipient: &str,
+        name: EmailTemplateName,
+        locale: EmailLocale,
+        data: &TemplateData,
+        send_at: DateTime<Utc>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<QueuedEmail> {
+        let email = sqlx::query_as::<_, QueuedEmail>(
+            r#"
+            INSERT INTO email_queue (template, recipient, locale, template_data, send_at, next_attempt_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $5, $6)
+            RETURNING *
+            "#,
+        )
+        .bind(name.as_str())
+        .bind(recipient)
+        .bind(locale.as_str())
+        .bind(serde_json::to_value(data.values())?)
+        .bind(send_at)
+        .bind(expires_at)
+        .fetch_one(&self.pool)
+        .await?;
+
+        info!(email_id = %email.id, template = name.as_str(), send_at = %email.send_at, "Email queued");
+        self.announce(&email).await;
+        Ok(email)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn find(&self, id: Uuid) -> Result<Option<QueuedEmail>> {
+        let email = sqlx::query_as::<_, QueuedEmail>("SELECT * FROM email_queue WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(email)
+    }
+
+    /// Emails queued for `recipient`, newest first
+    #[instrument(skip(self, recipient))]
+    pub async fn list_by_recipient(&self, recipient: &str, limit: i64) -> Result<Vec<QueuedEmail>> {
+        let emails = sqlx::query_as::<_, QueuedEmail>(
+            "SELECT * FROM email_queue WHERE lower(recipient) = lower($1) ORDER BY created_at DESC LIMIT $2",
+        )
+        .bind(recipient)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(emails)
+    }
+
+    /// Drop an email that hasn't been attempted yet; returns whether it was dropped
+    #[instrument(skip(self))]
+    pub async fn cancel(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM email_queue WHERE id = $1 AND status = 'pending' AND attempts = 0")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() > 0 {
+            info!(email_id = %id, "Queued email cancelled");
+        }
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Claim up to `limit` due emails, oldest first, along with emails whose claim is older than
+    /// `lease_seconds`. Concurrent workers claim disjoint emails.
+    #[instrument(skip(self))]
+    pub async fn claim(&self, limit: i64, lease_seconds: i64, max_attempts: i32) -> Result<Vec<QueuedEmail>> {
+        let emails = sqlx::query_as::<_, QueuedEmail>(
+            r#"
+            UPDATE email_queue
+            SET status = 'sending', attempts = attempts + 1, claimed_at = NOW(), updated_at = NOW()
+            WHERE id IN (
+                SELECT id FROM email_queue
+                WHERE attempts < $3
+                  AND ((status = 'pending' AND next_attempt_at <= NOW())
+                       OR (status = 'sending' AND claimed_at < NOW() - make_interval(secs => $2)))
+                ORDER BY next_attempt_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+        )
+        .bind(limit)
+        .bind(lease_seconds as f64)
+        .bind(max_attempts)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(emails)
+    }
+
+    /// Claim one email if it is due and not claimed by another worker, or its claim is older than
+    /// `lease_seconds`
+    #[instrument(skip(self))]
+    pub async fn claim_by_id(&self, id: Uuid, lease_seconds: i64, max_attempts: i32) -> Result<Option<QueuedEmail>> {
+        let email = sqlx::query_as::<_, QueuedEmail>(
+            r#"
+            UPDATE email_queue
+            SET status = 'sending', attempts = attempts + 1, claimed_at = NOW(), updated_at = NOW()
+            WHERE id = (
+                SELECT id FROM email_queue
+                WHERE id = $1
+                  AND attempts < $3
+                  AND ((status = 'pending' AND next_attempt_at <= NOW())
+                       OR (status = 'sending' AND claimed_at < NOW() - make_interval(secs => $2)))
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(lease_seconds as f64)
+        .bind(max_attempts)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(email)
+    }
+
+    /// Emails waiting to be sent
+    #[instrument(skip(self))]
+    pub async fn backlog(&self) -> Result<EmailQueueBacklog> {
+        let backlog = sqlx::query_as::<_, EmailQueueBacklog>(
+            r#"
+            SELECT
+                COUNT(*) FILTER (WHERE status = 'pending' AND next_attempt_at <= NOW()) AS due,
+                COUNT(*) FILTER (WHERE status = 'pending' AND next_attempt_at > NOW()) AS scheduled,
+                COUNT(*) FILTER (WHERE status = 'sending') AS sending,
+                MIN(next_attempt_at) FILTER (WHERE status = 'pending' AND next_attempt_at <= NOW()) AS oldest_due_at
+            FROM email_queue
+            WHERE status IN ('pending', 'sending')
+            "#,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(backlog)
+    }
+
+    /// Move emails whose last claim expired after their final attempt, such as when a worker
+    /// crashed mid-send, to the dead-letter state
+    #[instrument(skip(self))]
+    pub async fn bury_abandoned(&self, lease_seconds: i64, max_attempts: i32) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            UPDATE email_queue
+            SET status = 'dead', last_error = COALESCE(last_error, 'Send did not finish'), template_data = '{}',
+                updated_at = NOW()
+            WHERE status = 'sending' AND attempts >= $2 AND claimed_at < NOW() - make_interval(secs => $1)
+            "#,
+        )
+        .bind(lease_seconds as f64)
+        .bind(max_attempts)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Move emails that expired while waiting for their next attempt to the dead-letter state,
+    /// clearing their placeholder values
+    #[instrument(skip(self))]
+    pub async fn bury_expired(&self) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            UPDATE email_queue
+            SET status = 'dead', last_error = 'Expired before it could be sent', template_data = '{}',
+                updated_at = NOW()
+            WHERE status = 'pending' AND expires_at <= NOW()
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Record the send and clear the placeholder values
+    #[instrument(skip(self))]
+    pub async fn mark_sent(&self, id: Uuid, message_id: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE email_queue
+            SET status = 'sent', message_id = $2, template_data = '{}', last_error = NULL,
+                sent_at = NOW(), updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(message_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Put an email back in the queue until `next_attempt_at`
+    #[instrument(skip(self))]
+    pub async fn retry(&self, id: Uuid, error: &str, next_attempt_at: DateTime<Utc>) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE email_queue
+            SET status = 'pending', last_error = $2, next_attempt_at = $3, claimed_at = NULL, updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(error)
+        .bind(next_attempt_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Give up on an email, clearing its placeholder values
+    #[instrument(skip(self))]
+    pub async fn bury(&self, id: Uuid, error: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE email_queue
+            SET status = 'dead', last_error = $2, template_data = '{}', claimed_at = NULL, updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(error)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn email(template_data: serde_json::Value, expires_at: Option<DateTime<Utc>>) -> QueuedEmail {
+        let now = Utc::now();
+        QueuedEmail {
+            id: Uuid::nil(),
+            template: "otp_login".to_string(),
+            recipient: "ana@example.com".to_string(),
+            template_data,
+            locale: "es".to_string(),
+            status: "pending".to_string(),
+            send_at: now,
+            attempts: 0,
+            next_attempt_at: now,
+            expires_at,
+            claimed_at: None,
+            last_error: None,
+            message_id: None,
+            created_at: now,
+            updated_at: now,
+            sent_at: None,
+        }
+    }
+
+    #[test]
+    fn test_status_round_trip() {
+        for status in [
+            EmailQueueStatus::Pending,
+            EmailQueueStatus::Sending,
+            EmailQueueStatus::Sent,
+            EmailQueueStatus::Dead,
+        ] {
+            assert_eq!(EmailQueueStatus::parse(status.as_str()), Some(status));
+        }
+        assert_eq!(EmailQueueStatus::parse("failed"), None);
+    }
+
+    #[test]
+    fn test_retry_delay_backs_off_to_cap() {
+        assert_eq!(retry_delay(1), Duration::from_secs(30));
+        assert_eq!(retry_delay(2), Duration::from_secs(60));
+        assert_eq!(retry_delay(4), Duration::from_secs(240));
+        assert_eq!(retry_delay(8), MAX_RETRY_DELAY);
+        assert_eq!(retry_delay(i32::MAX), MAX_RETRY_DELAY);
+        assert_eq!(retry_delay(0), BASE_RETRY_DELAY);
+    }
+
+    #[test]
+    fn test_data_and_expiry() {
+        let now = Utc::now();
+        let queued = email(json!({ "otp_id": "6f1c", "count": 3 }), Some(now));
+        assert_eq!(queued.template_name(), Some(EmailTemplateName::OtpLogin));
+        assert_eq!(queued.locale(), EmailLocale::Spanish);
+        let data = queued.data();
+        assert_eq!(data.get("otp_id").map(String::as_str), Some("6f1c"));
+        assert_eq!(data.get("count"), None);
+        assert!(queued.is_expired(now));
+        assert!(!email(json!({}), None).is_expired(now));
+    }
+
+    #[test]
+    fn test_backlog_oldest_due_age() {
+        let now = Utc::now();
+        assert_eq!(EmailQueueBacklog::default().oldest_due_age(now), None);
+        let backlog = EmailQueueBacklog {
+            due: 3,
+            oldest_due_at: Some(now - chrono::Duration::minutes(5)),
+            ..Default::default()
+        };
+        assert_eq!(backlog.oldest_due_age(now), Some(chrono::Duration::minutes(5)));
+    }
+
+    #[test]
+    fn test_next_local_time() {
+        let at = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+
+        // 09:00 in New York is still ahead that day
+        let next = next_local_time(chrono_tz::America::New_York, 9, at("2024-03-04T12:00:00Z"));
+        assert_eq!(next, at("2024-03-04T14:00:00Z"));
+        // Already past 09:00 in Tokyo, so the next morning
+        let next = next_local_time(chrono_tz::Asia::Tokyo, 9, at("2024-03-04T12:00:00Z"));
+        assert_eq!(next, at("2024-03-05T00:00:00Z"));
+        // 02:00 doesn't exist on the day clocks go forward
+        let next = next_local_time(chrono_tz::America::New_York, 2, at("2024-03-10T05:00:00Z"));
+        assert_eq!(next, at("2024-03-10T07:00:00Z"));
+    }
+}
@@ -0,0 +1,151 @@
+use crate::adapter::email_templates::{EmailTemplateContent, EmailTemplateName, EmailTemplateSource};
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use tracing::{info, instrument};
+use uuid::Uuid;
+
+/// Stored version of an email template
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct EmailTemplate {
+    pub id: Uuid,
+    pub name: String,
+    pub version: i32,
+    pub subject: String,
+    pub html: String,
+    pub text: String,
+    pub active: bool,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub activated_at: Option<DateTime<Utc>>,
+}
+
+impl EmailTemplate {
+    pub fn content(&self) -> EmailTemplateContent {
+        EmailTemplateContent {
+            subject: self.subject.clone(),
+            html: self.html.clone(),
+            text: self.text.clone(),
+            version: Some(self.version),
+        }
+    }
+}
+
+/// Versions of the email templates; new versions are inactive until activated
+#[derive(Debug, Clone)]
+pub struct EmailTemplateRepository {
+    pool: PgPool,
+}
+
+impl EmailTemplateRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Versions of a template, newest first
+    #[instrument(skip(self), fields(name = name.as_str()))]
+    pub async fn list(&self, name: EmailTemplateName) -> Result<Vec<EmailTemplate>> {
+        let templates =
+            sqlx::query_as::<_, EmailTemplate>("SELECT * FROM email_templates WHERE name = $1 ORDER BY version DESC")
+                .bind(name.as_str())
+                .fetch_all(&self.pool)
+                .await?;
+
+        Ok(templates)
+    }
+
+    #[instrument(skip(self), fields(name = name.as_str()))]
+    pub async fn find(&self, name: EmailTemplateName, version: i32) -> Result<Option<EmailTemplate>> {
+        let template =
+            sqlx::query_as::<_, EmailTemplate>("SELECT * FROM email_templates WHERE name = $1 AND version = $2")
+                .bind(name.as_str())
+                .bind(version)
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(template)
+    }
+
+    #[instrument(skip(self), fields(name = name.as_str()))]
+    pub async fn find_active(&self, name: EmailTemplateName) -> Result<Option<EmailTemplate>> {
+        let template = sqlx::query_as::<_, EmailTemplate>("SELECT * FROM email_templates WHERE name = $1 AND active")
+            .bind(name.as_str())
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(template)
+    }
+
+    /// Store `content` as the template's next version
+    #[instrument(skip(self, content), fields(name = name.as_str()))]
+    pub async fn create(
+        &self,
+        name: EmailTemplateName,
+        content: &EmailTemplateContent,
+        created_by: Uuid,
+    ) -> Result<EmailTemplate> {
+        let template = sqlx::query_as::<_, EmailTemplate>(
+            r#"
+            INSERT INTO email_templates (name, version, subject, html, text, created_by)
+            SELECT $1, COALESCE(MAX(version), 0) + 1, $2, $3, $4, $5
+            FROM email_templates
+            WHERE name = $1
+            RETURNING *
+            "#,
+        )
+        .bind(name.as_str())
+        .bind(&content.subject)
+        .bind(&content.html)
+        .bind(&content.text)
+        .bind(created_by)
+        .fetch_one(&self.pool)
+        .await?;
+
+        info!(
+            name = name.as_str(),
+            version = template.version,
+            "Email template version created"
+        );
+        Ok(template)
+    }
+
+    /// Make `version` the one that is sent; returns `None` when it doesn't exist
+    #[instrument(skip(self), fields(name = name.as_str()))]
+    pub async fn activate(&self, name: EmailTemplateName, version: i32) -> Result<Option<EmailTemplate>> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("UPDATE email_templates SET active = FALSE WHERE name = $1 AND active AND version <> $2")
+            .bind(name.as_str())
+            .bind(version)
+            .execute(&mut *tx)
+            .await?;
+        let template = sqlx::query_as::<_, EmailTemplate>(
+            r#"
+            UPDATE email_templates
+            SET active = TRUE, activated_at = CASE WHEN active THEN activated_at ELSE NOW() END
+            WHERE name = $1 AND version = $2
+            RETURNING *
+            "#,
+        )
+        .bind(name.as_str())
+        .bind(version)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if template.is_none() {
+            // Leave the current version active
+            tx.rollback().await?;
+            return Ok(None);
+        }
+        tx.commit().await?;
+
+        info!(name = name.as_str(), version, "Email template version activated");
+        Ok(template)
+    }
+}
+
+#[async_trait]
+impl EmailTemplateSource for EmailTemplateRepository {
+    async fn active_template(&self, name: EmailTemplateName) -> Result<Option<EmailTemplateContent>> {
+        Ok(self.find_active(name).await?.map(|template| template.content()))
+    }
+}
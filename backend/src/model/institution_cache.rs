@@ -0,0 +1,80 @@
+use crate::adapter::plaid::Institution;
+use anyhow::{Context, Result};
+use deadpool_redis::Pool;
+use redis::AsyncCommands;
+use std::collections::HashMap;
+use tracing::{debug, instrument, warn};
+
+/// Institution metadata rarely changes, so entries live for a week by default
+pub const DEFAULT_TTL_SECONDS: u64 = 7 * 24 * 60 * 60;
+
+/// Redis cache of Plaid institution metadata, shared by all users
+#[derive(Clone)]
+pub struct InstitutionCache {
+    redis_pool: Pool,
+    ttl_seconds: u64,
+}
+
+fn institution_key(institution_id: &str) -> String {
+    format!("institution:{}", institution_id)
+}
+
+impl InstitutionCache {
+    pub fn new(redis_url: &str, ttl_seconds: u64) -> Result<Self> {
+        let cfg = deadpool_redis::Config::from_url(redis_url);
+        let redis_pool = cfg
+            .create_pool(Some(deadpool_redis::Runtime::Tokio1))
+            .context("Failed to create Redis connection pool")?;
+
+        Ok(Self {
+            redis_pool,
+            ttl_seconds: ttl_seconds.max(1),
+        })
+    }
+
+    /// Cached institutions among `institution_ids`; misses are absent from the map
+    #[instrument(skip(self), fields(institution_count = institution_ids.len()))]
+    pub async fn get_many(&self, institution_ids: &[String]) -> Result<HashMap<String, Institution>> {
+        if institution_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let mut conn = self.redis_pool.get().await
+            .context("Failed to get Redis connection from pool")?;
+
+        let keys: Vec<String> = institution_ids.iter().map(|id| institution_key(id)).collect();
+        let values: Vec<Option<String>> = redis::cmd("MGET")
+            .arg(&keys)
+            .query_async(&mut conn)
+            .await
+            .context("Failed to read cached institutions")?;
+
+        let mut institutions = HashMap::new();
+        for (institution_id, value) in institution_ids.iter().zip(values) {
+            let Some(value) = value else { continue };
+            match serde_json::from_str::<Institution>(&value) {
+                Ok(institution) => {
+                    institutions.insert(institution_id.clone(), institution);
+                }
+                // Treated as a miss so the entry is refetched and overwritten
+                Err(e) => warn!(institution_id = %institution_id, error = %e, "Discarding unreadable cached institution"),
+            }
+        }
+
+        debug!(hits = institutions.len(), "Read cached institutions");
+        Ok(institutions)
+    }
+
+    #[instrument(skip(self, institution), fields(institution_id = %institution.institution_id))]
+    pub async fn put(&self, institution: &Institution) -> Result<()> {
+        let mut conn = self.redis_pool.get().await
+            .context("Failed to get Redis connection from pool")?;
+
+        let data = serde_json::to_string(institution).context("Failed to serialize institution")?;
+        conn.set_ex::<_, _, ()>(institution_key(&institution.institution_id), data, self.ttl_seconds).await
+            .context("Failed to cache institution")?;
+
+        debug!(ttl_seconds = self.ttl_seconds, "Cached institution");
+        Ok(())
+    }
+}
@@ -0,0 +1,151 @@
+use crate::adapter::plaid::{Liability, LiabilityApr, LiabilityKind};
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::types::Json;
+use sqlx::PgPool;
+use tracing::{debug, info, instrument};
+use uuid::Uuid;
+
+/// Persisted liability joined with the balance of its account
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct StoredLiability {
+    pub id: Uuid,
+    pub account_id: String,
+    pub item_id: String,
+    pub user_id: Uuid,
+    pub liability_type: String,
+    pub aprs: Json<Vec<LiabilityApr>>,
+    pub interest_rate_percentage: Option<f64>,
+    pub is_overdue: Option<bool>,
+    pub last_payment_amount: Option<f64>,
+    pub last_payment_date: Option<NaiveDate>,
+    pub next_payment_due_date: Option<NaiveDate>,
+    pub minimum_payment_amount: Option<f64>,
+    pub last_statement_balance: Option<f64>,
+    pub last_statement_issue_date: Option<NaiveDate>,
+    pub origination_date: Option<NaiveDate>,
+    pub origination_principal_amount: Option<f64>,
+    pub maturity_date: Option<NaiveDate>,
+    pub details: Json<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub account_name: String,
+    pub current_balance: Option<f64>,
+    pub iso_currency_code: Option<String>,
+}
+
+impl StoredLiability {
+    pub fn kind(&self) -> Option<LiabilityKind> {
+        LiabilityKind::parse(&self.liability_type)
+    }
+}
+
+/// Liability repository for database operations
+#[derive(Debug, Clone)]
+pub struct LiabilityRepository {
+    pool: PgPool,
+}
+
+impl LiabilityRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Insert or refresh the liabilities of an item's accounts in a single transaction.
+    ///
+    /// Liabilities whose account is not stored yet are skipped; they are picked up on the
+    /// next refresh after the account sync.
+    #[instrument(skip(self, liabilities), fields(liability_count = liabilities.len()))]
+    pub async fn upsert_liabilities(
+        &self,
+        user_id: Uuid,
+        item_id: &str,
+        liabilities: &[Liability],
+    ) -> Result<usize> {
+        debug!(user_id = %user_id, item_id = %item_id, "Upserting liabilities");
+
+        let mut tx = self.pool.begin().await?;
+        let mut stored = 0;
+
+        for liability in liabilities {
+            let result = sqlx::query(
+                r#"
+                INSERT INTO liabilities (
+                    account_id, item_id, user_id, liability_type, aprs, interest_rate_percentage,
+                    is_overdue, last_payment_amount, last_payment_date, next_payment_due_date,
+                    minimum_payment_amount, last_statement_balance, last_statement_issue_date,
+                    origination_date, origination_principal_amount, maturity_date, details
+                )
+                SELECT $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17
+                WHERE EXISTS (
+                    SELECT 1 FROM bank_accounts WHERE account_id = $1 AND user_id = $3
+                )
+                ON CONFLICT (account_id) DO UPDATE SET
+                    liability_type = EXCLUDED.liability_type,
+                    aprs = EXCLUDED.aprs,
+                    interest_rate_percentage = EXCLUDED.interest_rate_percentage,
+                    is_overdue = EXCLUDED.is_overdue,
+                    last_payment_amount = EXCLUDED.last_payment_amount,
+                    last_payment_date = EXCLUDED.last_payment_date,
+                    next_payment_due_date = EXCLUDED.next_payment_due_date,
+                    minimum_payment_amount = EXCLUDED.minimum_payment_amount,
+                    last_statement_balance = EXCLUDED.last_statement_balance,
+                    last_statement_issue_date = EXCLUDED.last_statement_issue_date,
+                    origination_date = EXCLUDED.origination_date,
+                    origination_principal_amount = EXCLUDED.origination_principal_amount,
+                    maturity_date = EXCLUDED.maturity_date,
+                    details = EXCLUDED.details,
+                    updated_at = NOW()
+                WHERE liabilities.user_id = EXCLUDED.user_id
+                "#,
+            )
+            .bind(&liability.account_id)
+            .bind(item_id)
+            .bind(user_id)
+            .bind(liability.kind.as_str())
+            .bind(Json(&liability.aprs))
+            .bind(liability.interest_rate_percentage)
+            .bind(liability.is_overdue)
+            .bind(liability.last_payment_amount)
+            .bind(liability.last_payment_date)
+            .bind(liability.next_payment_due_date)
+            .bind(liability.minimum_payment_amount)
+            .bind(liability.last_statement_balance)
+            .bind(liability.last_statement_issue_date)
+            .bind(liability.origination_date)
+            .bind(liability.origination_principal_amount)
+            .bind(liability.maturity_date)
+            .bind(Json(&liability.details))
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("Failed to upsert liability for account {}", liability.account_id))?;
+
+            stored += result.rows_affected() as usize;
+        }
+
+        tx.commit().await?;
+
+        info!(user_id = %user_id, item_id = %item_id, stored, "Successfully stored liabilities");
+        Ok(stored)
+    }
+
+    /// List a user's liabilities on items that have not been removed, soonest payment first
+    #[instrument(skip(self))]
+    pub async fn list_by_user(&self, user_id: Uuid) -> Result<Vec<StoredLiability>> {
+        let liabilities = sqlx::query_as::<_, StoredLiability>(
+            r#"
+            SELECT l.*, a.name AS account_name, a.current_balance, a.iso_currency_code
+            FROM liabilities l
+            JOIN bank_accounts a ON a.account_id = l.account_id
+            JOIN plaid_items i ON i.item_id = l.item_id
+            WHERE l.user_id = $1 AND i.status <> 'removed'
+            ORDER BY l.next_payment_due_date NULLS LAST, a.name
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(liabilities)
+    }
+}
@@ -1,8 +1,90 @@
 pub mod greeting;
 pub mod user;
 pub mod auth;
+pub mod auth_session;
 pub mod otp;
+pub mod otp_cache;
+pub mod plaid_item;
+pub mod bank_account;
+pub mod db_pool;
+pub mod read_pool;
+pub mod dual_write;
+pub mod transaction;
+pub mod transaction_sync;
+pub mod pubsub;
+pub mod balance_cache;
+pub mod balance_history;
+pub mod institution_cache;
+pub mod liability;
+pub mod account_identity;
+pub mod transaction_category;
+pub mod transaction_embedding;
+pub mod net_worth;
+pub mod spending;
+pub mod audit_log;
+pub mod alert_rule;
+pub mod notification;
+pub mod bill;
+pub mod account_share;
+pub mod transaction_annotation;
+pub mod receipt;
+pub mod statement;
+pub mod tax_report;
+pub mod transfer;
+pub mod chat;
+pub mod ai_batch;
+pub mod ai_usage;
+pub mod ai_response_cache;
+pub mod ai_rate_limit;
+pub mod email_template;
+pub mod email_queue;
+pub mod email_campaign;
+pub mod email_preferences;
+pub mod email_event;
+pub mod email_idempotency;
+pub mod webhook;
 
 pub use user::{User, CreateUserRequest, UpdateUserRequest, UserRepository};
-pub use auth::{JwtManager, JwtConfig, SessionManager, TokenClaims, TokenPair, SessionInfo};
-pub use otp::{OtpCode, OtpRepository, OtpConfig, SendOtpRequest, VerifyOtpRequest, OtpVerificationResult};
\ No newline at end of file
+pub use auth::{JwtManager, JwtConfig, SessionManager, TokenClaims, TokenPair, SessionInfo, Scope, ClientType};
+pub use auth_session::AuthSessionRepository;
+pub use otp::{OtpCode, OtpRepository, OtpConfig, OtpStats, PendingOtp, SendOtpRequest, VerifyOtpRequest, OtpVerificationResult};
+pub use otp_cache::{OtpCache, OtpEvent};
+pub use plaid_item::{PlaidItem, PlaidItemRepository, PlaidItemStatus, CreatePlaidItemRequest};
+pub use bank_account::{StoredBankAccount, BankAccountRepository, AccountChange, SharedBankAccount};
+pub use db_pool::DbPoolConfig;
+pub use read_pool::ReadPool;
+pub use dual_write::{ConsistencySnapshot, DualWrite, MigrationPhase, OTP_STORE_MIGRATION, SESSION_STORE_MIGRATION};
+pub use transaction::{Transaction, TransactionFilter, TransactionRepository, SyncSummary};
+pub use transaction_sync::TransactionSyncer;
+pub use pubsub::{Topic, BalanceUpdate, BalanceUpdates};
+pub use balance_cache::{BalanceCache, CachedBalances, RefreshPermit};
+pub use institution_cache::InstitutionCache;
+pub use liability::{StoredLiability, LiabilityRepository};
+pub use account_identity::{StoredAccountIdentity, AccountIdentityRepository};
+pub use transaction_category::{TransactionCategory, TransactionCategoryRepository, CategoryAssignment, CategorySource, CATEGORY_TAXONOMY};
+pub use net_worth::{NetWorthSnapshot, NetWorthRepository, Granularity};
+pub use spending::{SpendingRepository, SpendingGroupBy, SpendingPeriod, SpendingTotal};
+pub use audit_log::{AuditEntry, AuditLogRepository};
+pub use alert_rule::{AlertRule, AlertRuleKind, AlertRuleRepository, NewAlertRule};
+pub use notification::{Notification, NotificationRepository, NewNotification};
+pub use bill::{Bill, BillCadence, BillRepository, DetectedBill};
+pub use balance_history::{BalanceHistoryRepository, BalanceSnapshot};
+pub use account_share::{AccountShare, AccountShareRepository, ShareAccess, ShareStatus};
+pub use transaction_annotation::{NewSplit, TransactionAnnotation, TransactionAnnotationRepository, TransactionAnnotations, TransactionSplit};
+pub use receipt::{NewReceipt, Receipt, ReceiptExtraction, ReceiptLineItem, ReceiptRepository, ReceiptStatus};
+pub use statement::{BankStatement, NewBankStatement, StatementRepository};
+pub use tax_report::{TaxLine, TaxReportRepository};
+pub use transfer::{NewTransfer, Transfer, TransferRepository, TransferStatus};
+pub use chat::{ChatConversation, ChatMessage, ChatReply, ChatRepository, ChatRole};
+pub use ai_batch::{AiBatch, AiBatchKind, AiBatchRepository, AiBatchStatus, AiBatchTask, AiTaskStatus};
+pub use ai_usage::{AiFeature, AiQuota, AiUsageRepository, AiUsageTotals, FeatureUsage, NewAiUsage};
+pub use ai_response_cache::{AiResponseCache, CacheMode};
+pub use transaction_embedding::{ScoredTransaction, SemanticSearch, TransactionEmbedder, TransactionEmbeddingRepository};
+pub use ai_rate_limit::{AiAdmission, AiLimit, AiPermit, AiRateLimiter, AiRateLimits};
+pub use email_template::{EmailTemplate, EmailTemplateRepository};
+pub use email_queue::{EmailQueueRepository, EmailQueueStatus, QueuedEmail};
+pub use email_preferences::{EmailPreferences, EmailPreferencesRepository};
+pub use email_event::{EmailEvent, EmailEventRepository, EmailEventType, NewEmailEvent};
+pub use email_idempotency::EmailIdempotencyCache;
+pub use email_campaign::{EmailCampaign, EmailCampaignAudience, EmailCampaignRepository, EmailCampaignStatus, NewCampaignRecipient};
+pub use webhook::{WebhookDelivery, WebhookDeliveryStatus, WebhookEndpoint, WebhookEventType, WebhookRepository, WebhookTarget};
\ No newline at end of file
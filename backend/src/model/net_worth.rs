@@ -0,0 +1,129 @@
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::PgPool;
+use tracing::{info, instrument};
+use uuid::Uuid;
+
+/// Plaid account types whose balance is owed rather than held
+pub const LIABILITY_ACCOUNT_TYPES: &[&str] = &["credit", "loan"];
+
+/// Net worth of one user in one currency on one day
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct NetWorthSnapshot {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub snapshot_date: NaiveDate,
+    /// ISO-4217 code, or Plaid's unofficial code for accounts without one
+    pub currency_code: String,
+    pub assets: f64,
+    pub liabilities: f64,
+    pub net_worth: f64,
+    pub account_count: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Bucket size for net worth history; each bucket reports its latest snapshot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Granularity {
+    Day,
+    Week,
+    Month,
+}
+
+impl Granularity {
+    /// Unit accepted by Postgres `date_trunc`
+    pub fn as_sql_unit(&self) -> &'static str {
+        match self {
+            Granularity::Day => "day",
+            Granularity::Week => "week",
+            Granularity::Month => "month",
+        }
+    }
+}
+
+/// Net worth snapshot repository for database operations
+#[derive(Debug, Clone)]
+pub struct NetWorthRepository {
+    pool: PgPool,
+}
+
+impl NetWorthRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Record every user's net worth for `snapshot_date` from the stored account balances.
+    ///
+    /// Runs as a single aggregate upsert, so re-running on the same day replaces that day's
+    /// snapshots. Accounts on removed items or without a balance or currency are ignored.
+    #[instrument(skip(self))]
+    pub async fn record_snapshots(&self, snapshot_date: NaiveDate) -> Result<usize> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO net_worth_snapshots (
+                user_id, snapshot_date, currency_code, assets, liabilities, net_worth, account_count
+            )
+            SELECT
+                a.user_id,
+                $1,
+                COALESCE(a.iso_currency_code, a.unofficial_currency_code) AS currency_code,
+                SUM(CASE WHEN a.account_type = ANY($2) THEN 0 ELSE a.current_balance END),
+                SUM(CASE WHEN a.account_type = ANY($2) THEN a.current_balance ELSE 0 END),
+                SUM(CASE WHEN a.account_type = ANY($2) THEN -a.current_balance ELSE a.current_balance END),
+                COUNT(*)
+            FROM bank_accounts a
+            JOIN plaid_items i ON i.item_id = a.item_id
+            WHERE i.status <> 'removed'
+              AND a.current_balance IS NOT NULL
+              AND COALESCE(a.iso_currency_code, a.unofficial_currency_code) IS NOT NULL
+            GROUP BY a.user_id, COALESCE(a.iso_currency_code, a.unofficial_currency_code)
+            ON CONFLICT (user_id, snapshot_date, currency_code) DO UPDATE SET
+                assets = EXCLUDED.assets,
+                liabilities = EXCLUDED.liabilities,
+                net_worth = EXCLUDED.net_worth,
+                account_count = EXCLUDED.account_count,
+                updated_at = NOW()
+            "#,
+        )
+        .bind(snapshot_date)
+        .bind(LIABILITY_ACCOUNT_TYPES)
+        .execute(&self.pool)
+        .await?;
+
+        let recorded = result.rows_affected() as usize;
+        info!(snapshot_date = %snapshot_date, recorded, "Recorded net worth snapshots");
+        Ok(recorded)
+    }
+
+    /// A user's snapshots between `start` and `end` (inclusive), oldest first, keeping the
+    /// latest snapshot per currency in each `granularity` bucket
+    #[instrument(skip(self))]
+    pub async fn history(
+        &self,
+        user_id: Uuid,
+        start: NaiveDate,
+        end: NaiveDate,
+        granularity: Granularity,
+    ) -> Result<Vec<NetWorthSnapshot>> {
+        let snapshots = sqlx::query_as::<_, NetWorthSnapshot>(
+            r#"
+            SELECT * FROM (
+                SELECT DISTINCT ON (date_trunc($4, snapshot_date), currency_code) *
+                FROM net_worth_snapshots
+                WHERE user_id = $1 AND snapshot_date BETWEEN $2 AND $3
+                ORDER BY date_trunc($4, snapshot_date), currency_code, snapshot_date DESC
+            ) latest
+            ORDER BY snapshot_date, currency_code
+            "#,
+        )
+        .bind(user_id)
+        .bind(start)
+        .bind(end)
+        .bind(granularity.as_sql_unit())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(snapshots)
+    }
+}
@@ -0,0 +1,106 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use tracing::{debug, instrument};
+use uuid::Uuid;
+
+/// Notification sent, or being sent, to a user
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Notification {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub rule_id: Option<Uuid>,
+    pub kind: String,
+    pub dedup_key: String,
+    pub channel: String,
+    pub subject: String,
+    pub body: String,
+    /// `pending`, `sent` or `failed`
+    pub status: String,
+    pub error: Option<String>,
+    pub sent_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Fields of a notification about to be sent
+#[derive(Debug, Clone)]
+pub struct NewNotification {
+    pub user_id: Uuid,
+    pub rule_id: Option<Uuid>,
+    pub kind: String,
+    /// Identifies the event; a second notification with the same key is never created
+    pub dedup_key: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Notification repository for database operations
+#[derive(Debug, Clone)]
+pub struct NotificationRepository {
+    pool: PgPool,
+}
+
+impl NotificationRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Record a pending notification, or return `None` if one with the same key already exists
+    #[instrument(skip(self, notification), fields(user_id = %notification.user_id, dedup_key = %notification.dedup_key))]
+    pub async fn claim(&self, notification: &NewNotification) -> Result<Option<Notification>> {
+        let claimed = sqlx::query_as::<_, Notification>(
+            r#"
+            INSERT INTO notifications (user_id, rule_id, kind, dedup_key, subject, body)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (user_id, dedup_key) DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(notification.user_id)
+        .bind(notification.rule_id)
+        .bind(&notification.kind)
+        .bind(&notification.dedup_key)
+        .bind(&notification.subject)
+        .bind(&notification.body)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        if claimed.is_none() {
+            debug!("Notification already recorded");
+        }
+        Ok(claimed)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn mark_sent(&self, id: Uuid) -> Result<()> {
+        sqlx::query("UPDATE notifications SET status = 'sent', error = NULL, sent_at = NOW() WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(skip(self, error))]
+    pub async fn mark_failed(&self, id: Uuid, error: &str) -> Result<()> {
+        sqlx::query("UPDATE notifications SET status = 'failed', error = $2 WHERE id = $1")
+            .bind(id)
+            .bind(error)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// A user's notifications, newest first
+    #[instrument(skip(self))]
+    pub async fn list_by_user(&self, user_id: Uuid, limit: i64) -> Result<Vec<Notification>> {
+        let notifications = sqlx::query_as::<_, Notification>(
+            "SELECT * FROM notifications WHERE user_id = $1 ORDER BY created_at DESC LIMIT $2"
+        )
+        .bind(user_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(notifications)
+    }
+}
@@ -1,3 +1,6 @@
+use crate::adapter::encryption::{EncryptedSecret, EnvelopeCipher};
+use crate::model::dual_write::DualWrite;
+use crate::model::otp_cache::{OtpCache, OtpEvent};
 use anyhow::{Context, Result};
 use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
 use argon2::password_hash::{rand_core::OsRng, SaltString};
@@ -5,6 +8,7 @@ use chrono::{DateTime, Duration, Utc};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
+use std::sync::Arc;
 use tracing::{debug, info, instrument, warn};
 use uuid::Uuid;
 
@@ -13,7 +17,6 @@ use uuid::Uuid;
 pub struct OtpCode {
     pub id: Uuid,
     pub email: String,
-    pub code: String,
     pub code_hash: String,
     pub created_at: DateTime<Utc>,
     pub expires_at: DateTime<Utc>,
@@ -23,6 +26,28 @@ pub struct OtpCode {
     pub user_id: Option<Uuid>,
 }
 
+/// A new code, hashed for verification and encrypted for the email delivering it, as written to each
+/// OTP store
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingOtp {
+    pub id: Uuid,
+    pub email: String,
+    pub code_hash: String,
+    pub encrypted_code: EncryptedSecret,
+    pub expires_at: DateTime<Utc>,
+    pub max_attempts: i32,
+    pub user_id: Option<Uuid>,
+}
+
+/// Encrypted code of an OTP still waiting to be used
+#[derive(sqlx::FromRow)]
+struct EncryptedCodeRow {
+    code_ciphertext: Vec<u8>,
+    code_nonce: Vec<u8>,
+    encrypted_data_key: Vec<u8>,
+    encryption_key_id: String,
+}
+
 /// Request to send OTP to email
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SendOtpRequest {
@@ -37,7 +62,7 @@ pub struct VerifyOtpRequest {
 }
 
 /// OTP verification result
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct OtpVerificationResult {
     pub success: bool,
     pub user_id: Option<Uuid>,
@@ -45,6 +70,28 @@ pub struct OtpVerificationResult {
     pub attempts_remaining: i32,
 }
 
+/// OTP activity over the last 24 hours
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, sqlx::FromRow)]
+pub struct OtpStats {
+    /// Codes sent
+    pub total_24h: i64,
+    /// Codes used to sign in
+    pub successful_24h: i64,
+    /// Codes locked after too many wrong guesses
+    pub failed_24h: i64,
+}
+
+impl OtpStats {
+    /// Percentage of sent codes used to sign in
+    pub fn success_rate_24h(&self) -> f64 {
+        if self.total_24h > 0 {
+            (self.successful_24h as f64 / self.total_24h as f64) * 100.0
+        } else {
+            0.0
+        }
+    }
+}
+
 /// Configuration for OTP functionality
 #[derive(Debug, Clone)]
 pub struct OtpConfig {
@@ -69,31 +116,40 @@ impl Default for OtpConfig {
     }
 }
 
-/// OTP repository for database operations
+/// OTP repository for database operations. Codes are stored hashed for verification and
+/// encrypted, until used or expired, for the email that delivers them.
 #[derive(Debug, Clone)]
 pub struct OtpRepository {
     pool: PgPool,
+    cipher: Arc<EnvelopeCipher>,
     config: OtpConfig,
     argon2: Argon2<'static>,
+    /// Redis store codes are moving to, and the phase of the move
+    migration: Option<(OtpCache, DualWrite)>,
 }
 
 impl OtpRepository {
-    pub fn new(pool: PgPool) -> Self {
-        Self {
-            pool,
-            config: OtpConfig::default(),
-            argon2: Argon2::default(),
-        }
+    pub fn new(pool: PgPool, cipher: Arc<EnvelopeCipher>) -> Self {
+        Self::with_config(pool, cipher, OtpConfig::default())
     }
 
-    pub fn with_config(pool: PgPool, config: OtpConfig) -> Self {
+    pub fn with_config(pool: PgPool, cipher: Arc<EnvelopeCipher>, config: OtpConfig) -> Self {
         Self {
             pool,
+            cipher,
             config,
             argon2: Argon2::default(),
+            migration: None,
         }
     }
 
+    /// Move codes to Redis in the phases `migration`'s flags select. Postgres stays the only store until
+    /// they are turned on.
+    pub fn with_migration(mut self, cache: OtpCache, migration: DualWrite) -> Self {
+        self.migration = Some((cache, migration));
+        self
+    }
+
     /// Generate a random OTP code
     fn generate_code(&self) -> String {
         let mut rng = rand::thread_rng();
@@ -151,27 +207,44 @@ impl OtpRepository {
         Ok(within_limit)
     }
 
-    /// Send OTP code (create and store in database)
+    async fn check_cached_rate_limit(&self, cache: &OtpCache, email: &str) -> Result<bool> {
+        Ok(cache.requests_in_last_hour(email).await? < self.config.max_requests_per_hour as i64)
+    }
+
+    /// Minutes a code stays valid
+    pub fn expires_minutes(&self) -> i64 {
+        self.config.expires_minutes
+    }
+
+    /// Create and store an OTP code; returns its id, from which `pending_code` reads the code
     #[instrument(skip(self), fields(email = %request.email))]
-    pub async fn send_otp(&self, request: SendOtpRequest) -> Result<String> {
+    pub async fn send_otp(&self, request: SendOtpRequest) -> Result<Uuid> {
         debug!("Sending OTP code to email");
 
         // Check rate limiting
-        if !self.check_rate_limit(&request.email).await? {
+        let within_limit = match &self.migration {
+            Some((cache, migration)) => {
+                migration
+                    .read(
+                        self.check_rate_limit(&request.email),
+                        self.check_cached_rate_limit(cache, &request.email),
+                    )
+                    .await?
+            }
+            None => self.check_rate_limit(&request.email).await?,
+        };
+        if !within_limit {
             return Err(anyhow::anyhow!("Rate limit exceeded. Too many OTP requests for this email."));
         }
 
-        // Invalidate any existing unused OTP codes for this email
-        sqlx::query(
-            "UPDATE otp_codes SET is_used = true WHERE email = $1 AND is_used = false AND expires_at > NOW()"
-        )
-        .bind(&request.email)
-        .execute(&self.pool)
-        .await?;
-
-        // Generate new OTP code
+        // Generate new OTP code; the id is the encryption context, so a code can't be moved to another row
+        let id = Uuid::new_v4();
         let code = self.generate_code();
         let code_hash = self.hash_code(&code)?;
+        let encrypted_code = self
+            .cipher
+            .encrypt(&code, &id.to_string())
+            .context("Failed to encrypt OTP code")?;
         let expires_at = Utc::now() + Duration::minutes(self.config.expires_minutes);
 
         // Check if user exists
@@ -182,22 +255,19 @@ impl OtpRepository {
         .fetch_optional(&self.pool)
         .await?;
 
-        // Store OTP in database
-        let otp = sqlx::query_as::<_, OtpCode>(
-            r#"
-            INSERT INTO otp_codes (email, code, code_hash, expires_at, max_attempts, user_id)
-            VALUES ($1, $2, $3, $4, $5, $6)
-            RETURNING *
-            "#,
-        )
-        .bind(&request.email)
-        .bind(&code)
-        .bind(&code_hash)
-        .bind(expires_at)
-        .bind(self.config.max_attempts)
-        .bind(user_id)
-        .fetch_one(&self.pool)
-        .await?;
+        let otp = PendingOtp {
+            id,
+            email: request.email.clone(),
+            code_hash,
+            encrypted_code,
+            expires_at,
+            max_attempts: self.config.max_attempts,
+            user_id,
+        };
+        match &self.migration {
+            Some((cache, migration)) => migration.write(self.insert_code(&otp), cache.insert(&otp)).await?,
+            None => self.insert_code(&otp).await?,
+        }
 
         info!(
             otp_id = %otp.id,
@@ -207,14 +277,114 @@ impl OtpRepository {
             "Successfully created OTP code"
         );
 
-        Ok(code)
+        Ok(otp.id)
     }
 
-    /// Verify OTP code
+    /// Store a new code in Postgres, replacing the address's unused ones
+    async fn insert_code(&self, otp: &PendingOtp) -> Result<()> {
+        // Invalidate any existing unused OTP codes for this email
+        sqlx::query(
+            r#"
+            UPDATE otp_codes
+            SET is_used = true, code_ciphertext = NULL, code_nonce = NULL, encrypted_data_key = NULL
+            WHERE email = $1 AND is_used = false AND expires_at > NOW()
+            "#,
+        )
+        .bind(&otp.email)
+        .execute(&self.pool)
+        .await?;
+
+        // Store OTP in database
+        sqlx::query(
+            r#"
+            INSERT INTO otp_codes (
+                id, email, code_hash, expires_at, max_attempts, user_id,
+                code_ciphertext, code_nonce, encrypted_data_key, encryption_key_id
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            "#,
+        )
+        .bind(otp.id)
+        .bind(&otp.email)
+        .bind(&otp.code_hash)
+        .bind(otp.expires_at)
+        .bind(otp.max_attempts)
+        .bind(otp.user_id)
+        .bind(&otp.encrypted_code.ciphertext)
+        .bind(&otp.encrypted_code.nonce)
+        .bind(&otp.encrypted_code.encrypted_data_key)
+        .bind(&otp.encrypted_code.key_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Code of OTP `id` for the email delivering it; `None` once it was used, replaced or expired
+    #[instrument(skip(self))]
+    pub async fn pending_code(&self, id: Uuid) -> Result<Option<String>> {
+        let secret = match &self.migration {
+            Some((cache, migration)) => {
+                let cached = async {
+                    let otp = cache.pending_by_id(id).await?;
+                    Ok::<_, anyhow::Error>(otp.map(|otp| otp.encrypted_code))
+                };
+                migration.read(self.pending_secret(id), cached).await?
+            }
+            None => self.pending_secret(id).await?,
+        };
+
+        let Some(secret) = secret else {
+            return Ok(None);
+        };
+        let code = self
+            .cipher
+            .decrypt(&secret, &id.to_string())
+            .context("Failed to decrypt OTP code")?;
+        Ok(Some(code))
+    }
+
+    /// Encrypted code of OTP `id` in Postgres
+    async fn pending_secret(&self, id: Uuid) -> Result<Option<EncryptedSecret>> {
+        let row = sqlx::query_as::<_, EncryptedCodeRow>(
+            r#"
+            SELECT code_ciphertext, code_nonce, encrypted_data_key, encryption_key_id
+            FROM otp_codes
+            WHERE id = $1 AND is_used = false AND expires_at > NOW() AND code_ciphertext IS NOT NULL
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|row| EncryptedSecret {
+            ciphertext: row.code_ciphertext,
+            nonce: row.code_nonce,
+            encrypted_data_key: row.encrypted_data_key,
+            key_id: row.encryption_key_id,
+        }))
+    }
+
+    /// Verify OTP code. While both stores hold codes, each counts the guess, so they agree on the outcome.
     #[instrument(skip(self), fields(email = %request.email))]
     pub async fn verify_otp(&self, request: VerifyOtpRequest) -> Result<OtpVerificationResult> {
         debug!("Verifying OTP code");
 
+        match &self.migration {
+            Some((cache, migration)) => {
+                migration
+                    .read(
+                        self.verify_stored_code(&request),
+                        self.verify_cached_code(cache, &request),
+                    )
+                    .await
+            }
+            None => self.verify_stored_code(&request).await,
+        }
+    }
+
+    /// Verify a code against Postgres
+    async fn verify_stored_code(&self, request: &VerifyOtpRequest) -> Result<OtpVerificationResult> {
         // Find the most recent unused OTP for this email
         let otp = sqlx::query_as::<_, OtpCode>(
             r#"
@@ -251,10 +421,16 @@ impl OtpRepository {
             );
             
             // Mark as used to prevent further attempts
-            sqlx::query("UPDATE otp_codes SET is_used = true WHERE id = $1")
-                .bind(otp.id)
-                .execute(&self.pool)
-                .await?;
+            sqlx::query(
+                r#"
+                UPDATE otp_codes
+                SET is_used = true, code_ciphertext = NULL, code_nonce = NULL, encrypted_data_key = NULL
+                WHERE id = $1
+                "#,
+            )
+            .bind(otp.id)
+            .execute(&self.pool)
+            .await?;
 
             return Ok(OtpVerificationResult {
                 success: false,
@@ -292,10 +468,16 @@ impl OtpRepository {
         }
 
         // Mark OTP as used
-        sqlx::query("UPDATE otp_codes SET is_used = true WHERE id = $1")
-            .bind(otp.id)
-            .execute(&self.pool)
-            .await?;
+        sqlx::query(
+            r#"
+            UPDATE otp_codes
+            SET is_used = true, code_ciphertext = NULL, code_nonce = NULL, encrypted_data_key = NULL
+            WHERE id = $1
+            "#,
+        )
+        .bind(otp.id)
+        .execute(&self.pool)
+        .await?;
 
         // Determine if this is a new user
         let is_new_user = otp.user_id.is_none();
@@ -316,11 +498,64 @@ impl OtpRepository {
         })
     }
 
+    /// Verify a code against Redis, with the same outcomes as `verify_stored_code`
+    async fn verify_cached_code(&self, cache: &OtpCache, request: &VerifyOtpRequest) -> Result<OtpVerificationResult> {
+        let failed = |user_id, attempts_remaining| OtpVerificationResult {
+            success: false,
+            user_id,
+            is_new_user: false,
+            attempts_remaining,
+        };
+        let Some(otp) = cache.pending(&request.email).await? else {
+            return Ok(failed(None, 0));
+        };
+
+        let attempts = cache.record_attempt(&otp).await?;
+        if attempts > otp.max_attempts {
+            cache.remove(&otp).await?;
+            return Ok(failed(None, 0));
+        }
+
+        if !self.verify_code(&request.code, &otp.code_hash)? {
+            if attempts == otp.max_attempts {
+                cache.record_event(OtpEvent::Locked).await?;
+            }
+            return Ok(failed(otp.user_id, otp.max_attempts - attempts));
+        }
+
+        cache.remove(&otp).await?;
+        cache.record_event(OtpEvent::Used).await?;
+        Ok(OtpVerificationResult {
+            success: true,
+            user_id: otp.user_id,
+            is_new_user: otp.user_id.is_none(),
+            attempts_remaining: otp.max_attempts - attempts,
+        })
+    }
+
+    /// Drop the encrypted copies of expired codes, which can't be delivered any more; the rows are
+    /// kept a day longer for the statistics
+    #[instrument(skip(self))]
+    pub async fn forget_expired_codes(&self) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            UPDATE otp_codes
+            SET code_ciphertext = NULL, code_nonce = NULL, encrypted_data_key = NULL
+            WHERE expires_at <= NOW() AND code_ciphertext IS NOT NULL
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
     /// Clean up expired OTP codes
     #[instrument(skip(self))]
     pub async fn cleanup_expired_codes(&self) -> Result<u64> {
         debug!("Cleaning up expired OTP codes");
 
+        self.forget_expired_codes().await?;
         let result = sqlx::query(
             "DELETE FROM otp_codes WHERE expires_at < NOW() - INTERVAL '24 hours'"
         )
@@ -338,42 +573,31 @@ impl OtpRepository {
 
     /// Get OTP statistics for monitoring
     #[instrument(skip(self))]
-    pub async fn get_otp_stats(&self) -> Result<serde_json::Value> {
+    pub async fn get_otp_stats(&self) -> Result<OtpStats> {
         debug!("Fetching OTP statistics");
 
-        // Total OTPs in last 24 hours
-        let total_24h: (i64,) = sqlx::query_as(
-            "SELECT COUNT(*) FROM otp_codes WHERE created_at > NOW() - INTERVAL '24 hours'"
-        )
-        .fetch_one(&self.pool)
-        .await?;
-
-        // Successful verifications in last 24 hours
-        let successful_24h: (i64,) = sqlx::query_as(
-            "SELECT COUNT(*) FROM otp_codes WHERE created_at > NOW() - INTERVAL '24 hours' AND is_used = true"
-        )
-        .fetch_one(&self.pool)
-        .await?;
+        // Redis counts events by the hour rather than codes by outcome, so the two aren't compared
+        if let Some((cache, migration)) = &self.migration {
+            if migration.phase().reads_new() {
+                return cache.stats().await;
+            }
+        }
 
-        // Failed attempts (exceeded max attempts) in last 24 hours
-        let failed_24h: (i64,) = sqlx::query_as(
-            "SELECT COUNT(*) FROM otp_codes WHERE created_at > NOW() - INTERVAL '24 hours' AND attempts >= max_attempts AND is_used = false"
+        // Codes sent, codes used, and codes locked after too many wrong guesses in the last 24 hours
+        let stats = sqlx::query_as::<_, OtpStats>(
+            r#"
+            SELECT
+                COUNT(*) AS total_24h,
+                COUNT(*) FILTER (WHERE is_used) AS successful_24h,
+                COUNT(*) FILTER (WHERE attempts >= max_attempts AND NOT is_used) AS failed_24h
+            FROM otp_codes
+            WHERE created_at > NOW() - INTERVAL '24 hours'
+            "#
         )
         .fetch_one(&self.pool)
         .await?;
 
-        let stats = serde_json::json!({
-            "total_otps_24h": total_24h.0,
-            "successful_verifications_24h": successful_24h.0,
-            "failed_attempts_24h": failed_24h.0,
-            "success_rate_24h": if total_24h.0 > 0 { 
-                (successful_24h.0 as f64 / total_24h.0 as f64) * 100.0 
-            } else { 
-                0.0 
-            }
-        });
-
-        debug!(stats = %stats, "Retrieved OTP statistics");
+        debug!(?stats, "Retrieved OTP statistics");
         Ok(stats)
     }
 }
@@ -426,4 +650,15 @@ mod tests {
         assert!(argon2.verify_password(code.as_bytes(), &parsed_hash).is_ok());
         assert!(argon2.verify_password("654321".as_bytes(), &parsed_hash).is_err());
     }
+
+    #[test]
+    fn test_otp_success_rate() {
+        let stats = OtpStats {
+            total_24h: 8,
+            successful_24h: 6,
+            failed_24h: 1,
+        };
+        assert_eq!(stats.success_rate_24h(), 75.0);
+        assert_eq!(OtpStats::default().success_rate_24h(), 0.0);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,225 @@
+use crate::model::otp::{OtpStats, PendingOtp};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use deadpool_redis::{Connection, Pool};
+use redis::AsyncCommands;
+use tracing::{debug, instrument};
+use uuid::Uuid;
+
+/// Hours of events `OtpCache::stats` adds up
+const STATS_HOURS: i64 = 24;
+
+/// What happened to a code, counted by the hour for the statistics
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OtpEvent {
+    Sent,
+    Used,
+    /// Locked after too many wrong guesses
+    Locked,
+}
+
+impl OtpEvent {
+    fn as_str(&self) -> &'static str {
+        match self {
+            OtpEvent::Sent => "sent",
+            OtpEvent::Used => "used",
+            OtpEvent::Locked => "locked",
+        }
+    }
+}
+
+/// Redis store of pending OTP codes, the store `OtpRepository` moves to from Postgres (see
+/// `OTP_STORE_MIGRATION`). An address's code is kept until it expires, is used or is locked; sends and
+/// guesses are counted in keys of their own so concurrent requests can't lose an increment.
+#[derive(Clone)]
+pub struct OtpCache {
+    redis_pool: Pool,
+}
+
+impl std::fmt::Debug for OtpCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OtpCache").finish_non_exhaustive()
+    }
+}
+
+fn code_key(email: &str) -> String {
+    format!("otp:{}", email)
+}
+
+fn id_key(id: Uuid) -> String {
+    format!("otp_id:{}", id)
+}
+
+fn attempts_key(id: Uuid) -> String {
+    format!("otp_attempts:{}", id)
+}
+
+fn requests_key(email: &str) -> String {
+    format!("otp_requests:{}", email)
+}
+
+fn stats_key(event: OtpEvent, hour: DateTime<Utc>) -> String {
+    format!("otp_stats:{}:{}", event.as_str(), hour.format("%Y%m%d%H"))
+}
+
+impl OtpCache {
+    pub fn new(redis_url: &str) -> Result<Self> {
+        let cfg = deadpool_redis::Config::from_url(redis_url);
+        let redis_pool = cfg
+            .create_pool(Some(deadpool_redis::Runtime::Tokio1))
+            .context("Failed to create Redis connection pool")?;
+
+        Ok(Self { redis_pool })
+    }
+
+    async fn connection(&self) -> Result<Connection> {
+        self.redis_pool
+            .get()
+            .await
+            .context("Failed to get Redis connection from pool")
+    }
+
+    /// Codes sent to `email` within the hour since the first of them
+    #[instrument(skip(self, email))]
+    pub async fn requests_in_last_hour(&self, email: &str) -> Result<i64> {
+        let mut conn = self.connection().await?;
+        let count: Option<i64> = conn
+            .get(requests_key(email))
+            .await
+            .context("Failed to read OTP request count")?;
+        Ok(count.unwrap_or(0))
+    }
+
+    /// Replace the address's pending code with `otp`
+    #[instrument(skip(self, otp), fields(otp_id = %otp.id))]
+    pub async fn insert(&self, otp: &PendingOtp) -> Result<()> {
+        let ttl_seconds = (otp.expires_at - Utc::now()).num_seconds().max(1) as u64;
+        let data = serde_json::to_string(otp).context("Failed to serialize OTP code")?;
+
+        let mut conn = self.connection().await?;
+        conn.set_ex::<_, _, ()>(code_key(&otp.email), data, ttl_seconds)
+            .await
+            .context("Failed to store OTP code")?;
+        conn.set_ex::<_, _, ()>(id_key(otp.id), &otp.email, ttl_seconds)
+            .await
+            .context("Failed to store OTP code id")?;
+
+        let requests: i64 = conn
+            .incr(requests_key(&otp.email), 1)
+            .await
+            .context("Failed to count OTP request")?;
+        if requests == 1 {
+            conn.expire::<_, ()>(requests_key(&otp.email), 3600)
+                .await
+                .context("Failed to set TTL on OTP request count")?;
+        }
+
+        self.count_event(&mut conn, OtpEvent::Sent).await
+    }
+
+    /// The address's pending code
+    #[instrument(skip(self, email))]
+    pub async fn pending(&self, email: &str) -> Result<Option<PendingOtp>> {
+        let mut conn = self.connection().await?;
+        let data: Option<String> = conn.get(code_key(email)).await.context("Failed to read OTP code")?;
+
+        data.map(|data| serde_json::from_str(&data).context("Failed to deserialize OTP code"))
+            .transpose()
+    }
+
+    /// Code `id`, while it is still its address's pending code
+    #[instrument(skip(self))]
+    pub async fn pending_by_id(&self, id: Uuid) -> Result<Option<PendingOtp>> {
+        let mut conn = self.connection().await?;
+        let email: Option<String> = conn.get(id_key(id)).await.context("Failed to read OTP code id")?;
+        let Some(email) = email else {
+            return Ok(None);
+        };
+
+        Ok(self.pending(&email).await?.filter(|otp| otp.id == id))
+    }
+
+    /// Count a guess at `otp`; returns the guesses made, this one included
+    #[instrument(skip(self, otp), fields(otp_id = %otp.id))]
+    pub async fn record_attempt(&self, otp: &PendingOtp) -> Result<i32> {
+        let mut conn = self.connection().await?;
+        let attempts: i32 = conn
+            .incr(attempts_key(otp.id), 1)
+            .await
+            .context("Failed to count OTP attempt")?;
+        let ttl_seconds = (otp.expires_at - Utc::now()).num_seconds().max(1);
+        conn.expire::<_, ()>(attempts_key(otp.id), ttl_seconds)
+            .await
+            .context("Failed to set TTL on OTP attempts")?;
+
+        Ok(attempts)
+    }
+
+    /// Drop `otp` once it was used or locked
+    #[instrument(skip(self, otp), fields(otp_id = %otp.id))]
+    pub async fn remove(&self, otp: &PendingOtp) -> Result<()> {
+        let mut conn = self.connection().await?;
+        // A newer code may already have replaced this one under the address
+        let current: Option<String> = conn
+            .get(code_key(&otp.email))
+            .await
+            .context("Failed to read OTP code")?;
+        let current = current.and_then(|data| serde_json::from_str::<PendingOtp>(&data).ok());
+        if current.is_some_and(|current| current.id == otp.id) {
+            conn.del::<_, ()>(code_key(&otp.email))
+                .await
+                .context("Failed to delete OTP code")?;
+        }
+        conn.del::<_, ()>(vec![id_key(otp.id), attempts_key(otp.id)])
+            .await
+            .context("Failed to delete OTP code id")?;
+
+        debug!("Removed OTP code from Redis");
+        Ok(())
+    }
+
+    /// Count what happened to a code in the statistics
+    pub async fn record_event(&self, event: OtpEvent) -> Result<()> {
+        let mut conn = self.connection().await?;
+        self.count_event(&mut conn, event).await
+    }
+
+    async fn count_event(&self, conn: &mut Connection, event: OtpEvent) -> Result<()> {
+        let key = stats_key(event, Utc::now());
+        conn.incr::<_, _, ()>(&key, 1)
+            .await
+            .context("Failed to count OTP event")?;
+        conn.expire::<_, ()>(&key, (STATS_HOURS + 1) * 3600)
+            .await
+            .context("Failed to set TTL on OTP statistics")?;
+        Ok(())
+    }
+
+    /// Codes sent, used and locked in the last 24 hours, counted by the hour they happened in
+    #[instrument(skip(self))]
+    pub async fn stats(&self) -> Result<OtpStats> {
+        let mut conn = self.connection().await?;
+        let now = Utc::now();
+        let mut totals = [0i64; 3];
+        for (total, event) in totals
+            .iter_mut()
+            .zip([OtpEvent::Sent, OtpEvent::Used, OtpEvent::Locked])
+        {
+            let keys: Vec<String> = (0..STATS_HOURS)
+                .map(|hour| stats_key(event, now - Duration::hours(hour)))
+                .collect();
+            let hourly: Vec<Option<i64>> = redis::cmd("MGET")
+                .arg(&keys)
+                .query_async(&mut conn)
+                .await
+                .context("Failed to read OTP statistics")?;
+            *total = hourly.into_iter().flatten().sum();
+        }
+
+        Ok(OtpStats {
+            total_24h: totals[0],
+            successful_24h: totals[1],
+            failed_24h: totals[2],
+        })
+    }
+}
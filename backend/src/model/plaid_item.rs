@@ -0,0 +1,445 @@
+use crate::adapter::encryption::{EncryptedSecret, EnvelopeCipher};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, instrument, warn};
+use uuid::Uuid;
+
+/// Lifecycle status of a linked Plaid item
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlaidItemStatus {
+    /// Item is healthy and can be synced
+    Active,
+    /// Institution requires the user to re-authenticate via Link update mode
+    LoginRequired,
+    /// Item returned a non-recoverable error on its last sync
+    Error,
+    /// Item was removed by the user
+    Removed,
+}
+
+impl PlaidItemStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PlaidItemStatus::Active => "active",
+            PlaidItemStatus::LoginRequired => "login_required",
+            PlaidItemStatus::Error => "error",
+            PlaidItemStatus::Removed => "removed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "login_required" => PlaidItemStatus::LoginRequired,
+            "error" => PlaidItemStatus::Error,
+            "removed" => PlaidItemStatus::Removed,
+            _ => PlaidItemStatus::Active,
+        }
+    }
+}
+
+/// Plaid item row; the access token is only available decrypted via the repository
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct PlaidItem {
+    pub id: Uuid,
+    pub item_id: String,
+    pub user_id: Uuid,
+    pub access_token_ciphertext: Vec<u8>,
+    pub access_token_nonce: Vec<u8>,
+    pub encrypted_data_key: Vec<u8>,
+    pub encryption_key_id: String,
+    pub institution_id: Option<String>,
+    pub institution_name: Option<String>,
+    /// Bank data provider the item was linked through, e.g. `plaid`
+    pub provider: String,
+    pub status: String,
+    pub error_code: Option<String>,
+    pub sync_cursor: Option<String>,
+    pub last_synced_at: Option<DateTime<Utc>>,
+    pub last_sync_attempt_at: Option<DateTime<Utc>>,
+    pub last_sync_status: Option<String>,
+    pub last_sync_error: Option<String>,
+    pub last_sync_duration_ms: Option<i32>,
+    pub consecutive_sync_failures: i32,
+    /// Until when a running sync holds the item; see `PlaidItemRepository::claim_sync`
+    pub sync_claimed_until: Option<DateTime<Utc>>,
+    /// When the user unlinked the item; set together with the `removed` status
+    pub removed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl PlaidItem {
+    pub fn status(&self) -> PlaidItemStatus {
+        PlaidItemStatus::parse(&self.status)
+    }
+
+    fn encrypted_access_token(&self) -> EncryptedSecret {
+        EncryptedSecret {
+            ciphertext: self.access_token_ciphertext.clone(),
+            nonce: self.access_token_nonce.clone(),
+            encrypted_data_key: self.encrypted_data_key.clone(),
+            key_id: self.encryption_key_id.clone(),
+        }
+    }
+}
+
+/// Request to store a newly exchanged Plaid item
+#[derive(Debug, Clone)]
+pub struct CreatePlaidItemRequest {
+    pub user_id: Uuid,
+    pub item_id: String,
+    pub access_token: String,
+    pub institution_id: Option<String>,
+    pub institution_name: Option<String>,
+    pub provider: String,
+}
+
+/// Plaid item repository for database operations
+#[derive(Debug, Clone)]
+pub struct PlaidItemRepository {
+    pool: PgPool,
+    cipher: Arc<EnvelopeCipher>,
+}
+
+impl PlaidItemRepository {
+    pub fn new(pool: PgPool, cipher: Arc<EnvelopeCipher>) -> Self {
+        Self { pool, cipher }
+    }
+
+    /// Store an item with its encrypted access token, re-activating it if it was linked before
+    #[instrument(skip(self, request), fields(user_id = %request.user_id, item_id = %request.item_id))]
+    pub async fn upsert_item(&self, request: CreatePlaidItemRequest) -> Result<PlaidItem> {
+        debug!("Storing Plaid item");
+
+        let secret = self
+            .cipher
+            .encrypt(&request.access_token, &request.item_id)
+            .context("Failed to encrypt Plaid access token")?;
+
+        let item = sqlx::query_as::<_, PlaidItem>(
+            r#"
+            INSERT INTO plaid_items (
+                item_id, user_id, access_token_ciphertext, access_token_nonce,
+                encrypted_data_key, encryption_key_id, institution_id, institution_name, provider
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (item_id) DO UPDATE SET
+                access_token_ciphertext = EXCLUDED.access_token_ciphertext,
+                access_token_nonce = EXCLUDED.access_token_nonce,
+                encrypted_data_key = EXCLUDED.encrypted_data_key,
+                encryption_key_id = EXCLUDED.encryption_key_id,
+                institution_id = COALESCE(EXCLUDED.institution_id, plaid_items.institution_id),
+                institution_name = COALESCE(EXCLUDED.institution_name, plaid_items.institution_name),
+                status = 'active',
+                error_code = NULL,
+                removed_at = NULL,
+                updated_at = NOW()
+            WHERE plaid_items.user_id = EXCLUDED.user_id
+            RETURNING *
+            "#,
+        )
+        .bind(&request.item_id)
+        .bind(request.user_id)
+        .bind(&secret.ciphertext)
+        .bind(&secret.nonce)
+        .bind(&secret.encrypted_data_key)
+        .bind(&secret.key_id)
+        .bind(&request.institution_id)
+        .bind(&request.institution_name)
+        .bind(&request.provider)
+        .fetch_one(&self.pool)
+        .await
+        .context("Failed to store Plaid item")?;
+
+        info!(
+            plaid_item_id = %item.id,
+            item_id = %item.item_id,
+            key_id = %item.encryption_key_id,
+            "Successfully stored Plaid item"
+        );
+
+        Ok(item)
+    }
+
+    /// Decrypt the access token of an item, re-wrapping it if it used a retired key
+    #[instrument(skip(self, item), fields(item_id = %item.item_id))]
+    pub async fn access_token(&self, item: &PlaidItem) -> Result<String> {
+        let secret = item.encrypted_access_token();
+        let access_token = self
+            .cipher
+            .decrypt(&secret, &item.item_id)
+            .context("Failed to decrypt Plaid access token")?;
+
+        if self.cipher.needs_rotation(&secret) {
+            if let Err(e) = self.rotate_access_token(item, &access_token).await {
+                warn!(error = %e, "Failed to re-encrypt access token under active key");
+            }
+        }
+
+        Ok(access_token)
+    }
+
+    async fn rotate_access_token(&self, item: &PlaidItem, access_token: &str) -> Result<()> {
+        let secret = self.cipher.encrypt(access_token, &item.item_id)?;
+
+        sqlx::query(
+            r#"
+            UPDATE plaid_items SET
+                access_token_ciphertext = $2,
+                access_token_nonce = $3,
+                encrypted_data_key = $4,
+                encryption_key_id = $5,
+                updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(item.id)
+        .bind(&secret.ciphertext)
+        .bind(&secret.nonce)
+        .bind(&secret.encrypted_data_key)
+        .bind(&secret.key_id)
+        .execute(&self.pool)
+        .await?;
+
+        info!(item_id = %item.item_id, key_id = %secret.key_id, "Rotated Plaid access token encryption key");
+        Ok(())
+    }
+
+    /// Find an item by Plaid item ID
+    #[instrument(skip(self))]
+    pub async fn find_by_item_id(&self, item_id: &str) -> Result<Option<PlaidItem>> {
+        debug!(item_id = %item_id, "Looking up Plaid item");
+
+        let item = sqlx::query_as::<_, PlaidItem>(
+            "SELECT * FROM plaid_items WHERE item_id = $1"
+        )
+        .bind(item_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(item)
+    }
+
+    /// List a user's items that have not been removed
+    #[instrument(skip(self))]
+    pub async fn list_by_user(&self, user_id: Uuid) -> Result<Vec<PlaidItem>> {
+        debug!(user_id = %user_id, "Listing Plaid items for user");
+
+        let items = sqlx::query_as::<_, PlaidItem>(
+            "SELECT * FROM plaid_items WHERE user_id = $1 AND status <> 'removed' ORDER BY created_at"
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(items)
+    }
+
+    /// List all items eligible for background sync
+    #[instrument(skip(self))]
+    pub async fn list_active(&self) -> Result<Vec<PlaidItem>> {
+        let items = sqlx::query_as::<_, PlaidItem>(
+            "SELECT * FROM plaid_items WHERE status = 'active' ORDER BY last_synced_at NULLS FIRST"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(items)
+    }
+
+    /// Update item status (e.g. after ITEM_LOGIN_REQUIRED)
+    #[instrument(skip(self))]
+    pub async fn update_status(
+        &self,
+        item_id: &str,
+        status: PlaidItemStatus,
+        error_code: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE plaid_items SET status = $2, error_code = $3, updated_at = NOW() WHERE item_id = $1"
+        )
+        .bind(item_id)
+        .bind(status.as_str())
+        .bind(error_code)
+        .execute(&self.pool)
+        .await?;
+
+        info!(item_id = %item_id, status = %status.as_str(), "Updated Plaid item status");
+        Ok(())
+    }
+
+    /// Record the outcome of a sync attempt; failures increment the consecutive failure count
+    #[instrument(skip(self, error))]
+    pub async fn record_sync_result(
+        &self,
+        item_id: &str,
+        succeeded: bool,
+        error: Option<&str>,
+        duration_ms: i32,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE plaid_items SET
+                last_sync_attempt_at = NOW(),
+                last_sync_status = $2,
+                last_sync_error = $3,
+                last_sync_duration_ms = $4,
+                consecutive_sync_failures = CASE WHEN $5 THEN 0 ELSE consecutive_sync_failures + 1 END,
+                updated_at = NOW()
+            WHERE item_id = $1
+            "#,
+        )
+        .bind(item_id)
+        .bind(if succeeded { "succeeded" } else { "failed" })
+        .bind(error)
+        .bind(duration_ms)
+        .bind(succeeded)
+        .execute(&self.pool)
+        .await?;
+
+        debug!(item_id = %item_id, succeeded, duration_ms, "Recorded sync result");
+        Ok(())
+    }
+
+    /// Claim the item's sync for `lease`, unless another sync holds an unexpired claim. Returns the
+    /// item as it is now, so the sync starts from the latest committed cursor, or `None` when the
+    /// item is claimed elsewhere or gone.
+    #[instrument(skip(self))]
+    pub async fn claim_sync(&self, item_id: &str, lease: Duration) -> Result<Option<PlaidItem>> {
+        let item = sqlx::query_as::<_, PlaidItem>(
+            r#"
+            UPDATE plaid_items SET sync_claimed_until = NOW() + make_interval(secs => $2)
+            WHERE item_id = $1 AND (sync_claimed_until IS NULL OR sync_claimed_until < NOW())
+            RETURNING *
+            "#,
+        )
+        .bind(item_id)
+        .bind(lease.as_secs_f64())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(item)
+    }
+
+    /// Give up a claim taken by `claim_sync`
+    #[instrument(skip(self))]
+    pub async fn release_sync(&self, item_id: &str) -> Result<()> {
+        sqlx::query("UPDATE plaid_items SET sync_claimed_until = NULL WHERE item_id = $1")
+            .bind(item_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Persist the transactions sync cursor after a successful sync
+    #[instrument(skip(self, cursor))]
+    pub async fn update_sync_cursor(&self, item_id: &str, cursor: &str) -> Result<()> {
+        sqlx::query(
+            "UPDATE plaid_items SET sync_cursor = $2, last_synced_at = NOW(), updated_at = NOW() WHERE item_id = $1"
+        )
+        .bind(item_id)
+        .bind(cursor)
+        .execute(&self.pool)
+        .await?;
+
+        debug!(item_id = %item_id, "Updated Plaid sync cursor");
+        Ok(())
+    }
+
+    /// Mark a user's item removed and soft-delete its transactions.
+    ///
+    /// Accounts of removed items are already hidden from listings; everything is deleted
+    /// by `purge_removed` once the retention period has passed. Returns the number of
+    /// transactions soft-deleted, or `None` if the user has no such live item.
+    #[instrument(skip(self))]
+    pub async fn mark_removed(&self, user_id: Uuid, item_id: &str) -> Result<Option<u64>> {
+        let mut tx = self.pool.begin().await?;
+
+        let updated = sqlx::query(
+            r#"
+            UPDATE plaid_items
+            SET status = 'removed', removed_at = NOW(), sync_cursor = NULL, updated_at = NOW()
+            WHERE item_id = $1 AND user_id = $2 AND status <> 'removed'
+            "#,
+        )
+        .bind(item_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if updated == 0 {
+            return Ok(None);
+        }
+
+        let transactions = sqlx::query(
+            "UPDATE transactions SET removed_at = NOW(), updated_at = NOW() WHERE item_id = $1 AND removed_at IS NULL"
+        )
+        .bind(item_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        tx.commit().await?;
+
+        info!(item_id = %item_id, transactions, "Marked Plaid item removed");
+        Ok(Some(transactions))
+    }
+
+    /// Delete items removed before `removed_before`; accounts, transactions and other item data cascade.
+    ///
+    /// Categories, annotations, splits and receipts have no foreign key to the partitioned
+    /// transactions table, so they are deleted with their transactions first.
+    #[instrument(skip(self))]
+    pub async fn purge_removed(&self, removed_before: DateTime<Utc>) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+
+        for table in ["transaction_categories", "transaction_annotations", "transaction_splits", "receipts"] {
+            sqlx::query(&format!(
+                r#"
+                DELETE FROM {table} WHERE transaction_id IN (
+                    SELECT t.transaction_id FROM transactions t
+                    JOIN plaid_items i ON i.item_id = t.item_id
+                    WHERE i.status = 'removed' AND i.removed_at < $1
+                )
+                "#
+            ))
+            .bind(removed_before)
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("Failed to purge {} of removed items", table))?;
+        }
+
+        let purged = sqlx::query(
+            "DELETE FROM plaid_items WHERE status = 'removed' AND removed_at < $1"
+        )
+        .bind(removed_before)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        tx.commit().await?;
+        Ok(purged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_round_trip() {
+        for status in [
+            PlaidItemStatus::Active,
+            PlaidItemStatus::LoginRequired,
+            PlaidItemStatus::Error,
+            PlaidItemStatus::Removed,
+        ] {
+            assert_eq!(PlaidItemStatus::parse(status.as_str()), status);
+        }
+    }
+}
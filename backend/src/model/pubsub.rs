@@ -0,0 +1,229 @@
+use crate::adapter::plaid::BankAccount;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::debug;
+use uuid::Uuid;
+
+/// Events buffered per topic before slow subscribers start lagging
+const DEFAULT_TOPIC_CAPACITY: usize = 256;
+
+/// In-process publish/subscribe topic.
+///
+/// Publishing never blocks; a subscriber that falls more than `capacity` events
+/// behind receives `RecvError::Lagged` and is expected to resynchronize.
+#[derive(Debug, Clone)]
+pub struct Topic<T> {
+    sender: broadcast::Sender<T>,
+}
+
+impl<T: Clone + Send + 'static> Topic<T> {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        Self { sender }
+    }
+
+    /// Publish an event, returning the number of subscribers it was delivered to
+    pub fn publish(&self, event: T) -> usize {
+        // Sending only fails when nobody is subscribed
+        self.sender.send(event).unwrap_or(0)
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<T> {
+        self.sender.subscribe()
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+}
+
+impl<T: Clone + Send + 'static> Default for Topic<T> {
+    fn default() -> Self {
+        Self::new(DEFAULT_TOPIC_CAPACITY)
+    }
+}
+
+/// Balances of some of a user's accounts changed
+#[derive(Debug, Clone)]
+pub struct BalanceUpdate {
+    pub user_id: Uuid,
+    pub accounts: Vec<BankAccount>,
+    pub updated_at: DateTime<Utc>,
+}
+
+type UserTopics = Arc<Mutex<HashMap<Uuid, Topic<Arc<BalanceUpdate>>>>>;
+
+/// Balance updates from item links, syncs and refreshes, on a topic per user, so a busy user can't make
+/// another user's streams lag. A user's topic is created by their first subscription and dropped with
+/// their last, and updates for users nobody subscribed to are dropped.
+#[derive(Debug, Clone)]
+pub struct BalanceUpdates {
+    capacity: usize,
+    topics: UserTopics,
+}
+
+impl BalanceUpdates {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            topics: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Publish refreshed balances for a user's accounts
+    pub fn publish_balances(&self, user_id: Uuid, accounts: Vec<BankAccount>) {
+        if accounts.is_empty() {
+            return;
+        }
+        let topic = self.topics.lock().unwrap().get(&user_id).cloned();
+        let Some(topic) = topic else {
+            return;
+        };
+        let delivered = topic.publish(Arc::new(BalanceUpdate {
+            user_id,
+            accounts,
+            updated_at: Utc::now(),
+        }));
+        debug!(user_id = %user_id, delivered, "Published balance update");
+    }
+
+    /// Receive the user's balance updates
+    pub fn subscribe(&self, user_id: Uuid) -> BalanceSubscription {
+        let mut topics = self.topics.lock().unwrap();
+        let receiver = topics
+            .entry(user_id)
+            .or_insert_with(|| Topic::new(self.capacity))
+            .subscribe();
+        BalanceSubscription {
+            user_id,
+            receiver,
+            topics: self.topics.clone(),
+        }
+    }
+
+    /// Open subscriptions to the user's balance updates
+    pub fn subscriber_count(&self, user_id: Uuid) -> usize {
+        let topics = self.topics.lock().unwrap();
+        topics.get(&user_id).map_or(0, Topic::subscriber_count)
+    }
+}
+
+impl Default for BalanceUpdates {
+    fn default() -> Self {
+        Self::new(DEFAULT_TOPIC_CAPACITY)
+    }
+}
+
+/// One stream's subscription to a user's balance updates
+#[derive(Debug)]
+pub struct BalanceSubscription {
+    user_id: Uuid,
+    receiver: broadcast::Receiver<Arc<BalanceUpdate>>,
+    topics: UserTopics,
+}
+
+impl BalanceSubscription {
+    /// The next update; `RecvError::Lagged` once more than the topic's capacity were missed
+    pub async fn recv(&mut self) -> Result<Arc<BalanceUpdate>, RecvError> {
+        self.receiver.recv().await
+    }
+}
+
+impl Drop for BalanceSubscription {
+    fn drop(&mut self) {
+        // Subscribing holds the same lock, so no subscription can join a topic being dropped
+        let mut topics = self.topics.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if topics
+            .get(&self.user_id)
+            .is_some_and(|topic| topic.subscriber_count() <= 1)
+        {
+            topics.remove(&self.user_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapter::plaid::AccountBalances;
+
+    #[tokio::test]
+    async fn test_publish_reaches_subscribers() {
+        let topic: Topic<u32> = Topic::new(4);
+        assert_eq!(topic.publish(1), 0);
+
+        let mut receiver = topic.subscribe();
+        assert_eq!(topic.publish(2), 1);
+        assert_eq!(receiver.recv().await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_slow_subscriber_lags_instead_of_blocking() {
+        let topic: Topic<u32> = Topic::new(2);
+        let mut receiver = topic.subscribe();
+        for i in 0..5 {
+            topic.publish(i);
+        }
+
+        assert!(matches!(receiver.recv().await, Err(RecvError::Lagged(3))));
+        assert_eq!(receiver.recv().await.unwrap(), 3);
+    }
+
+    fn account(account_id: &str) -> BankAccount {
+        BankAccount {
+            account_id: account_id.to_string(),
+            item_id: "item-1".to_string(),
+            mask: None,
+            name: "Checking".to_string(),
+            official_name: None,
+            account_type: "depository".to_string(),
+            account_subtype: None,
+            balances: AccountBalances {
+                available: None,
+                current: Some(100.0),
+                limit: None,
+                iso_currency_code: Some("USD".to_string()),
+                unofficial_currency_code: None,
+            },
+            institution_id: None,
+            institution_name: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_balance_updates_reach_only_their_user() {
+        let updates = BalanceUpdates::new(2);
+        let (alice, bob) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut alice_stream = updates.subscribe(alice);
+        let mut bob_stream = updates.subscribe(bob);
+
+        // Bob's updates overflow his topic only
+        for i in 0..5 {
+            updates.publish_balances(bob, vec![account(&format!("bob-{}", i))]);
+        }
+        updates.publish_balances(alice, vec![account("alice-1")]);
+
+        assert_eq!(alice_stream.recv().await.unwrap().accounts[0].account_id, "alice-1");
+        assert!(matches!(bob_stream.recv().await, Err(RecvError::Lagged(3))));
+    }
+
+    #[tokio::test]
+    async fn test_balance_topic_dropped_with_last_subscription() {
+        let updates = BalanceUpdates::default();
+        let user_id = Uuid::new_v4();
+        let first = updates.subscribe(user_id);
+        let second = updates.subscribe(user_id);
+        assert_eq!(updates.subscriber_count(user_id), 2);
+
+        drop(first);
+        assert_eq!(updates.subscriber_count(user_id), 1);
+        drop(second);
+        assert!(updates.topics.lock().unwrap().is_empty());
+
+        // Updates nobody subscribed to are dropped rather than creating a topic
+        updates.publish_balances(user_id, vec![account("acc-1")]);
+        assert!(updates.topics.lock().unwrap().is_empty());
+    }
+}
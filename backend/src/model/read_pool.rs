@@ -0,0 +1,206 @@
+use sqlx::postgres::PgPool;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Seconds the replica has to answer a health check
+const REPLICA_CHECK_TIMEOUT_SECS: u64 = 3;
+
+/// Pool for queries that tolerate replication lag, such as transaction lists and spending
+/// summaries: the read replica while it answers health checks, the primary otherwise. A read the
+/// replica fails to serve for lack of a connection is retried on the primary. Anything read back
+/// right after a write must keep using the primary.
+#[derive(Debug, Clone)]
+pub struct ReadPool {
+    primary: PgPool,
+    replica: Option<PgPool>,
+    replica_up: Arc<AtomicBool>,
+}
+
+impl ReadPool {
+    /// Reads from the primary only
+    pub fn new(primary: PgPool) -> Self {
+        Self {
+            primary,
+            replica: None,
+            replica_up: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Also read from `replica` once a health check finds it up. Open it with
+    /// `DbPoolConfig::for_replica` and `lazy`, so a replica that is down at startup doesn't stop
+    /// the server.
+    pub fn with_replica(mut self, replica: PgPool) -> Self {
+        self.replica = Some(replica);
+        self
+    }
+
+    /// Run a read on the replica if it is up, else on the primary. When the replica fails with
+    /// a connection or pool error, reads move to the primary until the next health check finds
+    /// the replica up, and `query` is run again there.
+    pub async fn fetch<T, F, Fut>(&self, query: F) -> Result<T, sqlx::Error>
+    where
+        F: Fn(PgPool) -> Fut,
+        Fut: Future<Output = Result<T, sqlx::Error>>,
+    {
+        let Some(replica) = self
+            .replica
+            .as_ref()
+            .filter(|_| self.replica_up.load(Ordering::Relaxed))
+        else {
+            return query(self.primary.clone()).await;
+        };
+        match query(replica.clone()).await {
+            Err(e) if is_connection_error(&e) => {
+                warn!(error = %e, "Read replica query failed; retrying on the primary");
+                self.set_replica_up(false);
+                query(self.primary.clone()).await
+            }
+            result => result,
+        }
+    }
+
+    /// Whether reads currently go to the replica
+    pub fn reads_replica(&self) -> bool {
+        self.replica.is_some() && self.replica_up.load(Ordering::Relaxed)
+    }
+
+    /// Probe the replica and route reads by the result; returns whether it answered
+    pub async fn check(&self) -> bool {
+        let Some(replica) = &self.replica else {
+            return false;
+        };
+        let probe = sqlx::query("SELECT 1").execute(replica);
+        let up = match tokio::time::timeout(Duration::from_secs(REPLICA_CHECK_TIMEOUT_SECS), probe).await {
+            Ok(Ok(_)) => true,
+            Ok(Err(e)) => {
+                warn!(error = %e, "Read replica health check failed");
+                false
+            }
+            Err(_) => {
+                warn!("Read replica health check timed out");
+                false
+            }
+        };
+        self.set_replica_up(up);
+        up
+    }
+
+    fn set_replica_up(&self, up: bool) {
+        let was_up = self.replica_up.swap(up, Ordering::Relaxed);
+        match (was_up, up) {
+            (false, true) => info!("Read replica available; routing reads to it"),
+            (true, false) => warn!("Read replica unavailable; reading from the primary"),
+            _ => {}
+        }
+    }
+
+    /// Check the replica every `interval` for the life of the process
+    pub fn spawn_health_checks(self, interval: Duration) {
+        if self.replica.is_none() {
+            return;
+        }
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                self.check().await;
+            }
+        });
+    }
+}
+
+/// Errors reaching the database rather than running the query, which another server may not share
+fn is_connection_error(error: &sqlx::Error) -> bool {
+    matches!(
+        error,
+        sqlx::Error::Io(_)
+            | sqlx::Error::Tls(_)
+            | sqlx::Error::PoolTimedOut
+            | sqlx::Error::PoolClosed
+            | sqlx::Error::WorkerCrashed
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::db_pool::DbPoolConfig;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Mutex;
+
+    #[tokio::test]
+    async fn test_reads_primary_until_replica_is_up() {
+        let primary = PgPool::connect_lazy("postgres://localhost/primary").unwrap();
+        let reads = ReadPool::new(primary);
+        assert!(!reads.reads_replica());
+        assert!(!reads.check().await);
+
+        let reads = reads.with_replica(PgPool::connect_lazy("postgres://localhost/replica").unwrap());
+        assert!(!reads.reads_replica());
+
+        reads.set_replica_up(true);
+        assert!(reads.reads_replica());
+        // Clones share the routing, so a health check moves every repository
+        let clone = reads.clone();
+        reads.set_replica_up(false);
+        assert!(!clone.reads_replica());
+    }
+
+    #[tokio::test]
+    async fn test_replica_connection_errors_retry_on_primary() {
+        let primary = PgPool::connect_lazy("postgres://localhost/primary").unwrap();
+        let reads = ReadPool::new(primary).with_replica(PgPool::connect_lazy("postgres://localhost/replica").unwrap());
+        reads.set_replica_up(true);
+
+        let attempts = Mutex::new(Vec::new());
+        let result = reads
+            .fetch(|pool| {
+                let mut attempts = attempts.lock().unwrap();
+                attempts.push(pool.connect_options().get_database().map(str::to_string));
+                let attempt = attempts.len() - 1;
+                async move {
+                    match attempt {
+                        0 => Err(sqlx::Error::PoolTimedOut),
+                        _ => Ok(attempt),
+                    }
+                }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(result, 1);
+        assert_eq!(
+            *attempts.lock().unwrap(),
+            vec![Some("replica".to_string()), Some("primary".to_string())]
+        );
+        assert!(!reads.reads_replica());
+    }
+
+    #[tokio::test]
+    async fn test_query_errors_are_not_retried() {
+        let primary = PgPool::connect_lazy("postgres://localhost/primary").unwrap();
+        let reads = ReadPool::new(primary).with_replica(PgPool::connect_lazy("postgres://localhost/replica").unwrap());
+        reads.set_replica_up(true);
+
+        let attempts = AtomicUsize::new(0);
+        let result: Result<(), _> = reads
+            .fetch(|_| {
+                attempts.fetch_add(1, Ordering::Relaxed);
+                async { Err(sqlx::Error::RowNotFound) }
+            })
+            .await;
+
+        assert!(matches!(result, Err(sqlx::Error::RowNotFound)));
+        assert_eq!(attempts.load(Ordering::Relaxed), 1);
+        assert!(reads.reads_replica());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_replica_url() {
+        let config = DbPoolConfig::default().for_replica();
+        assert!(config.connect("replica", "not a url", true).await.is_err());
+    }
+}
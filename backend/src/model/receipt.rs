@@ -0,0 +1,303 @@
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::PgPool;
+use tracing::{info, instrument};
+use uuid::Uuid;
+
+/// Largest receipt file accepted
+pub const MAX_RECEIPT_BYTES: i64 = 10 * 1024 * 1024;
+
+/// Receipt file types accepted, with the extension their objects are stored under
+const RECEIPT_CONTENT_TYPES: &[(&str, &str)] = &[
+    ("image/jpeg", "jpg"),
+    ("image/png", "png"),
+    ("image/heic", "heic"),
+    ("image/webp", "webp"),
+    ("application/pdf", "pdf"),
+];
+
+/// Extension for an accepted receipt content type
+pub fn receipt_extension(content_type: &str) -> Option<&'static str> {
+    let content_type = content_type.trim();
+    RECEIPT_CONTENT_TYPES
+        .iter()
+        .find(|(accepted, _)| accepted.eq_ignore_ascii_case(content_type))
+        .map(|(_, extension)| *extension)
+}
+
+/// Check a receipt's declared type and size, returning the normalized content type
+pub fn validate_receipt(content_type: &str, size_bytes: i64) -> Result<String, String> {
+    if receipt_extension(content_type).is_none() {
+        return Err(format!(
+            "Receipts must be one of: {}",
+            RECEIPT_CONTENT_TYPES.iter().map(|(t, _)| *t).collect::<Vec<_>>().join(", ")
+        ));
+    }
+    if size_bytes <= 0 {
+        return Err("size_bytes must be positive".to_string());
+    }
+    if size_bytes > MAX_RECEIPT_BYTES {
+        return Err(format!("Receipts may be at most {} MiB", MAX_RECEIPT_BYTES / (1024 * 1024)));
+    }
+    Ok(content_type.trim().to_ascii_lowercase())
+}
+
+/// S3 key of a receipt; keys are grouped under the owning user so a prefix covers all their files
+pub fn receipt_object_key(user_id: Uuid, transaction_id: &str, receipt_id: Uuid, extension: &str) -> String {
+    format!("receipts/{}/{}/{}.{}", user_id, transaction_id, receipt_id, extension)
+}
+
+/// Upload state of a receipt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReceiptStatus {
+    /// Upload URL handed out; the file may not exist yet
+    Pending,
+    /// Upload verified and the receipt shown on the transaction
+    Attached,
+}
+
+impl ReceiptStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReceiptStatus::Pending => "pending",
+            ReceiptStatus::Attached => "attached",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(ReceiptStatus::Pending),
+            "attached" => Some(ReceiptStatus::Attached),
+            _ => None,
+        }
+    }
+}
+
+/// Line of a scanned receipt
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReceiptLineItem {
+    pub description: String,
+    pub quantity: Option<f64>,
+    pub amount: Option<f64>,
+}
+
+/// Data read from a receipt by the vision model
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReceiptExtraction {
+    pub merchant: Option<String>,
+    pub date: Option<NaiveDate>,
+    pub total: Option<f64>,
+    pub tax: Option<f64>,
+    /// ISO-4217 code
+    pub currency: Option<String>,
+    pub line_items: Vec<ReceiptLineItem>,
+}
+
+impl ReceiptExtraction {
+    /// Whether the receipt total equals a transaction amount to the cent; refunds compare by magnitude
+    pub fn total_matches(&self, amount: f64) -> bool {
+        let cents = |value: f64| (value.abs() * 100.0).round() as i64;
+        matches!(self.total, Some(total) if cents(total) == cents(amount))
+    }
+}
+
+/// Receipt file stored against a transaction
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Receipt {
+    pub id: Uuid,
+    pub transaction_id: String,
+    /// Owner of the transaction
+    pub user_id: Uuid,
+    /// User who uploaded the file; differs from `user_id` for shared accounts
+    pub uploaded_by: Uuid,
+    pub object_key: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub file_name: Option<String>,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub attached_at: Option<DateTime<Utc>>,
+    /// Set once the receipt has been scanned
+    pub extraction: Option<Json<ReceiptExtraction>>,
+    pub scan_model: Option<String>,
+    pub scanned_at: Option<DateTime<Utc>>,
+}
+
+impl Receipt {
+    pub fn status(&self) -> Option<ReceiptStatus> {
+        ReceiptStatus::parse(&self.status)
+    }
+}
+
+/// Receipt to record before its upload
+#[derive(Debug, Clone)]
+pub struct NewReceipt {
+    pub id: Uuid,
+    pub transaction_id: String,
+    pub user_id: Uuid,
+    pub uploaded_by: Uuid,
+    pub object_key: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub file_name: Option<String>,
+}
+
+/// Receipt repository for database operations
+#[derive(Debug, Clone)]
+pub struct ReceiptRepository {
+    pool: PgPool,
+}
+
+impl ReceiptRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Record a receipt whose upload URL is being handed out
+    #[instrument(skip(self, receipt), fields(transaction_id = %receipt.transaction_id))]
+    pub async fn create_pending(&self, receipt: &NewReceipt) -> Result<Receipt> {
+        let stored = sqlx::query_as::<_, Receipt>(
+            r#"
+            INSERT INTO receipts (id, transaction_id, user_id, uploaded_by, object_key, content_type, size_bytes, file_name)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING *
+            "#,
+        )
+        .bind(receipt.id)
+        .bind(&receipt.transaction_id)
+        .bind(receipt.user_id)
+        .bind(receipt.uploaded_by)
+        .bind(&receipt.object_key)
+        .bind(&receipt.content_type)
+        .bind(receipt.size_bytes)
+        .bind(&receipt.file_name)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(stored)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn find(&self, receipt_id: Uuid) -> Result<Option<Receipt>> {
+        let receipt = sqlx::query_as::<_, Receipt>("SELECT * FROM receipts WHERE id = $1")
+            .bind(receipt_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(receipt)
+    }
+
+    /// Mark a pending receipt attached with the size S3 reports; returns `None` if it was not pending
+    #[instrument(skip(self))]
+    pub async fn mark_attached(&self, receipt_id: Uuid, size_bytes: i64) -> Result<Option<Receipt>> {
+        let receipt = sqlx::query_as::<_, Receipt>(
+            r#"
+            UPDATE receipts
+            SET status = 'attached', size_bytes = $2, attached_at = NOW()
+            WHERE id = $1 AND status = 'pending'
+            RETURNING *
+            "#,
+        )
+        .bind(receipt_id)
+        .bind(size_bytes)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        if receipt.is_some() {
+            info!(receipt_id = %receipt_id, size_bytes, "Receipt attached");
+        }
+        Ok(receipt)
+    }
+
+    /// Store what a scan read from an attached receipt, replacing an earlier scan
+    #[instrument(skip(self, extraction))]
+    pub async fn store_extraction(
+        &self,
+        receipt_id: Uuid,
+        extraction: &ReceiptExtraction,
+        model: &str,
+    ) -> Result<Option<Receipt>> {
+        let receipt = sqlx::query_as::<_, Receipt>(
+            r#"
+            UPDATE receipts
+            SET extraction = $2, scan_model = $3, scanned_at = NOW()
+            WHERE id = $1 AND status = 'attached'
+            RETURNING *
+            "#,
+        )
+        .bind(receipt_id)
+        .bind(Json(extraction))
+        .bind(model)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(receipt)
+    }
+
+    /// Attached receipts of a transaction, oldest first
+    #[instrument(skip(self))]
+    pub async fn list_for_transaction(&self, transaction_id: &str) -> Result<Vec<Receipt>> {
+        let receipts = sqlx::query_as::<_, Receipt>(
+            r#"
+            SELECT * FROM receipts
+            WHERE transaction_id = $1 AND status = 'attached'
+            ORDER BY created_at
+            "#,
+        )
+        .bind(transaction_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(receipts)
+    }
+
+    /// Delete a receipt row, returning it so the caller can remove the object
+    #[instrument(skip(self))]
+    pub async fn delete(&self, receipt_id: Uuid) -> Result<Option<Receipt>> {
+        let receipt = sqlx::query_as::<_, Receipt>("DELETE FROM receipts WHERE id = $1 RETURNING *")
+            .bind(receipt_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(receipt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_receipt() {
+        assert_eq!(validate_receipt("Image/JPEG", 1024), Ok("image/jpeg".to_string()));
+        assert_eq!(validate_receipt("application/pdf", MAX_RECEIPT_BYTES), Ok("application/pdf".to_string()));
+        assert!(validate_receipt("text/html", 1024).is_err());
+        assert!(validate_receipt("image/png", 0).is_err());
+        assert!(validate_receipt("image/png", MAX_RECEIPT_BYTES + 1).is_err());
+    }
+
+    #[test]
+    fn test_total_matches() {
+        let extraction = ReceiptExtraction {
+            total: Some(42.1),
+            ..Default::default()
+        };
+        assert!(extraction.total_matches(42.10));
+        assert!(extraction.total_matches(-42.10));
+        assert!(!extraction.total_matches(42.11));
+        assert!(!ReceiptExtraction::default().total_matches(0.0));
+    }
+
+    #[test]
+    fn test_receipt_object_key() {
+        let user_id = Uuid::nil();
+        let receipt_id = Uuid::nil();
+        assert_eq!(receipt_extension("image/png"), Some("png"));
+        assert_eq!(
+            receipt_object_key(user_id, "txn_1", receipt_id, "png"),
+            format!("receipts/{}/txn_1/{}.png", user_id, receipt_id)
+        );
+    }
+}
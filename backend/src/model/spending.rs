@@ -0,0 +1,525 @@
+use crate::model::read_pool::ReadPool;
+use anyhow::{Context, Result};
+use chrono::{Datelike, Duration, Months, NaiveDate};
+use sqlx::PgPool;
+use tracing::{info, instrument};
+use uuid::Uuid;
+
+/// Categories that move money between the user's own accounts and are not spending
+const NON_SPENDING_CATEGORIES: &[&str] = &["TRANSFER_IN", "TRANSFER_OUT"];
+
+/// Dimension spending is grouped by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpendingGroupBy {
+    Category,
+    Merchant,
+    Month,
+}
+
+impl SpendingGroupBy {
+    /// SQL expression producing the group key from `transactions t` joined to `transaction_categories c`
+    /// and `transaction_splits s`
+    fn key_expression(&self) -> &'static str {
+        match self {
+            SpendingGroupBy::Category => "COALESCE(s.category, c.category, 'UNCATEGORIZED')",
+            SpendingGroupBy::Merchant => "COALESCE(t.merchant_name, t.name)",
+            SpendingGroupBy::Month => "to_char(t.date, 'YYYY-MM')",
+        }
+    }
+
+    /// Group key and row filter over `spending_monthly_totals a`; `None` when the monthly
+    /// totals can't answer this grouping
+    fn aggregate(&self) -> Option<MonthlyAggregate> {
+        match self {
+            SpendingGroupBy::Category => Some(MonthlyAggregate {
+                key_expression: "a.category",
+                filter: "a.category <> ''",
+            }),
+            SpendingGroupBy::Month => Some(MonthlyAggregate {
+                key_expression: "to_char(a.month, 'YYYY-MM')",
+                filter: "a.category = ''",
+            }),
+            SpendingGroupBy::Merchant => None,
+        }
+    }
+}
+
+/// How a grouping reads `spending_monthly_totals`
+#[derive(Debug, Clone, Copy)]
+struct MonthlyAggregate {
+    key_expression: &'static str,
+    filter: &'static str,
+}
+
+/// Whole-period totals read the all-category rows
+const TOTAL_AGGREGATE: MonthlyAggregate = MonthlyAggregate {
+    key_expression: "NULL::TEXT",
+    filter: "a.category = ''",
+};
+
+/// Reads without monthly totals scan every transaction in the window
+const NO_AGGREGATE: MonthlyAggregate = MonthlyAggregate {
+    key_expression: "NULL::TEXT",
+    filter: "FALSE",
+};
+
+/// Inclusive date range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpendingPeriod {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+impl SpendingPeriod {
+    pub fn new(start: NaiveDate, end: NaiveDate) -> Self {
+        Self { start, end }
+    }
+
+    pub fn days(&self) -> i64 {
+        (self.end - self.start).num_days() + 1
+    }
+
+    /// Period of the same length ending the day before this one starts
+    pub fn previous(&self) -> SpendingPeriod {
+        let end = self.start - Duration::days(1);
+        SpendingPeriod {
+            start: end - Duration::days(self.days() - 1),
+            end,
+        }
+    }
+
+    /// First day of the month before the one `start` falls in
+    fn previous_month_start(&self) -> NaiveDate {
+        let first = self.start.with_day(1).unwrap_or(self.start);
+        (first - Duration::days(1)).with_day(1).unwrap_or(first)
+    }
+}
+
+/// Spending of one group (or the whole period when `group_key` is `None`) in one currency
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct SpendingTotal {
+    pub group_key: Option<String>,
+    pub currency_code: String,
+    pub amount: f64,
+    pub transaction_count: i64,
+    /// Amount in the comparison period: the previous period, or the previous month for monthly groups
+    pub previous_amount: f64,
+    pub previous_transaction_count: i64,
+}
+
+impl SpendingTotal {
+    /// Percentage change from the comparison period; `None` without prior spending
+    pub fn change_percent(&self) -> Option<f64> {
+        if self.previous_amount.abs() < f64::EPSILON {
+            None
+        } else {
+            Some((self.amount - self.previous_amount) / self.previous_amount * 100.0)
+        }
+    }
+}
+
+/// Which parts of a date window are read from monthly totals and which from transactions
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct ReadPlan {
+    /// First days of months read from `spending_monthly_totals`
+    aggregated_months: Vec<NaiveDate>,
+    /// Inclusive date ranges scanned from `transactions`
+    raw_ranges: Vec<(NaiveDate, NaiveDate)>,
+}
+
+impl ReadPlan {
+    /// Plan reads of `start..=end`. A month comes from its totals when the window covers all of
+    /// it, `split` (the start of the current period) doesn't fall inside it, and its totals are
+    /// not waiting on a refresh; everything else is scanned.
+    fn new(start: NaiveDate, end: NaiveDate, split: NaiveDate, stale_months: &[NaiveDate], use_aggregates: bool) -> Self {
+        let mut plan = ReadPlan::default();
+        let mut month = start.with_day(1).unwrap_or(start);
+
+        while month <= end {
+            let Some(next_month) = month.checked_add_months(Months::new(1)) else {
+                break;
+            };
+            let month_end = next_month - Duration::days(1);
+            let whole_month = start <= month && month_end <= end;
+            let split_inside = month < split && split <= month_end;
+
+            if use_aggregates && whole_month && !split_inside && !stale_months.contains(&month) {
+                plan.aggregated_months.push(month);
+            } else {
+                let range = (start.max(month), end.min(month_end));
+                match plan.raw_ranges.last_mut() {
+                    Some(last) if last.1 + Duration::days(1) == range.0 => last.1 = range.1,
+                    _ => plan.raw_ranges.push(range),
+                }
+            }
+            month = next_month;
+        }
+
+        plan
+    }
+
+    fn range_starts(&self) -> Vec<NaiveDate> {
+        self.raw_ranges.iter().map(|(start, _)| *start).collect()
+    }
+
+    fn range_ends(&self) -> Vec<NaiveDate> {
+        self.raw_ranges.iter().map(|(_, end)| *end).collect()
+    }
+}
+
+/// Posted outflows of one user excluding transfers between own accounts, as per-day rows of
+/// `(group_key, currency_code, period_date, amount, transaction_count)`.
+///
+/// Dates in the ranges `$2[i]..=$3[i]` are scanned from transactions, where a split transaction
+/// contributes one row per part with the part's amount and category. Months in `$6` come from
+/// the monthly totals, dated the first of the month.
+fn spending_cte(key_expression: &str, aggregate: MonthlyAggregate) -> String {
+    format!(
+        r#"
+        spend AS (
+            SELECT
+                {} AS group_key,
+                COALESCE(t.iso_currency_code, t.unofficial_currency_code, '') AS currency_code,
+                t.date AS period_date,
+                SUM(COALESCE(s.amount, t.amount)) AS amount,
+                COUNT(DISTINCT t.transaction_id) AS transaction_count
+            FROM transactions t
+            JOIN UNNEST($2::DATE[], $3::DATE[]) AS r(range_start, range_end)
+                ON t.date BETWEEN r.range_start AND r.range_end
+            LEFT JOIN transaction_categories c ON c.transaction_id = t.transaction_id
+            LEFT JOIN transaction_splits s ON s.transaction_id = t.transaction_id
+            WHERE t.user_id = $1
+              AND t.removed_at IS NULL
+              AND NOT t.pending
+              AND t.amount > 0
+              AND (COALESCE(s.category, c.category) IS NULL OR COALESCE(s.category, c.category) <> ALL($4))
+            GROUP BY 1, 2, 3
+            UNION ALL
+            SELECT {} AS group_key, a.currency_code, a.month, a.amount, a.transaction_count
+            FROM spending_monthly_totals a
+            WHERE a.user_id = $1 AND a.month = ANY($6) AND {}
+        )
+        "#,
+        key_expression, aggregate.key_expression, aggregate.filter
+    )
+}
+
+/// Aggregates spending from stored transactions in SQL
+#[derive(Debug, Clone)]
+pub struct SpendingRepository {
+    pool: PgPool,
+    reads: ReadPool,
+}
+
+impl SpendingRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            reads: ReadPool::new(pool.clone()),
+            pool,
+        }
+    }
+
+    /// Run spending summaries on `reads`; they may lag recent syncs when it routes to a replica
+    pub fn with_read_pool(mut self, reads: ReadPool) -> Self {
+        self.reads = reads;
+        self
+    }
+
+    /// Months of a user whose monthly totals are waiting on a refresh
+    async fn stale_months(&self, user_id: Uuid) -> Result<Vec<NaiveDate>> {
+        let months = self
+            .reads
+            .fetch(|pool| async move {
+                sqlx::query_scalar("SELECT month FROM spending_refresh_queue WHERE user_id = $1")
+                    .bind(user_id)
+                    .fetch_all(&pool)
+                    .await
+            })
+            .await?;
+
+        Ok(months)
+    }
+
+    /// Total spending per currency in `period`, compared with the previous period of equal length
+    #[instrument(skip(self))]
+    pub async fn totals(&self, user_id: Uuid, period: SpendingPeriod) -> Result<Vec<SpendingTotal>> {
+        let previous = period.previous();
+        let stale_months = self.stale_months(user_id).await?;
+        let plan = ReadPlan::new(previous.start, period.end, period.start, &stale_months, true);
+        let query = format!(
+            r#"
+            WITH {}
+            SELECT
+                NULL::TEXT AS group_key,
+                currency_code,
+                COALESCE(SUM(amount) FILTER (WHERE period_date >= $5), 0) AS amount,
+                COALESCE(SUM(transaction_count) FILTER (WHERE period_date >= $5), 0)::BIGINT AS transaction_count,
+                COALESCE(SUM(amount) FILTER (WHERE period_date < $5), 0) AS previous_amount,
+                COALESCE(SUM(transaction_count) FILTER (WHERE period_date < $5), 0)::BIGINT AS previous_transaction_count
+            FROM spend
+            GROUP BY currency_code
+            ORDER BY amount DESC, currency_code
+            "#,
+            spending_cte("NULL::TEXT", TOTAL_AGGREGATE)
+        );
+
+        let totals = self
+            .reads
+            .fetch(|pool| {
+                let (query, plan) = (&query, &plan);
+                async move {
+                    sqlx::query_as::<_, SpendingTotal>(query)
+                        .bind(user_id)
+                        .bind(plan.range_starts())
+                        .bind(plan.range_ends())
+                        .bind(NON_SPENDING_CATEGORIES)
+                        .bind(period.start)
+                        .bind(&plan.aggregated_months)
+                        .fetch_all(&pool)
+                        .await
+                }
+            })
+            .await?;
+
+        Ok(totals)
+    }
+
+    /// Spending in `period` grouped by `group_by`, largest first, keeping at most `limit` groups.
+    ///
+    /// Category and merchant groups are compared with the previous period of equal length;
+    /// monthly groups are listed oldest first and compared with the month before.
+    #[instrument(skip(self))]
+    pub async fn by_group(
+        &self,
+        user_id: Uuid,
+        period: SpendingPeriod,
+        group_by: SpendingGroupBy,
+        limit: i64,
+    ) -> Result<Vec<SpendingTotal>> {
+        let (query, window_start) = match group_by {
+            SpendingGroupBy::Month => (
+                format!(
+                    r#"
+                    WITH {},
+                    monthly AS (
+                        SELECT group_key, currency_code, SUM(amount) AS amount, SUM(transaction_count)::BIGINT AS transaction_count
+                        FROM spend
+                        GROUP BY group_key, currency_code
+                    ),
+                    compared AS (
+                        SELECT
+                            group_key,
+                            currency_code,
+                            amount,
+                            transaction_count,
+                            CASE WHEN LAG(group_key) OVER w = to_char(to_date(group_key, 'YYYY-MM') - INTERVAL '1 month', 'YYYY-MM')
+                                THEN LAG(amount) OVER w ELSE 0 END AS previous_amount,
+                            CASE WHEN LAG(group_key) OVER w = to_char(to_date(group_key, 'YYYY-MM') - INTERVAL '1 month', 'YYYY-MM')
+                                THEN LAG(transaction_count) OVER w ELSE 0 END AS previous_transaction_count
+                        FROM monthly
+                        WINDOW w AS (PARTITION BY currency_code ORDER BY group_key)
+                    )
+                    SELECT * FROM compared
+                    WHERE group_key >= to_char($5::DATE, 'YYYY-MM')
+                    ORDER BY group_key, amount DESC
+                    LIMIT $7
+                    "#,
+                    spending_cte(group_by.key_expression(), group_by.aggregate().unwrap_or(NO_AGGREGATE))
+                ),
+                period.previous_month_start(),
+            ),
+            SpendingGroupBy::Category | SpendingGroupBy::Merchant => (
+                format!(
+                    r#"
+                    WITH {}
+                    SELECT
+                        group_key,
+                        currency_code,
+                        COALESCE(SUM(amount) FILTER (WHERE period_date >= $5), 0) AS amount,
+                        COALESCE(SUM(transaction_count) FILTER (WHERE period_date >= $5), 0)::BIGINT AS transaction_count,
+                        COALESCE(SUM(amount) FILTER (WHERE period_date < $5), 0) AS previous_amount,
+                        COALESCE(SUM(transaction_count) FILTER (WHERE period_date < $5), 0)::BIGINT AS previous_transaction_count
+                    FROM spend
+                    GROUP BY group_key, currency_code
+                    ORDER BY amount DESC, previous_amount DESC, group_key
+                    LIMIT $7
+                    "#,
+                    spending_cte(group_by.key_expression(), group_by.aggregate().unwrap_or(NO_AGGREGATE))
+                ),
+                period.previous().start,
+            ),
+        };
+
+        // Monthly groups are whole months already, so the period start never splits one
+        let split = match group_by {
+            SpendingGroupBy::Month => window_start,
+            SpendingGroupBy::Category | SpendingGroupBy::Merchant => period.start,
+        };
+        let stale_months = self.stale_months(user_id).await?;
+        let plan = ReadPlan::new(window_start, period.end, split, &stale_months, group_by.aggregate().is_some());
+
+        let groups = self
+            .reads
+            .fetch(|pool| {
+                let (query, plan) = (&query, &plan);
+                async move {
+                    sqlx::query_as::<_, SpendingTotal>(query)
+                        .bind(user_id)
+                        .bind(plan.range_starts())
+                        .bind(plan.range_ends())
+                        .bind(NON_SPENDING_CATEGORIES)
+                        .bind(period.start)
+                        .bind(&plan.aggregated_months)
+                        .bind(limit)
+                        .fetch_all(&pool)
+                        .await
+                }
+            })
+            .await?;
+
+        Ok(groups)
+    }
+
+    /// Recompute the monthly totals of up to `limit` queued months, oldest first; returns how
+    /// many months were refreshed. Months queued again while this runs stay queued.
+    #[instrument(skip(self))]
+    pub async fn refresh_monthly_totals(&self, limit: i64) -> Result<usize> {
+        let mut tx = self.pool.begin().await?;
+
+        let months = sqlx::query_as::<_, (Uuid, NaiveDate)>(
+            r#"
+            DELETE FROM spending_refresh_queue
+            WHERE (user_id, month) IN (
+                SELECT user_id, month FROM spending_refresh_queue
+                ORDER BY queued_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING user_id, month
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&mut *tx)
+        .await
+        .context("Failed to dequeue spending months")?;
+        if months.is_empty() {
+            return Ok(0);
+        }
+        let user_ids: Vec<Uuid> = months.iter().map(|(user_id, _)| *user_id).collect();
+        let month_starts: Vec<NaiveDate> = months.iter().map(|(_, month)| *month).collect();
+
+        sqlx::query(
+            r#"
+            DELETE FROM spending_monthly_totals a
+            USING UNNEST($1::UUID[], $2::DATE[]) AS d(user_id, month)
+            WHERE a.user_id = d.user_id AND a.month = d.month
+            "#,
+        )
+        .bind(&user_ids)
+        .bind(&month_starts)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to clear monthly spending totals")?;
+
+        sqlx::query(
+            r#"
+            WITH spend AS (
+                SELECT
+                    d.user_id,
+                    d.month,
+                    COALESCE(s.category, c.category, 'UNCATEGORIZED') AS category,
+                    COALESCE(t.iso_currency_code, t.unofficial_currency_code, '') AS currency_code,
+                    t.transaction_id,
+                    COALESCE(s.amount, t.amount) AS amount
+                FROM UNNEST($1::UUID[], $2::DATE[]) AS d(user_id, month)
+                JOIN transactions t
+                    ON t.user_id = d.user_id
+                   AND t.date >= d.month
+                   AND t.date < (d.month + INTERVAL '1 month')::DATE
+                LEFT JOIN transaction_categories c ON c.transaction_id = t.transaction_id
+                LEFT JOIN transaction_splits s ON s.transaction_id = t.transaction_id
+                WHERE t.removed_at IS NULL
+                  AND NOT t.pending
+                  AND t.amount > 0
+                  AND (COALESCE(s.category, c.category) IS NULL OR COALESCE(s.category, c.category) <> ALL($3))
+            )
+            INSERT INTO spending_monthly_totals (user_id, month, category, currency_code, amount, transaction_count)
+            SELECT user_id, month, category, currency_code, SUM(amount), COUNT(DISTINCT transaction_id)
+            FROM spend
+            GROUP BY user_id, month, category, currency_code
+            UNION ALL
+            SELECT user_id, month, '', currency_code, SUM(amount), COUNT(DISTINCT transaction_id)
+            FROM spend
+            GROUP BY user_id, month, currency_code
+            "#,
+        )
+        .bind(&user_ids)
+        .bind(&month_starts)
+        .bind(NON_SPENDING_CATEGORIES)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to compute monthly spending totals")?;
+
+        tx.commit().await?;
+
+        info!(months = months.len(), "Refreshed monthly spending totals");
+        Ok(months.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_previous_period_has_equal_length() {
+        let period = SpendingPeriod::new(date(2024, 3, 1), date(2024, 3, 31));
+        assert_eq!(period.days(), 31);
+        assert_eq!(period.previous(), SpendingPeriod::new(date(2024, 1, 30), date(2024, 2, 29)));
+        assert_eq!(period.previous_month_start(), date(2024, 2, 1));
+
+        let january = SpendingPeriod::new(date(2024, 1, 15), date(2024, 1, 20));
+        assert_eq!(january.previous_month_start(), date(2023, 12, 1));
+    }
+
+    #[test]
+    fn test_read_plan_aggregates_whole_months() {
+        // Mid-January to mid-April, current period starting March 1st
+        let plan = ReadPlan::new(date(2024, 1, 15), date(2024, 4, 10), date(2024, 3, 1), &[], true);
+        assert_eq!(plan.aggregated_months, vec![date(2024, 2, 1), date(2024, 3, 1)]);
+        assert_eq!(
+            plan.raw_ranges,
+            vec![(date(2024, 1, 15), date(2024, 1, 31)), (date(2024, 4, 1), date(2024, 4, 10))]
+        );
+    }
+
+    #[test]
+    fn test_read_plan_scans_split_and_stale_months() {
+        let plan = ReadPlan::new(date(2024, 1, 1), date(2024, 4, 30), date(2024, 2, 15), &[date(2024, 3, 1)], true);
+        assert_eq!(plan.aggregated_months, vec![date(2024, 1, 1), date(2024, 4, 1)]);
+        assert_eq!(plan.raw_ranges, vec![(date(2024, 2, 1), date(2024, 3, 31))]);
+
+        let plan = ReadPlan::new(date(2024, 1, 1), date(2024, 2, 29), date(2024, 2, 1), &[], false);
+        assert!(plan.aggregated_months.is_empty());
+        assert_eq!(plan.raw_ranges, vec![(date(2024, 1, 1), date(2024, 2, 29))]);
+    }
+
+    #[test]
+    fn test_change_percent() {
+        let total = |amount, previous_amount| SpendingTotal {
+            group_key: None,
+            currency_code: "USD".to_string(),
+            amount,
+            transaction_count: 1,
+            previous_amount,
+            previous_transaction_count: 1,
+        };
+        assert_eq!(total(150.0, 100.0).change_percent(), Some(50.0));
+        assert_eq!(total(50.0, 100.0).change_percent(), Some(-50.0));
+        assert_eq!(total(50.0, 0.0).change_percent(), None);
+    }
+}
@@ -0,0 +1,165 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::collections::HashSet;
+use tracing::{info, instrument};
+use uuid::Uuid;
+
+/// S3 key of a statement PDF; keys are grouped under the owning user so a prefix covers all their files
+pub fn statement_object_key(user_id: Uuid, account_id: &str, year: i32, month: u32, statement_id: &str) -> String {
+    format!("statements/{}/{}/{}-{:02}-{}.pdf", user_id, account_id, year, month, statement_id)
+}
+
+/// File name suggested when a statement is downloaded
+pub fn statement_file_name(year: i32, month: i32) -> String {
+    format!("statement-{}-{:02}.pdf", year, month)
+}
+
+/// Bank statement PDF stored in S3
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct BankStatement {
+    pub id: Uuid,
+    /// Plaid statement identifier
+    pub statement_id: String,
+    pub user_id: Uuid,
+    pub item_id: String,
+    pub account_id: String,
+    pub year: i32,
+    pub month: i32,
+    pub object_key: String,
+    pub size_bytes: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Statement to record after its PDF is stored
+#[derive(Debug, Clone)]
+pub struct NewBankStatement {
+    pub statement_id: String,
+    pub user_id: Uuid,
+    pub item_id: String,
+    pub account_id: String,
+    pub year: i32,
+    pub month: i32,
+    pub object_key: String,
+    pub size_bytes: i64,
+}
+
+/// Bank statement repository for database operations
+#[derive(Debug, Clone)]
+pub struct StatementRepository {
+    pool: PgPool,
+}
+
+impl StatementRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Record a stored statement; recording one already known is a no-op returning `None`
+    #[instrument(skip(self, statement), fields(statement_id = %statement.statement_id))]
+    pub async fn record(&self, statement: &NewBankStatement) -> Result<Option<BankStatement>> {
+        let stored = sqlx::query_as::<_, BankStatement>(
+            r#"
+            INSERT INTO statements (statement_id, user_id, item_id, account_id, year, month, object_key, size_bytes)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (statement_id) DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(&statement.statement_id)
+        .bind(statement.user_id)
+        .bind(&statement.item_id)
+        .bind(&statement.account_id)
+        .bind(statement.year)
+        .bind(statement.month)
+        .bind(&statement.object_key)
+        .bind(statement.size_bytes)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        if stored.is_some() {
+            info!(account_id = %statement.account_id, year = statement.year, month = statement.month, "Statement stored");
+        }
+        Ok(stored)
+    }
+
+    /// Plaid IDs of an item's statements that are already stored
+    #[instrument(skip(self))]
+    pub async fn known_statement_ids(&self, item_id: &str) -> Result<HashSet<String>> {
+        let ids: Vec<String> = sqlx::query_scalar("SELECT statement_id FROM statements WHERE item_id = $1")
+            .bind(item_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(ids.into_iter().collect())
+    }
+
+    #[instrument(skip(self))]
+    pub async fn find(&self, id: Uuid) -> Result<Option<BankStatement>> {
+        let statement = sqlx::query_as::<_, BankStatement>("SELECT * FROM statements WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(statement)
+    }
+
+    /// Statements of the user's own accounts plus the given (shared) accounts, newest first,
+    /// optionally limited to one year
+    #[instrument(skip(self, account_ids), fields(account_count = account_ids.len()))]
+    pub async fn list_visible(
+        &self,
+        user_id: Uuid,
+        account_ids: &[String],
+        year: Option<i32>,
+    ) -> Result<Vec<BankStatement>> {
+        let statements = sqlx::query_as::<_, BankStatement>(
+            r#"
+            SELECT * FROM statements
+            WHERE (user_id = $1 OR account_id = ANY($2))
+              AND ($3::INTEGER IS NULL OR year = $3)
+            ORDER BY year DESC, month DESC, account_id
+            "#,
+        )
+        .bind(user_id)
+        .bind(account_ids)
+        .bind(year)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(statements)
+    }
+
+    /// Statements of one account, newest first, optionally limited to one year
+    #[instrument(skip(self))]
+    pub async fn list_for_account(&self, account_id: &str, year: Option<i32>) -> Result<Vec<BankStatement>> {
+        let statements = sqlx::query_as::<_, BankStatement>(
+            r#"
+            SELECT * FROM statements
+            WHERE account_id = $1 AND ($2::INTEGER IS NULL OR year = $2)
+            ORDER BY year DESC, month DESC
+            "#,
+        )
+        .bind(account_id)
+        .bind(year)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(statements)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_statement_object_key() {
+        let user_id = Uuid::nil();
+        assert_eq!(
+            statement_object_key(user_id, "acc_1", 2024, 3, "st_1"),
+            format!("statements/{}/acc_1/2024-03-st_1.pdf", user_id)
+        );
+        assert_eq!(statement_file_name(2024, 3), "statement-2024-03.pdf");
+    }
+}
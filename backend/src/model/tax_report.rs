@@ -0,0 +1,66 @@
+use anyhow::Result;
+use chrono::NaiveDate;
+use sqlx::PgPool;
+use tracing::instrument;
+use uuid::Uuid;
+
+/// Posted transaction, or one part of a split transaction, with its effective category and tags
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct TaxLine {
+    pub transaction_id: String,
+    pub date: NaiveDate,
+    pub name: String,
+    pub merchant_name: Option<String>,
+    /// Plaid sign convention: positive amounts are outflows
+    pub amount: f64,
+    pub currency_code: String,
+    pub category: Option<String>,
+    pub tags: Vec<String>,
+}
+
+/// Loads the transactions a tax report is built from
+#[derive(Debug, Clone)]
+pub struct TaxReportRepository {
+    pool: PgPool,
+}
+
+impl TaxReportRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Posted transactions of a user dated within `start..=end`, oldest first; a split transaction
+    /// contributes one line per part with the part's amount and category
+    #[instrument(skip(self))]
+    pub async fn lines(&self, user_id: Uuid, start: NaiveDate, end: NaiveDate) -> Result<Vec<TaxLine>> {
+        let lines = sqlx::query_as::<_, TaxLine>(
+            r#"
+            SELECT
+                t.transaction_id,
+                t.date,
+                COALESCE(s.description, t.name) AS name,
+                t.merchant_name,
+                COALESCE(s.amount, t.amount) AS amount,
+                COALESCE(t.iso_currency_code, t.unofficial_currency_code, '') AS currency_code,
+                COALESCE(s.category, c.category) AS category,
+                COALESCE(a.tags, '{}') AS tags
+            FROM transactions t
+            LEFT JOIN transaction_categories c ON c.transaction_id = t.transaction_id
+            LEFT JOIN transaction_splits s ON s.transaction_id = t.transaction_id
+            LEFT JOIN transaction_annotations a ON a.transaction_id = t.transaction_id
+            WHERE t.user_id = $1
+              AND t.removed_at IS NULL
+              AND NOT t.pending
+              AND t.date BETWEEN $2 AND $3
+            ORDER BY t.date, t.transaction_id, s.position
+            "#,
+        )
+        .bind(user_id)
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(lines)
+    }
+}
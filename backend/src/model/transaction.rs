@@ -0,0 +1,864 @@
+use crate::adapter::plaid::{
+    BankTransaction, TransactionLocation, TransactionPaymentMeta, TransactionSyncResponse,
+};
+use crate::model::read_pool::ReadPool;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use std::collections::HashMap;
+use sqlx::types::Json;
+use sqlx::{PgPool, Postgres, Transaction as DbTransaction};
+use tracing::{debug, info, instrument};
+use uuid::Uuid;
+
+/// Persisted transaction row
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Transaction {
+    pub id: Uuid,
+    pub transaction_id: String,
+    pub account_id: String,
+    pub item_id: String,
+    pub user_id: Uuid,
+    pub amount: f64,
+    pub iso_currency_code: Option<String>,
+    pub unofficial_currency_code: Option<String>,
+    pub date: NaiveDate,
+    pub datetime: Option<DateTime<Utc>>,
+    pub authorized_date: Option<NaiveDate>,
+    pub authorized_datetime: Option<DateTime<Utc>>,
+    pub name: String,
+    pub merchant_name: Option<String>,
+    pub original_description: Option<String>,
+    pub category: Vec<String>,
+    pub category_id: Option<String>,
+    pub check_number: Option<String>,
+    pub location: Option<Json<TransactionLocation>>,
+    pub payment_meta: Option<Json<TransactionPaymentMeta>>,
+    pub pending: bool,
+    pub pending_transaction_id: Option<String>,
+    pub account_owner: Option<String>,
+    pub transaction_type: String,
+    pub transaction_code: Option<String>,
+    pub removed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Counts of rows touched by applying one sync page
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SyncSummary {
+    pub added: usize,
+    pub modified: usize,
+    pub removed: usize,
+}
+
+impl SyncSummary {
+    pub fn merge(&mut self, other: SyncSummary) {
+        self.added += other.added;
+        self.modified += other.modified;
+        self.removed += other.removed;
+    }
+}
+
+/// Filters for listing a user's transactions
+#[derive(Debug, Clone, Default)]
+pub struct TransactionFilter {
+    pub account_id: Option<String>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub limit: i64,
+    pub offset: i64,
+    /// Load location, payment meta and the raw description; skipped when callers don't render them
+    pub include_details: bool,
+}
+
+/// Criteria for searching a user's live transactions; unset and empty fields don't filter
+#[derive(Debug, Clone, Default)]
+pub struct TransactionSearch {
+    /// Case-insensitive substring of the name or merchant name
+    pub query: Option<String>,
+    /// Inclusive bounds on the absolute amount, so ranges read the same for inflows and outflows
+    pub min_amount: Option<f64>,
+    pub max_amount: Option<f64>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    /// Effective categories: the user's override, else the AI category
+    pub categories: Vec<String>,
+    pub account_ids: Vec<String>,
+    pub pending: Option<bool>,
+    pub limit: i64,
+    /// Load location, payment meta and the raw description
+    pub include_details: bool,
+}
+
+/// `ILIKE` pattern matching `query` anywhere, with its wildcard characters escaped
+pub(crate) fn contains_pattern(query: &str) -> String {
+    let escaped = query
+        .trim()
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+fn parse_plaid_date(value: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .with_context(|| format!("Invalid Plaid date '{}'", value))
+}
+
+/// Rows per batched upsert statement; each row binds 22 array elements
+pub const UPSERT_BATCH_SIZE: usize = 1000;
+
+/// A batch of transactions as one array per column, bound to `UNNEST` so the batch is
+/// upserted in a single statement
+#[derive(Debug, Default)]
+struct TransactionColumns {
+    transaction_id: Vec<String>,
+    account_id: Vec<String>,
+    amount: Vec<f64>,
+    iso_currency_code: Vec<Option<String>>,
+    unofficial_currency_code: Vec<Option<String>>,
+    date: Vec<NaiveDate>,
+    datetime: Vec<Option<DateTime<Utc>>>,
+    authorized_date: Vec<Option<NaiveDate>>,
+    authorized_datetime: Vec<Option<DateTime<Utc>>>,
+    name: Vec<String>,
+    merchant_name: Vec<Option<String>>,
+    original_description: Vec<Option<String>>,
+    /// Postgres arrays can't nest, so each row's categories travel as a JSON array
+    category: Vec<Json<Vec<String>>>,
+    category_id: Vec<Option<String>>,
+    check_number: Vec<Option<String>>,
+    location: Vec<Option<Json<TransactionLocation>>>,
+    payment_meta: Vec<Option<Json<TransactionPaymentMeta>>>,
+    pending: Vec<bool>,
+    pending_transaction_id: Vec<Option<String>>,
+    account_owner: Vec<Option<String>>,
+    transaction_type: Vec<String>,
+    transaction_code: Vec<Option<String>>,
+}
+
+impl TransactionColumns {
+    /// Columns for `transactions`; when a transaction ID repeats, its last version wins
+    fn new<'a>(transactions: impl IntoIterator<Item = &'a BankTransaction>) -> Result<Self> {
+        let mut latest: Vec<&BankTransaction> = Vec::new();
+        let mut positions: HashMap<&str, usize> = HashMap::new();
+        for transaction in transactions {
+            match positions.get(transaction.transaction_id.as_str()) {
+                Some(&index) => latest[index] = transaction,
+                None => {
+                    positions.insert(&transaction.transaction_id, latest.len());
+                    latest.push(transaction);
+                }
+            }
+        }
+
+        let mut columns = Self::default();
+        for transaction in latest {
+            let authorized_date = transaction
+                .authorized_date
+                .as_deref()
+                .map(parse_plaid_date)
+                .transpose()?;
+            columns.transaction_id.push(transaction.transaction_id.clone());
+            columns.account_id.push(transaction.account_id.clone());
+            columns.amount.push(transaction.amount);
+            columns.iso_currency_code.push(transaction.iso_currency_code.clone());
+            columns.unofficial_currency_code.push(transaction.unofficial_currency_code.clone());
+            columns.date.push(parse_plaid_date(&transaction.date)?);
+            columns.datetime.push(transaction.datetime);
+            columns.authorized_date.push(authorized_date);
+            columns.authorized_datetime.push(transaction.authorized_datetime);
+            columns.name.push(transaction.name.clone());
+            columns.merchant_name.push(transaction.merchant_name.clone());
+            columns.original_description.push(transaction.original_description.clone());
+            columns.category.push(Json(transaction.category.clone()));
+            columns.category_id.push(transaction.category_id.clone());
+            columns.check_number.push(transaction.check_number.clone());
+            columns.location.push(transaction.location.clone().map(Json));
+            columns.payment_meta.push(transaction.payment_meta.clone().map(Json));
+            columns.pending.push(transaction.pending);
+            columns.pending_transaction_id.push(transaction.pending_transaction_id.clone());
+            columns.account_owner.push(transaction.account_owner.clone());
+            columns.transaction_type.push(transaction.transaction_type.clone());
+            columns.transaction_code.push(transaction.transaction_code.clone());
+        }
+        Ok(columns)
+    }
+
+    fn len(&self) -> usize {
+        self.transaction_id.len()
+    }
+}
+
+/// Transaction repository for database operations
+#[derive(Debug, Clone)]
+pub struct TransactionRepository {
+    pool: PgPool,
+    reads: ReadPool,
+}
+
+impl TransactionRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            reads: ReadPool::new(pool.clone()),
+            pool,
+        }
+    }
+
+    /// Run transaction lists and searches on `reads`, which may route them to a read replica
+    pub fn with_read_pool(mut self, reads: ReadPool) -> Self {
+        self.reads = reads;
+        self
+    }
+
+    /// Apply one `/transactions/sync` page atomically.
+    ///
+    /// Added and modified transactions are upserted and removed ones soft-deleted, so
+    /// replaying a page is a no-op. `next_cursor` is stored on the item in the same
+    /// database transaction; pass `None` for intermediate pages so an interrupted
+    /// pagination restarts from the last fully applied cursor. The cursor is only
+    /// stored while the item still holds `start_cursor`; if another sync moved it,
+    /// the whole page is rolled back.
+    #[instrument(skip(self, page, start_cursor, next_cursor), fields(
+        added = page.added.len(),
+        modified = page.modified.len(),
+        removed = page.removed.len()
+    ))]
+    pub async fn apply_sync_page(
+        &self,
+        user_id: Uuid,
+        item_id: &str,
+        page: &TransactionSyncResponse,
+        start_cursor: Option<&str>,
+        next_cursor: Option<&str>,
+    ) -> Result<SyncSummary> {
+        debug!(user_id = %user_id, item_id = %item_id, "Applying transaction sync page");
+
+        let mut tx = self.pool.begin().await?;
+        Self::lock_item(&mut tx, item_id).await?;
+
+        let changed: Vec<&BankTransaction> = page.added.iter().chain(page.modified.iter()).collect();
+        for batch in changed.chunks(UPSERT_BATCH_SIZE) {
+            Self::upsert_batch(&mut tx, user_id, item_id, batch.iter().copied()).await?;
+        }
+
+        let removed_ids: Vec<&str> = page.removed.iter().map(|r| r.transaction_id.as_str()).collect();
+        let removed = if removed_ids.is_empty() {
+            0
+        } else {
+            sqlx::query(
+                r#"
+                UPDATE transactions SET removed_at = NOW(), updated_at = NOW()
+                WHERE item_id = $1 AND transaction_id = ANY($2) AND removed_at IS NULL
+                "#,
+            )
+            .bind(item_id)
+            .bind(&removed_ids)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to remove transactions")?
+            .rows_affected() as usize
+        };
+
+        if let Some(cursor) = next_cursor {
+            let stored = sqlx::query(
+                r#"
+                UPDATE plaid_items SET sync_cursor = $2, last_synced_at = NOW(), updated_at = NOW()
+                WHERE item_id = $1 AND sync_cursor IS NOT DISTINCT FROM $3
+                "#,
+            )
+            .bind(item_id)
+            .bind(cursor)
+            .bind(start_cursor)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to store sync cursor")?
+            .rows_affected();
+            if stored == 0 {
+                bail!("Sync cursor of item {} moved during the sync; discarding page", item_id);
+            }
+        }
+
+        tx.commit().await?;
+
+        let summary = SyncSummary {
+            added: page.added.len(),
+            modified: page.modified.len(),
+            removed,
+        };
+        info!(
+            item_id = %item_id,
+            added = summary.added,
+            modified = summary.modified,
+            removed = summary.removed,
+            cursor_committed = next_cursor.is_some(),
+            "Applied transaction sync page"
+        );
+        Ok(summary)
+    }
+
+    /// Upsert transactions outside of a sync page (e.g. a `/transactions/get` backfill)
+    #[instrument(skip(self, transactions), fields(count = transactions.len()))]
+    pub async fn upsert_transactions(
+        &self,
+        user_id: Uuid,
+        item_id: &str,
+        transactions: &[BankTransaction],
+    ) -> Result<usize> {
+        self.upsert_transactions_in_batches(user_id, item_id, transactions, UPSERT_BATCH_SIZE)
+            .await
+    }
+
+    /// Upsert transactions `batch_size` rows per statement, all in one database transaction.
+    /// A batch size of one is the row-by-row upsert the batched statement replaced.
+    #[instrument(skip(self, transactions), fields(count = transactions.len()))]
+    pub async fn upsert_transactions_in_batches(
+        &self,
+        user_id: Uuid,
+        item_id: &str,
+        transactions: &[BankTransaction],
+        batch_size: usize,
+    ) -> Result<usize> {
+        let mut tx = self.pool.begin().await?;
+        Self::lock_item(&mut tx, item_id).await?;
+        for batch in transactions.chunks(batch_size.max(1)) {
+            Self::upsert_batch(&mut tx, user_id, item_id, batch).await?;
+        }
+        tx.commit().await?;
+
+        info!(item_id = %item_id, count = transactions.len(), "Upserted backfilled transactions");
+        Ok(transactions.len())
+    }
+
+    /// Serialize writers of one item's transactions. Without a unique constraint on
+    /// `transaction_id` alone, two concurrent upserts could both insert the same transaction.
+    async fn lock_item(tx: &mut DbTransaction<'_, Postgres>, item_id: &str) -> Result<()> {
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext('transactions:' || $1))")
+            .bind(item_id)
+            .execute(&mut **tx)
+            .await
+            .context("Failed to lock item transactions")?;
+        Ok(())
+    }
+
+    /// Upsert a batch in one statement: existing rows are updated by `transaction_id` (moving
+    /// partitions when the date changed) and the rest inserted. Rows owned by another user are left alone.
+    async fn upsert_batch<'a>(
+        tx: &mut DbTransaction<'_, Postgres>,
+        user_id: Uuid,
+        item_id: &str,
+        transactions: impl IntoIterator<Item = &'a BankTransaction>,
+    ) -> Result<()> {
+        let columns = TransactionColumns::new(transactions)?;
+        if columns.transaction_id.is_empty() {
+            return Ok(());
+        }
+
+        sqlx::query(
+            r#"
+            WITH incoming AS (
+                SELECT * FROM UNNEST(
+                    $3::text[], $4::text[], $5::float8[], $6::text[], $7::text[], $8::date[],
+                    $9::timestamptz[], $10::date[], $11::timestamptz[], $12::text[], $13::text[],
+                    $14::text[], $15::jsonb[], $16::text[], $17::text[], $18::jsonb[], $19::jsonb[],
+                    $20::bool[], $21::text[], $22::text[], $23::text[], $24::text[]
+                ) AS i(
+                    transaction_id, account_id, amount, iso_currency_code, unofficial_currency_code, date,
+                    datetime, authorized_date, authorized_datetime, name, merchant_name,
+                    original_description, category, category_id, check_number, location, payment_meta,
+                    pending, pending_transaction_id, account_owner, transaction_type, transaction_code
+                )
+            ),
+            updated AS (
+                UPDATE transactions t SET
+                    account_id = i.account_id,
+                    amount = i.amount,
+                    iso_currency_code = i.iso_currency_code,
+                    unofficial_currency_code = i.unofficial_currency_code,
+                    date = i.date,
+                    datetime = i.datetime,
+                    authorized_date = i.authorized_date,
+                    authorized_datetime = i.authorized_datetime,
+                    name = i.name,
+                    merchant_name = i.merchant_name,
+                    original_description = i.original_description,
+                    category = ARRAY(SELECT jsonb_array_elements_text(i.category)),
+                    category_id = i.category_id,
+                    check_number = i.check_number,
+                    location = i.location,
+                    payment_meta = i.payment_meta,
+                    pending = i.pending,
+                    pending_transaction_id = i.pending_transaction_id,
+                    account_owner = i.account_owner,
+                    transaction_type = i.transaction_type,
+                    transaction_code = i.transaction_code,
+                    removed_at = CASE WHEN t.merged_into IS NULL THEN NULL ELSE t.removed_at END,
+                    updated_at = NOW()
+                FROM incoming i
+                WHERE t.transaction_id = i.transaction_id AND t.user_id = $1
+                RETURNING t.transaction_id
+            )
+            INSERT INTO transactions (
+                transaction_id, account_id, item_id, user_id, amount, iso_currency_code,
+                unofficial_currency_code, date, datetime, authorized_date, authorized_datetime,
+                name, merchant_name, original_description, category, category_id, check_number,
+                location, payment_meta, pending, pending_transaction_id, account_owner,
+                transaction_type, transaction_code
+            )
+            SELECT
+                i.transaction_id, i.account_id, $2, $1, i.amount, i.iso_currency_code,
+                i.unofficial_currency_code, i.date, i.datetime, i.authorized_date, i.authorized_datetime,
+                i.name, i.merchant_name, i.original_description,
+                ARRAY(SELECT jsonb_array_elements_text(i.category)), i.category_id, i.check_number,
+                i.location, i.payment_meta, i.pending, i.pending_transaction_id, i.account_owner,
+                i.transaction_type, i.transaction_code
+            FROM incoming i
+            WHERE NOT EXISTS (SELECT 1 FROM transactions t WHERE t.transaction_id = i.transaction_id)
+            "#,
+        )
+        .bind(user_id)
+        .bind(item_id)
+        .bind(&columns.transaction_id)
+        .bind(&columns.account_id)
+        .bind(&columns.amount)
+        .bind(&columns.iso_currency_code)
+        .bind(&columns.unofficial_currency_code)
+        .bind(&columns.date)
+        .bind(&columns.datetime)
+        .bind(&columns.authorized_date)
+        .bind(&columns.authorized_datetime)
+        .bind(&columns.name)
+        .bind(&columns.merchant_name)
+        .bind(&columns.original_description)
+        .bind(&columns.category)
+        .bind(&columns.category_id)
+        .bind(&columns.check_number)
+        .bind(&columns.location)
+        .bind(&columns.payment_meta)
+        .bind(&columns.pending)
+        .bind(&columns.pending_transaction_id)
+        .bind(&columns.account_owner)
+        .bind(&columns.transaction_type)
+        .bind(&columns.transaction_code)
+        .execute(&mut **tx)
+        .await
+        .with_context(|| format!("Failed to upsert batch of {} transactions", columns.len()))?;
+
+        Ok(())
+    }
+
+    /// Create the monthly partitions for `months` (any day of each month); returns how many were new
+    #[instrument(skip(self, months), fields(count = months.len()))]
+    pub async fn ensure_partitions(&self, months: &[NaiveDate]) -> Result<usize> {
+        let mut created = 0;
+        for month in months {
+            let was_created: bool = sqlx::query_scalar("SELECT create_transactions_partition($1)")
+                .bind(month)
+                .fetch_one(&self.pool)
+                .await
+                .with_context(|| format!("Failed to create transactions partition for {}", month))?;
+            if was_created {
+                info!(month = %month.format("%Y-%m"), "Created transactions partition");
+                created += 1;
+            }
+        }
+        Ok(created)
+    }
+
+    /// List a user's live transactions, newest first
+    #[instrument(skip(self))]
+    pub async fn list_by_user(&self, user_id: Uuid, filter: &TransactionFilter) -> Result<Vec<Transaction>> {
+        debug!(user_id = %user_id, "Listing transactions for user");
+
+        let transactions = self
+            .reads
+            .fetch(|pool| async move {
+                sqlx::query_as::<_, Transaction>(
+                    r#"
+                    SELECT
+                        id, transaction_id, account_id, item_id, user_id, amount, iso_currency_code,
+                        unofficial_currency_code, date, datetime, authorized_date, authorized_datetime,
+                        name, merchant_name, category, category_id, check_number, pending,
+                        pending_transaction_id, account_owner, transaction_type, transaction_code,
+                        removed_at, created_at, updated_at,
+                        CASE WHEN $7 THEN original_description END AS original_description,
+                        CASE WHEN $7 THEN location END AS location,
+                        CASE WHEN $7 THEN payment_meta END AS payment_meta
+                    FROM transactions
+                    WHERE user_id = $1
+                      AND removed_at IS NULL
+                      AND ($2::VARCHAR IS NULL OR account_id = $2)
+                      AND ($3::DATE IS NULL OR date >= $3)
+                      AND ($4::DATE IS NULL OR date <= $4)
+                    ORDER BY date DESC, transaction_id
+                    LIMIT $5 OFFSET $6
+                    "#,
+                )
+                .bind(user_id)
+                .bind(&filter.account_id)
+                .bind(filter.start_date)
+                .bind(filter.end_date)
+                .bind(filter.limit)
+                .bind(filter.offset)
+                .bind(filter.include_details)
+                .fetch_all(&pool)
+                .await
+            })
+            .await?;
+
+        Ok(transactions)
+    }
+
+    /// Page of a user's live transactions, oldest first, after the `(date, transaction_id)` keyset position.
+    ///
+    /// `filter.limit` bounds the page; `filter.offset` and `filter.include_details` are ignored.
+    #[instrument(skip(self))]
+    pub async fn list_page_after(
+        &self,
+        user_id: Uuid,
+        filter: &TransactionFilter,
+        after: Option<(NaiveDate, &str)>,
+    ) -> Result<Vec<Transaction>> {
+        let transactions = self
+            .reads
+            .fetch(|pool| async move {
+                sqlx::query_as::<_, Transaction>(
+                    r#"
+                    SELECT * FROM transactions
+                    WHERE user_id = $1
+                      AND removed_at IS NULL
+                      AND ($2::VARCHAR IS NULL OR account_id = $2)
+                      AND ($3::DATE IS NULL OR date >= $3)
+                      AND ($4::DATE IS NULL OR date <= $4)
+                      AND ($5::DATE IS NULL OR (date, transaction_id) > ($5, $6))
+                    ORDER BY date, transaction_id
+                    LIMIT $7
+                    "#,
+                )
+                .bind(user_id)
+                .bind(&filter.account_id)
+                .bind(filter.start_date)
+                .bind(filter.end_date)
+                .bind(after.map(|(date, _)| date))
+                .bind(after.map(|(_, id)| id).unwrap_or(""))
+                .bind(filter.limit)
+                .fetch_all(&pool)
+                .await
+            })
+            .await?;
+
+        Ok(transactions)
+    }
+
+    /// List transactions (including soft-deleted ones) changed after the `(since, after_id)` keyset position
+    /// and no later than `until`
+    #[instrument(skip(self))]
+    pub async fn list_changed_since(
+        &self,
+        user_id: Uuid,
+        since: DateTime<Utc>,
+        after_id: &str,
+        until: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<Transaction>> {
+        let transactions = sqlx::query_as::<_, Transaction>(
+            r#"
+            SELECT * FROM transactions
+            WHERE user_id = $1 AND (updated_at, transaction_id) > ($2, $3) AND updated_at <= $4
+            ORDER BY updated_at, transaction_id
+            LIMIT $5
+            "#,
+        )
+        .bind(user_id)
+        .bind(since)
+        .bind(after_id)
+        .bind(until)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(transactions)
+    }
+
+    /// `(transaction_id, date, amount)` of an account's live transactions between two dates, for duplicate checks
+    #[instrument(skip(self))]
+    pub async fn list_amounts_by_account(
+        &self,
+        account_id: &str,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> Result<Vec<(String, NaiveDate, f64)>> {
+        let rows = sqlx::query_as::<_, (String, NaiveDate, f64)>(
+            r#"
+            SELECT transaction_id, date, amount FROM transactions
+            WHERE account_id = $1 AND removed_at IS NULL AND date BETWEEN $2 AND $3
+            "#,
+        )
+        .bind(account_id)
+        .bind(start_date)
+        .bind(end_date)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Soft-delete `merged_id` as a duplicate of `kept_id`, moving a manual category onto the kept transaction.
+    ///
+    /// Returns false when `merged_id` is not one of the user's live transactions.
+    #[instrument(skip(self))]
+    pub async fn merge_duplicate(&self, user_id: Uuid, kept_id: &str, merged_id: &str) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        let merged = sqlx::query(
+            r#"
+            UPDATE transactions SET removed_at = NOW(), merged_into = $3, updated_at = NOW()
+            WHERE user_id = $1 AND transaction_id = $2 AND removed_at IS NULL
+            "#,
+        )
+        .bind(user_id)
+        .bind(merged_id)
+        .bind(kept_id)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to merge transaction")?
+        .rows_affected();
+        if merged == 0 {
+            return Ok(false);
+        }
+
+        // A user's choice outranks the categorizer's guess for the kept transaction
+        sqlx::query(
+            r#"
+            INSERT INTO transaction_categories (transaction_id, user_id, category, confidence, source, model)
+            SELECT $2, user_id, category, confidence, source, model
+            FROM transaction_categories
+            WHERE transaction_id = $3 AND user_id = $1 AND source = 'manual'
+            ON CONFLICT (transaction_id) DO UPDATE SET
+                category = EXCLUDED.category,
+                confidence = EXCLUDED.confidence,
+                source = EXCLUDED.source,
+                model = EXCLUDED.model,
+                updated_at = NOW()
+            WHERE transaction_categories.source <> 'manual'
+            "#,
+        )
+        .bind(user_id)
+        .bind(kept_id)
+        .bind(merged_id)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to carry over category")?;
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// A user's live, posted outflows dated on or after `since`, oldest first, for recurring bill detection
+    #[instrument(skip(self))]
+    pub async fn list_posted_outflows_since(&self, user_id: Uuid, since: NaiveDate) -> Result<Vec<Transaction>> {
+        let transactions = sqlx::query_as::<_, Transaction>(
+            r#"
+            SELECT * FROM transactions
+            WHERE user_id = $1 AND removed_at IS NULL AND NOT pending AND amount > 0 AND date >= $2
+            ORDER BY date, transaction_id
+            "#,
+        )
+        .bind(user_id)
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(transactions)
+    }
+
+    /// An item's live transactions first stored at or after `since`
+    #[instrument(skip(self))]
+    pub async fn list_added_since(&self, item_id: &str, since: DateTime<Utc>) -> Result<Vec<Transaction>> {
+        let transactions = sqlx::query_as::<_, Transaction>(
+            r#"
+            SELECT * FROM transactions
+            WHERE item_id = $1 AND removed_at IS NULL AND created_at >= $2
+            ORDER BY date, transaction_id
+            "#,
+        )
+        .bind(item_id)
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(transactions)
+    }
+
+    /// Search a user's live transactions, newest first, after the `(date, transaction_id)` keyset position
+    #[instrument(skip(self, search))]
+    pub async fn search(
+        &self,
+        user_id: Uuid,
+        search: &TransactionSearch,
+        after: Option<(NaiveDate, &str)>,
+    ) -> Result<Vec<Transaction>> {
+        let pattern = search.query.as_deref().filter(|q| !q.trim().is_empty()).map(contains_pattern);
+        let pattern = pattern.as_deref();
+
+        let transactions = self
+            .reads
+            .fetch(|pool| async move {
+                sqlx::query_as::<_, Transaction>(
+                    r#"
+                    SELECT
+                        t.id, t.transaction_id, t.account_id, t.item_id, t.user_id, t.amount, t.iso_currency_code,
+                        t.unofficial_currency_code, t.date, t.datetime, t.authorized_date, t.authorized_datetime,
+                        t.name, t.merchant_name, t.category, t.category_id, t.check_number, t.pending,
+                        t.pending_transaction_id, t.account_owner, t.transaction_type, t.transaction_code,
+                        t.removed_at, t.created_at, t.updated_at,
+                        CASE WHEN $12 THEN t.original_description END AS original_description,
+                        CASE WHEN $12 THEN t.location END AS location,
+                        CASE WHEN $12 THEN t.payment_meta END AS payment_meta
+                    FROM transactions t
+                    LEFT JOIN transaction_categories c ON c.transaction_id = t.transaction_id
+                    WHERE t.user_id = $1
+                      AND t.removed_at IS NULL
+                      AND ($2::TEXT IS NULL OR t.name ILIKE $2 OR t.merchant_name ILIKE $2)
+                      AND ($3::DOUBLE PRECISION IS NULL OR ABS(t.amount) >= $3)
+                      AND ($4::DOUBLE PRECISION IS NULL OR ABS(t.amount) <= $4)
+                      AND ($5::DATE IS NULL OR t.date >= $5)
+                      AND ($6::DATE IS NULL OR t.date <= $6)
+                      AND (cardinality($7::TEXT[]) = 0 OR c.category = ANY($7))
+                      AND (cardinality($8::TEXT[]) = 0 OR t.account_id = ANY($8))
+                      AND ($9::BOOLEAN IS NULL OR t.pending = $9)
+                      AND ($10::DATE IS NULL OR (t.date, t.transaction_id) < ($10, $11))
+                    ORDER BY t.date DESC, t.transaction_id DESC
+                    LIMIT $13
+                    "#,
+                )
+                .bind(user_id)
+                .bind(pattern)
+                .bind(search.min_amount)
+                .bind(search.max_amount)
+                .bind(search.start_date)
+                .bind(search.end_date)
+                .bind(&search.categories)
+                .bind(&search.account_ids)
+                .bind(search.pending)
+                .bind(after.map(|(date, _)| date))
+                .bind(after.map(|(_, id)| id).unwrap_or(""))
+                .bind(search.include_details)
+                .bind(search.limit)
+                .fetch_all(&pool)
+                .await
+            })
+            .await?;
+
+        Ok(transactions)
+    }
+
+    /// Find a transaction by Plaid transaction ID, including removed ones
+    #[instrument(skip(self))]
+    pub async fn find_by_transaction_id(&self, transaction_id: &str) -> Result<Option<Transaction>> {
+        let transaction = sqlx::query_as::<_, Transaction>(
+            "SELECT * FROM transactions WHERE transaction_id = $1"
+        )
+        .bind(transaction_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(transaction)
+    }
+
+    /// A user's live transactions among `transaction_ids`; unknown and removed IDs are left out
+    #[instrument(skip(self, transaction_ids), fields(count = transaction_ids.len()))]
+    pub async fn find_by_transaction_ids(&self, user_id: Uuid, transaction_ids: &[String]) -> Result<Vec<Transaction>> {
+        let transactions = sqlx::query_as::<_, Transaction>(
+            r#"
+            SELECT * FROM transactions
+            WHERE user_id = $1 AND transaction_id = ANY($2) AND removed_at IS NULL
+            "#,
+        )
+        .bind(user_id)
+        .bind(transaction_ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(transactions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_plaid_date() {
+        assert_eq!(
+            parse_plaid_date("2024-02-29").unwrap(),
+            NaiveDate::from_ymd_opt(2024, 2, 29).unwrap()
+        );
+        assert!(parse_plaid_date("02/29/2024").is_err());
+    }
+
+    #[test]
+    fn test_contains_pattern_escapes_wildcards() {
+        assert_eq!(contains_pattern(" coffee "), "%coffee%");
+        assert_eq!(contains_pattern("50%_off\\"), "%50\\%\\_off\\\\%");
+    }
+
+    fn bank_transaction(transaction_id: &str, amount: f64) -> BankTransaction {
+        BankTransaction {
+            transaction_id: transaction_id.to_string(),
+            account_id: "acc_1".to_string(),
+            amount,
+            iso_currency_code: Some("USD".to_string()),
+            unofficial_currency_code: None,
+            category: vec!["Food and Drink".to_string(), "Coffee".to_string()],
+            category_id: None,
+            check_number: None,
+            date: "2024-03-01".to_string(),
+            datetime: None,
+            authorized_date: Some("2024-02-29".to_string()),
+            authorized_datetime: None,
+            location: None,
+            name: "Coffee".to_string(),
+            merchant_name: None,
+            original_description: None,
+            payment_meta: None,
+            pending: false,
+            pending_transaction_id: None,
+            account_owner: None,
+            transaction_type: "place".to_string(),
+            transaction_code: None,
+        }
+    }
+
+    #[test]
+    fn test_transaction_columns_keep_last_version() {
+        let transactions = [
+            bank_transaction("tx_1", 4.5),
+            bank_transaction("tx_2", 10.0),
+            bank_transaction("tx_1", 5.0),
+        ];
+        let columns = TransactionColumns::new(&transactions).unwrap();
+
+        assert_eq!(columns.len(), 2);
+        assert_eq!(columns.transaction_id, vec!["tx_1", "tx_2"]);
+        assert_eq!(columns.amount, vec![5.0, 10.0]);
+        assert_eq!(columns.date[0], NaiveDate::from_ymd_opt(2024, 3, 1).unwrap());
+        assert_eq!(columns.authorized_date[0], NaiveDate::from_ymd_opt(2024, 2, 29));
+        assert_eq!(columns.category[0].0, vec!["Food and Drink", "Coffee"]);
+    }
+
+    #[test]
+    fn test_transaction_columns_reject_bad_dates() {
+        let mut transaction = bank_transaction("tx_1", 1.0);
+        transaction.date = "03/01/2024".to_string();
+        assert!(TransactionColumns::new([&transaction]).is_err());
+    }
+
+    #[test]
+    fn test_sync_summary_merge() {
+        let mut total = SyncSummary::default();
+        total.merge(SyncSummary { added: 2, modified: 1, removed: 0 });
+        total.merge(SyncSummary { added: 1, modified: 0, removed: 3 });
+        assert_eq!(total, SyncSummary { added: 3, modified: 1, removed: 3 });
+    }
+}
@@ -0,0 +1,311 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use tracing::{info, instrument};
+use uuid::Uuid;
+
+/// Longest note a transaction may carry
+pub const MAX_NOTES_CHARS: usize = 2000;
+
+/// Most tags on one transaction
+pub const MAX_TAGS: usize = 20;
+
+/// Longest single tag
+pub const MAX_TAG_CHARS: usize = 32;
+
+/// Most parts a transaction may be split into
+pub const MAX_SPLITS: usize = 20;
+
+/// Notes and tags a user attached to a transaction
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct TransactionAnnotation {
+    pub transaction_id: String,
+    pub user_id: Uuid,
+    pub notes: Option<String>,
+    pub tags: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Categorized part of a split transaction
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct TransactionSplit {
+    pub id: Uuid,
+    pub transaction_id: String,
+    pub user_id: Uuid,
+    pub position: i32,
+    pub amount: f64,
+    pub category: String,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Part of a split to store; `category` is a taxonomy entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewSplit {
+    pub amount: f64,
+    pub category: &'static str,
+    pub description: Option<String>,
+}
+
+/// Everything a user attached to one transaction
+#[derive(Debug, Clone, Default)]
+pub struct TransactionAnnotations {
+    pub notes: Option<String>,
+    pub tags: Vec<String>,
+    /// Ordered by position; empty unless the transaction is split
+    pub splits: Vec<TransactionSplit>,
+}
+
+/// Trim and lowercase tags, dropping blanks and repeats while keeping their order
+pub fn normalize_tags(tags: &[String]) -> Result<Vec<String>, String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if tag.is_empty() || normalized.contains(&tag) {
+            continue;
+        }
+        if tag.chars().count() > MAX_TAG_CHARS {
+            return Err(format!("Tags may be at most {} characters", MAX_TAG_CHARS));
+        }
+        normalized.push(tag);
+    }
+    if normalized.len() > MAX_TAGS {
+        return Err(format!("A transaction may have at most {} tags", MAX_TAGS));
+    }
+    Ok(normalized)
+}
+
+/// Check that splits of a transaction of `amount` have its sign and add up to it to the cent;
+/// an empty list removes the split
+pub fn validate_splits(amount: f64, splits: &[NewSplit]) -> Result<(), String> {
+    if splits.is_empty() {
+        return Ok(());
+    }
+    if splits.len() == 1 {
+        return Err("A split needs at least two parts".to_string());
+    }
+    if splits.len() > MAX_SPLITS {
+        return Err(format!("A transaction may be split into at most {} parts", MAX_SPLITS));
+    }
+
+    let cents = |value: f64| (value * 100.0).round() as i64;
+    for split in splits {
+        if !split.amount.is_finite() || cents(split.amount) == 0 {
+            return Err("Split amounts must be non-zero".to_string());
+        }
+        if split.amount.signum() != amount.signum() {
+            return Err("Split amounts must have the same sign as the transaction".to_string());
+        }
+    }
+    let total: i64 = splits.iter().map(|split| cents(split.amount)).sum();
+    if total != cents(amount) {
+        return Err(format!(
+            "Split amounts add up to {:.2}, not the transaction amount {:.2}",
+            total as f64 / 100.0,
+            amount
+        ));
+    }
+    Ok(())
+}
+
+/// Transaction notes, tags and splits repository for database operations
+#[derive(Debug, Clone)]
+pub struct TransactionAnnotationRepository {
+    pool: PgPool,
+}
+
+impl TransactionAnnotationRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Replace a transaction's notes, `None` clearing them; returns `None` if the transaction is not the user's
+    #[instrument(skip(self, notes))]
+    pub async fn set_notes(
+        &self,
+        user_id: Uuid,
+        transaction_id: &str,
+        notes: Option<&str>,
+    ) -> Result<Option<TransactionAnnotation>> {
+        let annotation = sqlx::query_as::<_, TransactionAnnotation>(
+            r#"
+            INSERT INTO transaction_annotations (transaction_id, user_id, notes)
+            SELECT $1, $2, $3
+            WHERE EXISTS (
+                SELECT 1 FROM transactions
+                WHERE transaction_id = $1 AND user_id = $2 AND removed_at IS NULL
+            )
+            ON CONFLICT (transaction_id) DO UPDATE SET
+                notes = EXCLUDED.notes,
+                updated_at = NOW()
+            WHERE transaction_annotations.user_id = EXCLUDED.user_id
+            RETURNING *
+            "#,
+        )
+        .bind(transaction_id)
+        .bind(user_id)
+        .bind(notes)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(annotation)
+    }
+
+    /// Replace a transaction's tags; returns `None` if the transaction is not the user's
+    #[instrument(skip(self))]
+    pub async fn set_tags(
+        &self,
+        user_id: Uuid,
+        transaction_id: &str,
+        tags: &[String],
+    ) -> Result<Option<TransactionAnnotation>> {
+        let annotation = sqlx::query_as::<_, TransactionAnnotation>(
+            r#"
+            INSERT INTO transaction_annotations (transaction_id, user_id, tags)
+            SELECT $1, $2, $3
+            WHERE EXISTS (
+                SELECT 1 FROM transactions
+                WHERE transaction_id = $1 AND user_id = $2 AND removed_at IS NULL
+            )
+            ON CONFLICT (transaction_id) DO UPDATE SET
+                tags = EXCLUDED.tags,
+                updated_at = NOW()
+            WHERE transaction_annotations.user_id = EXCLUDED.user_id
+            RETURNING *
+            "#,
+        )
+        .bind(transaction_id)
+        .bind(user_id)
+        .bind(tags)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(annotation)
+    }
+
+    /// Replace a transaction's splits in a single transaction; an empty list unsplits it.
+    /// Callers check ownership and run `validate_splits` first.
+    #[instrument(skip(self, splits), fields(count = splits.len()))]
+    pub async fn replace_splits(
+        &self,
+        user_id: Uuid,
+        transaction_id: &str,
+        splits: &[NewSplit],
+    ) -> Result<Vec<TransactionSplit>> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM transaction_splits WHERE transaction_id = $1 AND user_id = $2")
+            .bind(transaction_id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let mut stored = Vec::with_capacity(splits.len());
+        for (position, split) in splits.iter().enumerate() {
+            let row = sqlx::query_as::<_, TransactionSplit>(
+                r#"
+                INSERT INTO transaction_splits (transaction_id, user_id, position, amount, category, description)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                RETURNING *
+                "#,
+            )
+            .bind(transaction_id)
+            .bind(user_id)
+            .bind(position as i32)
+            .bind(split.amount)
+            .bind(split.category)
+            .bind(&split.description)
+            .fetch_one(&mut *tx)
+            .await?;
+            stored.push(row);
+        }
+
+        tx.commit().await?;
+
+        info!(user_id = %user_id, transaction_id = %transaction_id, parts = stored.len(), "Transaction splits replaced");
+        Ok(stored)
+    }
+
+    /// Notes, tags and splits of the given transactions, keyed by transaction ID; transactions
+    /// without any are left out
+    #[instrument(skip(self, transaction_ids), fields(count = transaction_ids.len()))]
+    pub async fn find_for_transactions(
+        &self,
+        user_id: Uuid,
+        transaction_ids: &[String],
+    ) -> Result<HashMap<String, TransactionAnnotations>> {
+        if transaction_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let annotations = sqlx::query_as::<_, TransactionAnnotation>(
+            "SELECT * FROM transaction_annotations WHERE user_id = $1 AND transaction_id = ANY($2)"
+        )
+        .bind(user_id)
+        .bind(transaction_ids)
+        .fetch_all(&self.pool)
+        .await?;
+        let splits = sqlx::query_as::<_, TransactionSplit>(
+            r#"
+            SELECT * FROM transaction_splits
+            WHERE user_id = $1 AND transaction_id = ANY($2)
+            ORDER BY transaction_id, position
+            "#,
+        )
+        .bind(user_id)
+        .bind(transaction_ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut found: HashMap<String, TransactionAnnotations> = HashMap::new();
+        for annotation in annotations {
+            let entry = found.entry(annotation.transaction_id).or_default();
+            entry.notes = annotation.notes;
+            entry.tags = annotation.tags;
+        }
+        for split in splits {
+            found.entry(split.transaction_id.clone()).or_default().splits.push(split);
+        }
+        Ok(found)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(amount: f64) -> NewSplit {
+        NewSplit {
+            amount,
+            category: "FOOD_AND_DRINK",
+            description: None,
+        }
+    }
+
+    #[test]
+    fn test_normalize_tags() {
+        let tags = vec![" Vacation ".to_string(), "vacation".to_string(), "".to_string(), "Work".to_string()];
+        assert_eq!(normalize_tags(&tags), Ok(vec!["vacation".to_string(), "work".to_string()]));
+
+        let long = vec!["x".repeat(MAX_TAG_CHARS + 1)];
+        assert!(normalize_tags(&long).is_err());
+
+        let many: Vec<String> = (0..=MAX_TAGS).map(|i| format!("tag{}", i)).collect();
+        assert!(normalize_tags(&many).is_err());
+    }
+
+    #[test]
+    fn test_validate_splits() {
+        assert!(validate_splits(100.0, &[]).is_ok());
+        assert!(validate_splits(100.0, &[split(60.0), split(40.0)]).is_ok());
+        assert!(validate_splits(0.3, &[split(0.1), split(0.2)]).is_ok());
+        assert!(validate_splits(-50.0, &[split(-20.0), split(-30.0)]).is_ok());
+
+        assert!(validate_splits(100.0, &[split(100.0)]).is_err());
+        assert!(validate_splits(100.0, &[split(60.0), split(30.0)]).is_err());
+        assert!(validate_splits(100.0, &[split(120.0), split(-20.0)]).is_err());
+        assert!(validate_splits(100.0, &[split(100.0), split(0.0)]).is_err());
+    }
+}
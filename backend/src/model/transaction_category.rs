@@ -0,0 +1,263 @@
+use crate::model::transaction::Transaction;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use tracing::{debug, info, instrument};
+use uuid::Uuid;
+
+/// Categories a transaction can be assigned, modelled on Plaid's primary personal finance categories
+pub const CATEGORY_TAXONOMY: &[&str] = &[
+    "INCOME",
+    "TRANSFER_IN",
+    "TRANSFER_OUT",
+    "LOAN_PAYMENTS",
+    "BANK_FEES",
+    "ENTERTAINMENT",
+    "FOOD_AND_DRINK",
+    "GENERAL_MERCHANDISE",
+    "HOME_IMPROVEMENT",
+    "MEDICAL",
+    "PERSONAL_CARE",
+    "GENERAL_SERVICES",
+    "GOVERNMENT_AND_NON_PROFIT",
+    "TRANSPORTATION",
+    "TRAVEL",
+    "RENT_AND_UTILITIES",
+    "OTHER",
+];
+
+/// Fallback for AI answers that cannot be trusted; stored so the transaction is not re-queried
+pub const UNCATEGORIZED: &str = "OTHER";
+
+/// Normalize `value` to a taxonomy entry, or `None` when it is not part of the taxonomy
+pub fn taxonomy_category(value: &str) -> Option<&'static str> {
+    let value = value.trim();
+    CATEGORY_TAXONOMY
+        .iter()
+        .find(|category| category.eq_ignore_ascii_case(value))
+        .copied()
+}
+
+/// Who assigned a transaction's category
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CategorySource {
+    Ai,
+    /// Set by the user; never overwritten by the categorizer
+    Manual,
+}
+
+impl CategorySource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CategorySource::Ai => "ai",
+            CategorySource::Manual => "manual",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "ai" => Some(CategorySource::Ai),
+            "manual" => Some(CategorySource::Manual),
+            _ => None,
+        }
+    }
+}
+
+/// Category produced by the categorizer for one transaction
+#[derive(Debug, Clone, PartialEq)]
+pub struct CategoryAssignment {
+    pub transaction_id: String,
+    pub user_id: Uuid,
+    pub category: &'static str,
+    pub confidence: f64,
+}
+
+/// Persisted transaction category
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct TransactionCategory {
+    pub transaction_id: String,
+    pub user_id: Uuid,
+    pub category: String,
+    pub confidence: f64,
+    pub source: String,
+    pub model: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl TransactionCategory {
+    pub fn source(&self) -> Option<CategorySource> {
+        CategorySource::parse(&self.source)
+    }
+}
+
+/// Transaction category repository for database operations
+#[derive(Debug, Clone)]
+pub struct TransactionCategoryRepository {
+    pool: PgPool,
+}
+
+impl TransactionCategoryRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Live transactions of any user that have no category yet, newest first
+    #[instrument(skip(self))]
+    pub async fn list_uncategorized(&self, limit: i64) -> Result<Vec<Transaction>> {
+        let transactions = sqlx::query_as::<_, Transaction>(
+            r#"
+            SELECT t.* FROM transactions t
+            LEFT JOIN transaction_categories c ON c.transaction_id = t.transaction_id
+            WHERE c.transaction_id IS NULL AND t.removed_at IS NULL
+            ORDER BY t.date DESC, t.transaction_id
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(transactions)
+    }
+
+    /// Store categorizer results in a single transaction; manual overrides are left untouched
+    #[instrument(skip(self, assignments), fields(count = assignments.len()))]
+    pub async fn store_ai_categories(&self, assignments: &[CategoryAssignment], model: &str) -> Result<usize> {
+        let mut tx = self.pool.begin().await?;
+        let mut stored = 0;
+
+        for assignment in assignments {
+            let result = sqlx::query(
+                r#"
+                INSERT INTO transaction_categories (transaction_id, user_id, category, confidence, source, model)
+                SELECT $1, $2, $3, $4, 'ai', $5
+                WHERE EXISTS (
+                    SELECT 1 FROM transactions WHERE transaction_id = $1 AND user_id = $2
+                )
+                ON CONFLICT (transaction_id) DO UPDATE SET
+                    category = EXCLUDED.category,
+                    confidence = EXCLUDED.confidence,
+                    model = EXCLUDED.model,
+                    updated_at = NOW()
+                WHERE transaction_categories.source = 'ai'
+                  AND transaction_categories.user_id = EXCLUDED.user_id
+                "#,
+            )
+            .bind(&assignment.transaction_id)
+            .bind(assignment.user_id)
+            .bind(assignment.category)
+            .bind(assignment.confidence)
+            .bind(model)
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("Failed to store category for transaction {}", assignment.transaction_id))?;
+
+            stored += result.rows_affected() as usize;
+        }
+
+        tx.commit().await?;
+
+        debug!(stored, model = %model, "Stored AI transaction categories");
+        Ok(stored)
+    }
+
+    /// Override a transaction's category; returns `None` if the transaction is not the user's
+    #[instrument(skip(self))]
+    pub async fn set_manual(
+        &self,
+        user_id: Uuid,
+        transaction_id: &str,
+        category: &'static str,
+    ) -> Result<Option<TransactionCategory>> {
+        let stored = sqlx::query_as::<_, TransactionCategory>(
+            r#"
+            INSERT INTO transaction_categories (transaction_id, user_id, category, confidence, source, model)
+            SELECT $1, $2, $3, 1.0, 'manual', NULL
+            WHERE EXISTS (
+                SELECT 1 FROM transactions
+                WHERE transaction_id = $1 AND user_id = $2 AND removed_at IS NULL
+            )
+            ON CONFLICT (transaction_id) DO UPDATE SET
+                category = EXCLUDED.category,
+                confidence = EXCLUDED.confidence,
+                source = EXCLUDED.source,
+                model = NULL,
+                updated_at = NOW()
+            WHERE transaction_categories.user_id = EXCLUDED.user_id
+            RETURNING *
+            "#,
+        )
+        .bind(transaction_id)
+        .bind(user_id)
+        .bind(category)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        if stored.is_some() {
+            info!(user_id = %user_id, transaction_id = %transaction_id, category, "Transaction category overridden");
+        }
+        Ok(stored)
+    }
+
+    /// Drop a manual override so the categorizer picks the transaction up again
+    #[instrument(skip(self))]
+    pub async fn clear_manual(&self, user_id: Uuid, transaction_id: &str) -> Result<bool> {
+        let result = sqlx::query(
+            "DELETE FROM transaction_categories WHERE transaction_id = $1 AND user_id = $2 AND source = 'manual'"
+        )
+        .bind(transaction_id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Categories of the given transactions, keyed by transaction ID
+    #[instrument(skip(self, transaction_ids), fields(count = transaction_ids.len()))]
+    pub async fn find_for_transactions(
+        &self,
+        user_id: Uuid,
+        transaction_ids: &[String],
+    ) -> Result<HashMap<String, TransactionCategory>> {
+        if transaction_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let categories = sqlx::query_as::<_, TransactionCategory>(
+            "SELECT * FROM transaction_categories WHERE user_id = $1 AND transaction_id = ANY($2)"
+        )
+        .bind(user_id)
+        .bind(transaction_ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(categories
+            .into_iter()
+            .map(|category| (category.transaction_id.clone(), category))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_taxonomy_category_normalizes_case() {
+        assert_eq!(taxonomy_category("food_and_drink "), Some("FOOD_AND_DRINK"));
+        assert_eq!(taxonomy_category("TRAVEL"), Some("TRAVEL"));
+        assert_eq!(taxonomy_category("GROCERIES"), None);
+        assert!(taxonomy_category(UNCATEGORIZED).is_some());
+    }
+
+    #[test]
+    fn test_category_source_round_trip() {
+        for source in [CategorySource::Ai, CategorySource::Manual] {
+            assert_eq!(CategorySource::parse(source.as_str()), Some(source));
+        }
+        assert_eq!(CategorySource::parse("rules"), None);
+    }
+}
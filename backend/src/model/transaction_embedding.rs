@@ -0,0 +1,338 @@
+use crate::adapter::embeddings::EmbeddingsClient;
+use crate::model::transaction::Transaction;
+use anyhow::{Context, Result};
+use chrono::{Datelike, Duration, Months, NaiveDate};
+use sqlx::PgPool;
+use std::fmt::Write;
+use std::sync::Arc;
+use tracing::{debug, info, instrument};
+use uuid::Uuid;
+
+/// Transactions embedded per request while catching up an item
+const EMBEDDING_BATCH_SIZE: i64 = 100;
+
+/// Batches embedded per item after a sync; the rest are picked up after the next sync
+const MAX_BATCHES_PER_ITEM: usize = 20;
+
+/// Text embedded for a transaction: what it is, where it was spent and how it was categorized
+pub fn embedding_text(transaction: &Transaction) -> String {
+    let mut text = String::new();
+    if let Some(merchant) = transaction.merchant_name.as_deref().filter(|m| !m.is_empty()) {
+        let _ = write!(text, "{} | ", merchant);
+    }
+    text.push_str(&transaction.name);
+    if !transaction.category.is_empty() {
+        let _ = write!(text, " | {}", transaction.category.join(" > "));
+    }
+    let direction = if transaction.amount < 0.0 { "money in" } else { "money out" };
+    let _ = write!(text, " | {} | {}", direction, transaction.transaction_type);
+    text
+}
+
+/// pgvector text literal for binding an embedding as `$n::vector`
+pub fn vector_literal(embedding: &[f32]) -> String {
+    let mut literal = String::with_capacity(embedding.len() * 10 + 2);
+    literal.push('[');
+    for (i, value) in embedding.iter().enumerate() {
+        if i > 0 {
+            literal.push(',');
+        }
+        let _ = write!(literal, "{}", value);
+    }
+    literal.push(']');
+    literal
+}
+
+/// Dates named by phrases like "last month" or "past 30 days" in a search query, inclusive
+pub fn relative_date_range(query: &str, today: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+    let query = query.to_lowercase();
+    let month_start = today.with_day(1)?;
+    let words: Vec<&str> = query.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()).collect();
+
+    for window in words.windows(3) {
+        if let ["last" | "past", count, unit] = window {
+            if let Ok(count) = count.parse::<i64>() {
+                let days = match *unit {
+                    "day" | "days" => count,
+                    "week" | "weeks" => count * 7,
+                    _ => continue,
+                };
+                return Some((today - Duration::days(days.clamp(1, 3660) - 1), today));
+            }
+        }
+    }
+
+    let contains = |phrase: &str| {
+        let phrase: Vec<&str> = phrase.split(' ').collect();
+        words.windows(phrase.len()).any(|w| w == phrase.as_slice())
+    };
+    if contains("today") {
+        Some((today, today))
+    } else if contains("yesterday") {
+        let yesterday = today - Duration::days(1);
+        Some((yesterday, yesterday))
+    } else if contains("this week") {
+        Some((today - Duration::days(today.weekday().num_days_from_monday() as i64), today))
+    } else if contains("last week") {
+        let this_week = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+        Some((this_week - Duration::days(7), this_week - Duration::days(1)))
+    } else if contains("this month") {
+        Some((month_start, today))
+    } else if contains("last month") {
+        Some((month_start - Months::new(1), month_start - Duration::days(1)))
+    } else if contains("this year") {
+        Some((NaiveDate::from_ymd_opt(today.year(), 1, 1)?, today))
+    } else if contains("last year") {
+        Some((
+            NaiveDate::from_ymd_opt(today.year() - 1, 1, 1)?,
+            NaiveDate::from_ymd_opt(today.year() - 1, 12, 31)?,
+        ))
+    } else {
+        None
+    }
+}
+
+/// Filters applied alongside similarity in a semantic search
+#[derive(Debug, Clone, Default)]
+pub struct SemanticSearch {
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub account_ids: Vec<String>,
+    /// Matches further than this cosine distance are left out
+    pub max_distance: Option<f64>,
+    pub limit: i64,
+}
+
+/// Transaction matching a semantic search
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ScoredTransaction {
+    #[sqlx(flatten)]
+    pub transaction: Transaction,
+    /// Cosine distance from the query, 0 for identical meaning
+    pub distance: f64,
+}
+
+/// Transaction embedding repository for database operations
+#[derive(Debug, Clone)]
+pub struct TransactionEmbeddingRepository {
+    pool: PgPool,
+}
+
+impl TransactionEmbeddingRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// An item's live transactions without an embedding from `model`, or changed since they were embedded
+    #[instrument(skip(self))]
+    pub async fn list_unembedded(&self, item_id: &str, model: &str, limit: i64) -> Result<Vec<Transaction>> {
+        let transactions = sqlx::query_as::<_, Transaction>(
+            r#"
+            SELECT t.* FROM transactions t
+            LEFT JOIN transaction_embeddings e ON e.transaction_id = t.transaction_id
+            WHERE t.item_id = $1
+              AND t.removed_at IS NULL
+              AND (e.transaction_id IS NULL OR e.model <> $2 OR e.updated_at < t.updated_at)
+            ORDER BY t.date DESC, t.transaction_id
+            LIMIT $3
+            "#,
+        )
+        .bind(item_id)
+        .bind(model)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(transactions)
+    }
+
+    /// Store embeddings in a single transaction, replacing earlier ones
+    #[instrument(skip(self, embeddings), fields(count = embeddings.len()))]
+    pub async fn store(&self, embeddings: &[(&Transaction, Vec<f32>)], model: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        for (transaction, embedding) in embeddings {
+            sqlx::query(
+                r#"
+                INSERT INTO transaction_embeddings (transaction_id, user_id, item_id, model, embedding)
+                VALUES ($1, $2, $3, $4, $5::vector)
+                ON CONFLICT (transaction_id) DO UPDATE SET
+                    model = EXCLUDED.model,
+                    embedding = EXCLUDED.embedding,
+                    updated_at = NOW()
+                "#,
+            )
+            .bind(&transaction.transaction_id)
+            .bind(transaction.user_id)
+            .bind(&transaction.item_id)
+            .bind(model)
+            .bind(vector_literal(embedding))
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("Failed to store embedding for transaction {}", transaction.transaction_id))?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// A user's live transactions closest in meaning to `embedding`, closest first
+    #[instrument(skip(self, embedding))]
+    pub async fn search(
+        &self,
+        user_id: Uuid,
+        embedding: &[f32],
+        model: &str,
+        search: &SemanticSearch,
+    ) -> Result<Vec<ScoredTransaction>> {
+        let transactions = sqlx::query_as::<_, ScoredTransaction>(
+            r#"
+            SELECT * FROM (
+                SELECT t.*, (e.embedding <=> $2::vector) AS distance
+                FROM transaction_embeddings e
+                JOIN transactions t ON t.transaction_id = e.transaction_id
+                WHERE e.user_id = $1
+                  AND e.model = $3
+                  AND t.removed_at IS NULL
+                  AND ($4::DATE IS NULL OR t.date >= $4)
+                  AND ($5::DATE IS NULL OR t.date <= $5)
+                  AND (cardinality($6::TEXT[]) = 0 OR t.account_id = ANY($6))
+            ) matches
+            WHERE $7::DOUBLE PRECISION IS NULL OR distance <= $7
+            ORDER BY distance, date DESC
+            LIMIT $8
+            "#,
+        )
+        .bind(user_id)
+        .bind(vector_literal(embedding))
+        .bind(model)
+        .bind(search.start_date)
+        .bind(search.end_date)
+        .bind(&search.account_ids)
+        .bind(search.max_distance)
+        .bind(search.limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(transactions)
+    }
+}
+
+/// Keeps transaction embeddings current and answers semantic searches with them
+#[derive(Clone)]
+pub struct TransactionEmbedder {
+    client: Arc<EmbeddingsClient>,
+    embeddings: TransactionEmbeddingRepository,
+}
+
+impl TransactionEmbedder {
+    pub fn new(client: Arc<EmbeddingsClient>, embeddings: TransactionEmbeddingRepository) -> Self {
+        Self { client, embeddings }
+    }
+
+    /// Embed an item's new and changed transactions; returns how many were embedded
+    #[instrument(skip(self))]
+    pub async fn embed_item(&self, item_id: &str) -> Result<usize> {
+        let model = self.client.model();
+        let mut embedded = 0;
+
+        for _ in 0..MAX_BATCHES_PER_ITEM {
+            let batch = self.embeddings.list_unembedded(item_id, model, EMBEDDING_BATCH_SIZE).await?;
+            if batch.is_empty() {
+                break;
+            }
+            let texts: Vec<String> = batch.iter().map(embedding_text).collect();
+            let vectors = self.client.embed(&texts).await?;
+            let rows: Vec<(&Transaction, Vec<f32>)> = batch.iter().zip(vectors).collect();
+            self.embeddings.store(&rows, model).await?;
+
+            embedded += batch.len();
+            if (batch.len() as i64) < EMBEDDING_BATCH_SIZE {
+                break;
+            }
+        }
+
+        if embedded > 0 {
+            info!(embedded, "Embedded transactions");
+        }
+        Ok(embedded)
+    }
+
+    /// A user's transactions closest in meaning to a natural-language query
+    #[instrument(skip(self, query))]
+    pub async fn search(&self, user_id: Uuid, query: &str, search: &SemanticSearch) -> Result<Vec<ScoredTransaction>> {
+        let mut vectors = self.client.embed(&[query.trim().to_string()]).await?;
+        let embedding = vectors.pop().context("Embeddings API returned no vector for the query")?;
+        let matches = self.embeddings.search(user_id, &embedding, self.client.model(), search).await?;
+
+        debug!(matches = matches.len(), "Semantic transaction search");
+        Ok(matches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn transaction(name: &str, merchant: Option<&str>, amount: f64) -> Transaction {
+        Transaction {
+            id: Uuid::new_v4(),
+            transaction_id: "tx_1".to_string(),
+            account_id: "acc_1".to_string(),
+            item_id: "item_1".to_string(),
+            user_id: Uuid::nil(),
+            amount,
+            iso_currency_code: Some("USD".to_string()),
+            unofficial_currency_code: None,
+            date: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            datetime: None,
+            authorized_date: None,
+            authorized_datetime: None,
+            name: name.to_string(),
+            merchant_name: merchant.map(str::to_string),
+            original_description: None,
+            category: vec!["Food and Drink".to_string(), "Coffee Shop".to_string()],
+            category_id: None,
+            check_number: None,
+            location: None,
+            payment_meta: None,
+            pending: false,
+            pending_transaction_id: None,
+            account_owner: None,
+            transaction_type: "place".to_string(),
+            transaction_code: None,
+            removed_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_embedding_text() {
+        assert_eq!(
+            embedding_text(&transaction("SQ *BLUE BOTTLE 1234", Some("Blue Bottle Coffee"), 5.25)),
+            "Blue Bottle Coffee | SQ *BLUE BOTTLE 1234 | Food and Drink > Coffee Shop | money out | place"
+        );
+        assert!(embedding_text(&transaction("REFUND", None, -5.25)).starts_with("REFUND | "));
+    }
+
+    #[test]
+    fn test_vector_literal() {
+        assert_eq!(vector_literal(&[0.5, -1.0, 0.25]), "[0.5,-1,0.25]");
+        assert_eq!(vector_literal(&[]), "[]");
+    }
+
+    #[test]
+    fn test_relative_date_range() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 14).unwrap(); // a Thursday
+        let date = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+
+        assert_eq!(relative_date_range("coffee shops last month", today), Some((date(2, 1), date(2, 29))));
+        assert_eq!(relative_date_range("Groceries this month", today), Some((date(3, 1), today)));
+        assert_eq!(relative_date_range("rides last week", today), Some((date(3, 4), date(3, 10))));
+        assert_eq!(relative_date_range("gas in the past 30 days", today), Some((date(2, 14), today)));
+        assert_eq!(relative_date_range("yesterday's lunch", today), Some((date(3, 13), date(3, 13))));
+        assert_eq!(relative_date_range("coffee shops", today), None);
+    }
+}
@@ -0,0 +1,466 @@
+use crate::adapter::bank_data::{BankDataProvider, BankDataProviders};
+use crate::adapter::plaid::{BankAccount, TransactionSyncRequest, TransactionSyncResponse};
+use crate::model::bank_account::BankAccountRepository;
+use crate::model::plaid_item::{PlaidItem, PlaidItemRepository};
+use crate::model::pubsub::BalanceUpdates;
+use crate::model::transaction::{SyncSummary, TransactionRepository};
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use tracing::{info, instrument, warn};
+use uuid::Uuid;
+
+/// Page size requested from `/transactions/sync`
+const SYNC_PAGE_SIZE: i32 = 500;
+
+/// Restarts allowed when Plaid reports the item changed mid-pagination
+const MAX_PAGINATION_RESTARTS: usize = 3;
+
+/// Where fetched sync pages are applied
+#[async_trait]
+trait SyncPageSink: Send + Sync {
+    /// Store one page; `commit_cursor` is set only on the final page and must be saved with it
+    async fn apply_page(&self, page: &TransactionSyncResponse, commit_cursor: Option<&str>) -> Result<SyncSummary>;
+}
+
+/// Applies pages to one item's stored transactions and cursor
+struct ItemPages<'a> {
+    transactions: &'a TransactionRepository,
+    item: &'a PlaidItem,
+}
+
+#[async_trait]
+impl SyncPageSink for ItemPages<'_> {
+    async fn apply_page(&self, page: &TransactionSyncResponse, commit_cursor: Option<&str>) -> Result<SyncSummary> {
+        self.transactions
+            .apply_sync_page(
+                self.item.user_id,
+                &self.item.item_id,
+                page,
+                self.item.sync_cursor.as_deref(),
+                commit_cursor,
+            )
+            .await
+    }
+}
+
+/// Where an item's refreshed accounts are stored
+#[async_trait]
+trait AccountSink: Send + Sync {
+    async fn store_accounts(&self, user_id: Uuid, item_id: &str, accounts: &[BankAccount]) -> Result<()>;
+}
+
+#[async_trait]
+impl AccountSink for BankAccountRepository {
+    async fn store_accounts(&self, user_id: Uuid, item_id: &str, accounts: &[BankAccount]) -> Result<()> {
+        self.upsert_accounts(user_id, item_id, accounts).await?;
+        Ok(())
+    }
+}
+
+fn is_pagination_mutation(error: &anyhow::Error) -> bool {
+    format!("{:?}", error).contains("TRANSACTIONS_SYNC_MUTATION_DURING_PAGINATION")
+}
+
+/// Fetch and apply every page after `cursor`, committing the cursor with the final page
+async fn sync_pages(
+    provider: &dyn BankDataProvider,
+    access_token: &str,
+    mut cursor: Option<String>,
+    sink: &dyn SyncPageSink,
+) -> Result<SyncSummary> {
+    let mut total = SyncSummary::default();
+
+    loop {
+        let page = provider
+            .sync_transactions(TransactionSyncRequest {
+                access_token: access_token.to_string(),
+                cursor: cursor.clone(),
+                count: Some(SYNC_PAGE_SIZE),
+            })
+            .await?;
+
+        let commit_cursor = (!page.has_more).then_some(page.next_cursor.as_str());
+        total.merge(sink.apply_page(&page, commit_cursor).await?);
+
+        if !page.has_more {
+            return Ok(total);
+        }
+        // A provider repeating its cursor would otherwise page forever
+        if cursor.as_deref() == Some(page.next_cursor.as_str()) {
+            bail!("Transaction sync cursor did not advance past '{}'", page.next_cursor);
+        }
+        cursor = Some(page.next_cursor);
+    }
+}
+
+/// `sync_pages` from the last saved cursor, starting over from it when the provider reports
+/// the item changed mid-pagination; re-applying pages is idempotent
+async fn sync_pages_with_restarts(
+    provider: &dyn BankDataProvider,
+    access_token: &str,
+    saved_cursor: Option<&str>,
+    sink: &dyn SyncPageSink,
+) -> Result<SyncSummary> {
+    for attempt in 0..=MAX_PAGINATION_RESTARTS {
+        match sync_pages(provider, access_token, saved_cursor.map(str::to_string), sink).await {
+            Err(e) if is_pagination_mutation(&e) => {
+                warn!(attempt, "Item changed during pagination, restarting from stored cursor");
+            }
+            result => return result,
+        }
+    }
+
+    bail!("Transaction sync kept changing during pagination")
+}
+
+/// Store the balances `/accounts/get` reports, then publish them to balance stream subscribers;
+/// these may be cached by Plaid, but cost nothing
+async fn sync_balances(
+    provider: &dyn BankDataProvider,
+    access_token: &str,
+    user_id: Uuid,
+    item_id: &str,
+    sink: &dyn AccountSink,
+    balance_updates: &BalanceUpdates,
+) -> Result<()> {
+    let accounts = provider.get_accounts(access_token).await?;
+    sink.store_accounts(user_id, item_id, &accounts).await?;
+    balance_updates.publish_balances(user_id, accounts);
+    Ok(())
+}
+
+/// Incrementally syncs an item's transactions from its stored cursor
+#[derive(Clone)]
+pub struct TransactionSyncer {
+    providers: BankDataProviders,
+    items: PlaidItemRepository,
+    transactions: TransactionRepository,
+    accounts: BankAccountRepository,
+    balance_updates: BalanceUpdates,
+}
+
+impl TransactionSyncer {
+    pub fn new(
+        providers: BankDataProviders,
+        items: PlaidItemRepository,
+        transactions: TransactionRepository,
+        accounts: BankAccountRepository,
+    ) -> Self {
+        Self {
+            providers,
+            items,
+            transactions,
+            accounts,
+            balance_updates: BalanceUpdates::default(),
+        }
+    }
+
+    /// Publish the balances stored after each sync on `balance_updates`
+    pub fn with_balance_updates(mut self, balance_updates: BalanceUpdates) -> Self {
+        self.balance_updates = balance_updates;
+        self
+    }
+
+    /// Pull every page after the item's stored cursor and persist it, then store and publish
+    /// the item's current balances (recording a balance snapshot per account).
+    ///
+    /// Pages are applied as they arrive, but the cursor only advances once the final
+    /// page is stored. If Plaid reports a mutation during pagination the loop restarts
+    /// from the stored cursor; re-applying pages is idempotent.
+    #[instrument(skip(self, item), fields(item_id = %item.item_id, user_id = %item.user_id))]
+    pub async fn sync_item(&self, item: &PlaidItem) -> Result<SyncSummary> {
+        let provider = self.providers.get(&item.provider)?;
+        let access_token = self.items.access_token(item).await?;
+        let sink = ItemPages {
+            transactions: &self.transactions,
+            item,
+        };
+
+        let summary =
+            sync_pages_with_restarts(provider.as_ref(), &access_token, item.sync_cursor.as_deref(), &sink).await?;
+        info!(
+            item_id = %item.item_id,
+            added = summary.added,
+            modified = summary.modified,
+            removed = summary.removed,
+            "Transaction sync completed"
+        );
+
+        // Transactions are already stored, so a balance failure doesn't fail the sync
+        if let Err(e) = sync_balances(
+            provider.as_ref(),
+            &access_token,
+            item.user_id,
+            &item.item_id,
+            &self.accounts,
+            &self.balance_updates,
+        )
+        .await
+        {
+            warn!(error = %e, "Failed to store balances after transaction sync");
+        }
+        Ok(summary)
+    }
+
+    /// Backfill transactions older than the sync window via `/transactions/get`
+    #[instrument(skip(self, item), fields(item_id = %item.item_id))]
+    pub async fn backfill(&self, item: &PlaidItem, start_date: &str, end_date: &str) -> Result<usize> {
+        let provider = self.providers.get(&item.provider)?;
+        let access_token = self.items.access_token(item).await?;
+        let transactions = provider
+            .get_all_transactions(&access_token, start_date, end_date)
+            .await?;
+
+        self.transactions
+            .upsert_transactions(item.user_id, &item.item_id, &transactions)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapter::plaid::{
+        AccountBalances, AccountIdentity, BankTransaction, Institution, Liability, LinkTokenRequest, LinkTokenResponse,
+        PublicTokenExchangeRequest, PublicTokenExchangeResponse,
+    };
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    /// Provider answering `/transactions/sync` from a script, recording the cursors it was asked for
+    struct ScriptedProvider {
+        responses: Mutex<VecDeque<Result<TransactionSyncResponse>>>,
+        requested_cursors: Mutex<Vec<Option<String>>>,
+        accounts: Vec<BankAccount>,
+    }
+
+    impl ScriptedProvider {
+        fn new(responses: Vec<Result<TransactionSyncResponse>>) -> Self {
+            Self {
+                responses: Mutex::new(responses.into()),
+                requested_cursors: Mutex::new(Vec::new()),
+                accounts: Vec::new(),
+            }
+        }
+
+        fn with_accounts(mut self, accounts: Vec<BankAccount>) -> Self {
+            self.accounts = accounts;
+            self
+        }
+
+        fn requested_cursors(&self) -> Vec<Option<String>> {
+            self.requested_cursors.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait]
+    impl BankDataProvider for ScriptedProvider {
+        fn name(&self) -> &'static str {
+            "scripted"
+        }
+
+        async fn create_link_token(&self, _request: LinkTokenRequest) -> Result<LinkTokenResponse> {
+            unimplemented!()
+        }
+
+        async fn exchange_public_token(&self, _request: PublicTokenExchangeRequest) -> Result<PublicTokenExchangeResponse> {
+            unimplemented!()
+        }
+
+        async fn get_accounts(&self, _access_token: &str) -> Result<Vec<BankAccount>> {
+            Ok(self.accounts.clone())
+        }
+
+        async fn get_balances(&self, _access_token: &str) -> Result<Vec<BankAccount>> {
+            unimplemented!()
+        }
+
+        async fn sync_transactions(&self, request: TransactionSyncRequest) -> Result<TransactionSyncResponse> {
+            self.requested_cursors.lock().unwrap().push(request.cursor);
+            self.responses
+                .lock()
+                .unwrap()
+                .pop_front()
+                .unwrap_or_else(|| Err(anyhow::anyhow!("No scripted response left")))
+        }
+
+        async fn get_all_transactions(
+            &self,
+            _access_token: &str,
+            _start_date: &str,
+            _end_date: &str,
+        ) -> Result<Vec<BankTransaction>> {
+            unimplemented!()
+        }
+
+        async fn get_liabilities(&self, _access_token: &str) -> Result<Vec<Liability>> {
+            unimplemented!()
+        }
+
+        async fn get_identity(&self, _access_token: &str) -> Result<Vec<AccountIdentity>> {
+            unimplemented!()
+        }
+
+        async fn get_institution(&self, _institution_id: &str, _country_codes: Vec<&str>) -> Result<Institution> {
+            unimplemented!()
+        }
+
+        async fn remove_item(&self, _access_token: &str) -> Result<()> {
+            unimplemented!()
+        }
+    }
+
+    /// Sink recording the cursor committed with each applied page
+    #[derive(Default)]
+    struct RecordingSink {
+        commits: Mutex<Vec<Option<String>>>,
+    }
+
+    #[async_trait]
+    impl SyncPageSink for RecordingSink {
+        async fn apply_page(&self, page: &TransactionSyncResponse, commit_cursor: Option<&str>) -> Result<SyncSummary> {
+            self.commits.lock().unwrap().push(commit_cursor.map(str::to_string));
+            Ok(SyncSummary {
+                added: page.added.len(),
+                modified: page.modified.len(),
+                removed: page.removed.len(),
+            })
+        }
+    }
+
+    /// Sink recording the accounts stored for each item
+    #[derive(Default)]
+    struct RecordingAccounts {
+        stored: Mutex<Vec<(String, usize)>>,
+    }
+
+    #[async_trait]
+    impl AccountSink for RecordingAccounts {
+        async fn store_accounts(&self, _user_id: Uuid, item_id: &str, accounts: &[BankAccount]) -> Result<()> {
+            self.stored.lock().unwrap().push((item_id.to_string(), accounts.len()));
+            Ok(())
+        }
+    }
+
+    fn account(account_id: &str, current: f64) -> BankAccount {
+        BankAccount {
+            account_id: account_id.to_string(),
+            item_id: "item-1".to_string(),
+            mask: None,
+            name: "Checking".to_string(),
+            official_name: None,
+            account_type: "depository".to_string(),
+            account_subtype: None,
+            balances: AccountBalances {
+                available: None,
+                current: Some(current),
+                limit: None,
+                iso_currency_code: Some("USD".to_string()),
+                unofficial_currency_code: None,
+            },
+            institution_id: None,
+            institution_name: None,
+        }
+    }
+
+    fn page(next_cursor: &str, has_more: bool) -> Result<TransactionSyncResponse> {
+        Ok(TransactionSyncResponse {
+            added: Vec::new(),
+            modified: Vec::new(),
+            removed: vec![crate::adapter::plaid::RemovedTransaction {
+                transaction_id: format!("removed-{}", next_cursor),
+            }],
+            next_cursor: next_cursor.to_string(),
+            has_more,
+            request_id: String::new(),
+        })
+    }
+
+    fn mutation() -> Result<TransactionSyncResponse> {
+        Err(anyhow::anyhow!("Plaid error: TRANSACTIONS_SYNC_MUTATION_DURING_PAGINATION"))
+    }
+
+    fn cursors(values: &[Option<&str>]) -> Vec<Option<String>> {
+        values.iter().map(|v| v.map(str::to_string)).collect()
+    }
+
+    #[tokio::test]
+    async fn test_pages_follow_cursor_and_commit_last() {
+        let provider = ScriptedProvider::new(vec![page("c1", true), page("c2", true), page("c3", false)]);
+        let sink = RecordingSink::default();
+
+        let summary = sync_pages_with_restarts(&provider, "token", Some("c0"), &sink).await.unwrap();
+
+        assert_eq!(summary.removed, 3);
+        assert_eq!(provider.requested_cursors(), cursors(&[Some("c0"), Some("c1"), Some("c2")]));
+        assert_eq!(*sink.commits.lock().unwrap(), cursors(&[None, None, Some("c3")]));
+    }
+
+    #[tokio::test]
+    async fn test_mutation_restarts_from_saved_cursor() {
+        let provider = ScriptedProvider::new(vec![
+            page("c1", true),
+            mutation(),
+            page("c1", true),
+            page("c2", false),
+        ]);
+        let sink = RecordingSink::default();
+
+        sync_pages_with_restarts(&provider, "token", None, &sink).await.unwrap();
+
+        assert_eq!(provider.requested_cursors(), cursors(&[None, Some("c1"), None, Some("c1")]));
+        assert_eq!(*sink.commits.lock().unwrap(), cursors(&[None, None, Some("c2")]));
+    }
+
+    #[tokio::test]
+    async fn test_repeated_mutations_give_up() {
+        let provider = ScriptedProvider::new((0..=MAX_PAGINATION_RESTARTS).map(|_| mutation()).collect());
+        let sink = RecordingSink::default();
+
+        let error = sync_pages_with_restarts(&provider, "token", Some("c0"), &sink).await.unwrap_err();
+
+        assert!(error.to_string().contains("kept changing"));
+        assert_eq!(provider.requested_cursors().len(), MAX_PAGINATION_RESTARTS + 1);
+        assert!(sink.commits.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_other_errors_are_not_retried() {
+        let provider = ScriptedProvider::new(vec![Err(anyhow::anyhow!("ITEM_LOGIN_REQUIRED"))]);
+        let sink = RecordingSink::default();
+
+        let error = sync_pages_with_restarts(&provider, "token", None, &sink).await.unwrap_err();
+
+        assert!(format!("{:?}", error).contains("ITEM_LOGIN_REQUIRED"));
+        assert_eq!(provider.requested_cursors().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_stuck_cursor_stops_pagination() {
+        let provider = ScriptedProvider::new(vec![page("c1", true), page("c1", true), page("c1", true)]);
+        let sink = RecordingSink::default();
+
+        let error = sync_pages_with_restarts(&provider, "token", None, &sink).await.unwrap_err();
+
+        assert!(error.to_string().contains("did not advance"));
+        assert_eq!(provider.requested_cursors(), cursors(&[None, Some("c1")]));
+        assert_eq!(*sink.commits.lock().unwrap(), cursors(&[None, None]));
+    }
+
+    #[tokio::test]
+    async fn test_balances_published_after_they_are_stored() {
+        let provider = ScriptedProvider::new(Vec::new()).with_accounts(vec![account("acc-1", 120.5)]);
+        let sink = RecordingAccounts::default();
+        let updates = BalanceUpdates::default();
+        let user_id = Uuid::new_v4();
+        let mut receiver = updates.subscribe(user_id);
+
+        sync_balances(&provider, "token", user_id, "item-1", &sink, &updates).await.unwrap();
+
+        assert_eq!(*sink.stored.lock().unwrap(), vec![("item-1".to_string(), 1)]);
+        let update = receiver.recv().await.unwrap();
+        assert_eq!(update.user_id, user_id);
+        assert_eq!(update.accounts[0].account_id, "acc-1");
+        assert_eq!(update.accounts[0].balances.current, Some(120.5));
+    }
+}
@@ -0,0 +1,313 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use tracing::{info, instrument};
+use uuid::Uuid;
+
+/// Lifecycle of a transfer between two of a user's accounts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferStatus {
+    /// Plaid approved the debit; waiting for the user to confirm
+    Authorized,
+    /// Plaid's risk check declined the debit
+    Declined,
+    /// Debit from the source account sent, waiting for it to settle
+    DebitPending,
+    /// Debit settled, credit to the destination account sent
+    CreditPending,
+    /// Credit settled
+    Completed,
+    /// A leg failed, was returned or could not be authorized
+    Failed,
+    /// Cancelled before the debit went out
+    Cancelled,
+}
+
+impl TransferStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransferStatus::Authorized => "authorized",
+            TransferStatus::Declined => "declined",
+            TransferStatus::DebitPending => "debit_pending",
+            TransferStatus::CreditPending => "credit_pending",
+            TransferStatus::Completed => "completed",
+            TransferStatus::Failed => "failed",
+            TransferStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "authorized" => Some(TransferStatus::Authorized),
+            "declined" => Some(TransferStatus::Declined),
+            "debit_pending" => Some(TransferStatus::DebitPending),
+            "credit_pending" => Some(TransferStatus::CreditPending),
+            "completed" => Some(TransferStatus::Completed),
+            "failed" => Some(TransferStatus::Failed),
+            "cancelled" => Some(TransferStatus::Cancelled),
+            _ => None,
+        }
+    }
+
+    /// Whether no further state changes are expected
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            TransferStatus::Declined | TransferStatus::Completed | TransferStatus::Failed | TransferStatus::Cancelled
+        )
+    }
+}
+
+/// Persisted transfer; the Plaid IDs of each leg are filled in as the legs are created
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Transfer {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub from_account_id: String,
+    pub to_account_id: String,
+    pub amount: f64,
+    pub iso_currency_code: String,
+    pub description: String,
+    pub status: String,
+    pub debit_authorization_id: Option<String>,
+    pub debit_transfer_id: Option<String>,
+    pub credit_transfer_id: Option<String>,
+    pub failure_reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Transfer {
+    pub fn status(&self) -> Option<TransferStatus> {
+        TransferStatus::parse(&self.status)
+    }
+}
+
+/// Transfer to record once Plaid has decided on the debit authorization
+#[derive(Debug, Clone)]
+pub struct NewTransfer {
+    pub user_id: Uuid,
+    pub from_account_id: String,
+    pub to_account_id: String,
+    pub amount: f64,
+    pub iso_currency_code: String,
+    pub description: String,
+    /// `Authorized` or `Declined`
+    pub status: TransferStatus,
+    pub debit_authorization_id: String,
+    pub failure_reason: Option<String>,
+}
+
+/// Transfer repository for database operations
+#[derive(Debug, Clone)]
+pub struct TransferRepository {
+    pool: PgPool,
+}
+
+impl TransferRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    #[instrument(skip(self, transfer), fields(user_id = %transfer.user_id))]
+    pub async fn create(&self, transfer: &NewTransfer) -> Result<Transfer> {
+        let created = sqlx::query_as::<_, Transfer>(
+            r#"
+            INSERT INTO transfers (
+                user_id, from_account_id, to_account_id, amount, iso_currency_code, description,
+                status, debit_authorization_id, failure_reason
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING *
+            "#,
+        )
+        .bind(transfer.user_id)
+        .bind(&transfer.from_account_id)
+        .bind(&transfer.to_account_id)
+        .bind(transfer.amount)
+        .bind(&transfer.iso_currency_code)
+        .bind(&transfer.description)
+        .bind(transfer.status.as_str())
+        .bind(&transfer.debit_authorization_id)
+        .bind(&transfer.failure_reason)
+        .fetch_one(&self.pool)
+        .await?;
+
+        info!(transfer_id = %created.id, status = %created.status, "Transfer recorded");
+        Ok(created)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn find(&self, id: Uuid) -> Result<Option<Transfer>> {
+        let transfer = sqlx::query_as::<_, Transfer>("SELECT * FROM transfers WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(transfer)
+    }
+
+    /// Transfer with a leg carrying the given Plaid transfer ID
+    #[instrument(skip(self))]
+    pub async fn find_by_plaid_transfer_id(&self, plaid_transfer_id: &str) -> Result<Option<Transfer>> {
+        let transfer = sqlx::query_as::<_, Transfer>(
+            "SELECT * FROM transfers WHERE debit_transfer_id = $1 OR credit_transfer_id = $1",
+        )
+        .bind(plaid_transfer_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(transfer)
+    }
+
+    /// A user's transfers, newest first
+    #[instrument(skip(self))]
+    pub async fn list_by_user(&self, user_id: Uuid, limit: i64) -> Result<Vec<Transfer>> {
+        let transfers = sqlx::query_as::<_, Transfer>(
+            "SELECT * FROM transfers WHERE user_id = $1 ORDER BY created_at DESC LIMIT $2",
+        )
+        .bind(user_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(transfers)
+    }
+
+    /// Record the debit leg of an authorized transfer; `None` when it is no longer authorized
+    #[instrument(skip(self))]
+    pub async fn mark_debit_pending(&self, id: Uuid, debit_transfer_id: &str) -> Result<Option<Transfer>> {
+        let transfer = sqlx::query_as::<_, Transfer>(
+            r#"
+            UPDATE transfers SET status = 'debit_pending', debit_transfer_id = $2, updated_at = NOW()
+            WHERE id = $1 AND status = 'authorized'
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(debit_transfer_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(transfer)
+    }
+
+    /// Record the credit leg once the debit settled; `None` when the debit is no longer pending
+    #[instrument(skip(self))]
+    pub async fn mark_credit_pending(&self, id: Uuid, credit_transfer_id: &str) -> Result<Option<Transfer>> {
+        let transfer = sqlx::query_as::<_, Transfer>(
+            r#"
+            UPDATE transfers SET status = 'credit_pending', credit_transfer_id = $2, updated_at = NOW()
+            WHERE id = $1 AND status = 'debit_pending'
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(credit_transfer_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(transfer)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn mark_completed(&self, id: Uuid) -> Result<Option<Transfer>> {
+        let transfer = sqlx::query_as::<_, Transfer>(
+            r#"
+            UPDATE transfers SET status = 'completed', updated_at = NOW()
+            WHERE id = $1 AND status = 'credit_pending'
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(transfer)
+    }
+
+    /// Fail a transfer that is still in progress
+    #[instrument(skip(self))]
+    pub async fn mark_failed(&self, id: Uuid, reason: &str) -> Result<Option<Transfer>> {
+        let transfer = sqlx::query_as::<_, Transfer>(
+            r#"
+            UPDATE transfers SET status = 'failed', failure_reason = $2, updated_at = NOW()
+            WHERE id = $1 AND status IN ('authorized', 'debit_pending', 'credit_pending')
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(reason)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(transfer)
+    }
+
+    /// Cancel a transfer whose debit has not settled
+    #[instrument(skip(self))]
+    pub async fn mark_cancelled(&self, id: Uuid) -> Result<Option<Transfer>> {
+        let transfer = sqlx::query_as::<_, Transfer>(
+            r#"
+            UPDATE transfers SET status = 'cancelled', updated_at = NOW()
+            WHERE id = $1 AND status IN ('authorized', 'debit_pending')
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(transfer)
+    }
+
+    /// ID of the last Plaid transfer event applied
+    #[instrument(skip(self))]
+    pub async fn last_event_id(&self) -> Result<i64> {
+        let last_event_id: i64 = sqlx::query_scalar("SELECT last_event_id FROM transfer_event_cursor WHERE id")
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(last_event_id)
+    }
+
+    /// Advance the event cursor; it never moves backwards
+    #[instrument(skip(self))]
+    pub async fn save_last_event_id(&self, event_id: i64) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE transfer_event_cursor
+            SET last_event_id = GREATEST(last_event_id, $1), updated_at = NOW()
+            WHERE id
+            "#,
+        )
+        .bind(event_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transfer_status_round_trip() {
+        for status in [
+            TransferStatus::Authorized,
+            TransferStatus::Declined,
+            TransferStatus::DebitPending,
+            TransferStatus::CreditPending,
+            TransferStatus::Completed,
+            TransferStatus::Failed,
+            TransferStatus::Cancelled,
+        ] {
+            assert_eq!(TransferStatus::parse(status.as_str()), Some(status));
+        }
+        assert_eq!(TransferStatus::parse("settled"), None);
+        assert!(TransferStatus::Completed.is_terminal());
+        assert!(!TransferStatus::CreditPending.is_terminal());
+    }
+}
@@ -1,4 +1,7 @@
+use crate::model::read_pool::ReadPool;
+use crate::model::transaction::contains_pattern;
 use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use std::collections::HashMap;
@@ -14,10 +17,27 @@ pub struct User {
     pub email: String,
     pub name: String,
     pub picture_url: Option<String>,
+    /// ISO-4217 currency aggregates are converted to
+    pub display_currency: String,
+    /// Opted in to the weekly summary email
+    pub weekly_digest_enabled: bool,
+    /// Language tag the user's emails are written in, such as "es-MX"
+    pub locale: String,
+    /// IANA time zone scheduled emails are sent in, such as "Europe/Madrid"
+    pub timezone: String,
+    /// May call the admin RPCs; granted with the `create-admin` command
+    pub is_admin: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+impl User {
+    /// The user's time zone; UTC if the stored name isn't known
+    pub fn time_zone(&self) -> Tz {
+        self.timezone.parse().unwrap_or(Tz::UTC)
+    }
+}
+
 /// Request structure for creating a new user from Google OAuth data
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateUserRequest {
@@ -25,6 +45,8 @@ pub struct CreateUserRequest {
     pub email: String,
     pub name: String,
     pub picture_url: Option<String>,
+    /// Language tag from the identity provider; English when unknown
+    pub locale: Option<String>,
 }
 
 /// Request structure for updating user profile
@@ -32,17 +54,29 @@ pub struct CreateUserRequest {
 pub struct UpdateUserRequest {
     pub name: Option<String>,
     pub picture_url: Option<String>,
+    pub locale: Option<String>,
 }
 
 /// User repository for database operations
 #[derive(Debug, Clone)]
 pub struct UserRepository {
     pool: PgPool,
+    reads: ReadPool,
 }
 
 impl UserRepository {
     pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+        Self {
+            reads: ReadPool::new(pool.clone()),
+            pool,
+        }
+    }
+
+    /// Run user searches, stats and digest recipient lists on `reads`, which may route them to a
+    /// read replica
+    pub fn with_read_pool(mut self, reads: ReadPool) -> Self {
+        self.reads = reads;
+        self
     }
 
     /// Create a new user from Google OAuth data
@@ -52,8 +86,8 @@ impl UserRepository {
 
         let user = sqlx::query_as::<_, User>(
             r#"
-            INSERT INTO users (google_id, email, name, picture_url)
-            VALUES ($1, $2, $3, $4)
+            INSERT INTO users (google_id, email, name, picture_url, locale)
+            VALUES ($1, $2, $3, $4, COALESCE($5, 'en'))
             RETURNING *
             "#,
         )
@@ -61,6 +95,7 @@ impl UserRepository {
         .bind(&request.email)
         .bind(&request.name)
         .bind(&request.picture_url)
+        .bind(&request.locale)
         .fetch_one(&self.pool)
         .await?;
 
@@ -134,6 +169,25 @@ impl UserRepository {
         Ok(user)
     }
 
+    /// Users whose email or name contains `query`, or the user with that ID, newest first
+    #[instrument(skip(self, query))]
+    pub async fn search(&self, query: &str, limit: i64) -> Result<Vec<User>, sqlx::Error> {
+        if let Ok(user_id) = Uuid::parse_str(query.trim()) {
+            return Ok(self.find_by_id(user_id).await?.into_iter().collect());
+        }
+
+        self.reads
+            .fetch(|pool| async move {
+                sqlx::query_as::<_, User>(
+                    "SELECT * FROM users WHERE email ILIKE $1 OR name ILIKE $1 ORDER BY created_at DESC LIMIT $2",
+                )
+                .bind(contains_pattern(query))
+                .bind(limit)
+                .fetch_all(&pool)
+                .await
+            })
+            .await
+    }
 
     /// Update user profile information (typically from updated Google profile)
     #[instrument(skip(self), fields(user_id = %user_id))]
@@ -145,6 +199,7 @@ impl UserRepository {
             UPDATE users SET
                 name = COALESCE($2, name),
                 picture_url = COALESCE($3, picture_url),
+                locale = COALESCE($4, locale),
                 updated_at = NOW()
             WHERE id = $1
             RETURNING *
@@ -153,6 +208,7 @@ impl UserRepository {
         .bind(user_id)
         .bind(&request.name)
         .bind(&request.picture_url)
+        .bind(&request.locale)
         .fetch_one(&self.pool)
         .await?;
 
@@ -161,6 +217,97 @@ impl UserRepository {
         Ok(user)
     }
 
+    /// Set the currency aggregates are displayed in
+    #[instrument(skip(self), fields(user_id = %user_id))]
+    pub async fn set_display_currency(&self, user_id: Uuid, currency: &str) -> Result<Option<User>, sqlx::Error> {
+        let user = sqlx::query_as::<_, User>(
+            "UPDATE users SET display_currency = $2, updated_at = NOW() WHERE id = $1 RETURNING *"
+        )
+        .bind(user_id)
+        .bind(currency)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        if user.is_some() {
+            info!(currency = %currency, "Updated display currency");
+        }
+        Ok(user)
+    }
+
+    /// Opt in to or out of the weekly summary email
+    #[instrument(skip(self), fields(user_id = %user_id))]
+    pub async fn set_weekly_digest(&self, user_id: Uuid, enabled: bool) -> Result<Option<User>, sqlx::Error> {
+        let user = sqlx::query_as::<_, User>(
+            "UPDATE users SET weekly_digest_enabled = $2, updated_at = NOW() WHERE id = $1 RETURNING *"
+        )
+        .bind(user_id)
+        .bind(enabled)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        if user.is_some() {
+            info!(enabled, "Updated weekly digest preference");
+        }
+        Ok(user)
+    }
+
+    /// Set the time zone scheduled emails are sent in; `timezone` must be a known IANA name
+    #[instrument(skip(self), fields(user_id = %user_id))]
+    pub async fn set_timezone(&self, user_id: Uuid, timezone: &str) -> Result<Option<User>, sqlx::Error> {
+        let user = sqlx::query_as::<_, User>(
+            "UPDATE users SET timezone = $2, updated_at = NOW() WHERE id = $1 RETURNING *"
+        )
+        .bind(user_id)
+        .bind(timezone)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        if user.is_some() {
+            info!(timezone = %timezone, "Updated time zone");
+        }
+        Ok(user)
+    }
+
+    /// Grant or revoke the admin role
+    #[instrument(skip(self), fields(user_id = %user_id))]
+    pub async fn set_admin(&self, user_id: Uuid, is_admin: bool) -> Result<Option<User>, sqlx::Error> {
+        let user = sqlx::query_as::<_, User>(
+            "UPDATE users SET is_admin = $2, updated_at = NOW() WHERE id = $1 RETURNING *"
+        )
+        .bind(user_id)
+        .bind(is_admin)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        if user.is_some() {
+            info!(is_admin, "Updated admin role");
+        }
+        Ok(user)
+    }
+
+    /// Whether the user has the admin role; read from the primary, so a grant or revocation applies at once
+    #[instrument(skip(self))]
+    pub async fn is_admin(&self, user_id: Uuid) -> Result<bool, sqlx::Error> {
+        let is_admin: Option<bool> = sqlx::query_scalar("SELECT is_admin FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(is_admin.unwrap_or(false))
+    }
+
+    /// Users who opted in to the weekly summary email
+    #[instrument(skip(self))]
+    pub async fn list_weekly_digest_recipients(&self) -> Result<Vec<User>, sqlx::Error> {
+        self.reads
+            .fetch(|pool| async move {
+                sqlx::query_as::<_, User>("SELECT * FROM users WHERE weekly_digest_enabled ORDER BY id")
+                    .fetch_all(&pool)
+                    .await
+            })
+            .await
+    }
+
     /// Delete a user (for GDPR compliance)
     #[instrument(skip(self))]
     pub async fn delete_user(&self, user_id: Uuid) -> Result<(), sqlx::Error> {
@@ -193,6 +340,7 @@ impl UserRepository {
             let update_request = UpdateUserRequest {
                 name: Some(request.name),
                 picture_url: request.picture_url,
+                locale: request.locale,
             };
 
             let updated_user = self.update_user(existing_user.id, update_request).await?;
@@ -214,27 +362,32 @@ impl UserRepository {
         let mut stats = HashMap::new();
 
         // Total users
-        let total_users: (i64,) = sqlx::query_as(
-            "SELECT COUNT(*) FROM users"
-        )
-        .fetch_one(&self.pool)
-        .await?;
+        let total_users: (i64,) = self
+            .reads
+            .fetch(|pool| async move { sqlx::query_as("SELECT COUNT(*) FROM users").fetch_one(&pool).await })
+            .await?;
         stats.insert("total_users".to_string(), total_users.0);
 
         // Users created in last 24 hours
-        let new_users_24h: (i64,) = sqlx::query_as(
-            "SELECT COUNT(*) FROM users WHERE created_at > NOW() - INTERVAL '24 hours'"
-        )
-        .fetch_one(&self.pool)
-        .await?;
+        let new_users_24h: (i64,) = self
+            .reads
+            .fetch(|pool| async move {
+                sqlx::query_as("SELECT COUNT(*) FROM users WHERE created_at > NOW() - INTERVAL '24 hours'")
+                    .fetch_one(&pool)
+                    .await
+            })
+            .await?;
         stats.insert("new_users_24h".to_string(), new_users_24h.0);
 
         // Users created in last 7 days
-        let new_users_7d: (i64,) = sqlx::query_as(
-            "SELECT COUNT(*) FROM users WHERE created_at > NOW() - INTERVAL '7 days'"
-        )
-        .fetch_one(&self.pool)
-        .await?;
+        let new_users_7d: (i64,) = self
+            .reads
+            .fetch(|pool| async move {
+                sqlx::query_as("SELECT COUNT(*) FROM users WHERE created_at > NOW() - INTERVAL '7 days'")
+                    .fetch_one(&pool)
+                    .await
+            })
+            .await?;
         stats.insert("new_users_7d".to_string(), new_users_7d.0);
 
         debug!(
@@ -273,6 +426,7 @@ mod tests {
             email: "test@example.com".to_string(),
             name: "Test User".to_string(),
             picture_url: Some("https://example.com/picture.jpg".to_string()),
+            locale: None,
         };
 
         let user = repo.create_user(request).await.unwrap();
@@ -0,0 +1,554 @@
+use crate::adapter::encryption::{EncryptedSecret, EnvelopeCipher};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, instrument};
+use uuid::Uuid;
+
+/// Wait before the first retry; doubled after each further failure
+const BASE_RETRY_DELAY: Duration = Duration::from_secs(30);
+/// Longest wait between attempts
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(6 * 3600);
+
+/// Events endpoints can subscribe to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WebhookEventType {
+    /// A sync stored a new transaction
+    TransactionCreated,
+    /// A bank item was linked
+    ItemLinked,
+}
+
+impl WebhookEventType {
+    pub const ALL: [WebhookEventType; 2] = [WebhookEventType::TransactionCreated, WebhookEventType::ItemLinked];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEventType::TransactionCreated => "transaction.created",
+            WebhookEventType::ItemLinked => "item.linked",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|event_type| event_type.as_str() == value)
+    }
+}
+
+/// Delivery state of a webhook call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookDeliveryStatus {
+    /// Waiting for its next attempt
+    Pending,
+    /// Claimed by a worker
+    Delivering,
+    /// The endpoint answered with a 2xx status
+    Delivered,
+    /// Out of attempts; kept for the delivery log
+    Failed,
+}
+
+impl WebhookDeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookDeliveryStatus::Pending => "pending",
+            WebhookDeliveryStatus::Delivering => "delivering",
+            WebhookDeliveryStatus::Delivered => "delivered",
+            WebhookDeliveryStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(WebhookDeliveryStatus::Pending),
+            "delivering" => Some(WebhookDeliveryStatus::Delivering),
+            "delivered" => Some(WebhookDeliveryStatus::Delivered),
+            "failed" => Some(WebhookDeliveryStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// Wait before retrying a delivery that failed `attempts` times
+pub fn retry_delay(attempts: i32) -> Duration {
+    let doublings = attempts.saturating_sub(1).clamp(0, 16) as u32;
+    BASE_RETRY_DELAY.saturating_mul(1 << doublings).min(MAX_RETRY_DELAY)
+}
+
+/// Body sent for an event: its ID, type and time, with the event's own fields under `data`
+pub fn event_payload(event_id: Uuid, event_type: WebhookEventType, created_at: DateTime<Utc>, data: Value) -> Value {
+    serde_json::json!({
+        "id": event_id,
+        "type": event_type.as_str(),
+        "created_at": created_at.to_rfc3339(),
+        "data": data,
+    })
+}
+
+/// Registered endpoint; its signing secret is only read through `WebhookRepository::target`
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct WebhookEndpoint {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub url: String,
+    pub description: String,
+    pub event_types: Vec<String>,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Where and how to sign calls to an endpoint
+pub struct WebhookTarget {
+    pub url: String,
+    pub secret: String,
+}
+
+/// URL and encrypted signing secret of an endpoint
+#[derive(sqlx::FromRow)]
+struct TargetRow {
+    url: String,
+    secret_ciphertext: Vec<u8>,
+    secret_nonce: Vec<u8>,
+    encrypted_data_key: Vec<u8>,
+    encryption_key_id: String,
+}
+
+/// One event for one endpoint, and the outcome of its latest attempt
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub endpoint_id: Uuid,
+    pub event_id: Uuid,
+    pub event_type: String,
+    pub payload: Value,
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub claimed_at: Option<DateTime<Utc>>,
+    /// HTTP status the endpoint answered the latest attempt with
+    pub response_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub duration_ms: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+impl WebhookDelivery {
+    pub fn status(&self) -> Option<WebhookDeliveryStatus> {
+        WebhookDeliveryStatus::parse(&self.status)
+    }
+}
+
+const ENDPOINT_COLUMNS: &str = "id, user_id, url, description, event_types, enabled, created_at, updated_at";
+
+/// Webhook endpoints and their delivery log
+#[derive(Clone)]
+pub struct WebhookRepository {
+    pool: PgPool,
+    cipher: Arc<EnvelopeCipher>,
+}
+
+impl WebhookRepository {
+    pub fn new(pool: PgPool, cipher: Arc<EnvelopeCipher>) -> Self {
+        Self { pool, cipher }
+    }
+
+    /// Register an endpoint; `secret` signs every call made to it
+    #[instrument(skip(self, url, secret))]
+    pub async fn create_endpoint(
+        &self,
+        user_id: Uuid,
+        url: &str,
+        description: &str,
+        event_types: &[WebhookEventType],
+        secret: &str,
+    ) -> Result<WebhookEndpoint> {
+        let id = Uuid::new_v4();
+        let encrypted = self
+            .cipher
+            .encrypt(secret, &id.to_string())
+            .context("Failed to encrypt webhook secret")?;
+        let event_types: Vec<&str> = event_types.iter().map(WebhookEventType::as_str).collect();
+
+        let endpoint = sqlx::query_as::<_, WebhookEndpoint>(&format!(
+            r#"
+            INSERT INTO webhook_endpoints (
+                id, user_id, url, description, event_types,
+                secret_ciphertext, secret_nonce, encrypted_data_key, encryption_key_id
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING {}
+            "#,
+            ENDPOINT_COLUMNS
+        ))
+        .bind(id)
+        .bind(user_id)
+        .bind(url)
+        .bind(description)
+        .bind(&event_types)
+        .bind(&encrypted.ciphertext)
+        .bind(&encrypted.nonce)
+        .bind(&encrypted.encrypted_data_key)
+        .bind(&encrypted.key_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        info!(user_id = %user_id, endpoint_id = %endpoint.id, "Webhook endpoint registered");
+        Ok(endpoint)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn list_endpoints(&self, user_id: Uuid) -> Result<Vec<WebhookEndpoint>> {
+        let endpoints = sqlx::query_as::<_, WebhookEndpoint>(&format!(
+            "SELECT {} FROM webhook_endpoints WHERE user_id = $1 ORDER BY created_at",
+            ENDPOINT_COLUMNS
+        ))
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(endpoints)
+    }
+
+    /// One of the user's endpoints
+    #[instrument(skip(self))]
+    pub async fn find_endpoint(&self, user_id: Uuid, id: Uuid) -> Result<Option<WebhookEndpoint>> {
+        let endpoint = sqlx::query_as::<_, WebhookEndpoint>(&format!(
+            "SELECT {} FROM webhook_endpoints WHERE id = $1 AND user_id = $2",
+            ENDPOINT_COLUMNS
+        ))
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(endpoint)
+    }
+
+    /// Remove one of the user's endpoints along with its delivery log; returns whether it existed
+    #[instrument(skip(self))]
+    pub async fn delete_endpoint(&self, user_id: Uuid, id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM webhook_endpoints WHERE id = $1 AND user_id = $2")
+            .bind(id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() > 0 {
+            info!(user_id = %user_id, endpoint_id = %id, "Webhook endpoint deleted");
+        }
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Replace the signing secret of one of the user's endpoints; returns whether it exists.
+    /// Deliveries already claimed may still be signed with the old secret.
+    #[instrument(skip(self, secret))]
+    pub async fn rotate_secret(&self, user_id: Uuid, id: Uuid, secret: &str) -> Result<bool> {
+        let encrypted = self
+            .cipher
+            .encrypt(secret, &id.to_string())
+            .context("Failed to encrypt webhook secret")?;
+        let result = sqlx::query(
+            r#"
+            UPDATE webhook_endpoints
+            SET secret_ciphertext = $3, secret_nonce = $4, encrypted_data_key = $5, encryption_key_id = $6,
+                updated_at = NOW()
+            WHERE id = $1 AND user_id = $2
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(&encrypted.ciphertext)
+        .bind(&encrypted.nonce)
+        .bind(&encrypted.encrypted_data_key)
+        .bind(&encrypted.key_id)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() > 0 {
+            info!(user_id = %user_id, endpoint_id = %id, "Webhook secret rotated");
+        }
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// URL and decrypted signing secret of an endpoint, unless it was deleted or disabled
+    #[instrument(skip(self))]
+    pub async fn target(&self, endpoint_id: Uuid) -> Result<Option<WebhookTarget>> {
+        let row = sqlx::query_as::<_, TargetRow>(
+            r#"
+            SELECT url, secret_ciphertext, secret_nonce, encrypted_data_key, encryption_key_id
+            FROM webhook_endpoints
+            WHERE id = $1 AND enabled
+            "#,
+        )
+        .bind(endpoint_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let secret = EncryptedSecret {
+            ciphertext: row.secret_ciphertext,
+            nonce: row.secret_nonce,
+            encrypted_data_key: row.encrypted_data_key,
+            key_id: row.encryption_key_id,
+        };
+        let secret = self
+            .cipher
+            .decrypt(&secret, &endpoint_id.to_string())
+            .context("Failed to decrypt webhook secret")?;
+        Ok(Some(WebhookTarget { url: row.url, secret }))
+    }
+
+    /// Whether the user has an enabled endpoint subscribed to `event_type`, so events nobody
+    /// receives aren't built
+    #[instrument(skip(self))]
+    pub async fn has_subscribers(&self, user_id: Uuid, event_type: WebhookEventType) -> Result<bool> {
+        let subscribed = sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM webhook_endpoints
+                WHERE user_id = $1 AND enabled AND $2 = ANY(event_types)
+            )
+            "#,
+        )
+        .bind(user_id)
+        .bind(event_type.as_str())
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(subscribed)
+    }
+
+    /// Queue an event for each of the user's enabled endpoints subscribed to its type; returns the
+    /// deliveries queued. An event already queued for an endpoint isn't queued again.
+    #[instrument(skip(self, payload))]
+    pub async fn enqueue(
+        &self,
+        user_id: Uuid,
+        event_type: WebhookEventType,
+        event_id: Uuid,
+        payload: &Value,
+    ) -> Result<Vec<Uuid>> {
+        let ids = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            INSERT INTO webhook_deliveries (endpoint_id, event_id, event_type, payload)
+            SELECT id, $3, $2, $4 FROM webhook_endpoints
+            WHERE user_id = $1 AND enabled AND $2 = ANY(event_types)
+            ON CONFLICT (endpoint_id, event_id) DO NOTHING
+            RETURNING id
+            "#,
+        )
+        .bind(user_id)
+        .bind(event_type.as_str())
+        .bind(event_id)
+        .bind(payload)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(ids)
+    }
+
+    /// Claim up to `limit` due deliveries, oldest first, along with deliveries whose claim is
+    /// older than `lease_seconds`. Concurrent workers claim disjoint deliveries.
+    #[instrument(skip(self))]
+    pub async fn claim(&self, limit: i64, lease_seconds: i64, max_attempts: i32) -> Result<Vec<WebhookDelivery>> {
+        let deliveries = sqlx::query_as::<_, WebhookDelivery>(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = 'delivering', attempts = attempts + 1, claimed_at = NOW(), updated_at = NOW()
+            WHERE id IN (
+                SELECT id FROM webhook_deliveries
+                WHERE attempts < $3
+                  AND ((status = 'pending' AND next_attempt_at <= NOW())
+                       OR (status = 'delivering' AND claimed_at < NOW() - make_interval(secs => $2)))
+                ORDER BY next_attempt_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+        )
+        .bind(limit)
+        .bind(lease_seconds as f64)
+        .bind(max_attempts)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(deliveries)
+    }
+
+    /// Claim one delivery if it is due and not claimed by another worker
+    #[instrument(skip(self))]
+    pub async fn claim_by_id(
+        &self,
+        id: Uuid,
+        lease_seconds: i64,
+        max_attempts: i32,
+    ) -> Result<Option<WebhookDelivery>> {
+        let delivery = sqlx::query_as::<_, WebhookDelivery>(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = 'delivering', attempts = attempts + 1, claimed_at = NOW(), updated_at = NOW()
+            WHERE id = (
+                SELECT id FROM webhook_deliveries
+                WHERE id = $1
+                  AND attempts < $3
+                  AND ((status = 'pending' AND next_attempt_at <= NOW())
+                       OR (status = 'delivering' AND claimed_at < NOW() - make_interval(secs => $2)))
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(lease_seconds as f64)
+        .bind(max_attempts)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(delivery)
+    }
+
+    /// Fail deliveries whose last attempt was claimed more than `lease_seconds` ago and never
+    /// finished, so they can't be claimed again; returns how many
+    #[instrument(skip(self))]
+    pub async fn fail_abandoned(&self, lease_seconds: i64, max_attempts: i32) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = 'failed', last_error = 'Last attempt did not finish', claimed_at = NULL, updated_at = NOW()
+            WHERE status = 'delivering'
+              AND attempts >= $2
+              AND claimed_at < NOW() - make_interval(secs => $1)
+            "#,
+        )
+        .bind(lease_seconds as f64)
+        .bind(max_attempts)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    #[instrument(skip(self))]
+    pub async fn mark_delivered(&self, id: Uuid, response_status: i32, duration_ms: i32) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = 'delivered', response_status = $2, duration_ms = $3, last_error = NULL,
+                claimed_at = NULL, delivered_at = NOW(), updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(response_status)
+        .bind(duration_ms)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Record a failed attempt; with `next_attempt_at` the delivery is retried then, without it
+    /// the delivery has failed for good
+    #[instrument(skip(self, error))]
+    pub async fn record_failure(
+        &self,
+        id: Uuid,
+        response_status: Option<i32>,
+        error: &str,
+        duration_ms: i32,
+        next_attempt_at: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = CASE WHEN $5::timestamptz IS NULL THEN 'failed' ELSE 'pending' END,
+                response_status = $2, last_error = $3, duration_ms = $4,
+                next_attempt_at = COALESCE($5, next_attempt_at), claimed_at = NULL, updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(response_status)
+        .bind(error)
+        .bind(duration_ms)
+        .bind(next_attempt_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Deliveries to one of the user's endpoints, newest first, starting after `after`
+    /// (`created_at`, `id`) of the previous page
+    #[instrument(skip(self))]
+    pub async fn list_deliveries(
+        &self,
+        user_id: Uuid,
+        endpoint_id: Uuid,
+        after: Option<(DateTime<Utc>, Uuid)>,
+        limit: i64,
+    ) -> Result<Vec<WebhookDelivery>> {
+        let (after_created_at, after_id) = after.unzip();
+        let deliveries = sqlx::query_as::<_, WebhookDelivery>(
+            r#"
+            SELECT d.* FROM webhook_deliveries d
+            JOIN webhook_endpoints e ON e.id = d.endpoint_id
+            WHERE d.endpoint_id = $1 AND e.user_id = $2
+              AND ($3::timestamptz IS NULL OR (d.created_at, d.id) < ($3, $4))
+            ORDER BY d.created_at DESC, d.id DESC
+            LIMIT $5
+            "#,
+        )
+        .bind(endpoint_id)
+        .bind(user_id)
+        .bind(after_created_at)
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(deliveries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_type_round_trip() {
+        for event_type in WebhookEventType::ALL {
+            assert_eq!(WebhookEventType::parse(event_type.as_str()), Some(event_type));
+        }
+        assert_eq!(WebhookEventType::parse("transaction.deleted"), None);
+    }
+
+    #[test]
+    fn test_retry_delay_backs_off() {
+        assert_eq!(retry_delay(1), Duration::from_secs(30));
+        assert_eq!(retry_delay(3), Duration::from_secs(120));
+        assert_eq!(retry_delay(20), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn test_event_payload_wraps_data() {
+        let event_id = Uuid::new_v4();
+        let payload = event_payload(
+            event_id,
+            WebhookEventType::ItemLinked,
+            Utc::now(),
+            serde_json::json!({ "item_id": "item-1" }),
+        );
+        assert_eq!(payload["id"], event_id.to_string());
+        assert_eq!(payload["type"], "item.linked");
+        assert_eq!(payload["data"]["item_id"], "item-1");
+    }
+}
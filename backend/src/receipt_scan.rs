@@ -0,0 +1,235 @@
+// Receipt OCR through Claude vision
+use crate::adapter::claude_ai::{ClaudeContentBlock, ClaudeContentMessage, MAX_INLINE_MEDIA_BYTES};
+use crate::adapter::llm::CLAUDE_PROVIDER;
+use crate::adapter::s3::S3Client;
+use crate::adapter::ClaudeAIClient;
+use crate::model::ai_rate_limit::AiRateLimiter;
+use crate::model::ai_usage::{AiFeature, AiUsageRepository, NewAiUsage};
+use crate::model::receipt::{Receipt, ReceiptExtraction, ReceiptLineItem, ReceiptRepository};
+use crate::prompts::{PromptKey, PromptRegistry};
+use anyhow::{anyhow, Context, Result};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::{info, instrument, warn};
+use uuid::Uuid;
+
+/// Output tokens allowed for one receipt
+const MAX_SCAN_TOKENS: u32 = 2048;
+
+/// Most line items kept from one receipt
+const MAX_LINE_ITEMS: usize = 100;
+
+/// Prefilled start of the assistant turn, forcing the reply to continue a JSON object
+const RESPONSE_PREFILL: &str = "{";
+
+/// Compiled default system prompt
+pub const SYSTEM_PROMPT: &str = "You read shopping receipts and invoices. Reply with JSON only, in the form \
+    {\"merchant\": <store name or null>, \"date\": <purchase date as YYYY-MM-DD or null>, \
+    \"total\": <amount paid or null>, \"tax\": <tax amount or null>, \"currency\": <ISO-4217 code or null>, \
+    \"line_items\": [{\"description\": <item>, \"quantity\": <number or null>, \"amount\": <line total or null>}]}. \
+    Amounts are plain numbers without currency symbols. Use null for anything you cannot read.";
+
+#[derive(Debug, Deserialize)]
+struct ExtractionPayload {
+    merchant: Option<String>,
+    date: Option<String>,
+    total: Option<f64>,
+    tax: Option<f64>,
+    currency: Option<String>,
+    #[serde(default)]
+    line_items: Vec<LineItemPayload>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LineItemPayload {
+    description: Option<String>,
+    quantity: Option<f64>,
+    amount: Option<f64>,
+}
+
+/// Validate the model's reply; unreadable fields become `None` rather than failing the scan
+fn parse_extraction(reply: &str) -> Result<ReceiptExtraction> {
+    let json = format!("{}{}", RESPONSE_PREFILL, reply.trim());
+    let payload: ExtractionPayload = serde_json::from_str(&json).context("Receipt scan reply is not valid JSON")?;
+    let finite = |value: Option<f64>| value.filter(|v| v.is_finite());
+    let text = |value: Option<String>| value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+
+    Ok(ReceiptExtraction {
+        merchant: text(payload.merchant),
+        date: payload
+            .date
+            .and_then(|date| NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").ok()),
+        total: finite(payload.total),
+        tax: finite(payload.tax),
+        currency: text(payload.currency)
+            .map(|c| c.to_ascii_uppercase())
+            .filter(|c| c.len() == 3 && c.chars().all(|ch| ch.is_ascii_alphabetic())),
+        line_items: payload
+            .line_items
+            .into_iter()
+            .filter_map(|item| {
+                Some(ReceiptLineItem {
+                    description: text(item.description)?,
+                    quantity: finite(item.quantity),
+                    amount: finite(item.amount),
+                })
+            })
+            .take(MAX_LINE_ITEMS)
+            .collect(),
+    })
+}
+
+/// Whether a file of `size_bytes` is small enough to send inline
+fn sent_inline(size_bytes: i64) -> bool {
+    usize::try_from(size_bytes).is_ok_and(|size| size <= MAX_INLINE_MEDIA_BYTES)
+}
+
+/// Reads merchant, total and line items from attached receipts and stores them on the receipt
+#[derive(Clone)]
+pub struct ReceiptScanner {
+    client: Arc<ClaudeAIClient>,
+    storage: Arc<S3Client>,
+    receipts: ReceiptRepository,
+    ai_usage: AiUsageRepository,
+    prompts: Arc<PromptRegistry>,
+    rate_limiter: Arc<AiRateLimiter>,
+}
+
+impl ReceiptScanner {
+    pub fn new(
+        client: Arc<ClaudeAIClient>,
+        storage: Arc<S3Client>,
+        receipts: ReceiptRepository,
+        ai_usage: AiUsageRepository,
+        prompts: Arc<PromptRegistry>,
+        rate_limiter: Arc<AiRateLimiter>,
+    ) -> Self {
+        Self {
+            client,
+            storage,
+            receipts,
+            ai_usage,
+            prompts,
+            rate_limiter,
+        }
+    }
+
+    /// Usage repository scans are recorded in, for checking quotas before a scan
+    pub fn ai_usage(&self) -> &AiUsageRepository {
+        &self.ai_usage
+    }
+
+    /// Limits scans are admitted under
+    pub fn rate_limiter(&self) -> &AiRateLimiter {
+        &self.rate_limiter
+    }
+
+    /// Whether receipts of this content type can be scanned
+    pub fn supports(content_type: &str) -> bool {
+        ClaudeContentBlock::media(content_type, &[]).is_some()
+    }
+
+    /// Image or PDF block of a receipt's file. Small files are sent inline; larger ones through a
+    /// presigned download URL the API fetches itself.
+    async fn media_block(&self, receipt: &Receipt) -> Result<Option<ClaudeContentBlock>> {
+        if !sent_inline(receipt.size_bytes) {
+            let url = self.storage.presigned_download_url(&receipt.object_key, None).await?;
+            return Ok(ClaudeContentBlock::media_url(&receipt.content_type, &url.url));
+        }
+        let file = self.storage.get_object(&receipt.object_key).await?;
+        Ok(ClaudeContentBlock::media(&receipt.content_type, &file))
+    }
+
+    /// Scan an attached receipt for `user_id` and store the result, returning the updated receipt
+    #[instrument(skip(self, receipt), fields(receipt_id = %receipt.id))]
+    pub async fn scan(&self, receipt: &Receipt, user_id: Uuid) -> Result<Receipt> {
+        let media = self
+            .media_block(receipt)
+            .await?
+            .ok_or_else(|| anyhow!("Receipts of type {} cannot be scanned", receipt.content_type))?;
+
+        let system = self.prompts.text(PromptKey::ReceiptScan);
+        let response = self
+            .client
+            .send_content_conversation(
+                vec![
+                    ClaudeContentMessage::user(vec![media, ClaudeContentBlock::text("Read this receipt.")]),
+                    ClaudeContentMessage::assistant(vec![ClaudeContentBlock::text(RESPONSE_PREFILL)]),
+                ],
+                Some(&system),
+                Some(MAX_SCAN_TOKENS),
+                Some(0.0),
+            )
+            .await
+            .context("Receipt scan request failed")?;
+
+        let usage = NewAiUsage {
+            user_id,
+            feature: AiFeature::ReceiptScan,
+            provider: CLAUDE_PROVIDER,
+            model: &response.model,
+            input_tokens: response.usage.input_tokens,
+            output_tokens: response.usage.output_tokens,
+            cache_read_tokens: response.usage.cache_read_input_tokens,
+            cache_write_tokens: response.usage.cache_creation_input_tokens,
+        };
+        if let Err(e) = self.ai_usage.record(&usage).await {
+            warn!("Failed to record receipt scan usage: {:?}", e);
+        }
+
+        let reply: String = response.content.iter().map(|block| block.text.as_str()).collect();
+        let extraction = parse_extraction(&reply)?;
+        if extraction.total.is_none() {
+            warn!("Receipt scan found no total");
+        }
+
+        let stored = self
+            .receipts
+            .store_extraction(receipt.id, &extraction, &response.model)
+            .await?
+            .ok_or_else(|| anyhow!("Receipt is no longer attached"))?;
+
+        info!(line_items = extraction.line_items.len(), model = %response.model, "Receipt scanned");
+        Ok(stored)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_large_files_are_sent_by_url() {
+        assert!(sent_inline(120_000));
+        assert!(sent_inline(MAX_INLINE_MEDIA_BYTES as i64));
+        assert!(!sent_inline(MAX_INLINE_MEDIA_BYTES as i64 + 1));
+        assert!(!sent_inline(-1));
+    }
+
+    #[test]
+    fn test_parse_extraction() {
+        let reply = r#""merchant": " Corner Cafe ", "date": "2024-03-02", "total": 12.5, "tax": 1.0,
+            "currency": "usd", "line_items": [
+                {"description": "Latte", "quantity": 2, "amount": 9.0},
+                {"description": " ", "quantity": null, "amount": 1.0},
+                {"description": "Muffin", "quantity": null, "amount": 2.5}
+            ]}"#;
+        let extraction = parse_extraction(reply).unwrap();
+        assert_eq!(extraction.merchant.as_deref(), Some("Corner Cafe"));
+        assert_eq!(extraction.date, NaiveDate::from_ymd_opt(2024, 3, 2));
+        assert_eq!(extraction.total, Some(12.5));
+        assert_eq!(extraction.currency.as_deref(), Some("USD"));
+        assert_eq!(extraction.line_items.len(), 2);
+        assert_eq!(extraction.line_items[0].quantity, Some(2.0));
+    }
+
+    #[test]
+    fn test_parse_extraction_tolerates_unreadable_fields() {
+        let reply = r#""merchant": null, "date": "March 2nd", "total": null, "currency": "$"}"#;
+        let extraction = parse_extraction(reply).unwrap();
+        assert_eq!(extraction, ReceiptExtraction::default());
+
+        assert!(parse_extraction("not json").is_err());
+    }
+}
@@ -0,0 +1,274 @@
+// Year-end reports built from categorized transactions
+use crate::export::csv_field;
+use crate::model::tax_report::TaxLine;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Tags a user can put on a transaction to mark it as a deduction candidate
+const DEDUCTIBLE_TAGS: &[&str] = &["deductible", "tax-deductible", "business"];
+
+/// Tax-relevant grouping of transaction categories
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaxBucket {
+    Income,
+    MedicalExpenses,
+    CharitableAndGovernment,
+    LoanPayments,
+    HousingAndUtilities,
+    HomeImprovement,
+    Transportation,
+    Personal,
+    Uncategorized,
+}
+
+impl TaxBucket {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaxBucket::Income => "income",
+            TaxBucket::MedicalExpenses => "medical_expenses",
+            TaxBucket::CharitableAndGovernment => "charitable_and_government",
+            TaxBucket::LoanPayments => "loan_payments",
+            TaxBucket::HousingAndUtilities => "housing_and_utilities",
+            TaxBucket::HomeImprovement => "home_improvement",
+            TaxBucket::Transportation => "transportation",
+            TaxBucket::Personal => "personal",
+            TaxBucket::Uncategorized => "uncategorized",
+        }
+    }
+
+    /// Bucket of a taxonomy category; `None` for transfers between the user's own accounts,
+    /// which are left out of the report
+    pub fn for_category(category: Option<&str>) -> Option<TaxBucket> {
+        let bucket = match category {
+            None => TaxBucket::Uncategorized,
+            Some("TRANSFER_IN") | Some("TRANSFER_OUT") => return None,
+            Some("INCOME") => TaxBucket::Income,
+            Some("MEDICAL") => TaxBucket::MedicalExpenses,
+            Some("GOVERNMENT_AND_NON_PROFIT") => TaxBucket::CharitableAndGovernment,
+            Some("LOAN_PAYMENTS") => TaxBucket::LoanPayments,
+            Some("RENT_AND_UTILITIES") => TaxBucket::HousingAndUtilities,
+            Some("HOME_IMPROVEMENT") => TaxBucket::HomeImprovement,
+            Some("TRANSPORTATION") => TaxBucket::Transportation,
+            Some(_) => TaxBucket::Personal,
+        };
+        Some(bucket)
+    }
+
+    /// Why spending in this bucket may be deductible, if it commonly is
+    pub fn deduction_reason(&self) -> Option<&'static str> {
+        match self {
+            TaxBucket::MedicalExpenses => Some("Medical and dental expenses"),
+            TaxBucket::CharitableAndGovernment => Some("Charitable donations and taxes paid"),
+            TaxBucket::LoanPayments => Some("Mortgage or student loan interest"),
+            _ => None,
+        }
+    }
+}
+
+/// Total of one bucket in one currency; income is reported as a positive amount received,
+/// every other bucket as a positive amount spent, with refunds netted off
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TaxBucketTotal {
+    pub bucket: TaxBucket,
+    pub currency_code: String,
+    pub amount: f64,
+    pub transaction_count: usize,
+}
+
+/// Outflow worth reviewing as a possible deduction
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeductibleCandidate {
+    pub transaction_id: String,
+    pub date: String,
+    pub description: String,
+    pub amount: f64,
+    pub currency_code: String,
+    pub bucket: TaxBucket,
+    pub reason: String,
+}
+
+/// Yearly tax summary of one user's transactions
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TaxReport {
+    pub year: i32,
+    pub buckets: Vec<TaxBucketTotal>,
+    pub deductible_candidates: Vec<DeductibleCandidate>,
+}
+
+const CSV_COLUMNS: &[&str] = &[
+    "record_type",
+    "bucket",
+    "currency_code",
+    "amount",
+    "transaction_count",
+    "transaction_id",
+    "date",
+    "description",
+    "deduction_reason",
+];
+
+impl TaxReport {
+    /// Group a year's transaction lines into buckets and pick out deduction candidates
+    pub fn build(year: i32, lines: &[TaxLine]) -> Self {
+        let mut totals: BTreeMap<(TaxBucket, &str), (i64, Vec<&str>)> = BTreeMap::new();
+        let mut deductible_candidates = Vec::new();
+
+        for line in lines {
+            let Some(bucket) = TaxBucket::for_category(line.category.as_deref()) else {
+                continue;
+            };
+            let cents = (line.amount * 100.0).round() as i64;
+            let signed = if bucket == TaxBucket::Income { -cents } else { cents };
+            let entry = totals.entry((bucket, line.currency_code.as_str())).or_default();
+            entry.0 += signed;
+            entry.1.push(&line.transaction_id);
+
+            // Only outflows are deductions; refunds in a deductible bucket are netted in its total
+            if line.amount <= 0.0 {
+                continue;
+            }
+            let tagged = line.tags.iter().any(|tag| DEDUCTIBLE_TAGS.contains(&tag.as_str()));
+            let reason = match bucket.deduction_reason() {
+                Some(reason) => reason,
+                None if tagged => "Tagged as deductible",
+                None => continue,
+            };
+            deductible_candidates.push(DeductibleCandidate {
+                transaction_id: line.transaction_id.clone(),
+                date: line.date.format("%Y-%m-%d").to_string(),
+                description: line.merchant_name.clone().unwrap_or_else(|| line.name.clone()),
+                amount: line.amount,
+                currency_code: line.currency_code.clone(),
+                bucket,
+                reason: reason.to_string(),
+            });
+        }
+
+        let buckets = totals
+            .into_iter()
+            .map(|((bucket, currency_code), (cents, mut transaction_ids))| {
+                transaction_ids.dedup();
+                TaxBucketTotal {
+                    bucket,
+                    currency_code: currency_code.to_string(),
+                    amount: cents as f64 / 100.0,
+                    transaction_count: transaction_ids.len(),
+                }
+            })
+            .collect();
+
+        Self {
+            year,
+            buckets,
+            deductible_candidates,
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to serialize tax report")
+    }
+
+    /// One CSV table: a `bucket_total` row per bucket and currency, then a
+    /// `deductible_candidate` row per candidate
+    pub fn to_csv(&self) -> String {
+        let mut csv = format!("{}\n", CSV_COLUMNS.join(","));
+        for total in &self.buckets {
+            let fields = [
+                "bucket_total".to_string(),
+                total.bucket.as_str().to_string(),
+                csv_field(&total.currency_code),
+                format!("{:.2}", total.amount),
+                total.transaction_count.to_string(),
+                String::new(),
+                String::new(),
+                String::new(),
+                String::new(),
+            ];
+            csv.push_str(&fields.join(","));
+            csv.push('\n');
+        }
+        for candidate in &self.deductible_candidates {
+            let fields = [
+                "deductible_candidate".to_string(),
+                candidate.bucket.as_str().to_string(),
+                csv_field(&candidate.currency_code),
+                format!("{:.2}", candidate.amount),
+                String::new(),
+                csv_field(&candidate.transaction_id),
+                candidate.date.clone(),
+                csv_field(&candidate.description),
+                csv_field(&candidate.reason),
+            ];
+            csv.push_str(&fields.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn line(transaction_id: &str, amount: f64, category: Option<&str>, tags: &[&str]) -> TaxLine {
+        TaxLine {
+            transaction_id: transaction_id.to_string(),
+            date: NaiveDate::from_ymd_opt(2024, 5, 1).unwrap(),
+            name: "Payment".to_string(),
+            merchant_name: None,
+            amount,
+            currency_code: "USD".to_string(),
+            category: category.map(str::to_string),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_build_tax_report() {
+        let lines = vec![
+            line("tx_1", -3000.0, Some("INCOME"), &[]),
+            line("tx_2", 120.5, Some("MEDICAL"), &[]),
+            line("tx_3", -20.5, Some("MEDICAL"), &[]),
+            line("tx_4", 500.0, Some("TRANSFER_OUT"), &[]),
+            line("tx_5", 80.0, Some("GENERAL_SERVICES"), &["business"]),
+            line("tx_6", 15.0, Some("FOOD_AND_DRINK"), &[]),
+        ];
+        let report = TaxReport::build(2024, &lines);
+
+        let income = report.buckets.iter().find(|t| t.bucket == TaxBucket::Income).unwrap();
+        assert_eq!(income.amount, 3000.0);
+        let medical = report.buckets.iter().find(|t| t.bucket == TaxBucket::MedicalExpenses).unwrap();
+        assert_eq!(medical.amount, 100.0);
+        assert_eq!(medical.transaction_count, 2);
+        let personal = report.buckets.iter().find(|t| t.bucket == TaxBucket::Personal).unwrap();
+        assert_eq!(personal.amount, 95.0);
+        assert!(report.buckets.iter().all(|t| t.transaction_count > 0));
+
+        let candidates: Vec<&str> = report.deductible_candidates.iter().map(|c| c.transaction_id.as_str()).collect();
+        assert_eq!(candidates, vec!["tx_2", "tx_5"]);
+    }
+
+    #[test]
+    fn test_split_parts_count_once() {
+        let lines = vec![line("tx_1", 60.0, Some("MEDICAL"), &[]), line("tx_1", 40.0, Some("MEDICAL"), &[])];
+        let report = TaxReport::build(2024, &lines);
+        assert_eq!(report.buckets[0].amount, 100.0);
+        assert_eq!(report.buckets[0].transaction_count, 1);
+    }
+
+    #[test]
+    fn test_to_csv() {
+        let report = TaxReport::build(2024, &[line("tx_1", 10.0, Some("MEDICAL"), &[])]);
+        let csv = report.to_csv();
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[1], "bucket_total,medical_expenses,USD,10.00,1,,,,");
+        assert_eq!(
+            rows[2],
+            "deductible_candidate,medical_expenses,USD,10.00,,tx_1,2024-05-01,Payment,Medical and dental expenses"
+        );
+    }
+}
@@ -0,0 +1,168 @@
+// Request IDs identifying one call in logs, responses and error details, so a user can quote one to support
+use futures::future::BoxFuture;
+use std::future::Future;
+use std::task::{Context, Poll};
+use tonic::codegen::http::{self, HeaderValue};
+use tower::{Layer, Service};
+use tracing::Span;
+use uuid::Uuid;
+
+/// Metadata key a request ID is read from and echoed in
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest caller-supplied request ID kept; longer ones are replaced
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    static CURRENT: RequestId;
+}
+
+/// ID of the call being served, in the request extensions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    /// Keep the caller's ID when it is a reasonable token, otherwise generate one
+    pub fn accept_or_generate(value: Option<&str>) -> Self {
+        match value.map(str::trim) {
+            Some(id) if is_valid(id) => Self(id.to_string()),
+            _ => Self(Uuid::new_v4().to_string()),
+        }
+    }
+
+    /// ID of the request, as set by `RequestIdLayer` or the REST gateway
+    pub fn from_request<T>(request: &tonic::Request<T>) -> Option<&RequestId> {
+        request.extensions().get::<RequestId>()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Run `future` with this as the current request ID
+    pub fn scope<F: Future>(self, future: F) -> impl Future<Output = F::Output> {
+        CURRENT.scope(self, future)
+    }
+}
+
+/// IDs are echoed in headers and logs, so only short printable tokens are trusted
+fn is_valid(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b':'))
+}
+
+/// ID of the request being served on this task, if any
+pub fn current() -> Option<String> {
+    CURRENT.try_with(|id| id.0.clone()).ok()
+}
+
+/// Tower layer giving every gRPC call a request ID.
+///
+/// The ID comes from the `x-request-id` metadata or is generated. It is recorded on the `grpc_request`
+/// span, set in the request metadata and extensions, current for the handler (so `AppError` statuses carry
+/// it) and echoed in the response metadata.
+#[derive(Debug, Clone, Default)]
+pub struct RequestIdLayer;
+
+impl<S> Layer<S> for RequestIdLayer {
+    type Service = RequestIdService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestIdService { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RequestIdService<S> {
+    inner: S,
+}
+
+impl<S, ReqBody, ResBody> Service<http::Request<ReqBody>> for RequestIdService<S>
+where
+    S: Service<http::Request<ReqBody>, Response = http::Response<ResBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: http::Request<ReqBody>) -> Self::Future {
+        let id = RequestId::accept_or_generate(
+            request
+                .headers()
+                .get(REQUEST_ID_HEADER)
+                .and_then(|value| value.to_str().ok()),
+        );
+        // Valid IDs are ASCII, so this can't fail
+        let header = HeaderValue::from_str(id.as_str()).ok();
+        if let Some(header) = &header {
+            request.headers_mut().insert(REQUEST_ID_HEADER, header.clone());
+        }
+        request.extensions_mut().insert(id.clone());
+
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            // The request span is entered while the call is polled, not when it is created
+            Span::current().record("request_id", id.as_str());
+            let mut result = id.scope(future).await;
+            if let (Ok(response), Some(header)) = (&mut result, header) {
+                response.headers_mut().insert(REQUEST_ID_HEADER, header);
+            }
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accept_or_generate() {
+        assert_eq!(RequestId::accept_or_generate(Some("req-42")).as_str(), "req-42");
+        assert_eq!(RequestId::accept_or_generate(Some(" abc:1.2_3 ")).as_str(), "abc:1.2_3");
+
+        for rejected in [None, Some(""), Some("has space"), Some("<script>")] {
+            let id = RequestId::accept_or_generate(rejected);
+            assert!(Uuid::parse_str(id.as_str()).is_ok(), "{:?} was kept", rejected);
+        }
+        let long = "a".repeat(MAX_REQUEST_ID_LEN + 1);
+        assert_ne!(RequestId::accept_or_generate(Some(&long)).as_str(), long);
+    }
+
+    #[tokio::test]
+    async fn test_current_is_scoped_to_the_call() {
+        assert_eq!(current(), None);
+        let id = RequestId("req-1".to_string()).scope(async { current() }).await;
+        assert_eq!(id.as_deref(), Some("req-1"));
+        assert_eq!(current(), None);
+    }
+
+    #[tokio::test]
+    async fn test_layer_echoes_request_id() {
+        let service = tower::service_fn(|request: http::Request<()>| async move {
+            let seen = request.extensions().get::<RequestId>().cloned();
+            assert_eq!(seen.map(|id| id.0), current());
+            Ok::<_, std::convert::Infallible>(http::Response::new(()))
+        });
+        let mut service = RequestIdLayer.layer(service);
+
+        let request = http::Request::builder()
+            .header(REQUEST_ID_HEADER, "req-7")
+            .body(())
+            .unwrap();
+        let response = service.call(request).await.unwrap();
+        assert_eq!(response.headers().get(REQUEST_ID_HEADER).unwrap(), "req-7");
+
+        let response = service.call(http::Request::new(())).await.unwrap();
+        let generated = response.headers().get(REQUEST_ID_HEADER).unwrap().to_str().unwrap();
+        assert!(Uuid::parse_str(generated).is_ok());
+    }
+}
@@ -0,0 +1,661 @@
+// TLS termination for the gRPC server: certificates read from files or Parameter Store, chosen by the
+// host name the client asks for (SNI), and read again periodically so a rotated certificate is served
+// without a restart. Internal services may present a client certificate signed by a configured CA
+// (mutual TLS), which identifies them without a JWT.
+use crate::adapter::parameter_store::ParameterStore;
+use crate::settings::SettingValues;
+use anyhow::{anyhow, bail, Context, Result};
+use futures::Stream;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_rustls::rustls::crypto::ring::sign::any_supported_type;
+use tokio_rustls::rustls::server::{ClientHello, ResolvesServerCert, WebPkiClientVerifier};
+use tokio_rustls::rustls::sign::CertifiedKey;
+use tokio_rustls::rustls::{RootCertStore, ServerConfig};
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+use tonic::Request;
+use tracing::{debug, info, warn};
+use x509_parser::extensions::GeneralName;
+
+/// Prefix of a location naming a Parameter Store parameter rather than a file
+const PARAMETER_PREFIX: &str = "ssm:";
+/// Certificates are checked for rotation every five minutes by default
+const DEFAULT_RELOAD_SECS: u64 = 300;
+/// Longest a client may take to finish its handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Handshaken connections waiting for the server to pick them up
+const ACCEPT_BACKLOG: usize = 128;
+
+/// Where a PEM document is read from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PemSource {
+    File(String),
+    /// Full name of a Parameter Store parameter, e.g. a SecureString holding the private key
+    Parameter(String),
+}
+
+impl PemSource {
+    /// `ssm:/origin/prod/grpc-tls-key` names a parameter; anything else is a file path
+    pub fn parse(location: &str) -> Self {
+        let location = location.trim();
+        match location.strip_prefix(PARAMETER_PREFIX) {
+            Some(name) => Self::Parameter(name.to_string()),
+            None => Self::File(location.to_string()),
+        }
+    }
+
+    async fn read(&self, store: Option<&ParameterStore>) -> Result<String> {
+        match self {
+            Self::File(path) => tokio::fs::read_to_string(path)
+                .await
+                .with_context(|| format!("Failed to read {}", path)),
+            Self::Parameter(name) => {
+                let store = store.ok_or_else(|| anyhow!("No Parameter Store client to read {}", name))?;
+                let (value, _) = store
+                    .get_versioned_parameter(name)
+                    .await?
+                    .ok_or_else(|| anyhow!("Parameter {} not found", name))?;
+                Ok(value)
+            }
+        }
+    }
+}
+
+impl fmt::Display for PemSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File(path) => write!(f, "{}", path),
+            Self::Parameter(name) => write!(f, "{}{}", PARAMETER_PREFIX, name),
+        }
+    }
+}
+
+/// Certificate chain and private key served together
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertificateSource {
+    pub cert: PemSource,
+    pub key: PemSource,
+}
+
+impl CertificateSource {
+    fn uses_parameter_store(&self) -> bool {
+        matches!(self.cert, PemSource::Parameter(_)) || matches!(self.key, PemSource::Parameter(_))
+    }
+}
+
+/// Verification of client certificates (mutual TLS)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientAuth {
+    /// PEM bundle of the CAs that sign internal services' certificates
+    pub ca: PemSource,
+    /// Refuse connections without a client certificate; otherwise they are optional, so apps can
+    /// still connect and authenticate with JWTs
+    pub required: bool,
+    /// Services whose certificate is accepted in place of an access token
+    pub trusted_services: HashSet<String>,
+}
+
+/// Configuration for serving gRPC over TLS
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    /// Served when the client names no host, or one without its own certificate
+    pub default: CertificateSource,
+    /// Certificates for specific host names, e.g. `api.example.com` or `*.example.com`
+    pub sni: Vec<(String, CertificateSource)>,
+    /// How often certificates are read again to pick up rotations (`None`: never)
+    pub reload_interval: Option<Duration>,
+    /// `None` when clients aren't asked for a certificate
+    pub client_auth: Option<ClientAuth>,
+}
+
+impl TlsConfig {
+    /// Read from settings, or `None` to serve plaintext as in local development. Locations are file paths,
+    /// or `ssm:<name>` for a Parameter Store parameter.
+    /// - GRPC_TLS_CERT: PEM certificate chain
+    /// - GRPC_TLS_KEY: PEM private key
+    /// - GRPC_TLS_SNI: Certificates for other host names, as `host=cert,key;host=cert,key`
+    /// - GRPC_TLS_RELOAD_SECS: Seconds between checks for rotated certificates (default: 300, 0 disables)
+    /// - GRPC_TLS_CLIENT_CA: PEM CA bundle verifying client certificates; enables mutual TLS
+    /// - GRPC_TLS_CLIENT_AUTH: `optional` or `required` client certificates (default: optional)
+    /// - GRPC_TLS_TRUSTED_SERVICES: Comma-separated service names that may call the RPCs open to services
+    ///   (see `trusted_service`) without an access token
+    pub fn from_values(values: &mut SettingValues) -> Option<Self> {
+        let (cert, key) = match (values.optional("grpc-tls-cert"), values.optional("grpc-tls-key")) {
+            (None, None) => return None,
+            (Some(cert), Some(key)) => (cert, key),
+            _ => {
+                values.error("grpc-tls-cert and grpc-tls-key must be set together");
+                return None;
+            }
+        };
+        let reload_secs = values.parsed("grpc-tls-reload-secs", DEFAULT_RELOAD_SECS);
+        let client_auth = match (
+            values.optional("grpc-tls-client-ca"),
+            values.optional("grpc-tls-client-auth"),
+        ) {
+            (Some(ca), mode) => Some(ClientAuth {
+                ca: PemSource::parse(&ca),
+                required: match mode.as_deref() {
+                    None | Some("optional") => false,
+                    Some("required") => true,
+                    Some(other) => {
+                        values.error(format!(
+                            "grpc-tls-client-auth must be optional or required, not '{}'",
+                            other
+                        ));
+                        false
+                    }
+                },
+                trusted_services: parse_service_names(&values.or("grpc-tls-trusted-services", "")),
+            }),
+            (None, Some(_)) => {
+                values.error("grpc-tls-client-auth requires grpc-tls-client-ca");
+                None
+            }
+            (None, None) => None,
+        };
+        let sni = parse_sni(&values.or("grpc-tls-sni", "")).unwrap_or_else(|e| {
+            values.error(format!("grpc-tls-sni is invalid: {}", e));
+            Vec::new()
+        });
+
+        Some(Self {
+            default: CertificateSource {
+                cert: PemSource::parse(&cert),
+                key: PemSource::parse(&key),
+            },
+            sni,
+            reload_interval: (reload_secs > 0).then(|| Duration::from_secs(reload_secs)),
+            client_auth,
+        })
+    }
+
+    fn uses_parameter_store(&self) -> bool {
+        self.default.uses_parameter_store()
+            || self.sni.iter().any(|(_, source)| source.uses_parameter_store())
+            || matches!(
+                self.client_auth,
+                Some(ClientAuth {
+                    ca: PemSource::Parameter(_),
+                    ..
+                })
+            )
+    }
+
+    /// Services that may call without an access token; empty without mutual TLS
+    pub fn trusted_services(&self) -> HashSet<String> {
+        self.client_auth
+            .as_ref()
+            .map(|auth| auth.trusted_services.clone())
+            .unwrap_or_default()
+    }
+}
+
+fn parse_service_names(value: &str) -> HashSet<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Parse `host=cert,key` entries separated by `;`
+fn parse_sni(value: &str) -> Result<Vec<(String, CertificateSource)>> {
+    value
+        .split(';')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (host, locations) = entry
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Expected host=cert,key, got '{}'", entry))?;
+            let (cert, key) = locations
+                .split_once(',')
+                .ok_or_else(|| anyhow!("Expected a certificate and a key for '{}'", host.trim()))?;
+            let host = host.trim().trim_end_matches('.').to_ascii_lowercase();
+            if host.is_empty() || host.contains(char::is_whitespace) {
+                bail!("Invalid host name in '{}'", entry);
+            }
+            if cert.trim().is_empty() || key.trim().is_empty() {
+                bail!("Expected a certificate and a key for '{}'", host);
+            }
+            Ok((
+                host,
+                CertificateSource {
+                    cert: PemSource::parse(cert),
+                    key: PemSource::parse(key),
+                },
+            ))
+        })
+        .collect()
+}
+
+/// The entry for a host name: an exact match, else a wildcard covering its parent domain
+fn lookup<'a, T>(by_name: &'a HashMap<String, T>, server_name: &str) -> Option<&'a T> {
+    let name = server_name.trim_end_matches('.').to_ascii_lowercase();
+    by_name.get(&name).or_else(|| {
+        let (_, parent) = name.split_once('.')?;
+        by_name.get(&format!("*.{}", parent))
+    })
+}
+
+/// Parse a PEM certificate chain and private key into a key rustls can serve
+fn certified_key(cert_pem: &str, key_pem: &str) -> Result<CertifiedKey> {
+    let certs = rustls_pemfile::certs(&mut cert_pem.as_bytes())
+        .collect::<Result<Vec<_>, _>>()
+        .context("Failed to parse certificate PEM")?;
+    if certs.is_empty() {
+        bail!("No certificate found in PEM");
+    }
+    let key = rustls_pemfile::private_key(&mut key_pem.as_bytes())
+        .context("Failed to parse private key PEM")?
+        .ok_or_else(|| anyhow!("No private key found in PEM"))?;
+    let key = any_supported_type(&key).map_err(|e| anyhow!("Unsupported private key: {}", e))?;
+    Ok(CertifiedKey::new(certs, key))
+}
+
+/// Parse a PEM CA bundle into the roots client certificates are verified against
+fn root_store(ca_pem: &str) -> Result<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+    for cert in rustls_pemfile::certs(&mut ca_pem.as_bytes()) {
+        let cert = cert.context("Failed to parse CA certificate PEM")?;
+        roots.add(cert).map_err(|e| anyhow!("Invalid CA certificate: {}", e))?;
+    }
+    if roots.is_empty() {
+        bail!("No CA certificate found in PEM");
+    }
+    Ok(roots)
+}
+
+/// Internal service that connected with a client certificate the client CA signed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceIdentity {
+    /// The certificate's common name, else its first DNS or URI subject alternative name
+    pub name: String,
+    /// SHA-256 of the certificate, hex encoded
+    pub fingerprint: String,
+}
+
+impl ServiceIdentity {
+    /// Identity of a DER-encoded certificate
+    pub fn from_der(der: &[u8]) -> Result<Self> {
+        let (_, cert) = x509_parser::parse_x509_certificate(der).map_err(|e| anyhow!("Invalid certificate: {}", e))?;
+        let common_name = cert
+            .subject()
+            .iter_common_name()
+            .find_map(|cn| cn.as_str().ok())
+            .map(str::to_string);
+        let alt_name = || {
+            let san = cert.subject_alternative_name().ok().flatten()?;
+            san.value.general_names.iter().find_map(|name| match name {
+                GeneralName::DNSName(name) | GeneralName::URI(name) => Some(name.to_string()),
+                _ => None,
+            })
+        };
+        let name = common_name
+            .filter(|name| !name.trim().is_empty())
+            .or_else(alt_name)
+            .ok_or_else(|| anyhow!("Certificate names no service"))?;
+
+        Ok(Self {
+            name,
+            fingerprint: format!("{:x}", Sha256::digest(der)),
+        })
+    }
+
+    /// Identity of the client certificate on the request's connection, which the handshake verified
+    /// against the client CA; `None` without mutual TLS
+    pub fn from_peer<T>(request: &Request<T>) -> Option<Self> {
+        let certs = request.peer_certs()?;
+        let leaf = certs.first()?;
+        match Self::from_der(leaf.get_ref()) {
+            Ok(identity) => Some(identity),
+            Err(e) => {
+                warn!(error = %e, "Ignoring unreadable client certificate");
+                None
+            }
+        }
+    }
+
+    /// The caller's identity, inserted into request extensions by `AuthInterceptor`
+    pub fn from_request<T>(request: &Request<T>) -> Option<Self> {
+        request.extensions().get::<Self>().cloned()
+    }
+}
+
+/// Certificates being served
+#[derive(Debug)]
+struct Certificates {
+    default: Arc<CertifiedKey>,
+    by_name: HashMap<String, Arc<CertifiedKey>>,
+    /// CAs client certificates are verified against, with mutual TLS
+    client_roots: Option<Arc<RootCertStore>>,
+    /// Hash of every PEM document read, to tell whether a reload changed anything
+    fingerprint: String,
+}
+
+impl Certificates {
+    async fn load(config: &TlsConfig, store: Option<&ParameterStore>) -> Result<Self> {
+        let mut hasher = Sha256::new();
+        let default = Self::load_one(&config.default, store, &mut hasher).await?;
+        let mut by_name = HashMap::new();
+        for (host, source) in &config.sni {
+            by_name.insert(host.clone(), Self::load_one(source, store, &mut hasher).await?);
+        }
+        let client_roots = match &config.client_auth {
+            Some(auth) => {
+                let ca = auth.ca.read(store).await?;
+                hasher.update(&ca);
+                let roots = root_store(&ca).with_context(|| format!("Invalid client CA bundle {}", auth.ca))?;
+                Some(Arc::new(roots))
+            }
+            None => None,
+        };
+        Ok(Self {
+            default,
+            by_name,
+            client_roots,
+            fingerprint: format!("{:x}", hasher.finalize()),
+        })
+    }
+
+    async fn load_one(
+        source: &CertificateSource,
+        store: Option<&ParameterStore>,
+        hasher: &mut Sha256,
+    ) -> Result<Arc<CertifiedKey>> {
+        let cert = source.cert.read(store).await?;
+        let key = source.key.read(store).await?;
+        hasher.update(&cert);
+        hasher.update(&key);
+        let certified = certified_key(&cert, &key)
+            .with_context(|| format!("Invalid certificate {} or key {}", source.cert, source.key))?;
+        Ok(Arc::new(certified))
+    }
+}
+
+/// Picks the certificate for each handshake from the ones currently loaded
+#[derive(Debug)]
+struct CertificateResolver {
+    certs: RwLock<Certificates>,
+}
+
+impl ResolvesServerCert for CertificateResolver {
+    fn resolve(&self, client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        let certs = self.certs.read().unwrap_or_else(PoisonError::into_inner);
+        let cert = client_hello
+            .server_name()
+            .and_then(|name| lookup(&certs.by_name, name))
+            .unwrap_or(&certs.default);
+        Some(cert.clone())
+    }
+}
+
+/// TLS for the gRPC server; handshakes use whatever certificates were loaded last
+pub struct GrpcTls {
+    config: TlsConfig,
+    store: Option<ParameterStore>,
+    resolver: Arc<CertificateResolver>,
+    /// Rebuilt on reload, as a rotated client CA bundle changes the verifier
+    acceptor: RwLock<TlsAcceptor>,
+}
+
+impl GrpcTls {
+    /// Load the configured certificates; fails if any can't be read or parsed
+    pub async fn new(config: TlsConfig) -> Result<Self> {
+        let store = if config.uses_parameter_store() {
+            // Reloads must see rotated certificates, not cached ones
+            Some(ParameterStore::new().await.with_cache_ttl(Duration::ZERO))
+        } else {
+            None
+        };
+        let certs = Certificates::load(&config, store.as_ref()).await?;
+        let client_roots = certs.client_roots.clone();
+        let resolver = Arc::new(CertificateResolver {
+            certs: RwLock::new(certs),
+        });
+        let acceptor = Self::acceptor_for(&config, resolver.clone(), client_roots)?;
+
+        info!(
+            cert = %config.default.cert,
+            sni_hosts = config.sni.len(),
+            mutual_tls = config.client_auth.is_some(),
+            "Loaded gRPC TLS certificates"
+        );
+        Ok(Self {
+            config,
+            store,
+            resolver,
+            acceptor: RwLock::new(acceptor),
+        })
+    }
+
+    /// Read the certificates again and serve them to new connections if they changed; returns whether
+    /// they did. On failure the current certificates stay in use.
+    pub async fn reload(&self) -> Result<bool> {
+        let certs = Certificates::load(&self.config, self.store.as_ref()).await?;
+        let mut current = self.resolver.certs.write().unwrap_or_else(PoisonError::into_inner);
+        if current.fingerprint == certs.fingerprint {
+            return Ok(false);
+        }
+        let acceptor = Self::acceptor_for(&self.config, self.resolver.clone(), certs.client_roots.clone())?;
+        *current = certs;
+        *self.acceptor.write().unwrap_or_else(PoisonError::into_inner) = acceptor;
+        info!("Reloaded rotated gRPC TLS certificates");
+        Ok(true)
+    }
+
+    /// Reload the certificates every `interval` in the background
+    pub fn spawn_reload(self: Arc<Self>, interval: Duration) {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            // The first tick completes immediately; certificates were loaded at startup
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.reload().await {
+                    warn!(error = ?e, "Failed to reload gRPC TLS certificates; keeping the current ones");
+                }
+            }
+        });
+    }
+
+    fn acceptor_for(
+        config: &TlsConfig,
+        resolver: Arc<CertificateResolver>,
+        client_roots: Option<Arc<RootCertStore>>,
+    ) -> Result<TlsAcceptor> {
+        let builder = ServerConfig::builder();
+        let mut server_config = match (&config.client_auth, client_roots) {
+            (Some(auth), Some(roots)) => {
+                let verifier = WebPkiClientVerifier::builder(roots);
+                let verifier = if auth.required {
+                    verifier
+                } else {
+                    verifier.allow_unauthenticated()
+                };
+                let verifier = verifier
+                    .build()
+                    .map_err(|e| anyhow!("Failed to build client certificate verifier: {}", e))?;
+                builder.with_client_cert_verifier(verifier)
+            }
+            _ => builder.with_no_client_auth(),
+        }
+        .with_cert_resolver(resolver);
+        // gRPC runs over HTTP/2 only
+        server_config.alpn_protocols = vec![b"h2".to_vec()];
+        Ok(TlsAcceptor::from(Arc::new(server_config)))
+    }
+
+    fn acceptor(&self) -> TlsAcceptor {
+        self.acceptor.read().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// Listen on `addr` and yield connections once their handshake completes, for
+    /// `Router::serve_with_incoming`. Handshakes run concurrently so a slow client can't hold up others.
+    pub async fn incoming(
+        self: &Arc<Self>,
+        addr: SocketAddr,
+    ) -> Result<impl Stream<Item = std::io::Result<TlsStream<TcpStream>>>> {
+        let listener = TcpListener::bind(addr)
+            .await
+            .with_context(|| format!("Failed to listen on {}", addr))?;
+        let tls = self.clone();
+        let (tx, rx) = mpsc::channel(ACCEPT_BACKLOG);
+
+        tokio::spawn(async move {
+            while !tx.is_closed() {
+                let (stream, peer) = match listener.accept().await {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        // Usually out of file descriptors; back off rather than spin
+                        warn!(error = %e, "Failed to accept gRPC connection");
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        continue;
+                    }
+                };
+                let acceptor = tls.acceptor();
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                        Ok(Ok(stream)) => {
+                            let _ = tx.send(stream).await;
+                        }
+                        Ok(Err(e)) => debug!(peer = %peer, error = %e, "TLS handshake failed"),
+                        Err(_) => debug!(peer = %peer, "TLS handshake timed out"),
+                    }
+                });
+            }
+        });
+
+        Ok(futures::stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|stream| (Ok(stream), rx))
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pem_source_parse() {
+        assert_eq!(
+            PemSource::parse("/etc/origin/tls.crt"),
+            PemSource::File("/etc/origin/tls.crt".to_string())
+        );
+        let source = PemSource::parse(" ssm:/origin/prod/grpc-tls-key ");
+        assert_eq!(source, PemSource::Parameter("/origin/prod/grpc-tls-key".to_string()));
+        assert_eq!(source.to_string(), "ssm:/origin/prod/grpc-tls-key");
+    }
+
+    #[test]
+    fn test_parse_sni() {
+        let sni =
+            parse_sni("API.example.com=/tls/api.crt,ssm:/origin/prod/api-key; *.example.com=/tls/w.crt,/tls/w.key;")
+                .unwrap();
+        assert_eq!(sni.len(), 2);
+        assert_eq!(sni[0].0, "api.example.com");
+        assert_eq!(sni[0].1.cert, PemSource::File("/tls/api.crt".to_string()));
+        assert_eq!(sni[0].1.key, PemSource::Parameter("/origin/prod/api-key".to_string()));
+        assert_eq!(sni[1].0, "*.example.com");
+
+        assert!(parse_sni("").unwrap().is_empty());
+        assert!(parse_sni("api.example.com=/tls/api.crt").is_err());
+        assert!(parse_sni("/tls/api.crt,/tls/api.key").is_err());
+        assert!(parse_sni("=/tls/api.crt,/tls/api.key").is_err());
+    }
+
+    #[test]
+    fn test_lookup_exact_then_wildcard() {
+        let by_name: HashMap<String, &str> = [("api.example.com", "api"), ("*.example.com", "wildcard")]
+            .into_iter()
+            .map(|(host, cert)| (host.to_string(), cert))
+            .collect();
+
+        assert_eq!(lookup(&by_name, "api.example.com"), Some(&"api"));
+        assert_eq!(lookup(&by_name, "API.Example.com."), Some(&"api"));
+        assert_eq!(lookup(&by_name, "admin.example.com"), Some(&"wildcard"));
+        // A wildcard covers one label only
+        assert_eq!(lookup(&by_name, "a.b.example.com"), None);
+        assert_eq!(lookup(&by_name, "example.com"), None);
+    }
+
+    #[test]
+    fn test_certified_key_rejects_bad_pem() {
+        assert!(certified_key("", "").is_err());
+        assert!(certified_key("not a certificate", "not a key").is_err());
+    }
+
+    /// Self-signed, `CN=billing`, with DNS and SPIFFE URI alternative names
+    const BILLING_CERT: &str = "-----BEGIN CERTIFICATE-----\n\
+MIIB3jCCAYSgAwIBAgIUaj6o/mFp88+e+gRvTx2tKEJ4nHgwCgYIKoZIzj0EAwIw\n\
+IzEPMA0GA1UECgwGT3JpZ2luMRAwDgYDVQQDDAdiaWxsaW5nMCAXDTI2MTAxNjA4\n\
+MTcwMloYDzIxMjYwOTIyMDgxNzAyWjAjMQ8wDQYDVQQKDAZPcmlnaW4xEDAOBgNV\n\
+BAMMB2JpbGxpbmcwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAAQE7l3+dAn2oVuZ\n\
+NtMcj4LbUpQOb2DwBr7xTqWcs5eeXZI2Ri6FNbODI6Yp6T1/E9M1OA7epsqE3Nti\n\
+aFSKSfyYo4GTMIGQMB0GA1UdDgQWBBQgGHvgArLHFTJ3gOSGeC1rCG/zBDAfBgNV\n\
+HSMEGDAWgBQgGHvgArLHFTJ3gOSGeC1rCG/zBDAPBgNVHRMBAf8EBTADAQH/MD0G\n\
+A1UdEQQ2MDSCEGJpbGxpbmcuaW50ZXJuYWyGIHNwaWZmZTovL29yaWdpbi5pbnRl\n\
+cm5hbC9iaWxsaW5nMAoGCCqGSM49BAMCA0gAMEUCIQCj4bLsr7FyvzVqzrnWpduL\n\
+lYGuE3LCoBWky0T5KZ/HjgIgVGzHl8aQApr+TzOR6oHg8h6igPezCXoneMsEtHUh\n\
+LqY=\n\
+-----END CERTIFICATE-----\n";
+    /// Self-signed, no common name, DNS alternative name `ledger.internal`
+    const LEDGER_CERT: &str = "-----BEGIN CERTIFICATE-----\n\
+MIIBljCCATugAwIBAgIUUucvuT7Qs6SUvel0lXWR1MoR3V0wCgYIKoZIzj0EAwIw\n\
+ETEPMA0GA1UECgwGT3JpZ2luMCAXDTI2MTAxNjA4MTcwMloYDzIxMjYwOTIyMDgx\n\
+NzAyWjARMQ8wDQYDVQQKDAZPcmlnaW4wWTATBgcqhkjOPQIBBggqhkjOPQMBBwNC\n\
+AARJvnmJpMRzYTQPAQ6U+ptq7bFC89lgvXQRolO+rfGqL3cARyigj79h9jD2Ij8E\n\
+GeqwHva4JsKheIRKDx4xp+SUo28wbTAdBgNVHQ4EFgQUtoYMytZeMd98gwONXvd2\n\
+wJCI5SUwHwYDVR0jBBgwFoAUtoYMytZeMd98gwONXvd2wJCI5SUwDwYDVR0TAQH/\n\
+BAUwAwEB/zAaBgNVHREEEzARgg9sZWRnZXIuaW50ZXJuYWwwCgYIKoZIzj0EAwID\n\
+SQAwRgIhALfXG0TXxLOpwF65X6cokz57sNoFvxnukV0JsniyzegtAiEAjmbRzL/g\n\
+nip7ZArySyMTIpeC2TWtHM2ccZLiX3eG25Q=\n\
+-----END CERTIFICATE-----\n";
+
+    fn der(pem: &str) -> Vec<u8> {
+        rustls_pemfile::certs(&mut pem.as_bytes())
+            .next()
+            .unwrap()
+            .unwrap()
+            .to_vec()
+    }
+
+    #[test]
+    fn test_service_identity_from_der() {
+        let identity = ServiceIdentity::from_der(&der(BILLING_CERT)).unwrap();
+        assert_eq!(identity.name, "billing");
+        assert_eq!(identity.fingerprint.len(), 64);
+
+        let identity = ServiceIdentity::from_der(&der(LEDGER_CERT)).unwrap();
+        assert_eq!(identity.name, "ledger.internal");
+
+        assert!(ServiceIdentity::from_der(b"not a certificate").is_err());
+        // Without a TLS connection there is no peer certificate
+        assert_eq!(ServiceIdentity::from_peer(&Request::new(())), None);
+    }
+
+    #[test]
+    fn test_root_store() {
+        let bundle = format!("{}{}", BILLING_CERT, LEDGER_CERT);
+        assert_eq!(root_store(&bundle).unwrap().len(), 2);
+        assert!(root_store("").is_err());
+    }
+
+    #[test]
+    fn test_parse_service_names() {
+        let names = parse_service_names(" billing, ledger.internal,,");
+        assert_eq!(names.len(), 2);
+        assert!(names.contains("billing") && names.contains("ledger.internal"));
+        assert!(parse_service_names("").is_empty());
+    }
+}
@@ -0,0 +1,120 @@
+//! Compares the batched `UNNEST` transaction upsert with row-by-row upserts (batches of one).
+//!
+//! Needs a migrated Postgres database and is ignored by default:
+//! `DATABASE_URL=postgres://... cargo test --release --test bench_transaction_ingest -- --ignored --nocapture`
+use anyhow::Result;
+use chrono::{Duration, NaiveDate};
+use sqlx::PgPool;
+use std::time::Instant;
+use template::adapter::BankTransaction;
+use template::model::transaction::{TransactionRepository, UPSERT_BATCH_SIZE};
+use tracing::info;
+use uuid::Uuid;
+
+const TRANSACTION_COUNT: usize = 5_000;
+
+fn transactions(prefix: &str, count: usize) -> Vec<BankTransaction> {
+    let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+    (0..count)
+        .map(|i| BankTransaction {
+            transaction_id: format!("{}-{}", prefix, i),
+            account_id: format!("{}-account", prefix),
+            amount: (i % 200) as f64 + 0.99,
+            iso_currency_code: Some("USD".to_string()),
+            unofficial_currency_code: None,
+            category: vec!["Shops".to_string(), "Supermarkets and Groceries".to_string()],
+            category_id: Some("19047000".to_string()),
+            check_number: None,
+            date: (start + Duration::days((i % 365) as i64)).format("%Y-%m-%d").to_string(),
+            datetime: None,
+            authorized_date: None,
+            authorized_datetime: None,
+            location: None,
+            name: format!("Grocery store #{}", i % 50),
+            merchant_name: Some("Grocery store".to_string()),
+            original_description: None,
+            payment_meta: None,
+            pending: false,
+            pending_transaction_id: None,
+            account_owner: None,
+            transaction_type: "place".to_string(),
+            transaction_code: None,
+        })
+        .collect()
+}
+
+/// A throwaway user and item to own the benchmark transactions
+async fn create_item(pool: &PgPool, prefix: &str) -> Result<Uuid> {
+    let user_id: Uuid = sqlx::query_scalar(
+        "INSERT INTO users (google_id, email, name) VALUES ($1, $2, 'Benchmark') RETURNING id",
+    )
+    .bind(prefix)
+    .bind(format!("{}@example.com", prefix))
+    .fetch_one(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO plaid_items (
+            item_id, user_id, access_token_ciphertext, access_token_nonce, encrypted_data_key, encryption_key_id
+        )
+        VALUES ($1, $2, '\x00', '\x00', '\x00', 'benchmark')
+        "#,
+    )
+    .bind(prefix)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+
+    Ok(user_id)
+}
+
+/// Seconds to insert `TRANSACTION_COUNT` new transactions, then to update them all
+async fn time_upserts(repository: &TransactionRepository, pool: &PgPool, batch_size: usize) -> Result<(f64, f64)> {
+    let prefix = format!("bench-{}", Uuid::new_v4());
+    let user_id = create_item(pool, &prefix).await?;
+    let mut batch = transactions(&prefix, TRANSACTION_COUNT);
+
+    let started = Instant::now();
+    repository
+        .upsert_transactions_in_batches(user_id, &prefix, &batch, batch_size)
+        .await?;
+    let insert_seconds = started.elapsed().as_secs_f64();
+
+    for transaction in &mut batch {
+        transaction.amount += 1.0;
+        transaction.pending = true;
+    }
+    let started = Instant::now();
+    repository
+        .upsert_transactions_in_batches(user_id, &prefix, &batch, batch_size)
+        .await?;
+    let update_seconds = started.elapsed().as_secs_f64();
+
+    sqlx::query("DELETE FROM users WHERE id = $1").bind(user_id).execute(pool).await?;
+    Ok((insert_seconds, update_seconds))
+}
+
+#[tokio::test]
+#[ignore] // Needs a migrated database in DATABASE_URL
+async fn bench_batched_vs_row_by_row_upsert() -> Result<()> {
+    let _ = tracing_subscriber::fmt().with_env_filter("info").try_init();
+    let pool = PgPool::connect(&std::env::var("DATABASE_URL")?).await?;
+    let repository = TransactionRepository::new(pool.clone());
+
+    let (row_insert, row_update) = time_upserts(&repository, &pool, 1).await?;
+    let (batch_insert, batch_update) = time_upserts(&repository, &pool, UPSERT_BATCH_SIZE).await?;
+
+    info!(
+        transactions = TRANSACTION_COUNT,
+        row_by_row_insert_seconds = row_insert,
+        row_by_row_update_seconds = row_update,
+        batched_insert_seconds = batch_insert,
+        batched_update_seconds = batch_update,
+        insert_speedup = row_insert / batch_insert,
+        update_speedup = row_update / batch_update,
+        "Transaction upsert benchmark"
+    );
+    assert!(batch_insert < row_insert, "Batched inserts should beat row-by-row inserts");
+    Ok(())
+}